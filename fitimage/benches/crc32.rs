@@ -0,0 +1,69 @@
+//! CRC32 throughput benchmark
+//!
+//! Compares the bytewise table implementation fitimage used before with the
+//! slice-by-16 table and the default (hardware-accelerated) path.
+//!
+//! ```bash
+//! cargo bench -p fitimage --bench crc32 [SIZE_MIB]
+//! ```
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use crc::{Crc, Table, CRC_32_ISO_HDLC};
+use fitimage::crc::{calculate_crc32, Crc32Calculator};
+
+const BYTEWISE: Crc<u32, Table<1>> = Crc::<u32, Table<1>>::new(&CRC_32_ISO_HDLC);
+const SLICE16: Crc<u32, Table<16>> = Crc::<u32, Table<16>>::new(&CRC_32_ISO_HDLC);
+
+fn measure(
+    name: &str,
+    data: &[u8],
+    baseline: Option<Duration>,
+    f: impl Fn(&[u8]) -> u32,
+) -> Duration {
+    // Warm up caches and CPU frequency
+    black_box(f(black_box(data)));
+
+    let runs = 5;
+    let mut best = Duration::MAX;
+    for _ in 0..runs {
+        let start = Instant::now();
+        black_box(f(black_box(data)));
+        best = best.min(start.elapsed());
+    }
+
+    let mib_s = data.len() as f64 / (1024.0 * 1024.0) / best.as_secs_f64();
+    let speedup = baseline
+        .map(|b| format!("{:>6.1}x", b.as_secs_f64() / best.as_secs_f64()))
+        .unwrap_or_default();
+    println!("{name:<24} {:>10.2?} {mib_s:>10.0} MiB/s {speedup}", best);
+    best
+}
+
+fn main() {
+    // `cargo bench` passes `--bench`; the first numeric argument is the size
+    let size_mib = std::env::args()
+        .skip(1)
+        .find_map(|a| a.parse::<usize>().ok())
+        .unwrap_or(200);
+    let data: Vec<u8> = (0..size_mib * 1024 * 1024)
+        .map(|i| (i as u32).wrapping_mul(2_654_435_761).rotate_left(7) as u8)
+        .collect();
+
+    println!("CRC32 over {size_mib} MiB (best of 5)");
+    let baseline = measure("bytewise table", &data, None, |d| BYTEWISE.checksum(d));
+    measure("slice-by-16 table", &data, Some(baseline), |d| {
+        SLICE16.checksum(d)
+    });
+    measure("Crc32Calculator", &data, Some(baseline), |d| {
+        let mut calc = Crc32Calculator::new();
+        for chunk in d.chunks(1024 * 1024) {
+            calc.update(chunk);
+        }
+        calc.crc32()
+    });
+    measure("calculate_crc32", &data, Some(baseline), calculate_crc32);
+
+    assert_eq!(BYTEWISE.checksum(&data), calculate_crc32(&data));
+}
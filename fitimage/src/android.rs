@@ -0,0 +1,496 @@
+//! Android boot images
+//!
+//! Equivalent of `mkbootimg` for boards whose bootloader expects an Android
+//! `boot.img` instead of a FIT image. Header versions 2, 3 and 4 are
+//! supported; every section is padded to the page size.
+//!
+//! - v2: kernel, ramdisk and DTB with load addresses in the header, page size
+//!   configurable (2048 by default)
+//! - v3/v4: kernel and ramdisk only, fixed 4096-byte pages; load addresses and
+//!   the DTB live in `vendor_boot` and are left to the bootloader
+
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+
+use crate::error::{MkImageError, Result};
+
+/// Magic at the start of every Android boot image
+pub const BOOT_MAGIC: &[u8; 8] = b"ANDROID!";
+
+/// Page size used by header versions 3 and 4
+pub const BOOT_V3_PAGE_SIZE: u32 = 4096;
+
+const BOOT_NAME_SIZE: usize = 16;
+const BOOT_ARGS_SIZE: usize = 512;
+const BOOT_EXTRA_ARGS_SIZE: usize = 1024;
+const BOOT_V3_ARGS_SIZE: usize = 1536;
+
+const BOOT_V2_HEADER_SIZE: u32 = 1660;
+const BOOT_V3_HEADER_SIZE: u32 = 1580;
+const BOOT_V4_HEADER_SIZE: u32 = 1584;
+
+/// Boot image header version.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BootImageVersion {
+    /// Version 2: load addresses and a DTB section in the header
+    #[default]
+    V2,
+    /// Version 3: kernel and ramdisk only, 4 KiB pages
+    V3,
+    /// Version 4: version 3 plus a (here empty) boot signature section
+    V4,
+}
+
+impl BootImageVersion {
+    /// Numeric `header_version` field.
+    pub fn number(self) -> u32 {
+        match self {
+            BootImageVersion::V2 => 2,
+            BootImageVersion::V3 => 3,
+            BootImageVersion::V4 => 4,
+        }
+    }
+
+    /// Look up a version by its numeric value.
+    pub fn from_number(version: u32) -> Option<Self> {
+        match version {
+            2 => Some(BootImageVersion::V2),
+            3 => Some(BootImageVersion::V3),
+            4 => Some(BootImageVersion::V4),
+            _ => None,
+        }
+    }
+}
+
+/// Builder for Android boot images.
+///
+/// Load addresses follow `mkbootimg`: each one is `base` plus a fixed offset.
+#[derive(Debug, Clone)]
+pub struct AndroidBootImage {
+    /// Kernel image
+    pub kernel: Vec<u8>,
+    /// Ramdisk, may be empty
+    pub ramdisk: Vec<u8>,
+    /// Device tree blob (v2 only)
+    pub dtb: Option<Vec<u8>>,
+    /// Kernel command line
+    pub cmdline: String,
+    /// Product name (v2 only, at most 15 bytes)
+    pub name: String,
+    /// Header version
+    pub version: BootImageVersion,
+    /// Flash page size (v2 only)
+    pub page_size: u32,
+    /// Physical base address
+    pub base: u64,
+    /// Kernel offset from `base`
+    pub kernel_offset: u64,
+    /// Ramdisk offset from `base`
+    pub ramdisk_offset: u64,
+    /// Tags (ATAGS/DTB) offset from `base`
+    pub tags_offset: u64,
+    /// DTB offset from `base`
+    pub dtb_offset: u64,
+    /// Packed OS version and patch level
+    pub os_version: u32,
+}
+
+impl AndroidBootImage {
+    /// Create a v2 boot image with `mkbootimg` default addresses.
+    pub fn new(kernel: Vec<u8>) -> Self {
+        Self {
+            kernel,
+            ramdisk: Vec::new(),
+            dtb: None,
+            cmdline: String::new(),
+            name: String::new(),
+            version: BootImageVersion::V2,
+            page_size: 2048,
+            base: 0x1000_0000,
+            kernel_offset: 0x0000_8000,
+            ramdisk_offset: 0x0100_0000,
+            tags_offset: 0x0000_0100,
+            dtb_offset: 0x01f0_0000,
+            os_version: 0,
+        }
+    }
+
+    /// Set the ramdisk
+    pub fn with_ramdisk(mut self, ramdisk: Vec<u8>) -> Self {
+        self.ramdisk = ramdisk;
+        self
+    }
+
+    /// Set the device tree blob
+    pub fn with_dtb(mut self, dtb: Vec<u8>) -> Self {
+        self.dtb = Some(dtb);
+        self
+    }
+
+    /// Set the kernel command line
+    pub fn with_cmdline(mut self, cmdline: impl Into<String>) -> Self {
+        self.cmdline = cmdline.into();
+        self
+    }
+
+    /// Set the product name
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Set the header version
+    pub fn with_version(mut self, version: BootImageVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Set the page size (v2 only)
+    pub fn with_page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Set the base address
+    pub fn with_base(mut self, base: u64) -> Self {
+        self.base = base;
+        self
+    }
+
+    /// Set the kernel offset from the base address
+    pub fn with_kernel_offset(mut self, offset: u64) -> Self {
+        self.kernel_offset = offset;
+        self
+    }
+
+    /// Set the ramdisk offset from the base address
+    pub fn with_ramdisk_offset(mut self, offset: u64) -> Self {
+        self.ramdisk_offset = offset;
+        self
+    }
+
+    /// Set the DTB offset from the base address
+    pub fn with_dtb_offset(mut self, offset: u64) -> Self {
+        self.dtb_offset = offset;
+        self
+    }
+
+    /// Set the packed OS version and patch level
+    pub fn with_os_version(mut self, os_version: u32) -> Self {
+        self.os_version = os_version;
+        self
+    }
+
+    /// Build the boot image.
+    pub fn build(&self) -> Result<Vec<u8>> {
+        match self.version {
+            BootImageVersion::V2 => self.build_v2(),
+            BootImageVersion::V3 | BootImageVersion::V4 => self.build_v3(),
+        }
+    }
+
+    fn build_v2(&self) -> Result<Vec<u8>> {
+        let page_size = self.page_size;
+        if !page_size.is_power_of_two() || page_size < 2048 {
+            return Err(MkImageError::InvalidAlignment {
+                alignment: page_size,
+            });
+        }
+        let name = self.name.as_bytes();
+        if name.len() >= BOOT_NAME_SIZE {
+            return Err(MkImageError::NameTooLong {
+                len: name.len(),
+                max: BOOT_NAME_SIZE - 1,
+            });
+        }
+        // The command line spills over into extra_cmdline; one NUL at the end
+        let cmdline = self.cmdline.as_bytes();
+        let max_cmdline = BOOT_ARGS_SIZE + BOOT_EXTRA_ARGS_SIZE - 1;
+        if cmdline.len() > max_cmdline {
+            return Err(MkImageError::CmdlineTooLong {
+                len: cmdline.len(),
+                max: max_cmdline,
+            });
+        }
+        let (args, extra_args) = if cmdline.len() < BOOT_ARGS_SIZE {
+            (cmdline, &[][..])
+        } else {
+            cmdline.split_at(BOOT_ARGS_SIZE - 1)
+        };
+        let dtb = self.dtb.as_deref().unwrap_or_default();
+
+        let mut header = Vec::with_capacity(BOOT_V2_HEADER_SIZE as usize);
+        header.extend_from_slice(BOOT_MAGIC);
+        put_u32(&mut header, section_size(&self.kernel)?);
+        put_u32(&mut header, self.addr(self.kernel_offset)?);
+        put_u32(&mut header, section_size(&self.ramdisk)?);
+        put_u32(&mut header, self.addr(self.ramdisk_offset)?);
+        put_u32(&mut header, 0); // second_size
+        put_u32(&mut header, 0); // second_addr
+        put_u32(&mut header, self.addr(self.tags_offset)?);
+        put_u32(&mut header, page_size);
+        put_u32(&mut header, BootImageVersion::V2.number());
+        put_u32(&mut header, self.os_version);
+        put_padded(&mut header, name, BOOT_NAME_SIZE);
+        put_padded(&mut header, args, BOOT_ARGS_SIZE);
+        put_padded(&mut header, &self.id(dtb), 32);
+        put_padded(&mut header, extra_args, BOOT_EXTRA_ARGS_SIZE);
+        put_u32(&mut header, 0); // recovery_dtbo_size
+        header.extend_from_slice(&0u64.to_le_bytes()); // recovery_dtbo_offset
+        put_u32(&mut header, BOOT_V2_HEADER_SIZE);
+        put_u32(&mut header, section_size(dtb)?);
+        header.extend_from_slice(&(self.base + self.dtb_offset).to_le_bytes());
+        debug_assert_eq!(header.len(), BOOT_V2_HEADER_SIZE as usize);
+
+        let mut image = Vec::new();
+        for section in [&header[..], &self.kernel, &self.ramdisk, dtb] {
+            put_section(&mut image, section, page_size);
+        }
+        Ok(image)
+    }
+
+    fn build_v3(&self) -> Result<Vec<u8>> {
+        if self.dtb.is_some() {
+            return Err(MkImageError::invalid_image_data(
+                "boot image v3/v4 carries no DTB, it belongs in vendor_boot",
+            ));
+        }
+        let cmdline = self.cmdline.as_bytes();
+        if cmdline.len() >= BOOT_V3_ARGS_SIZE {
+            return Err(MkImageError::CmdlineTooLong {
+                len: cmdline.len(),
+                max: BOOT_V3_ARGS_SIZE - 1,
+            });
+        }
+        let v4 = self.version == BootImageVersion::V4;
+        let header_size = if v4 {
+            BOOT_V4_HEADER_SIZE
+        } else {
+            BOOT_V3_HEADER_SIZE
+        };
+
+        let mut header = Vec::with_capacity(header_size as usize);
+        header.extend_from_slice(BOOT_MAGIC);
+        put_u32(&mut header, section_size(&self.kernel)?);
+        put_u32(&mut header, section_size(&self.ramdisk)?);
+        put_u32(&mut header, self.os_version);
+        put_u32(&mut header, header_size);
+        header.extend_from_slice(&[0; 16]); // reserved
+        put_u32(&mut header, self.version.number());
+        put_padded(&mut header, cmdline, BOOT_V3_ARGS_SIZE);
+        if v4 {
+            put_u32(&mut header, 0); // signature_size
+        }
+        debug_assert_eq!(header.len(), header_size as usize);
+
+        let mut image = Vec::new();
+        for section in [&header[..], &self.kernel, &self.ramdisk] {
+            put_section(&mut image, section, BOOT_V3_PAGE_SIZE);
+        }
+        Ok(image)
+    }
+
+    /// Absolute 32-bit load address for an offset from `base`
+    fn addr(&self, offset: u64) -> Result<u32> {
+        let address = self.base + offset;
+        u32::try_from(address).map_err(|_| MkImageError::InvalidLoadAddress { address })
+    }
+
+    /// `mkbootimg` image id: SHA1 over each section followed by its size
+    fn id(&self, dtb: &[u8]) -> [u8; 20] {
+        let mut hasher = Sha1::new();
+        for section in [&self.kernel[..], &self.ramdisk, &[], &[], dtb] {
+            hasher.update(section);
+            hasher.update((section.len() as u32).to_le_bytes());
+        }
+        hasher.finalize().into()
+    }
+}
+
+/// Sections of a parsed Android boot image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootImageInfo {
+    /// Header version
+    pub version: BootImageVersion,
+    /// Page size
+    pub page_size: u32,
+    /// Kernel command line
+    pub cmdline: String,
+    /// Kernel load address (v2 only)
+    pub kernel_addr: Option<u32>,
+    /// Kernel image
+    pub kernel: Vec<u8>,
+    /// Ramdisk
+    pub ramdisk: Vec<u8>,
+    /// Device tree blob (v2 only)
+    pub dtb: Option<Vec<u8>>,
+}
+
+/// Parse an Android boot image produced by [`AndroidBootImage`] or `mkbootimg`.
+pub fn read_boot_image(data: &[u8]) -> Result<BootImageInfo> {
+    if !data.starts_with(BOOT_MAGIC) {
+        return Err(MkImageError::invalid_image_data(
+            "not an Android boot image",
+        ));
+    }
+    let word = |offset: usize| -> Result<u32> {
+        data.get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+            .ok_or_else(|| MkImageError::invalid_image_data("boot image header truncated"))
+    };
+    // header_version sits at offset 40 in every layout
+    let number = word(40)?;
+    let version = BootImageVersion::from_number(number).ok_or_else(|| {
+        MkImageError::invalid_image_data(format!("unsupported boot image version {number}"))
+    })?;
+    let text = |range: std::ops::Range<usize>| -> Result<String> {
+        let bytes = data
+            .get(range)
+            .ok_or_else(|| MkImageError::invalid_image_data("boot image header truncated"))?;
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+    };
+
+    let (page_size, kernel_addr, cmdline, sizes) = match version {
+        BootImageVersion::V2 => {
+            let args_start = 64;
+            let extra_start = args_start + BOOT_ARGS_SIZE + 32;
+            let mut cmdline = text(args_start..args_start + BOOT_ARGS_SIZE)?;
+            cmdline.push_str(&text(extra_start..extra_start + BOOT_EXTRA_ARGS_SIZE)?);
+            // kernel, ramdisk, second, recovery_dtbo, dtb
+            let sizes = [word(8)?, word(16)?, word(24)?, word(1632)?, word(1648)?];
+            (word(36)?, Some(word(12)?), cmdline, sizes.to_vec())
+        }
+        BootImageVersion::V3 | BootImageVersion::V4 => {
+            let cmdline = text(44..44 + BOOT_V3_ARGS_SIZE)?;
+            (BOOT_V3_PAGE_SIZE, None, cmdline, vec![word(8)?, word(12)?])
+        }
+    };
+    if page_size == 0 {
+        return Err(MkImageError::invalid_image_data(
+            "boot image page size is 0",
+        ));
+    }
+
+    let mut offset = page_size as usize;
+    let mut sections = Vec::new();
+    for size in sizes {
+        let size = size as usize;
+        let section = data
+            .get(offset..offset + size)
+            .ok_or_else(|| MkImageError::invalid_image_data("boot image section truncated"))?;
+        sections.push(section.to_vec());
+        offset += size.div_ceil(page_size as usize) * page_size as usize;
+    }
+
+    let mut sections = sections.into_iter();
+    let kernel = sections.next().unwrap_or_default();
+    let ramdisk = sections.next().unwrap_or_default();
+    let dtb = sections.last().filter(|d| !d.is_empty());
+    Ok(BootImageInfo {
+        version,
+        page_size,
+        cmdline,
+        kernel_addr,
+        kernel,
+        ramdisk,
+        dtb,
+    })
+}
+
+fn section_size(data: &[u8]) -> Result<u32> {
+    u32::try_from(data.len()).map_err(|_| MkImageError::DataTooLarge {
+        size: data.len() as u64,
+        max: u32::MAX as u64,
+    })
+}
+
+fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Write `data` into a zero-filled field of `len` bytes
+fn put_padded(buffer: &mut Vec<u8>, data: &[u8], len: usize) {
+    buffer.extend_from_slice(data);
+    buffer.resize(buffer.len() + len - data.len(), 0);
+}
+
+/// Append a section, zero-padded to a whole number of pages
+fn put_section(image: &mut Vec<u8>, data: &[u8], page_size: u32) {
+    image.extend_from_slice(data);
+    let page = page_size as usize;
+    image.resize(image.len().div_ceil(page) * page, 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_v2_layout_and_roundtrip() {
+        let image = AndroidBootImage::new(vec![0x11; 3000])
+            .with_ramdisk(vec![0x22; 100])
+            .with_dtb(vec![0xd0, 0x0d, 0xfe, 0xed])
+            .with_cmdline("console=ttyS0,115200")
+            .with_name("ostool")
+            .with_base(0x4000_0000)
+            .build()
+            .unwrap();
+
+        // header + 2 kernel pages + ramdisk page + dtb page
+        assert_eq!(image.len(), 5 * 2048);
+        assert_eq!(&image[0..8], BOOT_MAGIC);
+        assert_eq!(image[12..16], 0x4000_8000u32.to_le_bytes());
+        assert_eq!(image[1644..1648], BOOT_V2_HEADER_SIZE.to_le_bytes());
+
+        let info = read_boot_image(&image).unwrap();
+        assert_eq!(info.version, BootImageVersion::V2);
+        assert_eq!(info.kernel_addr, Some(0x4000_8000));
+        assert_eq!(info.cmdline, "console=ttyS0,115200");
+        assert_eq!(info.kernel, vec![0x11; 3000]);
+        assert_eq!(info.ramdisk, vec![0x22; 100]);
+        assert_eq!(info.dtb, Some(vec![0xd0, 0x0d, 0xfe, 0xed]));
+    }
+
+    #[test]
+    fn test_v2_long_cmdline_uses_extra_args() {
+        let cmdline = "x".repeat(700);
+        let image = AndroidBootImage::new(vec![1])
+            .with_cmdline(&cmdline)
+            .build()
+            .unwrap();
+        assert_eq!(read_boot_image(&image).unwrap().cmdline, cmdline);
+
+        let err = AndroidBootImage::new(vec![1])
+            .with_cmdline("x".repeat(2000))
+            .build();
+        assert!(matches!(err, Err(MkImageError::CmdlineTooLong { .. })));
+    }
+
+    #[test]
+    fn test_v3_and_v4() {
+        for version in [BootImageVersion::V3, BootImageVersion::V4] {
+            let image = AndroidBootImage::new(vec![7; 10])
+                .with_ramdisk(vec![8; 5000])
+                .with_cmdline("quiet")
+                .with_version(version)
+                .build()
+                .unwrap();
+            assert_eq!(image.len(), 4 * 4096);
+
+            let info = read_boot_image(&image).unwrap();
+            assert_eq!(info.version, version);
+            assert_eq!(info.kernel, vec![7; 10]);
+            assert_eq!(info.ramdisk, vec![8; 5000]);
+            assert_eq!(info.cmdline, "quiet");
+            assert_eq!(info.dtb, None);
+        }
+
+        let err = AndroidBootImage::new(vec![1])
+            .with_dtb(vec![0; 4])
+            .with_version(BootImageVersion::V3)
+            .build();
+        assert!(err.is_err());
+    }
+}
@@ -17,6 +17,9 @@ pub enum MkImageError {
     #[error("Unsupported architecture: {0}")]
     UnsupportedArch(String),
 
+    #[error("Unsupported operating system: {0}")]
+    UnsupportedOs(String),
+
     #[error("Unsupported compression type: {0}")]
     UnsupportedCompression(String),
 
@@ -35,9 +38,22 @@ pub enum MkImageError {
     #[error("Image name too long: {len} bytes (max {max} bytes)")]
     NameTooLong { len: usize, max: usize },
 
+    #[error("Kernel command line too long: {len} bytes (max {max} bytes)")]
+    CmdlineTooLong { len: usize, max: usize },
+
     #[error("Data size too large: {size} bytes (max {max} bytes)")]
     DataTooLarge { size: u64, max: u64 },
 
+    #[error("Image too large: {size} bytes (max {max} bytes)\n{breakdown}")]
+    ImageTooLarge {
+        size: u64,
+        max: u64,
+        breakdown: String,
+    },
+
+    #[error("Invalid alignment: {alignment} (must be a power of two)")]
+    InvalidAlignment { alignment: u32 },
+
     #[error("Invalid load address: 0x{address:x}")]
     InvalidLoadAddress { address: u64 },
 
@@ -56,6 +72,9 @@ pub enum MkImageError {
     #[error("FIT serialization error: {0}")]
     FitSerialization(String),
 
+    #[error("Signature error: {0}")]
+    Signature(String),
+
     #[error("Unknown error: {0}")]
     Other(String),
 }
@@ -76,6 +95,11 @@ impl MkImageError {
         Self::UnsupportedArch(arch.into())
     }
 
+    /// Create an unsupported operating system error
+    pub fn unsupported_os(os: impl Into<String>) -> Self {
+        Self::UnsupportedOs(os.into())
+    }
+
     /// Create an unsupported compression error
     pub fn unsupported_compression(comp: impl Into<String>) -> Self {
         Self::UnsupportedCompression(comp.into())
@@ -104,6 +128,11 @@ impl MkImageError {
         Self::Serialization(msg.into())
     }
 
+    /// Create a signature error
+    pub fn signature(msg: impl Into<String>) -> Self {
+        Self::Signature(msg.into())
+    }
+
     /// Create an other error
     pub fn other(msg: impl Into<String>) -> Self {
         Self::Other(msg.into())
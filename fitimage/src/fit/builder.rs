@@ -4,53 +4,191 @@
 
 use crate::compression::gzip::GzipCompressor;
 use crate::compression::traits::CompressionInterface;
-use crate::error::Result;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::thread;
+
+use crate::error::{MkImageError, Result};
 use crate::fit::config::FitImageConfig;
 use crate::fit::standard_dt_builder::StandardFdtBuilder;
+use crate::hash::HashAlgorithm;
+use crate::signature::SigningKey;
 
 /// Main FIT image builder
-pub struct FitImageBuilder;
+#[derive(Debug, Clone)]
+pub struct FitImageBuilder {
+    /// Alignment of image payloads within the blob
+    data_alignment: Option<u32>,
+    /// Block size the image is padded to
+    block_size: Option<u32>,
+    /// Maximum total image size
+    max_size: Option<u64>,
+    /// Key used to sign every image and configuration
+    signing_key: Option<SigningKey>,
+}
 
 impl FitImageBuilder {
     /// Create a new FIT image builder
     pub fn new() -> Self {
-        Self
+        Self {
+            data_alignment: None,
+            block_size: None,
+            max_size: None,
+            signing_key: None,
+        }
+    }
+
+    /// Align each image `data` payload to `alignment` bytes (a power of two).
+    pub fn with_data_alignment(mut self, alignment: u32) -> Self {
+        self.data_alignment = Some(alignment);
+        self
+    }
+
+    /// Pad the final image to a multiple of `block_size` bytes (e.g. 512 or 4096).
+    pub fn with_block_size(mut self, block_size: u32) -> Self {
+        self.block_size = Some(block_size);
+        self
+    }
+
+    /// Fail the build if the final image exceeds `max_size` bytes.
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Sign every image payload and every configuration with `key`,
+    /// adding `signature-1` nodes for U-Boot verified boot.
+    ///
+    /// Configuration signatures cover the images through their hashes, so
+    /// images without any requested hash get a SHA-256 hash node.
+    pub fn with_signing_key(mut self, key: SigningKey) -> Self {
+        self.signing_key = Some(key);
+        self
     }
 
     /// Build a FIT image from configuration
     pub fn build(&mut self, mut config: FitImageConfig) -> Result<Vec<u8>> {
-        // Apply compression to kernel if requested
-        if let Some(ref mut kernel) = config.kernel {
-            if kernel.compression {
-                let compressor = GzipCompressor::default();
-                kernel.data = compressor.compress(&kernel.data)?;
-            }
-        }
-        if let Some(ref mut fdt) = config.fdt {
-            if fdt.compression {
-                let compressor = GzipCompressor::default();
-                fdt.data = compressor.compress(&fdt.data)?;
-            }
-        }
+        // 压缩、哈希和签名是构建中最耗时的部分，各组件并行处理
+        let prepared = prepare_components(&mut config, self.signing_key.as_ref())?;
 
-        if let Some(ref mut ramdisk) = config.ramdisk {
-            if ramdisk.compression {
-                let compressor = GzipCompressor::default();
-                ramdisk.data = compressor.compress(&ramdisk.data)?;
-            }
-        }
+        config.validate()?;
 
         // Build standard FDT structure
-        let mut dt_builder = StandardFdtBuilder::new()?;
+        let mut dt_builder = StandardFdtBuilder::new()?.with_hash_values(prepared.hashes);
+        if let Some(key) = &self.signing_key {
+            dt_builder = dt_builder
+                .with_signing_key(key.clone())
+                .with_signature_values(prepared.signatures);
+        }
+        if let Some(alignment) = self.data_alignment {
+            dt_builder = dt_builder.with_data_alignment(alignment)?;
+        }
+        if let Some(block_size) = self.block_size {
+            dt_builder = dt_builder.with_block_size(block_size)?;
+        }
         dt_builder.build_fit_tree(&config)?;
 
         // Generate FIT image data
         let fit_data = dt_builder.finalize()?;
 
+        if let Some(max) = self.max_size {
+            let size = fit_data.len() as u64;
+            if size > max {
+                return Err(MkImageError::ImageTooLarge {
+                    size,
+                    max,
+                    breakdown: size_breakdown(&config, size),
+                });
+            }
+        }
+
         Ok(fit_data)
     }
 }
 
+/// Hashes and signatures of the components, by component name.
+#[derive(Default)]
+struct Prepared {
+    /// Hex hash values, in the order of the component's `hashes`
+    hashes: HashMap<String, Vec<String>>,
+    /// Signature values, when a signing key is set
+    signatures: HashMap<String, Vec<u8>>,
+}
+
+/// Compress the components that request it, then calculate their hashes
+/// and signatures, one thread per component.
+fn prepare_components(config: &mut FitImageConfig, key: Option<&SigningKey>) -> Result<Prepared> {
+    let components = config
+        .kernel
+        .iter_mut()
+        .chain(&mut config.fdt)
+        .chain(&mut config.overlays)
+        .chain(&mut config.ramdisk)
+        .chain(&mut config.script)
+        .chain(&mut config.firmware)
+        .chain(&mut config.loadables);
+
+    thread::scope(|scope| {
+        let handles = components
+            .map(|component| {
+                scope.spawn(move || -> Result<(String, Vec<String>, Option<Vec<u8>>)> {
+                    if component.compression {
+                        let compressor = GzipCompressor::default();
+                        component.data = compressor.compress(&component.data)?;
+                    }
+                    if key.is_some() && component.hashes.is_empty() {
+                        component.hashes.push(HashAlgorithm::Sha256);
+                    }
+                    let values = component
+                        .hashes
+                        .iter()
+                        .map(|algo| algo.calculate(&component.data))
+                        .collect();
+                    let signature = key.map(|key| key.sign(&component.data)).transpose()?;
+                    Ok((component.name.clone(), values, signature))
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut prepared = Prepared::default();
+        for handle in handles {
+            let (name, hashes, signature) = handle.join().expect("component thread panicked")?;
+            if let Some(signature) = signature {
+                prepared.signatures.insert(name.clone(), signature);
+            }
+            prepared.hashes.insert(name, hashes);
+        }
+        Ok(prepared)
+    })
+}
+
+/// Per-component size listing used in size limit errors.
+fn size_breakdown(config: &FitImageConfig, total: u64) -> String {
+    let components = config
+        .kernel
+        .iter()
+        .chain(&config.fdt)
+        .chain(&config.overlays)
+        .chain(&config.ramdisk)
+        .chain(&config.script)
+        .chain(&config.firmware)
+        .chain(&config.loadables);
+
+    let mut out = String::new();
+    let mut payload = 0;
+    for component in components {
+        let size = component.data.len() as u64;
+        payload += size;
+        let _ = writeln!(out, "  {:<20} {size:>10} bytes", component.name);
+    }
+    let _ = write!(
+        out,
+        "  {:<20} {:>10} bytes",
+        "(metadata/padding)",
+        total.saturating_sub(payload)
+    );
+    out
+}
+
 impl Default for FitImageBuilder {
     fn default() -> Self {
         Self::new()
@@ -103,6 +241,132 @@ mod tests {
         assert_eq!(&fit_data[0..4], b"\xd0\x0d\xfe\xed");
     }
 
+    #[test]
+    fn test_parallel_compression_and_hashes() {
+        let kernel = (0..64 * 1024).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let ramdisk = vec![0x5a; 32 * 1024];
+        let config = FitImageConfig::new("Parallel")
+            .with_kernel(
+                ComponentConfig::new("kernel", kernel)
+                    .with_compression(true)
+                    .with_hash(HashAlgorithm::Crc32)
+                    .with_hash(HashAlgorithm::Sha1),
+            )
+            .with_fdt(ComponentConfig::new("fdt", vec![4, 5, 6]).with_hash(HashAlgorithm::Md5))
+            .with_ramdisk(
+                ComponentConfig::new("ramdisk", ramdisk)
+                    .with_compression(true)
+                    .with_hash(HashAlgorithm::Sha1),
+            );
+
+        let fit_data = FitImageBuilder::new().build(config).unwrap();
+
+        let reader = crate::fit::FitImageReader::parse(&fit_data).unwrap();
+        let report = reader.verify();
+        assert!(report.is_ok(), "{report:?}");
+        assert_eq!(report.checks.len(), 4);
+        // 哈希针对压缩后的数据
+        assert!(reader.image_data("ramdisk").unwrap().len() < 32 * 1024);
+    }
+
+    #[test]
+    fn test_data_alignment_and_block_padding() {
+        let config = FitImageConfig::new("Aligned").with_kernel(
+            ComponentConfig::new("kernel", vec![0xaa; 100]).with_load_address(0x80080000),
+        );
+
+        let fit_data = FitImageBuilder::new()
+            .with_data_alignment(64)
+            .with_block_size(512)
+            .build(config)
+            .unwrap();
+
+        assert_eq!(fit_data.len() % 512, 0);
+        let totalsize = u32::from_be_bytes(fit_data[4..8].try_into().unwrap());
+        assert_eq!(totalsize as usize, fit_data.len());
+
+        let pos = fit_data
+            .windows(100)
+            .position(|w| w == [0xaa; 100])
+            .unwrap();
+        assert_eq!(pos % 64, 0);
+
+        let reader = crate::fit::FitImageReader::parse(&fit_data).unwrap();
+        assert_eq!(reader.image_data("kernel").unwrap(), &[0xaa; 100]);
+    }
+
+    #[test]
+    fn test_max_size_reports_breakdown() {
+        let config = FitImageConfig::new("Too big")
+            .with_kernel(ComponentConfig::new("kernel", vec![0; 4096]))
+            .with_fdt(ComponentConfig::new("fdt", vec![0; 128]));
+
+        let err = FitImageBuilder::new()
+            .with_max_size(4096)
+            .build(config)
+            .unwrap_err();
+
+        let msg = err.to_string();
+        assert!(matches!(err, MkImageError::ImageTooLarge { max: 4096, .. }));
+        assert!(
+            msg.contains("kernel") && msg.contains("4096 bytes"),
+            "{msg}"
+        );
+        assert!(msg.contains("fdt") && msg.contains("128 bytes"), "{msg}");
+    }
+
+    #[test]
+    fn test_reproducible_build() {
+        let config = || {
+            FitImageConfig::new("Reproducible")
+                .with_timestamp(crate::fit::Timestamp::Fixed(1_700_000_000))
+                .with_creator("fitimage test")
+                .with_kernel(ComponentConfig::new("kernel", vec![1, 2, 3]).with_compression(true))
+                .with_fdt(ComponentConfig::new("fdt", vec![4, 5, 6]))
+                .with_configuration(
+                    "conf-b",
+                    "b",
+                    Some("kernel"),
+                    None::<String>,
+                    None::<String>,
+                )
+                .with_configuration("conf-a", "a", Some("kernel"), Some("fdt"), None::<String>)
+                .with_configuration(
+                    "conf-c",
+                    "c",
+                    Some("kernel"),
+                    None::<String>,
+                    None::<String>,
+                )
+        };
+
+        let first = FitImageBuilder::new().build(config()).unwrap();
+        let second = FitImageBuilder::new().build(config()).unwrap();
+        assert_eq!(first, second);
+
+        let reader = crate::fit::FitImageReader::parse(&first).unwrap();
+        assert_eq!(reader.root().property_u64("timestamp"), Some(1_700_000_000));
+        assert_eq!(reader.root().property_str("creator"), Some("fitimage test"));
+        let names: Vec<_> = reader
+            .configurations()
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(names, ["conf-a", "conf-b", "conf-c"]);
+    }
+
+    #[test]
+    fn test_invalid_alignment() {
+        let err = FitImageBuilder::new()
+            .with_data_alignment(48)
+            .build(FitImageConfig::new("Bad"))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            MkImageError::InvalidAlignment { alignment: 48 }
+        ));
+    }
+
     #[test]
     fn test_empty_config() {
         let config = FitImageConfig::new("Empty FIT");
@@ -4,6 +4,11 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::error::{MkImageError, Result};
+use crate::fit::property::{FitArch, FitOs, FitType};
+use crate::hash::HashAlgorithm;
+use crate::uimage::ImageType;
+
 /// Supported compression algorithms for FIT components.
 #[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq, Eq)]
 pub enum CompressionAlgorithm {
@@ -20,21 +25,80 @@ impl CompressionAlgorithm {
     }
 }
 
+/// Source of the `timestamp` property written to generated images.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Timestamp {
+    /// `SOURCE_DATE_EPOCH` when set and valid, otherwise the current time
+    /// (the same rule mkimage follows)
+    #[default]
+    SourceDateEpoch,
+    /// Always the current time
+    Now,
+    /// A fixed value, in seconds since the Unix epoch
+    Fixed(u32),
+}
+
+impl Timestamp {
+    /// Resolve to seconds since the Unix epoch.
+    pub fn resolve(self) -> u32 {
+        let now = || {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs() as u32
+        };
+        match self {
+            Timestamp::SourceDateEpoch => std::env::var("SOURCE_DATE_EPOCH")
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or_else(now),
+            Timestamp::Now => now(),
+            Timestamp::Fixed(ts) => ts,
+        }
+    }
+}
+
 /// Configuration for building a FIT image.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FitImageConfig {
     /// Description of the FIT image
     pub description: String,
 
+    /// Root `timestamp` property source
+    #[serde(default)]
+    pub timestamp: Timestamp,
+
+    /// Root `creator` property, omitted when unset
+    #[serde(default)]
+    pub creator: Option<String>,
+
     /// Kernel component configuration
     pub kernel: Option<ComponentConfig>,
 
     /// Device tree component configuration
     pub fdt: Option<ComponentConfig>,
 
+    /// Device tree overlay components (`flat_dt` images applied on top of `fdt`)
+    #[serde(default)]
+    pub overlays: Vec<ComponentConfig>,
+
     /// Ramdisk component configuration
     pub ramdisk: Option<ComponentConfig>,
 
+    /// Boot script component configuration (`type = "script"`)
+    #[serde(default)]
+    pub script: Option<ComponentConfig>,
+
+    /// Firmware SPL jumps to (`type = "firmware"`): U-Boot proper, or ATF
+    /// when it hands over to a U-Boot among the loadables
+    #[serde(default)]
+    pub firmware: Option<ComponentConfig>,
+
+    /// Images SPL loads before starting `firmware`, e.g. ATF or OP-TEE
+    #[serde(default)]
+    pub loadables: Vec<ComponentConfig>,
+
     /// Default configuration name
     pub default_config: Option<String>,
 
@@ -53,8 +117,20 @@ pub struct FitConfiguration {
     pub kernel: Option<String>,
     /// FDT image node reference.
     pub fdt: Option<String>,
+    /// Overlay image node references, applied on top of `fdt` in order.
+    #[serde(default)]
+    pub overlays: Vec<String>,
     /// Ramdisk image node reference.
     pub ramdisk: Option<String>,
+    /// Script image node reference, run by U-Boot's `source` command.
+    #[serde(default)]
+    pub script: Option<String>,
+    /// Firmware image node reference, started by SPL.
+    #[serde(default)]
+    pub firmware: Option<String>,
+    /// Image node references SPL loads to their `load` address, in order.
+    #[serde(default)]
+    pub loadables: Vec<String>,
 }
 
 /// Configuration for a single component (kernel, fdt, ramdisk)
@@ -70,13 +146,13 @@ pub struct ComponentConfig {
     pub description: Option<String>,
 
     /// Component type (kernel, flat_dt, ramdisk, etc.)
-    pub component_type: Option<String>,
+    pub component_type: Option<FitType>,
 
     /// Architecture (arm, arm64, etc.)
-    pub arch: Option<String>,
+    pub arch: Option<FitArch>,
 
     /// OS type (linux, etc.)
-    pub os: Option<String>,
+    pub os: Option<FitOs>,
 
     /// Whether to gzip-compress this component before embedding.
     pub compression: bool,
@@ -86,6 +162,10 @@ pub struct ComponentConfig {
 
     /// Entry point address (for kernel)
     pub entry_point: Option<u64>,
+
+    /// Hash nodes (`hash-1`, `hash-2`, ...) to emit for this component
+    #[serde(default)]
+    pub hashes: Vec<HashAlgorithm>,
 }
 
 impl ComponentConfig {
@@ -101,6 +181,7 @@ impl ComponentConfig {
             compression: false,
             load_address: None,
             entry_point: None,
+            hashes: Vec::new(),
         }
     }
 
@@ -111,19 +192,19 @@ impl ComponentConfig {
     }
 
     /// Set component type
-    pub fn with_type(mut self, component_type: impl Into<String>) -> Self {
+    pub fn with_type(mut self, component_type: impl Into<FitType>) -> Self {
         self.component_type = Some(component_type.into());
         self
     }
 
     /// Set architecture
-    pub fn with_arch(mut self, arch: impl Into<String>) -> Self {
+    pub fn with_arch(mut self, arch: impl Into<FitArch>) -> Self {
         self.arch = Some(arch.into());
         self
     }
 
     /// Set OS type
-    pub fn with_os(mut self, os: impl Into<String>) -> Self {
+    pub fn with_os(mut self, os: impl Into<FitOs>) -> Self {
         self.os = Some(os.into());
         self
     }
@@ -145,6 +226,12 @@ impl ComponentConfig {
         self.entry_point = Some(entry_point);
         self
     }
+
+    /// Add a hash node computed over the embedded data
+    pub fn with_hash(mut self, algorithm: HashAlgorithm) -> Self {
+        self.hashes.push(algorithm);
+        self
+    }
 }
 
 impl FitImageConfig {
@@ -152,14 +239,34 @@ impl FitImageConfig {
     pub fn new(description: impl Into<String>) -> Self {
         Self {
             description: description.into(),
+            timestamp: Timestamp::default(),
+            creator: None,
             kernel: None,
             fdt: None,
+            overlays: Vec::new(),
             ramdisk: None,
+            script: None,
+            firmware: None,
+            loadables: Vec::new(),
             default_config: None,
             configurations: std::collections::HashMap::new(),
         }
     }
 
+    /// Set the source of the root `timestamp` property.
+    ///
+    /// Use [`Timestamp::Fixed`] for byte-identical rebuilds.
+    pub fn with_timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Set the root `creator` property.
+    pub fn with_creator(mut self, creator: impl Into<String>) -> Self {
+        self.creator = Some(creator.into());
+        self
+    }
+
     /// Set kernel component.
     pub fn with_kernel(mut self, kernel: ComponentConfig) -> Self {
         self.kernel = Some(kernel);
@@ -172,12 +279,85 @@ impl FitImageConfig {
         self
     }
 
+    /// Add a device tree overlay component.
+    ///
+    /// The default configuration applies every overlay in the order added.
+    pub fn with_overlay(mut self, overlay: ComponentConfig) -> Self {
+        self.overlays.push(overlay);
+        self
+    }
+
+    /// Check that component types fit the slot they are placed in.
+    ///
+    /// Custom types are accepted as-is.
+    pub fn validate(&self) -> Result<()> {
+        let slots = self
+            .kernel
+            .iter()
+            .map(|c| (c, "kernel"))
+            .chain(self.fdt.iter().chain(&self.overlays).map(|c| (c, "fdt")))
+            .chain(
+                self.firmware
+                    .iter()
+                    .chain(&self.loadables)
+                    .map(|c| (c, "firmware")),
+            );
+        for (component, slot) in slots {
+            let Some(ty) = component.component_type.as_ref().and_then(|t| t.known()) else {
+                continue;
+            };
+            let ok = match slot {
+                "kernel" => matches!(
+                    ty,
+                    ImageType::Kernel
+                        | ImageType::KernelNoload
+                        | ImageType::Firmware
+                        | ImageType::Standalone
+                ),
+                "firmware" => matches!(
+                    ty,
+                    ImageType::Firmware | ImageType::Standalone | ImageType::Tee
+                ),
+                _ => ty == ImageType::FlatDt,
+            };
+            if !ok {
+                return Err(MkImageError::config_parse(format!(
+                    "component `{}` has type `{ty}`, which is not valid as {slot}",
+                    component.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
     /// Set ramdisk component.
     pub fn with_ramdisk(mut self, ramdisk: ComponentConfig) -> Self {
         self.ramdisk = Some(ramdisk);
         self
     }
 
+    /// Set boot script component.
+    pub fn with_script(mut self, script: ComponentConfig) -> Self {
+        self.script = Some(script);
+        self
+    }
+
+    /// Set the firmware SPL jumps to, U-Boot proper or ATF.
+    ///
+    /// Together with [`Self::with_loadable`] this builds a `u-boot.itb`
+    /// style image: the default configuration lists the firmware, its
+    /// loadables and the device trees.
+    pub fn with_firmware(mut self, firmware: ComponentConfig) -> Self {
+        self.firmware = Some(firmware);
+        self
+    }
+
+    /// Add an image SPL loads before starting the firmware, e.g. ATF or OP-TEE.
+    pub fn with_loadable(mut self, loadable: ComponentConfig) -> Self {
+        self.loadables.push(loadable);
+        self
+    }
+
     /// Set default configuration name.
     pub fn with_default_config(mut self, default: impl Into<String>) -> Self {
         self.default_config = Some(default.into());
@@ -201,16 +381,49 @@ impl FitImageConfig {
                 description: description.into(),
                 kernel: kernel.map(Into::into),
                 fdt: fdt.map(Into::into),
+                overlays: Vec::new(),
                 ramdisk: ramdisk.map(Into::into),
+                script: None,
+                firmware: None,
+                loadables: Vec::new(),
             },
         );
         self
     }
+
+    /// Set the ordered overlay list of a configuration added with
+    /// [`Self::with_configuration`]; unknown configuration names are ignored.
+    pub fn with_configuration_overlays<S: Into<String>>(
+        mut self,
+        name: &str,
+        overlays: impl IntoIterator<Item = S>,
+    ) -> Self {
+        if let Some(conf) = self.configurations.get_mut(name) {
+            conf.overlays = overlays.into_iter().map(Into::into).collect();
+        }
+        self
+    }
+
+    /// Set the firmware and loadables of a configuration added with
+    /// [`Self::with_configuration`]; unknown configuration names are ignored.
+    pub fn with_configuration_firmware<S: Into<String>>(
+        mut self,
+        name: &str,
+        firmware: Option<impl Into<String>>,
+        loadables: impl IntoIterator<Item = S>,
+    ) -> Self {
+        if let Some(conf) = self.configurations.get_mut(name) {
+            conf.firmware = firmware.map(Into::into);
+            conf.loadables = loadables.into_iter().map(Into::into).collect();
+        }
+        self
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::uimage::{ImageArch, ImageOs};
 
     #[test]
     fn test_config_creation() {
@@ -228,9 +441,9 @@ mod tests {
     fn test_component_config() {
         let component = ComponentConfig::new("test", vec![1, 2, 3])
             .with_description("Test component")
-            .with_type("kernel")
-            .with_arch("arm64")
-            .with_os("linux")
+            .with_type(ImageType::Kernel)
+            .with_arch(ImageArch::Arm64)
+            .with_os(ImageOs::Linux)
             .with_compression(false)
             .with_load_address(0x80000)
             .with_entry_point(0x80000);
@@ -238,9 +451,9 @@ mod tests {
         assert_eq!(component.name, "test");
         assert_eq!(component.data, vec![1, 2, 3]);
         assert_eq!(component.description, Some("Test component".to_string()));
-        assert_eq!(component.component_type, Some("kernel".to_string()));
-        assert_eq!(component.arch, Some("arm64".to_string()));
-        assert_eq!(component.os, Some("linux".to_string()));
+        assert_eq!(component.component_type, Some(ImageType::Kernel.into()));
+        assert_eq!(component.arch, Some(ImageArch::Arm64.into()));
+        assert_eq!(component.os, Some(ImageOs::Linux.into()));
         assert!(!component.compression);
         assert_eq!(component.load_address, Some(0x80000));
         assert_eq!(component.entry_point, Some(0x80000));
@@ -266,4 +479,58 @@ mod tests {
         assert_eq!(config.default_config, Some("default".to_string()));
         assert!(config.configurations.contains_key("default"));
     }
+
+    #[test]
+    fn test_validate_slot_types() {
+        let config = FitImageConfig::new("Test FIT")
+            .with_kernel(ComponentConfig::new("kernel", vec![1]).with_type(ImageType::FlatDt));
+        assert!(config.validate().is_err());
+
+        let config = FitImageConfig::new("Test FIT")
+            .with_kernel(ComponentConfig::new("kernel", vec![1]).with_type(ImageType::KernelNoload))
+            .with_fdt(ComponentConfig::new("fdt", vec![2]).with_type(FitType::custom("fdt_blob")));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_configuration_overlays() {
+        let config = FitImageConfig::new("Test FIT")
+            .with_fdt(ComponentConfig::new("fdt", vec![1]))
+            .with_overlay(ComponentConfig::new("overlay-a", vec![2]))
+            .with_overlay(ComponentConfig::new("overlay-b", vec![3]))
+            .with_configuration(
+                "conf",
+                "Variant",
+                None::<String>,
+                Some("fdt"),
+                None::<String>,
+            )
+            .with_configuration_overlays("conf", ["overlay-b", "overlay-a"])
+            .with_configuration_overlays("missing", ["overlay-a"]);
+
+        assert_eq!(config.overlays.len(), 2);
+        assert_eq!(
+            config.configurations["conf"].overlays,
+            vec!["overlay-b", "overlay-a"]
+        );
+        assert!(!config.configurations.contains_key("missing"));
+    }
+
+    #[test]
+    fn test_firmware_slots() {
+        let config = FitImageConfig::new("U-Boot")
+            .with_firmware(ComponentConfig::new("atf", vec![1]).with_type(ImageType::Firmware))
+            .with_loadable(ComponentConfig::new("uboot", vec![2]).with_type(ImageType::Standalone))
+            .with_loadable(ComponentConfig::new("tee", vec![3]).with_type(ImageType::Tee))
+            .with_configuration("conf", "SPL", None::<String>, Some("fdt"), None::<String>)
+            .with_configuration_firmware("conf", Some("atf"), ["uboot", "tee"]);
+        assert!(config.validate().is_ok());
+        let conf = &config.configurations["conf"];
+        assert_eq!(conf.firmware.as_deref(), Some("atf"));
+        assert_eq!(conf.loadables, ["uboot", "tee"]);
+
+        let config = FitImageConfig::new("U-Boot")
+            .with_loadable(ComponentConfig::new("fdt", vec![1]).with_type(ImageType::FlatDt));
+        assert!(config.validate().is_err());
+    }
 }
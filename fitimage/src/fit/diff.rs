@@ -0,0 +1,262 @@
+//! FIT image comparison
+//!
+//! Walks two parsed FIT trees side by side and reports added/removed nodes,
+//! changed properties and differing image payloads.
+
+use std::fmt;
+
+use crate::fit::reader::{FdtNode, FitImageReader};
+use crate::hash::HashAlgorithm;
+
+/// Properties that only describe where payload bytes live in the blob.
+const LAYOUT_PROPERTIES: &[&str] = &["data", "data-offset", "data-position", "data-size"];
+
+/// One difference between two FIT images.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FitChange {
+    /// A node exists only in the new image.
+    NodeAdded {
+        /// Node path, e.g. `/images/ramdisk`.
+        path: String,
+    },
+    /// A node exists only in the old image.
+    NodeRemoved {
+        /// Node path.
+        path: String,
+    },
+    /// A property was added, removed or changed its value.
+    PropertyChanged {
+        /// Path of the node holding the property.
+        path: String,
+        /// Property name.
+        name: String,
+        /// Formatted old value, `None` if the property was added.
+        old: Option<String>,
+        /// Formatted new value, `None` if the property was removed.
+        new: Option<String>,
+    },
+    /// The payload of an image differs.
+    DataChanged {
+        /// Image node name.
+        image: String,
+        /// Old payload size in bytes.
+        old_size: usize,
+        /// New payload size in bytes.
+        new_size: usize,
+        /// SHA1 of the old payload.
+        old_sha1: String,
+        /// SHA1 of the new payload.
+        new_sha1: String,
+    },
+}
+
+impl fmt::Display for FitChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FitChange::NodeAdded { path } => write!(f, "+ {path}"),
+            FitChange::NodeRemoved { path } => write!(f, "- {path}"),
+            FitChange::PropertyChanged {
+                path,
+                name,
+                old,
+                new,
+            } => write!(
+                f,
+                "~ {path}:{name}: {} -> {}",
+                old.as_deref().unwrap_or("<none>"),
+                new.as_deref().unwrap_or("<none>")
+            ),
+            FitChange::DataChanged {
+                image,
+                old_size,
+                new_size,
+                old_sha1,
+                new_sha1,
+            } => write!(
+                f,
+                "~ /images/{image}: data {old_size} -> {new_size} bytes ({:+}), sha1 {} -> {}",
+                *new_size as i64 - *old_size as i64,
+                &old_sha1[..old_sha1.len().min(12)],
+                &new_sha1[..new_sha1.len().min(12)]
+            ),
+        }
+    }
+}
+
+/// Result of comparing two FIT images.
+#[derive(Debug, Clone, Default)]
+pub struct FitDiff {
+    /// Differences in tree order.
+    pub changes: Vec<FitChange>,
+    /// Total size of the old image.
+    pub old_size: u32,
+    /// Total size of the new image.
+    pub new_size: u32,
+}
+
+impl FitDiff {
+    /// Compare `old` against `new`.
+    pub fn compare(old: &FitImageReader, new: &FitImageReader) -> Self {
+        let mut diff = FitDiff {
+            changes: Vec::new(),
+            old_size: old.header().totalsize,
+            new_size: new.header().totalsize,
+        };
+        diff.compare_node("", old.root(), new.root());
+
+        for image in old.images() {
+            let Some(new_image) = new.images().iter().find(|n| n.name == image.name) else {
+                continue;
+            };
+            let (Ok(a), Ok(b)) = (old.node_data(image), new.node_data(new_image)) else {
+                continue;
+            };
+            if a != b {
+                diff.changes.push(FitChange::DataChanged {
+                    image: image.name.clone(),
+                    old_size: a.len(),
+                    new_size: b.len(),
+                    old_sha1: HashAlgorithm::Sha1.calculate(a),
+                    new_sha1: HashAlgorithm::Sha1.calculate(b),
+                });
+            }
+        }
+        diff
+    }
+
+    /// Whether the images are structurally identical.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Change of the total image size in bytes.
+    pub fn size_delta(&self) -> i64 {
+        self.new_size as i64 - self.old_size as i64
+    }
+
+    fn compare_node(&mut self, path: &str, old: &FdtNode, new: &FdtNode) {
+        let in_images = path == "/images" || path.starts_with("/images/");
+        let skip = |name: &str| in_images && LAYOUT_PROPERTIES.contains(&name);
+
+        for prop in &old.properties {
+            if skip(&prop.name) {
+                continue;
+            }
+            let new_value = new.property(&prop.name);
+            if new_value != Some(prop.value.as_slice()) {
+                self.changes.push(FitChange::PropertyChanged {
+                    path: display_path(path),
+                    name: prop.name.clone(),
+                    old: Some(format_value(&prop.value)),
+                    new: new_value.map(format_value),
+                });
+            }
+        }
+        for prop in &new.properties {
+            if skip(&prop.name) || old.property(&prop.name).is_some() {
+                continue;
+            }
+            self.changes.push(FitChange::PropertyChanged {
+                path: display_path(path),
+                name: prop.name.clone(),
+                old: None,
+                new: Some(format_value(&prop.value)),
+            });
+        }
+
+        for child in &old.children {
+            let child_path = format!("{path}/{}", child.name);
+            match new.child(&child.name) {
+                Some(new_child) => self.compare_node(&child_path, child, new_child),
+                None => self
+                    .changes
+                    .push(FitChange::NodeRemoved { path: child_path }),
+            }
+        }
+        for child in &new.children {
+            if old.child(&child.name).is_none() {
+                self.changes.push(FitChange::NodeAdded {
+                    path: format!("{path}/{}", child.name),
+                });
+            }
+        }
+    }
+}
+
+fn display_path(path: &str) -> String {
+    if path.is_empty() {
+        "/".into()
+    } else {
+        path.into()
+    }
+}
+
+/// Render a property value as strings, a cell value or hex bytes.
+fn format_value(value: &[u8]) -> String {
+    let printable =
+        |s: &[u8]| !s.is_empty() && s.iter().all(|b| b.is_ascii_graphic() || *b == b' ');
+    if value.last() == Some(&0) && value[..value.len() - 1].split(|&b| b == 0).all(printable) {
+        return value[..value.len() - 1]
+            .split(|&b| b == 0)
+            .map(|s| format!("\"{}\"", String::from_utf8_lossy(s)))
+            .collect::<Vec<_>>()
+            .join(", ");
+    }
+    match value.len() {
+        4 => format!("<{:#x}>", u32::from_be_bytes(value.try_into().unwrap())),
+        8 => format!("<{:#x}>", u64::from_be_bytes(value.try_into().unwrap())),
+        n if n <= 32 => format!("[{}]", hex::encode(value)),
+        n => format!("[{}... ({n} bytes)]", hex::encode(&value[..16])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fit::{ComponentConfig, FitImageBuilder, FitImageConfig, Timestamp};
+
+    fn build(kernel: Vec<u8>, with_fdt: bool, load: u64) -> FitImageReader {
+        let mut config = FitImageConfig::new("Diff test")
+            .with_timestamp(Timestamp::Fixed(0))
+            .with_kernel(ComponentConfig::new("kernel", kernel).with_load_address(load));
+        if with_fdt {
+            config = config.with_fdt(ComponentConfig::new("fdt", vec![9; 8]));
+        }
+        FitImageReader::parse(&FitImageBuilder::new().build(config).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_identical_images() {
+        let a = build(vec![1, 2, 3], true, 0x8000_0000);
+        let b = build(vec![1, 2, 3], true, 0x8000_0000);
+        let diff = FitDiff::compare(&a, &b);
+        assert!(diff.is_empty(), "{:?}", diff.changes);
+        assert_eq!(diff.size_delta(), 0);
+    }
+
+    #[test]
+    fn test_detects_changes() {
+        let old = build(vec![1, 2, 3], true, 0x8000_0000);
+        let new = build(vec![1, 2, 3, 4, 5], false, 0x8020_0000);
+        let diff = FitDiff::compare(&old, &new);
+
+        assert!(diff.changes.contains(&FitChange::NodeRemoved {
+            path: "/images/fdt".into()
+        }));
+        assert!(diff.changes.contains(&FitChange::PropertyChanged {
+            path: "/images/kernel".into(),
+            name: "load".into(),
+            old: Some("<0x80000000>".into()),
+            new: Some("<0x80200000>".into()),
+        }));
+        assert!(diff.changes.iter().any(|c| matches!(
+            c,
+            FitChange::DataChanged {
+                old_size: 3,
+                new_size: 5,
+                ..
+            }
+        )));
+        assert!(diff.size_delta() < 0);
+    }
+}
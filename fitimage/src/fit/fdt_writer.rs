@@ -0,0 +1,311 @@
+//! General-purpose FDT writer
+//!
+//! Sequential writer for arbitrary flattened device trees: memory reservations
+//! first, then nodes and properties in tree order, then [`FdtWriter::finish`].
+//! It produces the same layout as the FIT builder (header, reserve map,
+//! structure block, strings block) and is useful for small control DTBs or
+//! `/chosen` patches.
+//!
+//! ```rust
+//! use fitimage::fit::FdtWriter;
+//!
+//! let mut fdt = FdtWriter::new();
+//! fdt.add_mem_reserve(0x8000_0000, 0x10_0000).unwrap();
+//! fdt.begin_node("").unwrap();
+//! fdt.property_u32("#address-cells", 2).unwrap();
+//! fdt.begin_node("chosen").unwrap();
+//! fdt.property_string("bootargs", "console=ttyS0,115200").unwrap();
+//! fdt.property_u64("linux,initrd-start", 0x8400_0000).unwrap();
+//! fdt.end_node().unwrap();
+//! fdt.end_node().unwrap();
+//! let dtb = fdt.finish().unwrap();
+//! assert_eq!(dtb[0..4], [0xd0, 0x0d, 0xfe, 0xed]);
+//! ```
+
+use crate::error::{MkImageError, Result};
+use crate::fit::{FdtHeader, FdtToken, FdtTokenUtils, MemReserveEntry, StringTable};
+
+/// Builds an FDT blob node by node.
+#[derive(Debug, Clone, Default)]
+pub struct FdtWriter {
+    header: FdtHeader,
+    string_table: StringTable,
+    struct_buffer: Vec<u8>,
+    mem_reserve: Vec<MemReserveEntry>,
+    /// Number of currently open nodes
+    depth: usize,
+    /// Whether the root node has been closed
+    root_done: bool,
+}
+
+impl FdtWriter {
+    /// Create an empty writer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a memory reservation entry.
+    ///
+    /// Must be called before the root node is begun, since the reserve map
+    /// precedes the structure block.
+    pub fn add_mem_reserve(&mut self, address: u64, size: u64) -> Result<()> {
+        if !self.struct_buffer.is_empty() {
+            return Err(MkImageError::fit_serialization_error(
+                "memory reservations must be added before the first node",
+            ));
+        }
+        self.mem_reserve.push(MemReserveEntry::new(address, size));
+        Ok(())
+    }
+
+    /// Set the `boot_cpuid_phys` header field.
+    pub fn set_boot_cpuid_phys(&mut self, cpuid: u32) {
+        self.header.boot_cpuid_phys = cpuid;
+    }
+
+    /// Open a node. The first node is the root and must have an empty name.
+    pub fn begin_node(&mut self, name: &str) -> Result<()> {
+        if self.root_done {
+            return Err(MkImageError::fit_serialization_error(format!(
+                "node `{name}` begun after the root node was closed"
+            )));
+        }
+        if self.depth == 0 && !name.is_empty() {
+            return Err(MkImageError::fit_serialization_error(format!(
+                "root node must have an empty name, got `{name}`"
+            )));
+        }
+        if self.depth > 0 && (name.is_empty() || name.contains(['\0', '/'])) {
+            return Err(MkImageError::fit_serialization_error(format!(
+                "invalid node name `{name}`"
+            )));
+        }
+        FdtToken::BeginNode.write_to_buffer(&mut self.struct_buffer);
+        FdtTokenUtils::write_string(&mut self.struct_buffer, name)?;
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Close the most recently opened node.
+    pub fn end_node(&mut self) -> Result<()> {
+        if self.depth == 0 {
+            return Err(MkImageError::fit_serialization_error(
+                "end_node without a matching begin_node",
+            ));
+        }
+        FdtToken::EndNode.write_to_buffer(&mut self.struct_buffer);
+        self.depth -= 1;
+        self.root_done = self.depth == 0;
+        Ok(())
+    }
+
+    /// Add a property with a raw value.
+    pub fn property(&mut self, name: &str, value: &[u8]) -> Result<()> {
+        if self.depth == 0 {
+            return Err(MkImageError::fit_serialization_error(format!(
+                "property `{name}` outside of a node"
+            )));
+        }
+        if name.is_empty() || name.contains('\0') {
+            return Err(MkImageError::fit_serialization_error(format!(
+                "invalid property name `{name}`"
+            )));
+        }
+        let len = u32::try_from(value.len()).map_err(|_| MkImageError::DataTooLarge {
+            size: value.len() as u64,
+            max: u32::MAX as u64,
+        })?;
+        let name_offset = self.string_table.add_string(name);
+
+        FdtToken::Prop.write_to_buffer(&mut self.struct_buffer);
+        FdtTokenUtils::write_prop_header(&mut self.struct_buffer, len, name_offset)?;
+        FdtTokenUtils::write_prop_data(&mut self.struct_buffer, value)
+    }
+
+    /// Add an empty (boolean) property.
+    pub fn property_empty(&mut self, name: &str) -> Result<()> {
+        self.property(name, &[])
+    }
+
+    /// Add a NUL-terminated string property.
+    pub fn property_string(&mut self, name: &str, value: &str) -> Result<()> {
+        self.property_string_list(name, &[value])
+    }
+
+    /// Add a string list property (NUL-separated strings).
+    pub fn property_string_list<S: AsRef<str>>(&mut self, name: &str, values: &[S]) -> Result<()> {
+        let mut data = Vec::new();
+        for value in values {
+            data.extend_from_slice(value.as_ref().as_bytes());
+            data.push(0);
+        }
+        self.property(name, &data)
+    }
+
+    /// Add a single-cell property.
+    pub fn property_u32(&mut self, name: &str, value: u32) -> Result<()> {
+        self.property(name, &value.to_be_bytes())
+    }
+
+    /// Add a two-cell property.
+    pub fn property_u64(&mut self, name: &str, value: u64) -> Result<()> {
+        self.property(name, &value.to_be_bytes())
+    }
+
+    /// Add a property made of 32-bit cells, e.g. `reg` or `interrupts`.
+    pub fn property_cells(&mut self, name: &str, cells: &[u32]) -> Result<()> {
+        let data: Vec<u8> = cells.iter().flat_map(|c| c.to_be_bytes()).collect();
+        self.property(name, &data)
+    }
+
+    /// Insert `FDT_NOP` tokens so the value of the next property starts at a
+    /// multiple of `alignment` bytes from the start of the blob.
+    pub fn align_next_property(&mut self, alignment: u32) -> Result<()> {
+        check_alignment(alignment)?;
+        let align = alignment as usize;
+        // Value follows the PROP token, length and name offset
+        while !(self.struct_offset() + self.struct_buffer.len() + 12).is_multiple_of(align) {
+            FdtToken::Nop.write_to_buffer(&mut self.struct_buffer);
+        }
+        Ok(())
+    }
+
+    /// Finish the tree and return the blob.
+    pub fn finish(self) -> Result<Vec<u8>> {
+        self.finish_padded(1)
+    }
+
+    /// Finish the tree, zero-padding the blob (and `totalsize`) to a multiple
+    /// of `block_size`.
+    pub fn finish_padded(mut self, block_size: u32) -> Result<Vec<u8>> {
+        check_alignment(block_size)?;
+        if !self.root_done {
+            return Err(MkImageError::fit_serialization_error(if self.depth == 0 {
+                "FDT has no root node".to_string()
+            } else {
+                format!("{} node(s) left open", self.depth)
+            }));
+        }
+        FdtToken::End.write_to_buffer(&mut self.struct_buffer);
+
+        // Layout: [Header][Mem Reserve Map][FDT Structure][String Table]
+        let off_mem_rsvmap = FdtHeader::size() as u32;
+        let off_dt_struct = self.struct_offset() as u32;
+        let struct_size = self.struct_buffer.len() as u32;
+        let strings_size = self.string_table.size() as u32;
+        let off_dt_strings = off_dt_struct + struct_size;
+        let total_size = (off_dt_strings + strings_size).div_ceil(block_size) * block_size;
+
+        self.header.finalize(
+            total_size,
+            off_dt_struct,
+            off_dt_strings,
+            off_mem_rsvmap,
+            strings_size,
+            struct_size,
+        );
+
+        let mut result = Vec::with_capacity(total_size as usize);
+        self.header.write_to_buffer(&mut result);
+        for entry in &self.mem_reserve {
+            entry.write_to_buffer(&mut result);
+        }
+        MemReserveEntry::write_terminator(&mut result);
+        result.extend_from_slice(&self.struct_buffer);
+        result.extend_from_slice(self.string_table.data());
+        result.resize(total_size as usize, 0);
+
+        Ok(result)
+    }
+
+    /// Offset of the structure block within the final blob
+    fn struct_offset(&self) -> usize {
+        FdtHeader::size() + (self.mem_reserve.len() + 1) * MemReserveEntry::size()
+    }
+}
+
+pub(crate) fn check_alignment(alignment: u32) -> Result<()> {
+    if alignment.is_power_of_two() {
+        Ok(())
+    } else {
+        Err(MkImageError::InvalidAlignment { alignment })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fit::FitImageReader;
+
+    #[test]
+    fn test_roundtrip_through_reader() {
+        let mut fdt = FdtWriter::new();
+        fdt.add_mem_reserve(0x8000_0000, 0x1000).unwrap();
+        fdt.set_boot_cpuid_phys(1);
+        fdt.begin_node("").unwrap();
+        fdt.property_string("compatible", "ostool,test").unwrap();
+        fdt.begin_node("chosen").unwrap();
+        fdt.property_string_list("stdout-path", &["serial0", "uart"])
+            .unwrap();
+        fdt.property_cells("reg", &[0, 0x4000_0000, 0x1000])
+            .unwrap();
+        fdt.property_empty("ostool,patched").unwrap();
+        fdt.end_node().unwrap();
+        fdt.end_node().unwrap();
+        let blob = fdt.finish().unwrap();
+
+        let reader = FitImageReader::parse(&blob).unwrap();
+        assert_eq!(reader.header().totalsize as usize, blob.len());
+        assert_eq!(reader.header().boot_cpuid_phys, 1);
+        assert_eq!(
+            reader.root().property_str("compatible"),
+            Some("ostool,test")
+        );
+        let chosen = reader.root().child("chosen").unwrap();
+        assert_eq!(chosen.property_strings("stdout-path"), ["serial0", "uart"]);
+        assert_eq!(
+            chosen.property("reg").unwrap(),
+            [0, 0, 0, 0, 0x40, 0, 0, 0, 0, 0, 0x10, 0]
+        );
+        assert_eq!(chosen.property("ostool,patched"), Some(&[][..]));
+
+        // One entry plus the terminator
+        let rsv = reader.header().off_mem_rsvmap as usize;
+        assert_eq!(blob[rsv..rsv + 8], 0x8000_0000u64.to_be_bytes());
+        assert_eq!(blob[rsv + 8..rsv + 16], 0x1000u64.to_be_bytes());
+        assert_eq!(blob[rsv + 16..rsv + 32], [0; 16]);
+    }
+
+    #[test]
+    fn test_rejects_malformed_trees() {
+        let mut fdt = FdtWriter::new();
+        assert!(fdt.property_u32("x", 1).is_err());
+        assert!(fdt.begin_node("root").is_err());
+        fdt.begin_node("").unwrap();
+        assert!(fdt.add_mem_reserve(0, 1).is_err());
+        assert!(fdt.begin_node("a/b").is_err());
+        fdt.begin_node("child").unwrap();
+        assert!(fdt.clone().finish().is_err());
+        fdt.end_node().unwrap();
+        fdt.end_node().unwrap();
+        assert!(fdt.end_node().is_err());
+        assert!(fdt.begin_node("").is_err());
+        assert!(FdtWriter::new().finish().is_err());
+    }
+
+    #[test]
+    fn test_alignment_and_padding() {
+        let mut fdt = FdtWriter::new();
+        fdt.begin_node("").unwrap();
+        fdt.property_u32("a", 1).unwrap();
+        fdt.align_next_property(64).unwrap();
+        fdt.property("data", &[0xaa; 5]).unwrap();
+        fdt.end_node().unwrap();
+        let blob = fdt.finish_padded(512).unwrap();
+
+        assert!(blob.len().is_multiple_of(512));
+        let pos = blob.windows(5).position(|w| w == [0xaa; 5]).unwrap();
+        assert!(pos.is_multiple_of(64));
+        assert!(FdtWriter::new().align_next_property(3).is_err());
+    }
+}
@@ -0,0 +1,927 @@
+//! ITS (Image Tree Source) support
+//!
+//! Converts between [`FitImageConfig`] and the `.its` device-tree source
+//! format consumed by `mkimage -f`, so existing mkimage workflows can be
+//! replaced by [`crate::FitImageBuilder`] without rewriting their sources.
+//!
+//! Only the subset of DTS syntax used by FIT sources is understood: nodes,
+//! string / string-list properties, `<cells>`, `[bytes]`, `/incbin/("file")`
+//! and C/C++ style comments.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::compression::gzip::GzipCompressor;
+use crate::compression::traits::CompressionInterface;
+use crate::error::{MkImageError, Result};
+use crate::fit::config::{ComponentConfig, FitConfiguration, FitImageConfig, Timestamp};
+use crate::hash::HashAlgorithm;
+
+/// Gzip stream magic bytes.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// A property value in an ITS source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ItsValue {
+    /// Property without a value (`name;`).
+    Empty,
+    /// One or more strings (`"a", "b"`).
+    Strings(Vec<String>),
+    /// 32-bit cells (`<0x1 0x2>`).
+    Cells(Vec<u32>),
+    /// Raw bytes (`[01 02]`).
+    Bytes(Vec<u8>),
+    /// File reference (`/incbin/("path")`), relative to the source file.
+    Incbin(PathBuf),
+}
+
+impl ItsValue {
+    /// Return the first string of a string value.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ItsValue::Strings(s) => s.first().map(String::as_str),
+            _ => None,
+        }
+    }
+
+    /// Interpret one or two cells as an address.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            ItsValue::Cells(c) if c.len() == 1 => Some(c[0] as u64),
+            ItsValue::Cells(c) if c.len() == 2 => Some(((c[0] as u64) << 32) | c[1] as u64),
+            _ => None,
+        }
+    }
+}
+
+/// A node of a parsed ITS source tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ItsNode {
+    /// Node name (empty for the root node).
+    pub name: String,
+    /// Properties in source order.
+    pub properties: Vec<(String, ItsValue)>,
+    /// Child nodes in source order.
+    pub children: Vec<ItsNode>,
+}
+
+impl ItsNode {
+    /// Find a property by name.
+    pub fn property(&self, name: &str) -> Option<&ItsValue> {
+        self.properties
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v)
+    }
+
+    /// Find a direct child node by name.
+    pub fn child(&self, name: &str) -> Option<&ItsNode> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    fn string(&self, name: &str) -> Option<String> {
+        self.property(name)
+            .and_then(ItsValue::as_str)
+            .map(String::from)
+    }
+}
+
+/// Parse ITS source text into a node tree.
+pub fn parse_its(source: &str) -> Result<ItsNode> {
+    let mut parser = ItsParser {
+        src: source.as_bytes(),
+        pos: 0,
+    };
+    parser.parse_document()
+}
+
+impl FitImageConfig {
+    /// Parse an `.its` file, resolving `/incbin/` references relative to it.
+    pub fn from_its_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)?;
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+        Self::from_its(&source, base_dir)
+    }
+
+    /// Parse ITS source text, resolving `/incbin/` references against `base_dir`.
+    ///
+    /// Components marked `compression = "gzip"` whose payload is already a gzip
+    /// stream are decompressed, so that building the result reproduces an
+    /// equivalent image instead of compressing twice.
+    pub fn from_its(source: &str, base_dir: impl AsRef<Path>) -> Result<Self> {
+        let base_dir = base_dir.as_ref();
+        let root = parse_its(source)?;
+
+        let mut config = FitImageConfig::new(root.string("description").unwrap_or_default());
+        config.creator = root.string("creator");
+        if let Some(ts) = root.property("timestamp").and_then(ItsValue::as_u64) {
+            let ts = u32::try_from(ts).map_err(|_| {
+                MkImageError::config_parse(format!("timestamp {ts:#x} does not fit in 32 bits"))
+            })?;
+            config.timestamp = Timestamp::Fixed(ts);
+        }
+
+        let images = root
+            .child("images")
+            .ok_or_else(|| MkImageError::config_parse("missing /images node"))?;
+        // Images a configuration starts as `firmware`; other SPL images are loadables
+        let firmware_refs: Vec<String> = root
+            .child("configurations")
+            .map(|c| {
+                c.children
+                    .iter()
+                    .filter_map(|n| n.string("firmware"))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        for node in &images.children {
+            let component = component_from_node(node, base_dir)?;
+            let ty = node.string("type").unwrap_or_default();
+            if matches!(ty.as_str(), "firmware" | "standalone" | "tee") {
+                if config.firmware.is_none() && firmware_refs.contains(&node.name) {
+                    config.firmware = Some(component);
+                } else {
+                    config.loadables.push(component);
+                }
+                continue;
+            }
+            // The first `flat_dt` image is the base tree, later ones are overlays
+            if ty == "flat_dt" && config.fdt.is_some() {
+                config.overlays.push(component);
+                continue;
+            }
+            let slot = match ty.as_str() {
+                "kernel" | "kernel_noload" => &mut config.kernel,
+                "flat_dt" => &mut config.fdt,
+                "ramdisk" => &mut config.ramdisk,
+                "script" => &mut config.script,
+                other => return Err(MkImageError::unsupported_image_type(other)),
+            };
+            if slot.is_some() {
+                return Err(MkImageError::config_parse(format!(
+                    "multiple `{ty}` images are not supported (node `{}`)",
+                    node.name
+                )));
+            }
+            *slot = Some(component);
+        }
+
+        if let Some(configurations) = root.child("configurations") {
+            config.default_config = configurations.string("default");
+            for node in &configurations.children {
+                config.configurations.insert(
+                    node.name.clone(),
+                    FitConfiguration {
+                        name: node.name.clone(),
+                        description: node.string("description").unwrap_or_default(),
+                        kernel: node.string("kernel"),
+                        fdt: node.string("fdt"),
+                        overlays: match node.property("fdt") {
+                            Some(ItsValue::Strings(list)) => list.iter().skip(1).cloned().collect(),
+                            _ => Vec::new(),
+                        },
+                        ramdisk: node.string("ramdisk"),
+                        script: node.string("script"),
+                        firmware: node.string("firmware"),
+                        loadables: match node.property("loadables") {
+                            Some(ItsValue::Strings(list)) => list.clone(),
+                            _ => Vec::new(),
+                        },
+                    },
+                );
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Render this configuration as ITS source with image data inlined as byte arrays.
+    pub fn to_its(&self) -> Result<String> {
+        self.render_its(|_, data| {
+            let mut s = String::from("[");
+            for (i, b) in data.iter().enumerate() {
+                if i > 0 {
+                    s.push(' ');
+                }
+                let _ = write!(s, "{b:02x}");
+            }
+            s.push(']');
+            Ok(s)
+        })
+    }
+
+    /// Write this configuration as an `.its` file plus one data file per component.
+    ///
+    /// Data files are placed next to `its_path` and referenced via `/incbin/`,
+    /// matching what a hand-written mkimage source looks like.
+    pub fn write_its(&self, its_path: impl AsRef<Path>) -> Result<()> {
+        let its_path = its_path.as_ref();
+        let dir = its_path.parent().unwrap_or(Path::new("."));
+        let source = self.render_its(|component, data| {
+            let ext = if component.compression {
+                "bin.gz"
+            } else {
+                "bin"
+            };
+            let file_name = format!("{}.{ext}", component.name);
+            std::fs::write(dir.join(&file_name), data)?;
+            Ok(format!("/incbin/(\"./{file_name}\")"))
+        })?;
+        std::fs::write(its_path, source)?;
+        Ok(())
+    }
+
+    fn render_its(
+        &self,
+        mut data_ref: impl FnMut(&ComponentConfig, &[u8]) -> Result<String>,
+    ) -> Result<String> {
+        let mut out = String::new();
+        out.push_str("/dts-v1/;\n\n/ {\n");
+        let _ = writeln!(out, "\tdescription = {};", quote(&self.description));
+        if let Some(creator) = &self.creator {
+            let _ = writeln!(out, "\tcreator = {};", quote(creator));
+        }
+        if let Timestamp::Fixed(ts) = self.timestamp {
+            let _ = writeln!(out, "\ttimestamp = <{ts:#x}>;");
+        }
+        out.push_str("\t#address-cells = <2>;\n\n\timages {\n");
+
+        let components = [
+            (self.kernel.as_ref(), ComponentDefaults::KERNEL),
+            (self.fdt.as_ref(), ComponentDefaults::FDT),
+        ]
+        .into_iter()
+        .chain(
+            self.overlays
+                .iter()
+                .map(|o| (Some(o), ComponentDefaults::FDT)),
+        )
+        .chain([
+            (self.ramdisk.as_ref(), ComponentDefaults::RAMDISK),
+            (self.script.as_ref(), ComponentDefaults::SCRIPT),
+            (self.firmware.as_ref(), ComponentDefaults::FIRMWARE),
+        ])
+        .chain(
+            self.loadables
+                .iter()
+                .map(|l| (Some(l), ComponentDefaults::LOADABLE)),
+        );
+        let mut first = true;
+        for (component, defaults) in components {
+            let Some(component) = component else {
+                continue;
+            };
+            if !first {
+                out.push('\n');
+            }
+            first = false;
+
+            let data = if component.compression {
+                GzipCompressor::default().compress(&component.data)?
+            } else {
+                component.data.clone()
+            };
+
+            let _ = writeln!(out, "\t\t{} {{", component.name);
+            let prop = |out: &mut String, name: &str, value: &str| {
+                let _ = writeln!(out, "\t\t\t{name} = {value};");
+            };
+            prop(
+                &mut out,
+                "description",
+                &quote(
+                    component
+                        .description
+                        .as_deref()
+                        .unwrap_or(defaults.description),
+                ),
+            );
+            prop(&mut out, "data", &data_ref(component, &data)?);
+            prop(
+                &mut out,
+                "type",
+                &quote(
+                    component
+                        .component_type
+                        .as_ref()
+                        .map_or(defaults.ty, |t| t.as_str()),
+                ),
+            );
+            if let Some(arch) = component
+                .arch
+                .as_ref()
+                .map(|a| a.as_str())
+                .or(defaults.arch)
+            {
+                prop(&mut out, "arch", &quote(arch));
+            }
+            if defaults.has_os {
+                if let Some(os) = component.os.as_ref().map(|o| o.as_str()).or(defaults.os) {
+                    prop(&mut out, "os", &quote(os));
+                }
+            }
+            prop(
+                &mut out,
+                "compression",
+                &quote(if component.compression {
+                    "gzip"
+                } else {
+                    "none"
+                }),
+            );
+            if let Some(load) = component.load_address {
+                prop(&mut out, "load", &cells(load));
+            }
+            if let Some(entry) = component.entry_point {
+                prop(&mut out, "entry", &cells(entry));
+            }
+            for (i, algo) in component.hashes.iter().enumerate() {
+                let _ = writeln!(out, "\t\t\thash-{} {{", i + 1);
+                let _ = writeln!(out, "\t\t\t\talgo = {};", quote(algo.as_str()));
+                out.push_str("\t\t\t};\n");
+            }
+            out.push_str("\t\t};\n");
+        }
+        out.push_str("\t};\n\n\tconfigurations {\n");
+
+        if self.configurations.is_empty() {
+            out.push_str("\t\tdefault = \"config-1\";\n\n\t\tconfig-1 {\n");
+            out.push_str("\t\t\tdescription = \"Default configuration\";\n");
+            let fdts = self.fdt.iter().chain(&self.overlays);
+            for (key, names) in [
+                (
+                    "firmware",
+                    quote_list(self.firmware.iter().map(|c| &c.name)),
+                ),
+                ("kernel", quote_list(self.kernel.iter().map(|c| &c.name))),
+                ("fdt", quote_list(fdts.map(|c| &c.name))),
+                ("ramdisk", quote_list(self.ramdisk.iter().map(|c| &c.name))),
+                ("script", quote_list(self.script.iter().map(|c| &c.name))),
+                (
+                    "loadables",
+                    quote_list(self.loadables.iter().map(|c| &c.name)),
+                ),
+            ] {
+                if let Some(names) = names {
+                    let _ = writeln!(out, "\t\t\t{key} = {names};");
+                }
+            }
+            out.push_str("\t\t};\n");
+        } else {
+            if let Some(default) = &self.default_config {
+                let _ = writeln!(out, "\t\tdefault = {};", quote(default));
+            }
+            let mut names: Vec<_> = self.configurations.keys().collect();
+            names.sort();
+            for name in names {
+                let conf = &self.configurations[name];
+                let _ = writeln!(out, "\n\t\t{name} {{");
+                let _ = writeln!(out, "\t\t\tdescription = {};", quote(&conf.description));
+                for (key, value) in [
+                    ("firmware", quote_list(&conf.firmware)),
+                    ("kernel", quote_list(&conf.kernel)),
+                    ("fdt", quote_list(conf.fdt.iter().chain(&conf.overlays))),
+                    ("ramdisk", quote_list(&conf.ramdisk)),
+                    ("script", quote_list(&conf.script)),
+                    ("loadables", quote_list(&conf.loadables)),
+                ] {
+                    if let Some(value) = value {
+                        let _ = writeln!(out, "\t\t\t{key} = {value};");
+                    }
+                }
+                out.push_str("\t\t};\n");
+            }
+        }
+        out.push_str("\t};\n};\n");
+        Ok(out)
+    }
+}
+
+/// Property defaults applied by [`crate::fit::StandardFdtBuilder`] per component kind.
+struct ComponentDefaults {
+    description: &'static str,
+    ty: &'static str,
+    arch: Option<&'static str>,
+    has_os: bool,
+    /// `os` written when the component sets none
+    os: Option<&'static str>,
+}
+
+impl ComponentDefaults {
+    const KERNEL: Self = Self {
+        description: "Linux Kernel",
+        ty: "kernel",
+        arch: Some("arm64"),
+        has_os: true,
+        os: Some("linux"),
+    };
+    const FDT: Self = Self {
+        description: "Device Tree Blob",
+        ty: "flat_dt",
+        arch: Some("arm64"),
+        has_os: false,
+        os: None,
+    };
+    const RAMDISK: Self = Self {
+        description: "Ramdisk Image",
+        ty: "ramdisk",
+        arch: Some("arm64"),
+        has_os: true,
+        os: Some("linux"),
+    };
+    const SCRIPT: Self = Self {
+        description: "Boot Script",
+        ty: "script",
+        arch: None,
+        has_os: false,
+        os: None,
+    };
+    const FIRMWARE: Self = Self {
+        description: "Firmware",
+        ty: "firmware",
+        arch: Some("arm64"),
+        has_os: true,
+        os: Some("u-boot"),
+    };
+    const LOADABLE: Self = Self {
+        description: "Firmware",
+        ty: "firmware",
+        arch: Some("arm64"),
+        has_os: true,
+        os: None,
+    };
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Quote a string list as `"a", "b"`, or `None` when it is empty.
+fn quote_list<'a>(values: impl IntoIterator<Item = &'a String>) -> Option<String> {
+    let quoted: Vec<_> = values.into_iter().map(|v| quote(v)).collect();
+    (!quoted.is_empty()).then(|| quoted.join(", "))
+}
+
+/// Format an address as two cells, matching `#address-cells = <2>`.
+fn cells(value: u64) -> String {
+    format!("<{:#x} {:#x}>", value >> 32, value as u32)
+}
+
+fn component_from_node(node: &ItsNode, base_dir: &Path) -> Result<ComponentConfig> {
+    let data = match node.property("data") {
+        Some(ItsValue::Incbin(path)) => {
+            let path = base_dir.join(path);
+            std::fs::read(&path).map_err(|e| {
+                MkImageError::config_parse(format!("cannot read {}: {e}", path.display()))
+            })?
+        }
+        Some(ItsValue::Bytes(bytes)) => bytes.clone(),
+        Some(ItsValue::Strings(s)) => s.join("\0").into_bytes(),
+        _ => {
+            return Err(MkImageError::config_parse(format!(
+                "image `{}` has no data",
+                node.name
+            )))
+        }
+    };
+
+    let mut component = ComponentConfig::new(node.name.clone(), data);
+    component.description = node.string("description");
+    component.component_type = node.string("type").map(|s| s.parse()).transpose()?;
+    component.arch = node.string("arch").map(|s| s.parse()).transpose()?;
+    component.os = node.string("os").map(|s| s.parse()).transpose()?;
+    component.load_address = node.property("load").and_then(ItsValue::as_u64);
+    component.entry_point = node.property("entry").and_then(ItsValue::as_u64);
+
+    for hash in node.children.iter().filter(|c| c.name.starts_with("hash")) {
+        let algo = hash.string("algo").unwrap_or_default();
+        component
+            .hashes
+            .push(HashAlgorithm::from_name(&algo).ok_or_else(|| {
+                MkImageError::config_parse(format!("unsupported hash algorithm `{algo}`"))
+            })?);
+    }
+
+    match node.string("compression").as_deref() {
+        None | Some("none") => {}
+        Some("gzip") => {
+            // The builder compresses on its own, so keep the raw payload here.
+            if component.data.starts_with(&GZIP_MAGIC) {
+                component.data = GzipCompressor::default().decompress(&component.data)?;
+            }
+            component.compression = true;
+        }
+        Some(other) => return Err(MkImageError::unsupported_compression(other)),
+    }
+
+    Ok(component)
+}
+
+struct ItsParser<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl ItsParser<'_> {
+    fn parse_document(&mut self) -> Result<ItsNode> {
+        let mut root = None;
+        loop {
+            self.skip_ws();
+            if self.pos >= self.src.len() {
+                break;
+            }
+            if self.eat("/dts-v1/") || self.eat("/plugin/") {
+                self.expect(b';')?;
+                continue;
+            }
+            if self.eat("/") {
+                let mut node = self.parse_node_body()?;
+                node.name = String::new();
+                root = Some(node);
+                continue;
+            }
+            return Err(self.error("unexpected top-level content"));
+        }
+        root.ok_or_else(|| MkImageError::config_parse("ITS source has no root node"))
+    }
+
+    /// Parse `{ ... };` after a node name.
+    fn parse_node_body(&mut self) -> Result<ItsNode> {
+        self.skip_ws();
+        self.expect(b'{')?;
+        let mut node = ItsNode::default();
+        loop {
+            self.skip_ws();
+            if self.eat("}") {
+                self.skip_ws();
+                self.expect(b';')?;
+                return Ok(node);
+            }
+            let name = self.ident()?;
+            self.skip_ws();
+            match self.peek() {
+                Some(b'{') => {
+                    let mut child = self.parse_node_body()?;
+                    child.name = name;
+                    node.children.push(child);
+                }
+                Some(b'=') => {
+                    self.pos += 1;
+                    let value = self.parse_value()?;
+                    node.properties.push((name, value));
+                }
+                Some(b';') => {
+                    self.pos += 1;
+                    node.properties.push((name, ItsValue::Empty));
+                }
+                _ => return Err(self.error("expected `{`, `=` or `;`")),
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<ItsValue> {
+        let mut strings = Vec::new();
+        let mut value = None;
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                Some(b'"') => strings.push(self.string()?),
+                Some(b'<') => {
+                    self.pos += 1;
+                    let mut cells = Vec::new();
+                    loop {
+                        self.skip_ws();
+                        if self.eat(">") {
+                            break;
+                        }
+                        let word = self.ident()?;
+                        cells.push(
+                            parse_number(&word)
+                                .and_then(|n| u32::try_from(n).ok())
+                                .ok_or_else(|| {
+                                    self.error(&format!("invalid cell value `{word}`"))
+                                })?,
+                        );
+                    }
+                    value = Some(ItsValue::Cells(cells));
+                }
+                Some(b'[') => {
+                    self.pos += 1;
+                    let mut hex = String::new();
+                    loop {
+                        match self.peek() {
+                            Some(b']') => break,
+                            Some(c) if c.is_ascii_hexdigit() => hex.push(c as char),
+                            Some(c) if c.is_ascii_whitespace() => {}
+                            _ => return Err(self.error("invalid byte string")),
+                        }
+                        self.pos += 1;
+                    }
+                    self.pos += 1;
+                    let bytes = hex::decode(&hex).map_err(|e| self.error(&e.to_string()))?;
+                    value = Some(ItsValue::Bytes(bytes));
+                }
+                _ if self.eat("/incbin/") => {
+                    self.skip_ws();
+                    self.expect(b'(')?;
+                    self.skip_ws();
+                    let path = self.string()?;
+                    self.skip_ws();
+                    if self.eat(",") {
+                        return Err(self.error("/incbin/ with offset/length is not supported"));
+                    }
+                    self.expect(b')')?;
+                    value = Some(ItsValue::Incbin(PathBuf::from(path)));
+                }
+                _ => return Err(self.error("invalid property value")),
+            }
+            self.skip_ws();
+            if self.eat(";") {
+                break;
+            }
+            self.expect(b',')?;
+        }
+        if !strings.is_empty() {
+            if value.is_some() {
+                return Err(self.error("mixed property value types are not supported"));
+            }
+            return Ok(ItsValue::Strings(strings));
+        }
+        Ok(value.unwrap_or(ItsValue::Empty))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.expect(b'"')?;
+        let mut s = Vec::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some(b'"') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let c = self
+                        .peek()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    s.push(match c {
+                        b'n' => b'\n',
+                        b't' => b'\t',
+                        b'0' => 0,
+                        c => c,
+                    });
+                    self.pos += 1;
+                }
+                Some(c) => {
+                    s.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        String::from_utf8(s).map_err(|e| self.error(&e.to_string()))
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || b",._+-#@?".contains(&c) {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        if start == self.pos {
+            return Err(self.error("expected identifier"));
+        }
+        Ok(String::from_utf8_lossy(&self.src[start..self.pos]).into_owned())
+    }
+
+    fn skip_ws(&mut self) {
+        loop {
+            while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+                self.pos += 1;
+            }
+            if self.src[self.pos..].starts_with(b"/*") {
+                match find(&self.src[self.pos + 2..], b"*/") {
+                    Some(end) => self.pos += end + 4,
+                    None => self.pos = self.src.len(),
+                }
+            } else if self.src[self.pos..].starts_with(b"//") {
+                while self.peek().is_some_and(|c| c != b'\n') {
+                    self.pos += 1;
+                }
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.src[self.pos..].starts_with(token.as_bytes()) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<()> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", c as char)))
+        }
+    }
+
+    fn error(&self, msg: &str) -> MkImageError {
+        let line = self.src[..self.pos.min(self.src.len())]
+            .iter()
+            .filter(|&&c| c == b'\n')
+            .count()
+            + 1;
+        MkImageError::config_parse(format!("ITS line {line}: {msg}"))
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn parse_number(s: &str) -> Option<u64> {
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()
+    } else {
+        s.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_basic_tree() {
+        let src = r#"
+            /dts-v1/;
+            // comment
+            / {
+                description = "demo";
+                #address-cells = <1>;
+                images {
+                    k { data = [01 02 0a]; load = <0x1 0x80000>; flag; };
+                };
+            };
+        "#;
+        let root = parse_its(src).unwrap();
+        assert_eq!(root.string("description").as_deref(), Some("demo"));
+        let k = root.child("images").unwrap().child("k").unwrap();
+        assert_eq!(k.property("data"), Some(&ItsValue::Bytes(vec![1, 2, 10])));
+        assert_eq!(
+            k.property("load").and_then(ItsValue::as_u64),
+            Some(0x1_0008_0000)
+        );
+        assert_eq!(k.property("flag"), Some(&ItsValue::Empty));
+    }
+
+    #[test]
+    fn test_parse_error_reports_line() {
+        let err = parse_its("/dts-v1/;\n/ {\n  a = ;\n};").unwrap_err();
+        assert!(err.to_string().contains("line 3"), "{err}");
+    }
+
+    #[test]
+    fn test_oversized_values_rejected() {
+        let err = parse_its("/dts-v1/;\n/ {\n  a = <0x100000000>;\n};").unwrap_err();
+        assert!(err.to_string().contains("invalid cell value"), "{err}");
+
+        let its = "/dts-v1/;\n/ {\n  timestamp = <0x1 0x0>;\n  images { };\n};";
+        let err = FitImageConfig::from_its(its, ".").unwrap_err();
+        assert!(err.to_string().contains("timestamp"), "{err}");
+    }
+
+    #[test]
+    fn test_its_roundtrip() {
+        let config = FitImageConfig::new("Roundtrip")
+            .with_timestamp(Timestamp::Fixed(0x6500_0000))
+            .with_creator("ostool")
+            .with_kernel(
+                ComponentConfig::new("kernel", vec![1, 2, 3, 4])
+                    .with_compression(true)
+                    .with_load_address(0x8008_0000)
+                    .with_entry_point(0x8008_0000)
+                    .with_hash(HashAlgorithm::Crc32),
+            )
+            .with_fdt(ComponentConfig::new("fdt", vec![5, 6]).with_load_address(0x1_0000_0000))
+            .with_default_config("conf")
+            .with_configuration("conf", "test", Some("kernel"), Some("fdt"), None::<String>);
+
+        let its = config.to_its().unwrap();
+        assert!(its.contains("#address-cells = <2>;"));
+        assert!(its.contains("load = <0x0 0x80080000>;"));
+        assert!(its.contains("load = <0x1 0x0>;"));
+        let parsed = FitImageConfig::from_its(&its, ".").unwrap();
+
+        assert_eq!(parsed.description, "Roundtrip");
+        assert_eq!(parsed.timestamp, Timestamp::Fixed(0x6500_0000));
+        assert_eq!(parsed.creator.as_deref(), Some("ostool"));
+        let kernel = parsed.kernel.unwrap();
+        assert_eq!(kernel.data, vec![1, 2, 3, 4]);
+        assert!(kernel.compression);
+        assert_eq!(kernel.entry_point, Some(0x8008_0000));
+        assert_eq!(kernel.hashes, vec![HashAlgorithm::Crc32]);
+        assert_eq!(parsed.fdt.unwrap().load_address, Some(0x1_0000_0000));
+        assert_eq!(parsed.default_config.as_deref(), Some("conf"));
+        assert_eq!(
+            parsed.configurations["conf"].kernel.as_deref(),
+            Some("kernel")
+        );
+    }
+
+    #[test]
+    fn test_its_overlays_roundtrip() {
+        let config = FitImageConfig::new("Overlays")
+            .with_fdt(ComponentConfig::new("fdt-base", vec![1]))
+            .with_overlay(ComponentConfig::new("overlay-a", vec![2]))
+            .with_overlay(ComponentConfig::new("overlay-b", vec![3]))
+            .with_configuration(
+                "conf",
+                "variant",
+                None::<String>,
+                Some("fdt-base"),
+                None::<String>,
+            )
+            .with_configuration_overlays("conf", ["overlay-b", "overlay-a"]);
+
+        let its = config.to_its().unwrap();
+        assert!(its.contains("fdt = \"fdt-base\", \"overlay-b\", \"overlay-a\";"));
+
+        let parsed = FitImageConfig::from_its(&its, ".").unwrap();
+        assert_eq!(parsed.fdt.unwrap().name, "fdt-base");
+        let overlays: Vec<_> = parsed.overlays.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(overlays, ["overlay-a", "overlay-b"]);
+        let conf = &parsed.configurations["conf"];
+        assert_eq!(conf.fdt.as_deref(), Some("fdt-base"));
+        assert_eq!(conf.overlays, ["overlay-b", "overlay-a"]);
+    }
+
+    #[test]
+    fn test_its_firmware_roundtrip() {
+        let config = FitImageConfig::new("U-Boot FIT")
+            .with_firmware(
+                ComponentConfig::new("atf", vec![1])
+                    .with_os(crate::ImageOs::ArmTrustedFirmware)
+                    .with_load_address(0x4_0000)
+                    .with_entry_point(0x4_0000),
+            )
+            .with_loadable(
+                ComponentConfig::new("uboot", vec![2])
+                    .with_type(crate::ImageType::Standalone)
+                    .with_os(crate::ImageOs::UBoot)
+                    .with_load_address(0x20_0000),
+            )
+            .with_loadable(
+                ComponentConfig::new("optee", vec![3])
+                    .with_type(crate::ImageType::Tee)
+                    .with_load_address(0x840_0000),
+            )
+            .with_fdt(ComponentConfig::new("fdt", vec![4]));
+
+        let its = config.to_its().unwrap();
+        assert!(its.contains("firmware = \"atf\";"));
+        assert!(its.contains("loadables = \"uboot\", \"optee\";"));
+        assert!(its.contains("os = \"arm-trusted-firmware\";"));
+
+        let parsed = FitImageConfig::from_its(&its, ".").unwrap();
+        let firmware = parsed.firmware.unwrap();
+        assert_eq!(firmware.name, "atf");
+        assert_eq!(firmware.entry_point, Some(0x4_0000));
+        let loadables: Vec<_> = parsed.loadables.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(loadables, ["uboot", "optee"]);
+        let conf = &parsed.configurations["config-1"];
+        assert_eq!(conf.firmware.as_deref(), Some("atf"));
+        assert_eq!(conf.loadables, ["uboot", "optee"]);
+    }
+
+    #[test]
+    fn test_write_its_with_incbin() {
+        let dir = tempfile::tempdir().unwrap();
+        let its_path = dir.path().join("image.its");
+        let config = FitImageConfig::new("Files")
+            .with_kernel(ComponentConfig::new("kernel", b"kernel-data".to_vec()));
+        config.write_its(&its_path).unwrap();
+
+        let source = std::fs::read_to_string(&its_path).unwrap();
+        assert!(source.contains("/incbin/(\"./kernel.bin\")"));
+
+        let parsed = FitImageConfig::from_its_file(&its_path).unwrap();
+        assert_eq!(parsed.kernel.unwrap().data, b"kernel-data");
+    }
+}
@@ -4,15 +4,29 @@
 
 pub mod builder;
 pub mod config;
+pub mod diff;
 pub mod fdt_header;
 pub mod fdt_tokens;
+pub mod fdt_writer;
+pub mod its;
+pub mod property;
+pub mod reader;
+mod regions;
 pub mod standard_dt_builder;
 pub mod string_table;
 
 // 重新导出主要类型
 pub use builder::FitImageBuilder;
-pub use config::{ComponentConfig, FitImageConfig};
+pub use config::{ComponentConfig, FitImageConfig, Timestamp};
+pub use diff::{FitChange, FitDiff};
 pub use fdt_header::{FdtHeader, MemReserveEntry, FDT_LAST_COMP_VERSION, FDT_MAGIC, FDT_VERSION};
 pub use fdt_tokens::{FdtToken, FdtTokenUtils, FDT_STRUCT_ALIGN};
+pub use fdt_writer::FdtWriter;
+pub use its::{parse_its, ItsNode, ItsValue};
+pub use property::{FitArch, FitOs, FitProperty, FitType};
+pub use reader::{
+    FdtNode, FdtProperty, FitImageReader, HashCheck, HashStatus, SignatureCheck, SignatureStatus,
+    VerifyReport,
+};
 pub use standard_dt_builder::StandardFdtBuilder;
 pub use string_table::StringTable;
@@ -0,0 +1,215 @@
+//! Typed FIT image properties
+//!
+//! The `arch`, `os` and `type` properties of image nodes accept the names
+//! U-Boot recognizes through [`ImageArch`], [`ImageOs`] and [`ImageType`].
+//! Anything else has to be spelled out as [`FitProperty::Custom`], so a typo
+//! is rejected while building or deserializing the configuration instead of
+//! by `bootm` on the board.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::{MkImageError, Result};
+use crate::uimage::{ImageArch, ImageOs, ImageType};
+
+/// A set of property names known to U-Boot.
+pub trait KnownName: Copy + Sized {
+    /// Property name, used in error messages.
+    const PROPERTY: &'static str;
+
+    /// Name written to the FIT property.
+    fn name(self) -> &'static str;
+
+    /// Look up a known name.
+    fn parse_name(name: &str) -> Option<Self>;
+
+    /// Error returned for names outside the known set.
+    fn unknown(name: &str) -> MkImageError;
+}
+
+impl KnownName for ImageArch {
+    const PROPERTY: &'static str = "arch";
+
+    fn name(self) -> &'static str {
+        self.as_str()
+    }
+
+    fn parse_name(name: &str) -> Option<Self> {
+        Self::from_name(name)
+    }
+
+    fn unknown(name: &str) -> MkImageError {
+        MkImageError::unsupported_arch(name)
+    }
+}
+
+impl KnownName for ImageOs {
+    const PROPERTY: &'static str = "os";
+
+    fn name(self) -> &'static str {
+        self.as_str()
+    }
+
+    fn parse_name(name: &str) -> Option<Self> {
+        Self::from_name(name)
+    }
+
+    fn unknown(name: &str) -> MkImageError {
+        MkImageError::unsupported_os(name)
+    }
+}
+
+impl KnownName for ImageType {
+    const PROPERTY: &'static str = "type";
+
+    fn name(self) -> &'static str {
+        self.as_str()
+    }
+
+    fn parse_name(name: &str) -> Option<Self> {
+        Self::from_name(name)
+    }
+
+    fn unknown(name: &str) -> MkImageError {
+        MkImageError::unsupported_image_type(name)
+    }
+}
+
+/// A FIT property value: a name U-Boot knows, or an explicit custom string.
+///
+/// Serialized as the plain name for known values and as
+/// `{ custom = "<name>" }` for custom ones.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FitProperty<T> {
+    /// A value from the known set.
+    Known(T),
+    /// Escape hatch for values this crate does not know about.
+    Custom(String),
+}
+
+/// `arch` property of an image node.
+pub type FitArch = FitProperty<ImageArch>;
+
+/// `os` property of an image node.
+pub type FitOs = FitProperty<ImageOs>;
+
+/// `type` property of an image node.
+pub type FitType = FitProperty<ImageType>;
+
+impl<T: KnownName> FitProperty<T> {
+    /// Create a custom value that bypasses validation.
+    pub fn custom(name: impl Into<String>) -> Self {
+        Self::Custom(name.into())
+    }
+
+    /// Name written to the FIT property.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Known(v) => v.name(),
+            Self::Custom(s) => s,
+        }
+    }
+
+    /// The known value, if this is not a custom one.
+    pub fn known(&self) -> Option<T> {
+        match self {
+            Self::Known(v) => Some(*v),
+            Self::Custom(_) => None,
+        }
+    }
+}
+
+impl<T> From<T> for FitProperty<T> {
+    fn from(value: T) -> Self {
+        Self::Known(value)
+    }
+}
+
+impl<T: KnownName> FromStr for FitProperty<T> {
+    type Err = MkImageError;
+
+    /// Parse a known name; unknown names are an error.
+    fn from_str(s: &str) -> Result<Self> {
+        T::parse_name(s)
+            .map(Self::Known)
+            .ok_or_else(|| T::unknown(s))
+    }
+}
+
+impl<T: KnownName> fmt::Display for FitProperty<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Repr<'a> {
+    Name(std::borrow::Cow<'a, str>),
+    Custom { custom: String },
+}
+
+impl<T: KnownName> Serialize for FitProperty<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Self::Known(v) => Repr::Name(v.name().into()),
+            Self::Custom(s) => Repr::Custom { custom: s.clone() },
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T: KnownName> Deserialize<'de> for FitProperty<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        match Repr::deserialize(deserializer)? {
+            Repr::Name(name) => name
+                .parse()
+                .map_err(|_| serde::de::Error::custom(format!("unknown {} `{name}`", T::PROPERTY))),
+            Repr::Custom { custom } => Ok(Self::Custom(custom)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_known_and_reject_typos() {
+        assert_eq!("arm64".parse::<FitArch>().unwrap(), ImageArch::Arm64.into());
+        assert_eq!(
+            "u-boot".parse::<FitOs>().unwrap().known(),
+            Some(ImageOs::UBoot)
+        );
+        assert!(matches!(
+            "arm46".parse::<FitArch>(),
+            Err(MkImageError::UnsupportedArch(_))
+        ));
+        assert!(matches!(
+            "kernal".parse::<FitType>(),
+            Err(MkImageError::UnsupportedImageType(_))
+        ));
+    }
+
+    #[test]
+    fn test_serde_representation() {
+        #[derive(Serialize, Deserialize)]
+        struct Node {
+            arch: FitArch,
+            os: FitOs,
+        }
+
+        let json = r#"{"arch":"riscv","os":{"custom":"zephyr"}}"#;
+        let node: Node = serde_json::from_str(json).unwrap();
+        assert_eq!(node.arch, FitArch::from(ImageArch::Riscv));
+        assert_eq!(node.os, FitOs::custom("zephyr"));
+        assert_eq!(serde_json::to_string(&node).unwrap(), json);
+
+        let err = serde_json::from_str::<Node>(r#"{"arch":"risc-v","os":"linux"}"#)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("unknown arch `risc-v`"), "{err}");
+    }
+}
@@ -0,0 +1,772 @@
+//! FIT image reader
+//!
+//! Parses an existing FIT blob back into a node tree so images can be
+//! inspected, verified against their hash nodes and extracted.
+
+use std::path::Path;
+
+use crate::error::{MkImageError, Result};
+use crate::fit::{regions, FdtHeader, FdtToken, MemReserveEntry, FDT_MAGIC};
+use crate::hash::HashAlgorithm;
+use crate::signature::{SignatureAlgorithm, VerifyingKey};
+
+/// A property of a parsed FDT node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FdtProperty {
+    /// Property name.
+    pub name: String,
+    /// Raw big-endian property value.
+    pub value: Vec<u8>,
+}
+
+/// A node of a parsed FDT structure block.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FdtNode {
+    /// Node name (empty for the root node).
+    pub name: String,
+    /// Properties in blob order.
+    pub properties: Vec<FdtProperty>,
+    /// Child nodes in blob order.
+    pub children: Vec<FdtNode>,
+}
+
+impl FdtNode {
+    /// Find a property by name.
+    pub fn property(&self, name: &str) -> Option<&[u8]> {
+        self.properties
+            .iter()
+            .find(|p| p.name == name)
+            .map(|p| p.value.as_slice())
+    }
+
+    /// Read a NUL-terminated string property.
+    pub fn property_str(&self, name: &str) -> Option<&str> {
+        let value = self.property(name)?;
+        let end = value.iter().position(|&b| b == 0).unwrap_or(value.len());
+        std::str::from_utf8(&value[..end]).ok()
+    }
+
+    /// Read a string list property (e.g. a configuration's `fdt` with overlays).
+    pub fn property_strings(&self, name: &str) -> Vec<&str> {
+        self.property(name)
+            .map(|value| {
+                value
+                    .split(|&b| b == 0)
+                    .filter(|s| !s.is_empty())
+                    .filter_map(|s| std::str::from_utf8(s).ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Read a 32-bit or 64-bit integer property.
+    pub fn property_u64(&self, name: &str) -> Option<u64> {
+        let value = self.property(name)?;
+        match value.len() {
+            4 => Some(u32::from_be_bytes(value.try_into().ok()?) as u64),
+            8 => Some(u64::from_be_bytes(value.try_into().ok()?)),
+            _ => None,
+        }
+    }
+
+    /// Find a direct child node by name.
+    pub fn child(&self, name: &str) -> Option<&FdtNode> {
+        self.children.iter().find(|c| c.name == name)
+    }
+}
+
+/// Outcome of checking one hash node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HashStatus {
+    /// Stored value matches the recomputed one.
+    Ok,
+    /// Stored value differs from the recomputed one.
+    Mismatch {
+        /// Value stored in the image (hex).
+        expected: String,
+        /// Value computed from the image data (hex).
+        calculated: String,
+    },
+    /// The algorithm is not supported by this crate.
+    Unsupported(String),
+    /// The hash node has no `value` property.
+    MissingValue,
+}
+
+/// Result of checking one `hash-N` node.
+#[derive(Debug, Clone)]
+pub struct HashCheck {
+    /// Image node name.
+    pub image: String,
+    /// Hash node name.
+    pub node: String,
+    /// Algorithm name from the `algo` property.
+    pub algo: String,
+    /// Check outcome.
+    pub status: HashStatus,
+}
+
+/// Report produced by [`FitImageReader::verify`].
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    /// One entry per hash node found.
+    pub checks: Vec<HashCheck>,
+    /// Images that carry no hash node at all.
+    pub unhashed: Vec<String>,
+    /// Structural problems (dangling configuration references, missing data).
+    pub errors: Vec<String>,
+}
+
+impl VerifyReport {
+    /// Whether every hash matched and no structural error was found.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty() && self.checks.iter().all(|c| c.status == HashStatus::Ok)
+    }
+
+    /// Checks that did not pass.
+    pub fn failures(&self) -> impl Iterator<Item = &HashCheck> {
+        self.checks.iter().filter(|c| c.status != HashStatus::Ok)
+    }
+}
+
+/// Outcome of checking one signature node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureStatus {
+    /// Signature verified with the named key.
+    Ok(String),
+    /// No candidate key accepted the signature.
+    Invalid,
+    /// No key matches the node's algorithm and `key-name-hint`.
+    NoKey,
+    /// The algorithm is not supported by this crate.
+    Unsupported(String),
+    /// The signature node has no `value` property.
+    MissingValue,
+}
+
+/// Result of checking one `signature-N` node of an image or configuration.
+#[derive(Debug, Clone)]
+pub struct SignatureCheck {
+    /// Image or configuration node name.
+    pub image: String,
+    /// Whether the signature belongs to a configuration.
+    pub configuration: bool,
+    /// Signature node name.
+    pub node: String,
+    /// Algorithm name from the `algo` property.
+    pub algo: String,
+    /// Key name from the `key-name-hint` property.
+    pub key_name_hint: Option<String>,
+    /// Check outcome.
+    pub status: SignatureStatus,
+}
+
+/// Reader for FIT images produced by mkimage or [`crate::FitImageBuilder`].
+#[derive(Debug, Clone)]
+pub struct FitImageReader {
+    blob: Vec<u8>,
+    header: FdtHeader,
+    root: FdtNode,
+}
+
+impl FitImageReader {
+    /// Parse a FIT image from memory.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let header = read_header(data)?;
+        header.validate()?;
+        if header.totalsize as usize > data.len() {
+            return Err(MkImageError::invalid_image_data(format!(
+                "truncated FIT image: header says {} bytes, got {}",
+                header.totalsize,
+                data.len()
+            )));
+        }
+
+        let struct_block = slice(data, header.off_dt_struct, header.size_dt_struct)?;
+        let strings = slice(data, header.off_dt_strings, header.size_dt_strings)?;
+        let root = StructParser {
+            data: struct_block,
+            strings,
+            pos: 0,
+        }
+        .parse()?;
+
+        Ok(Self {
+            blob: data.to_vec(),
+            header,
+            root,
+        })
+    }
+
+    /// Read and parse a FIT image file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&std::fs::read(path)?)
+    }
+
+    /// FDT header of the image.
+    pub fn header(&self) -> &FdtHeader {
+        &self.header
+    }
+
+    /// Entries of the memory reservation block.
+    pub fn mem_reserve(&self) -> Result<Vec<MemReserveEntry>> {
+        let mut entries = Vec::new();
+        let mut offset = self.header.off_mem_rsvmap as usize;
+        loop {
+            let entry = self.blob.get(offset..offset + 16).ok_or_else(|| {
+                MkImageError::invalid_image_data("memory reservation block out of bounds")
+            })?;
+            let address = u64::from_be_bytes(entry[..8].try_into().unwrap());
+            let size = u64::from_be_bytes(entry[8..].try_into().unwrap());
+            if address == 0 && size == 0 {
+                return Ok(entries);
+            }
+            entries.push(MemReserveEntry::new(address, size));
+            offset += 16;
+        }
+    }
+
+    /// Root node of the image tree.
+    pub fn root(&self) -> &FdtNode {
+        &self.root
+    }
+
+    /// Top-level `description` property.
+    pub fn description(&self) -> Option<&str> {
+        self.root.property_str("description")
+    }
+
+    /// Nodes below `/images`.
+    pub fn images(&self) -> &[FdtNode] {
+        self.root
+            .child("images")
+            .map(|n| n.children.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Nodes below `/configurations`.
+    pub fn configurations(&self) -> &[FdtNode] {
+        self.root
+            .child("configurations")
+            .map(|n| n.children.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Name of the default configuration.
+    pub fn default_config(&self) -> Option<&str> {
+        self.root.child("configurations")?.property_str("default")
+    }
+
+    /// Find an image node by node name, falling back to its `type` property
+    /// (e.g. `kernel`, `flat_dt`, `ramdisk`) when no node has that name.
+    pub fn image(&self, name: &str) -> Option<&FdtNode> {
+        let images = self.images();
+        images
+            .iter()
+            .find(|n| n.name == name)
+            .or_else(|| images.iter().find(|n| n.property_str("type") == Some(name)))
+            .or_else(|| match name {
+                "fdt" => images
+                    .iter()
+                    .find(|n| n.property_str("type") == Some("flat_dt")),
+                _ => None,
+            })
+    }
+
+    /// Stored payload of an image, embedded (`data`) or external (`data-offset`/`data-position`).
+    pub fn image_data(&self, name: &str) -> Result<&[u8]> {
+        let node = self
+            .image(name)
+            .ok_or_else(|| MkImageError::invalid_image_data(format!("no image `{name}`")))?;
+        self.node_data(node)
+    }
+
+    pub(crate) fn node_data<'a>(&'a self, node: &'a FdtNode) -> Result<&'a [u8]> {
+        if let Some(data) = node.property("data") {
+            return Ok(data);
+        }
+        let size = node.property_u64("data-size");
+        let start = if let Some(pos) = node.property_u64("data-position") {
+            Some(pos)
+        } else {
+            // External data offsets are relative to the 4-byte aligned end of the FDT.
+            let fdt_end = (self.header.totalsize as u64 + 3) & !3;
+            node.property_u64("data-offset").map(|off| fdt_end + off)
+        };
+        match (start, size) {
+            (Some(start), Some(size)) => self
+                .blob
+                .get(start as usize..(start + size) as usize)
+                .ok_or_else(|| {
+                    MkImageError::invalid_image_data(format!(
+                        "external data of `{}` is out of bounds",
+                        node.name
+                    ))
+                }),
+            _ => Err(MkImageError::invalid_image_data(format!(
+                "image `{}` has no data",
+                node.name
+            ))),
+        }
+    }
+
+    /// Write the stored payload of an image to `path`.
+    pub fn extract(&self, component: &str, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.image_data(component)?)?;
+        Ok(())
+    }
+
+    /// Recompute every hash node and check configuration references.
+    pub fn verify(&self) -> VerifyReport {
+        let mut report = VerifyReport::default();
+
+        for image in self.images() {
+            let data = match self.node_data(image) {
+                Ok(data) => data,
+                Err(e) => {
+                    report.errors.push(e.to_string());
+                    continue;
+                }
+            };
+            let hashes: Vec<_> = image
+                .children
+                .iter()
+                .filter(|c| c.name.starts_with("hash"))
+                .collect();
+            if hashes.is_empty() {
+                report.unhashed.push(image.name.clone());
+            }
+            for hash in hashes {
+                let algo = hash.property_str("algo").unwrap_or_default().to_string();
+                let status = match (HashAlgorithm::from_name(&algo), hash.property("value")) {
+                    (None, _) => HashStatus::Unsupported(algo.clone()),
+                    (_, None) => HashStatus::MissingValue,
+                    (Some(a), Some(value)) => {
+                        let expected = hex::encode(value);
+                        let calculated = a.calculate(data);
+                        if expected == calculated {
+                            HashStatus::Ok
+                        } else {
+                            HashStatus::Mismatch {
+                                expected,
+                                calculated,
+                            }
+                        }
+                    }
+                };
+                report.checks.push(HashCheck {
+                    image: image.name.clone(),
+                    node: hash.name.clone(),
+                    algo,
+                    status,
+                });
+            }
+        }
+
+        for conf in self.configurations() {
+            for prop in &conf.properties {
+                if !matches!(
+                    prop.name.as_str(),
+                    "kernel" | "fdt" | "ramdisk" | "firmware" | "loadables" | "script"
+                ) {
+                    continue;
+                }
+                for target in conf.property_strings(&prop.name) {
+                    if !self.images().iter().any(|i| i.name == target) {
+                        report.errors.push(format!(
+                            "configuration `{}` references missing image `{target}` ({})",
+                            conf.name, prop.name
+                        ));
+                    }
+                }
+            }
+        }
+
+        if let Some(default) = self.default_config() {
+            if !self.configurations().iter().any(|c| c.name == default) {
+                report
+                    .errors
+                    .push(format!("default configuration `{default}` does not exist"));
+            }
+        }
+
+        report
+    }
+
+    /// Verify every image and configuration `signature-N` node against
+    /// `keys`.
+    ///
+    /// Keys are matched by algorithm and, when the node carries one, by
+    /// `key-name-hint`. Image signatures cover the payload; configuration
+    /// signatures cover the `hashed-nodes` regions and `hashed-strings` of
+    /// the tree the way U-Boot checks them, and are invalid unless the
+    /// configuration itself is among the hashed nodes.
+    pub fn verify_signatures(&self, keys: &[VerifyingKey]) -> Result<Vec<SignatureCheck>> {
+        let mut checks = Vec::new();
+        for image in self.images() {
+            let signatures = signature_nodes(image);
+            if signatures.is_empty() {
+                continue;
+            }
+            let data = self.node_data(image)?;
+            for signature in signatures {
+                checks.push(check_signature(image, false, signature, Some(data), keys));
+            }
+        }
+        for conf in self.configurations() {
+            for signature in signature_nodes(conf) {
+                let data = self.config_signed_data(conf, signature)?;
+                checks.push(check_signature(conf, true, signature, data.as_deref(), keys));
+            }
+        }
+        Ok(checks)
+    }
+
+    /// Data covered by a configuration signature, `None` if the node does
+    /// not sign the configuration.
+    fn config_signed_data(&self, conf: &FdtNode, signature: &FdtNode) -> Result<Option<Vec<u8>>> {
+        let nodes = signature.property_strings("hashed-nodes");
+        let conf_path = format!("/configurations/{}", conf.name);
+        let strings_size = match signature.property("hashed-strings") {
+            Some(cells) if cells.len() == 8 => u32::from_be_bytes(cells[4..].try_into().unwrap()),
+            _ => return Ok(None),
+        };
+        if !nodes.contains(&conf_path.as_str()) {
+            return Ok(None);
+        }
+        regions::signed_data(&self.blob, &nodes, strings_size as usize).map(Some)
+    }
+}
+
+/// `signature-N` children of an image or configuration node.
+fn signature_nodes(node: &FdtNode) -> Vec<&FdtNode> {
+    node.children
+        .iter()
+        .filter(|c| c.name.starts_with("signature"))
+        .collect()
+}
+
+/// Check `signature` over `data`; without data the signature is invalid.
+fn check_signature(
+    node: &FdtNode,
+    configuration: bool,
+    signature: &FdtNode,
+    data: Option<&[u8]>,
+    keys: &[VerifyingKey],
+) -> SignatureCheck {
+    let algo = signature
+        .property_str("algo")
+        .unwrap_or_default()
+        .to_string();
+    let hint = signature.property_str("key-name-hint").map(str::to_string);
+    let status = match (
+        SignatureAlgorithm::from_name(&algo),
+        signature.property("value"),
+    ) {
+        (None, _) => SignatureStatus::Unsupported(algo.clone()),
+        (_, None) => SignatureStatus::MissingValue,
+        (Some(a), Some(value)) => {
+            let mut candidates = keys
+                .iter()
+                .filter(|k| k.algorithm() == a)
+                .filter(|k| hint.as_deref().is_none_or(|h| k.name() == h))
+                .peekable();
+            if candidates.peek().is_none() {
+                SignatureStatus::NoKey
+            } else {
+                data.and_then(|data| candidates.find(|k| k.verify(data, value)))
+                    .map(|k| SignatureStatus::Ok(k.name().to_string()))
+                    .unwrap_or(SignatureStatus::Invalid)
+            }
+        }
+    };
+    SignatureCheck {
+        image: node.name.clone(),
+        configuration,
+        node: signature.name.clone(),
+        algo,
+        key_name_hint: hint,
+        status,
+    }
+}
+
+fn read_header(data: &[u8]) -> Result<FdtHeader> {
+    if data.len() < FdtHeader::size() {
+        return Err(MkImageError::invalid_image_data(
+            "image smaller than FDT header",
+        ));
+    }
+    let word = |i: usize| u32::from_be_bytes(data[i * 4..i * 4 + 4].try_into().unwrap());
+    let magic = word(0);
+    if magic != FDT_MAGIC {
+        return Err(MkImageError::invalid_magic(FDT_MAGIC, magic));
+    }
+    Ok(FdtHeader {
+        magic,
+        totalsize: word(1),
+        off_dt_struct: word(2),
+        off_dt_strings: word(3),
+        off_mem_rsvmap: word(4),
+        version: word(5),
+        last_comp_version: word(6),
+        boot_cpuid_phys: word(7),
+        size_dt_strings: word(8),
+        size_dt_struct: word(9),
+        reserved0: word(10),
+        reserved1: word(11),
+        reserved2: word(12),
+        reserved3: word(13),
+    })
+}
+
+fn slice(data: &[u8], offset: u32, size: u32) -> Result<&[u8]> {
+    data.get(offset as usize..offset as usize + size as usize)
+        .ok_or_else(|| MkImageError::invalid_image_data("FDT block out of bounds"))
+}
+
+struct StructParser<'a> {
+    data: &'a [u8],
+    strings: &'a [u8],
+    pos: usize,
+}
+
+impl StructParser<'_> {
+    fn parse(mut self) -> Result<FdtNode> {
+        let mut stack: Vec<FdtNode> = Vec::new();
+        let mut root = None;
+        loop {
+            let token = self.u32()?;
+            match token {
+                t if t == FdtToken::BeginNode.value() => {
+                    let name = self.cstr()?;
+                    self.align();
+                    stack.push(FdtNode {
+                        name,
+                        ..Default::default()
+                    });
+                }
+                t if t == FdtToken::EndNode.value() => {
+                    let node = stack
+                        .pop()
+                        .ok_or_else(|| MkImageError::invalid_image_data("unbalanced END_NODE"))?;
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(node),
+                        None => root = Some(node),
+                    }
+                }
+                t if t == FdtToken::Prop.value() => {
+                    let len = self.u32()? as usize;
+                    let name_off = self.u32()? as usize;
+                    let value = self
+                        .data
+                        .get(self.pos..self.pos + len)
+                        .ok_or_else(|| MkImageError::invalid_image_data("property out of bounds"))?
+                        .to_vec();
+                    self.pos += len;
+                    self.align();
+                    let name = self
+                        .strings
+                        .get(name_off..)
+                        .and_then(|s| s.split(|&b| b == 0).next())
+                        .map(|s| String::from_utf8_lossy(s).into_owned())
+                        .ok_or_else(|| MkImageError::invalid_image_data("bad string offset"))?;
+                    stack
+                        .last_mut()
+                        .ok_or_else(|| MkImageError::invalid_image_data("property outside node"))?
+                        .properties
+                        .push(FdtProperty { name, value });
+                }
+                t if t == FdtToken::Nop.value() => {}
+                t if t == FdtToken::End.value() => break,
+                t => {
+                    return Err(MkImageError::invalid_image_data(format!(
+                        "unknown FDT token {t:#x}"
+                    )))
+                }
+            }
+        }
+        root.ok_or_else(|| MkImageError::invalid_image_data("FDT has no root node"))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(|| MkImageError::invalid_image_data("unexpected end of structure block"))?;
+        self.pos += 4;
+        Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn cstr(&mut self) -> Result<String> {
+        let rest = &self.data[self.pos..];
+        let end = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| MkImageError::invalid_image_data("unterminated node name"))?;
+        self.pos += end + 1;
+        Ok(String::from_utf8_lossy(&rest[..end]).into_owned())
+    }
+
+    fn align(&mut self) {
+        self.pos = (self.pos + 3) & !3;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fit::{ComponentConfig, FitImageBuilder, FitImageConfig};
+
+    fn build_image() -> Vec<u8> {
+        let config = FitImageConfig::new("Reader test")
+            .with_kernel(
+                ComponentConfig::new("kernel", vec![1, 2, 3, 4, 5])
+                    .with_load_address(0x8008_0000)
+                    .with_hash(HashAlgorithm::Crc32)
+                    .with_hash(HashAlgorithm::Sha1),
+            )
+            .with_fdt(ComponentConfig::new("fdt", vec![6, 7, 8]).with_hash(HashAlgorithm::Md5));
+        FitImageBuilder::new().build(config).unwrap()
+    }
+
+    #[test]
+    fn test_read_back_built_image() {
+        let reader = FitImageReader::parse(&build_image()).unwrap();
+        assert_eq!(reader.description(), Some("Reader test"));
+        assert_eq!(reader.images().len(), 2);
+        assert_eq!(reader.default_config(), Some("config-1"));
+        assert_eq!(reader.image_data("kernel").unwrap(), &[1, 2, 3, 4, 5]);
+        assert_eq!(reader.image_data("flat_dt").unwrap(), &[6, 7, 8]);
+        assert_eq!(
+            reader.image("kernel").unwrap().property_u64("load"),
+            Some(0x8008_0000)
+        );
+    }
+
+    #[test]
+    fn test_verify_detects_corruption() {
+        let mut image = build_image();
+        let reader = FitImageReader::parse(&image).unwrap();
+        let report = reader.verify();
+        assert!(report.is_ok(), "{report:?}");
+        assert_eq!(report.checks.len(), 3);
+
+        // Flip one kernel payload byte in place.
+        let pos = image.windows(5).position(|w| w == [1, 2, 3, 4, 5]).unwrap();
+        image[pos] ^= 0xff;
+        let report = FitImageReader::parse(&image).unwrap().verify();
+        assert!(!report.is_ok());
+        assert_eq!(report.failures().count(), 2);
+    }
+
+    #[test]
+    fn test_verify_signatures() {
+        use crate::signature::SigningKey;
+        use ring::rand::SystemRandom;
+        use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+        let pkcs8 =
+            EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &SystemRandom::new())
+                .unwrap();
+        let key = SigningKey::from_der(SignatureAlgorithm::EcdsaP256Sha256, "dev", pkcs8.as_ref())
+            .unwrap();
+        let public = key.verifying_key();
+        let config = FitImageConfig::new("Signed")
+            .with_kernel(
+                ComponentConfig::new("kernel", vec![1, 2, 3, 4, 5]).with_hash(HashAlgorithm::Sha1),
+            )
+            .with_fdt(ComponentConfig::new("fdt", vec![6, 7, 8]));
+        let mut image = FitImageBuilder::new()
+            .with_signing_key(key)
+            .build(config)
+            .unwrap();
+
+        let reader = FitImageReader::parse(&image).unwrap();
+        assert!(reader.verify().is_ok());
+        let checks = reader
+            .verify_signatures(std::slice::from_ref(&public))
+            .unwrap();
+        assert_eq!(checks.len(), 3);
+        assert!(checks
+            .iter()
+            .all(|c| c.status == SignatureStatus::Ok("dev".into())));
+        assert!(checks[2].configuration);
+        assert_eq!(checks[2].image, "config-1");
+        assert_eq!(checks[0].algo, "sha256,ecdsa256");
+        assert_eq!(checks[0].key_name_hint.as_deref(), Some("dev"));
+
+        let other = public.clone().with_name("prod");
+        let checks = reader.verify_signatures(&[other]).unwrap();
+        assert!(checks.iter().all(|c| c.status == SignatureStatus::NoKey));
+
+        // 配置签名只覆盖哈希值，不覆盖镜像数据
+        let pos = image.windows(5).position(|w| w == [1, 2, 3, 4, 5]).unwrap();
+        image[pos] ^= 0xff;
+        let checks = FitImageReader::parse(&image)
+            .unwrap()
+            .verify_signatures(std::slice::from_ref(&public))
+            .unwrap();
+        assert_eq!(checks[0].status, SignatureStatus::Invalid);
+        assert_eq!(checks[1].status, SignatureStatus::Ok("dev".into()));
+        assert_eq!(checks[2].status, SignatureStatus::Ok("dev".into()));
+
+        let pos = image
+            .windows(7)
+            .position(|w| w == b"Default")
+            .unwrap();
+        image[pos] = b'd';
+        let checks = FitImageReader::parse(&image)
+            .unwrap()
+            .verify_signatures(&[public])
+            .unwrap();
+        assert_eq!(checks[2].status, SignatureStatus::Invalid);
+    }
+
+    #[test]
+    fn test_default_config_applies_overlays() {
+        let config = FitImageConfig::new("Overlays")
+            .with_fdt(ComponentConfig::new("fdt", vec![1]))
+            .with_overlay(ComponentConfig::new("overlay-a", vec![2]))
+            .with_overlay(ComponentConfig::new("overlay-b", vec![3]));
+        let image = FitImageBuilder::new().build(config).unwrap();
+        let reader = FitImageReader::parse(&image).unwrap();
+
+        let conf = &reader.configurations()[0];
+        assert_eq!(
+            conf.property_strings("fdt"),
+            ["fdt", "overlay-a", "overlay-b"]
+        );
+        assert_eq!(reader.image_data("overlay-b").unwrap(), &[3]);
+        assert!(reader.verify().is_ok());
+    }
+
+    #[test]
+    fn test_default_config_lists_firmware() {
+        let config = FitImageConfig::new("U-Boot FIT")
+            .with_firmware(ComponentConfig::new("uboot", vec![1]).with_load_address(0x20_0000))
+            .with_loadable(ComponentConfig::new("atf", vec![2]).with_load_address(0x4_0000))
+            .with_loadable(ComponentConfig::new("optee", vec![3]))
+            .with_fdt(ComponentConfig::new("fdt", vec![4]));
+        let image = FitImageBuilder::new().build(config).unwrap();
+        let reader = FitImageReader::parse(&image).unwrap();
+
+        let conf = &reader.configurations()[0];
+        assert_eq!(conf.property_strings("firmware"), ["uboot"]);
+        assert_eq!(conf.property_strings("loadables"), ["atf", "optee"]);
+        let uboot = reader.image("uboot").unwrap();
+        assert_eq!(uboot.property_str("type"), Some("firmware"));
+        assert_eq!(uboot.property_str("os"), Some("u-boot"));
+        assert_eq!(reader.image("atf").unwrap().property_str("os"), None);
+        assert!(reader.verify().is_ok());
+    }
+
+    #[test]
+    fn test_reject_bad_magic() {
+        let mut image = build_image();
+        image[0] = 0;
+        assert!(matches!(
+            FitImageReader::parse(&image),
+            Err(MkImageError::InvalidMagic { .. })
+        ));
+    }
+}
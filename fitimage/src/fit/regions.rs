@@ -0,0 +1,280 @@
+//! FDT regions covered by configuration signatures
+//!
+//! A configuration signature does not sign image payloads directly. Like
+//! mkimage and U-Boot (libfdt's `fdt_find_regions()`), it signs the parts
+//! of the structure block that belong to the `hashed-nodes` (the root, the
+//! configuration and its images with their hash nodes) without any `data`
+//! properties, followed by the first `hashed-strings` bytes of the strings
+//! block. The image payloads are covered through their hash values.
+
+use std::ops::Range;
+
+use crate::error::{MkImageError, Result};
+use crate::fit::FdtToken;
+
+/// Properties left out of the signed regions; payloads are covered by hashes
+const EXCLUDED_PROPS: [&str; 4] = ["data", "data-size", "data-position", "data-offset"];
+
+/// One token of the structure block
+enum Tag<'a> {
+    BeginNode(&'a str),
+    EndNode,
+    Prop { name: &'a str, value: Range<usize> },
+    Nop,
+    End,
+}
+
+/// Structure and strings blocks of an FDT blob
+struct Blocks<'a> {
+    structure: &'a [u8],
+    strings: &'a [u8],
+    /// Offset of the structure block within the blob
+    struct_offset: usize,
+}
+
+impl<'a> Blocks<'a> {
+    fn new(blob: &'a [u8]) -> Result<Self> {
+        let word = |i: usize| -> Result<usize> {
+            blob.get(i * 4..i * 4 + 4)
+                .map(|b| u32::from_be_bytes(b.try_into().unwrap()) as usize)
+                .ok_or_else(|| MkImageError::invalid_image_data("image smaller than FDT header"))
+        };
+        let (off_struct, off_strings) = (word(2)?, word(3)?);
+        let (size_strings, size_struct) = (word(8)?, word(9)?);
+        let block = |off: usize, size: usize| {
+            blob.get(off..off + size)
+                .ok_or_else(|| MkImageError::invalid_image_data("FDT block out of bounds"))
+        };
+        Ok(Self {
+            structure: block(off_struct, size_struct)?,
+            strings: block(off_strings, size_strings)?,
+            struct_offset: off_struct,
+        })
+    }
+
+    /// Read the token at `offset`, returning it and the offset of the next one.
+    fn tag(&self, offset: usize) -> Result<(Tag<'a>, usize)> {
+        let bad = |what: &str| MkImageError::invalid_image_data(format!("bad FDT {what}"));
+        let word = |at: usize| {
+            self.structure
+                .get(at..at + 4)
+                .map(|b| u32::from_be_bytes(b.try_into().unwrap()) as usize)
+                .ok_or_else(|| bad("structure block"))
+        };
+        let align = |at: usize| (at + 3) & !3;
+        let token = word(offset)? as u32;
+        let body = offset + 4;
+        match token {
+            t if t == FdtToken::BeginNode.value() => {
+                let rest = &self.structure[body..];
+                let len = rest.iter().position(|&b| b == 0).ok_or_else(|| bad("node name"))?;
+                let name = std::str::from_utf8(&rest[..len]).map_err(|_| bad("node name"))?;
+                Ok((Tag::BeginNode(name), align(body + len + 1)))
+            }
+            t if t == FdtToken::EndNode.value() => Ok((Tag::EndNode, body)),
+            t if t == FdtToken::Prop.value() => {
+                let len = word(body)?;
+                let name_off = word(body + 4)?;
+                let value = body + 8..body + 8 + len;
+                if value.end > self.structure.len() {
+                    return Err(bad("property"));
+                }
+                let name = self
+                    .strings
+                    .get(name_off..)
+                    .and_then(|s| s.split(|&b| b == 0).next())
+                    .and_then(|s| std::str::from_utf8(s).ok())
+                    .ok_or_else(|| bad("string offset"))?;
+                Ok((Tag::Prop { name, value: value.clone() }, align(value.end)))
+            }
+            t if t == FdtToken::Nop.value() => Ok((Tag::Nop, body)),
+            t if t == FdtToken::End.value() => Ok((Tag::End, body)),
+            t => Err(MkImageError::invalid_image_data(format!(
+                "unknown FDT token {t:#x}"
+            ))),
+        }
+    }
+
+    /// Regions of the structure block belonging to the nodes in `include`,
+    /// as libfdt's `fdt_find_regions()` computes them.
+    ///
+    /// Included nodes contribute all their tokens except excluded
+    /// properties; their direct children only their begin and end tokens,
+    /// so the tree shape is signed but nothing else about them.
+    fn regions(&self, include: &[&str]) -> Result<Vec<Range<usize>>> {
+        let mut regions: Vec<Range<usize>> = Vec::new();
+        let mut start: Option<usize> = None;
+        let mut stack = Vec::new();
+        let mut path = String::new();
+        let mut want = 0u8;
+        let mut next = 0;
+        loop {
+            let offset = next;
+            let (tag, after) = self.tag(offset)?;
+            next = after;
+            let mut stop_at = next;
+            let include_tag = match tag {
+                Tag::Prop { name, .. } => {
+                    stop_at = offset;
+                    want >= 2 && !EXCLUDED_PROPS.contains(&name)
+                }
+                Tag::Nop => {
+                    stop_at = offset;
+                    want >= 2
+                }
+                Tag::BeginNode(name) => {
+                    stack.push((want, path.len()));
+                    if path != "/" {
+                        path.push('/');
+                    }
+                    path.push_str(name);
+                    if want == 1 {
+                        stop_at = offset;
+                    }
+                    if include.contains(&path.as_str()) {
+                        want = 2;
+                    } else if want > 0 {
+                        want -= 1;
+                    } else {
+                        stop_at = offset;
+                    }
+                    want > 0
+                }
+                Tag::EndNode => {
+                    let included = want > 0;
+                    let (parent_want, len) = stack
+                        .pop()
+                        .ok_or_else(|| MkImageError::invalid_image_data("unbalanced END_NODE"))?;
+                    want = parent_want;
+                    path.truncate(len);
+                    included
+                }
+                Tag::End => true,
+            };
+
+            if include_tag && start.is_none() {
+                // Merge with the previous region when they touch
+                start = match regions.last() {
+                    Some(last) if last.end == offset => regions.pop().map(|r| r.start),
+                    _ => Some(offset),
+                };
+            }
+            if !include_tag {
+                if let Some(begin) = start.take() {
+                    regions.push(begin..stop_at);
+                }
+            }
+            if matches!(tag, Tag::End) {
+                break;
+            }
+        }
+        if let Some(begin) = start {
+            regions.push(begin..next);
+        }
+        Ok(regions)
+    }
+
+    /// Absolute range of the value of property `prop` of node `node_path`.
+    fn property(&self, node_path: &str, prop: &str) -> Result<Option<Range<usize>>> {
+        let mut path = String::new();
+        let mut lens = Vec::new();
+        let mut next = 0;
+        loop {
+            let (tag, after) = self.tag(next)?;
+            next = after;
+            match tag {
+                Tag::BeginNode(name) => {
+                    lens.push(path.len());
+                    if path != "/" {
+                        path.push('/');
+                    }
+                    path.push_str(name);
+                }
+                Tag::EndNode => path.truncate(lens.pop().unwrap_or_default()),
+                Tag::Prop { name, value } if name == prop && path == node_path => {
+                    let start = self.struct_offset + value.start;
+                    return Ok(Some(start..self.struct_offset + value.end));
+                }
+                Tag::End => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Data a configuration signature covers: the regions of `hashed_nodes`
+/// followed by the first `strings_len` bytes of the strings block.
+pub(crate) fn signed_data(blob: &[u8], hashed_nodes: &[&str], strings_len: usize) -> Result<Vec<u8>> {
+    let blocks = Blocks::new(blob)?;
+    let strings = blocks
+        .strings
+        .get(..strings_len)
+        .ok_or_else(|| MkImageError::invalid_image_data("hashed-strings out of bounds"))?;
+    let mut data = Vec::new();
+    for region in blocks.regions(hashed_nodes)? {
+        data.extend_from_slice(&blocks.structure[region]);
+    }
+    data.extend_from_slice(strings);
+    Ok(data)
+}
+
+/// Absolute range of a property value within `blob`, for patching it in place.
+pub(crate) fn property_range(
+    blob: &[u8],
+    node_path: &str,
+    prop: &str,
+) -> Result<Option<Range<usize>>> {
+    Blocks::new(blob)?.property(node_path, prop)
+}
+
+/// Size of the strings block of `blob`.
+pub(crate) fn strings_size(blob: &[u8]) -> Result<usize> {
+    Ok(Blocks::new(blob)?.strings.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fit::FdtWriter;
+
+    fn blob(data: &[u8]) -> Vec<u8> {
+        let mut fdt = FdtWriter::new();
+        fdt.begin_node("").unwrap();
+        fdt.property_string("description", "test").unwrap();
+        fdt.begin_node("images").unwrap();
+        fdt.begin_node("kernel").unwrap();
+        fdt.property("data", data).unwrap();
+        fdt.begin_node("hash-1").unwrap();
+        fdt.property_string("algo", "sha256").unwrap();
+        fdt.end_node().unwrap();
+        fdt.end_node().unwrap();
+        fdt.begin_node("other").unwrap();
+        fdt.property_string("type", "ramdisk").unwrap();
+        fdt.end_node().unwrap();
+        fdt.end_node().unwrap();
+        fdt.end_node().unwrap();
+        fdt.finish().unwrap()
+    }
+
+    #[test]
+    fn test_signed_data() {
+        let nodes = ["/", "/images/kernel", "/images/kernel/hash-1"];
+        let strings = strings_size(&blob(b"a")).unwrap();
+        let signed = signed_data(&blob(b"a"), &nodes, strings).unwrap();
+        // Payloads and nodes outside the list do not count
+        assert_eq!(signed, signed_data(&blob(b"b"), &nodes, strings).unwrap());
+        assert!(!signed.windows(7).any(|w| w == b"ramdisk"));
+        assert!(signed.windows(6).any(|w| w == b"sha256"));
+        // The strings block follows, names of unsigned properties included
+        assert!(signed.windows(5).any(|w| w == b"type\0"));
+
+        let range = property_range(&blob(b"xyz"), "/images/kernel", "data")
+            .unwrap()
+            .unwrap();
+        assert_eq!(&blob(b"xyz")[range], b"xyz");
+        assert_eq!(
+            property_range(&blob(b"a"), "/images/other", "data").unwrap(),
+            None
+        );
+    }
+}
@@ -2,49 +2,99 @@
 //!
 //! Creates U-Boot compatible FIT images using proper FDT structure.
 
-use crate::error::Result;
+use std::collections::HashMap;
+
+use crate::error::{MkImageError, Result};
 use crate::fit::config::{ComponentConfig, FitImageConfig};
-use crate::fit::{FdtHeader, FdtToken, FdtTokenUtils, MemReserveEntry, StringTable};
+use crate::fit::fdt_writer::{check_alignment, FdtWriter};
+use crate::fit::regions;
+use crate::fit::FDT_STRUCT_ALIGN;
+use crate::signature::SigningKey;
 
 /// Standard FDT builder that creates U-Boot compatible FIT images
 pub struct StandardFdtBuilder {
-    /// FDT header
-    header: FdtHeader,
-    /// String table for property names
-    string_table: StringTable,
-    /// Structure block buffer
-    struct_buffer: Vec<u8>,
-    /// Memory reserve map entries
-    mem_reserve: Vec<MemReserveEntry>,
+    /// Underlying FDT writer
+    writer: FdtWriter,
+    /// Alignment of image `data` payloads within the blob
+    data_alignment: u32,
+    /// Block size the final blob is padded to
+    block_size: Option<u32>,
+    /// Hash values calculated beforehand, by component name
+    hash_values: HashMap<String, Vec<String>>,
+    /// Key used to sign every image payload and configuration
+    signing_key: Option<SigningKey>,
+    /// Signatures calculated beforehand, by component name
+    signature_values: HashMap<String, Vec<u8>>,
+    /// Number of `hash-N` nodes written, by image name
+    hash_counts: HashMap<String, usize>,
+    /// Configuration signature nodes to sign in [`Self::finalize`], with
+    /// their `hashed-nodes`
+    config_signatures: Vec<(String, Vec<String>)>,
 }
 
 impl StandardFdtBuilder {
     /// Create a new standard FDT builder
     pub fn new() -> Result<Self> {
         Ok(Self {
-            header: FdtHeader::new(),
-            string_table: StringTable::new(),
-            struct_buffer: Vec::new(),
-            mem_reserve: Vec::new(),
+            writer: FdtWriter::new(),
+            data_alignment: 4,
+            block_size: None,
+            hash_values: HashMap::new(),
+            signing_key: None,
+            signature_values: HashMap::new(),
+            hash_counts: HashMap::new(),
+            config_signatures: Vec::new(),
         })
     }
 
-    /// Build a FIT device tree from configuration
-    pub fn build_fit_tree(&mut self, config: &FitImageConfig) -> Result<()> {
-        // Add memory reserve entries (typically empty for FIT images)
-        self.add_default_memory_reserve();
+    /// Align every image `data` payload to `alignment` bytes within the blob.
+    ///
+    /// Padding is inserted as `FDT_NOP` tokens, so the result stays a valid
+    /// FDT and U-Boot can use payloads in place.
+    pub fn with_data_alignment(mut self, alignment: u32) -> Result<Self> {
+        check_alignment(alignment)?;
+        self.data_alignment = alignment.max(FDT_STRUCT_ALIGN as u32);
+        Ok(self)
+    }
 
-        // Build the structure block
-        self.build_structure_block(config)?;
+    /// Pad the final blob (and its `totalsize`) to a multiple of `block_size`.
+    pub fn with_block_size(mut self, block_size: u32) -> Result<Self> {
+        check_alignment(block_size)?;
+        self.block_size = Some(block_size);
+        Ok(self)
+    }
 
-        Ok(())
+    /// Use hash values calculated beforehand instead of hashing while
+    /// writing the tree.
+    ///
+    /// `values` maps a component name to the hex values of its `hashes`, in
+    /// the same order; components without an entry are hashed as usual.
+    pub fn with_hash_values(mut self, values: HashMap<String, Vec<String>>) -> Self {
+        self.hash_values = values;
+        self
+    }
+
+    /// Add a `signature-1` node signed with `key` to every image and every
+    /// configuration.
+    ///
+    /// Configuration signatures cover the images through their hash nodes,
+    /// so every image a configuration references needs at least one hash.
+    pub fn with_signing_key(mut self, key: SigningKey) -> Self {
+        self.signing_key = Some(key);
+        self
+    }
+
+    /// Use signatures calculated beforehand instead of signing while
+    /// writing the tree; components without an entry are signed as usual.
+    pub fn with_signature_values(mut self, values: HashMap<String, Vec<u8>>) -> Self {
+        self.signature_values = values;
+        self
     }
 
-    /// Add default memory reserve entries
-    fn add_default_memory_reserve(&mut self) {
-        // For FIT images, we typically don't need memory reservations
-        // Just add the terminator
-        self.mem_reserve.push(MemReserveEntry::new(0, 0));
+    /// Build a FIT device tree from configuration
+    pub fn build_fit_tree(&mut self, config: &FitImageConfig) -> Result<()> {
+        // FIT images carry no memory reservations, only the terminator
+        self.build_structure_block(config)
     }
 
     /// Build the main structure block
@@ -53,14 +103,11 @@ impl StandardFdtBuilder {
         self.begin_node("")?;
 
         // Add root properties to match mkimage standard
-        self.add_property_u32(
-            "timestamp",
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs() as u32,
-        )?;
+        self.add_property_u32("timestamp", config.timestamp.resolve())?;
         self.add_property_string("description", &config.description)?;
+        if let Some(ref creator) = config.creator {
+            self.add_property_string("creator", creator)?;
+        }
         self.add_property_u32("#address-cells", 2)?;
         self.add_property_u32("#size-cells", 1)?;
 
@@ -75,12 +122,7 @@ impl StandardFdtBuilder {
         self.end_node()?; // End configurations node
 
         // End root node
-        self.end_node()?;
-
-        // Add END token
-        FdtToken::End.write_to_buffer(&mut self.struct_buffer);
-
-        Ok(())
+        self.end_node()
     }
 
     /// Add images to the structure block
@@ -103,6 +145,13 @@ impl StandardFdtBuilder {
             component_names.push(("fdt", node_name));
         }
 
+        // Add FDT overlays
+        for overlay in &config.overlays {
+            let node_name = overlay.name.clone();
+            self.add_fdt_image(&node_name, overlay)?;
+            component_names.push(("fdt", node_name));
+        }
+
         // Add ramdisk
         if let Some(ref ramdisk) = config.ramdisk {
             // Use standard naming without prefix to match mkimage
@@ -111,6 +160,25 @@ impl StandardFdtBuilder {
             component_names.push(("ramdisk", node_name));
         }
 
+        // Add boot script
+        if let Some(ref script) = config.script {
+            let node_name = script.name.clone();
+            self.add_script_image(&node_name, script)?;
+            component_names.push(("script", node_name));
+        }
+
+        // Add SPL firmware and loadables
+        if let Some(ref firmware) = config.firmware {
+            let node_name = firmware.name.clone();
+            self.add_firmware_image(&node_name, firmware, Some("u-boot"))?;
+            component_names.push(("firmware", node_name));
+        }
+        for loadable in &config.loadables {
+            let node_name = loadable.name.clone();
+            self.add_firmware_image(&node_name, loadable, None)?;
+            component_names.push(("loadables", node_name));
+        }
+
         Ok(())
     }
 
@@ -123,18 +191,51 @@ impl StandardFdtBuilder {
             self.add_property_string("description", "Default configuration")?;
 
             // Add component references using standard naming
+            if let Some(ref firmware) = config.firmware {
+                self.add_property_string("firmware", &firmware.name)?;
+            }
+
             if let Some(ref kernel) = config.kernel {
                 self.add_property_string("kernel", &kernel.name)?;
             }
 
-            if let Some(ref fdt) = config.fdt {
-                self.add_property_string("fdt", &fdt.name)?;
+            // Base FDT first, followed by overlays in application order
+            let fdts: Vec<&str> = config
+                .fdt
+                .iter()
+                .chain(&config.overlays)
+                .map(|c| c.name.as_str())
+                .collect();
+            if !fdts.is_empty() {
+                self.add_property_string_list("fdt", &fdts)?;
             }
 
             if let Some(ref ramdisk) = config.ramdisk {
                 self.add_property_string("ramdisk", &ramdisk.name)?;
             }
 
+            if let Some(ref script) = config.script {
+                self.add_property_string("script", &script.name)?;
+            }
+
+            let loadables: Vec<&str> = config.loadables.iter().map(|c| c.name.as_str()).collect();
+            if !loadables.is_empty() {
+                self.add_property_string_list("loadables", &loadables)?;
+            }
+
+            let name = |c: &Option<ComponentConfig>| -> Vec<String> {
+                c.iter().map(|c| c.name.clone()).collect()
+            };
+            let images: [(&str, Vec<String>); 6] = [
+                ("firmware", name(&config.firmware)),
+                ("kernel", name(&config.kernel)),
+                ("fdt", fdts.iter().map(|s| s.to_string()).collect()),
+                ("ramdisk", name(&config.ramdisk)),
+                ("script", name(&config.script)),
+                ("loadables", loadables.iter().map(|s| s.to_string()).collect()),
+            ];
+            self.add_config_signature("config-1", &images)?;
+
             self.end_node()?;
 
             // Set default configuration reference
@@ -145,24 +246,56 @@ impl StandardFdtBuilder {
                 self.add_property_string("default", default_config)?;
             }
 
-            // Add specified configurations
-            for (config_name, val) in &config.configurations {
+            // Add specified configurations, sorted by name for reproducible output
+            let mut names: Vec<_> = config.configurations.keys().collect();
+            names.sort();
+            for config_name in names {
+                let val = &config.configurations[config_name];
                 self.begin_node(config_name)?;
                 self.add_property_string("description", &val.description)?;
 
                 // Add component references
+                if let Some(ref firmware_ref) = val.firmware {
+                    self.add_property_string("firmware", firmware_ref)?;
+                }
+
                 if let Some(ref kernel_ref) = val.kernel {
                     self.add_property_string("kernel", kernel_ref)?;
                 }
 
-                if let Some(ref fdt_ref) = val.fdt {
-                    self.add_property_string("fdt", fdt_ref)?;
+                let fdts: Vec<&str> = val
+                    .fdt
+                    .iter()
+                    .chain(&val.overlays)
+                    .map(String::as_str)
+                    .collect();
+                if !fdts.is_empty() {
+                    self.add_property_string_list("fdt", &fdts)?;
                 }
 
                 if let Some(ref ramdisk_ref) = val.ramdisk {
                     self.add_property_string("ramdisk", ramdisk_ref)?;
                 }
 
+                if let Some(ref script_ref) = val.script {
+                    self.add_property_string("script", script_ref)?;
+                }
+
+                if !val.loadables.is_empty() {
+                    let loadables: Vec<&str> = val.loadables.iter().map(String::as_str).collect();
+                    self.add_property_string_list("loadables", &loadables)?;
+                }
+
+                let images: [(&str, Vec<String>); 6] = [
+                    ("firmware", val.firmware.iter().cloned().collect()),
+                    ("kernel", val.kernel.iter().cloned().collect()),
+                    ("fdt", fdts.iter().map(|s| s.to_string()).collect()),
+                    ("ramdisk", val.ramdisk.iter().cloned().collect()),
+                    ("script", val.script.iter().cloned().collect()),
+                    ("loadables", val.loadables.clone()),
+                ];
+                self.add_config_signature(config_name, &images)?;
+
                 self.end_node()?;
             }
         }
@@ -183,21 +316,21 @@ impl StandardFdtBuilder {
 
         // Use custom type if provided, otherwise default
         if let Some(ref type_str) = component.component_type {
-            self.add_property_string("type", type_str)?;
+            self.add_property_string("type", type_str.as_str())?;
         } else {
             self.add_property_string("type", "kernel")?;
         }
 
         // Use custom arch if provided, otherwise default
         if let Some(ref arch_str) = component.arch {
-            self.add_property_string("arch", arch_str)?;
+            self.add_property_string("arch", arch_str.as_str())?;
         } else {
             self.add_property_string("arch", "arm64")?;
         }
 
         // Use custom os if provided, otherwise default
         if let Some(ref os_str) = component.os {
-            self.add_property_string("os", os_str)?;
+            self.add_property_string("os", os_str.as_str())?;
         } else {
             self.add_property_string("os", "linux")?;
         }
@@ -219,10 +352,10 @@ impl StandardFdtBuilder {
             self.add_property_u64("entry", entry_addr)?;
         }
 
-        self.add_property_data("data", &component.data)?;
+        self.add_image_data(&component.data)?;
 
         // Add hash nodes to match mkimage standard
-        // self.add_hash_nodes(&component.data)?;
+        self.add_hash_nodes(component)?;
 
         self.end_node()?;
         Ok(())
@@ -241,14 +374,14 @@ impl StandardFdtBuilder {
 
         // Use custom type if provided, otherwise default
         if let Some(ref type_str) = component.component_type {
-            self.add_property_string("type", type_str)?;
+            self.add_property_string("type", type_str.as_str())?;
         } else {
             self.add_property_string("type", "flat_dt")?;
         }
 
         // Use custom arch if provided, otherwise default
         if let Some(ref arch_str) = component.arch {
-            self.add_property_string("arch", arch_str)?;
+            self.add_property_string("arch", arch_str.as_str())?;
         } else {
             self.add_property_string("arch", "arm64")?;
         }
@@ -265,10 +398,10 @@ impl StandardFdtBuilder {
             self.add_property_u64("load", load_addr)?;
         }
 
-        self.add_property_data("data", &component.data)?;
+        self.add_image_data(&component.data)?;
 
         // Add hash nodes to match mkimage standard
-        // self.add_hash_nodes(&component.data)?;
+        self.add_hash_nodes(component)?;
 
         self.end_node()?;
         Ok(())
@@ -277,10 +410,16 @@ impl StandardFdtBuilder {
     /// Add ramdisk image node
     fn add_ramdisk_image(&mut self, name: &str, component: &ComponentConfig) -> Result<()> {
         self.begin_node(name)?;
-        self.add_property_string("description", "Ramdisk Image")?;
+        let description = component.description.as_deref().unwrap_or("Ramdisk Image");
+        self.add_property_string("description", description)?;
         self.add_property_string("type", "ramdisk")?;
-        self.add_property_string("arch", "arm64")?;
-        self.add_property_string("os", "linux")?;
+        let arch = component
+            .arch
+            .as_ref()
+            .map_or("arm64", |arch| arch.as_str());
+        self.add_property_string("arch", arch)?;
+        let os = component.os.as_ref().map_or("linux", |os| os.as_str());
+        self.add_property_string("os", os)?;
         // Use custom compression if provided, otherwise default
         if component.compression {
             self.add_property_string("compression", "gzip")?;
@@ -293,114 +432,252 @@ impl StandardFdtBuilder {
             self.add_property_u64("load", load_addr)?;
         }
 
-        self.add_property_data("data", &component.data)?;
+        self.add_image_data(&component.data)?;
 
         // Add hash nodes to match mkimage standard
-        // self.add_hash_nodes(&component.data)?;
+        self.add_hash_nodes(component)?;
 
         self.end_node()?;
         Ok(())
     }
 
+    /// Add boot script image node
+    fn add_script_image(&mut self, name: &str, component: &ComponentConfig) -> Result<()> {
+        self.begin_node(name)?;
+
+        if let Some(ref desc) = component.description {
+            self.add_property_string("description", desc)?;
+        } else {
+            self.add_property_string("description", "Boot Script")?;
+        }
+        self.add_property_string("type", "script")?;
+
+        if let Some(ref arch_str) = component.arch {
+            self.add_property_string("arch", arch_str.as_str())?;
+        }
+
+        if component.compression {
+            self.add_property_string("compression", "gzip")?;
+        } else {
+            self.add_property_string("compression", "none")?;
+        }
+
+        self.add_image_data(&component.data)?;
+
+        self.add_hash_nodes(component)?;
+
+        self.end_node()?;
+        Ok(())
+    }
+
+    /// Add a firmware or loadable image node started by SPL
+    fn add_firmware_image(
+        &mut self,
+        name: &str,
+        component: &ComponentConfig,
+        os: Option<&str>,
+    ) -> Result<()> {
+        self.begin_node(name)?;
+
+        if let Some(ref desc) = component.description {
+            self.add_property_string("description", desc)?;
+        } else {
+            self.add_property_string("description", "Firmware")?;
+        }
+
+        if let Some(ref type_str) = component.component_type {
+            self.add_property_string("type", type_str.as_str())?;
+        } else {
+            self.add_property_string("type", "firmware")?;
+        }
+
+        if let Some(ref arch_str) = component.arch {
+            self.add_property_string("arch", arch_str.as_str())?;
+        } else {
+            self.add_property_string("arch", "arm64")?;
+        }
+
+        // SPL picks the hand-over (ATF, OP-TEE, U-Boot) by `os`
+        if let Some(os) = component.os.as_ref().map(|o| o.as_str()).or(os) {
+            self.add_property_string("os", os)?;
+        }
+
+        if component.compression {
+            self.add_property_string("compression", "gzip")?;
+        } else {
+            self.add_property_string("compression", "none")?;
+        }
+
+        if let Some(load_addr) = component.load_address {
+            self.add_property_u64("load", load_addr)?;
+        }
+
+        if let Some(entry_addr) = component.entry_point {
+            self.add_property_u64("entry", entry_addr)?;
+        }
+
+        self.add_image_data(&component.data)?;
+
+        self.add_hash_nodes(component)?;
+
+        self.end_node()?;
+        Ok(())
+    }
+
+    /// Add the `data` property, preceded by NOPs to honor the data alignment
+    fn add_image_data(&mut self, data: &[u8]) -> Result<()> {
+        self.writer.align_next_property(self.data_alignment)?;
+        self.add_property_data("data", data)
+    }
+
+    /// Add `hash-N` subnodes for the requested algorithms, followed by a
+    /// `signature-1` node when a signing key is set
+    fn add_hash_nodes(&mut self, component: &ComponentConfig) -> Result<()> {
+        let precomputed = self.hash_values.get(&component.name).cloned();
+        for (i, algo) in component.hashes.iter().enumerate() {
+            let value = match precomputed.as_ref().and_then(|values| values.get(i)) {
+                Some(value) => hex::decode(value),
+                None => hex::decode(algo.calculate(&component.data)),
+            }
+            .map_err(|e| MkImageError::other(e.to_string()))?;
+            self.begin_node(&format!("hash-{}", i + 1))?;
+            self.add_property_data("value", &value)?;
+            self.add_property_string("algo", algo.as_str())?;
+            self.end_node()?;
+        }
+        self.hash_counts
+            .insert(component.name.clone(), component.hashes.len());
+
+        if let Some(key) = self.signing_key.clone() {
+            let value = match self.signature_values.get(&component.name) {
+                Some(value) => value.clone(),
+                None => key.sign(&component.data)?,
+            };
+            self.begin_node("signature-1")?;
+            self.add_property_data("value", &value)?;
+            self.add_property_string("algo", key.algorithm().as_str())?;
+            self.add_property_string("key-name-hint", key.name())?;
+            self.add_property_string("signer-name", "fitimage")?;
+            self.add_property_string("signer-version", crate::VERSION)?;
+            self.end_node()?;
+        }
+        Ok(())
+    }
+
+    /// Add a `signature-1` node to configuration `config_name` when a
+    /// signing key is set.
+    ///
+    /// `images` lists the referenced images by property name, as they go
+    /// into `sign-images`. The value is written as a placeholder and signed
+    /// in [`Self::finalize`], once the whole tree is known.
+    fn add_config_signature(
+        &mut self,
+        config_name: &str,
+        images: &[(&str, Vec<String>)],
+    ) -> Result<()> {
+        let Some(key) = self.signing_key.clone() else {
+            return Ok(());
+        };
+
+        // Same order as mkimage: root, configuration, then each image
+        // followed by its hash nodes
+        let mut hashed_nodes = vec!["/".to_string(), format!("/configurations/{config_name}")];
+        let mut sign_images = Vec::new();
+        for (prop, names) in images {
+            if names.is_empty() {
+                continue;
+            }
+            sign_images.push(*prop);
+            for name in names {
+                let hashes = self.hash_counts.get(name).copied().unwrap_or_default();
+                if hashes == 0 {
+                    return Err(MkImageError::signature(format!(
+                        "configuration `{config_name}` cannot be signed: image `{name}` has no hash node"
+                    )));
+                }
+                hashed_nodes.push(format!("/images/{name}"));
+                for i in 1..=hashes {
+                    hashed_nodes.push(format!("/images/{name}/hash-{i}"));
+                }
+            }
+        }
+
+        self.begin_node("signature-1")?;
+        self.add_property_data("value", &vec![0; key.algorithm().signature_len()])?;
+        self.add_property_string("algo", key.algorithm().as_str())?;
+        self.add_property_string("key-name-hint", key.name())?;
+        self.add_property_string_list("sign-images", &sign_images)?;
+        self.writer
+            .property_string_list("hashed-nodes", &hashed_nodes)?;
+        // Size of the strings block, filled in by finalize
+        self.writer.property_cells("hashed-strings", &[0, 0])?;
+        self.add_property_string("signer-name", "fitimage")?;
+        self.add_property_string("signer-version", crate::VERSION)?;
+        self.end_node()?;
+
+        self.config_signatures
+            .push((format!("/configurations/{config_name}/signature-1"), hashed_nodes));
+        Ok(())
+    }
+
     /// Begin a node
     fn begin_node(&mut self, name: &str) -> Result<()> {
-        FdtToken::BeginNode.write_to_buffer(&mut self.struct_buffer);
-        FdtTokenUtils::write_string(&mut self.struct_buffer, name)?;
-        Ok(())
+        self.writer.begin_node(name)
     }
 
     /// End a node
     fn end_node(&mut self) -> Result<()> {
-        FdtToken::EndNode.write_to_buffer(&mut self.struct_buffer);
-        Ok(())
+        self.writer.end_node()
     }
 
     /// Add string property
     fn add_property_string(&mut self, name: &str, value: &str) -> Result<()> {
-        let name_offset = self.string_table.add_string(name);
-
-        FdtToken::Prop.write_to_buffer(&mut self.struct_buffer);
-        FdtTokenUtils::write_prop_header(
-            &mut self.struct_buffer,
-            value.len() as u32 + 1,
-            name_offset,
-        )?;
-        FdtTokenUtils::write_string(&mut self.struct_buffer, value)?;
-        Ok(())
+        self.writer.property_string(name, value)
+    }
+
+    /// Add string list property (NUL-separated strings)
+    fn add_property_string_list(&mut self, name: &str, values: &[&str]) -> Result<()> {
+        self.writer.property_string_list(name, values)
     }
 
     /// Add u32 property
     fn add_property_u32(&mut self, name: &str, value: u32) -> Result<()> {
-        let name_offset = self.string_table.add_string(name);
-
-        FdtToken::Prop.write_to_buffer(&mut self.struct_buffer);
-        FdtTokenUtils::write_prop_header(&mut self.struct_buffer, 4, name_offset)?;
-        self.struct_buffer.extend_from_slice(&value.to_be_bytes());
-        Ok(())
+        self.writer.property_u32(name, value)
     }
 
     /// Add u64 property
     fn add_property_u64(&mut self, name: &str, value: u64) -> Result<()> {
-        let name_offset = self.string_table.add_string(name);
-
-        FdtToken::Prop.write_to_buffer(&mut self.struct_buffer);
-        FdtTokenUtils::write_prop_header(&mut self.struct_buffer, 8, name_offset)?;
-        self.struct_buffer.extend_from_slice(&value.to_be_bytes());
-        Ok(())
+        self.writer.property_u64(name, value)
     }
 
     /// Add data property
     fn add_property_data(&mut self, name: &str, data: &[u8]) -> Result<()> {
-        let name_offset = self.string_table.add_string(name);
-
-        FdtToken::Prop.write_to_buffer(&mut self.struct_buffer);
-        FdtTokenUtils::write_prop_header(&mut self.struct_buffer, data.len() as u32, name_offset)?;
-        FdtTokenUtils::write_prop_data(&mut self.struct_buffer, data)?;
-        Ok(())
+        self.writer.property(name, data)
     }
 
-    /// Finalize and return the complete FDT
-    pub fn finalize(mut self) -> Result<Vec<u8>> {
-        // Calculate all offsets and sizes
-        let header_size = FdtHeader::size() as u32;
-        let mem_rsvmap_size = (self.mem_reserve.len() * MemReserveEntry::size()) as u32;
-        let struct_size = self.struct_buffer.len() as u32;
-        let strings_size = self.string_table.size() as u32;
-
-        // Calculate offsets to match mkimage layout: [Header][Mem Reserve Map][FDT Structure][String Table]
-        let off_dt_struct = header_size + mem_rsvmap_size;
-        let off_mem_rsvmap = header_size; // Memory reserve map comes right after header
-        let off_dt_strings = off_dt_struct + struct_size;
-        let total_size = off_dt_strings + strings_size;
-
-        // Finalize header
-        self.header.finalize(
-            total_size,
-            off_dt_struct,
-            off_dt_strings,
-            off_mem_rsvmap,
-            strings_size,
-            struct_size,
-        );
-
-        // Build final FDT with mkimage-compatible layout
-        let mut result = Vec::with_capacity(total_size as usize);
-
-        // Write header
-        self.header.write_to_buffer(&mut result);
-
-        // Write memory reserve map (comes right after header in mkimage)
-        for entry in &self.mem_reserve {
-            entry.write_to_buffer(&mut result);
+    /// Finalize and return the complete FDT, signing the configurations
+    pub fn finalize(self) -> Result<Vec<u8>> {
+        let mut fdt = self.writer.finish_padded(self.block_size.unwrap_or(1))?;
+        let Some(key) = &self.signing_key else {
+            return Ok(fdt);
+        };
+
+        let strings_size = regions::strings_size(&fdt)?;
+        for (path, hashed_nodes) in &self.config_signatures {
+            let property = |fdt: &[u8], name: &str| {
+                regions::property_range(fdt, path, name)?.ok_or_else(|| {
+                    MkImageError::fit_serialization_error(format!("`{path}` has no `{name}`"))
+                })
+            };
+            // Neither property is part of the signed regions
+            let cells = property(&fdt, "hashed-strings")?;
+            fdt[cells.start + 4..cells.end].copy_from_slice(&(strings_size as u32).to_be_bytes());
+            let nodes: Vec<&str> = hashed_nodes.iter().map(String::as_str).collect();
+            let signature = key.sign(&regions::signed_data(&fdt, &nodes, strings_size)?)?;
+            let value = property(&fdt, "value")?;
+            fdt[value].copy_from_slice(&signature);
         }
-
-        // Write structure block
-        result.extend_from_slice(&self.struct_buffer);
-
-        // Write strings block
-        result.extend_from_slice(self.string_table.data());
-
-        Ok(result)
+        Ok(fdt)
     }
 }
 
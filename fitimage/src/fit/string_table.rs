@@ -5,6 +5,7 @@
 use std::collections::HashMap;
 
 /// FDT string table manager
+#[derive(Debug, Clone)]
 pub struct StringTable {
     /// Storage for all unique strings
     strings: Vec<u8>,
@@ -1,6 +1,6 @@
 //! Hash calculation utilities for FIT image components
 //!
-//! Provides MD5, SHA1, and CRC32 hash calculations compatible with U-Boot's FIT image format.
+//! Provides MD5, SHA1, SHA256 and CRC32 hash calculations compatible with U-Boot's FIT image format.
 
 use crate::crc::calculate_crc32;
 
@@ -17,18 +17,25 @@ pub fn calculate_sha1(data: &[u8]) -> String {
     format!("{:x}", hasher.finalize())
 }
 
+/// Calculate SHA256 hash for data
+pub fn calculate_sha256(data: &[u8]) -> String {
+    hex::encode(ring::digest::digest(&ring::digest::SHA256, data))
+}
+
 /// Calculate CRC32 hash for data
 pub fn calculate_crc32_hash(data: &[u8]) -> String {
     format!("{:08x}", calculate_crc32(data))
 }
 
 /// Hash algorithm types supported by U-Boot FIT images
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum HashAlgorithm {
     /// MD5 hash algorithm
     Md5,
     /// SHA1 hash algorithm
     Sha1,
+    /// SHA256 hash algorithm
+    Sha256,
     /// CRC32 hash algorithm
     Crc32,
 }
@@ -39,15 +46,28 @@ impl HashAlgorithm {
         match self {
             HashAlgorithm::Md5 => "md5",
             HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Sha256 => "sha256",
             HashAlgorithm::Crc32 => "crc32",
         }
     }
 
+    /// Parse an `algo` property value from a FIT hash node
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "md5" => Some(HashAlgorithm::Md5),
+            "sha1" => Some(HashAlgorithm::Sha1),
+            "sha256" => Some(HashAlgorithm::Sha256),
+            "crc32" => Some(HashAlgorithm::Crc32),
+            _ => None,
+        }
+    }
+
     /// Calculate hash using this algorithm
     pub fn calculate(&self, data: &[u8]) -> String {
         match self {
             HashAlgorithm::Md5 => calculate_md5(data),
             HashAlgorithm::Sha1 => calculate_sha1(data),
+            HashAlgorithm::Sha256 => calculate_sha256(data),
             HashAlgorithm::Crc32 => calculate_crc32_hash(data),
         }
     }
@@ -118,6 +138,19 @@ mod tests {
         assert_eq!(sha1_hash, expected);
     }
 
+    #[test]
+    fn test_sha256_calculation() {
+        let data = b"Hello, World!";
+        assert_eq!(
+            calculate_sha256(data),
+            "dffd6021bb2bd5b0af676290809ec3a53191dd81c7f70a4b28688a362182986f"
+        );
+        assert_eq!(
+            HashAlgorithm::from_name("sha256"),
+            Some(HashAlgorithm::Sha256)
+        );
+    }
+
     #[test]
     fn test_crc32_calculation() {
         let data = b"Hello, World!";
@@ -5,10 +5,18 @@
 //! ## Features
 //!
 //! - Complete FIT image creation functionality
-//! - Support for kernel, FDT (device tree), and ramdisk components
+//! - Support for kernel, FDT (device tree) with overlays, and ramdisk components
 //! - Gzip compression support
-//! - Multiple hash algorithms (MD5, SHA1, CRC32)
+//! - Multiple hash algorithms (MD5, SHA1, SHA256, CRC32)
+//! - RSA/ECDSA image signatures for U-Boot verified boot
 //! - U-Boot compatible device tree structure
+//! - `.its` source generation and parsing for mkimage-style workflows
+//! - FIT image reading, hash verification and component extraction
+//! - Structural comparison of two FIT images
+//! - General-purpose FDT writer for small control DTBs and `/chosen` patches
+//! - Legacy uImage (64-byte header) creation and verification
+//! - U-Boot boot script (`boot.scr`) images, legacy and FIT flavors
+//! - Android boot images (`boot.img`, header v2/v3/v4)
 //!
 //! ## Quick Start
 //!
@@ -36,9 +44,16 @@
 //!
 //! - [`fit`] - Core FIT image building functionality
 //! - [`compression`] - Compression algorithms (gzip)
-//! - [`hash`] - Hash calculation utilities (MD5, SHA1, CRC32)
+//! - [`hash`] - Hash calculation utilities (MD5, SHA1, SHA256, CRC32)
+//! - [`signature`] - Image signing and signature verification
 //! - [`crc`] - CRC32 checksum calculation
 //! - [`error`] - Error types and result definitions
+//! - [`uimage`] - Legacy U-Boot image format
+//! - [`script`] - U-Boot boot script images
+//! - [`android`] - Android boot images
+
+/// Android boot images (`mkbootimg`).
+pub mod android;
 
 /// Compression algorithms support (gzip, etc.)
 pub mod compression;
@@ -52,15 +67,31 @@ pub mod error;
 /// Core FIT image building functionality.
 pub mod fit;
 
-/// Hash calculation utilities (MD5, SHA1, CRC32).
+/// Hash calculation utilities (MD5, SHA1, SHA256, CRC32).
 pub mod hash;
 
+/// U-Boot boot script images (`mkimage -T script`).
+pub mod script;
+
+/// RSA/ECDSA signatures for verified boot.
+pub mod signature;
+
+/// Legacy U-Boot image (uImage) format.
+pub mod uimage;
+
 // Re-export main types for convenience
+pub use android::{AndroidBootImage, BootImageVersion};
 pub use compression::traits::CompressionInterface;
 pub use crc::calculate_crc32;
 pub use error::{MkImageError, Result};
-pub use fit::{ComponentConfig, FitImageBuilder, FitImageConfig};
+pub use fit::{
+    ComponentConfig, FdtWriter, FitImageBuilder, FitImageConfig, FitImageReader, SignatureCheck,
+    SignatureStatus,
+};
 pub use hash::{calculate_hashes, default_hash_algorithms, HashAlgorithm, HashResult};
+pub use script::{ScriptFormat, ScriptImage};
+pub use signature::{KeyRequirement, SignatureAlgorithm, SigningKey, VerifyingKey};
+pub use uimage::{ImageArch, ImageCompression, ImageOs, ImageType, UImageConfig, UImageHeader};
 
 /// Current version of the fitimage implementation
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,210 @@
+//! U-Boot boot script images
+//!
+//! Equivalent of `mkimage -T script`: wraps a plain-text boot script so that
+//! U-Boot's `source` command can run it. Two flavors are supported:
+//!
+//! - [`ScriptFormat::Legacy`] - a legacy image of type `script`, whose payload
+//!   is a multi-file length table (`<len> 0`) followed by the script text
+//! - [`ScriptFormat::Fit`] - a FIT image with a single `script` image that is
+//!   referenced from the default configuration
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{MkImageError, Result};
+use crate::fit::{ComponentConfig, FitImageBuilder, FitImageConfig, FitImageReader};
+use crate::uimage::{verify_uimage, ImageArch, ImageType, UImageConfig};
+
+/// Node name of the script image in FIT scripts
+pub const FIT_SCRIPT_NODE: &str = "script-1";
+
+/// Container format of a boot script image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptFormat {
+    /// Legacy 64-byte header image (`mkimage -T script`)
+    #[default]
+    Legacy,
+    /// FIT image with a `type = "script"` image node
+    Fit,
+}
+
+/// Builder for `boot.scr` style script images.
+#[derive(Debug, Clone)]
+pub struct ScriptImage {
+    /// Image name (legacy) or FIT description
+    pub name: String,
+    /// Script text, one U-Boot command per line
+    pub script: String,
+    /// Output container format
+    pub format: ScriptFormat,
+    /// Architecture recorded in the image header
+    pub arch: ImageArch,
+    /// Creation timestamp for legacy images; `None` uses the current time
+    pub timestamp: Option<u32>,
+}
+
+impl ScriptImage {
+    /// Create a legacy script image from script text.
+    pub fn new(script: impl Into<String>) -> Self {
+        Self {
+            name: "boot script".into(),
+            script: script.into(),
+            format: ScriptFormat::Legacy,
+            arch: ImageArch::Arm64,
+            timestamp: None,
+        }
+    }
+
+    /// Create a script image from a list of commands, one per line.
+    pub fn from_commands<S: AsRef<str>>(commands: &[S]) -> Self {
+        let mut script = String::new();
+        for cmd in commands {
+            script.push_str(cmd.as_ref());
+            script.push('\n');
+        }
+        Self::new(script)
+    }
+
+    /// Set image name
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Set output format
+    pub fn with_format(mut self, format: ScriptFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set architecture
+    pub fn with_arch(mut self, arch: ImageArch) -> Self {
+        self.arch = arch;
+        self
+    }
+
+    /// Set a fixed creation timestamp (legacy format only)
+    pub fn with_timestamp(mut self, timestamp: u32) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Build the script image.
+    pub fn build(&self) -> Result<Vec<u8>> {
+        match self.format {
+            ScriptFormat::Legacy => {
+                let text = self.script.as_bytes();
+                let len = u32::try_from(text.len()).map_err(|_| MkImageError::DataTooLarge {
+                    size: text.len() as u64,
+                    max: u32::MAX as u64,
+                })?;
+                // Multi-file length table: one entry, then a zero terminator
+                let mut payload = Vec::with_capacity(8 + text.len());
+                payload.extend_from_slice(&len.to_be_bytes());
+                payload.extend_from_slice(&0u32.to_be_bytes());
+                payload.extend_from_slice(text);
+
+                let mut config = UImageConfig::new(&self.name, payload)
+                    .with_type(ImageType::Script)
+                    .with_arch(self.arch);
+                if let Some(ts) = self.timestamp {
+                    config = config.with_timestamp(ts);
+                }
+                config.build()
+            }
+            ScriptFormat::Fit => {
+                let config = FitImageConfig::new(&self.name).with_script(
+                    ComponentConfig::new(FIT_SCRIPT_NODE, self.script.clone().into_bytes())
+                        .with_description(&self.name)
+                        .with_arch(self.arch),
+                );
+                FitImageBuilder::new().build(config)
+            }
+        }
+    }
+}
+
+/// Extract the script text from a legacy or FIT script image.
+pub fn read_script(data: &[u8]) -> Result<String> {
+    let text = if data.starts_with(&crate::fit::FDT_MAGIC.to_be_bytes()) {
+        let reader = FitImageReader::parse(data)?;
+        reader.image_data("script")?.to_vec()
+    } else {
+        let (header, payload) = verify_uimage(data)?;
+        if header.image_type != ImageType::Script.code() {
+            return Err(MkImageError::unsupported_image_type(
+                ImageType::from_code(header.image_type)
+                    .map(|t| t.as_str().to_string())
+                    .unwrap_or_else(|| header.image_type.to_string()),
+            ));
+        }
+        let len_bytes: [u8; 4] = payload
+            .get(..4)
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| MkImageError::invalid_image_data("script length table truncated"))?;
+        let len = u32::from_be_bytes(len_bytes) as usize;
+        // Skip the length table up to and including its zero terminator
+        let mut offset = 4;
+        while payload
+            .get(offset..offset + 4)
+            .ok_or_else(|| MkImageError::invalid_image_data("script length table truncated"))?
+            != [0; 4]
+        {
+            offset += 4;
+        }
+        offset += 4;
+        payload
+            .get(offset..offset + len)
+            .ok_or_else(|| MkImageError::invalid_image_data("script data truncated"))?
+            .to_vec()
+    };
+    String::from_utf8(text).map_err(|e| MkImageError::invalid_image_data(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uimage::{UIMAGE_HEADER_SIZE, UIMAGE_MAGIC};
+
+    const SCRIPT: &str = "setenv bootargs console=ttyS0\nbootm ${loadaddr}\n";
+
+    #[test]
+    fn test_legacy_script_layout() {
+        let image = ScriptImage::new(SCRIPT).with_timestamp(0).build().unwrap();
+        assert_eq!(image[0..4], UIMAGE_MAGIC.to_be_bytes());
+        assert_eq!(image[30], ImageType::Script.code());
+
+        let payload = &image[UIMAGE_HEADER_SIZE..];
+        assert_eq!(payload[0..4], (SCRIPT.len() as u32).to_be_bytes());
+        assert_eq!(payload[4..8], [0; 4]);
+        assert_eq!(&payload[8..], SCRIPT.as_bytes());
+
+        assert_eq!(read_script(&image).unwrap(), SCRIPT);
+    }
+
+    #[test]
+    fn test_fit_script_roundtrip() {
+        let image = ScriptImage::from_commands(&["setenv bootargs console=ttyS0", "bootm"])
+            .with_format(ScriptFormat::Fit)
+            .build()
+            .unwrap();
+        let reader = FitImageReader::parse(&image).unwrap();
+        let conf = reader.default_config().unwrap();
+        let conf = reader
+            .configurations()
+            .iter()
+            .find(|c| c.name == conf)
+            .unwrap();
+        assert_eq!(conf.property_str("script"), Some(FIT_SCRIPT_NODE));
+        assert_eq!(
+            read_script(&image).unwrap(),
+            "setenv bootargs console=ttyS0\nbootm\n"
+        );
+    }
+
+    #[test]
+    fn test_read_script_rejects_kernel_image() {
+        let image = UImageConfig::new("kernel", vec![0; 16]).build().unwrap();
+        assert!(read_script(&image).is_err());
+    }
+}
@@ -0,0 +1,627 @@
+//! Signature support for FIT image components
+//!
+//! Signs image payloads and configurations the way U-Boot verified boot
+//! expects (`signature-N` nodes holding a SHA-256 RSA PKCS#1 v1.5 or ECDSA
+//! P-256 signature), verifies such signatures again and adds public keys to
+//! the U-Boot control DTB like `mkimage -K`.
+
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use ring::rand::SystemRandom;
+use ring::signature::{self as ring_sig, EcdsaKeyPair, KeyPair, RsaKeyPair, UnparsedPublicKey};
+
+use crate::error::{MkImageError, Result};
+use crate::fit::{FdtNode, FdtProperty, FdtWriter, FitImageReader};
+
+/// Signature algorithms supported by U-Boot FIT images
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SignatureAlgorithm {
+    /// SHA-256 with a 2048-bit RSA key
+    Rsa2048Sha256,
+    /// SHA-256 with a 4096-bit RSA key
+    Rsa4096Sha256,
+    /// SHA-256 with an ECDSA P-256 key
+    EcdsaP256Sha256,
+}
+
+impl SignatureAlgorithm {
+    /// Get the `algo` property value of the signature node
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignatureAlgorithm::Rsa2048Sha256 => "sha256,rsa2048",
+            SignatureAlgorithm::Rsa4096Sha256 => "sha256,rsa4096",
+            SignatureAlgorithm::EcdsaP256Sha256 => "sha256,ecdsa256",
+        }
+    }
+
+    /// Parse an `algo` property value from a FIT signature node
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sha256,rsa2048" => Some(SignatureAlgorithm::Rsa2048Sha256),
+            "sha256,rsa4096" => Some(SignatureAlgorithm::Rsa4096Sha256),
+            "sha256,ecdsa256" => Some(SignatureAlgorithm::EcdsaP256Sha256),
+            _ => None,
+        }
+    }
+
+    /// Size of a signature value in bytes
+    pub fn signature_len(&self) -> usize {
+        match self {
+            SignatureAlgorithm::Rsa2048Sha256 => 256,
+            SignatureAlgorithm::Rsa4096Sha256 => 512,
+            SignatureAlgorithm::EcdsaP256Sha256 => 64,
+        }
+    }
+}
+
+/// Signatures U-Boot requires a key of the control DTB to verify
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyRequirement {
+    /// Every loaded image must be signed with the key
+    Image,
+    /// The selected configuration must be signed with the key
+    Config,
+}
+
+impl KeyRequirement {
+    /// Get the `required` property value of the key node
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyRequirement::Image => "image",
+            KeyRequirement::Config => "conf",
+        }
+    }
+}
+
+enum KeyPairInner {
+    Rsa(RsaKeyPair),
+    Ecdsa(EcdsaKeyPair),
+}
+
+/// Private key used to sign image payloads
+#[derive(Clone)]
+pub struct SigningKey {
+    algorithm: SignatureAlgorithm,
+    name: String,
+    pair: Arc<KeyPairInner>,
+}
+
+impl SigningKey {
+    /// Load a key from DER.
+    ///
+    /// RSA keys may be PKCS#8 or PKCS#1, ECDSA keys must be PKCS#8
+    /// (`openssl genpkey -algorithm EC -pkeyopt ec_paramgen_curve:P-256`).
+    /// `name` becomes the `key-name-hint` of the signature nodes.
+    pub fn from_der(algorithm: SignatureAlgorithm, name: &str, der: &[u8]) -> Result<Self> {
+        let pair = match algorithm {
+            SignatureAlgorithm::Rsa2048Sha256 | SignatureAlgorithm::Rsa4096Sha256 => {
+                let pair = RsaKeyPair::from_pkcs8(der)
+                    .or_else(|_| RsaKeyPair::from_der(der))
+                    .map_err(|e| MkImageError::signature(format!("invalid RSA key: {e}")))?;
+                let bits = pair.public().modulus_len() * 8;
+                if bits != algorithm.signature_len() * 8 {
+                    return Err(MkImageError::signature(format!(
+                        "{bits}-bit RSA key does not match `{}`",
+                        algorithm.as_str()
+                    )));
+                }
+                KeyPairInner::Rsa(pair)
+            }
+            SignatureAlgorithm::EcdsaP256Sha256 => {
+                let pair = EcdsaKeyPair::from_pkcs8(
+                    &ring_sig::ECDSA_P256_SHA256_FIXED_SIGNING,
+                    der,
+                    &SystemRandom::new(),
+                )
+                .map_err(|e| MkImageError::signature(format!("invalid ECDSA P-256 key: {e}")))?;
+                KeyPairInner::Ecdsa(pair)
+            }
+        };
+        Ok(Self {
+            algorithm,
+            name: name.to_string(),
+            pair: Arc::new(pair),
+        })
+    }
+
+    /// Load a key from PEM (`PRIVATE KEY` or `RSA PRIVATE KEY`).
+    pub fn from_pem(algorithm: SignatureAlgorithm, name: &str, pem: &str) -> Result<Self> {
+        let der = pem_decode(pem, &["PRIVATE KEY", "RSA PRIVATE KEY"])?;
+        Self::from_der(algorithm, name, &der)
+    }
+
+    /// Read a PEM or DER key file, named after the file stem like mkimage's
+    /// `<keydir>/<name>.key`.
+    pub fn open(algorithm: SignatureAlgorithm, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let name = key_name(path);
+        let data = std::fs::read(path)?;
+        match std::str::from_utf8(&data) {
+            Ok(pem) if pem.contains("-----BEGIN") => Self::from_pem(algorithm, &name, pem),
+            _ => Self::from_der(algorithm, &name, &data),
+        }
+    }
+
+    /// Use `name` as `key-name-hint` instead of the loaded name.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Signature algorithm of this key
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        self.algorithm
+    }
+
+    /// Key name written as `key-name-hint`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Sign `data`
+    pub fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let rng = SystemRandom::new();
+        match self.pair.as_ref() {
+            KeyPairInner::Rsa(pair) => {
+                let mut signature = vec![0; pair.public().modulus_len()];
+                pair.sign(&ring_sig::RSA_PKCS1_SHA256, &rng, data, &mut signature)
+                    .map_err(|_| MkImageError::signature("RSA signing failed"))?;
+                Ok(signature)
+            }
+            KeyPairInner::Ecdsa(pair) => pair
+                .sign(&rng, data)
+                .map(|s| s.as_ref().to_vec())
+                .map_err(|_| MkImageError::signature("ECDSA signing failed")),
+        }
+    }
+
+    /// Public half of this key
+    pub fn verifying_key(&self) -> VerifyingKey {
+        let key = match self.pair.as_ref() {
+            KeyPairInner::Rsa(pair) => pair.public_key().as_ref().to_vec(),
+            KeyPairInner::Ecdsa(pair) => pair.public_key().as_ref().to_vec(),
+        };
+        VerifyingKey {
+            algorithm: self.algorithm,
+            name: self.name.clone(),
+            key,
+        }
+    }
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 不输出私钥内容
+        f.debug_struct("SigningKey")
+            .field("algorithm", &self.algorithm)
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Public key used to verify signature nodes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyingKey {
+    algorithm: SignatureAlgorithm,
+    name: String,
+    /// PKCS#1 `RSAPublicKey` or uncompressed EC point
+    key: Vec<u8>,
+}
+
+impl VerifyingKey {
+    /// Load a key from DER, either `SubjectPublicKeyInfo` or a bare PKCS#1
+    /// `RSAPublicKey` / uncompressed EC point.
+    pub fn from_der(algorithm: SignatureAlgorithm, name: &str, der: &[u8]) -> Result<Self> {
+        let key = spki_public_key(der).unwrap_or(der);
+        if key.is_empty() {
+            return Err(MkImageError::signature("empty public key"));
+        }
+        Ok(Self {
+            algorithm,
+            name: name.to_string(),
+            key: key.to_vec(),
+        })
+    }
+
+    /// Load a key from PEM (`PUBLIC KEY` or `RSA PUBLIC KEY`).
+    pub fn from_pem(algorithm: SignatureAlgorithm, name: &str, pem: &str) -> Result<Self> {
+        let der = pem_decode(pem, &["PUBLIC KEY", "RSA PUBLIC KEY"])?;
+        Self::from_der(algorithm, name, &der)
+    }
+
+    /// Read a PEM or DER key file, named after the file stem.
+    pub fn open(algorithm: SignatureAlgorithm, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let name = key_name(path);
+        let data = std::fs::read(path)?;
+        match std::str::from_utf8(&data) {
+            Ok(pem) if pem.contains("-----BEGIN") => Self::from_pem(algorithm, &name, pem),
+            _ => Self::from_der(algorithm, &name, &data),
+        }
+    }
+
+    /// Use `name` to match `key-name-hint` instead of the loaded name.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Signature algorithm of this key
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        self.algorithm
+    }
+
+    /// Key name matched against `key-name-hint`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Check `signature` over `data`
+    pub fn verify(&self, data: &[u8], signature: &[u8]) -> bool {
+        if signature.len() != self.algorithm.signature_len() {
+            return false;
+        }
+        let algorithm: &dyn ring_sig::VerificationAlgorithm = match self.algorithm {
+            SignatureAlgorithm::Rsa2048Sha256 | SignatureAlgorithm::Rsa4096Sha256 => {
+                &ring_sig::RSA_PKCS1_2048_8192_SHA256
+            }
+            SignatureAlgorithm::EcdsaP256Sha256 => &ring_sig::ECDSA_P256_SHA256_FIXED,
+        };
+        UnparsedPublicKey::new(algorithm, &self.key)
+            .verify(data, signature)
+            .is_ok()
+    }
+
+    /// Add this key to a U-Boot control DTB as `/signature/key-<name>`, the
+    /// way `mkimage -K` does; an existing node of that name is replaced.
+    ///
+    /// With `required` set U-Boot refuses images that are not signed with
+    /// this key; without it the key is only used for signatures that name
+    /// it in `key-name-hint`.
+    pub fn add_to_control_dtb(
+        &self,
+        dtb: &[u8],
+        required: Option<KeyRequirement>,
+    ) -> Result<Vec<u8>> {
+        let reader = FitImageReader::parse(dtb)?;
+        let mut root = reader.root().clone();
+
+        let node_name = format!("key-{}", self.name);
+        let mut key_node = FdtNode {
+            name: node_name.clone(),
+            ..Default::default()
+        };
+        let mut add = |name: &str, value: Vec<u8>| {
+            key_node.properties.push(FdtProperty {
+                name: name.to_string(),
+                value,
+            })
+        };
+        match self.algorithm {
+            SignatureAlgorithm::Rsa2048Sha256 | SignatureAlgorithm::Rsa4096Sha256 => {
+                let (modulus, exponent) = rsa_public_numbers(&self.key)
+                    .ok_or_else(|| MkImageError::signature("invalid RSA public key"))?;
+                let n0 = u32::from_be_bytes(modulus[modulus.len() - 4..].try_into().unwrap());
+                add("rsa,num-bits", ((modulus.len() * 8) as u32).to_be_bytes().to_vec());
+                add("rsa,n0-inverse", n0_inverse(n0).to_be_bytes().to_vec());
+                add("rsa,exponent", exponent.to_be_bytes().to_vec());
+                add("rsa,r-squared", r_squared(modulus));
+                add("rsa,modulus", modulus.to_vec());
+            }
+            SignatureAlgorithm::EcdsaP256Sha256 => {
+                // Uncompressed point: 0x04 || X || Y
+                let point = match self.key.split_first() {
+                    Some((4, point)) if point.len() == 64 => point,
+                    _ => return Err(MkImageError::signature("invalid ECDSA P-256 public key")),
+                };
+                add("ecdsa,curve", cstr("prime256v1"));
+                add("ecdsa,x-point", point[..32].to_vec());
+                add("ecdsa,y-point", point[32..].to_vec());
+            }
+        }
+        add("algo", cstr(self.algorithm.as_str()));
+        if let Some(required) = required {
+            add("required", cstr(required.as_str()));
+        }
+        add("key-name-hint", cstr(&self.name));
+
+        let signature = match root.children.iter().position(|c| c.name == "signature") {
+            Some(i) => &mut root.children[i],
+            None => {
+                root.children.push(FdtNode {
+                    name: "signature".to_string(),
+                    ..Default::default()
+                });
+                root.children.last_mut().unwrap()
+            }
+        };
+        signature.children.retain(|c| c.name != node_name);
+        signature.children.push(key_node);
+
+        let mut writer = FdtWriter::new();
+        for entry in reader.mem_reserve()? {
+            writer.add_mem_reserve(entry.address, entry.size)?;
+        }
+        writer.set_boot_cpuid_phys(reader.header().boot_cpuid_phys);
+        write_node(&mut writer, &root)?;
+        writer.finish()
+    }
+}
+
+fn key_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Decode the first PEM block carrying one of `labels`.
+fn pem_decode(pem: &str, labels: &[&str]) -> Result<Vec<u8>> {
+    for label in labels {
+        let begin = format!("-----BEGIN {label}-----");
+        let end = format!("-----END {label}-----");
+        let Some(start) = pem.find(&begin).map(|i| i + begin.len()) else {
+            continue;
+        };
+        let body = pem[start..]
+            .find(&end)
+            .map(|i| &pem[start..start + i])
+            .ok_or_else(|| MkImageError::signature(format!("unterminated PEM `{label}`")))?;
+        return base64_decode(body)
+            .ok_or_else(|| MkImageError::signature(format!("invalid base64 in PEM `{label}`")));
+    }
+    Err(MkImageError::signature(format!(
+        "no PEM block found (expected {})",
+        labels.join(" or ")
+    )))
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ => return None,
+        };
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Some(out)
+}
+
+/// Read one DER TLV, returning its tag, contents and the remaining input.
+fn der_item(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = data.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 || rest.len() < n {
+            return None;
+        }
+        let len = rest[..n]
+            .iter()
+            .fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        (len, &rest[n..])
+    };
+    (rest.len() >= len).then(|| (tag, &rest[..len], &rest[len..]))
+}
+
+/// Modulus (without leading zeros) and public exponent of a PKCS#1
+/// `RSAPublicKey`.
+fn rsa_public_numbers(key: &[u8]) -> Option<(&[u8], u64)> {
+    let (0x30, body, _) = der_item(key)? else {
+        return None;
+    };
+    let (0x02, modulus, rest) = der_item(body)? else {
+        return None;
+    };
+    let (0x02, exponent, _) = der_item(rest)? else {
+        return None;
+    };
+    let modulus = &modulus[modulus.iter().position(|&b| b != 0)?..];
+    let exponent = &exponent[exponent.iter().position(|&b| b != 0)?..];
+    if modulus.len() < 4 || exponent.len() > 8 {
+        return None;
+    }
+    let exponent = exponent.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+    Some((modulus, exponent))
+}
+
+/// `-1 / n0 mod 2^32` for the lowest word `n0` of an odd modulus, as
+/// U-Boot's Montgomery multiplication expects it in `rsa,n0-inverse`.
+fn n0_inverse(n0: u32) -> u32 {
+    // Newton's iteration doubles the number of correct low bits each step
+    let mut inverse = 1u32;
+    for _ in 0..5 {
+        inverse = inverse.wrapping_mul(2u32.wrapping_sub(n0.wrapping_mul(inverse)));
+    }
+    inverse.wrapping_neg()
+}
+
+/// `2^(2 * bits) mod n` as a big-endian number of the modulus' length, for
+/// `rsa,r-squared`.
+fn r_squared(modulus: &[u8]) -> Vec<u8> {
+    let mut r = vec![0u8; modulus.len()];
+    r[modulus.len() - 1] = 1;
+    for _ in 0..modulus.len() * 16 {
+        // r < n, so 2r < 2n needs at most one subtraction
+        let mut carry = 0;
+        for byte in r.iter_mut().rev() {
+            let value = (*byte as u16) << 1 | carry;
+            *byte = value as u8;
+            carry = value >> 8;
+        }
+        if carry != 0 || r.as_slice() >= modulus {
+            let mut borrow = 0;
+            for (byte, &m) in r.iter_mut().zip(modulus).rev() {
+                let value = *byte as i16 - m as i16 - borrow;
+                *byte = value as u8;
+                borrow = (value < 0) as i16;
+            }
+        }
+    }
+    r
+}
+
+/// NUL-terminated string property value.
+fn cstr(value: &str) -> Vec<u8> {
+    let mut bytes = value.as_bytes().to_vec();
+    bytes.push(0);
+    bytes
+}
+
+fn write_node(writer: &mut FdtWriter, node: &FdtNode) -> Result<()> {
+    writer.begin_node(&node.name)?;
+    for property in &node.properties {
+        writer.property(&property.name, &property.value)?;
+    }
+    for child in &node.children {
+        write_node(writer, child)?;
+    }
+    writer.end_node()
+}
+
+/// Extract the key bits of a `SubjectPublicKeyInfo`.
+fn spki_public_key(der: &[u8]) -> Option<&[u8]> {
+    let (0x30, body, _) = der_item(der)? else {
+        return None;
+    };
+    let (0x30, _, rest) = der_item(body)? else {
+        return None;
+    };
+    let (0x03, bits, _) = der_item(rest)? else {
+        return None;
+    };
+    match bits.split_first()? {
+        (0, key) => Some(key),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ecdsa_key(name: &str) -> SigningKey {
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(
+            &ring_sig::ECDSA_P256_SHA256_FIXED_SIGNING,
+            &SystemRandom::new(),
+        )
+        .unwrap();
+        SigningKey::from_der(SignatureAlgorithm::EcdsaP256Sha256, name, pkcs8.as_ref()).unwrap()
+    }
+
+    #[test]
+    fn test_algorithm_names() {
+        for algo in [
+            SignatureAlgorithm::Rsa2048Sha256,
+            SignatureAlgorithm::Rsa4096Sha256,
+            SignatureAlgorithm::EcdsaP256Sha256,
+        ] {
+            assert_eq!(SignatureAlgorithm::from_name(algo.as_str()), Some(algo));
+        }
+        assert_eq!(SignatureAlgorithm::from_name("sha1,rsa2048"), None);
+    }
+
+    #[test]
+    fn test_ecdsa_sign_and_verify() {
+        let key = ecdsa_key("dev");
+        let signature = key.sign(b"kernel").unwrap();
+        assert_eq!(signature.len(), 64);
+
+        let public = key.verifying_key();
+        assert_eq!(public.name(), "dev");
+        assert!(public.verify(b"kernel", &signature));
+        assert!(!public.verify(b"kernel!", &signature));
+        assert!(!ecdsa_key("dev")
+            .verifying_key()
+            .verify(b"kernel", &signature));
+    }
+
+    #[test]
+    fn test_pem_and_spki() {
+        assert_eq!(base64_decode("aGVsbG8=").unwrap(), b"hello");
+        let pem = "-----BEGIN PUBLIC KEY-----\naGVs\nbG8=\n-----END PUBLIC KEY-----\n";
+        assert_eq!(pem_decode(pem, &["PUBLIC KEY"]).unwrap(), b"hello");
+        assert!(pem_decode(pem, &["PRIVATE KEY"]).is_err());
+
+        // SEQUENCE { SEQUENCE { OID }, BIT STRING { 0, key } }
+        let spki = [
+            0x30, 0x0a, 0x30, 0x03, 0x06, 0x01, 0x00, 0x03, 0x03, 0x00, 0xab, 0xcd,
+        ];
+        assert_eq!(spki_public_key(&spki), Some(&[0xab, 0xcd][..]));
+        assert_eq!(spki_public_key(&spki[..8]), None);
+    }
+
+    #[test]
+    fn test_rsa_key_numbers() {
+        // 241 = 0xf1: 2^16 mod 241 = 225
+        assert_eq!(r_squared(&[0xf1]), [0xe1]);
+        for n0 in [0xf1u32, 0x8000_0001, 0xffff_ffff, 0x1234_5677] {
+            assert_eq!(n0.wrapping_mul(n0_inverse(n0)), u32::MAX);
+        }
+
+        // SEQUENCE { INTEGER 0x00c0000001, INTEGER 65537 }
+        let key = [
+            0x30, 0x0c, 0x02, 0x05, 0x00, 0xc0, 0x00, 0x00, 0x01, 0x02, 0x03, 0x01, 0x00, 0x01,
+        ];
+        assert_eq!(
+            rsa_public_numbers(&key),
+            Some((&[0xc0, 0x00, 0x00, 0x01][..], 65537))
+        );
+        assert_eq!(rsa_public_numbers(&key[..8]), None);
+    }
+
+    #[test]
+    fn test_ecdsa_key_in_control_dtb() {
+        let mut dtb = FdtWriter::new();
+        dtb.add_mem_reserve(0x8000_0000, 0x1000).unwrap();
+        dtb.begin_node("").unwrap();
+        dtb.property_string("model", "board").unwrap();
+        dtb.begin_node("signature").unwrap();
+        dtb.begin_node("key-dev").unwrap();
+        dtb.property_string("algo", "stale").unwrap();
+        dtb.end_node().unwrap();
+        dtb.end_node().unwrap();
+        dtb.end_node().unwrap();
+        let dtb = dtb.finish().unwrap();
+
+        let public = ecdsa_key("dev").verifying_key();
+        let dtb = public
+            .add_to_control_dtb(&dtb, Some(KeyRequirement::Config))
+            .unwrap();
+        let reader = FitImageReader::parse(&dtb).unwrap();
+        assert_eq!(reader.root().property_str("model"), Some("board"));
+        assert_eq!(reader.mem_reserve().unwrap().len(), 1);
+        let signature = reader.root().child("signature").unwrap();
+        assert_eq!(signature.children.len(), 1);
+        let key = signature.child("key-dev").unwrap();
+        assert_eq!(key.property_str("algo"), Some("sha256,ecdsa256"));
+        assert_eq!(key.property_str("required"), Some("conf"));
+        assert_eq!(key.property_str("key-name-hint"), Some("dev"));
+        assert_eq!(key.property_str("ecdsa,curve"), Some("prime256v1"));
+        assert_eq!(key.property("ecdsa,x-point").unwrap().len(), 32);
+        assert_eq!(key.property("ecdsa,y-point").unwrap().len(), 32);
+    }
+
+    #[test]
+    fn test_invalid_key() {
+        let err = SigningKey::from_der(SignatureAlgorithm::Rsa2048Sha256, "dev", b"bogus");
+        assert!(err.is_err());
+    }
+}
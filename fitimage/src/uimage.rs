@@ -0,0 +1,482 @@
+//! Legacy U-Boot image (uImage) support
+//!
+//! Produces and parses the classic 64-byte `image_header_t` format consumed
+//! by `bootm` on bootloaders that predate FIT support.
+//!
+//! Layout (all fields big-endian):
+//!
+//! | Offset | Field   | Description                         |
+//! |--------|---------|-------------------------------------|
+//! | 0      | magic   | `0x27051956`                        |
+//! | 4      | hcrc    | CRC32 of the header with hcrc = 0   |
+//! | 8      | time    | Creation timestamp                  |
+//! | 12     | size    | Payload size                        |
+//! | 16     | load    | Load address                        |
+//! | 20     | ep      | Entry point                         |
+//! | 24     | dcrc    | CRC32 of the payload                |
+//! | 28     | os/arch/type/comp | One byte each             |
+//! | 32     | name    | NUL-padded image name (32 bytes)    |
+
+use serde::{Deserialize, Serialize};
+
+use crate::compression::gzip::GzipCompressor;
+use crate::compression::traits::CompressionInterface;
+use crate::crc::calculate_crc32;
+use crate::error::{MkImageError, Result};
+use crate::fit::config::Timestamp;
+
+/// Legacy image magic number
+pub const UIMAGE_MAGIC: u32 = 0x2705_1956;
+
+/// Size of the legacy image header in bytes
+pub const UIMAGE_HEADER_SIZE: usize = 64;
+
+/// Maximum image name length (including the terminating NUL)
+pub const UIMAGE_NAME_LEN: usize = 32;
+
+macro_rules! image_enum {
+    (
+        $(#[$meta:meta])*
+        $name:ident { $($(#[$vmeta:meta])* $variant:ident = $code:literal, $str:literal;)* }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(rename_all = "snake_case")]
+        pub enum $name {
+            $($(#[$vmeta])* $variant,)*
+        }
+
+        impl $name {
+            /// Numeric code stored in the legacy image header.
+            pub fn code(self) -> u8 {
+                match self {
+                    $($name::$variant => $code,)*
+                }
+            }
+
+            /// Look up a value by its header code.
+            pub fn from_code(code: u8) -> Option<Self> {
+                match code {
+                    $($code => Some($name::$variant),)*
+                    _ => None,
+                }
+            }
+
+            /// Name used by mkimage and FIT properties.
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $($name::$variant => $str,)*
+                }
+            }
+
+            /// Parse a mkimage / FIT property name.
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $($str => Some($name::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
+
+image_enum! {
+    /// Operating system codes (`IH_OS_*`)
+    ImageOs {
+        /// Linux kernel
+        Linux = 5, "linux";
+        /// VxWorks
+        Vxworks = 14, "vxworks";
+        /// QNX
+        Qnx = 16, "qnx";
+        /// U-Boot itself
+        UBoot = 17, "u-boot";
+        /// RTEMS
+        Rtems = 18, "rtems";
+        /// ARM Trusted Firmware
+        ArmTrustedFirmware = 25, "arm-trusted-firmware";
+        /// Trusted Execution Environment
+        Tee = 26, "tee";
+        /// RISC-V OpenSBI
+        OpenSbi = 27, "opensbi";
+        /// EFI application
+        Efi = 28, "efi";
+    }
+}
+
+image_enum! {
+    /// CPU architecture codes (`IH_ARCH_*`)
+    ImageArch {
+        /// 32-bit ARM
+        Arm = 2, "arm";
+        /// 32-bit x86
+        X86 = 3, "x86";
+        /// 32-bit MIPS
+        Mips = 5, "mips";
+        /// 64-bit MIPS
+        Mips64 = 6, "mips64";
+        /// PowerPC
+        Ppc = 7, "powerpc";
+        /// Sandbox
+        Sandbox = 19, "sandbox";
+        /// 64-bit ARM
+        Arm64 = 22, "arm64";
+        /// ARC
+        Arc = 23, "arc";
+        /// 64-bit x86
+        X86_64 = 24, "x86_64";
+        /// Xtensa
+        Xtensa = 25, "xtensa";
+        /// RISC-V
+        Riscv = 26, "riscv";
+    }
+}
+
+image_enum! {
+    /// Image type codes (`IH_TYPE_*`)
+    ImageType {
+        /// Standalone program
+        Standalone = 1, "standalone";
+        /// OS kernel
+        Kernel = 2, "kernel";
+        /// Ramdisk
+        Ramdisk = 3, "ramdisk";
+        /// Multi-file image
+        Multi = 4, "multi";
+        /// Firmware
+        Firmware = 5, "firmware";
+        /// Boot script
+        Script = 6, "script";
+        /// Filesystem image
+        Filesystem = 7, "filesystem";
+        /// Flattened device tree
+        FlatDt = 8, "flat_dt";
+        /// Kernel that runs in place
+        KernelNoload = 14, "kernel_noload";
+        /// Trusted Execution Environment (OP-TEE)
+        Tee = 31, "tee";
+    }
+}
+
+image_enum! {
+    /// Compression codes (`IH_COMP_*`)
+    ImageCompression {
+        /// Uncompressed
+        None = 0, "none";
+        /// gzip
+        Gzip = 1, "gzip";
+        /// bzip2
+        Bzip2 = 2, "bzip2";
+        /// LZMA
+        Lzma = 3, "lzma";
+        /// LZO
+        Lzo = 4, "lzo";
+        /// LZ4
+        Lz4 = 5, "lz4";
+        /// Zstandard
+        Zstd = 6, "zstd";
+    }
+}
+
+/// Parsed legacy image header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UImageHeader {
+    /// Header CRC32
+    pub header_crc: u32,
+    /// Creation timestamp (seconds since the Unix epoch)
+    pub timestamp: u32,
+    /// Payload size in bytes
+    pub size: u32,
+    /// Load address
+    pub load_address: u32,
+    /// Entry point
+    pub entry_point: u32,
+    /// Payload CRC32
+    pub data_crc: u32,
+    /// Raw operating system code
+    pub os: u8,
+    /// Raw architecture code
+    pub arch: u8,
+    /// Raw image type code
+    pub image_type: u8,
+    /// Raw compression code
+    pub compression: u8,
+    /// Image name
+    pub name: String,
+}
+
+impl UImageHeader {
+    /// Serialize the header; `header_crc` is recomputed.
+    pub fn to_bytes(&self) -> Result<[u8; UIMAGE_HEADER_SIZE]> {
+        if self.name.len() >= UIMAGE_NAME_LEN {
+            return Err(MkImageError::NameTooLong {
+                len: self.name.len(),
+                max: UIMAGE_NAME_LEN - 1,
+            });
+        }
+
+        let mut buf = [0u8; UIMAGE_HEADER_SIZE];
+        buf[0..4].copy_from_slice(&UIMAGE_MAGIC.to_be_bytes());
+        buf[8..12].copy_from_slice(&self.timestamp.to_be_bytes());
+        buf[12..16].copy_from_slice(&self.size.to_be_bytes());
+        buf[16..20].copy_from_slice(&self.load_address.to_be_bytes());
+        buf[20..24].copy_from_slice(&self.entry_point.to_be_bytes());
+        buf[24..28].copy_from_slice(&self.data_crc.to_be_bytes());
+        buf[28] = self.os;
+        buf[29] = self.arch;
+        buf[30] = self.image_type;
+        buf[31] = self.compression;
+        buf[32..32 + self.name.len()].copy_from_slice(self.name.as_bytes());
+
+        let hcrc = calculate_crc32(&buf);
+        buf[4..8].copy_from_slice(&hcrc.to_be_bytes());
+        Ok(buf)
+    }
+
+    /// Parse a header from the start of `data`, checking magic and header CRC.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < UIMAGE_HEADER_SIZE {
+            return Err(MkImageError::invalid_image_data(
+                "data smaller than uImage header",
+            ));
+        }
+        let word = |off: usize| u32::from_be_bytes(data[off..off + 4].try_into().unwrap());
+
+        let magic = word(0);
+        if magic != UIMAGE_MAGIC {
+            return Err(MkImageError::invalid_magic(UIMAGE_MAGIC, magic));
+        }
+
+        let mut raw = [0u8; UIMAGE_HEADER_SIZE];
+        raw.copy_from_slice(&data[..UIMAGE_HEADER_SIZE]);
+        raw[4..8].fill(0);
+        let header_crc = word(4);
+        let calculated = calculate_crc32(&raw);
+        if header_crc != calculated {
+            return Err(MkImageError::crc_mismatch(header_crc, calculated));
+        }
+
+        let name = &data[32..32 + UIMAGE_NAME_LEN];
+        let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+
+        Ok(Self {
+            header_crc,
+            timestamp: word(8),
+            size: word(12),
+            load_address: word(16),
+            entry_point: word(20),
+            data_crc: word(24),
+            os: data[28],
+            arch: data[29],
+            image_type: data[30],
+            compression: data[31],
+            name: String::from_utf8_lossy(&name[..end]).into_owned(),
+        })
+    }
+}
+
+/// Configuration for building a legacy uImage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UImageConfig {
+    /// Image name (at most 31 bytes)
+    pub name: String,
+
+    /// Payload data (uncompressed)
+    pub data: Vec<u8>,
+
+    /// Operating system
+    pub os: ImageOs,
+
+    /// CPU architecture
+    pub arch: ImageArch,
+
+    /// Image type
+    pub image_type: ImageType,
+
+    /// Whether to gzip-compress the payload before embedding
+    pub compression: bool,
+
+    /// Load address
+    pub load_address: u32,
+
+    /// Entry point, defaults to the load address
+    pub entry_point: Option<u32>,
+
+    /// Creation timestamp, defaults to `SOURCE_DATE_EPOCH` or the current time
+    pub timestamp: Option<u32>,
+}
+
+impl UImageConfig {
+    /// Create a kernel image configuration for Linux on arm64
+    pub fn new(name: impl Into<String>, data: Vec<u8>) -> Self {
+        Self {
+            name: name.into(),
+            data,
+            os: ImageOs::Linux,
+            arch: ImageArch::Arm64,
+            image_type: ImageType::Kernel,
+            compression: false,
+            load_address: 0,
+            entry_point: None,
+            timestamp: None,
+        }
+    }
+
+    /// Set operating system
+    pub fn with_os(mut self, os: ImageOs) -> Self {
+        self.os = os;
+        self
+    }
+
+    /// Set architecture
+    pub fn with_arch(mut self, arch: ImageArch) -> Self {
+        self.arch = arch;
+        self
+    }
+
+    /// Set image type
+    pub fn with_type(mut self, image_type: ImageType) -> Self {
+        self.image_type = image_type;
+        self
+    }
+
+    /// Enable or disable gzip compression of the payload
+    pub fn with_compression(mut self, b: bool) -> Self {
+        self.compression = b;
+        self
+    }
+
+    /// Set load address
+    pub fn with_load_address(mut self, load_address: u32) -> Self {
+        self.load_address = load_address;
+        self
+    }
+
+    /// Set entry point address
+    pub fn with_entry_point(mut self, entry_point: u32) -> Self {
+        self.entry_point = Some(entry_point);
+        self
+    }
+
+    /// Set a fixed creation timestamp
+    pub fn with_timestamp(mut self, timestamp: u32) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Build the image: 64-byte header followed by the payload
+    pub fn build(&self) -> Result<Vec<u8>> {
+        let data = if self.compression {
+            GzipCompressor::default().compress(&self.data)?
+        } else {
+            self.data.clone()
+        };
+        let size = u32::try_from(data.len()).map_err(|_| MkImageError::DataTooLarge {
+            size: data.len() as u64,
+            max: u32::MAX as u64,
+        })?;
+
+        let header = UImageHeader {
+            header_crc: 0,
+            timestamp: self
+                .timestamp
+                .unwrap_or_else(|| Timestamp::default().resolve()),
+            size,
+            load_address: self.load_address,
+            entry_point: self.entry_point.unwrap_or(self.load_address),
+            data_crc: calculate_crc32(&data),
+            os: self.os.code(),
+            arch: self.arch.code(),
+            image_type: self.image_type.code(),
+            compression: if self.compression {
+                ImageCompression::Gzip.code()
+            } else {
+                ImageCompression::None.code()
+            },
+            name: self.name.clone(),
+        };
+
+        let mut out = Vec::with_capacity(UIMAGE_HEADER_SIZE + data.len());
+        out.extend_from_slice(&header.to_bytes()?);
+        out.extend_from_slice(&data);
+        Ok(out)
+    }
+}
+
+/// Parse a legacy image and verify both header and payload CRCs.
+///
+/// Returns the header and a slice of the stored payload.
+pub fn verify_uimage(data: &[u8]) -> Result<(UImageHeader, &[u8])> {
+    let header = UImageHeader::parse(data)?;
+    let payload = data
+        .get(UIMAGE_HEADER_SIZE..UIMAGE_HEADER_SIZE + header.size as usize)
+        .ok_or_else(|| MkImageError::invalid_image_data("uImage payload is truncated"))?;
+    let calculated = calculate_crc32(payload);
+    if calculated != header.data_crc {
+        return Err(MkImageError::crc_mismatch(header.data_crc, calculated));
+    }
+    Ok((header, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uimage_roundtrip() {
+        let image = UImageConfig::new("test kernel", vec![0xaa; 100])
+            .with_arch(ImageArch::Riscv)
+            .with_load_address(0x8020_0000)
+            .with_timestamp(1_700_000_000)
+            .build()
+            .unwrap();
+
+        assert_eq!(image.len(), UIMAGE_HEADER_SIZE + 100);
+        assert_eq!(&image[0..4], &UIMAGE_MAGIC.to_be_bytes());
+
+        let (header, payload) = verify_uimage(&image).unwrap();
+        assert_eq!(header.name, "test kernel");
+        assert_eq!(header.size, 100);
+        assert_eq!(header.load_address, 0x8020_0000);
+        assert_eq!(header.entry_point, 0x8020_0000);
+        assert_eq!(header.timestamp, 1_700_000_000);
+        assert_eq!(ImageArch::from_code(header.arch), Some(ImageArch::Riscv));
+        assert_eq!(ImageOs::from_code(header.os), Some(ImageOs::Linux));
+        assert_eq!(payload, &[0xaa; 100][..]);
+    }
+
+    #[test]
+    fn test_uimage_detects_corruption() {
+        let mut image = UImageConfig::new("k", vec![1, 2, 3]).build().unwrap();
+        image[UIMAGE_HEADER_SIZE] ^= 0xff;
+        assert!(matches!(
+            verify_uimage(&image),
+            Err(MkImageError::CrcMismatch { .. })
+        ));
+
+        image[40] ^= 0xff;
+        assert!(matches!(
+            UImageHeader::parse(&image),
+            Err(MkImageError::CrcMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_uimage_name_too_long() {
+        let err = UImageConfig::new("x".repeat(32), vec![]).build();
+        assert!(matches!(err, Err(MkImageError::NameTooLong { .. })));
+    }
+
+    #[test]
+    fn test_enum_names() {
+        assert_eq!(ImageOs::from_name("u-boot"), Some(ImageOs::UBoot));
+        assert_eq!(ImageType::FlatDt.as_str(), "flat_dt");
+        assert_eq!(ImageCompression::from_code(1), Some(ImageCompression::Gzip));
+    }
+}
@@ -0,0 +1,6 @@
+#!/bin/sh
+# Regenerates signed.itb, a FIT image whose configuration is signed by
+# U-Boot's mkimage, for test_mkimage_signed_configuration.
+set -e
+cd "$(dirname "$0")"
+mkimage -f signed.its -k keys signed.itb
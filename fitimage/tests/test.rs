@@ -1,5 +1,5 @@
 use anyhow::{Context, Result};
-use fitimage::{ComponentConfig, FitImageBuilder, FitImageConfig};
+use fitimage::{ComponentConfig, FitImageBuilder, FitImageConfig, ImageArch, ImageOs, ImageType};
 use std::fs;
 use std::path::Path;
 use std::process::Command;
@@ -109,17 +109,17 @@ fn generate_rust_fit_image(output_path: &Path, kernel_data: &[u8], fdt_data: &[u
         .with_kernel(
             ComponentConfig::new("kernel", kernel_data.to_vec())
                 .with_description("This kernel")
-                .with_type("kernel")
-                .with_arch("arm64")
-                .with_os("linux")
+                .with_type(ImageType::Kernel)
+                .with_arch(ImageArch::Arm64)
+                .with_os(ImageOs::Linux)
                 .with_load_address(0x90100000)
                 .with_entry_point(0x90100000),
         )
         .with_fdt(
             ComponentConfig::new("fdt", fdt_data.to_vec())
                 .with_description("This fdt")
-                .with_type("flat_dt")
-                .with_arch("arm64"),
+                .with_type(ImageType::FlatDt)
+                .with_arch(ImageArch::Arm64),
         )
         .with_default_config("config-ostool")
         .with_configuration(
@@ -303,3 +303,29 @@ fn test_fit_image_basic_functionality() -> Result<()> {
     println!("✅ FIT image 基本功能测试通过");
     Ok(())
 }
+
+/// 解析 tests/test.its 并使用 Rust 实现构建 FIT image
+#[test]
+fn test_build_from_its_source() -> Result<()> {
+    let test_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let config = FitImageConfig::from_its_file(test_dir.join("test.its"))?;
+
+    assert_eq!(
+        config.description,
+        "Various kernels, ramdisks and FDT blobs"
+    );
+    let kernel = config.kernel.as_ref().context("kernel 节点缺失")?;
+    assert_eq!(kernel.data, fs::read(test_dir.join("kernel.txt"))?);
+    assert_eq!(kernel.load_address, Some(0x90100000));
+    assert_eq!(config.default_config.as_deref(), Some("config-ostool"));
+    assert_eq!(
+        config.configurations["config-ostool"].fdt.as_deref(),
+        Some("fdt")
+    );
+
+    let fit_data = FitImageBuilder::new().build(config)?;
+    assert_eq!(fit_data[0..4], [0xd0, 0x0d, 0xfe, 0xed], "设备树魔数不正确");
+
+    println!("✅ ITS 源文件解析测试通过");
+    Ok(())
+}
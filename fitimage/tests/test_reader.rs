@@ -0,0 +1,84 @@
+use std::io::Read;
+
+use fitimage::fit::HashStatus;
+use fitimage::{ComponentConfig, FitImageBuilder, FitImageConfig, FitImageReader, HashAlgorithm};
+
+const TEST_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests");
+
+/// 构建后再解析，检查组件、配置和哈希都能原样读回
+#[test]
+fn test_build_and_read_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    let kernel = std::fs::read(format!("{TEST_DIR}/kernel.txt"))?;
+    let fdt = std::fs::read(format!("{TEST_DIR}/dtb.txt"))?;
+    let ramdisk = std::fs::read(format!("{TEST_DIR}/ramfs.txt"))?;
+
+    let config = FitImageConfig::new("Round trip")
+        .with_kernel(
+            ComponentConfig::new("kernel", kernel.clone())
+                .with_load_address(0x80080000)
+                .with_entry_point(0x80080000)
+                .with_compression(true)
+                .with_hash(HashAlgorithm::Sha256)
+                .with_hash(HashAlgorithm::Crc32),
+        )
+        .with_fdt(ComponentConfig::new("fdt", fdt.clone()).with_hash(HashAlgorithm::Md5))
+        .with_ramdisk(
+            ComponentConfig::new("ramdisk", ramdisk.clone()).with_hash(HashAlgorithm::Sha1),
+        );
+    let fit_data = FitImageBuilder::new().build(config)?;
+
+    let reader = FitImageReader::parse(&fit_data)?;
+    assert_eq!(reader.description(), Some("Round trip"));
+    let names: Vec<_> = reader.images().iter().map(|i| i.name.as_str()).collect();
+    assert_eq!(names, ["kernel", "fdt", "ramdisk"]);
+
+    // 内核以 gzip 存储，解压后与原始数据一致
+    let image = reader.image("kernel").unwrap();
+    assert_eq!(image.property_str("compression"), Some("gzip"));
+    assert_eq!(image.property_u64("load"), Some(0x80080000));
+    let mut decompressed = Vec::new();
+    flate2::read::GzDecoder::new(reader.image_data("kernel")?).read_to_end(&mut decompressed)?;
+    assert_eq!(decompressed, kernel);
+    assert_eq!(reader.image_data("flat_dt")?, fdt.as_slice());
+
+    let out = tempfile::NamedTempFile::new()?;
+    reader.extract("ramdisk", out.path())?;
+    assert_eq!(std::fs::read(out.path())?, ramdisk);
+
+    let conf = &reader.configurations()[0];
+    assert_eq!(reader.default_config(), Some(conf.name.as_str()));
+    assert_eq!(conf.property_str("kernel"), Some("kernel"));
+    assert_eq!(conf.property_str("ramdisk"), Some("ramdisk"));
+
+    let report = reader.verify();
+    assert!(report.is_ok(), "{report:?}");
+    assert_eq!(report.checks.len(), 4);
+    assert!(report
+        .checks
+        .iter()
+        .any(|c| c.algo == "sha256" && c.status == HashStatus::Ok));
+    Ok(())
+}
+
+/// 从 .its 构建的镜像读回时保留源文件中的节点名和默认配置
+#[test]
+fn test_read_image_built_from_its() -> Result<(), Box<dyn std::error::Error>> {
+    let config = FitImageConfig::from_its_file(format!("{TEST_DIR}/test.its"))?;
+    let reader = FitImageReader::parse(&FitImageBuilder::new().build(config)?)?;
+
+    assert_eq!(reader.default_config(), Some("config-ostool"));
+    assert_eq!(
+        reader.image_data("kernel")?,
+        std::fs::read(format!("{TEST_DIR}/kernel.txt"))?.as_slice()
+    );
+    let hashes: Vec<_> = reader
+        .image("kernel")
+        .unwrap()
+        .children
+        .iter()
+        .filter_map(|c| c.property_str("algo"))
+        .collect();
+    assert_eq!(hashes, ["md5", "sha1"]);
+    assert!(reader.verify().is_ok());
+    Ok(())
+}
@@ -0,0 +1,196 @@
+use std::{path::Path, process::Command};
+
+use fitimage::{
+    fit::FdtWriter, ComponentConfig, FitImageBuilder, FitImageConfig, FitImageReader,
+    KeyRequirement, SignatureAlgorithm, SignatureStatus, SigningKey, VerifyingKey,
+};
+
+const KEY_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/keys");
+const TEST_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests");
+
+/// 使用 openssl 生成的 RSA-2048 密钥签名，再用公钥文件验证
+#[test]
+fn test_rsa2048_signed_fit() -> Result<(), Box<dyn std::error::Error>> {
+    let key = SigningKey::open(
+        SignatureAlgorithm::Rsa2048Sha256,
+        format!("{KEY_DIR}/dev.key"),
+    )?;
+    assert_eq!(key.name(), "dev");
+
+    let config = FitImageConfig::new("Signed FIT")
+        .with_kernel(
+            ComponentConfig::new("kernel", b"signed kernel".to_vec())
+                .with_load_address(0x80080000)
+                .with_compression(true),
+        )
+        .with_fdt(ComponentConfig::new("fdt", b"signed fdt".to_vec()));
+    let fit_data = FitImageBuilder::new()
+        .with_signing_key(key.clone())
+        .build(config)?;
+
+    let reader = FitImageReader::parse(&fit_data)?;
+    let node = reader
+        .image("kernel")
+        .unwrap()
+        .child("signature-1")
+        .unwrap();
+    assert_eq!(node.property_str("algo"), Some("sha256,rsa2048"));
+    assert_eq!(node.property_str("key-name-hint"), Some("dev"));
+    assert_eq!(node.property("value").unwrap().len(), 256);
+
+    // 公钥文件与私钥派生的公钥一致
+    let public = VerifyingKey::open(
+        SignatureAlgorithm::Rsa2048Sha256,
+        format!("{KEY_DIR}/dev.pub"),
+    )?;
+    assert_eq!(public, key.verifying_key());
+
+    let checks = reader.verify_signatures(&[public])?;
+    assert_eq!(checks.len(), 3);
+    for check in checks {
+        assert_eq!(check.status, SignatureStatus::Ok("dev".into()), "{check:?}");
+    }
+    Ok(())
+}
+
+/// mkimage 签名的配置能通过本 crate 的验证，与 U-Boot 计算的签名数据一致
+///
+/// 使用 `gen-signed.sh` 生成的 `signed.itb`，尚未生成时直接调用 mkimage
+#[test]
+fn test_mkimage_signed_configuration() -> Result<(), Box<dyn std::error::Error>> {
+    let fixture = Path::new(TEST_DIR).join("signed.itb");
+    let fit_data = if fixture.exists() {
+        std::fs::read(&fixture)?
+    } else {
+        let dir = tempfile::tempdir()?;
+        let output = dir.path().join("signed.itb");
+        let status = Command::new("mkimage")
+            .arg("-f")
+            .arg(Path::new(TEST_DIR).join("signed.its"))
+            .arg("-k")
+            .arg(KEY_DIR)
+            .arg(&output)
+            .status()?;
+        assert!(status.success(), "mkimage failed: {status}");
+        std::fs::read(&output)?
+    };
+
+    let reader = FitImageReader::parse(&fit_data)?;
+    let signature = reader.configurations()[0].child("signature-1").unwrap();
+    assert!(signature.property("hashed-nodes").is_some());
+    let public = VerifyingKey::open(
+        SignatureAlgorithm::Rsa2048Sha256,
+        format!("{KEY_DIR}/dev.pub"),
+    )?;
+    let checks = reader.verify_signatures(&[public])?;
+    assert_eq!(checks.len(), 1);
+    assert!(checks[0].configuration);
+    assert_eq!(checks[0].status, SignatureStatus::Ok("dev".into()));
+    Ok(())
+}
+
+/// 配置签名覆盖配置节点和所引用镜像的哈希节点
+#[test]
+fn test_rsa2048_signed_configuration() -> Result<(), Box<dyn std::error::Error>> {
+    let key = SigningKey::open(
+        SignatureAlgorithm::Rsa2048Sha256,
+        format!("{KEY_DIR}/dev.key"),
+    )?;
+    let config = FitImageConfig::new("Signed FIT")
+        .with_kernel(ComponentConfig::new("kernel", b"signed kernel".to_vec()))
+        .with_fdt(ComponentConfig::new("fdt", b"signed fdt".to_vec()))
+        .with_overlay(ComponentConfig::new("overlay", b"signed overlay".to_vec()));
+    let mut fit_data = FitImageBuilder::new()
+        .with_signing_key(key.clone())
+        .build(config)?;
+
+    let reader = FitImageReader::parse(&fit_data)?;
+    let node = reader.configurations()[0].child("signature-1").unwrap();
+    assert_eq!(node.property_strings("sign-images"), ["kernel", "fdt"]);
+    assert_eq!(
+        node.property_strings("hashed-nodes"),
+        [
+            "/",
+            "/configurations/config-1",
+            "/images/kernel",
+            "/images/kernel/hash-1",
+            "/images/fdt",
+            "/images/fdt/hash-1",
+            "/images/overlay",
+            "/images/overlay/hash-1",
+        ]
+    );
+    assert_eq!(
+        node.property("hashed-strings").unwrap()[4..],
+        reader.header().size_dt_strings.to_be_bytes()
+    );
+    // 未设置哈希的镜像自动加上 SHA-256 哈希
+    let hash = reader.image("kernel").unwrap().child("hash-1").unwrap();
+    assert_eq!(hash.property_str("algo"), Some("sha256"));
+
+    let public = key.verifying_key();
+    let check = |data: &[u8]| -> Result<SignatureStatus, fitimage::MkImageError> {
+        let checks =
+            FitImageReader::parse(data)?.verify_signatures(std::slice::from_ref(&public))?;
+        let check = checks.into_iter().find(|c| c.configuration).unwrap();
+        assert_eq!(check.image, "config-1");
+        Ok(check.status)
+    };
+    assert_eq!(check(&fit_data)?, SignatureStatus::Ok("dev".into()));
+
+    // 篡改哈希值后配置签名失效
+    let value = hash.property("value").unwrap().to_vec();
+    let pos = fit_data
+        .windows(value.len())
+        .position(|w| w == value)
+        .unwrap();
+    fit_data[pos] ^= 0xff;
+    assert_eq!(check(&fit_data)?, SignatureStatus::Invalid);
+    Ok(())
+}
+
+/// 像 `mkimage -K` 一样把公钥写入 U-Boot 控制设备树
+#[test]
+fn test_rsa_key_in_control_dtb() -> Result<(), Box<dyn std::error::Error>> {
+    let public = VerifyingKey::open(
+        SignatureAlgorithm::Rsa2048Sha256,
+        format!("{KEY_DIR}/dev.pub"),
+    )?;
+    let mut dtb = FdtWriter::new();
+    dtb.begin_node("")?;
+    dtb.property_string("model", "board")?;
+    dtb.end_node()?;
+    let dtb = public.add_to_control_dtb(&dtb.finish()?, Some(KeyRequirement::Image))?;
+
+    let reader = FitImageReader::parse(&dtb)?;
+    let key = reader
+        .root()
+        .child("signature")
+        .and_then(|n| n.child("key-dev"))
+        .unwrap();
+    assert_eq!(key.property_str("algo"), Some("sha256,rsa2048"));
+    assert_eq!(key.property_str("required"), Some("image"));
+    assert_eq!(key.property_str("key-name-hint"), Some("dev"));
+    assert_eq!(
+        key.property("rsa,num-bits"),
+        Some(&2048u32.to_be_bytes()[..])
+    );
+    assert_eq!(key.property_u64("rsa,exponent"), Some(65537));
+    let modulus = key.property("rsa,modulus").unwrap();
+    assert_eq!(modulus.len(), 256);
+    assert_eq!(key.property("rsa,r-squared").unwrap().len(), 256);
+    let n0 = u32::from_be_bytes(modulus[252..].try_into()?);
+    let n0_inverse = u32::from_be_bytes(key.property("rsa,n0-inverse").unwrap().try_into()?);
+    assert_eq!(n0.wrapping_mul(n0_inverse), u32::MAX);
+    Ok(())
+}
+
+#[test]
+fn test_rsa_key_size_must_match_algorithm() {
+    let err = SigningKey::open(
+        SignatureAlgorithm::Rsa4096Sha256,
+        format!("{KEY_DIR}/dev.key"),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("2048-bit"), "{err}");
+}
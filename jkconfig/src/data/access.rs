@@ -0,0 +1,447 @@
+use std::fs;
+
+use anyhow::{Context, anyhow, bail};
+use serde_json::Value;
+
+use crate::data::{
+    app_data::{AppData, format_violations},
+    format::ConfigFormat,
+    item::{ItemType, int_to_json, parse_int},
+    menu::MenuRoot,
+    types::ElementType,
+};
+
+impl MenuRoot {
+    /// Map a dotted path as it appears in the config file to an element key.
+    ///
+    /// File paths name the selected OneOf variant explicitly
+    /// (`system.Cargo.features`), while element keys do not
+    /// (`system.features`); the variant segment may be given or omitted.
+    pub fn resolve_path(&self, path: &str) -> anyhow::Result<String> {
+        self.resolve(path).map(|(key, _)| key)
+    }
+
+    /// Resolve `path` to the element key and the full path in the file.
+    fn resolve(&self, path: &str) -> anyhow::Result<(String, Vec<String>)> {
+        let mut key = Vec::new();
+        let mut file_path = Vec::new();
+        let mut current = &self.menu;
+        let mut segments = path.split('.').peekable();
+        while let Some(segment) = segments.next() {
+            // A flattened OneOf has no segment of its own: step into it and
+            // look the segment up again in the selected variant.
+            loop {
+                if let ElementType::OneOf(one_of) = current {
+                    let Some(selected) = one_of.selected() else {
+                        bail!("`{}` has no variant selected", one_of.key());
+                    };
+                    if !one_of.untagged
+                        && one_of.variants.iter().any(|v| v.struct_name == segment)
+                        && selected.struct_name != segment
+                    {
+                        bail!(
+                            "variant `{segment}` of `{}` is not selected (current: `{}`)",
+                            one_of.key(),
+                            selected.struct_name
+                        );
+                    }
+                    if let ElementType::Menu(menu) = selected
+                        && !one_of.untagged
+                    {
+                        file_path.push(menu.struct_name.clone());
+                    }
+                    current = selected;
+                    if !one_of.untagged && selected.struct_name == segment {
+                        break;
+                    }
+                }
+                let ElementType::Menu(menu) = current else {
+                    bail!("`{}` has no field `{segment}`", key.join("."));
+                };
+                if let Some(child) = menu.get_child_by_key(segment) {
+                    key.push(segment.to_string());
+                    file_path.push(segment.to_string());
+                    current = child;
+                    break;
+                }
+                let Some(flattened) = menu.children.iter().find(|child| match child {
+                    ElementType::OneOf(one_of) if one_of.flatten => match one_of.selected() {
+                        Some(ElementType::Menu(m)) if one_of.untagged => {
+                            m.get_child_by_key(segment).is_some()
+                        }
+                        _ => one_of.variants.iter().any(|v| v.struct_name == segment),
+                    },
+                    _ => false,
+                }) else {
+                    bail!("unknown config key `{path}`");
+                };
+                key.push(flattened.field_name());
+                current = flattened;
+            }
+            if segments.peek().is_none() {
+                break;
+            }
+        }
+        Ok((key.join("."), file_path))
+    }
+}
+
+impl AppData {
+    /// Read the value at a dotted config path, `Value::Null` when unset.
+    pub fn get_value(&self, path: &str) -> anyhow::Result<Value> {
+        let (_, file_path) = self.root.resolve(path)?;
+        let mut value = self.root.as_json();
+        for segment in &file_path {
+            match value.get_mut(segment) {
+                Some(v) => value = v.take(),
+                None => return Ok(Value::Null),
+            }
+        }
+        Ok(value)
+    }
+
+    /// Set the value at a dotted config path from its command-line form.
+    ///
+    /// Scalars are parsed according to the schema type; arrays accept a JSON
+    /// array or a comma-separated list; objects and OneOfs take JSON. The new
+    /// value must satisfy the schema constraints, otherwise the tree is left
+    /// unchanged. `readOnly` and `const` items are rejected.
+    pub fn set_value(&mut self, path: &str, raw: &str) -> anyhow::Result<()> {
+        let key = self.root.resolve_path(path)?;
+        let elem = self
+            .root
+            .get_by_key(&key)
+            .ok_or_else(|| anyhow!("unknown config key `{path}`"))?;
+        if let ElementType::Item(item) = elem
+            && item.is_read_only()
+        {
+            bail!("`{path}` is read-only");
+        }
+        let value = parse_raw(elem, raw).with_context(|| format!("invalid value for `{path}`"))?;
+
+        let before = elem.clone();
+        let result = self
+            .edit(&key, |elem| {
+                elem.update_from_value(&value, None)?;
+                if let ElementType::Menu(menu) = elem {
+                    menu.is_set = true;
+                }
+                match elem {
+                    ElementType::Item(item) => item.validate().map_err(|e| anyhow!(e)),
+                    _ => Ok(()),
+                }
+            })
+            .expect("resolved key exists");
+        if let Err(e) = result {
+            if let Some(elem) = self.root.get_mut_by_key(&key) {
+                *elem = before;
+            }
+            return Err(e.context(format!("invalid value for `{path}`")));
+        }
+
+        self.mark_ancestors_set(&key);
+        self.needs_save = true;
+        Ok(())
+    }
+
+    /// Copy the value at a dotted config path to the clipboard.
+    pub fn copy(&mut self, path: &str) -> anyhow::Result<&Value> {
+        let key = self.root.resolve_path(path)?;
+        let elem = self
+            .root
+            .get_by_key(&key)
+            .ok_or_else(|| anyhow!("unknown config key `{path}`"))?;
+        let struct_name = elem.struct_name.clone();
+        let value = self.get_value(path)?;
+        Ok(&self.clipboard.insert((struct_name, value)).1)
+    }
+
+    /// Replace the value at a dotted config path with the clipboard.
+    ///
+    /// The target must have the same type as the copied element. Fields
+    /// missing from the clipboard are reset rather than kept, and the result
+    /// must satisfy the schema, otherwise the tree is left unchanged.
+    pub fn paste(&mut self, path: &str) -> anyhow::Result<()> {
+        let (struct_name, value) = self.clipboard.clone().context("the clipboard is empty")?;
+        let (key, file_path) = self.root.resolve(path)?;
+        let elem = self
+            .root
+            .get_by_key(&key)
+            .ok_or_else(|| anyhow!("unknown config key `{path}`"))?;
+        if let ElementType::Item(item) = elem
+            && item.is_read_only()
+        {
+            bail!("`{path}` is read-only");
+        }
+        if elem.struct_name != struct_name {
+            bail!(
+                "the clipboard holds a `{struct_name}`, `{path}` is a `{}`",
+                elem.struct_name
+            );
+        }
+
+        // Rebuild from the schema so that nothing of the old value survives
+        let mut json = self.root.as_json();
+        let mut slot = &mut json;
+        for segment in &file_path {
+            if !slot.is_object() {
+                *slot = Value::Object(Default::default());
+            }
+            slot = slot
+                .as_object_mut()
+                .expect("just made an object")
+                .entry(segment.clone())
+                .or_insert(Value::Null);
+        }
+        *slot = value;
+        let mut root = MenuRoot::try_from(&self.schema)?;
+        root.update_by_value(&json)
+            .with_context(|| format!("the clipboard does not fit `{path}`"))?;
+        let violations: Vec<_> = root
+            .validate()
+            .into_iter()
+            .filter(|v| v.key == key || v.key.starts_with(&format!("{key}.")))
+            .collect();
+        if !violations.is_empty() {
+            bail!("{}", format_violations(&violations));
+        }
+        let mut pasted = root
+            .get_by_key(&key)
+            .cloned()
+            .ok_or_else(|| anyhow!("the clipboard does not fit `{path}`"))?;
+        if let ElementType::Menu(menu) = &mut pasted {
+            menu.is_set = true;
+        }
+
+        self.edit(&key, |elem| *elem = pasted);
+        self.mark_ancestors_set(&key);
+        self.needs_save = true;
+        Ok(())
+    }
+
+    /// Enclosing optional menus must be set for a value to be written.
+    fn mark_ancestors_set(&mut self, key: &str) {
+        let segments: Vec<&str> = key.split('.').collect();
+        for depth in 1..segments.len() {
+            if let Some(ElementType::Menu(menu)) =
+                self.root.get_mut_by_key(&segments[..depth].join("."))
+            {
+                menu.is_set = true;
+            }
+        }
+    }
+
+    /// Validate the whole tree and write it to the config file, without
+    /// creating a backup.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let violations = self.root.validate();
+        if !violations.is_empty() {
+            bail!("{}", format_violations(&violations));
+        }
+        let original = fs::read_to_string(&self.config).unwrap_or_default();
+        let content = ConfigFormat::from_path(&self.config)?
+            .update_string(&original, &self.root.as_json())?;
+        fs::write(&self.config, content)
+            .with_context(|| format!("Failed to write {}", self.config.display()))?;
+        Ok(())
+    }
+}
+
+/// Convert a command-line value to JSON according to the element type.
+fn parse_raw(elem: &ElementType, raw: &str) -> anyhow::Result<Value> {
+    let ElementType::Item(item) = elem else {
+        return serde_json::from_str(raw).context("expected a JSON value");
+    };
+    Ok(match &item.item_type {
+        ItemType::String { .. } | ItemType::Enum(_) => Value::String(raw.to_string()),
+        ItemType::Integer { .. } => parse_int(raw)
+            .ok()
+            .and_then(int_to_json)
+            .context("expected an integer")?,
+        ItemType::Number { .. } => raw
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number)
+            .context("expected a number")?,
+        ItemType::Boolean { .. } => match raw.trim().to_ascii_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" => Value::Bool(true),
+            "false" | "no" | "off" | "0" => Value::Bool(false),
+            _ => bail!("expected a boolean"),
+        },
+        ItemType::Array(_) => {
+            let raw = raw.trim();
+            if raw.starts_with('[') {
+                serde_json::from_str(raw).context("expected a JSON array")?
+            } else {
+                Value::Array(
+                    raw.split(',')
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .map(|s| Value::String(s.to_string()))
+                        .collect(),
+                )
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schemars::{JsonSchema, schema_for};
+    use serde::{Deserialize, Serialize};
+    use std::path::Path;
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct Config {
+        system: System,
+        #[schemars(range(max = 8))]
+        smp: Option<u32>,
+        debug: Option<Debug>,
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    enum System {
+        Cargo(Cargo),
+        Custom(Custom),
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct Cargo {
+        package: String,
+        features: Vec<String>,
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct Custom {
+        build_cmd: String,
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct Debug {
+        gdb: bool,
+    }
+
+    fn app() -> AppData {
+        let init = r#"{"system": {"Cargo": {"package": "kernel", "features": ["a"]}}}"#;
+        let schema = schema_for!(Config);
+        AppData::new_with_init_and_schema(init, Path::new("c.json"), schema.as_value()).unwrap()
+    }
+
+    #[test]
+    fn test_get_and_set_by_file_path() {
+        let mut app = app();
+        assert_eq!(
+            app.root.resolve_path("system.Cargo.features").unwrap(),
+            "system.features"
+        );
+        assert_eq!(app.get_value("system.Cargo.package").unwrap(), "kernel");
+        assert_eq!(app.get_value("system.package").unwrap(), "kernel");
+        assert_eq!(app.get_value("smp").unwrap(), Value::Null);
+
+        app.set_value("system.Cargo.features", "net, fs").unwrap();
+        assert_eq!(
+            app.get_value("system.Cargo.features").unwrap(),
+            serde_json::json!(["net", "fs"])
+        );
+        app.set_value("smp", "4").unwrap();
+        assert_eq!(app.get_value("smp").unwrap(), 4);
+
+        // Setting a field inside an unset optional menu enables the menu
+        app.set_value("debug.gdb", "yes").unwrap();
+        assert_eq!(
+            app.get_value("debug").unwrap(),
+            serde_json::json!({"gdb": true})
+        );
+        assert!(app.needs_save);
+    }
+
+    #[test]
+    fn test_set_rejects_invalid_values() {
+        let mut app = app();
+        assert!(app.get_value("nope").is_err());
+        assert!(app.set_value("system.Custom.build_cmd", "make").is_err());
+        assert!(app.set_value("smp", "four").is_err());
+        // Out of range values leave the tree unchanged
+        assert!(app.set_value("smp", "16").is_err());
+        assert_eq!(app.get_value("smp").unwrap(), Value::Null);
+        assert!(app.set_value("system.Cargo.package.x", "1").is_err());
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct Flat {
+        #[serde(flatten)]
+        mode: Mode,
+        #[serde(flatten)]
+        source: Source,
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    enum Mode {
+        Fast { jobs: u32 },
+        Slow { delay: u32 },
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    #[serde(untagged)]
+    enum Source {
+        Path { path: String },
+        Url { url: String },
+    }
+
+    #[test]
+    fn test_flattened_paths() {
+        let init = r#"{"Fast": {"jobs": 2}, "path": "/a"}"#;
+        let schema = schema_for!(Flat);
+        let mut app =
+            AppData::new_with_init_and_schema(init, Path::new("f.json"), schema.as_value())
+                .unwrap();
+        assert_eq!(app.get_value("Fast.jobs").unwrap(), 2);
+        assert_eq!(app.get_value("path").unwrap(), "/a");
+        assert!(app.get_value("Slow.delay").is_err());
+
+        app.set_value("Fast.jobs", "8").unwrap();
+        app.set_value("path", "/b").unwrap();
+        assert_eq!(
+            app.root.as_json(),
+            serde_json::json!({"Fast": {"jobs": 8}, "path": "/b"})
+        );
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct Boards {
+        primary: Board,
+        backup: Option<Board>,
+        name: String,
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct Board {
+        uart: String,
+        baud: Option<u32>,
+    }
+
+    #[test]
+    fn test_copy_paste() {
+        let init = r#"{"primary": {"uart": "ttyS0"}, "backup": {"uart": "ttyUSB0", "baud": 9600}, "name": "a"}"#;
+        let schema = schema_for!(Boards);
+        let mut app =
+            AppData::new_with_init_and_schema(init, Path::new("b.json"), schema.as_value())
+                .unwrap();
+        assert!(app.paste("backup").is_err());
+
+        app.copy("primary").unwrap();
+        app.paste("backup").unwrap();
+        // Fields missing from the clipboard are reset
+        assert_eq!(
+            app.get_value("backup").unwrap(),
+            serde_json::json!({"uart": "ttyS0"})
+        );
+        assert!(app.paste("name").is_err());
+
+        app.undo();
+        assert_eq!(app.get_value("backup.baud").unwrap(), 9600);
+    }
+}
@@ -9,7 +9,10 @@ use std::{
 use anyhow::bail;
 use cursive::Cursive;
 
-use crate::data::{menu::MenuRoot, types::ElementType};
+use crate::data::{
+    constraint::Violation, format::ConfigFormat, history::History, menu::MenuRoot,
+    types::ElementType,
+};
 
 /// Callback used to provide the list of available features.
 pub type FeaturesCallback = Arc<dyn Fn() -> Vec<String> + Send + Sync>;
@@ -43,6 +46,14 @@ pub struct AppData {
     pub temp_data: Option<(String, serde_json::Value)>,
     /// Registered element hooks.
     pub elem_hocks: Vec<ElemHock>,
+    /// Undo/redo history of edits.
+    pub history: History,
+    /// The JSON Schema the menu tree was built from.
+    pub schema: serde_json::Value,
+    /// Values as loaded or last saved, to list pending changes against.
+    pub baseline: MenuRoot,
+    /// Struct name and value of the last copied element.
+    pub clipboard: Option<(String, serde_json::Value)>,
 }
 
 const DEFAULT_CONFIG_PATH: &str = ".config.toml";
@@ -108,31 +119,22 @@ impl AppData {
         let mut root = MenuRoot::try_from(schema)?;
 
         if !init.trim().is_empty() {
-            let init_json: serde_json::Value = match init_value_path
-                .extension()
-                .and_then(|s| s.to_str())
-                .unwrap_or("")
-            {
-                "json" => serde_json::from_str(init)?,
-                "toml" => {
-                    let v: toml::Value = toml::from_str(init)?;
-                    serde_json::to_value(v)?
-                }
-                ext => {
-                    bail!("Unsupported config file extension: {ext:?}");
-                }
-            };
+            let init_json = ConfigFormat::from_path(init_value_path)?.parse(init)?;
             root.update_by_value(&init_json)?;
         }
 
         Ok(AppData {
+            baseline: root.clone(),
             root,
+            clipboard: None,
             current_key: Vec::new(),
             needs_save: false,
             config: init_value_path.into(),
             temp_data: None,
             elem_hocks: Vec::new(),
             user_data: HashMap::new(),
+            history: History::default(),
+            schema: schema.clone(),
         })
     }
 
@@ -150,32 +152,23 @@ impl AppData {
         if init_value_path.exists() {
             let init_content = fs::read_to_string(&init_value_path)?;
             if !init_content.trim().is_empty() {
-                let ext = init_value_path
-                    .extension()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("");
-                let init_json: serde_json::Value = match ext {
-                    "json" => serde_json::from_str(&init_content)?,
-                    "toml" => {
-                        let v: toml::Value = toml::from_str(&init_content)?;
-                        serde_json::to_value(v)?
-                    }
-                    _ => {
-                        bail!("Unsupported config file extension: {ext:?}");
-                    }
-                };
+                let init_json = ConfigFormat::from_path(&init_value_path)?.parse(&init_content)?;
                 root.update_by_value(&init_json)?;
             }
         }
 
         Ok(AppData {
+            baseline: root.clone(),
             root,
+            clipboard: None,
             current_key: Vec::new(),
             needs_save: false,
             config: init_value_path,
             temp_data: None,
             elem_hocks: Vec::new(),
             user_data: HashMap::new(),
+            history: History::default(),
+            schema: schema.clone(),
         })
     }
 
@@ -190,17 +183,17 @@ impl AppData {
             .and_then(|s| s.to_str())
             .unwrap_or("");
 
+        let violations = self.root.validate();
+        if !violations.is_empty() {
+            bail!("{}", format_violations(&violations));
+        }
+
         let json_value = self.root.as_json();
 
         println!("value to save:\n {:?}", json_value);
 
-        let s = match ext {
-            "toml" | "tml" => toml::to_string_pretty(&json_value)?,
-            "json" => serde_json::to_string_pretty(&json_value)?,
-            _ => {
-                bail!("Unsupported config file extension: {}", ext);
-            }
-        };
+        let original = fs::read_to_string(&self.config).unwrap_or_default();
+        let s = ConfigFormat::from_path(&self.config)?.update_string(&original, &json_value)?;
 
         if self.config.exists() {
             let bk = format!(
@@ -217,6 +210,15 @@ impl AppData {
         Ok(())
     }
 
+    /// Directory relative paths in the config are resolved against: the
+    /// directory of the config file.
+    pub fn base_dir(&self) -> PathBuf {
+        match self.config.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    }
+
     /// Enter a submenu path (dot-separated).
     pub fn enter(&mut self, key: &str) {
         if key.is_empty() {
@@ -257,6 +259,15 @@ impl AppData {
     }
 }
 
+/// Render constraint violations as a multi-line message.
+pub fn format_violations(violations: &[Violation]) -> String {
+    let mut msg = String::from("Configuration violates schema constraints:");
+    for v in violations {
+        msg.push_str(&format!("\n  - {v}"));
+    }
+    msg
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
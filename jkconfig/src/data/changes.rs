@@ -0,0 +1,135 @@
+use serde_json::Value;
+
+use crate::data::{app_data::AppData, oneof::OneOf, types::ElementType};
+
+/// A value that differs from the last loaded or saved config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingChange {
+    /// Dot-separated key of the changed element.
+    pub key: String,
+    /// Value in the loaded config.
+    pub old: Value,
+    /// Current value.
+    pub new: Value,
+}
+
+impl AppData {
+    /// List every value that differs from the loaded (or last saved) config.
+    ///
+    /// Items report their values, optional menus whether they are set, and
+    /// OneOfs the selected variant name. A switched variant is reported
+    /// once, without the fields below it.
+    pub fn pending_changes(&self) -> Vec<PendingChange> {
+        let mut out = Vec::new();
+        diff(&self.baseline.menu, &self.root.menu, &mut out);
+        out
+    }
+
+    /// Restore the element at `key` to its loaded value, recording the
+    /// change for undo.
+    ///
+    /// For a menu only whether it is set is restored; for a OneOf the whole
+    /// variant is. Returns `false` if `key` does not exist.
+    pub fn revert_change(&mut self, key: &str) -> bool {
+        let Some(before) = self.baseline.get_by_key(key).cloned() else {
+            return false;
+        };
+        self.edit(key, |elem| match (elem, before) {
+            (ElementType::Menu(menu), ElementType::Menu(before)) => menu.is_set = before.is_set,
+            (elem, before) => *elem = before,
+        })
+        .is_some()
+    }
+
+    /// Take the current values as the new baseline after writing them out.
+    pub fn mark_saved(&mut self) {
+        self.baseline = self.root.clone();
+        self.needs_save = false;
+    }
+}
+
+pub(crate) fn diff(old: &ElementType, new: &ElementType, out: &mut Vec<PendingChange>) {
+    let change = |old: Value, new_value: Value| PendingChange {
+        key: new.key(),
+        old,
+        new: new_value,
+    };
+    match (old, new) {
+        (ElementType::Item(a), ElementType::Item(b)) => {
+            let (before, after) = (a.as_json(), b.as_json());
+            if before != after {
+                out.push(change(before, after));
+            }
+        }
+        (ElementType::Menu(a), ElementType::Menu(b)) => {
+            if a.is_set != b.is_set && !b.is_required {
+                out.push(change(Value::Bool(a.is_set), Value::Bool(b.is_set)));
+            }
+            if b.is_none() {
+                return;
+            }
+            for (a, b) in a.children.iter().zip(&b.children) {
+                diff(a, b, out);
+            }
+        }
+        (ElementType::OneOf(a), ElementType::OneOf(b)) => {
+            if a.selected_index != b.selected_index {
+                out.push(change(variant_name(a), variant_name(b)));
+            } else if let (Some(a), Some(b)) = (a.selected(), b.selected()) {
+                diff(a, b, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn variant_name(one_of: &OneOf) -> Value {
+    match one_of.selected_index {
+        Some(idx) => Value::String(one_of.variant_display(idx)),
+        None => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_pending_changes_and_revert() {
+        let schema = serde_json::json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Test",
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"},
+                "level": {"type": "integer"}
+            }
+        });
+        let mut app = AppData::new_with_init_and_schema(
+            r#"{"name": "a", "level": 1}"#,
+            Path::new("a.json"),
+            &schema,
+        )
+        .unwrap();
+        assert!(app.pending_changes().is_empty());
+
+        app.set_value("name", "b").unwrap();
+        app.set_value("level", "2").unwrap();
+        let changes = app.pending_changes();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].old, "a");
+        assert_eq!(changes[0].new, "b");
+
+        assert!(app.revert_change("level"));
+        assert_eq!(app.root.as_json()["level"], 1);
+        assert_eq!(app.pending_changes().len(), 1);
+        // Reverting is itself undoable
+        assert_eq!(app.undo().as_deref(), Some("level"));
+        assert_eq!(app.root.as_json()["level"], 2);
+
+        app.mark_saved();
+        assert!(app.pending_changes().is_empty());
+        assert!(!app.revert_change("missing"));
+    }
+}
@@ -0,0 +1,481 @@
+use std::{fmt, path::Path};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::data::{menu::MenuRoot, types::ElementType};
+
+/// JSON Schema validation keywords attached to an item.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Constraints {
+    /// `minimum` (inclusive).
+    pub minimum: Option<f64>,
+    /// `maximum` (inclusive).
+    pub maximum: Option<f64>,
+    /// `exclusiveMinimum`.
+    pub exclusive_minimum: Option<f64>,
+    /// `exclusiveMaximum`.
+    pub exclusive_maximum: Option<f64>,
+    /// `multipleOf`.
+    pub multiple_of: Option<f64>,
+    /// `minLength` in characters.
+    pub min_length: Option<usize>,
+    /// `maxLength` in characters.
+    pub max_length: Option<usize>,
+    /// `pattern` (unanchored regular expression).
+    pub pattern: Option<String>,
+    /// `minItems`.
+    pub min_items: Option<usize>,
+    /// `maxItems`.
+    pub max_items: Option<usize>,
+    /// `uniqueItems`.
+    pub unique_items: bool,
+    /// The string is a filesystem path (`format: "path"` or `x-path`).
+    pub path: Option<PathKind>,
+    /// `readOnly`: shown for information but not editable.
+    pub read_only: bool,
+    /// `const`: the only allowed value, always written on save.
+    pub const_value: Option<Value>,
+}
+
+/// What a path-typed string must point to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PathKind {
+    /// A file or a directory (`format: "path"`, `x-path: "any"`).
+    Any,
+    /// A regular file (`format: "file-path"`, `x-path: "file"`).
+    File,
+    /// A directory (`format: "dir-path"`, `x-path: "dir"`).
+    Dir,
+}
+
+impl PathKind {
+    fn from_schema(schema: &Value) -> Option<Self> {
+        if let Some(hint) = schema.get("x-path").and_then(Value::as_str) {
+            return match hint {
+                "file" => Some(Self::File),
+                "dir" => Some(Self::Dir),
+                _ => Some(Self::Any),
+            };
+        }
+        match schema.get("format").and_then(Value::as_str)? {
+            "path" => Some(Self::Any),
+            "file-path" => Some(Self::File),
+            "dir-path" => Some(Self::Dir),
+            _ => None,
+        }
+    }
+}
+
+impl Constraints {
+    /// Read the validation keywords from a schema node.
+    pub fn from_schema(schema: &Value) -> Self {
+        let num = |k: &str| schema.get(k).and_then(Value::as_f64);
+        let size = |k: &str| schema.get(k).and_then(Value::as_u64).map(|v| v as usize);
+        Self {
+            minimum: num("minimum"),
+            maximum: num("maximum"),
+            exclusive_minimum: num("exclusiveMinimum"),
+            exclusive_maximum: num("exclusiveMaximum"),
+            multiple_of: num("multipleOf").filter(|m| *m > 0.0),
+            min_length: size("minLength"),
+            max_length: size("maxLength"),
+            pattern: schema
+                .get("pattern")
+                .and_then(Value::as_str)
+                .map(String::from),
+            min_items: size("minItems"),
+            max_items: size("maxItems"),
+            unique_items: schema
+                .get("uniqueItems")
+                .and_then(Value::as_bool)
+                .unwrap_or_default(),
+            path: PathKind::from_schema(schema),
+            read_only: schema
+                .get("readOnly")
+                .and_then(Value::as_bool)
+                .unwrap_or_default(),
+            const_value: schema.get("const").cloned(),
+        }
+    }
+
+    /// Whether no keyword is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Lowest and highest integer the range keywords allow.
+    pub fn int_bounds(&self) -> (Option<i128>, Option<i128>) {
+        let lo = [
+            self.minimum.map(f64::ceil),
+            self.exclusive_minimum.map(|m| m.floor() + 1.0),
+        ]
+        .into_iter()
+        .flatten()
+        .reduce(f64::max);
+        let hi = [
+            self.maximum.map(f64::floor),
+            self.exclusive_maximum.map(|m| m.ceil() - 1.0),
+        ]
+        .into_iter()
+        .flatten()
+        .reduce(f64::min);
+        (lo.map(|v| v as i128), hi.map(|v| v as i128))
+    }
+
+    /// Lowest and highest number the range keywords allow; exclusive bounds
+    /// are returned as they are.
+    pub fn number_bounds(&self) -> (Option<f64>, Option<f64>) {
+        let lo = self.minimum.into_iter().chain(self.exclusive_minimum);
+        let hi = self.maximum.into_iter().chain(self.exclusive_maximum);
+        (lo.reduce(f64::max), hi.reduce(f64::min))
+    }
+
+    /// Check a numeric value against the range and `multipleOf` keywords.
+    pub fn check_number(&self, value: f64) -> Result<(), String> {
+        if let Some(min) = self.minimum
+            && value < min
+        {
+            return Err(format!("{value} is less than the minimum {min}"));
+        }
+        if let Some(max) = self.maximum
+            && value > max
+        {
+            return Err(format!("{value} is greater than the maximum {max}"));
+        }
+        if let Some(min) = self.exclusive_minimum
+            && value <= min
+        {
+            return Err(format!("{value} must be greater than {min}"));
+        }
+        if let Some(max) = self.exclusive_maximum
+            && value >= max
+        {
+            return Err(format!("{value} must be less than {max}"));
+        }
+        if let Some(step) = self.multiple_of {
+            let ratio = value / step;
+            if (ratio - ratio.round()).abs() > 1e-9 {
+                return Err(format!("{value} is not a multiple of {step}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a string against the length and `pattern` keywords.
+    pub fn check_str(&self, value: &str) -> Result<(), String> {
+        let len = value.chars().count();
+        if let Some(min) = self.min_length
+            && len < min
+        {
+            return Err(format!("must be at least {min} characters long"));
+        }
+        if let Some(max) = self.max_length
+            && len > max
+        {
+            return Err(format!("must be at most {max} characters long"));
+        }
+        if let Some(pattern) = &self.pattern {
+            match Regex::new(pattern) {
+                Ok(re) if !re.is_match(value) => {
+                    return Err(format!("does not match pattern `{pattern}`"));
+                }
+                Ok(_) => {}
+                Err(_e) => {
+                    warn!("Ignoring invalid schema pattern `{pattern}`: {_e}");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that a path-typed string exists, relative paths being resolved
+    /// against `base`.
+    ///
+    /// Values with `${...}` placeholders cannot be resolved here and pass.
+    pub fn check_path(&self, value: &str, base: &Path) -> Result<(), String> {
+        let Some(kind) = self.path else {
+            return Ok(());
+        };
+        if value.is_empty() || value.contains("${") {
+            return Ok(());
+        }
+        let path = base.join(value);
+        match kind {
+            PathKind::Any if !path.exists() => Err(format!("`{value}` does not exist")),
+            PathKind::File if !path.is_file() => Err(format!("`{value}` is not a file")),
+            PathKind::Dir if !path.is_dir() => Err(format!("`{value}` is not a directory")),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check an array against the item count and uniqueness keywords.
+    pub fn check_items(&self, values: &[String]) -> Result<(), String> {
+        if let Some(min) = self.min_items
+            && values.len() < min
+        {
+            return Err(format!("needs at least {min} items"));
+        }
+        if let Some(max) = self.max_items
+            && values.len() > max
+        {
+            return Err(format!("allows at most {max} items"));
+        }
+        if self.unique_items {
+            for (i, value) in values.iter().enumerate() {
+                if values[..i].contains(value) {
+                    return Err(format!("duplicate item `{value}`"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Short human-readable summary for hint lines, `None` if unconstrained.
+    pub fn describe(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        let lower = self
+            .minimum
+            .map(|v| format!("{v} ≤ "))
+            .or(self.exclusive_minimum.map(|v| format!("{v} < ")));
+        let upper = self
+            .maximum
+            .map(|v| format!(" ≤ {v}"))
+            .or(self.exclusive_maximum.map(|v| format!(" < {v}")));
+        if lower.is_some() || upper.is_some() {
+            parts.push(format!(
+                "{}x{}",
+                lower.unwrap_or_default(),
+                upper.unwrap_or_default()
+            ));
+        }
+        if let Some(step) = self.multiple_of {
+            parts.push(format!("multiple of {step}"));
+        }
+        match (self.min_length, self.max_length) {
+            (Some(min), Some(max)) => parts.push(format!("length {min}..={max}")),
+            (Some(min), None) => parts.push(format!("length ≥ {min}")),
+            (None, Some(max)) => parts.push(format!("length ≤ {max}")),
+            (None, None) => {}
+        }
+        if let Some(pattern) = &self.pattern {
+            parts.push(format!("pattern {pattern}"));
+        }
+        match (self.min_items, self.max_items) {
+            (Some(min), Some(max)) => parts.push(format!("{min}..={max} items")),
+            (Some(min), None) => parts.push(format!("≥ {min} items")),
+            (None, Some(max)) => parts.push(format!("≤ {max} items")),
+            (None, None) => {}
+        }
+        if self.unique_items {
+            parts.push("unique items".into());
+        }
+        match self.path {
+            Some(PathKind::Any) => parts.push("existing path".into()),
+            Some(PathKind::File) => parts.push("existing file".into()),
+            Some(PathKind::Dir) => parts.push("existing directory".into()),
+            None => {}
+        }
+        if let Some(value) = &self.const_value {
+            parts.push(format!("constant {value}"));
+        } else if self.read_only {
+            parts.push("read-only".into());
+        }
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+/// A value that breaks its schema constraints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Dot-separated key of the offending item.
+    pub key: String,
+    /// What is wrong with the value.
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
+/// Message of the violation reported for unset required values.
+pub const MISSING_REQUIRED: &str = "required value is missing";
+
+impl MenuRoot {
+    /// Check every set value in the tree against its schema constraints.
+    ///
+    /// Required values that are unset are reported as [`MISSING_REQUIRED`],
+    /// since the config would not deserialize without them. Unset optional
+    /// menus and unselected OneOf variants are skipped, since they are not
+    /// serialized.
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        validate_element(&self.menu, &mut violations);
+        violations
+    }
+
+    /// Keys of the required values that are unset, in menu order.
+    pub fn missing_required(&self) -> Vec<String> {
+        missing_required(&self.menu)
+    }
+}
+
+/// Keys of the unset required values in `elem` and below.
+pub fn missing_required(elem: &ElementType) -> Vec<String> {
+    let mut violations = Vec::new();
+    validate_element(elem, &mut violations);
+    violations
+        .into_iter()
+        .filter(|v| v.message == MISSING_REQUIRED)
+        .map(|v| v.key)
+        .collect()
+}
+
+fn validate_element(elem: &ElementType, violations: &mut Vec<Violation>) {
+    if elem.is_missing() {
+        violations.push(Violation {
+            key: elem.key(),
+            message: MISSING_REQUIRED.to_string(),
+        });
+        return;
+    }
+    match elem {
+        ElementType::Menu(menu) => {
+            if menu.is_none() {
+                return;
+            }
+            for child in &menu.children {
+                validate_element(child, violations);
+            }
+        }
+        ElementType::OneOf(one_of) => {
+            if let Some(selected) = one_of.selected() {
+                validate_element(selected, violations);
+            }
+        }
+        ElementType::Item(item) => {
+            if let Err(message) = item.validate() {
+                violations.push(Violation {
+                    key: item.base.key(),
+                    message,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_and_check_numbers() {
+        let c = Constraints::from_schema(&json!({
+            "type": "integer", "minimum": 0, "maximum": 255, "multipleOf": 4
+        }));
+        assert_eq!(c.describe().unwrap(), "0 ≤ x ≤ 255, multiple of 4");
+        assert!(c.check_number(8.0).is_ok());
+        assert!(c.check_number(6.0).is_err());
+        assert!(c.check_number(-4.0).is_err());
+        assert!(c.check_number(256.0).is_err());
+        assert_eq!(c.int_bounds(), (Some(0), Some(255)));
+
+        let c = Constraints::from_schema(&json!({
+            "type": "number", "exclusiveMinimum": 0.5, "maximum": 2.5
+        }));
+        assert_eq!(c.int_bounds(), (Some(1), Some(2)));
+        assert_eq!(c.number_bounds(), (Some(0.5), Some(2.5)));
+    }
+
+    #[test]
+    fn test_check_strings_and_arrays() {
+        let c = Constraints::from_schema(&json!({
+            "minLength": 2, "maxLength": 4, "pattern": "^[a-z]+$",
+            "minItems": 1, "maxItems": 2, "uniqueItems": true
+        }));
+        assert!(c.check_str("abc").is_ok());
+        assert!(c.check_str("a").is_err());
+        assert!(c.check_str("abcde").is_err());
+        assert!(c.check_str("ab1").is_err());
+
+        assert!(c.check_items(&["a".into()]).is_ok());
+        assert!(c.check_items(&[]).is_err());
+        assert!(c.check_items(&["a".into(), "a".into()]).is_err());
+        assert!(
+            c.check_items(&["a".into(), "b".into(), "c".into()])
+                .is_err()
+        );
+        assert!(Constraints::default().describe().is_none());
+    }
+
+    #[test]
+    fn test_missing_required() {
+        let schema = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Test",
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"},
+                "note": {"type": "string"},
+                "debug": {
+                    "type": "object",
+                    "properties": {"port": {"type": "integer"}},
+                    "required": ["port"]
+                }
+            },
+            "required": ["name"]
+        });
+        let mut root = MenuRoot::try_from(&schema).unwrap();
+        // Fields of an unset optional menu are not required
+        assert_eq!(root.missing_required(), ["name"]);
+        assert_eq!(root.validate()[0].message, MISSING_REQUIRED);
+
+        root.update_by_value(&json!({"name": "a"})).unwrap();
+        if let Some(ElementType::Menu(debug)) = root.get_mut_by_key("debug") {
+            debug.is_set = true;
+        }
+        assert_eq!(root.missing_required(), ["debug.port"]);
+        root.update_by_value(&json!({"name": "a", "debug": {"port": 1}}))
+            .unwrap();
+        assert!(root.validate().is_empty());
+    }
+
+    #[test]
+    fn test_path_kind() {
+        let c = Constraints::from_schema(&json!({"type": "string", "format": "file-path"}));
+        assert_eq!(c.path, Some(PathKind::File));
+        assert_eq!(c.describe().unwrap(), "existing file");
+        let c = Constraints::from_schema(&json!({"type": "string", "x-path": "dir"}));
+        assert_eq!(c.path, Some(PathKind::Dir));
+        let c = Constraints::from_schema(&json!({"type": "string", "format": "path"}));
+        assert_eq!(c.path, Some(PathKind::Any));
+        assert!(
+            Constraints::from_schema(&json!({"format": "uri"}))
+                .path
+                .is_none()
+        );
+
+        let base = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let file = Constraints {
+            path: Some(PathKind::File),
+            ..Default::default()
+        };
+        assert!(file.check_path("Cargo.toml", base).is_ok());
+        assert!(file.check_path("src", base).is_err());
+        assert!(file.check_path("missing.bin", base).is_err());
+        // Placeholders are resolved later by the consumer
+        assert!(file.check_path("${workspaceFolder}/x", base).is_ok());
+        let dir = Constraints {
+            path: Some(PathKind::Dir),
+            ..Default::default()
+        };
+        assert!(dir.check_path("src", base).is_ok());
+        assert!(Constraints::default().check_path("missing", base).is_ok());
+    }
+}
@@ -0,0 +1,243 @@
+use log::trace;
+use serde_json::Value;
+
+use crate::data::{
+    app_data::AppData,
+    item::{Item, ItemType},
+    schema::SchemaError,
+    types::ElementType,
+};
+
+impl ItemType {
+    /// Take a schema `default` value as this item's default.
+    pub fn set_default(&mut self, value: &Value, path: &str) -> Result<(), SchemaError> {
+        let mut probe = self.clone();
+        probe.update_from_value(value, path)?;
+        match (self, probe) {
+            (ItemType::String { default, .. }, ItemType::String { value, .. }) => *default = value,
+            (ItemType::Number { default, .. }, ItemType::Number { value, .. }) => *default = value,
+            (ItemType::Integer { default, .. }, ItemType::Integer { value, .. }) => {
+                *default = value
+            }
+            (ItemType::Boolean { default, .. }, ItemType::Boolean { value, .. }) => {
+                *default = value
+            }
+            (ItemType::Enum(item), ItemType::Enum(probe)) => item.default = probe.value,
+            (ItemType::Array(item), ItemType::Array(probe)) => item.default = probe.values,
+            _ => unreachable!("probe has the same type"),
+        }
+        Ok(())
+    }
+
+    /// Replace the value with the default, or unset it when there is none.
+    pub fn reset(&mut self) {
+        match self {
+            ItemType::String { value, default } => *value = default.clone(),
+            ItemType::Number { value, default } => *value = *default,
+            ItemType::Integer { value, default, .. } => *value = *default,
+            ItemType::Boolean { value, default } => *value = *default,
+            ItemType::Enum(item) => item.value = item.default,
+            ItemType::Array(item) => item.values = item.default.clone(),
+        }
+    }
+
+    /// Whether no value has been given. Booleans are never unset.
+    fn is_unset(&self) -> bool {
+        match self {
+            ItemType::String { value, .. } => value.is_none(),
+            ItemType::Number { value, .. } => value.is_none(),
+            ItemType::Integer { value, .. } => value.is_none(),
+            ItemType::Boolean { .. } => false,
+            ItemType::Enum(item) => item.value.is_none(),
+            ItemType::Array(item) => item.values.is_empty(),
+        }
+    }
+}
+
+impl Item {
+    fn reset(&mut self) {
+        // `const` items are pinned to their value
+        if self.constraints.const_value.is_none() {
+            self.item_type.reset();
+        }
+    }
+}
+
+impl ElementType {
+    /// Record a schema `default` value on this element.
+    ///
+    /// Object defaults are spread over the fields, and a OneOf takes the
+    /// variant the default names. Defaults that don't fit are ignored.
+    pub fn apply_default(&mut self, value: &Value) {
+        match self {
+            ElementType::Item(item) => {
+                let path = item.base.key();
+                match item.item_type.set_default(value, &path) {
+                    Ok(()) => {
+                        // Booleans have no unset state: start at the default
+                        if let ItemType::Boolean { value, default } = &mut item.item_type {
+                            *value = *default;
+                        }
+                    }
+                    Err(e) => trace!("Ignoring default of {path}: {e}"),
+                }
+            }
+            ElementType::Menu(menu) => {
+                let Some(obj) = value.as_object() else {
+                    return;
+                };
+                for (key, value) in obj {
+                    if let Some(child) = menu.get_child_mut_by_key(key) {
+                        child.apply_default(value);
+                    }
+                }
+            }
+            ElementType::OneOf(one_of) => {
+                let mut probe = one_of.clone();
+                if probe.update_from_value(value).is_ok() {
+                    one_of.default_index = probe.selected_index;
+                }
+            }
+        }
+    }
+
+    /// Fill every unset value in this element and below with its default.
+    ///
+    /// Unset optional menus stay unset; a OneOf without a selection takes
+    /// its default variant.
+    pub fn fill_defaults(&mut self) {
+        match self {
+            ElementType::Item(item) => {
+                if item.item_type.is_unset() {
+                    item.reset();
+                }
+            }
+            ElementType::Menu(menu) => {
+                if menu.is_none() {
+                    return;
+                }
+                for child in &mut menu.children {
+                    child.fill_defaults();
+                }
+            }
+            ElementType::OneOf(one_of) => {
+                if one_of.selected_index.is_none()
+                    && let Some(idx) = one_of.default_index
+                {
+                    let _ = one_of.set_selected_index(idx);
+                }
+                if let Some(selected) = one_of.selected_mut() {
+                    selected.fill_defaults();
+                }
+            }
+        }
+    }
+
+    /// Reset this element and everything below it to the schema defaults.
+    pub fn reset_to_default(&mut self) {
+        match self {
+            ElementType::Item(item) => item.reset(),
+            ElementType::Menu(menu) => {
+                for child in &mut menu.children {
+                    child.reset_to_default();
+                }
+            }
+            ElementType::OneOf(one_of) => {
+                match one_of.default_index {
+                    Some(idx) => {
+                        let _ = one_of.set_selected_index(idx);
+                    }
+                    None if !one_of.is_required => one_of.selected_index = None,
+                    None => {}
+                }
+                if let Some(selected) = one_of.selected_mut() {
+                    selected.reset_to_default();
+                }
+            }
+        }
+    }
+}
+
+impl AppData {
+    /// Fill every unset value with its schema default and mark the config
+    /// for saving. Returns whether anything changed; undone as one edit.
+    pub fn fill_defaults(&mut self) -> bool {
+        let before = self.root.as_json();
+        self.edit("", ElementType::fill_defaults);
+        let changed = self.root.as_json() != before;
+        if changed {
+            self.needs_save = true;
+        }
+        changed
+    }
+
+    /// Reset the value at a dotted config path to its schema default.
+    pub fn reset_to_default(&mut self, path: &str) -> anyhow::Result<()> {
+        let key = self.root.resolve_path(path)?;
+        self.edit(&key, ElementType::reset_to_default)
+            .ok_or_else(|| anyhow::anyhow!("unknown config key `{path}`"))?;
+        self.needs_save = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn app() -> AppData {
+        let schema = serde_json::json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Test",
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "default": "kernel"},
+                "smp": {"type": "integer", "default": 2},
+                "log": {"type": "string"},
+                "qemu": {
+                    "type": "object",
+                    "properties": {
+                        "graphic": {"type": "boolean"},
+                        "args": {"type": "array", "items": {"type": "string"}}
+                    },
+                    "required": ["graphic", "args"],
+                    "default": {"graphic": true, "args": ["-s"]}
+                }
+            },
+            "required": ["qemu"]
+        });
+        AppData::new_with_init_and_schema(r#"{"smp": 4}"#, Path::new("a.json"), &schema).unwrap()
+    }
+
+    #[test]
+    fn test_fill_defaults() {
+        let mut app = app();
+        assert!(app.fill_defaults());
+        assert!(app.needs_save);
+        assert_eq!(
+            app.root.as_json(),
+            serde_json::json!({
+                "name": "kernel",
+                "smp": 4,
+                "qemu": {"graphic": true, "args": ["-s"]}
+            })
+        );
+        assert!(!app.fill_defaults());
+
+        // The fill is a single undoable edit
+        app.undo();
+        assert!(app.root.as_json().get("name").is_none());
+    }
+
+    #[test]
+    fn test_reset_to_default() {
+        let mut app = app();
+        app.reset_to_default("smp").unwrap();
+        assert_eq!(app.get_value("smp").unwrap(), 2);
+        app.set_value("log", "info").unwrap();
+        app.reset_to_default("log").unwrap();
+        assert_eq!(app.get_value("log").unwrap(), Value::Null);
+        assert!(app.reset_to_default("nope").is_err());
+    }
+}
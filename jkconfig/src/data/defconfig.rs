@@ -0,0 +1,155 @@
+use std::{fs, path::Path};
+
+use anyhow::Context;
+use serde_json::{Map, Value};
+
+use crate::data::{
+    app_data::AppData,
+    changes::{PendingChange, diff},
+    format::ConfigFormat,
+    menu::MenuRoot,
+    types::ElementType,
+};
+
+impl AppData {
+    /// List every value that differs from its schema default, like
+    /// `make savedefconfig`. `old` holds the default, `new` the value.
+    pub fn non_default_values(&self) -> Vec<PendingChange> {
+        let mut out = Vec::new();
+        diff(&self.defaults_tree().menu, &self.root.menu, &mut out);
+        out
+    }
+
+    /// The config reduced to the values that differ from the schema
+    /// defaults. Loading it and filling the defaults gives the config back.
+    pub fn defconfig(&self) -> Value {
+        let defaults = self.defaults_tree().as_json();
+        prune(&self.root.as_json(), &defaults).unwrap_or_else(|| Value::Object(Map::new()))
+    }
+
+    /// Write [`Self::defconfig`] to `path`, in the format of its extension.
+    pub fn save_defconfig(&self, path: &Path) -> anyhow::Result<()> {
+        let content = ConfigFormat::from_path(path)?.to_string(&self.defconfig())?;
+        fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Default location for the defconfig of this config:
+    /// `.build.toml` gives `.build.defconfig.toml`.
+    pub fn defconfig_path(&self) -> std::path::PathBuf {
+        let ext = self
+            .config
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("toml");
+        self.config.with_extension(format!("defconfig.{ext}"))
+    }
+
+    /// The tree with every value at its default and optional menus unset.
+    fn defaults_tree(&self) -> MenuRoot {
+        let mut tree = self.root.clone();
+        unset_optional(&mut tree.menu);
+        tree.menu.reset_to_default();
+        tree
+    }
+}
+
+fn unset_optional(elem: &mut ElementType) {
+    match elem {
+        ElementType::Menu(menu) => {
+            if !menu.is_required {
+                menu.is_set = false;
+            }
+            menu.children.iter_mut().for_each(unset_optional);
+        }
+        ElementType::OneOf(one_of) => one_of.variants.iter_mut().for_each(unset_optional),
+        ElementType::Item(_) => {}
+    }
+}
+
+/// The parts of `current` that differ from `default`.
+fn prune(current: &Value, default: &Value) -> Option<Value> {
+    if current == default {
+        return None;
+    }
+    let (Value::Object(current), Value::Object(default)) = (current, default) else {
+        return Some(current.clone());
+    };
+    let out: Map<String, Value> = current
+        .iter()
+        .filter_map(|(key, value)| match default.get(key) {
+            Some(default) => prune(value, default).map(|v| (key.clone(), v)),
+            None => Some((key.clone(), value.clone())),
+        })
+        .collect();
+    (!out.is_empty()).then_some(Value::Object(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_defconfig() {
+        let schema = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Test",
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "default": "kernel"},
+                "smp": {"type": "integer", "default": 2},
+                "log": {"type": "string"},
+                "qemu": {
+                    "type": "object",
+                    "properties": {
+                        "graphic": {"type": "boolean"},
+                        "args": {"type": "array", "items": {"type": "string"}}
+                    },
+                    "required": ["graphic", "args"],
+                    "default": {"graphic": true, "args": ["-s"]}
+                }
+            },
+            "required": ["name", "smp", "qemu"]
+        });
+        let mut app = AppData::new_with_init_and_schema(
+            r#"{"name": "kernel", "smp": 4, "log": "info", "qemu": {"graphic": true, "args": ["-S"]}}"#,
+            Path::new("/tmp/.build.json"),
+            &schema,
+        )
+        .unwrap();
+
+        let keys: Vec<_> = app
+            .non_default_values()
+            .into_iter()
+            .map(|c| (c.key, c.old, c.new))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                ("smp".into(), json!(2), json!(4)),
+                ("log".into(), Value::Null, json!("info")),
+                ("qemu.args".into(), json!(["-s"]), json!(["-S"])),
+            ]
+        );
+        let defconfig = app.defconfig();
+        assert_eq!(
+            defconfig,
+            json!({"smp": 4, "log": "info", "qemu": {"args": ["-S"]}})
+        );
+        assert_eq!(
+            app.defconfig_path(),
+            Path::new("/tmp/.build.defconfig.json")
+        );
+
+        // Loading the defconfig and filling the defaults gives the config back
+        let full = app.root.as_json();
+        let mut reloaded =
+            AppData::new_with_init_and_schema(&defconfig.to_string(), Path::new("a.json"), &schema)
+                .unwrap();
+        reloaded.fill_defaults();
+        assert_eq!(reloaded.root.as_json(), full);
+
+        app.reset_to_default("smp").unwrap();
+        assert_eq!(app.non_default_values().len(), 2);
+    }
+}
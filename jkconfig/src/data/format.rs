@@ -0,0 +1,83 @@
+use std::path::Path;
+
+use anyhow::bail;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::data::{toml_doc, yaml};
+
+/// Configuration file format, chosen by file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// `.toml` / `.tml`
+    Toml,
+    /// `.json`
+    Json,
+    /// `.yaml` / `.yml`
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Look up the format for a file extension (without the dot).
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "toml" | "tml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Look up the format of a config file path.
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+        match Self::from_extension(ext) {
+            Some(format) => Ok(format),
+            None => bail!("Unsupported config file extension: {ext:?}"),
+        }
+    }
+
+    /// Parse file content into a JSON value.
+    pub fn parse(self, content: &str) -> anyhow::Result<Value> {
+        Ok(match self {
+            Self::Toml => {
+                let v: toml::Value = toml::from_str(content)?;
+                serde_json::to_value(v)?
+            }
+            Self::Json => serde_json::from_str(content)?,
+            Self::Yaml => yaml::from_str(content)?,
+        })
+    }
+
+    /// Parse file content directly into a typed config.
+    pub fn parse_typed<C: DeserializeOwned>(self, content: &str) -> anyhow::Result<C> {
+        Ok(match self {
+            Self::Toml => toml::from_str(content)?,
+            Self::Json => serde_json::from_str(content)?,
+            Self::Yaml => serde_json::from_value(yaml::from_str(content)?)?,
+        })
+    }
+
+    /// Serialize a JSON value in this format.
+    pub fn to_string(self, value: &Value) -> anyhow::Result<String> {
+        Ok(match self {
+            Self::Toml => toml::to_string_pretty(value)?,
+            Self::Json => serde_json::to_string_pretty(value)?,
+            Self::Yaml => yaml::to_string(value),
+        })
+    }
+
+    /// Serialize a JSON value to replace the file content `original`.
+    ///
+    /// TOML keeps the comments, key order and formatting of `original` for
+    /// unchanged values; other formats, and TOML that fails to parse, are
+    /// written from scratch like [`ConfigFormat::to_string`].
+    pub fn update_string(self, original: &str, value: &Value) -> anyhow::Result<String> {
+        match self {
+            Self::Toml if !original.trim().is_empty() => {
+                toml_doc::update(original, value).or_else(|_| self.to_string(value))
+            }
+            _ => self.to_string(value),
+        }
+    }
+}
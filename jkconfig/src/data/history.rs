@@ -0,0 +1,173 @@
+use crate::data::{app_data::AppData, types::ElementType};
+
+/// Maximum number of edits kept for undo.
+pub const HISTORY_LIMIT: usize = 256;
+
+/// One recorded change: the element at `key` before and after the edit.
+#[derive(Debug, Clone)]
+pub struct Edit {
+    /// Dot-separated key of the edited element.
+    pub key: String,
+    /// Element state before the edit.
+    pub before: ElementType,
+    /// Element state after the edit.
+    pub after: ElementType,
+}
+
+/// Undo/redo stacks for the configuration editor.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+}
+
+impl History {
+    /// Record a new edit. Clears the redo stack.
+    pub fn push(&mut self, edit: Edit) {
+        if self.undo.len() == HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+        self.undo.push(edit);
+        self.redo.clear();
+    }
+
+    /// Whether there is an edit to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Whether there is an edit to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Drop all recorded edits.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+impl AppData {
+    /// Apply `f` to the element at `key` and record the change for undo.
+    ///
+    /// Returns `None` if no element exists at `key`. Edits that leave the
+    /// element unchanged are not recorded.
+    pub fn edit<R>(&mut self, key: &str, f: impl FnOnce(&mut ElementType) -> R) -> Option<R> {
+        let elem = self.root.get_mut_by_key(key)?;
+        let before = elem.clone();
+        let result = f(elem);
+        self.record_edit(key, before);
+        Some(result)
+    }
+
+    /// Record that the element at `key` changed from `before` to its current
+    /// state, for callers that mutate the tree directly.
+    pub fn record_edit(&mut self, key: &str, before: ElementType) {
+        let Some(after) = self.root.get_by_key(key) else {
+            return;
+        };
+        if *after == before {
+            return;
+        }
+        let after = after.clone();
+        self.history.push(Edit {
+            key: key.to_string(),
+            before,
+            after,
+        });
+    }
+
+    /// Revert the most recent edit, returning the key it touched.
+    pub fn undo(&mut self) -> Option<String> {
+        let edit = self.history.undo.pop()?;
+        if let Some(elem) = self.root.get_mut_by_key(&edit.key) {
+            *elem = edit.before.clone();
+        }
+        let key = edit.key.clone();
+        self.history.redo.push(edit);
+        Some(key)
+    }
+
+    /// Re-apply the most recently undone edit, returning the key it touched.
+    pub fn redo(&mut self) -> Option<String> {
+        let edit = self.history.redo.pop()?;
+        if let Some(elem) = self.root.get_mut_by_key(&edit.key) {
+            *elem = edit.after.clone();
+        }
+        let key = edit.key.clone();
+        self.history.undo.push(edit);
+        Some(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::item::ItemType;
+    use std::path::Path;
+
+    fn app() -> AppData {
+        let schema = serde_json::json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Test",
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"},
+                "level": {"type": "integer"}
+            }
+        });
+        AppData::new_with_init_and_schema("", Path::new("a.json"), &schema).unwrap()
+    }
+
+    fn set_name(app: &mut AppData, name: &str) {
+        app.edit("name", |elem| {
+            if let ElementType::Item(item) = elem
+                && let ItemType::String { value, .. } = &mut item.item_type
+            {
+                *value = Some(name.to_string());
+            }
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut app = app();
+        set_name(&mut app, "a");
+        set_name(&mut app, "b");
+        // Unchanged edits are not recorded
+        set_name(&mut app, "b");
+        assert_eq!(app.root.as_json()["name"], "b");
+
+        assert_eq!(app.undo().as_deref(), Some("name"));
+        assert_eq!(app.root.as_json()["name"], "a");
+        assert_eq!(app.undo().as_deref(), Some("name"));
+        assert!(app.root.as_json().get("name").is_none());
+        assert!(app.undo().is_none());
+
+        assert_eq!(app.redo().as_deref(), Some("name"));
+        assert_eq!(app.root.as_json()["name"], "a");
+        assert!(app.history.can_redo());
+
+        // A new edit drops the redo stack
+        set_name(&mut app, "c");
+        assert!(!app.history.can_redo());
+        assert!(app.redo().is_none());
+        assert!(app.edit("missing", |_| ()).is_none());
+    }
+
+    #[test]
+    fn test_history_limit() {
+        let mut app = app();
+        for i in 0..HISTORY_LIMIT + 10 {
+            set_name(&mut app, &i.to_string());
+        }
+        let mut undone = 0;
+        while app.undo().is_some() {
+            undone += 1;
+        }
+        assert_eq!(undone, HISTORY_LIMIT);
+        assert_eq!(app.root.as_json()["name"], "9");
+    }
+}
@@ -1,18 +1,20 @@
-use crate::data::{schema::SchemaError, types::ElementBase};
+use crate::data::{constraint::Constraints, schema::SchemaError, types::ElementBase};
 
 use serde_json::Value;
 
 /// Leaf configuration item with a concrete value type.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Item {
     /// Shared element metadata.
     pub base: ElementBase,
     /// Value storage and type information.
     pub item_type: ItemType,
+    /// Validation keywords from the schema.
+    pub constraints: Box<Constraints>,
 }
 
 /// Supported value types for leaf items.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ItemType {
     /// String value with optional default.
     String {
@@ -25,9 +27,13 @@ pub enum ItemType {
         default: Option<f64>,
     },
     /// Integer value with optional default.
+    ///
+    /// Stored as `i128` so that both `i64` and `u64` values (e.g. high
+    /// kernel addresses) round-trip.
     Integer {
-        value: Option<i64>,
-        default: Option<i64>,
+        value: Option<i128>,
+        default: Option<i128>,
+        display: IntDisplay,
     },
     /// Boolean value with default.
     Boolean { value: bool, default: bool },
@@ -37,8 +43,115 @@ pub enum ItemType {
     Array(ArrayItem),
 }
 
+/// Steps a bounded number's range is divided into, by [`Item::step`] and
+/// the slider editor.
+pub const NUMBER_STEPS: u32 = 20;
+
+/// Enums with at most this many variants are picked inline in the menu.
+pub const INLINE_ENUM_MAX: usize = 4;
+
+/// How an integer item is displayed and pre-filled for editing.
+///
+/// Chosen by the `x-display` schema keyword, or a `format` of the same
+/// name. Input always accepts every notation, and the value is serialized as
+/// a plain integer regardless.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IntDisplay {
+    /// `4096`
+    #[default]
+    Decimal,
+    /// `0x1000`, for addresses and masks (`x-display: hex`).
+    Hex,
+    /// `4K`, `512M`, for sizes (`x-display: size`); falls back to decimal
+    /// when the value is not a whole number of units.
+    Size,
+}
+
+const SIZE_UNITS: [(char, i128); 4] = [
+    ('T', 1 << 40),
+    ('G', 1 << 30),
+    ('M', 1 << 20),
+    ('K', 1 << 10),
+];
+
+impl IntDisplay {
+    /// Read the display hint of an integer schema.
+    pub fn from_schema(schema: &Value) -> Self {
+        let hint = schema
+            .get("x-display")
+            .or_else(|| schema.get("format"))
+            .and_then(Value::as_str);
+        match hint {
+            Some("hex") => Self::Hex,
+            Some("size") => Self::Size,
+            _ => Self::Decimal,
+        }
+    }
+
+    /// Format `value` in this notation.
+    pub fn format(self, value: i128) -> String {
+        match self {
+            Self::Decimal => value.to_string(),
+            Self::Hex if value < 0 => format!("-{:#x}", value.unsigned_abs()),
+            Self::Hex => format!("{value:#x}"),
+            Self::Size => SIZE_UNITS
+                .iter()
+                .find(|(_, unit)| value != 0 && value % unit == 0)
+                .map(|(suffix, unit)| format!("{}{suffix}", value / unit))
+                .unwrap_or_else(|| value.to_string()),
+        }
+    }
+}
+
+/// Parse an integer written in decimal, hex (`0x`), octal (`0o`) or binary
+/// (`0b`), or with a binary size suffix (`K`, `M`, `G`, `T`, optionally
+/// followed by `iB`/`B`). `_` separators are ignored.
+pub fn parse_int(input: &str) -> Result<i128, String> {
+    let err = || format!("`{input}` is not an integer");
+    let cleaned: String = input.trim().chars().filter(|&c| c != '_').collect();
+    let (negative, digits) = match cleaned.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, cleaned.strip_prefix('+').unwrap_or(&cleaned)),
+    };
+
+    let radix_prefix = digits.get(..2).map(str::to_ascii_lowercase);
+    let magnitude = match radix_prefix.as_deref() {
+        Some("0x") => i128::from_str_radix(&digits[2..], 16).map_err(|_| err())?,
+        Some("0o") => i128::from_str_radix(&digits[2..], 8).map_err(|_| err())?,
+        Some("0b") => i128::from_str_radix(&digits[2..], 2).map_err(|_| err())?,
+        _ => {
+            let upper = digits.to_ascii_uppercase();
+            let number = upper
+                .strip_suffix("IB")
+                .or_else(|| upper.strip_suffix('B'))
+                .unwrap_or(&upper);
+            let unit = SIZE_UNITS
+                .iter()
+                .find(|(suffix, _)| number.ends_with(*suffix));
+            match unit {
+                Some((_, unit)) => number[..number.len() - 1]
+                    .parse::<i128>()
+                    .ok()
+                    .and_then(|n| n.checked_mul(*unit))
+                    .ok_or_else(err)?,
+                None => upper.parse::<i128>().map_err(|_| err())?,
+            }
+        }
+    };
+    Ok(if negative { -magnitude } else { magnitude })
+}
+
+/// Convert an integer to JSON, if it fits `i64` or `u64`.
+pub fn int_to_json(value: i128) -> Option<Value> {
+    if let Ok(v) = i64::try_from(value) {
+        Some(Value::from(v))
+    } else {
+        u64::try_from(value).ok().map(Value::from)
+    }
+}
+
 /// Array item metadata and values.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct ArrayItem {
     /// Array element type (e.g., "string", "integer")
     pub element_type: String,
@@ -46,10 +159,13 @@ pub struct ArrayItem {
     pub values: Vec<String>,
     /// Default values
     pub default: Vec<String>,
+    /// Validation keywords applied to each element
+    #[serde(default)]
+    pub item_constraints: Box<Constraints>,
 }
 
 /// Enum variants and selected index.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct EnumItem {
     /// List of variant labels.
     pub variants: Vec<String>,
@@ -60,6 +176,11 @@ pub struct EnumItem {
 }
 
 impl EnumItem {
+    /// Whether the variants are shown and picked inline in the menu.
+    pub fn is_inline(&self) -> bool {
+        self.variants.len() <= INLINE_ENUM_MAX
+    }
+
     /// Get the currently selected variant as string, if any.
     pub fn value_str(&self) -> Option<&str> {
         self.value
@@ -158,7 +279,7 @@ impl ItemType {
                 ..
             } => match value {
                 Value::Number(n) => {
-                    if let Some(i) = n.as_i64() {
+                    if let Some(i) = n.as_i64().map(i128::from).or(n.as_u64().map(i128::from)) {
                         *current_value = Some(i);
                         Ok(())
                     } else {
@@ -234,10 +355,7 @@ impl Item {
                 ),
                 None => Value::Null,
             },
-            ItemType::Integer { value, .. } => match value {
-                Some(v) => Value::Number(serde_json::Number::from(*v)),
-                None => Value::Null,
-            },
+            ItemType::Integer { value, .. } => value.and_then(int_to_json).unwrap_or(Value::Null),
             ItemType::Boolean { value, .. } => Value::Bool(*value),
             ItemType::Enum(enum_item) => match enum_item.value_str() {
                 Some(v) => Value::String(v.to_string()),
@@ -269,8 +387,226 @@ impl Item {
     }
 
     /// Update the item from a JSON value.
+    ///
+    /// A `const` item keeps its schema value whatever is loaded.
     pub fn update_from_value(&mut self, value: &Value) -> Result<(), SchemaError> {
         let path = self.base.key();
+        let value = self.constraints.const_value.as_ref().unwrap_or(value);
         self.item_type.update_from_value(value, &path)
     }
+
+    /// Whether the item may not be edited (`readOnly` or `const`).
+    pub fn is_read_only(&self) -> bool {
+        self.constraints.read_only || self.constraints.const_value.is_some()
+    }
+
+    /// Steps the value `delta` steps within the range keywords: an integer
+    /// by `multipleOf` or 1, a number by `multipleOf` or a
+    /// [`NUMBER_STEPS`]th of its range, and an enum to the neighbouring
+    /// variant, wrapping around. An unset value steps from the default.
+    ///
+    /// Returns whether the value changed.
+    pub fn step(&mut self, delta: i32) -> bool {
+        let c = &self.constraints;
+        match &mut self.item_type {
+            ItemType::Integer { value, default, .. } => {
+                let step = c.multiple_of.map_or(1, |m| (m as i128).max(1));
+                let (lo, hi) = c.int_bounds();
+                let next = match value.or(*default) {
+                    Some(v) => v.saturating_add(step * delta as i128),
+                    None => lo.unwrap_or(0),
+                };
+                let next = next
+                    .max(lo.unwrap_or(i128::MIN))
+                    .min(hi.unwrap_or(i128::MAX));
+                let changed = *value != Some(next);
+                *value = Some(next);
+                changed
+            }
+            ItemType::Number { value, default } => {
+                let (lo, hi) = c.number_bounds();
+                let step = match (c.multiple_of, lo, hi) {
+                    (Some(m), _, _) => m,
+                    (None, Some(lo), Some(hi)) if hi > lo => (hi - lo) / NUMBER_STEPS as f64,
+                    _ => 1.0,
+                };
+                let next = match value.or(*default) {
+                    Some(v) => v + step * delta as f64,
+                    None => lo.unwrap_or(0.0),
+                };
+                let next = next.clamp(lo.unwrap_or(f64::MIN), hi.unwrap_or(f64::MAX));
+                if c.check_number(next).is_err() {
+                    return false;
+                }
+                let changed = *value != Some(next);
+                *value = Some(next);
+                changed
+            }
+            ItemType::Enum(e) => {
+                let len = e.variants.len() as i64;
+                if len == 0 {
+                    return false;
+                }
+                let next = match e.value.or(e.default) {
+                    Some(idx) => (idx as i64 + delta as i64).rem_euclid(len) as usize,
+                    None if delta < 0 => len as usize - 1,
+                    None => 0,
+                };
+                let changed = e.value != Some(next);
+                e.value = Some(next);
+                changed
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the value is stepped with ←/→ in the menu: integers and
+    /// numbers with a range keyword, and enums.
+    pub fn is_steppable(&self) -> bool {
+        match &self.item_type {
+            ItemType::Integer { .. } => self.constraints.int_bounds() != (None, None),
+            ItemType::Number { .. } => self.constraints.number_bounds() != (None, None),
+            ItemType::Enum(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Check the current value against the schema constraints.
+    ///
+    /// Unset values pass; whether a value is required is checked elsewhere.
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.item_type {
+            ItemType::String { value: Some(v), .. } => self.constraints.check_str(v),
+            ItemType::Number { value: Some(v), .. } => self.constraints.check_number(*v),
+            ItemType::Integer { value: Some(v), .. } => self.constraints.check_number(*v as f64),
+            ItemType::Array(array) => {
+                self.constraints.check_items(&array.values)?;
+                for (idx, v) in array.values.iter().enumerate() {
+                    array
+                        .check_element(v)
+                        .map_err(|e| format!("item [{idx}] {e}"))?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl ArrayItem {
+    /// Check a single element against the element type and constraints.
+    pub fn check_element(&self, value: &str) -> Result<(), String> {
+        match self.element_type.as_str() {
+            "integer" => {
+                let v = value
+                    .parse::<i64>()
+                    .map_err(|_| format!("`{value}` is not an integer"))?;
+                self.item_constraints.check_number(v as f64)
+            }
+            "number" => {
+                let v = value
+                    .parse::<f64>()
+                    .map_err(|_| format!("`{value}` is not a number"))?;
+                self.item_constraints.check_number(v)
+            }
+            _ => self.item_constraints.check_str(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_int() {
+        assert_eq!(parse_int("4096"), Ok(4096));
+        assert_eq!(parse_int(" 0x8020_0000 "), Ok(0x8020_0000));
+        assert_eq!(parse_int("0XFF"), Ok(255));
+        assert_eq!(parse_int("0o17"), Ok(15));
+        assert_eq!(parse_int("0b101"), Ok(5));
+        assert_eq!(parse_int("-0x10"), Ok(-16));
+        assert_eq!(parse_int("512M"), Ok(512 << 20));
+        assert_eq!(parse_int("4k"), Ok(4096));
+        assert_eq!(parse_int("2GiB"), Ok(2 << 30));
+        assert_eq!(parse_int("1TB"), Ok(1 << 40));
+        assert_eq!(parse_int("0xffff000080000000"), Ok(0xffff_0000_8000_0000));
+        assert!(parse_int("").is_err());
+        assert!(parse_int("0x").is_err());
+        assert!(parse_int("12Q").is_err());
+        assert!(parse_int("1.5M").is_err());
+    }
+
+    #[test]
+    fn test_int_display() {
+        assert_eq!(IntDisplay::Hex.format(0x8020_0000), "0x80200000");
+        assert_eq!(IntDisplay::Hex.format(-16), "-0x10");
+        assert_eq!(IntDisplay::Size.format(512 << 20), "512M");
+        assert_eq!(IntDisplay::Size.format(3 << 10), "3K");
+        assert_eq!(IntDisplay::Size.format(1000), "1000");
+        assert_eq!(IntDisplay::Size.format(0), "0");
+        for v in [0, 1, 4096, 0x8020_0000, 1 << 40, -4096] {
+            for display in [IntDisplay::Decimal, IntDisplay::Hex, IntDisplay::Size] {
+                assert_eq!(parse_int(&display.format(v)), Ok(v));
+            }
+        }
+
+        let hint = serde_json::json!({"type": "integer", "x-display": "hex"});
+        assert_eq!(IntDisplay::from_schema(&hint), IntDisplay::Hex);
+        let hint = serde_json::json!({"type": "integer", "format": "size"});
+        assert_eq!(IntDisplay::from_schema(&hint), IntDisplay::Size);
+        let hint = serde_json::json!({"type": "integer", "format": "uint64"});
+        assert_eq!(IntDisplay::from_schema(&hint), IntDisplay::Decimal);
+    }
+
+    #[test]
+    fn test_step() {
+        let schema = serde_json::json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Test",
+            "type": "object",
+            "properties": {
+                "smp": {"type": "integer", "minimum": 1, "maximum": 8},
+                "ratio": {"type": "number", "minimum": 0, "maximum": 1},
+                "level": {"type": "string", "enum": ["error", "warn", "info"]},
+                "addr": {"type": "integer"}
+            }
+        });
+        let app = crate::data::AppData::new_with_init_and_schema(
+            r#"{"smp": 8, "ratio": 0.5}"#,
+            std::path::Path::new("a.json"),
+            &schema,
+        )
+        .unwrap();
+        let item = |key: &str| match app.root.get_by_key(key) {
+            Some(crate::data::types::ElementType::Item(item)) => item.clone(),
+            _ => panic!("no item {key}"),
+        };
+
+        let mut smp = item("smp");
+        assert!(smp.is_steppable());
+        assert!(!smp.step(1));
+        assert!(smp.step(-3));
+        assert_eq!(smp.as_json(), 5);
+        assert!(smp.step(-100));
+        assert_eq!(smp.as_json(), 1);
+
+        let mut ratio = item("ratio");
+        assert!(ratio.step(2));
+        assert_eq!(ratio.as_json(), 0.6);
+        assert!(ratio.step(100));
+        assert_eq!(ratio.as_json(), 1.0);
+
+        let mut level = item("level");
+        assert!(level.step(-1));
+        assert_eq!(level.as_json(), "info");
+        assert!(level.step(1));
+        assert_eq!(level.as_json(), "error");
+        let ItemType::Enum(e) = &level.item_type else {
+            panic!("not an enum");
+        };
+        assert!(e.is_inline());
+
+        assert!(!item("addr").is_steppable());
+    }
 }
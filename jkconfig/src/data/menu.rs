@@ -85,7 +85,7 @@ impl Debug for MenuRoot {
 }
 
 /// Menu node for schema objects (type: object).
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct Menu {
     /// Shared element metadata.
     pub base: ElementBase,
@@ -189,7 +189,9 @@ impl Menu {
         })?;
         trace!("Updating Menu at {} with value: {:?}", self.key(), value);
         for (key, val) in value {
-            if let Some(element) = self.get_child_mut_by_key(key) {
+            if let Some(element) = self.get_child_mut_by_key(key)
+                && !matches!(element, ElementType::OneOf(one_of) if one_of.flatten)
+            {
                 element.update_from_value(val, None)?;
                 trace!("Updated child {} of Menu at {}", key, self.key());
             }
@@ -197,9 +199,37 @@ impl Menu {
             // If key doesn't exist in menu children, skip it as per requirement
         }
 
+        // Flattened choices read their variant from this object's keys
+        for child in &mut self.children {
+            if let ElementType::OneOf(one_of) = child
+                && one_of.flatten
+            {
+                one_of.update_from_flattened(value)?;
+            }
+        }
+
         Ok(())
     }
 
+    /// Whether `value` has the shape of this menu: every required field is
+    /// present and, when `strict`, every key is a known field. Used to pick
+    /// untagged variants; flattened ones share the object with sibling
+    /// fields and are matched non-strictly.
+    pub fn accepts(&self, value: &serde_json::Map<String, Value>, strict: bool) -> bool {
+        let flattened = !strict
+            || self
+                .children
+                .iter()
+                .any(|c| matches!(c, ElementType::OneOf(one_of) if one_of.flatten));
+        let known = |key: &String| flattened || self.get_child_by_key(key).is_some();
+        value.keys().all(known)
+            && self.children.iter().all(|child| {
+                !child.is_required
+                    || matches!(child, ElementType::OneOf(one_of) if one_of.flatten)
+                    || value.contains_key(&child.field_name())
+            })
+    }
+
     /// Whether this menu is considered unset.
     pub fn is_none(&self) -> bool {
         if self.is_required {
@@ -208,6 +238,13 @@ impl Menu {
         !self.is_set
     }
 
+    /// Number of children that have a value, for menu summaries.
+    ///
+    /// Booleans always count as set.
+    pub fn set_count(&self) -> usize {
+        self.children.iter().filter(|c| !c.is_none()).count()
+    }
+
     /// Return a copy of child elements for UI rendering.
     pub fn fields(&self) -> Vec<ElementType> {
         self.children.to_vec()
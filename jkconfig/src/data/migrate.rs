@@ -0,0 +1,238 @@
+use std::path::Path;
+
+use anyhow::bail;
+use serde_json::{Map, Value};
+
+use crate::data::format::ConfigFormat;
+
+/// Top-level key holding the layout version of a config file.
+pub const VERSION_KEY: &str = "version";
+
+/// A change of the config layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// A field or section moved from one dotted path to another, which
+    /// also covers renames (`serial` → `serial.port`).
+    Move {
+        from: &'static str,
+        to: &'static str,
+    },
+    /// A field that is no longer used.
+    Remove(&'static str),
+}
+
+/// Changes upgrading a config file to `version` from the version before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Migration {
+    pub version: u32,
+    pub changes: &'static [Change],
+}
+
+/// Current layout version for `migrations`, listed oldest first.
+///
+/// Files without a `version` are version 1, the layout before any
+/// migration.
+pub fn current_version(migrations: &[Migration]) -> u32 {
+    migrations.last().map_or(1, |m| m.version)
+}
+
+/// Upgrades `value` to the current layout, returning what changed.
+///
+/// Nothing changes, and `version` is not added, when the file is current.
+///
+/// # Errors
+///
+/// Returns an error if `version` is not a number or newer than the current
+/// version, i.e. the file was written for a newer program.
+pub fn migrate(value: &mut Value, migrations: &[Migration]) -> anyhow::Result<Vec<String>> {
+    let Value::Object(root) = value else {
+        bail!("a config file must be a table");
+    };
+    let version = match root.get(VERSION_KEY) {
+        None => 1,
+        Some(v) => match v.as_u64().and_then(|v| u32::try_from(v).ok()) {
+            Some(v) => v,
+            None => bail!("invalid config `{VERSION_KEY}`: {v}"),
+        },
+    };
+    let current = current_version(migrations);
+    if version > current {
+        bail!("config version {version} is newer than the supported version {current}");
+    }
+
+    let mut report = Vec::new();
+    for migration in migrations.iter().filter(|m| m.version > version) {
+        for change in migration.changes {
+            apply(root, change, &mut report);
+        }
+    }
+    if version < current {
+        root.insert(VERSION_KEY.to_string(), Value::from(current));
+        report.push(format!("{VERSION_KEY} {version} → {current}"));
+    }
+    Ok(report)
+}
+
+/// Upgrades config file `content`, in the format of `path`, returning the
+/// new content and what changed, or `None` if the file is current.
+///
+/// TOML keeps its comments and formatting, see
+/// [`ConfigFormat::update_string`].
+pub fn migrate_content(
+    path: &Path,
+    content: &str,
+    migrations: &[Migration],
+) -> anyhow::Result<Option<(String, Vec<String>)>> {
+    let format = ConfigFormat::from_path(path)?;
+    let mut value = format.parse(content)?;
+    let report = migrate(&mut value, migrations)?;
+    if report.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some((format.update_string(content, &value)?, report)))
+}
+
+fn apply(root: &mut Map<String, Value>, change: &Change, report: &mut Vec<String>) {
+    match *change {
+        Change::Move { from, to } => {
+            let Some(value) = take(root, from) else {
+                return;
+            };
+            if get(root, to).is_some() {
+                report.push(format!("{from}: dropped, {to} is already set"));
+            } else if insert(root, to, value) {
+                report.push(format!("{from} → {to}"));
+            } else {
+                report.push(format!("{from}: dropped, {to} is not a table"));
+            }
+        }
+        Change::Remove(path) => {
+            if take(root, path).is_some() {
+                report.push(format!("{path}: removed"));
+            }
+        }
+    }
+}
+
+fn get<'a>(root: &'a Map<String, Value>, path: &str) -> Option<&'a Value> {
+    let (parent, key) = match path.rsplit_once('.') {
+        Some((parent, key)) => (get(root, parent)?.as_object()?, key),
+        None => (root, path),
+    };
+    parent.get(key)
+}
+
+fn take(root: &mut Map<String, Value>, path: &str) -> Option<Value> {
+    let mut table = root;
+    let mut keys = path.split('.').peekable();
+    while let Some(key) = keys.next() {
+        if keys.peek().is_none() {
+            return table.remove(key);
+        }
+        table = table.get_mut(key)?.as_object_mut()?;
+    }
+    None
+}
+
+/// Inserts `value` at `path`, creating the missing tables; `false` if a
+/// parent is not a table.
+fn insert(root: &mut Map<String, Value>, path: &str, value: Value) -> bool {
+    let mut table = root;
+    let mut keys = path.split('.').peekable();
+    while let Some(key) = keys.next() {
+        if keys.peek().is_none() {
+            table.insert(key.to_string(), value);
+            return true;
+        }
+        let next = table
+            .entry(key.to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+        let Some(next) = next.as_object_mut() else {
+            return false;
+        };
+        table = next;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const MIGRATIONS: &[Migration] = &[
+        Migration {
+            version: 2,
+            changes: &[
+                Change::Move {
+                    from: "baud",
+                    to: "serial.baud_rate",
+                },
+                Change::Remove("legacy"),
+            ],
+        },
+        Migration {
+            version: 3,
+            changes: &[Change::Move {
+                from: "serial",
+                to: "console",
+            }],
+        },
+    ];
+
+    #[test]
+    fn test_migrate() {
+        let mut value = json!({"baud": 115200, "legacy": true, "other": 1});
+        let report = migrate(&mut value, MIGRATIONS).unwrap();
+        assert_eq!(
+            value,
+            json!({"console": {"baud_rate": 115200}, "other": 1, "version": 3})
+        );
+        assert_eq!(
+            report,
+            [
+                "baud → serial.baud_rate",
+                "legacy: removed",
+                "serial → console",
+                "version 1 → 3"
+            ]
+        );
+
+        // 当前版本的文件保持不变
+        let mut current = value.clone();
+        assert!(migrate(&mut current, MIGRATIONS).unwrap().is_empty());
+        assert_eq!(current, value);
+        let mut unversioned = json!({"other": 1});
+        assert!(migrate(&mut unversioned, &[]).unwrap().is_empty());
+        assert_eq!(unversioned, json!({"other": 1}));
+
+        let mut newer = json!({"version": 4});
+        assert!(migrate(&mut newer, MIGRATIONS).is_err());
+    }
+
+    #[test]
+    fn test_move_onto_existing() {
+        let mut value = json!({"version": 2, "serial": {"port": "a"}, "console": {"port": "b"}});
+        let report = migrate(&mut value, MIGRATIONS).unwrap();
+        assert_eq!(value, json!({"version": 3, "console": {"port": "b"}}));
+        assert_eq!(report[0], "serial: dropped, console is already set");
+    }
+
+    #[test]
+    fn test_migrate_content_keeps_comments() {
+        let content = "other = 1 # kept\nbaud = 115200\n";
+        let (content, _) = migrate_content(Path::new(".uboot.toml"), content, MIGRATIONS)
+            .unwrap()
+            .unwrap();
+        assert!(content.starts_with("other = 1 # kept\n"));
+        let value: Value = ConfigFormat::Toml.parse(&content).unwrap();
+        assert_eq!(value["console"]["baud_rate"], 115200);
+        assert_eq!(value["version"], 3);
+        assert!(
+            migrate_content(Path::new(".uboot.toml"), &content, MIGRATIONS)
+                .unwrap()
+                .is_none()
+        );
+    }
+}
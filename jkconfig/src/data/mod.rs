@@ -5,35 +5,91 @@
 //!
 //! - Schema parsing and conversion to internal representation
 //! - Configuration value management
-//! - Serialization to TOML/JSON formats
+//! - Serialization to TOML/JSON/YAML formats
 //!
 //! ## Architecture
 //!
 //! The data module is organized into several submodules:
 //!
+//! - [`access`] - Headless get/set by dotted config path
 //! - [`app_data`] - Main application data container
+//! - [`changes`] - Pending changes against the loaded config
+//! - [`constraint`] - JSON Schema validation keywords
+//! - [`defaults`] - Schema default values
+//! - [`defconfig`] - Values that differ from the schema defaults
+//! - [`format`] - Config file formats by extension
+//! - [`history`] - Undo/redo edit history
 //! - [`item`] - Individual configuration items
 //! - [`menu`] - Menu structure for navigation
+//! - [`migrate`] - Upgrades of config files to the current layout
+//! - [`multi`] - Several config files edited as one tree
 //! - [`oneof`] - OneOf/AnyOf schema variant handling
+//! - [`overrides`] - Environment/command-line overrides of config values
+//! - [`preset`] - Named presets of a config file
 //! - [`schema`] - JSON Schema parsing utilities
+//! - [`search`] - Full-text search across the menu tree
+//! - [`toml_doc`] - Comment-preserving TOML updates
 //! - [`types`] - Element type definitions
+//! - [`yaml`] - Minimal YAML reader/writer
+
+/// Headless get/set by dotted config path.
+pub mod access;
 
 /// Main application data container and configuration management.
 pub mod app_data;
 
+/// Pending changes against the loaded config.
+pub mod changes;
+
+/// JSON Schema validation keywords and violations.
+pub mod constraint;
+
+/// Schema default values.
+pub mod defaults;
+
+/// Values that differ from the schema defaults.
+pub mod defconfig;
+
+/// Config file formats (TOML/JSON/YAML).
+pub mod format;
+
+/// Undo/redo edit history.
+pub mod history;
+
 /// Individual configuration item representation.
 pub mod item;
 
 /// Menu structure for hierarchical navigation.
 pub mod menu;
 
+/// Versioned config layouts and upgrades of older files.
+pub mod migrate;
+
+/// Several config files edited as one menu tree.
+pub mod multi;
+
 /// OneOf/AnyOf schema variant handling.
 pub mod oneof;
 
+/// Environment/command-line overrides of config values.
+pub mod overrides;
+
+/// Named presets of a config file.
+pub mod preset;
+
 /// JSON Schema parsing utilities.
 pub mod schema;
 
+/// Full-text search across the menu tree.
+pub mod search;
+
+/// Comment-preserving TOML updates.
+pub mod toml_doc;
+
 /// Element type definitions for different data types.
 pub mod types;
 
+/// Minimal YAML reader/writer.
+pub mod yaml;
+
 pub use app_data::AppData;
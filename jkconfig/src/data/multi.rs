@@ -0,0 +1,289 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, bail};
+use serde_json::{Map, Value, json};
+
+use crate::data::{
+    app_data::{AppData, format_violations},
+    format::ConfigFormat,
+    menu::MenuRoot,
+};
+
+/// A config file mounted as a top-level submenu of a [`MultiConfig`].
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    /// Field name of the submenu, e.g. `build`.
+    pub key: String,
+    /// Path of the file the submenu is loaded from and saved to.
+    pub path: PathBuf,
+    /// JSON Schema of the file.
+    pub schema: Value,
+    /// Content to start from instead of the file's, e.g. a template.
+    pub init: Option<String>,
+}
+
+impl ConfigFile {
+    pub fn new(key: impl Into<String>, path: impl Into<PathBuf>, schema: Value) -> Self {
+        Self {
+            key: key.into(),
+            path: path.into(),
+            schema,
+            init: None,
+        }
+    }
+
+    /// Start the editor from `content` instead of the file; it is written
+    /// on save even if left unchanged.
+    pub fn with_init(mut self, content: impl Into<String>) -> Self {
+        self.init = Some(content.into());
+        self
+    }
+
+    fn original(&self) -> String {
+        match &self.init {
+            Some(init) => init.clone(),
+            None => fs::read_to_string(&self.path).unwrap_or_default(),
+        }
+    }
+}
+
+/// Several config files edited as one menu tree, each saved on its own.
+///
+/// Files that do not exist yet are mounted as optional submenus, so they
+/// are only created once something is set in them.
+#[derive(Debug, Clone)]
+pub struct MultiConfig {
+    /// Title of the root menu.
+    pub title: String,
+    /// Path the combined tree is known by, for presets and relative paths.
+    /// It is never written.
+    pub config: PathBuf,
+    pub files: Vec<ConfigFile>,
+}
+
+impl MultiConfig {
+    pub fn new(title: impl Into<String>, config: impl Into<PathBuf>) -> Self {
+        Self {
+            title: title.into(),
+            config: config.into(),
+            files: Vec::new(),
+        }
+    }
+
+    pub fn with_file(mut self, file: ConfigFile) -> Self {
+        self.files.push(file);
+        self
+    }
+
+    /// The combined schema: one property per file, with the `$defs` of all
+    /// files merged. Definitions whose names clash are renamed to
+    /// `<key>.<name>`.
+    pub fn schema(&self) -> Value {
+        let mut defs = Map::new();
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        for file in &self.files {
+            let mut schema = file.schema.clone();
+            let Some(obj) = schema.as_object_mut() else {
+                continue;
+            };
+            obj.remove("$schema");
+            let own_defs = match obj.remove("$defs") {
+                Some(Value::Object(own_defs)) => own_defs,
+                _ => Map::new(),
+            };
+
+            let mut renames = HashMap::new();
+            for (name, def) in &own_defs {
+                if defs.get(name).is_some_and(|existing| existing != def) {
+                    renames.insert(
+                        format!("#/$defs/{name}"),
+                        format!("#/$defs/{}.{name}", file.key),
+                    );
+                }
+            }
+            for (name, mut def) in own_defs {
+                rename_refs(&mut def, &renames);
+                let name = if renames.contains_key(&format!("#/$defs/{name}")) {
+                    format!("{}.{name}", file.key)
+                } else {
+                    name
+                };
+                defs.insert(name, def);
+            }
+            rename_refs(&mut schema, &renames);
+
+            if file.path.exists() || file.init.is_some() {
+                required.push(Value::String(file.key.clone()));
+            }
+            properties.insert(file.key.clone(), schema);
+        }
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": self.title,
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "$defs": defs,
+        })
+    }
+
+    /// Build the editor state, loading every file that exists.
+    pub fn load(&self) -> anyhow::Result<AppData> {
+        let schema = self.schema();
+        let mut root = MenuRoot::try_from(&schema)?;
+        let mut value = Map::new();
+        for file in &self.files {
+            let v = match &file.init {
+                Some(init) => parse_value(&file.path, init)?,
+                None => read_value(&file.path)?,
+            };
+            if let Some(v) = v {
+                value.insert(file.key.clone(), v);
+            }
+        }
+        root.update_by_value(&Value::Object(value))?;
+
+        let mut app = AppData::new_with_init_and_schema("", &self.config, &schema)?;
+        app.baseline = root.clone();
+        app.root = root;
+        app.needs_save = self.files.iter().any(|f| f.init.is_some());
+        Ok(app)
+    }
+
+    /// Write the files whose values changed in `app`, returning their paths.
+    ///
+    /// TOML files keep their comments and layout.
+    pub fn save(&self, app: &AppData) -> anyhow::Result<Vec<PathBuf>> {
+        let violations = app.root.validate();
+        if !violations.is_empty() {
+            bail!("{}", format_violations(&violations));
+        }
+        let current = app.root.as_json();
+        let baseline = app.baseline.as_json();
+        let mut written = Vec::new();
+        for file in &self.files {
+            let Some(value) = current.get(&file.key) else {
+                continue;
+            };
+            if baseline.get(&file.key) == Some(value) && file.path.exists() && file.init.is_none() {
+                continue;
+            }
+            let content =
+                ConfigFormat::from_path(&file.path)?.update_string(&file.original(), value)?;
+            fs::write(&file.path, content)
+                .with_context(|| format!("Failed to write {}", file.path.display()))?;
+            written.push(file.path.clone());
+        }
+        Ok(written)
+    }
+}
+
+fn read_value(path: &Path) -> anyhow::Result<Option<Value>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    parse_value(path, &content)
+}
+
+fn parse_value(path: &Path, content: &str) -> anyhow::Result<Option<Value>> {
+    if content.trim().is_empty() {
+        return Ok(None);
+    }
+    let value = ConfigFormat::from_path(path)?
+        .parse(content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(Some(value))
+}
+
+fn rename_refs(value: &mut Value, renames: &HashMap<String, String>) {
+    if renames.is_empty() {
+        return;
+    }
+    match value {
+        Value::Object(obj) => {
+            for (key, v) in obj.iter_mut() {
+                if key == "$ref"
+                    && let Some(new) = v.as_str().and_then(|r| renames.get(r))
+                {
+                    *v = Value::String(new.clone());
+                } else {
+                    rename_refs(v, renames);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| rename_refs(v, renames)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multi_config_roundtrip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        let def = |ty: &str| json!({ "type": "object", "properties": { "v": { "type": ty } } });
+        let schema = |ty: &str| {
+            json!({
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "title": "Part",
+                "type": "object",
+                "properties": { "inner": { "$ref": "#/$defs/Inner" } },
+                "required": ["inner"],
+                "$defs": { "Inner": def(ty) },
+            })
+        };
+        let a = dir.join("a.toml");
+        fs::write(&a, "# keep me\n[inner]\nv = \"x\"\n").unwrap();
+        let b = dir.join("b.toml");
+
+        let multi = MultiConfig::new("All", dir.join(".all.toml"))
+            .with_file(ConfigFile::new("a", &a, schema("string")))
+            .with_file(ConfigFile::new("b", &b, schema("integer")));
+
+        // Clashing definitions are renamed per file
+        let combined = multi.schema();
+        assert_eq!(combined["$defs"]["Inner"], def("string"));
+        assert_eq!(combined["$defs"]["b.Inner"], def("integer"));
+        assert_eq!(
+            combined["properties"]["b"]["properties"]["inner"]["$ref"],
+            "#/$defs/b.Inner"
+        );
+        assert_eq!(combined["required"], json!(["a"]));
+
+        let mut app = multi.load().unwrap();
+        assert_eq!(app.get_value("a.inner.v").unwrap(), "x");
+
+        // Nothing changed, nothing written
+        assert!(multi.save(&app).unwrap().is_empty());
+        assert!(!b.exists());
+
+        app.set_value("b.inner.v", "7").unwrap();
+        assert_eq!(multi.save(&app).unwrap(), vec![b.clone()]);
+        assert!(fs::read_to_string(&a).unwrap().starts_with("# keep me"));
+        let b_value: Value = toml::from_str(&fs::read_to_string(&b).unwrap()).unwrap();
+        assert_eq!(b_value, json!({ "inner": { "v": 7 } }));
+
+        // A template is loaded instead of the file and written on save
+        let multi = MultiConfig::new("All", dir.join(".all.toml")).with_file(
+            ConfigFile::new("a", &a, schema("string")).with_init("[inner] # tpl\nv = \"t\"\n"),
+        );
+        let app = multi.load().unwrap();
+        assert!(app.needs_save);
+        assert_eq!(app.get_value("a.inner.v").unwrap(), "t");
+        assert_eq!(multi.save(&app).unwrap(), vec![a.clone()]);
+        assert_eq!(
+            fs::read_to_string(&a).unwrap(),
+            "[inner] # tpl\nv = \"t\"\n"
+        );
+    }
+}
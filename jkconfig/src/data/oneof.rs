@@ -13,7 +13,7 @@ use log::trace;
 use serde_json::Value;
 
 /// OneOf/AnyOf variant container.
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct OneOf {
     /// Shared element metadata.
     pub base: ElementBase,
@@ -23,6 +23,12 @@ pub struct OneOf {
     pub selected_index: Option<usize>,
     /// Default variant index.
     pub default_index: Option<usize>,
+    /// Variants are untagged (`anyOf`): the value is written without the
+    /// variant name and the variant is picked by shape when loading.
+    pub untagged: bool,
+    /// The choice is flattened into the parent object (`oneOf`/`anyOf` next
+    /// to `properties`, as emitted for `#[serde(flatten)]` enums).
+    pub flatten: bool,
 }
 
 impl OneOf {
@@ -123,11 +129,24 @@ impl OneOf {
             return false;
         };
         trace!("Try index {index} , {variant:?}");
+        // A `const` variant only matches its own value
+        if let ElementType::Item(item) = variant
+            && item
+                .constraints
+                .const_value
+                .as_ref()
+                .is_some_and(|c| c != value)
+        {
+            return false;
+        }
         variant.update_from_value(value, name).is_ok()
     }
 
     /// Update selection and data from a JSON value.
     pub fn update_from_value(&mut self, value: &Value) -> Result<(), SchemaError> {
+        if self.untagged {
+            return self.update_untagged(value);
+        }
         let mut name: Option<String> = None;
         let mut value = value;
         if let Some(obj) = value.as_object()
@@ -160,18 +179,74 @@ impl OneOf {
         })
     }
 
+    /// Pick the first variant whose shape matches `value`.
+    fn update_untagged(&mut self, value: &Value) -> Result<(), SchemaError> {
+        for idx in 0..self.variants.len() {
+            let matches = match &self.variants[idx] {
+                ElementType::Menu(menu) => value
+                    .as_object()
+                    .is_some_and(|obj| menu.accepts(obj, !self.flatten)),
+                _ => true,
+            };
+            if matches && self.try_update_index(idx, None, value) {
+                self.selected_index = Some(idx);
+                return Ok(());
+            }
+        }
+        Err(SchemaError::TypeMismatch {
+            path: self.key(),
+            expected: format!("any of {} variants", self.variants.len()),
+            actual: value.to_string(),
+        })
+    }
+
+    /// Update a flattened choice from the parent object.
+    ///
+    /// Leaves the choice unselected when no variant is present.
+    pub fn update_from_flattened(
+        &mut self,
+        parent: &serde_json::Map<String, Value>,
+    ) -> Result<(), SchemaError> {
+        if self.untagged {
+            let parent = Value::Object(parent.clone());
+            if self.update_untagged(&parent).is_err() {
+                trace!("No flattened variant of {} matches", self.key());
+            }
+            return Ok(());
+        }
+        let found = self
+            .variants
+            .iter()
+            .position(|v| parent.contains_key(&v.struct_name));
+        if let Some(idx) = found {
+            let name = self.variants[idx].struct_name.clone();
+            self.variants[idx].update_from_value(&parent[&name], Some(&name))?;
+            self.selected_index = Some(idx);
+        }
+        Ok(())
+    }
+
     /// Serialize the selected variant into JSON.
     pub fn as_json(&self) -> Value {
         if let Some(selected) = self.selected() {
             match selected {
                 ElementType::Menu(menu) => {
-                    let mut obj = serde_json::Map::new();
-                    obj.insert(menu.struct_name.clone(), menu.as_json());
+                    let inner = if self.untagged {
+                        menu.as_json()
+                    } else {
+                        let mut obj = serde_json::Map::new();
+                        obj.insert(menu.struct_name.clone(), menu.as_json());
+                        Value::Object(obj)
+                    };
+                    if self.flatten {
+                        // Merged into the parent object by `Menu::as_json`
+                        return inner;
+                    }
 
                     let mut result = serde_json::Map::new();
                     // For OneOf, the variant name should be the field name from the menu
                     let variant_name = menu.field_name();
-                    result.insert(variant_name, Value::Object(obj));
+                    result.insert(variant_name, inner);
                     Value::Object(result)
                 }
                 ElementType::Item(item) => {
@@ -245,6 +320,8 @@ impl Debug for OneOf {
             .field("variants", &self.variants)
             .field("selected_index", &self.selected_index)
             .field("default_index", &self.default_index)
+            .field("untagged", &self.untagged)
+            .field("flatten", &self.flatten)
             .finish()
     }
 }
@@ -0,0 +1,260 @@
+use std::path::Path;
+
+use anyhow::{Context, bail};
+use log::trace;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::data::{
+    app_data::AppData, app_data::format_violations, format::ConfigFormat, menu::MenuRoot,
+};
+
+/// A single `path=value` override of a config value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Override {
+    /// Dotted config path as it appears in the file (`system.Cargo.target`).
+    pub path: String,
+    /// Value in command-line form, parsed like [`AppData::set_value`].
+    pub value: String,
+}
+
+/// Overrides applied on top of a loaded config file without modifying it.
+///
+/// Sources are applied in the order they were added, so later entries win:
+/// add environment variables first and command-line `--set` values after.
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    entries: Vec<Override>,
+}
+
+impl Overrides {
+    /// Collect overrides from environment variables starting with `prefix`.
+    ///
+    /// The rest of the name is the config path with `__` as separator:
+    /// `OSTOOL_CFG__system__Cargo__target` sets `system.Cargo.target` for
+    /// prefix `OSTOOL_CFG__`.
+    pub fn from_env(prefix: &str) -> Self {
+        Self::from_vars(prefix, std::env::vars())
+    }
+
+    /// Like [`Overrides::from_env`], reading from the given variables.
+    pub fn from_vars(prefix: &str, vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        let mut vars: Vec<_> = vars
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let path = name.strip_prefix(prefix)?;
+                (!path.is_empty()).then(|| (path.replace("__", "."), value))
+            })
+            .collect();
+        // Environment order is unspecified; keep the result deterministic
+        vars.sort();
+        let mut overrides = Self::default();
+        for (path, value) in vars {
+            overrides.push(path, value);
+        }
+        overrides
+    }
+
+    /// Add an override.
+    pub fn push(&mut self, path: impl Into<String>, value: impl Into<String>) {
+        self.entries.push(Override {
+            path: path.into(),
+            value: value.into(),
+        });
+    }
+
+    /// Add an override from its `path=value` command-line form.
+    pub fn push_arg(&mut self, arg: &str) -> anyhow::Result<()> {
+        let Some((path, value)) = arg.split_once('=') else {
+            bail!("invalid override `{arg}`: expected `path=value`");
+        };
+        let path = path.trim();
+        if path.is_empty() {
+            bail!("invalid override `{arg}`: empty path");
+        }
+        self.push(path, value);
+        Ok(())
+    }
+
+    /// Whether there is nothing to override.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The overrides in the order they are applied.
+    pub fn entries(&self) -> &[Override] {
+        &self.entries
+    }
+
+    /// Check that every override names a field of at least one of
+    /// `schemas`, the schemas of all config files the overrides are shared
+    /// by.
+    ///
+    /// [`Overrides::apply`] skips entries that belong to another config
+    /// file, so a mistyped path would otherwise do nothing. The error names
+    /// every override that matches no config file.
+    pub fn check(&self, schemas: &[Value]) -> anyhow::Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let roots = schemas
+            .iter()
+            .map(MenuRoot::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        let unmatched: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|entry| !roots.iter().any(|root| applies_to(root, &entry.path)))
+            .map(|entry| format!("`{}`", entry.path))
+            .collect();
+        if !unmatched.is_empty() {
+            bail!(
+                "config overrides matching no config file: {}",
+                unmatched.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Apply the overrides to `app`, returning how many were applied.
+    ///
+    /// The same overrides are shared by several config files with different
+    /// schemas, so entries whose first segment is not a field of this schema
+    /// are skipped; use [`Overrides::check`] to catch entries no file has.
+    /// Anything else that does not resolve or validate is an error.
+    pub fn apply(&self, app: &mut AppData) -> anyhow::Result<usize> {
+        let mut applied = 0;
+        for entry in &self.entries {
+            if !applies_to(&app.root, &entry.path) {
+                trace!("Override `{}` does not apply to this config", entry.path);
+                continue;
+            }
+            app.set_value(&entry.path, &entry.value)
+                .with_context(|| format!("failed to apply override `{}`", entry.path))?;
+            applied += 1;
+        }
+        Ok(applied)
+    }
+
+    /// Parse config file `content` into `C` with the overrides applied.
+    ///
+    /// `path` selects the file format; nothing is written.
+    pub fn load<C: DeserializeOwned>(
+        &self,
+        path: &Path,
+        content: &str,
+        schema: &Value,
+    ) -> anyhow::Result<C> {
+        let format = ConfigFormat::from_path(path)?;
+        if self.is_empty() {
+            return format.parse_typed(content);
+        }
+        let mut app = AppData::new_with_init_and_schema(content, path, schema)?;
+        self.apply(&mut app)?;
+        let violations = app.root.validate();
+        if !violations.is_empty() {
+            bail!("{}", format_violations(&violations));
+        }
+        // Round-trip through the file format so overrides behave like edits
+        format.parse_typed(&format.to_string(&app.root.as_json())?)
+    }
+}
+
+/// Whether the first segment of `path` is a field of `root`.
+fn applies_to(root: &MenuRoot, path: &str) -> bool {
+    let top = path.split('.').next().unwrap_or_default();
+    root.resolve_path(top).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schemars::{JsonSchema, schema_for};
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, JsonSchema)]
+    struct Config {
+        system: System,
+        #[schemars(range(max = 8))]
+        smp: u32,
+    }
+
+    #[derive(Debug, Deserialize, JsonSchema)]
+    enum System {
+        Cargo { target: String },
+    }
+
+    const CONTENT: &str = "smp = 1\n[system.Cargo]\ntarget = \"aarch64\"\n";
+
+    #[test]
+    fn test_from_vars() {
+        let vars = [
+            ("OSTOOL_CFG__smp", "2"),
+            ("PATH", "/bin"),
+            ("OSTOOL_CFG__system__Cargo__target", "riscv64"),
+            ("OSTOOL_CFG__", "x"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        let overrides = Overrides::from_vars("OSTOOL_CFG__", vars);
+        let paths: Vec<_> = overrides.entries().iter().map(|e| &e.path).collect();
+        assert_eq!(paths, ["smp", "system.Cargo.target"]);
+
+        let mut overrides = Overrides::default();
+        overrides.push_arg("a.b=c=d").unwrap();
+        assert_eq!(overrides.entries()[0].value, "c=d");
+        assert!(overrides.push_arg("novalue").is_err());
+    }
+
+    #[derive(JsonSchema)]
+    #[allow(dead_code)]
+    struct Serial {
+        baud_rate: u32,
+    }
+
+    #[test]
+    fn test_check() {
+        let schemas = [
+            schema_for!(Config).to_value(),
+            schema_for!(Serial).to_value(),
+        ];
+        let mut overrides = Overrides::default();
+        overrides.push("system.Cargo.target", "riscv64gc");
+        overrides.push("baud_rate", "115200");
+        overrides.check(&schemas).unwrap();
+
+        overrides.push("smpp", "2");
+        overrides.push("serial.baud_rate", "9600");
+        let err = overrides.check(&schemas).unwrap_err().to_string();
+        assert!(err.ends_with(": `smpp`, `serial.baud_rate`"), "{err}");
+    }
+
+    #[test]
+    fn test_load_with_overrides() {
+        let schema = schema_for!(Config);
+        let path = Path::new(".build.toml");
+        let mut overrides = Overrides::default();
+        overrides.push("system.Cargo.target", "riscv64gc");
+        overrides.push("smp", "2");
+        overrides.push("smp", "4");
+        // Keys of other config files are skipped
+        overrides.push("baud_rate", "115200");
+
+        let c: Config = overrides.load(path, CONTENT, schema.as_value()).unwrap();
+        let System::Cargo { target } = c.system;
+        assert_eq!(target, "riscv64gc");
+        assert_eq!(c.smp, 4);
+
+        let mut bad = Overrides::default();
+        bad.push("smp", "16");
+        assert!(
+            bad.load::<Config>(path, CONTENT, schema.as_value())
+                .is_err()
+        );
+        let mut bad = Overrides::default();
+        bad.push("system.Cargo.nope", "1");
+        assert!(
+            bad.load::<Config>(path, CONTENT, schema.as_value())
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, bail};
+
+use crate::data::{app_data::AppData, format::ConfigFormat, menu::MenuRoot};
+
+/// Directory, relative to the config file, where presets are stored.
+pub const PRESET_DIR: &str = ".ostool/presets";
+
+/// Check that a preset name is usable as a file name.
+fn check_name(name: &str) -> anyhow::Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        bail!("invalid preset name `{name}`: use letters, digits, `-`, `_` and `.`");
+    }
+    Ok(())
+}
+
+impl AppData {
+    /// Directory holding the presets of this config file:
+    /// `<config dir>/.ostool/presets/<config name>/`.
+    ///
+    /// Presets are grouped by config file name since each file has its own
+    /// schema (`.build.toml` presets live under `build/`).
+    pub fn presets_dir(&self) -> PathBuf {
+        let stem = self
+            .config
+            .file_stem()
+            .map(|s| s.to_string_lossy().trim_start_matches('.').to_string())
+            .unwrap_or_default();
+        self.base_dir().join(PRESET_DIR).join(stem)
+    }
+
+    /// Names of the saved presets, sorted.
+    pub fn list_presets(&self) -> anyhow::Result<Vec<String>> {
+        let dir = self.presets_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let supported = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .and_then(ConfigFormat::from_extension)
+                .is_some();
+            if supported && let Some(stem) = path.file_stem() {
+                names.push(stem.to_string_lossy().to_string());
+            }
+        }
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    /// Save the current values as preset `name`, replacing an existing one.
+    ///
+    /// The preset uses the format of the config file.
+    pub fn save_preset(&self, name: &str) -> anyhow::Result<PathBuf> {
+        check_name(name)?;
+        let format = ConfigFormat::from_path(&self.config).unwrap_or(ConfigFormat::Json);
+        let ext = self
+            .config
+            .extension()
+            .and_then(|e| e.to_str())
+            .filter(|e| ConfigFormat::from_extension(e).is_some())
+            .unwrap_or("json");
+
+        let dir = self.presets_dir();
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        if let Ok(old) = self.preset_path(name) {
+            fs::remove_file(old)?;
+        }
+        let path = dir.join(format!("{name}.{ext}"));
+        fs::write(&path, format.to_string(&self.root.as_json())?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Replace all values with those of preset `name`.
+    ///
+    /// The preset becomes the base for further editing: the edit history is
+    /// cleared and the config is marked as needing a save.
+    pub fn apply_preset(&mut self, name: &str) -> anyhow::Result<()> {
+        let path = self.preset_path(name)?;
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let value = ConfigFormat::from_path(&path)?.parse(&content)?;
+
+        let mut root = MenuRoot::try_from(&self.schema)?;
+        root.update_by_value(&value)
+            .with_context(|| format!("preset `{name}` does not match the schema"))?;
+        self.root = root;
+        self.current_key.clear();
+        self.history.clear();
+        self.needs_save = true;
+        Ok(())
+    }
+
+    fn preset_path(&self, name: &str) -> anyhow::Result<PathBuf> {
+        check_name(name)?;
+        let dir = self.presets_dir();
+        if dir.exists() {
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+                if path.file_stem().is_some_and(|s| s == name)
+                    && ConfigFormat::from_extension(ext).is_some()
+                {
+                    return Ok(path);
+                }
+            }
+        }
+        bail!("preset `{name}` not found in {}", dir.display())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn app(dir: &Path) -> AppData {
+        let schema = serde_json::json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Test",
+            "type": "object",
+            "properties": {
+                "board": {"type": "string"},
+                "smp": {"type": "integer"}
+            }
+        });
+        let init = "board = \"qemu\"\nsmp = 2\n";
+        AppData::new_with_init_and_schema(init, &dir.join(".build.toml"), &schema).unwrap()
+    }
+
+    #[test]
+    fn test_save_list_apply() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        let mut app = app(&dir);
+        assert_eq!(app.presets_dir(), dir.join(".ostool/presets/build"));
+        assert!(app.list_presets().unwrap().is_empty());
+
+        let path = app.save_preset("qemu-ci").unwrap();
+        assert_eq!(path.extension().unwrap(), "toml");
+        app.set_value("board", "devboard-a").unwrap();
+        app.save_preset("devboard-a").unwrap();
+        assert_eq!(app.list_presets().unwrap(), ["devboard-a", "qemu-ci"]);
+
+        app.apply_preset("qemu-ci").unwrap();
+        assert_eq!(
+            app.root.as_json(),
+            serde_json::json!({"board": "qemu", "smp": 2})
+        );
+        assert!(app.needs_save);
+        assert!(!app.history.can_undo());
+
+        assert!(app.apply_preset("missing").is_err());
+        assert!(app.save_preset("../escape").is_err());
+    }
+}
@@ -3,7 +3,8 @@ use std::{collections::HashSet, ops::Deref, path::PathBuf};
 use serde_json::Value;
 
 use crate::data::{
-    item::{EnumItem, Item, ItemType},
+    constraint::Constraints,
+    item::{EnumItem, IntDisplay, Item, ItemType},
     menu::{Menu, MenuRoot},
     oneof::OneOf,
     types::{ElementBase, ElementType},
@@ -86,6 +87,110 @@ impl WalkContext {
         Ok(desc)
     }
 
+    fn with_value(&self, value: Value) -> Self {
+        Self {
+            path: self.path.clone(),
+            value,
+            defs: self.defs.clone(),
+        }
+    }
+
+    /// Definition referenced by a local `$ref`, with its name.
+    fn ref_target(&self, value: &Value) -> Option<(String, Value)> {
+        let ref_str = value.get("$ref")?.as_str()?;
+        let def_name = ref_str.trim_start_matches("#/$defs/");
+        let def = self.defs.as_ref()?.get(def_name)?;
+        Some((def_name.to_string(), def.clone()))
+    }
+
+    /// Merge `allOf` members into a single schema.
+    ///
+    /// Properties and `required` are unioned, other keywords keep the first
+    /// value seen. `oneOf`/`anyOf` groups of the members (flattened enums)
+    /// cannot be merged and are kept as the remaining `allOf` entries.
+    fn merged(&self) -> Self {
+        let Some(Value::Object(base)) = Some(&self.value) else {
+            return self.clone();
+        };
+        let Some(members) = base.get("allOf").and_then(Value::as_array) else {
+            return self.clone();
+        };
+
+        let mut merged = base.clone();
+        merged.remove("allOf");
+        let mut groups = Vec::new();
+        for member in members {
+            let member = match self.ref_target(member) {
+                Some((_, def)) => def,
+                None => member.clone(),
+            };
+            let Value::Object(member) = self.with_value(member).merged().value else {
+                continue;
+            };
+            for (key, value) in member {
+                match key.as_str() {
+                    "properties" => {
+                        let props = merged
+                            .entry("properties")
+                            .or_insert_with(|| Value::Object(Default::default()));
+                        if let (Some(props), Value::Object(new)) = (props.as_object_mut(), value) {
+                            for (name, schema) in new {
+                                props.entry(name).or_insert(schema);
+                            }
+                        }
+                    }
+                    "required" => {
+                        let required = merged
+                            .entry("required")
+                            .or_insert_with(|| Value::Array(Vec::new()));
+                        if let (Some(required), Value::Array(new)) =
+                            (required.as_array_mut(), value)
+                        {
+                            for name in new {
+                                if !required.contains(&name) {
+                                    required.push(name);
+                                }
+                            }
+                        }
+                    }
+                    "oneOf" | "anyOf" => {
+                        let mut group = serde_json::Map::new();
+                        group.insert(key, value);
+                        groups.push(Value::Object(group));
+                    }
+                    "allOf" => groups.extend(value.as_array().cloned().unwrap_or_default()),
+                    _ => {
+                        merged.entry(key).or_insert(value);
+                    }
+                }
+            }
+        }
+        if !groups.is_empty() {
+            merged.insert("allOf".into(), Value::Array(groups));
+        }
+        self.with_value(Value::Object(merged))
+    }
+
+    /// `oneOf`/`anyOf` groups that sit next to `properties`, i.e. enums
+    /// flattened into this object.
+    fn flattened_groups(&self) -> Vec<Value> {
+        let mut groups = Vec::new();
+        let is_object = self.get("type").and_then(Value::as_str) == Some("object");
+        if is_object || self.get("properties").is_some() {
+            for key in ["oneOf", "anyOf"] {
+                if let Some(variants) = self.get(key) {
+                    let mut group = serde_json::Map::new();
+                    group.insert(key.to_string(), variants.clone());
+                    groups.push(Value::Object(group));
+                }
+            }
+        }
+        if let Some(rest) = self.get("allOf").and_then(Value::as_array) {
+            groups.extend(rest.iter().cloned());
+        }
+        groups
+    }
+
     fn handle_object(
         &self,
         is_required: bool,
@@ -101,13 +206,17 @@ impl WalkContext {
             } else if let Some(props) = self.get("properties")
                 && let Some(props) = props.as_object()
             {
-                for prop in props.values() {
+                for (name, prop) in props {
                     let mut walk = self.clone();
                     walk.value = prop.clone();
 
                     if let Some(ElementType::Menu(menu)) = walk.as_ref(is_required)? {
                         return Ok(Some(menu));
                     }
+                    // Struct variant declared inline: `{"A": {"type": "object", ...}}`
+                    if prop.get("type").and_then(Value::as_str) == Some("object") {
+                        return Ok(Some(Menu::from_schema(&walk, is_required, name)?));
+                    }
                 }
             }
         }
@@ -139,27 +248,89 @@ impl WalkContext {
             && let Some(variants) = one_of.as_array()
             && let Some(field_name) = field_name
         {
-            let mut variant_elements = Vec::new();
-            for variant in variants {
-                // Process each variant
-                let mut walk = self.clone();
-                walk.value = variant.clone();
-                if let Some(element_type) = walk.as_element_type(false, None)? {
-                    variant_elements.push(element_type);
-                }
-            }
+            return self
+                .build_choice(variants, false, false, is_required, field_name)
+                .map(Some);
+        }
 
-            let one_of = OneOf {
-                base: ElementBase::new(&self.path, self.description()?, is_required, field_name),
-                variants: variant_elements,
-                selected_index: None,
-                default_index: None,
+        Ok(None)
+    }
+
+    /// Build a OneOf from `oneOf` (tagged) or `anyOf` (untagged) variants.
+    fn build_choice(
+        &self,
+        variants: &[Value],
+        untagged: bool,
+        flatten: bool,
+        is_required: bool,
+        field_name: &str,
+    ) -> Result<OneOf, SchemaError> {
+        let mut variant_elements = Vec::new();
+        for (idx, variant) in variants.iter().enumerate() {
+            // Process each variant
+            let walk = self.with_value(variant.clone()).merged();
+            let element = if untagged {
+                walk.untagged_variant(idx)?
+            } else {
+                walk.as_element_type(false, None)?
             };
+            if let Some(element_type) = element {
+                variant_elements.push(element_type);
+            }
+        }
+
+        Ok(OneOf {
+            base: ElementBase::new(&self.path, self.description()?, is_required, field_name),
+            variants: variant_elements,
+            selected_index: None,
+            default_index: None,
+            untagged,
+            flatten,
+        })
+    }
 
-            return Ok(Some(one_of));
+    /// An `anyOf` variant: the variant schema itself is the value shape.
+    fn untagged_variant(&self, idx: usize) -> Result<Option<ElementType>, SchemaError> {
+        if self.get("$ref").is_some() {
+            return self.as_ref(false);
+        }
+        if self.get("type").and_then(Value::as_str) == Some("object") {
+            let name = match self.get_str("title")? {
+                Some(title) => title.to_string(),
+                None => format!("Variant {}", idx + 1),
+            };
+            return Ok(Some(ElementType::Menu(Menu::from_schema(
+                self, false, &name,
+            )?)));
         }
+        self.as_element_type(false, None)
+    }
 
-        Ok(None)
+    /// Build the OneOf for a flattened `oneOf`/`anyOf` group of an object.
+    fn flattened_choice(&self, group: &Value) -> Result<Option<OneOf>, SchemaError> {
+        let (variants, untagged) = match (group.get("oneOf"), group.get("anyOf")) {
+            (Some(Value::Array(v)), _) => (v, false),
+            (_, Some(Value::Array(v))) => (v, true),
+            _ => return Ok(None),
+        };
+        let is_null = |v: &Value| v.get("type").and_then(Value::as_str) == Some("null");
+        let non_null: Vec<Value> = variants.iter().filter(|v| !is_null(v)).cloned().collect();
+        let is_required = non_null.len() == variants.len();
+
+        let mut one_of = self.build_choice(&non_null, untagged, true, is_required, "")?;
+        // No field name in the schema: name the choice after its variants
+        let name = one_of
+            .variants
+            .iter()
+            .map(|v| v.struct_name.clone())
+            .collect::<Vec<_>>()
+            .join("|");
+        let path = self.path.join(&name);
+        one_of.base = ElementBase::new(&path, None, is_required, &name);
+        for variant in &mut one_of.variants {
+            rebase(variant, &self.path, &path);
+        }
+        Ok(Some(one_of))
     }
 
     /// field_name 为 [None] 时，代表是 array 的元素
@@ -167,6 +338,27 @@ impl WalkContext {
         &self,
         is_required: bool,
         field_name: Option<&str>,
+    ) -> Result<Option<ElementType>, SchemaError> {
+        let mut elem = if self.get("allOf").is_some()
+            && let merged = self.merged()
+            && (merged.get("allOf").is_none() || merged.get("properties").is_some())
+        {
+            merged.as_element_type_inner(is_required, field_name)?
+        } else {
+            self.as_element_type_inner(is_required, field_name)?
+        };
+        if let Some(elem) = &mut elem
+            && let Some(default) = self.get("default")
+        {
+            elem.apply_default(default);
+        }
+        Ok(elem)
+    }
+
+    fn as_element_type_inner(
+        &self,
+        is_required: bool,
+        field_name: Option<&str>,
     ) -> Result<Option<ElementType>, SchemaError> {
         if let Some(menu) = self.handle_object(is_required, field_name)? {
             return Ok(Some(ElementType::Menu(menu)));
@@ -184,7 +376,7 @@ impl WalkContext {
             return Ok(Some(item));
         }
 
-        if let Some(anyof) = self.as_anyof(is_required)? {
+        if let Some(anyof) = self.as_anyof(is_required, field_name)? {
             return Ok(Some(anyof));
         }
         Ok(None)
@@ -199,7 +391,7 @@ impl WalkContext {
             "string" | "number" | "integer" | "boolean" | "array" => {
                 // Create Item based on type
                 // Placeholder implementation
-                let item = Item {
+                let mut item = Item {
                     base: ElementBase::new(&self.path, self.description()?, is_required, ty_str),
                     item_type: match ty_str {
                         "string" => {
@@ -230,6 +422,7 @@ impl WalkContext {
                         "integer" => ItemType::Integer {
                             value: None,
                             default: None,
+                            display: IntDisplay::from_schema(&self.value),
                         },
                         "boolean" => ItemType::Boolean {
                             value: false,
@@ -250,11 +443,20 @@ impl WalkContext {
                                 element_type,
                                 values: Vec::new(),
                                 default: Vec::new(),
+                                item_constraints: Box::new(
+                                    self.get("items")
+                                        .map(Constraints::from_schema)
+                                        .unwrap_or_default(),
+                                ),
                             })
                         }
                         _ => unreachable!(),
                     },
+                    constraints: Box::new(Constraints::from_schema(self)),
                 };
+                if let Some(value) = item.constraints.const_value.clone() {
+                    item.update_from_value(&value)?;
+                }
                 return Ok(Some(ElementType::Item(item)));
             }
             _ => {}
@@ -280,19 +482,46 @@ impl WalkContext {
             }
         }
 
-        Ok(None)
+        // A bare `const` takes its type from the value
+        let const_ty = match self.get("const") {
+            Some(Value::String(_)) => "string",
+            Some(Value::Bool(_)) => "boolean",
+            Some(Value::Number(n)) if n.is_f64() => "number",
+            Some(Value::Number(_)) => "integer",
+            _ => return Ok(None),
+        };
+        self._as_item(const_ty, is_required)
     }
 
-    fn as_anyof(&self, _is_required: bool) -> Result<Option<ElementType>, SchemaError> {
-        if let Some(one_of) = self.get("anyOf")
-            && let Some(variants) = one_of.as_array()
+    fn as_anyof(
+        &self,
+        is_required: bool,
+        field_name: Option<&str>,
+    ) -> Result<Option<ElementType>, SchemaError> {
+        if let Some(any_of) = self.get("anyOf")
+            && let Some(variants) = any_of.as_array()
         {
-            let var_object = variants[0].clone();
-            let mut walk = self.clone();
-            walk.value = var_object;
-            if let Some(element_type) = walk.as_element_type(false, None)? {
-                return Ok(Some(element_type));
+            let is_null = |v: &Value| v.get("type").and_then(Value::as_str) == Some("null");
+            let non_null: Vec<Value> = variants.iter().filter(|v| !is_null(v)).cloned().collect();
+
+            // `Option<T>` is `anyOf: [T, null]`: unwrap to T
+            if non_null.len() == 1 || field_name.is_none() {
+                let Some(var_object) = non_null.into_iter().next() else {
+                    return Ok(None);
+                };
+                let mut walk = self.clone();
+                walk.value = var_object;
+                if let Some(element_type) = walk.as_element_type(false, None)? {
+                    return Ok(Some(element_type));
+                }
+                return Ok(None);
             }
+
+            let is_required = is_required && non_null.len() == variants.len();
+            let field_name = field_name.unwrap_or_default();
+            return self
+                .build_choice(&non_null, true, false, is_required, field_name)
+                .map(|one_of| Some(ElementType::OneOf(one_of)));
         }
 
         Ok(None)
@@ -308,6 +537,7 @@ impl TryFrom<&Value> for MenuRoot {
         let title = walk.required_field_as_string("title")?;
 
         walk.defs = walk.get("$defs").cloned();
+        let walk = walk.merged();
 
         let menu = Menu::from_schema(&walk, true, &title)?;
 
@@ -361,6 +591,12 @@ impl Menu {
             }
         }
 
+        for group in walk.flattened_groups() {
+            if let Some(one_of) = walk.flattened_choice(&group)? {
+                menu.children.push(ElementType::OneOf(one_of));
+            }
+        }
+
         // Placeholder implementation
         Ok(menu)
     }
@@ -377,3 +613,23 @@ impl Menu {
         Ok(())
     }
 }
+
+/// Move an element subtree built at `from` to `to`, keeping relative paths.
+fn rebase(elem: &mut ElementType, from: &std::path::Path, to: &std::path::Path) {
+    if let Ok(rest) = elem.path.strip_prefix(from) {
+        elem.path = to.join(rest);
+    }
+    match elem {
+        ElementType::Menu(menu) => {
+            for child in &mut menu.children {
+                rebase(child, from, to);
+            }
+        }
+        ElementType::OneOf(one_of) => {
+            for variant in &mut one_of.variants {
+                rebase(variant, from, to);
+            }
+        }
+        ElementType::Item(_) => {}
+    }
+}
@@ -0,0 +1,142 @@
+use crate::data::{menu::MenuRoot, types::ElementType};
+
+/// Which part of an element matched a search query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchField {
+    /// The field name (last path segment).
+    Name,
+    /// The display title.
+    Title,
+    /// The schema description.
+    Description,
+}
+
+/// One search result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit {
+    /// Dot-separated key of the matching element.
+    pub key: String,
+    /// Display title of the matching element.
+    pub title: String,
+    /// Best field that matched.
+    pub matched: MatchField,
+}
+
+impl MenuRoot {
+    /// Search the whole tree for elements whose field name, title or
+    /// description contains `query` (case-insensitive).
+    ///
+    /// Only the selected variant of a OneOf is searched, since other variants
+    /// are not reachable by key. Name matches come first, then title matches,
+    /// then description matches; each group keeps tree order.
+    pub fn search(&self, query: &str) -> Vec<SearchHit> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        let mut hits = Vec::new();
+        if let ElementType::Menu(menu) = &self.menu {
+            for child in &menu.children {
+                search_element(child, &query, &mut hits);
+            }
+        }
+        hits.sort_by_key(|hit| hit.matched);
+        hits
+    }
+}
+
+fn search_element(elem: &ElementType, query: &str, hits: &mut Vec<SearchHit>) {
+    let contains = |s: &str| s.to_lowercase().contains(query);
+    let matched = if contains(&elem.field_name()) {
+        Some(MatchField::Name)
+    } else if contains(&elem.title) {
+        Some(MatchField::Title)
+    } else if elem.help.as_deref().is_some_and(contains) {
+        Some(MatchField::Description)
+    } else {
+        None
+    };
+    if let Some(matched) = matched {
+        hits.push(SearchHit {
+            key: elem.key(),
+            title: elem.title.clone(),
+            matched,
+        });
+    }
+
+    match elem {
+        ElementType::Menu(menu) => {
+            for child in &menu.children {
+                search_element(child, query, hits);
+            }
+        }
+        ElementType::OneOf(one_of) => {
+            // A selected menu variant shares the OneOf's key; search its fields
+            match one_of.selected() {
+                Some(ElementType::Menu(menu)) => {
+                    for child in &menu.children {
+                        search_element(child, query, hits);
+                    }
+                }
+                Some(other @ ElementType::OneOf(_)) => search_element(other, query, hits),
+                _ => {}
+            }
+        }
+        ElementType::Item(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schemars::{JsonSchema, schema_for};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct Board {
+        serial: Serial,
+        /// Kernel load address
+        load_addr: u64,
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct Serial {
+        /// Baud rate of the console
+        baud_rate: u32,
+        /// Device path
+        ///
+        /// e.g. /dev/ttyUSB0
+        port: String,
+    }
+
+    fn root() -> MenuRoot {
+        MenuRoot::try_from(schema_for!(Board).as_value()).unwrap()
+    }
+
+    #[test]
+    fn test_search_matches_name_title_and_description() {
+        let root = root();
+
+        let hits = root.search("BAUD");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].key, "serial.baud_rate");
+        assert_eq!(hits[0].matched, MatchField::Name);
+
+        let hits = root.search("of the console");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].key, "serial.baud_rate");
+        assert_eq!(hits[0].matched, MatchField::Title);
+
+        let hits = root.search("ttyUSB");
+        assert_eq!(hits[0].key, "serial.port");
+        assert_eq!(hits[0].matched, MatchField::Description);
+    }
+
+    #[test]
+    fn test_search_orders_name_matches_first() {
+        let hits = root().search("addr");
+        assert_eq!(hits[0].key, "load_addr");
+        assert!(root().search("   ").is_empty());
+    }
+}
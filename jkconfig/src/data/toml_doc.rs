@@ -0,0 +1,193 @@
+//! Comment-preserving TOML updates.
+//!
+//! Writing a config through `serde_json::Value` → `toml` drops comments and
+//! reorders keys. [`update`] instead applies the new values to the original
+//! document with `toml_edit`, so only the values that changed are rewritten.
+
+use serde_json::Value;
+use toml_edit::{Array, ArrayOfTables, DocumentMut, Item, Table, TableLike};
+
+/// Apply `value` to the TOML document `original`.
+///
+/// Unchanged values keep their formatting (e.g. `0x8020_0000` stays hex),
+/// changed ones keep their surrounding comments, removed keys are dropped
+/// and new keys are appended. `value` must be an object.
+pub fn update(original: &str, value: &Value) -> anyhow::Result<String> {
+    let Value::Object(obj) = value else {
+        anyhow::bail!("a TOML document must be a table, got {value}");
+    };
+    let mut doc: DocumentMut = original.parse()?;
+    merge_table(doc.as_table_mut(), obj)?;
+    Ok(doc.to_string())
+}
+
+fn merge_table(
+    table: &mut dyn TableLike,
+    obj: &serde_json::Map<String, Value>,
+) -> anyhow::Result<()> {
+    let stale: Vec<String> = table
+        .iter()
+        .map(|(k, _)| k.to_string())
+        .filter(|k| !matches!(obj.get(k), Some(v) if !v.is_null()))
+        .collect();
+    for key in stale {
+        table.remove(&key);
+    }
+
+    for (key, value) in obj {
+        if value.is_null() {
+            continue;
+        }
+        match table.get_mut(key) {
+            Some(existing) => merge_item(existing, value)?,
+            None => {
+                table.insert(key, to_item(value)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn merge_item(item: &mut Item, value: &Value) -> anyhow::Result<()> {
+    match (&mut *item, value) {
+        (Item::Table(table), Value::Object(obj)) => return merge_table(table, obj),
+        (Item::Value(toml_edit::Value::InlineTable(table)), Value::Object(obj)) => {
+            return merge_table(table, obj);
+        }
+        (Item::ArrayOfTables(tables), Value::Array(values))
+            if values.iter().all(Value::is_object) =>
+        {
+            return merge_tables(tables, values);
+        }
+        (Item::Value(existing), _) => {
+            if value_to_json(existing).as_ref() == Some(value) {
+                return Ok(());
+            }
+            // Keep the comments around the value
+            let decor = existing.decor().clone();
+            let mut new = to_item(value)?
+                .into_value()
+                .map_err(|_| anyhow::anyhow!("cannot write {value} as an inline value"))?;
+            *new.decor_mut() = decor;
+            *existing = new;
+            return Ok(());
+        }
+        _ => {}
+    }
+    *item = to_item(value)?;
+    Ok(())
+}
+
+fn merge_tables(tables: &mut ArrayOfTables, values: &[Value]) -> anyhow::Result<()> {
+    while tables.len() > values.len() {
+        tables.remove(tables.len() - 1);
+    }
+    for (idx, value) in values.iter().enumerate() {
+        let Value::Object(obj) = value else {
+            unreachable!("checked by the caller");
+        };
+        match tables.get_mut(idx) {
+            Some(table) => merge_table(table, obj)?,
+            None => tables.push(to_table(obj)?),
+        }
+    }
+    Ok(())
+}
+
+/// The JSON form of an existing TOML value, for change detection.
+fn value_to_json(value: &toml_edit::Value) -> Option<Value> {
+    let mut value = value.clone();
+    value.decor_mut().clear();
+    let table: toml::Table = toml::from_str(&format!("v = {value}")).ok()?;
+    serde_json::to_value(table.get("v")?).ok()
+}
+
+fn to_item(value: &Value) -> anyhow::Result<Item> {
+    Ok(match value {
+        Value::Object(obj) => Item::Table(to_table(obj)?),
+        Value::Array(values) if !values.is_empty() && values.iter().all(Value::is_object) => {
+            let mut tables = ArrayOfTables::new();
+            for value in values {
+                if let Value::Object(obj) = value {
+                    tables.push(to_table(obj)?);
+                }
+            }
+            Item::ArrayOfTables(tables)
+        }
+        other => Item::Value(to_value(other)?),
+    })
+}
+
+fn to_table(obj: &serde_json::Map<String, Value>) -> anyhow::Result<Table> {
+    let mut table = Table::new();
+    for (key, value) in obj {
+        if !value.is_null() {
+            table.insert(key, to_item(value)?);
+        }
+    }
+    Ok(table)
+}
+
+fn to_value(value: &Value) -> anyhow::Result<toml_edit::Value> {
+    Ok(match value {
+        Value::Bool(b) => (*b).into(),
+        Value::String(s) => s.as_str().into(),
+        Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => i.into(),
+            (None, Some(f)) if n.is_f64() => f.into(),
+            _ => anyhow::bail!("{n} does not fit a TOML integer"),
+        },
+        Value::Array(values) => {
+            let mut array = Array::new();
+            for value in values.iter().filter(|v| !v.is_null()) {
+                array.push(to_value(value)?);
+            }
+            toml_edit::Value::Array(array)
+        }
+        Value::Object(obj) => toml_edit::Value::InlineTable(to_table(obj)?.into_inline_table()),
+        Value::Null => anyhow::bail!("TOML has no null value"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_update_keeps_comments_and_order() {
+        let original = r#"# Kernel build config
+name = "kernel" # the package
+load_addr = 0x8020_0000
+removed = 1
+
+[qemu]
+# extra arguments
+args = ["-nographic"]
+smp = 1
+"#;
+        let value = json!({
+            "name": "kernel",
+            "load_addr": 0x8020_0000u32,
+            "qemu": {"args": ["-nographic"], "smp": 4, "memory": "1G"},
+            "added": true,
+        });
+        let updated = update(original, &value).unwrap();
+        assert_eq!(
+            updated,
+            r#"# Kernel build config
+name = "kernel" # the package
+load_addr = 0x8020_0000
+added = true
+
+[qemu]
+# extra arguments
+args = ["-nographic"]
+smp = 4
+memory = "1G"
+"#
+        );
+        let parsed: toml::Value = toml::from_str(&updated).unwrap();
+        assert_eq!(serde_json::to_value(parsed).unwrap(), value);
+    }
+}
@@ -8,7 +8,7 @@ use crate::data::{item::Item, menu::Menu, oneof::OneOf, schema::SchemaError};
 use serde_json::Value;
 
 /// Common fields shared by all schema elements.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct ElementBase {
     /// Schema path for this element.
     pub path: PathBuf,
@@ -70,7 +70,7 @@ impl ElementBase {
 }
 
 /// High-level element types used by the UI and serialization logic.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ElementType {
     Menu(Menu),
     OneOf(OneOf),
@@ -106,6 +106,11 @@ impl ElementType {
         value: &Value,
         struct_name: Option<&str>,
     ) -> Result<(), SchemaError> {
+        // `null` is how an unset `Option` field is written
+        if value.is_null() && !self.is_required {
+            self.set_none();
+            return Ok(());
+        }
         match self {
             ElementType::Menu(menu) => {
                 if let Some(name) = struct_name
@@ -140,11 +145,22 @@ impl ElementType {
         }
     }
 
+    /// Whether this element is required but unset.
+    pub fn is_missing(&self) -> bool {
+        self.is_required && self.is_none()
+    }
+
     /// Reset this element to an "unset" state when allowed.
     pub fn set_none(&mut self) {
         if self.is_required {
             return;
         }
+        // `const` values are always emitted
+        if let ElementType::Item(item) = self
+            && item.constraints.const_value.is_some()
+        {
+            return;
+        }
 
         match self {
             ElementType::Menu(menu) => {
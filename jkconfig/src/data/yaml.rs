@@ -0,0 +1,565 @@
+//! Minimal YAML reader/writer for configuration files.
+//!
+//! Covers the subset config files use in practice: block mappings and
+//! sequences, flow collections (`[a, b]`, `{k: v}`), plain, single- and
+//! double-quoted scalars, comments and a leading `---`. Anchors, aliases,
+//! tags and block scalars (`|`, `>`) are rejected rather than read as plain
+//! strings. Values are converted to and from [`serde_json::Value`], and
+//! mapping keys keep their order.
+
+use anyhow::{Context, bail};
+use serde_json::{Map, Number, Value};
+
+/// Parse a YAML document into a JSON value.
+pub fn from_str(s: &str) -> anyhow::Result<Value> {
+    let mut lines = Vec::new();
+    for (idx, raw) in s.lines().enumerate() {
+        let line_no = idx + 1;
+        let text = strip_comment(raw).trim_end();
+        let content = text.trim_start();
+        if content.is_empty() {
+            continue;
+        }
+        let indent = text.len() - content.len();
+        if text[..indent].contains('\t') {
+            bail!("line {line_no}: tabs are not allowed in indentation");
+        }
+        if indent == 0 && (content == "---" || content.starts_with("--- ")) {
+            if !lines.is_empty() {
+                bail!("line {line_no}: multiple YAML documents are not supported");
+            }
+            continue;
+        }
+        if indent == 0 && content == "..." {
+            break;
+        }
+        lines.push(Line {
+            no: line_no,
+            indent,
+            text: content.to_string(),
+        });
+    }
+
+    let mut parser = Parser { lines, pos: 0 };
+    let Some(first) = parser.lines.first() else {
+        return Ok(Value::Null);
+    };
+    let value = parser.block(first.indent)?;
+    if let Some(line) = parser.lines.get(parser.pos) {
+        bail!("line {}: unexpected indentation", line.no);
+    }
+    Ok(value)
+}
+
+/// Serialize a JSON value as a block-style YAML document.
+pub fn to_string(value: &Value) -> String {
+    let mut out = String::new();
+    match value {
+        Value::Object(map) if !map.is_empty() => write_map(&mut out, map, 0),
+        Value::Array(items) if !items.is_empty() => write_seq(&mut out, items, 0),
+        other => {
+            out.push_str(&scalar(other));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+struct Line {
+    no: usize,
+    indent: usize,
+    text: String,
+}
+
+struct Parser {
+    lines: Vec<Line>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Line> {
+        self.lines.get(self.pos)
+    }
+
+    /// Parse the block starting at the current line, which has `indent`.
+    fn block(&mut self, indent: usize) -> anyhow::Result<Value> {
+        let line = &self.lines[self.pos];
+        if is_seq_entry(&line.text) {
+            self.sequence(indent)
+        } else if split_key(&line.text).is_some() {
+            self.mapping(indent)
+        } else {
+            let (no, text) = (line.no, line.text.clone());
+            self.pos += 1;
+            parse_inline(&text).with_context(|| format!("line {no}"))
+        }
+    }
+
+    fn sequence(&mut self, indent: usize) -> anyhow::Result<Value> {
+        let mut items = Vec::new();
+        while let Some(line) = self.peek() {
+            if line.indent != indent || !is_seq_entry(&line.text) {
+                break;
+            }
+            let rest = line.text[1..].trim_start();
+            if rest.is_empty() {
+                self.pos += 1;
+                items.push(self.nested(indent, false)?);
+            } else {
+                // `- key: value` opens a mapping aligned with `key`
+                let offset = line.text.len() - rest.len();
+                let rest = rest.to_string();
+                let line = &mut self.lines[self.pos];
+                line.indent += offset;
+                line.text = rest;
+                let indent = line.indent;
+                items.push(self.block(indent)?);
+            }
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn mapping(&mut self, indent: usize) -> anyhow::Result<Value> {
+        let mut map = Map::new();
+        while let Some(line) = self.peek() {
+            if line.indent != indent {
+                break;
+            }
+            let no = line.no;
+            let Some((key, rest)) = split_key(&line.text) else {
+                bail!("line {no}: expected `key: value`");
+            };
+            let key = parse_key(key).with_context(|| format!("line {no}"))?;
+            let rest = rest.to_string();
+            self.pos += 1;
+
+            let value = if rest.is_empty() {
+                self.nested(indent, true)?
+            } else {
+                parse_inline(&rest).with_context(|| format!("line {no}"))?
+            };
+            if map.insert(key.clone(), value).is_some() {
+                bail!("line {no}: duplicate key `{key}`");
+            }
+        }
+        Ok(Value::Object(map))
+    }
+
+    /// Parse the value of an entry whose inline part was empty.
+    ///
+    /// Mapping values may be a sequence at the same indentation as the key.
+    fn nested(&mut self, indent: usize, allow_same_indent_seq: bool) -> anyhow::Result<Value> {
+        match self.peek() {
+            Some(next) if next.indent > indent => {
+                let indent = next.indent;
+                self.block(indent)
+            }
+            Some(next)
+                if allow_same_indent_seq && next.indent == indent && is_seq_entry(&next.text) =>
+            {
+                self.sequence(indent)
+            }
+            _ => Ok(Value::Null),
+        }
+    }
+}
+
+fn is_seq_entry(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Remove a trailing `# comment`, ignoring `#` inside quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut prev = ' ';
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some('"') if c == '\\' && prev == '\\' => {
+                prev = ' ';
+                continue;
+            }
+            Some('"') if c == '"' && prev != '\\' => quote = None,
+            Some('\'') if c == '\'' => quote = None,
+            Some(_) => {}
+            None if c == '#' && prev.is_whitespace() => return &line[..i],
+            None if (c == '"' || c == '\'') && starts_token(prev) => quote = Some(c),
+            None => {}
+        }
+        prev = c;
+    }
+    line
+}
+
+fn starts_token(prev: char) -> bool {
+    prev.is_whitespace() || matches!(prev, '[' | '{' | ',' | ':' | '-')
+}
+
+/// Split `key: rest` at the first mapping colon outside quotes and brackets.
+fn split_key(text: &str) -> Option<(&str, &str)> {
+    if text.starts_with(['[', '{']) {
+        return None;
+    }
+    let mut quote = None;
+    let mut prev = ' ';
+    for (i, c) in text.char_indices() {
+        match quote {
+            Some('"') if c == '"' && prev != '\\' => quote = None,
+            Some('\'') if c == '\'' => quote = None,
+            Some(_) => {}
+            None if (c == '"' || c == '\'') && i == 0 => quote = Some(c),
+            None if c == ':' => {
+                let rest = &text[i + 1..];
+                if rest.is_empty() || rest.starts_with(' ') {
+                    return Some((text[..i].trim_end(), rest.trim_start()));
+                }
+            }
+            None => {}
+        }
+        prev = c;
+    }
+    None
+}
+
+fn parse_key(key: &str) -> anyhow::Result<String> {
+    match parse_inline(key)? {
+        Value::String(s) => Ok(s),
+        Value::Null if !key.is_empty() => Ok(key.to_string()),
+        Value::Array(_) | Value::Object(_) | Value::Null => {
+            bail!("unsupported mapping key `{key}`")
+        }
+        other => Ok(other.to_string()),
+    }
+}
+
+/// Parse a single-line value: a flow collection or a scalar.
+fn parse_inline(text: &str) -> anyhow::Result<Value> {
+    let text = text.trim();
+    if let Some(inner) = text.strip_prefix('[') {
+        let Some(inner) = inner.strip_suffix(']') else {
+            bail!("unterminated flow sequence `{text}`");
+        };
+        return split_flow(inner)?
+            .into_iter()
+            .map(parse_inline)
+            .collect::<anyhow::Result<_>>()
+            .map(Value::Array);
+    }
+    if let Some(inner) = text.strip_prefix('{') {
+        let Some(inner) = inner.strip_suffix('}') else {
+            bail!("unterminated flow mapping `{text}`");
+        };
+        let mut map = Map::new();
+        for entry in split_flow(inner)? {
+            let (key, value) = split_key(entry).unwrap_or((entry, ""));
+            map.insert(parse_key(key)?, parse_inline(value)?);
+        }
+        return Ok(Value::Object(map));
+    }
+    let unsupported = match text.chars().next() {
+        Some('|' | '>') => Some("block scalars"),
+        Some('&') => Some("anchors"),
+        Some('*') => Some("aliases"),
+        Some('!') => Some("tags"),
+        _ => None,
+    };
+    if let Some(feature) = unsupported {
+        bail!("unsupported YAML feature: {feature} (`{text}`)");
+    }
+    if let Some(inner) = text.strip_prefix('"') {
+        let Some(inner) = inner.strip_suffix('"') else {
+            bail!("unterminated string `{text}`");
+        };
+        return unescape(inner).map(Value::String);
+    }
+    if let Some(inner) = text.strip_prefix('\'') {
+        let Some(inner) = inner.strip_suffix('\'') else {
+            bail!("unterminated string `{text}`");
+        };
+        return Ok(Value::String(inner.replace("''", "'")));
+    }
+    Ok(plain_scalar(text))
+}
+
+/// Split the inside of a flow collection at top-level commas.
+fn split_flow(inner: &str) -> anyhow::Result<Vec<&str>> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut quote = None;
+    let mut prev = ' ';
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match quote {
+            Some('"') if c == '"' && prev != '\\' => quote = None,
+            Some('\'') if c == '\'' => quote = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '[' | '{' => depth += 1,
+                ']' | '}' => depth -= 1,
+                ',' if depth == 0 => {
+                    parts.push(inner[start..i].trim());
+                    start = i + 1;
+                }
+                _ => {}
+            },
+        }
+        prev = c;
+    }
+    if depth != 0 || quote.is_some() {
+        bail!("unbalanced flow collection `{inner}`");
+    }
+    let last = inner[start..].trim();
+    if !last.is_empty() {
+        parts.push(last);
+    }
+    Ok(parts)
+}
+
+fn plain_scalar(text: &str) -> Value {
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => return Value::Null,
+        "true" | "True" | "TRUE" => return Value::Bool(true),
+        "false" | "False" | "FALSE" => return Value::Bool(false),
+        _ => {}
+    }
+    let digits = text.replace('_', "");
+    let (neg, unsigned) = match digits.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, digits.strip_prefix('+').unwrap_or(&digits)),
+    };
+    let radix = [("0x", 16), ("0o", 8), ("0b", 2)]
+        .into_iter()
+        .find_map(|(prefix, radix)| unsigned.strip_prefix(prefix).map(|d| (d, radix)));
+    let int = match radix {
+        Some((d, radix)) => u64::from_str_radix(d, radix).ok(),
+        None if unsigned.bytes().all(|b| b.is_ascii_digit()) => unsigned.parse().ok(),
+        None => None,
+    };
+    if let Some(n) = int {
+        if !neg {
+            return Value::Number(n.into());
+        }
+        if let Some(n) = 0i64.checked_sub_unsigned(n) {
+            return Value::Number(n.into());
+        }
+    }
+    if text.contains(['.', 'e', 'E'])
+        && let Ok(f) = digits.parse::<f64>()
+        && let Some(n) = Number::from_f64(f)
+    {
+        return Value::Number(n);
+    }
+    Value::String(text.to_string())
+}
+
+fn unescape(s: &str) -> anyhow::Result<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('0') => out.push('\0'),
+            Some('"') => out.push('"'),
+            Some('\\') => out.push('\\'),
+            Some('/') => out.push('/'),
+            Some(' ') => out.push(' '),
+            Some(kind @ ('x' | 'u' | 'U')) => {
+                let len = match kind {
+                    'x' => 2,
+                    'u' => 4,
+                    _ => 8,
+                };
+                let hex: String = chars.by_ref().take(len).collect();
+                let ch = u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .with_context(|| format!("invalid escape `\\{kind}{hex}`"))?;
+                out.push(ch);
+            }
+            other => bail!("invalid escape `\\{}`", other.unwrap_or(' ')),
+        }
+    }
+    Ok(out)
+}
+
+fn write_map(out: &mut String, map: &Map<String, Value>, indent: usize) {
+    for (key, value) in map {
+        out.push_str(&" ".repeat(indent));
+        out.push_str(&quote_if_needed(key));
+        out.push(':');
+        write_child(out, value, indent);
+    }
+}
+
+fn write_seq(out: &mut String, items: &[Value], indent: usize) {
+    for item in items {
+        match item {
+            Value::Object(map) if !map.is_empty() => {
+                // Put the first key on the dash line: `- key: value`
+                let mut nested = String::new();
+                write_map(&mut nested, map, indent + 2);
+                out.push_str(&" ".repeat(indent));
+                out.push_str("- ");
+                out.push_str(&nested[indent + 2..]);
+            }
+            _ => {
+                out.push_str(&" ".repeat(indent));
+                out.push('-');
+                write_child(out, item, indent);
+            }
+        }
+    }
+}
+
+/// Write the part after `key:` or `-`, including the newline.
+fn write_child(out: &mut String, value: &Value, indent: usize) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            out.push('\n');
+            write_map(out, map, indent + 2);
+        }
+        Value::Array(items) if !items.is_empty() => {
+            out.push('\n');
+            write_seq(out, items, indent + 2);
+        }
+        other => {
+            out.push(' ');
+            out.push_str(&scalar(other));
+            out.push('\n');
+        }
+    }
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "null".into(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => quote_if_needed(s),
+        Value::Array(_) => "[]".into(),
+        Value::Object(_) => "{}".into(),
+    }
+}
+
+/// Quote a string when it would not read back as the same plain string.
+fn quote_if_needed(s: &str) -> String {
+    let plain_ok = !s.is_empty()
+        && s.trim() == s
+        && !s.starts_with([
+            '-', '?', ':', ',', '[', ']', '{', '}', '#', '&', '*', '!', '|', '>', '\'', '"', '%',
+            '@', '`',
+        ])
+        && !s.contains(": ")
+        && !s.contains(" #")
+        && !s.ends_with(':')
+        && !s.chars().any(char::is_control)
+        && plain_scalar(s) == Value::String(s.to_string());
+    if plain_ok {
+        return s.to_string();
+    }
+
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_block_document() {
+        let doc = r#"
+---
+# board config
+name: rk3588   # trailing comment
+serial:
+  port: /dev/ttyUSB0
+  baud_rate: 1_500_000
+load_addr: 0x4008_0000
+offset: -12
+ratio: 2.5
+enabled: true
+cmdline: "console=ttyS2,1500000 # not a comment"
+quoted: 'it''s'
+empty:
+features:
+- net
+- "fs"
+disks:
+  - path: a.img
+    readonly: yes
+  - path: b.img
+flow: [1, "two", {k: v}]
+"#;
+        let value = from_str(doc).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "name": "rk3588",
+                "serial": {"port": "/dev/ttyUSB0", "baud_rate": 1_500_000},
+                "load_addr": 0x4008_0000u64,
+                "offset": -12,
+                "ratio": 2.5,
+                "enabled": true,
+                "cmdline": "console=ttyS2,1500000 # not a comment",
+                "quoted": "it's",
+                "empty": null,
+                "features": ["net", "fs"],
+                "disks": [{"path": "a.img", "readonly": "yes"}, {"path": "b.img"}],
+                "flow": [1, "two", {"k": "v"}],
+            })
+        );
+        let keys: Vec<_> = value.as_object().unwrap().keys().take(3).collect();
+        assert_eq!(keys, ["name", "serial", "load_addr"]);
+    }
+
+    #[test]
+    fn test_roundtrip_and_errors() {
+        let value = json!({
+            "z_first": "keeps order",
+            "text": "a: b\n\"c\"",
+            "looks_like_number": "0x10",
+            "looks_like_bool": "true",
+            "nested": [[1, 2], [], {}, {"a": [true, null]}],
+            "": "empty key",
+        });
+        let yaml = to_string(&value);
+        assert!(yaml.starts_with("z_first: keeps order\n"));
+        assert_eq!(from_str(&yaml).unwrap(), value);
+
+        assert!(from_str("a: 1\na: 2").is_err());
+        assert!(from_str("a: |\n  text").is_err());
+        for doc in [
+            "a: &x 5\nb: *x",
+            "a: &x\n  b: 1",
+            "a: [*x]",
+            "- !!str 123",
+            "a: !custom {k: v}",
+        ] {
+            let err = format!("{:#}", from_str(doc).unwrap_err());
+            assert!(err.contains("unsupported YAML feature"), "{doc}: {err}");
+        }
+        assert!(from_str("a:\n\tb: 1").is_err());
+        assert_eq!(from_str("# only a comment\n").unwrap(), Value::Null);
+    }
+}
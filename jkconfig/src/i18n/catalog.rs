@@ -0,0 +1,1556 @@
+//! Message catalogs: `(key, English, Chinese)`.
+
+pub(super) static MESSAGES: &[(&str, &str, &str)] = &[
+    ("btn.ok", "OK", "确定"),
+    ("btn.cancel", "Cancel", "取消"),
+    ("btn.back", "Back", "返回"),
+    ("btn.quit", "Quit", "退出"),
+    ("btn.close", "Close", "关闭"),
+    ("btn.save", "Save", "保存"),
+    ("btn.yes", "Yes", "是"),
+    ("btn.no", "No", "否"),
+    ("btn.goto", "Go to", "跳转"),
+    ("btn.fill", "Fill", "填充"),
+    ("btn.save_as", "Save as...", "另存为..."),
+    ("btn.apply", "Apply", "应用"),
+    ("btn.revert", "Revert", "撤销"),
+    ("btn.done", "Done", "完成"),
+    ("btn.add", "Add", "添加"),
+    ("btn.select_dir", "Select Dir", "选择目录"),
+    ("btn.type_path", "Type Path", "手动输入"),
+    ("btn.export", "Export", "导出"),
+    ("btn.reset", "Reset", "恢复默认"),
+    ("dialog.quit", "Quit", "退出"),
+    ("dialog.save", "Save", "保存"),
+    ("dialog.error", "Error", "错误"),
+    ("quit.confirm", "Quit without saving?", "不保存直接退出？"),
+    ("save.blocked", "Cannot Save", "无法保存"),
+    ("save.confirm", "Save and exit?", "保存并退出？"),
+    ("menu.root", "(Root)", "(根)"),
+    ("menu.current_path", "Current Path", "当前路径"),
+    ("menu.items", "Items", "配置项"),
+    ("menu.shortcuts", "Keyboard Shortcuts", "快捷键"),
+    (
+        "fill.confirm",
+        "Fill every unset value with its schema default?",
+        "用 schema 默认值填充所有未设置的配置项？",
+    ),
+    ("fill.title", "Fill Defaults", "填充默认值"),
+    (
+        "fill.nothing",
+        "No defaults to fill in",
+        "没有可填充的默认值",
+    ),
+    ("label.read_only", "[read-only]", "[只读]"),
+    ("label.error", "ERROR:", "错误:"),
+    ("label.required", "(required)", "(必填)"),
+    (
+        "label.required_missing",
+        "⚠ {count} required missing",
+        "⚠ 缺少 {count} 个必填项",
+    ),
+    ("key.move", "Move", "移动"),
+    ("key.select", "Select", "选择"),
+    ("key.back", "Back", "返回"),
+    ("key.help", "Help", "帮助"),
+    ("key.search", "Search", "搜索"),
+    ("key.step", "Adjust", "调整"),
+    ("key.clear", "Clear", "清除"),
+    ("key.default", "Default", "默认值"),
+    ("key.toggle", "Toggle", "开关"),
+    ("key.switch", "Switch", "切换"),
+    ("key.editor", "$EDITOR", "$EDITOR"),
+    ("key.undo", "Undo", "撤销"),
+    ("key.copy_paste", "Copy/Paste", "复制/粘贴"),
+    ("key.redo", "Redo", "重做"),
+    ("key.save_exit", "Save & Exit", "保存并退出"),
+    ("key.quit", "Quit", "退出"),
+    ("key.presets", "Presets", "预设"),
+    ("key.changes", "Changes", "修改"),
+    ("key.fill", "Fill Defaults", "填充默认值"),
+    ("key.console", "Console", "控制台"),
+    ("key.edit_add", "Edit/Add", "编辑/添加"),
+    ("key.delete", "Delete", "删除"),
+    ("key.duplicate", "Duplicate", "复制元素"),
+    ("key.non_defaults", "Non-defaults", "非默认值"),
+    ("detail.menu", "📁 Menu", "📁 菜单"),
+    ("detail.title", "Title: ", "标题: "),
+    ("detail.description", "Description:", "描述:"),
+    ("detail.items", "Items: ", "子项: "),
+    ("detail.item_count", "{count} items", "{count} 项"),
+    ("detail.oneof", "🔀 OneOf Selector", "🔀 多选一"),
+    ("detail.property", "Property: ", "属性: "),
+    ("detail.current_variant", "Current Variant: ", "当前分支: "),
+    ("detail.none", "(none)", "(无)"),
+    ("detail.variants", "Available Variants:", "可选分支:"),
+    ("detail.type", "Type: ", "类型: "),
+    ("detail.current_value", "Current Value:", "当前值:"),
+    ("detail.invalid", "(invalid)", "(无效)"),
+    ("detail.default", "Default: ", "默认值: "),
+    ("detail.options", "Options:", "选项:"),
+    ("detail.element_type", "Element Type: ", "元素类型: "),
+    (
+        "detail.heading",
+        "╔═══ Item Details ═══╗",
+        "╔═══ 配置项详情 ═══╗",
+    ),
+    ("info.menu", "Menu", "菜单"),
+    ("info.oneof", "OneOf", "多选一"),
+    ("info.item", "Item", "配置项"),
+    ("info.title", "Title: {title}", "标题: {title}"),
+    ("info.contains", "Contains {count} items", "包含 {count} 项"),
+    ("info.required", "Required: ", "必填: "),
+    ("info.variants", "Variants: {count}", "分支数: {count}"),
+    ("info.current", "Current: {value}", "当前: {value}"),
+    ("info.unset", "<Unset>", "<未设置>"),
+    ("info.empty", "<Empty>", "<空>"),
+    (
+        "info.tip_tab",
+        "Tip: Press Tab to switch variants",
+        "提示: 按 Tab 切换分支",
+    ),
+    ("info.name", "Name: {name}", "名称: {name}"),
+    ("info.constraints", "Constraints: {hint}", "约束: {hint}"),
+    ("info.invalid", "⚠ Invalid: {error}", "⚠ 无效: {error}"),
+    ("info.default", "Default: {value}", "默认值: {value}"),
+    ("info.true", "True", "是"),
+    ("info.false", "False", "否"),
+    (
+        "info.tip_toggle",
+        "Tip: Press Enter to toggle",
+        "提示: 按 Enter 切换",
+    ),
+    (
+        "info.tip_edit",
+        "Tip: Press Enter to edit",
+        "提示: 按 Enter 编辑",
+    ),
+    (
+        "info.tip_select",
+        "Tip: Press Enter to select",
+        "提示: 按 Enter 选择",
+    ),
+    (
+        "info.tip_array",
+        "Tip: Enter=Edit, Del=Delete item",
+        "提示: Enter 编辑，Del 删除元素",
+    ),
+    ("info.options", "Options: {options}", "选项: {options}"),
+    ("info.element_type", "Element Type: {ty}", "元素类型: {ty}"),
+    ("info.count", "Count: {count}", "数量: {count}"),
+    ("info.values", "Values:", "值:"),
+    ("info.more", "... and {count} more", "... 还有 {count} 项"),
+    (
+        "readonly.current",
+        "Current value: {value}",
+        "当前值: {value}",
+    ),
+    ("readonly.title", "Read-only Field", "只读字段"),
+    ("type.number_float", "Number (float)", "Number (浮点)"),
+    ("copy.title", "Copy", "复制"),
+    ("copy.failed", "Copy failed: {error}", "复制失败: {error}"),
+    ("copy.done", "Copied `{key}`", "已复制 `{key}`"),
+    ("paste.title", "Paste", "粘贴"),
+    ("paste.failed", "Paste failed: {error}", "粘贴失败: {error}"),
+    ("preset.title", "Presets", "预设"),
+    (
+        "preset.read_failed",
+        "Failed to read presets: {error}",
+        "读取预设失败: {error}",
+    ),
+    ("preset.empty", "(no presets yet)", "(还没有预设)"),
+    ("preset.dir", "Directory: {dir}", "目录: {dir}"),
+    (
+        "preset.apply_confirm",
+        "Replace the whole config with preset `{name}`?",
+        "用预设 `{name}` 替换当前全部配置？",
+    ),
+    ("preset.apply_title", "Apply Preset", "应用预设"),
+    (
+        "preset.apply_failed",
+        "Failed to apply preset: {error}",
+        "应用预设失败: {error}",
+    ),
+    (
+        "preset.applied",
+        "Applied preset `{name}`",
+        "已应用预设 `{name}`",
+    ),
+    ("preset.name", "Preset name:", "预设名称:"),
+    ("preset.save_title", "Save Preset", "保存预设"),
+    (
+        "preset.saved",
+        "Saved preset `{name}`\n{path}",
+        "已保存预设 `{name}`\n{path}",
+    ),
+    (
+        "preset.save_failed",
+        "Failed to save preset: {error}",
+        "保存预设失败: {error}",
+    ),
+    (
+        "changes.empty",
+        "(no unsaved changes)",
+        "(没有未保存的修改)",
+    ),
+    (
+        "changes.hint",
+        "Enter reverts the selected change",
+        "Enter 撤销所选修改",
+    ),
+    ("changes.title", "Changes ({count})", "修改 ({count})"),
+    (
+        "changes.revert_confirm",
+        "Restore `{key}` to its loaded value?",
+        "将 `{key}` 恢复为加载时的值？",
+    ),
+    ("changes.revert_title", "Revert", "撤销修改"),
+    (
+        "search.prompt",
+        "Search field names, titles and descriptions:",
+        "搜索字段名、标题和描述:",
+    ),
+    ("search.title", "Search", "搜索"),
+    ("search.in_description", "(description)", "(描述)"),
+    ("array.add", "Add new item", "添加新元素"),
+    (
+        "array.heading",
+        "📋 Array Editor: {title}",
+        "📋 数组编辑: {title}",
+    ),
+    ("array.items", "Items ({count})", "元素 ({count})"),
+    ("array.title", "Array Editor", "数组编辑"),
+    (
+        "array.delete_confirm",
+        "⚠️  Are you sure you want to delete this item?",
+        "⚠️  确定删除这个元素吗？",
+    ),
+    ("array.delete_title", "Confirm Delete", "确认删除"),
+    ("array.new_value", "➕ Enter new value:", "➕ 输入新值:"),
+    ("array.value", "Value", "值"),
+    ("array.add_title", "Add Item", "添加元素"),
+    (
+        "array.empty_value",
+        "⚠️  Value cannot be empty!",
+        "⚠️  值不能为空！",
+    ),
+    (
+        "array.edit_item",
+        "✏️  Edit item [{idx}]:",
+        "✏️  编辑元素 [{idx}]:",
+    ),
+    ("array.edit_title", "Edit Item", "编辑元素"),
+    ("array.each_item", "each item {hint}", "每个元素 {hint}"),
+    ("edit.heading", "Edit: {title}", "编辑: {title}"),
+    ("edit.select", "Select: {title}", "选择: {title}"),
+    ("edit.select_option", "Select Option", "选择选项"),
+    ("edit.select_item", "Select Item", "选择项目"),
+    (
+        "edit.select_variant",
+        "Select variant: {title}",
+        "选择分支: {title}",
+    ),
+    ("edit.select_oneof", "Select One Of", "选择分支"),
+    (
+        "edit.int_hint",
+        "0x/0o/0b prefixes and K/M/G/T suffixes are accepted",
+        "支持 0x/0o/0b 前缀和 K/M/G/T 后缀",
+    ),
+    ("edit.integer", "Edit Integer", "编辑整数"),
+    ("edit.slider", "Adjust Value", "调整数值"),
+    (
+        "edit.slider_hint",
+        "←/→ move the slider, Enter to confirm",
+        "←/→ 移动滑块，Enter 确认",
+    ),
+    ("edit.spin_hint", "↑/↓ step the value", "↑/↓ 微调数值"),
+    ("edit.number", "Edit Number", "编辑数字"),
+    ("edit.string", "Edit String", "编辑字符串"),
+    (
+        "edit.invalid_number",
+        "Invalid number format!",
+        "数字格式无效！",
+    ),
+    (
+        "edit.invalid_value",
+        "Invalid value: {error}",
+        "无效的值: {error}",
+    ),
+    (
+        "path.hint_file",
+        "Enter opens a directory or picks a file",
+        "Enter 打开目录/选择文件",
+    ),
+    (
+        "path.hint_dir",
+        "Enter opens a directory, Select Dir picks the current one",
+        "Enter 打开目录，Select Dir 选择当前目录",
+    ),
+    (
+        "path.hint_any",
+        "Enter opens a directory or picks a file, Select Dir picks the current directory",
+        "Enter 打开目录/选择文件，Select Dir 选择当前目录",
+    ),
+    ("path.title", "Select Path", "选择路径"),
+    (
+        "editor.failed",
+        "External editor failed: {error}",
+        "外部编辑器失败: {error}",
+    ),
+    ("editor.title", "Editor", "编辑器"),
+    (
+        "editor.spawn_failed",
+        "Cannot start editor `{editor}`",
+        "无法启动编辑器 `{editor}`",
+    ),
+    (
+        "editor.exit_status",
+        "Editor `{editor}` exited with {status}",
+        "编辑器 `{editor}` 退出状态 {status}",
+    ),
+    (
+        "features.title",
+        "Features for {package}",
+        "{package} 的 features",
+    ),
+    (
+        "features.not_found",
+        "Package '{package}' not found in Cargo.toml",
+        "Cargo.toml 中找不到包 '{package}'",
+    ),
+    (
+        "multi.selected",
+        "✓ {name}  [selected]",
+        "✓ {name}  [已选择]",
+    ),
+    (
+        "multi.unselected",
+        "○ {name}  [not selected]",
+        "○ {name}  [未选择]",
+    ),
+    (
+        "multi.status",
+        "{selected} / {total} selected | Enter: toggle | ESC: exit",
+        "已选择 {selected} / {total} 项 | Enter: 切换选择 | ESC: 退出",
+    ),
+    (
+        "multi.status_deps",
+        "{selected} / {total} selected | Enter: toggle/open dependency | ESC: exit",
+        "已选择 {selected} / {total} 项 | Enter: 切换选择/进入依赖项 | ESC: 退出",
+    ),
+    (
+        "multi.status_back",
+        "{selected} / {total} selected | Enter: toggle | ESC: back",
+        "已选择 {selected} / {total} 项 | Enter: 切换选择 | ESC: 返回",
+    ),
+    (
+        "multi.hint",
+        "💡 Tip: selections are saved automatically",
+        "💡 提示: 选择后自动保存，无需确认",
+    ),
+    ("multi.title", "🌟 Multi Select", "🌟 多选界面"),
+    (
+        "multi.deps",
+        "--- Dependency Features ---",
+        "--- 依赖项 Features ---",
+    ),
+    (
+        "multi.dep_selected",
+        "📦 {name} ({count} features selected)",
+        "📦 {name} (已选择 {count} 个 features)",
+    ),
+    (
+        "multi.dep_none",
+        "📦 {name} (no features selected)",
+        "📦 {name} (未选择 features)",
+    ),
+    (
+        "multi.hint_deps",
+        "💡 Tip: selections are saved automatically, including dependency features",
+        "💡 提示: 选择后自动保存，进入依赖项选择后也会自动更新",
+    ),
+    (
+        "multi.title_deps",
+        "🌟 Features and Dependencies",
+        "🌟 特性与依赖项选择",
+    ),
+    (
+        "multi.dep_title",
+        "📦 {name} Features",
+        "📦 {name} Features",
+    ),
+    (
+        "multi.hint_dep",
+        "💡 Tip: selections are applied to the main list and saved",
+        "💡 提示: 选择后自动更新到主界面，并保存",
+    ),
+    (
+        "multi.title_dep",
+        "🌟 Dependency Features",
+        "🌟 依赖项特性选择",
+    ),
+    (
+        "web.started",
+        "🚀 Web server started!",
+        "🚀 Web服务器启动成功！",
+    ),
+    ("web.address", "📍 Open: {url}", "📍 访问地址: {url}"),
+    (
+        "web.no_token",
+        "⚠️  No access token is set; anyone who can reach this port can edit and save the config",
+        "⚠️  未设置访问令牌，能访问该端口的任何人都可以修改并保存配置",
+    ),
+    (
+        "web.tls_unsupported",
+        "TLS requires jkconfig built with the `web-tls` feature",
+        "TLS 需要启用 `web-tls` 特性构建 jkconfig",
+    ),
+    (
+        "web.stop",
+        "⏹️  Press Ctrl+C to stop the server",
+        "⏹️  按 Ctrl+C 停止服务器",
+    ),
+    ("app.exiting", "Exiting jkconfig...", "正在退出 jkconfig..."),
+    ("summary.unset", "(unset)", "(未设置)"),
+    (
+        "summary.fields",
+        "{{total} fields, {set} set}",
+        "{共 {total} 项，已设置 {set} 项}",
+    ),
+    ("summary.unset_variant", "Unset", "未设置"),
+    ("preview.items", "[{count} items]", "[{count} 项]"),
+    (
+        "uboot.dir_error",
+        "Cannot get the kernel file directory",
+        "无法获取 kernel 文件目录",
+    ),
+    (
+        "uboot.kernel_read_error",
+        "Failed to read the kernel file",
+        "读取 kernel 文件失败",
+    ),
+    (
+        "uboot.dtb_read_error",
+        "Failed to read the DTB file",
+        "读取 DTB 文件失败",
+    ),
+    (
+        "uboot.fit_build_error",
+        "Failed to build the FIT image",
+        "构建 FIT image 失败",
+    ),
+    (
+        "uboot.fit_save_error",
+        "Failed to save the FIT image",
+        "保存 FIT image 失败",
+    ),
+    (
+        "uboot.script_read_error",
+        "Failed to read the boot script",
+        "读取启动脚本失败",
+    ),
+    (
+        "uboot.script_build_error",
+        "Failed to build the boot script",
+        "构建启动脚本失败",
+    ),
+    (
+        "uboot.dtb_loaded",
+        "Loaded DTB file: {path} (size: {size})",
+        "已读取 DTB 文件: {path} (大小: {size})",
+    ),
+    (
+        "uboot.ramdisk_read_error",
+        "Failed to read the ramdisk file",
+        "读取 ramdisk 文件失败",
+    ),
+    (
+        "uboot.ramdisk_loaded",
+        "Loaded ramdisk file: {path} (size: {size})",
+        "已读取 ramdisk 文件: {path} (大小: {size})",
+    ),
+    (
+        "uboot.no_dtb",
+        "No DTB file given, the FIT image will only contain the kernel",
+        "未指定 DTB 文件，将生成仅包含 kernel 的 FIT image",
+    ),
+    (
+        "uboot.dtbo_needs_dtb",
+        "dtbo_files requires dtb_file to be set",
+        "dtbo_files 需要同时指定 dtb_file",
+    ),
+    (
+        "uboot.dtbo_loaded",
+        "Loaded DTB overlay: {path} (size: {size})",
+        "已读取 DTB overlay: {path} (大小: {size})",
+    ),
+    (
+        "migrate.upgraded",
+        "Upgraded {path} to config version {version}:",
+        "已将 {path} 升级到配置版本 {version}：",
+    ),
+    (
+        "migrate.failed",
+        "Cannot upgrade {path}: {error}",
+        "无法升级 {path}：{error}",
+    ),
+    (
+        "config_dir.missing",
+        "Config directory {path} does not exist",
+        "配置目录 {path} 不存在",
+    ),
+    (
+        "uboot.using_config",
+        "Using U-Boot config: {path}",
+        "使用 U-Boot 配置: {path}",
+    ),
+    (
+        "uboot.waiting",
+        "Waiting for board on power or reset...",
+        "等待开发板上电或复位...",
+    ),
+    (
+        "uboot.interacting",
+        "Interacting with U-Boot shell...",
+        "正在与 U-Boot shell 交互...",
+    ),
+    (
+        "uboot.success_matched",
+        "=== SUCCESS PATTERN MATCHED ===",
+        "=== 匹配到成功模式 ===",
+    ),
+    (
+        "uboot.fail_matched",
+        "=== FAIL PATTERN MATCHED ===",
+        "=== 匹配到失败模式 ===",
+    ),
+    ("uboot.send_file", "send file", "发送文件"),
+    ("uboot.send_ok", "send ok", "发送完成"),
+    (
+        "uboot.verify_loading",
+        "Loading a second copy of the FIT image to {addr} for verification",
+        "加载第二份 FIT image 到 {addr} 用于校验",
+    ),
+    (
+        "uboot.verify_ok",
+        "Both copies of the FIT image match",
+        "两份 FIT image 内容一致",
+    ),
+    (
+        "uboot.verify_failed",
+        "The two copies of the FIT image differ at offset {offset}, the transfer is unreliable",
+        "两份 FIT image 在偏移 {offset} 处不一致，传输不可靠",
+    ),
+    (
+        "uboot.image_reused",
+        "Kernel and device tree unchanged, booting the FIT image still at {addr}",
+        "内核和设备树未变化，直接启动仍在 {addr} 的 FIT image",
+    ),
+    (
+        "uboot.dtb_only",
+        "Only the device tree changed, sending it to {addr}",
+        "只有设备树变化，仅发送设备树到 {addr}",
+    ),
+    (
+        "uboot.image_gone",
+        "The image at {addr} is no longer in the board's RAM, transferring the FIT image",
+        "{addr} 处的镜像已不在开发板内存中，重新传输 FIT image",
+    ),
+    (
+        "uboot.dtb_verify_failed",
+        "The device tree in the board's RAM does not match the sent file",
+        "开发板内存中的设备树与发送的文件不一致",
+    ),
+    (
+        "uboot_env.saved",
+        "Saved {count} U-Boot environment variables to {path}",
+        "已保存 {count} 个 U-Boot 环境变量到 {path}",
+    ),
+    (
+        "uboot_env.restored",
+        "Restored {count} U-Boot environment variables",
+        "已恢复 {count} 个 U-Boot 环境变量",
+    ),
+    (
+        "uboot_env.unchanged",
+        "U-Boot environment unchanged, nothing to restore",
+        "U-Boot 环境变量未改变，无需恢复",
+    ),
+    (
+        "uboot_env.empty",
+        "No environment variables in {path}, refusing to restore",
+        "{path} 中没有环境变量，拒绝恢复",
+    ),
+    (
+        "uboot_env.no_snapshot",
+        "No snapshot file, set `env_snapshot` in .uboot.toml or pass one",
+        "未指定快照文件，请在 .uboot.toml 中设置 `env_snapshot` 或在命令行中给出",
+    ),
+    (
+        "uboot_env.no_reset",
+        "Restoring the environment after the run needs `board_reset_cmd` or `remote`",
+        "运行后恢复环境变量需要配置 `board_reset_cmd` 或 `remote`",
+    ),
+    (
+        "menuconfig.current",
+        "Current config file: {path}",
+        "当前配置文件: {path}",
+    ),
+    (
+        "menuconfig.not_found",
+        "No config file found, using defaults",
+        "未找到配置文件，将使用默认配置",
+    ),
+    (
+        "menuconfig.qemu_saved",
+        "QEMU config saved to .qemu.toml",
+        "QEMU 配置已保存到 .qemu.toml",
+    ),
+    (
+        "menuconfig.qemu_unchanged",
+        "QEMU config unchanged",
+        "未更改 QEMU 配置",
+    ),
+    (
+        "menuconfig.uboot_mode",
+        "=== U-Boot config mode ===",
+        "=== U-Boot 配置模式 ===",
+    ),
+    (
+        "menuconfig.uboot_saved",
+        "U-Boot config saved to .uboot.toml",
+        "U-Boot 配置已保存到 .uboot.toml",
+    ),
+    (
+        "menuconfig.uboot_unchanged",
+        "U-Boot config unchanged",
+        "未更改 U-Boot 配置",
+    ),
+    (
+        "menuconfig.qemu",
+        "Configuring QEMU run options",
+        "配置 QEMU 运行参数",
+    ),
+    (
+        "menuconfig.uboot",
+        "Configuring U-Boot run options",
+        "配置 U-Boot 运行参数",
+    ),
+    (
+        "menuconfig.all",
+        "Configuring build, QEMU and U-Boot options",
+        "配置构建、QEMU 与 U-Boot 参数",
+    ),
+    ("menuconfig.saved", "Saved {path}", "已保存 {path}"),
+    (
+        "menuconfig.unchanged",
+        "No config changed",
+        "未更改任何配置",
+    ),
+    (
+        "menuconfig.template",
+        "Starting from template `{name}`",
+        "基于模板 `{name}` 开始编辑",
+    ),
+    (
+        "menuconfig.gdb",
+        "Configuring GDB options",
+        "配置 GDB 调试参数",
+    ),
+    (
+        "menuconfig.gdb_saved",
+        "GDB config saved to .gdb.toml",
+        "GDB 配置已保存到 .gdb.toml",
+    ),
+    (
+        "menuconfig.gdb_unchanged",
+        "GDB config unchanged",
+        "未更改 GDB 配置",
+    ),
+    (
+        "sterm.exited",
+        "✓ Left serial terminal mode",
+        "✓ 已退出串口终端模式",
+    ),
+    (
+        "sterm.read_error",
+        "Serial read error: {error}",
+        "串口读取错误: {error}",
+    ),
+    (
+        "sterm.send_key_failed",
+        "Failed to send key: {error}",
+        "发送按键失败: {error}",
+    ),
+    ("sterm.exit_by", "Exit by: Ctrl+A+x", "退出方式: Ctrl+A+x"),
+    (
+        "sterm.send_ctrl_a_failed",
+        "Failed to send Ctrl+A: {error}",
+        "发送 Ctrl+A 失败: {error}",
+    ),
+    (
+        "sterm.key_event_error",
+        "Keyboard event error: {error}",
+        "键盘事件错误: {error}",
+    ),
+    (
+        "sterm.log_failed",
+        "Stopped logging to {path}: {error}",
+        "已停止写入日志 {path}: {error}",
+    ),
+    (
+        "sterm.logging",
+        "Logging the session to {path}",
+        "会话日志写入 {path}",
+    ),
+    (
+        "tftp.start_failed",
+        "Failed to start the TFTP server: {error}. If permission is denied, run `sudo setcap cap_net_bind_service=+eip $(which cargo-osrun)&&sudo setcap cap_net_bind_service=+eip $(which ostool)` and restart the terminal",
+        "TFTP server 启动失败：{error}。若权限不足，尝试执行 `sudo setcap cap_net_bind_service=+eip $(which cargo-osrun)&&sudo setcap cap_net_bind_service=+eip $(which ostool)` 并重启终端",
+    ),
+    (
+        "prompt.fallback",
+        "No terminal for the full-screen UI, editing {title} line by line",
+        "当前不是终端，无法运行全屏界面，逐行编辑 {title}",
+    ),
+    (
+        "prompt.defaults",
+        "Unset values were filled with their schema defaults",
+        "未设置的值已填入 schema 默认值",
+    ),
+    (
+        "prompt.help",
+        "Commands: <path>=<value> sets a value, <path> shows it, list, save, quit",
+        "命令: <路径>=<值> 设置值，<路径> 查看值，list 列出改动，save 保存，quit 放弃",
+    ),
+    (
+        "prompt.incomplete",
+        "Pass `path=value` lines on stdin to set them",
+        "可在标准输入中逐行传入 `路径=值` 进行设置",
+    ),
+    ("prompt.missing", "Missing: {key}", "缺少必填项: {key}"),
+    (
+        "prompt.changed",
+        "{key}: {old} -> {new}",
+        "{key}: {old} -> {new}",
+    ),
+    (
+        "defconfig.title",
+        "Differs from defaults ({count})",
+        "与默认值不同 ({count})",
+    ),
+    (
+        "defconfig.empty",
+        "Every value is at its schema default.",
+        "所有值均为 schema 默认值。",
+    ),
+    (
+        "defconfig.hint",
+        "Enter: reset to default   Export: write only these values",
+        "回车: 恢复默认值   导出: 只写出这些值",
+    ),
+    (
+        "defconfig.item",
+        "{key}: {value} (default: {default})",
+        "{key}: {value} (默认: {default})",
+    ),
+    (
+        "defconfig.reset_confirm",
+        "Reset `{key}` to its default?",
+        "将 `{key}` 恢复为默认值？",
+    ),
+    ("defconfig.reset_title", "Reset to default", "恢复默认值"),
+    ("defconfig.path", "File to write:", "输出文件:"),
+    (
+        "defconfig.export_title",
+        "Export defconfig",
+        "导出 defconfig",
+    ),
+    ("defconfig.exported", "Wrote {path}", "已写入 {path}"),
+    (
+        "defconfig.export_failed",
+        "Export failed: {error}",
+        "导出失败: {error}",
+    ),
+    (
+        "qemu.snapshot_loading",
+        "Starting from snapshot {name}",
+        "从快照 {name} 启动",
+    ),
+    (
+        "qemu.snapshot_stale",
+        "The kernel changed since snapshot {name} was saved, booting normally",
+        "保存快照 {name} 后内核已变化，正常启动",
+    ),
+    (
+        "qemu.snapshot_missing",
+        "No snapshot {name}, save it first with --save-snapshot {name}",
+        "没有快照 {name}，请先用 --save-snapshot {name} 保存",
+    ),
+    (
+        "qemu.snapshot_no_regex",
+        "--save-snapshot needs `save_regex` in the [snapshot] section of the QEMU config",
+        "--save-snapshot 需要在 QEMU 配置的 [snapshot] 中设置 `save_regex`",
+    ),
+    (
+        "qemu.snapshot_own_qmp",
+        "--save-snapshot needs the QMP socket of ostool; remove -qmp from args",
+        "--save-snapshot 需要 ostool 自己的 QMP 端口，请从 args 中去掉 -qmp",
+    ),
+    (
+        "qemu.snapshot_saved",
+        "Saved snapshot {name}",
+        "已保存快照 {name}",
+    ),
+    (
+        "qemu.snapshot_failed",
+        "Failed to save snapshot {name}: {error}",
+        "保存快照 {name} 失败: {error}",
+    ),
+    (
+        "qemu.hostfwd_tap",
+        "hostfwd only applies to user-mode networking and is ignored for tap",
+        "hostfwd 只用于用户模式网络，tap 模式下忽略",
+    ),
+    (
+        "qemu.disk_created",
+        "Created empty disk image {path} ({size} MiB)",
+        "已创建空磁盘镜像 {path}（{size} MiB）",
+    ),
+    (
+        "qemu.gdb_hint",
+        "QEMU waits for a debugger on localhost:1234; attach with `ostool gdb` in another terminal",
+        "QEMU 正在 localhost:1234 等待调试器连接，可在另一个终端运行 `ostool gdb`",
+    ),
+    (
+        "gdb.elf_missing",
+        "ELF not found: {path}; build it first, e.g. `ostool run qemu -d`, or pass --elf",
+        "找不到 ELF 文件：{path}，请先构建（如 `ostool run qemu -d`）或通过 --elf 指定",
+    ),
+    (
+        "gdb.unknown_arch",
+        "Cannot detect the architecture of {path}",
+        "无法识别 {path} 的架构",
+    ),
+    (
+        "gdb.not_found",
+        "No debugger found for {arch}; install gdb-multiarch, a cross gdb or lldb, or set `debugger` in .gdb.toml",
+        "找不到适用于 {arch} 的调试器，请安装 gdb-multiarch、交叉工具链的 gdb 或 lldb，或在 .gdb.toml 中设置 `debugger`",
+    ),
+    (
+        "gdb.using",
+        "Using {debugger} for {arch}",
+        "使用 {debugger} 调试 {arch}",
+    ),
+    (
+        "gdb.lldb_no_load",
+        "lldb cannot load the ELF into the target; `load` is ignored",
+        "lldb 无法将 ELF 加载到目标，已忽略 `load`",
+    ),
+    (
+        "gdb.script",
+        "GDB script written to {path}; attach with `gdb-multiarch -x {path}`",
+        "GDB 脚本已写入 {path}，可运行 `gdb-multiarch -x {path}` 连接",
+    ),
+    (
+        "gdb.attaching",
+        "Starting {debugger} in another terminal",
+        "正在另一个终端中启动 {debugger}",
+    ),
+    (
+        "gdb.no_terminal",
+        "auto_attach needs tmux or `terminal` in .gdb.toml; start the debugger yourself",
+        "auto_attach 需要 tmux 或在 .gdb.toml 中设置 `terminal`，请手动启动调试器",
+    ),
+    (
+        "gdb.attach_failed",
+        "Failed to start the debugger: {error}",
+        "启动调试器失败：{error}",
+    ),
+    ("vscode.written", "Updated {path}", "已更新 {path}"),
+    (
+        "vscode.unparsable",
+        "Cannot merge into {path}: {error}; fix it or pass --force to overwrite it",
+        "无法合并到 {path}：{error}，请修正该文件或使用 --force 覆盖",
+    ),
+    (
+        "test.running",
+        "Running {name} ({path})",
+        "运行 {name}（{path}）",
+    ),
+    (
+        "test.timed_out",
+        "No test finished within {secs}s",
+        "{secs} 秒内没有测试结束",
+    ),
+    (
+        "test.fail_pattern",
+        "Output matched fail pattern '{pattern}'",
+        "输出匹配到失败模式 '{pattern}'",
+    ),
+    (
+        "test.exited",
+        "QEMU exited ({status}) before the suite finished",
+        "测试未全部结束 QEMU 就已退出（{status}）",
+    ),
+    (
+        "test.crashed",
+        "The kernel crashed; crash bundle saved to {path}",
+        "内核崩溃，崩溃现场已保存到 {path}",
+    ),
+    (
+        "test.crashed_no_bundle",
+        "The kernel crashed; no crash bundle could be captured",
+        "内核崩溃，未能保存崩溃现场",
+    ),
+    (
+        "crash.capturing",
+        "Kernel panic detected, freezing QEMU and capturing a crash bundle...",
+        "检测到内核 panic，正在冻结 QEMU 并保存崩溃现场...",
+    ),
+    (
+        "crash.saved",
+        "Crash bundle saved to {path}",
+        "崩溃现场已保存到 {path}",
+    ),
+    (
+        "crash.capture_failed",
+        "Failed to capture the crash bundle: {error}",
+        "保存崩溃现场失败：{error}",
+    ),
+    (
+        "crash.no_gdb",
+        "No gdb found for {arch}; install gdb-multiarch or a cross gdb to capture crash bundles",
+        "找不到适用于 {arch} 的 gdb，请安装 gdb-multiarch 或交叉工具链的 gdb 以保存崩溃现场",
+    ),
+    (
+        "crash.gdb_timeout",
+        "gdb did not finish in time; the crash bundle may be incomplete",
+        "gdb 未能及时结束，崩溃现场可能不完整",
+    ),
+    (
+        "crash.invalid_region",
+        "Invalid memory region address or size in [test.crash]: {value}",
+        "[test.crash] 中的内存区域地址或大小无效：{value}",
+    ),
+    (
+        "test.list_failed",
+        "The kernel did not list its tests; does its test runner support `--list`?",
+        "内核没有列出测试，其测试运行器是否支持 `--list`？",
+    ),
+    (
+        "test.no_result",
+        "The test did not report a result",
+        "测试没有报告结果",
+    ),
+    (
+        "test.needs_cargo",
+        "`ostool test` needs a Cargo build system",
+        "`ostool test` 需要 Cargo 构建系统",
+    ),
+    (
+        "test.no_binaries",
+        "cargo built no test binaries",
+        "cargo 没有构建出测试程序",
+    ),
+    (
+        "test.failed",
+        "Tests failed in: {suites}",
+        "以下测试未通过：{suites}",
+    ),
+    ("bench.run", "Boot {run}/{runs}", "第 {run}/{runs} 次启动"),
+    ("bench.timed_out", "timed out", "超时"),
+    (
+        "bench.incomplete",
+        "Boot did not reach `{milestone}`: {reason}",
+        "本次启动未到达 `{milestone}`：{reason}",
+    ),
+    (
+        "bench.no_reset_cmd",
+        "Benchmarking on hardware needs `board_reset_cmd` in the U-Boot config",
+        "在硬件上测量启动时间需要在 U-Boot 配置中设置 `board_reset_cmd`",
+    ),
+    (
+        "bench.no_complete_run",
+        "No boot reached the last milestone",
+        "没有一次启动到达最后一个里程碑",
+    ),
+    (
+        "bench.no_milestones",
+        "No milestones configured, add `[[milestones]]` with `name` and `regex` to {path}",
+        "未配置里程碑，请在 {path} 中添加包含 `name` 和 `regex` 的 `[[milestones]]`",
+    ),
+    (
+        "bench.summary",
+        "Boot time over {completed} of {runs} boots",
+        "{runs} 次启动中 {completed} 次完成的启动时间",
+    ),
+    (
+        "bench.baseline_saved",
+        "Baseline saved to {path}",
+        "基线已保存到 {path}",
+    ),
+    (
+        "bench.regressed",
+        "Boot time regressed: {phases}",
+        "启动时间退化：{phases}",
+    ),
+    (
+        "boards.no_boards",
+        "No boards file {path}, list the boards as `[[boards]]` with `name` and `config`",
+        "未找到板子列表 {path}，请以 `[[boards]]` 列出包含 `name` 和 `config` 的板子",
+    ),
+    (
+        "boards.config_missing",
+        "U-Boot config of board `{name}` not found: {path}",
+        "板子 `{name}` 的 U-Boot 配置不存在：{path}",
+    ),
+    (
+        "boards.duplicate",
+        "Board `{name}` is listed twice",
+        "板子 `{name}` 重复出现",
+    ),
+    (
+        "boards.unknown",
+        "Unknown board `{name}`",
+        "未知的板子 `{name}`",
+    ),
+    ("boards.empty", "No boards to run", "没有要运行的板子"),
+    (
+        "boards.shared_serial",
+        "Boards `{a}` and `{b}` use the same serial port {serial}",
+        "板子 `{a}` 和 `{b}` 使用了同一个串口 {serial}",
+    ),
+    (
+        "boards.timed_out",
+        "No success pattern matched within {secs}s",
+        "{secs} 秒内未匹配到成功正则",
+    ),
+    (
+        "boards.summary",
+        "Boards: {passed} passed, {failed} failed",
+        "板子：{passed} 块通过，{failed} 块失败",
+    ),
+    (
+        "boards.failed",
+        "Failed boards: {boards}",
+        "失败的板子：{boards}",
+    ),
+    (
+        "remote.closed",
+        "Connection to the agent closed",
+        "与 agent 的连接已关闭",
+    ),
+    (
+        "remote.bad_address",
+        "Invalid agent address {agent}",
+        "无效的 agent 地址 {agent}",
+    ),
+    (
+        "remote.connect_failed",
+        "Cannot connect to agent {agent}",
+        "无法连接 agent {agent}",
+    ),
+    (
+        "remote.refused",
+        "Agent {agent} refused: {error}",
+        "agent {agent} 拒绝请求：{error}",
+    ),
+    (
+        "agent.no_config",
+        "No agent config {path}, set `listen`, `token` and the `[[boards]]`",
+        "未找到 agent 配置 {path}，请设置 `listen`、`token` 和 `[[boards]]`",
+    ),
+    (
+        "agent.no_token",
+        "The agent needs a non-empty `token`",
+        "agent 需要设置非空的 `token`",
+    ),
+    (
+        "agent.no_tls",
+        "No `tls_cert`/`tls_key`, serving over plain TCP; the token and console are not encrypted",
+        "未配置 `tls_cert`/`tls_key`，使用明文 TCP，token 和串口数据不加密",
+    ),
+    (
+        "agent.tls_incomplete",
+        "`tls_cert` and `tls_key` must be set together",
+        "`tls_cert` 和 `tls_key` 需要同时设置",
+    ),
+    (
+        "agent.listening",
+        "Agent listening on {addr}, boards: {boards}",
+        "agent 监听 {addr}，板子：{boards}",
+    ),
+    ("agent.bad_token", "Invalid token", "token 无效"),
+    (
+        "agent.unknown_board",
+        "Unknown board `{name}`",
+        "未知的板子 `{name}`",
+    ),
+    (
+        "agent.busy",
+        "Console of `{name}` is in use",
+        "`{name}` 的串口正在被使用",
+    ),
+    (
+        "plugin.exited",
+        "Plugin exited before answering `{method}`",
+        "插件在响应 `{method}` 之前退出",
+    ),
+    (
+        "plugin.bad_message",
+        "Invalid message from plugin: {line}",
+        "插件返回了无效消息：{line}",
+    ),
+    (
+        "plugin.failed",
+        "Plugin `{method}` failed: {error}",
+        "插件 `{method}` 失败：{error}",
+    ),
+    (
+        "plugin.none",
+        "No plugins found; add executables named `{prefix}<name>` to .ostool/plugins or PATH",
+        "未找到插件；请将名为 `{prefix}<名称>` 的可执行文件放到 .ostool/plugins 或 PATH 中",
+    ),
+    (
+        "plugin.not_found",
+        "Plugin `{name}` not found, expected an executable `{prefix}{name}` in .ostool/plugins or PATH",
+        "未找到插件 `{name}`，需要在 .ostool/plugins 或 PATH 中提供可执行文件 `{prefix}{name}`",
+    ),
+    ("plugin.output", "Packaged: {path}", "已打包：{path}"),
+    (
+        "disk.no_space",
+        "Partition `{name}` does not fit on the disk",
+        "分区 `{name}` 超出了磁盘空间",
+    ),
+    (
+        "disk.no_gpt",
+        "No GPT found on the disk",
+        "磁盘上没有 GPT 分区表",
+    ),
+    (
+        "disk.bad_crc",
+        "GPT {what} checksum mismatch",
+        "GPT {what} 校验和不匹配",
+    ),
+    (
+        "disk.missing_tool",
+        "`{tool}` not found, install {package}",
+        "未找到 `{tool}`，请安装 {package}",
+    ),
+    (
+        "disk.no_mbr",
+        "No MBR partition table found on the disk",
+        "磁盘上没有 MBR 分区表",
+    ),
+    (
+        "disk.no_config",
+        "Disk image config {path} not found",
+        "未找到磁盘镜像配置 {path}",
+    ),
+    (
+        "disk.image_ok",
+        "Disk image ready: {path}",
+        "磁盘镜像已生成：{path}",
+    ),
+    (
+        "flash.no_device",
+        "Device {path} not found",
+        "设备 {path} 不存在",
+    ),
+    (
+        "flash.not_block",
+        "{path} is not a block device",
+        "{path} 不是块设备",
+    ),
+    (
+        "flash.is_partition",
+        "{path} is a partition, give the whole disk, e.g. /dev/sdb",
+        "{path} 是分区，请指定整块磁盘，例如 /dev/sdb",
+    ),
+    (
+        "flash.mounted",
+        "{path} is mounted at {mounts}, unmount it first",
+        "{path} 已挂载于 {mounts}，请先卸载",
+    ),
+    (
+        "flash.not_removable",
+        "{path} is not removable media; use --force if you are sure",
+        "{path} 不是可移动介质；确认无误请加 --force",
+    ),
+    (
+        "flash.too_large",
+        "{path} is {size}, larger than removable media usually is; use --force if you are sure",
+        "{path} 大小为 {size}，超出常见可移动介质；确认无误请加 --force",
+    ),
+    (
+        "flash.image_too_big",
+        "Image needs {image}, the disk has only {size}",
+        "镜像需要 {image}，磁盘只有 {size}",
+    ),
+    ("flash.target", "Flash target:", "烧写目标："),
+    (
+        "flash.erase_warning",
+        "All data on this disk will be lost!",
+        "该磁盘上的所有数据都将丢失！",
+    ),
+    (
+        "flash.need_yes",
+        "Not a terminal, pass --yes to confirm",
+        "当前不是终端，请用 --yes 确认",
+    ),
+    (
+        "flash.confirm",
+        "Type `{name}` to continue: ",
+        "输入 `{name}` 以继续：",
+    ),
+    ("flash.aborted", "Aborted", "已取消"),
+    (
+        "flash.open_failed",
+        "Failed to open {path} for writing, are you allowed to write it?",
+        "无法以写方式打开 {path}，是否有写权限？",
+    ),
+    ("flash.writing", "Writing", "写入"),
+    ("flash.syncing", "Syncing", "同步"),
+    ("flash.verifying", "Verifying", "校验"),
+    (
+        "flash.verified",
+        "Verified: the disk matches the image",
+        "校验通过：磁盘内容与镜像一致",
+    ),
+    (
+        "flash.verify_failed",
+        "Verification failed: {path} does not match what was written",
+        "校验失败：{path} 与写入内容不一致",
+    ),
+    (
+        "flash.done",
+        "Flashed {path}, safe to remove",
+        "{path} 烧写完成，可以安全拔出",
+    ),
+    (
+        "openocd.using_config",
+        "Using OpenOCD config: {path}",
+        "使用 OpenOCD 配置: {path}",
+    ),
+    (
+        "openocd.no_scripts",
+        "No OpenOCD scripts in {path}, set `scripts` to the interface and target scripts of the board, e.g. [\"interface/cmsis-dap.cfg\", \"target/stm32f4x.cfg\"]",
+        "{path} 中没有 OpenOCD 脚本，请在 `scripts` 中填写板子的接口和目标脚本，例如 [\"interface/cmsis-dap.cfg\", \"target/stm32f4x.cfg\"]",
+    ),
+    (
+        "openocd.not_found",
+        "`{program}` not found, install OpenOCD or set `openocd` in .openocd.toml",
+        "未找到 `{program}`，请安装 OpenOCD 或在 .openocd.toml 中设置 `openocd`",
+    ),
+    (
+        "openocd.no_symbol",
+        "Breakpoint symbol `{name}` not found in {path}",
+        "{path} 中找不到断点符号 `{name}`",
+    ),
+    (
+        "openocd.exited",
+        "OpenOCD exited: {status}",
+        "OpenOCD 已退出: {status}",
+    ),
+    (
+        "openocd.connect_failed",
+        "Failed to connect to the OpenOCD TCL port {port}",
+        "无法连接 OpenOCD TCL 端口 {port}",
+    ),
+    (
+        "openocd.closed",
+        "OpenOCD closed the TCL connection",
+        "OpenOCD 关闭了 TCL 连接",
+    ),
+    (
+        "openocd.command_failed",
+        "OpenOCD command `{cmd}` failed: {err}",
+        "OpenOCD 命令 `{cmd}` 失败: {err}",
+    ),
+    (
+        "openocd.running",
+        "Target running from {entry}, press Ctrl+C to stop",
+        "目标已从 {entry} 开始运行，按 Ctrl+C 停止",
+    ),
+    (
+        "openocd.halted",
+        "Target halted at the breakpoint, attach with `ostool gdb` (OpenOcd target, localhost:{port})",
+        "目标停在断点处，可用 `ostool gdb` 连接 (OpenOcd 目标, localhost:{port})",
+    ),
+    (
+        "openocd.success_matched",
+        "=== SUCCESS PATTERN MATCHED ===",
+        "=== 匹配到成功模式 ===",
+    ),
+    (
+        "openocd.fail_matched",
+        "=== FAIL PATTERN MATCHED ===",
+        "=== 匹配到失败模式 ===",
+    ),
+    (
+        "probe_rs.using_config",
+        "Using probe-rs config: {path}",
+        "使用 probe-rs 配置: {path}",
+    ),
+    (
+        "probe_rs.no_chip",
+        "No chip in {path}, set `chip` to the target chip, see `probe-rs chip list`",
+        "{path} 中没有设置芯片，请在 `chip` 中填写目标芯片，可用 `probe-rs chip list` 查看",
+    ),
+    (
+        "probe_rs.not_found",
+        "`{program}` not found, install probe-rs (`cargo install probe-rs-tools`) or set `probe_rs` in .probe-rs.toml",
+        "未找到 `{program}`，请安装 probe-rs（`cargo install probe-rs-tools`）或在 .probe-rs.toml 中设置 `probe_rs`",
+    ),
+    (
+        "probe_rs.exited",
+        "probe-rs exited: {status}",
+        "probe-rs 已退出: {status}",
+    ),
+    (
+        "probe_rs.success_matched",
+        "=== SUCCESS PATTERN MATCHED ===",
+        "=== 匹配到成功模式 ===",
+    ),
+    (
+        "probe_rs.fail_matched",
+        "=== FAIL PATTERN MATCHED ===",
+        "=== 匹配到失败模式 ===",
+    ),
+    (
+        "rules.warn",
+        "Console warning: {message}",
+        "控制台警告: {message}",
+    ),
+    ("rules.captured", "Captured: {message}", "已捕获: {message}"),
+    ("rules.captures", "Captured values:", "捕获的值:"),
+    (
+        "gdb_bridge.listening",
+        "GDB bridge on {addr}, connect with `ostool gdb`",
+        "GDB 桥接监听 {addr}，用 `ostool gdb` 连接",
+    ),
+    (
+        "gdb_bridge.connected",
+        "GDB connected from {peer}",
+        "GDB 已连接: {peer}",
+    ),
+    ("gdb_bridge.disconnected", "GDB disconnected", "GDB 已断开"),
+    (
+        "rules.run_failed",
+        "Console rule command `{cmd}` failed: {error}",
+        "控制台规则命令 `{cmd}` 执行失败: {error}",
+    ),
+    (
+        "defmt.enabled",
+        "Decoding defmt frames with {count} strings from {path}",
+        "使用 {path} 中的 {count} 个字符串解码 defmt 帧",
+    ),
+    (
+        "defmt.table_error",
+        "Cannot read the defmt table of {path}: {err}",
+        "无法读取 {path} 的 defmt 字符串表: {err}",
+    ),
+    (
+        "usb.missing_tool",
+        "`{tool}` not found, install it for USB transfer",
+        "未找到 `{tool}`，USB 传输需要先安装它",
+    ),
+    (
+        "usb.waiting",
+        "Waiting for the board to show up in `{tool}`...",
+        "等待板子出现在 `{tool}` 中...",
+    ),
+    (
+        "usb.not_found",
+        "The board did not show up in `{tool}` within {secs}s, check the USB cable and the gadget support of U-Boot",
+        "板子在 {secs} 秒内未出现在 `{tool}` 中，请检查 USB 线和 U-Boot 的 gadget 支持",
+    ),
+    (
+        "usb.remote",
+        "USB transfer needs the board connected to this machine, it cannot go through `ostool agent`",
+        "USB 传输需要板子连接在本机，无法通过 `ostool agent` 进行",
+    ),
+    (
+        "deploy.missing_tool",
+        "`{tool}` not found, install it to switch the SD mux",
+        "未找到 `{tool}`，切换 SD 复用器需要先安装它",
+    ),
+    (
+        "deploy.start",
+        "Deploying to {board}...",
+        "正在部署到 {board}...",
+    ),
+    (
+        "deploy.no_reset",
+        "{board} has no reset command, power it on by hand",
+        "{board} 没有复位命令，请手动上电",
+    ),
+    (
+        "deploy.done",
+        "Deployed to {board}, booting from the SD card",
+        "已部署到 {board}，正从 SD 卡启动",
+    ),
+    (
+        "deploy.no_mux_boards",
+        "No board in the boards file has an `sd_mux`",
+        "板子列表中没有配置 `sd_mux` 的板子",
+    ),
+    (
+        "deploy.which_board",
+        "Several boards have an SD mux, name the board to deploy to",
+        "多块板子配置了 SD 复用器，请指定要部署的板子",
+    ),
+    (
+        "deploy.no_mux",
+        "Board {board} has no `sd_mux`",
+        "板子 {board} 没有配置 `sd_mux`",
+    ),
+    (
+        "deploy.no_card",
+        "The SD card did not show up as {device} within {secs}s",
+        "SD 卡在 {secs} 秒内未出现为 {device}",
+    ),
+    (
+        "ports.remembered",
+        "Serial port {port} not found, using {name} chosen before",
+        "未找到串口 {port}，使用之前选择的 {name}",
+    ),
+    (
+        "ports.only",
+        "Serial port {port} not found, using {name}, the only USB serial device",
+        "未找到串口 {port}，使用唯一的 USB 串口设备 {name}",
+    ),
+    (
+        "ports.none",
+        "Serial port {port} not found and no USB serial device is connected",
+        "未找到串口 {port}，也没有连接 USB 串口设备",
+    ),
+    (
+        "ports.absent",
+        "Serial port {port} not found, candidates:",
+        "未找到串口 {port}，候选设备：",
+    ),
+    (
+        "ports.need_port",
+        "Several serial devices match; pick one with --port",
+        "有多个串口设备可用，请用 --port 指定",
+    ),
+    (
+        "ports.choose",
+        "Use which port [1-{n}]? ",
+        "使用哪个串口 [1-{n}]？",
+    ),
+    (
+        "ports.bad_choice",
+        "No valid port chosen",
+        "未选择有效的串口",
+    ),
+    (
+        "ports.save_failed",
+        "Failed to remember the serial port",
+        "保存串口选择失败",
+    ),
+    (
+        "cache.empty",
+        "The cache at {dir} is empty",
+        "缓存目录 {dir} 为空",
+    ),
+    (
+        "cache.total",
+        "{count} entries, {size} in {dir}",
+        "共 {count} 项，{size}，位于 {dir}",
+    ),
+    (
+        "cache.cleaned",
+        "Removed {count} cache entries, freed {size}",
+        "已删除 {count} 个缓存项，释放 {size}",
+    ),
+    (
+        "cache.stale",
+        "Download failed, using the cached copy {path}",
+        "下载失败，使用缓存副本 {path}",
+    ),
+    (
+        "cache.offline_missing",
+        "Offline and {url} is not in the cache; run once without --offline to fetch it",
+        "离线模式下缓存中没有 {url}，请先在联网时不带 --offline 运行一次",
+    ),
+    (
+        "cache.hash_mismatch",
+        "SHA-256 of {url} is {actual}, expected {expected}",
+        "{url} 的 SHA-256 为 {actual}，期望 {expected}",
+    ),
+    (
+        "report.written",
+        "Report written to {path} ({count} files)",
+        "报告已写入 {path}（{count} 个文件）",
+    ),
+    (
+        "report.review",
+        "Secrets were redacted, but check it for private data before attaching it to an issue",
+        "密钥已脱敏，但附加到 issue 前仍请检查其中是否有隐私信息",
+    ),
+    (
+        "task.none",
+        "No tasks, add a [tasks] table to .build.toml",
+        "没有任务，请在 .build.toml 中添加 [tasks] 表",
+    ),
+    (
+        "task.unknown",
+        "Unknown task `{name}`, run `ostool task` to list them",
+        "未知任务 `{name}`，运行 `ostool task` 查看所有任务",
+    ),
+    (
+        "task.cycle",
+        "Task `{name}` runs itself",
+        "任务 `{name}` 调用了自身",
+    ),
+    (
+        "task.failed",
+        "Step {index} of task `{name}` failed",
+        "任务 `{name}` 的第 {index} 步失败",
+    ),
+    (
+        "task.unclosed_quote",
+        "Unclosed quote in `{line}`",
+        "`{line}` 中的引号未闭合",
+    ),
+    ("progress.running", "{elapsed} elapsed", "已用 {elapsed}"),
+    (
+        "progress.finished",
+        "done in {elapsed}",
+        "完成，用时 {elapsed}",
+    ),
+    ("progress.download", "Downloading {name}", "下载 {name}"),
+    ("progress.objcopy", "Converting to BIN", "转换为 BIN"),
+    (
+        "memmap.failed",
+        "The load addresses do not fit the board's memory:",
+        "加载地址与板子的内存布局冲突：",
+    ),
+    (
+        "memmap.outside_dram",
+        "{name} is outside DRAM",
+        "{name} 超出了 DRAM 范围",
+    ),
+    (
+        "memmap.overlap",
+        "{name} overlaps {other}",
+        "{name} 与 {other} 重叠",
+    ),
+    (
+        "memmap.invalid_bank",
+        "Invalid DRAM bank value `{value}`",
+        "无效的 DRAM bank 值 `{value}`",
+    ),
+    (
+        "memmap.no_dram",
+        "DRAM size unknown, checking overlaps only; set `dram` in .uboot.toml",
+        "未知 DRAM 大小，只检查重叠；可在 .uboot.toml 中设置 `dram`",
+    ),
+    ("exit.build", "Build failed", "构建失败"),
+    ("exit.boot", "Boot failed", "启动失败"),
+    ("exit.test", "Tests failed", "测试失败"),
+    ("exit.aborted", "Aborted", "已中断"),
+];
@@ -0,0 +1,188 @@
+//! Localized UI strings.
+//!
+//! Messages are looked up by key in the built-in English and Chinese
+//! catalogs with [`t!`](crate::t). The language is taken from, in order,
+//! [`set_lang`], `JKCONFIG_LANG`, the `language` key of the user settings
+//! file and the locale (`LC_ALL`, `LC_MESSAGES`, `LANG`), and defaults to
+//! English.
+
+use std::{
+    collections::HashMap,
+    env,
+    fmt::Display,
+    sync::{
+        OnceLock,
+        atomic::{AtomicU8, Ordering},
+    },
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
+
+mod catalog;
+
+/// UI language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    En,
+    Zh,
+}
+
+/// 0 = not chosen yet, otherwise `Lang as u8 + 1`.
+static LANG: AtomicU8 = AtomicU8::new(0);
+
+impl Lang {
+    /// Parse a language name or locale, e.g. `zh`, `en_US.UTF-8`, `zh-CN`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_ascii_lowercase();
+        if value.starts_with("zh") {
+            Some(Lang::Zh)
+        } else if value.starts_with("en") || value == "c" || value == "posix" {
+            Some(Lang::En)
+        } else {
+            None
+        }
+    }
+
+    /// The language of the current locale, English when unknown.
+    pub fn from_locale() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|key| env::var(key).ok())
+            .find(|v| !v.is_empty())
+            .and_then(|v| Self::parse(&v))
+            .unwrap_or(Lang::En)
+    }
+}
+
+/// The current UI language, detected on first use.
+pub fn lang() -> Lang {
+    match LANG.load(Ordering::Relaxed) {
+        0 => {
+            let lang = Settings::load().language.unwrap_or_else(Lang::from_locale);
+            set_lang(lang);
+            lang
+        }
+        1 => Lang::En,
+        _ => Lang::Zh,
+    }
+}
+
+/// Choose the UI language, overriding detection.
+pub fn set_lang(lang: Lang) {
+    LANG.store(lang as u8 + 1, Ordering::Relaxed);
+}
+
+/// Look up `key` in the catalog of the current language.
+///
+/// Unknown keys are returned unchanged.
+pub fn tr(key: &'static str) -> &'static str {
+    static INDEX: OnceLock<HashMap<&'static str, (&'static str, &'static str)>> = OnceLock::new();
+    let index = INDEX.get_or_init(|| {
+        catalog::MESSAGES
+            .iter()
+            .map(|&(key, en, zh)| (key, (en, zh)))
+            .collect()
+    });
+    match (index.get(key), lang()) {
+        (Some((en, _)), Lang::En) => en,
+        (Some((_, zh)), Lang::Zh) => zh,
+        (None, _) => key,
+    }
+}
+
+/// Replace `{name}` placeholders in `template`.
+pub fn format(template: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut out = template.to_string();
+    for (name, value) in args {
+        out = out.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+    out
+}
+
+/// Translate a catalog key, filling `{name}` placeholders from the
+/// arguments:
+///
+/// ```
+/// use jkconfig::t;
+///
+/// let title: &str = t!("dialog.quit");
+/// let msg: String = t!("preset.applied", name = "qemu-ci");
+/// ```
+#[macro_export]
+macro_rules! t {
+    ($key:literal) => {
+        $crate::i18n::tr($key)
+    };
+    ($key:literal, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::format(
+            $crate::i18n::tr($key),
+            &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),+],
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, fs, path::Path};
+
+    use regex::Regex;
+
+    use super::*;
+
+    #[test]
+    fn test_lang_parse_and_format() {
+        assert_eq!(Lang::parse("zh_CN.UTF-8"), Some(Lang::Zh));
+        assert_eq!(Lang::parse("en_US.UTF-8"), Some(Lang::En));
+        assert_eq!(Lang::parse("C"), Some(Lang::En));
+        assert_eq!(Lang::parse("fr_FR"), None);
+        assert_eq!(
+            format("`{key}` = {value}", &[("key", &"a"), ("value", &1)]),
+            "`a` = 1"
+        );
+    }
+
+    #[test]
+    fn test_catalog_is_consistent() {
+        let placeholder = Regex::new(r"\{(\w+)\}").unwrap();
+        let names = |s: &str| -> HashSet<String> {
+            placeholder
+                .captures_iter(s)
+                .map(|c| c[1].to_string())
+                .collect()
+        };
+        let mut keys = HashSet::new();
+        for (key, en, zh) in catalog::MESSAGES {
+            assert!(keys.insert(*key), "duplicate key {key}");
+            assert_eq!(names(en), names(zh), "placeholders differ for {key}");
+        }
+
+        // Every key used in the sources exists
+        let used = Regex::new(r#"\bt!\(\s*"([^"]+)""#).unwrap();
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let mut dirs = vec![root.join("src"), root.join("../ostool/src")];
+        while let Some(dir) = dirs.pop() {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().is_some_and(|e| e == "rs") {
+                    let src = fs::read_to_string(&path).unwrap();
+                    for c in used.captures_iter(&src) {
+                        assert!(
+                            keys.contains(&c[1]),
+                            "{} uses unknown key {}",
+                            path.display(),
+                            &c[1]
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
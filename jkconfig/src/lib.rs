@@ -34,6 +34,9 @@
 //!
 //! - [`data`] - Configuration data structures and schema parsing
 //! - [`run`] - TUI application runner
+//! - [`prompt`] - Line-based fallback without a terminal
+//! - [`settings`] - User-level settings (theme, accessible mode, language)
+//! - [`i18n`] - Localized UI strings
 //! - [`ui`] - UI components and editors
 //! - [`web`] - Web server module (requires `web` feature)
 
@@ -53,6 +56,15 @@ pub mod data;
 /// TUI application runner and main entry points.
 pub mod run;
 
+/// Line-based editing when the TUI cannot run.
+pub mod prompt;
+
+/// User-level settings such as the color theme.
+pub mod settings;
+
+/// English and Chinese UI string catalogs.
+pub mod i18n;
+
 /// UI components and editors for different data types.
 pub mod ui;
 
@@ -1,9 +1,12 @@
 use clap::{Parser, Subcommand};
 use cursive::{Cursive, CursiveExt, event::Key};
-use std::path::PathBuf;
+use std::{net::IpAddr, path::PathBuf};
 
 use jkconfig::{
     data::AppData,
+    prompt,
+    settings::Settings,
+    t,
     ui::{components::menu::menu_view, handle_back, handle_quit, handle_save},
 };
 
@@ -39,6 +42,21 @@ enum Commands {
         /// server port
         #[arg(short = 'p', long = "port", default_value = "3000")]
         port: u16,
+        /// listen address, overrides `[web] bind` of the settings file
+        #[arg(long)]
+        bind: Option<IpAddr>,
+        /// access token, overrides `[web] token` and JKCONFIG_WEB_TOKEN
+        #[arg(long)]
+        token: Option<String>,
+        /// PEM certificate chain, serves HTTPS together with --tls-key
+        #[arg(long, requires = "tls_key")]
+        tls_cert: Option<PathBuf>,
+        /// PEM private key of --tls-cert
+        #[arg(long, requires = "tls_cert")]
+        tls_key: Option<PathBuf>,
+        /// origin allowed to call the API, `*` for any (repeatable)
+        #[arg(long = "allow-origin")]
+        allow_origin: Vec<String>,
     },
 }
 
@@ -59,8 +77,27 @@ fn main() -> anyhow::Result<()> {
 
     // 根据子命令决定运行模式
     match cli.command {
-        Some(Commands::Web { port }) => {
-            tokio::runtime::Runtime::new()?.block_on(jkconfig::web::run_server(app_data, port))?;
+        Some(Commands::Web {
+            port,
+            bind,
+            token,
+            tls_cert,
+            tls_key,
+            allow_origin,
+        }) => {
+            // 命令行参数优先于用户设置
+            let mut options = Settings::load().web;
+            options.bind = bind.or(options.bind);
+            options.token = token.or(options.token);
+            if tls_cert.is_some() {
+                options.tls_cert = tls_cert;
+                options.tls_key = tls_key;
+            }
+            if !allow_origin.is_empty() {
+                options.cors_origins = allow_origin;
+            }
+            tokio::runtime::Runtime::new()?
+                .block_on(jkconfig::web::serve(app_data, port, &options))?;
         }
         Some(Commands::Tui) | None => {
             // 运行TUI界面（默认行为）
@@ -72,7 +109,12 @@ fn main() -> anyhow::Result<()> {
 }
 
 /// 运行TUI界面
-fn run_tui(app_data: AppData) -> anyhow::Result<()> {
+fn run_tui(mut app_data: AppData) -> anyhow::Result<()> {
+    if !prompt::tui_available() {
+        prompt::edit_stdin(&mut app_data)?;
+        return app_data.on_exit();
+    }
+
     let title = app_data.root.title.clone();
     let fields = app_data.root.menu().fields();
 
@@ -80,6 +122,7 @@ fn run_tui(app_data: AppData) -> anyhow::Result<()> {
     cursive::logger::set_filter_levels_from_env();
     // 创建Cursive应用
     let mut siv = Cursive::default();
+    Settings::load().theme.apply(&mut siv);
 
     // 设置AppData为user_data
     siv.set_user_data(app_data);
@@ -97,7 +140,7 @@ fn run_tui(app_data: AppData) -> anyhow::Result<()> {
     // 运行应用
     siv.run();
 
-    println!("Exiting jkconfig...");
+    println!("{}", t!("app.exiting"));
     let mut app = siv.take_user_data::<AppData>().unwrap();
     println!("Data: \n{:#?}", app.root);
     app.on_exit()?;
@@ -0,0 +1,188 @@
+//! Line-based editing for terminals the TUI cannot run on.
+//!
+//! When stdin or stdout is not a terminal (CI, pipes) or `TERM=dumb`, the
+//! editor falls back to reading commands line by line. Unset values are
+//! first filled with their schema defaults, so an empty input (e.g.
+//! `</dev/null`) accepts the defaults:
+//!
+//! ```text
+//! > system.Cargo.target=riscv64gc-unknown-none-elf
+//! > system.Cargo.target
+//! "riscv64gc-unknown-none-elf"
+//! > save
+//! ```
+
+use std::{
+    env,
+    io::{self, BufRead, IsTerminal, Write},
+};
+
+use crate::{
+    data::{AppData, app_data::format_violations},
+    t,
+};
+
+/// Whether the full-screen TUI can run: stdin and stdout are terminals
+/// and `TERM` is not `dumb`.
+pub fn tui_available() -> bool {
+    io::stdin().is_terminal()
+        && io::stdout().is_terminal()
+        && env::var("TERM").map_or(true, |term| term != "dumb")
+}
+
+/// Edit `app` from stdin, prompting on stderr when stdin is a terminal.
+///
+/// See [`edit_lines`].
+pub fn edit_stdin(app: &mut AppData) -> anyhow::Result<()> {
+    let interactive = io::stdin().is_terminal();
+    edit_lines(app, io::stdin().lock(), io::stderr(), interactive)
+}
+
+/// Edit `app` with commands read from `input`, one per line:
+///
+/// - `path=value` sets a value, `path` prints it
+/// - `list` shows the missing required values and the pending changes
+/// - `save` (or end of input) finishes, `quit` discards the changes
+///
+/// `needs_save` is set when the result should be written. Missing required
+/// values keep an interactive session going and are an error otherwise.
+pub fn edit_lines(
+    app: &mut AppData,
+    input: impl BufRead,
+    mut out: impl Write,
+    interactive: bool,
+) -> anyhow::Result<()> {
+    writeln!(out, "{}", t!("prompt.fallback", title = app.root.title))?;
+    if app.fill_defaults() {
+        writeln!(out, "{}", t!("prompt.defaults"))?;
+    }
+    if !app.config.exists() {
+        app.needs_save = true;
+    }
+    if interactive {
+        writeln!(out, "{}", t!("prompt.help"))?;
+        list(app, &mut out)?;
+    }
+
+    let mut lines = input.lines();
+    loop {
+        if interactive {
+            write!(out, "> ")?;
+            out.flush()?;
+        }
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => "save".to_string(),
+        };
+        let line = line.trim();
+        match line {
+            "" => {}
+            "help" | "?" => writeln!(out, "{}", t!("prompt.help"))?,
+            "list" => list(app, &mut out)?,
+            "quit" => {
+                app.needs_save = false;
+                return Ok(());
+            }
+            "save" => {
+                let violations = app.root.validate();
+                if violations.is_empty() {
+                    return Ok(());
+                }
+                if !interactive {
+                    anyhow::bail!(
+                        "{}\n{}",
+                        format_violations(&violations),
+                        t!("prompt.incomplete")
+                    );
+                }
+                writeln!(out, "{}", format_violations(&violations))?;
+            }
+            _ if line.starts_with('#') => {}
+            _ => {
+                let result = match line.split_once('=') {
+                    Some((path, value)) => app
+                        .set_value(path.trim(), value.trim())
+                        .map(|_| app.needs_save = true),
+                    None => app.get_value(line).and_then(|v| Ok(writeln!(out, "{v}")?)),
+                };
+                if let Err(e) = result {
+                    if !interactive {
+                        return Err(e);
+                    }
+                    writeln!(out, "{e:#}")?;
+                }
+            }
+        }
+    }
+}
+
+fn list(app: &AppData, out: &mut impl Write) -> anyhow::Result<()> {
+    for key in app.root.missing_required() {
+        writeln!(out, "  {}", t!("prompt.missing", key = key))?;
+    }
+    for change in app.pending_changes() {
+        writeln!(
+            out,
+            "  {}",
+            t!(
+                "prompt.changed",
+                key = change.key,
+                old = change.old,
+                new = change.new
+            )
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn app() -> AppData {
+        let schema = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Test",
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "default": "kernel"},
+                "smp": {"type": "integer"},
+            },
+            "required": ["name", "smp"],
+        });
+        AppData::new_with_schema(Some("/nonexistent/test.toml"), &schema).unwrap()
+    }
+
+    #[test]
+    fn test_edit_lines() {
+        crate::i18n::set_lang(crate::i18n::Lang::En);
+
+        // Defaults are filled, but `smp` has none
+        let mut a = app();
+        let err = edit_lines(&mut a, "".as_bytes(), io::sink(), false).unwrap_err();
+        assert!(err.to_string().contains("smp"));
+
+        let mut a = app();
+        let mut out = Vec::new();
+        edit_lines(
+            &mut a,
+            "# comment\nsmp=4\nsmp\n".as_bytes(),
+            &mut out,
+            false,
+        )
+        .unwrap();
+        assert!(a.needs_save);
+        assert_eq!(a.root.as_json(), json!({"name": "kernel", "smp": 4}));
+        assert!(String::from_utf8(out).unwrap().ends_with("4\n"));
+
+        // Errors are reported and the session goes on when interactive
+        let mut a = app();
+        let mut out = Vec::new();
+        edit_lines(&mut a, "smp=x\nsave\nquit\n".as_bytes(), &mut out, true).unwrap();
+        assert!(!a.needs_save);
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Missing: smp"), "{out}");
+    }
+}
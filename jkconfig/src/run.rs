@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::Context;
 pub use cursive;
@@ -7,11 +7,16 @@ use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
 
 use crate::{
-    data::AppData,
+    data::{AppData, app_data::format_violations, format::ConfigFormat, overrides::Overrides},
+    prompt,
+    settings::Settings,
     ui::{components::menu::menu_view, handle_back, handle_quit, handle_save},
 };
 
-pub use crate::data::app_data::ElemHock;
+pub use crate::data::{
+    app_data::ElemHock,
+    multi::{ConfigFile, MultiConfig},
+};
 
 /// Run the configuration editor workflow for a typed config.
 ///
@@ -26,73 +31,146 @@ pub async fn run<C: JsonSchema + DeserializeOwned>(
     always_use_ui: bool,
     elem_hocks: &[ElemHock],
 ) -> anyhow::Result<Option<C>> {
-    let config_path = config_path.as_ref();
-    let schema = schemars::schema_for!(C);
-    let schema_json = serde_json::to_value(&schema)?;
+    run_with_overrides(
+        config_path,
+        always_use_ui,
+        elem_hocks,
+        &Overrides::default(),
+    )
+    .await
+}
 
+/// Like [`run`], applying `overrides` on top of the file before returning
+/// the typed config.
+///
+/// Overrides only affect the returned value: the UI edits and saves the
+/// file as it is on disk.
+///
+/// # Errors
+///
+/// Returns errors when schema generation, parsing, or I/O fails, or when an
+/// override does not resolve or breaks the schema constraints.
+pub async fn run_with_overrides<C: JsonSchema + DeserializeOwned>(
+    config_path: impl AsRef<Path>,
+    always_use_ui: bool,
+    elem_hocks: &[ElemHock],
+    overrides: &Overrides,
+) -> anyhow::Result<Option<C>> {
+    let config_path = config_path.as_ref();
     let content = tokio::fs::read_to_string(&config_path)
         .await
         .unwrap_or_default();
+    edit_typed(
+        config_path,
+        content,
+        false,
+        always_use_ui,
+        elem_hocks,
+        overrides,
+    )
+    .await
+}
 
-    let ext = config_path
-        .extension()
-        .map(|s| format!("{}", s.display()))
-        .unwrap_or(String::new());
+/// Like [`run`], opening the editor on `init` (e.g. a template) instead of
+/// the file's content.
+///
+/// Nothing is written unless the user saves; the file then takes the
+/// layout and comments of `init`.
+///
+/// # Errors
+///
+/// Returns errors when schema generation, parsing, or I/O fails.
+pub async fn run_with_init<C: JsonSchema + DeserializeOwned>(
+    config_path: impl AsRef<Path>,
+    init: &str,
+    elem_hocks: &[ElemHock],
+) -> anyhow::Result<Option<C>> {
+    edit_typed(
+        config_path.as_ref(),
+        init.to_string(),
+        true,
+        true,
+        elem_hocks,
+        &Overrides::default(),
+    )
+    .await
+}
 
-    if let Ok(c) = to_typed::<C>(&content, &ext)
+/// Edit `content` for `config_path`; `modified` marks it as differing from
+/// the file, so it is written even without further edits.
+async fn edit_typed<C: JsonSchema + DeserializeOwned>(
+    config_path: &Path,
+    content: String,
+    modified: bool,
+    always_use_ui: bool,
+    elem_hocks: &[ElemHock],
+    overrides: &Overrides,
+) -> anyhow::Result<Option<C>> {
+    let schema = schemars::schema_for!(C);
+    let schema_json = serde_json::to_value(&schema)?;
+
+    let format = ConfigFormat::from_path(config_path)?;
+
+    if let Ok(c) = format.parse_typed::<C>(&content)
         && !always_use_ui
     {
-        return Ok(Some(c));
+        if overrides.is_empty() {
+            return Ok(Some(c));
+        }
+        return overrides
+            .load(config_path, &content, &schema_json)
+            .map(Some);
     }
 
-    let app = get_content_by_ui(config_path, &content, &schema_json, elem_hocks).await?;
+    let app = get_content_by_ui(config_path, &content, &schema_json, elem_hocks, modified).await?;
     if !app.needs_save {
         return Ok(None);
     }
+    let violations = app.root.validate();
+    if !violations.is_empty() {
+        anyhow::bail!("{}", format_violations(&violations));
+    }
     let val = app.root.as_json();
 
-    let c = match ext.as_str() {
-        "json" => serde_json::from_value(val.clone())?,
-        "toml" => {
-            let content = toml::to_string_pretty(&val)?;
-            toml::from_str(&content)?
-        }
-        _ => {
-            anyhow::bail!("unsupported config file extension: {ext}",);
-        }
-    };
-
-    // Write the content based on the format
-    match ext.as_str() {
-        "json" => {
-            let content = serde_json::to_string_pretty(&val)?;
-            tokio::fs::write(&config_path, content)
-                .await
-                .with_context(|| format!("Failed to write {}", config_path.display()))?;
-        }
-        "toml" => {
-            let content = toml::to_string_pretty(&val)?;
-            tokio::fs::write(&config_path, content)
-                .await
-                .with_context(|| format!("Failed to write {}", config_path.display()))?;
-        }
-        _ => {
-            anyhow::bail!("unsupported config file extension: {ext}",);
-        }
+    // Round-trip through the target format so the result matches what is written;
+    // TOML keeps the comments and layout of the existing file
+    let content = format.update_string(&content, &val)?;
+    let c = format.parse_typed::<C>(&content)?;
+
+    tokio::fs::write(&config_path, &content)
+        .await
+        .with_context(|| format!("Failed to write {}", config_path.display()))?;
+
+    if !overrides.is_empty() {
+        return overrides
+            .load(config_path, &content, &schema_json)
+            .map(Some);
     }
 
     Ok(Some(c))
 }
 
-fn to_typed<C: JsonSchema + DeserializeOwned>(s: &str, ext: &str) -> anyhow::Result<C> {
-    let c = match ext {
-        "json" => serde_json::from_str::<C>(s)?,
-        "toml" => toml::from_str::<C>(s)?,
-        _ => {
-            anyhow::bail!("unsupported config file extension: {ext}",);
-        }
-    };
-    Ok(c)
+/// Edit several config files in one TUI session, each mounted as a
+/// top-level submenu.
+///
+/// Returns the paths of the files that were written; empty when the user
+/// quit without saving or nothing changed.
+///
+/// # Errors
+///
+/// Returns errors when a file cannot be parsed or written, or when the
+/// values break the schema constraints.
+pub async fn run_multi(
+    multi: &MultiConfig,
+    elem_hocks: &[ElemHock],
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut app_data = multi.load()?;
+    app_data.elem_hocks = elem_hocks.to_vec();
+    let app = run_ui(app_data)?;
+    if !app.needs_save {
+        return Ok(Vec::new());
+    }
+    multi.save(&app)
 }
 
 async fn get_content_by_ui(
@@ -100,9 +178,21 @@ async fn get_content_by_ui(
     content: &str,
     schema: &serde_json::Value,
     elem_hocks: &[ElemHock],
+    modified: bool,
 ) -> anyhow::Result<AppData> {
     let mut app_data = AppData::new_with_init_and_schema(content, config.as_ref(), schema)?;
     app_data.elem_hocks = elem_hocks.to_vec();
+    app_data.needs_save = modified;
+    run_ui(app_data)
+}
+
+/// Run the TUI on `app_data`, or the line-based fallback without a
+/// terminal.
+fn run_ui(mut app_data: AppData) -> anyhow::Result<AppData> {
+    if !prompt::tui_available() {
+        prompt::edit_stdin(&mut app_data)?;
+        return Ok(app_data);
+    }
 
     let title = app_data.root.title.clone();
     let fields = app_data.root.menu().fields();
@@ -114,6 +204,7 @@ async fn get_content_by_ui(
     }
     // 创建Cursive应用
     let mut siv = Cursive::default();
+    Settings::load().theme.apply(&mut siv);
 
     // 设置AppData为user_data
     siv.set_user_data(app_data);
@@ -131,7 +222,5 @@ async fn get_content_by_ui(
     // 运行应用
     siv.run();
 
-    let app = siv.take_user_data::<AppData>().unwrap();
-    // println!("Data: \n{:#?}", app.root);
-    Ok(app)
+    Ok(siv.take_user_data::<AppData>().unwrap())
 }
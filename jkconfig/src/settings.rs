@@ -0,0 +1,179 @@
+//! User-level jkconfig settings.
+//!
+//! Read from `$XDG_CONFIG_HOME/jkconfig/settings.toml` (falling back to
+//! `~/.config/jkconfig/settings.toml`, or `%APPDATA%\jkconfig\settings.toml`
+//! on Windows), or the file named by `JKCONFIG_SETTINGS`:
+//!
+//! ```toml
+//! language = "en"       # en | zh, defaults to the locale
+//!
+//! [theme]
+//! name = "light"        # auto | classic | dark | light | high-contrast | terminal
+//! accessible = false
+//!
+//! [theme.palette]
+//! highlight = "#005f87"
+//! error = "light red"
+//!
+//! [web]
+//! bind = "127.0.0.1"    # defaults to all interfaces
+//! token = "s3cret"      # required as bearer token, `?token=` or cookie
+//! tls_cert = "cert.pem" # serve HTTPS (needs the `web-tls` feature)
+//! tls_key = "key.pem"
+//! cors_origins = ["https://lab.example.com"] # or ["*"]
+//! ```
+//!
+//! `JKCONFIG_LANG`, `JKCONFIG_THEME`, `JKCONFIG_ACCESSIBLE=1` and
+//! `JKCONFIG_WEB_TOKEN` override the file, and `NO_COLOR` selects the
+//! terminal's own colors unless a theme is named.
+
+use std::{env, net::IpAddr, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    i18n::Lang,
+    ui::theme::{ThemeName, ThemeSettings},
+};
+
+/// Contents of the user settings file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// UI language; the locale decides when unset.
+    pub language: Option<Lang>,
+    pub theme: ThemeSettings,
+    pub web: WebSettings,
+}
+
+/// The `[web]` table of the settings file, for `jkconfig web`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebSettings {
+    /// Address to listen on; all interfaces when unset.
+    pub bind: Option<IpAddr>,
+    /// Token every request must carry; no authentication when unset.
+    pub token: Option<String>,
+    /// PEM certificate chain, serving HTTPS together with `tls_key`.
+    pub tls_cert: Option<PathBuf>,
+    /// PEM private key of `tls_cert`.
+    pub tls_key: Option<PathBuf>,
+    /// Origins allowed to call the API from other sites, `*` for any;
+    /// same-origin only when empty.
+    pub cors_origins: Vec<String>,
+}
+
+impl Settings {
+    /// Location of the settings file, if a config directory is known.
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os("JKCONFIG_SETTINGS") {
+            return Some(path.into());
+        }
+        let dir = if cfg!(windows) {
+            env::var_os("APPDATA").map(PathBuf::from)
+        } else {
+            env::var_os("XDG_CONFIG_HOME")
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        };
+        dir.map(|dir| dir.join("jkconfig").join("settings.toml"))
+    }
+
+    /// Load the settings file and apply environment overrides.
+    ///
+    /// A missing file gives the defaults; an unreadable one is reported on
+    /// stderr and ignored.
+    pub fn load() -> Self {
+        let mut settings = Self::path()
+            .filter(|path| path.exists())
+            .and_then(|path| match Self::from_file(&path) {
+                Ok(settings) => Some(settings),
+                Err(e) => {
+                    eprintln!("Ignoring {}: {e:#}", path.display());
+                    None
+                }
+            })
+            .unwrap_or_default();
+        settings.apply_env(|key| env::var(key).ok());
+        settings
+    }
+
+    fn from_file(path: &std::path::Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) {
+        if let Some(lang) = var("JKCONFIG_LANG") {
+            match Lang::parse(&lang) {
+                Some(lang) => self.language = Some(lang),
+                None => eprintln!("Unknown JKCONFIG_LANG `{lang}`"),
+            }
+        }
+        if let Some(name) = var("JKCONFIG_THEME") {
+            match ThemeName::parse(&name) {
+                Some(name) => self.theme.name = name,
+                None => eprintln!("Unknown JKCONFIG_THEME `{name}`"),
+            }
+        } else if var("NO_COLOR").is_some_and(|v| !v.is_empty())
+            && self.theme.name == ThemeName::Auto
+        {
+            self.theme.name = ThemeName::Terminal;
+        }
+        if let Some(v) = var("JKCONFIG_ACCESSIBLE") {
+            self.theme.accessible = matches!(v.as_str(), "1" | "true" | "yes" | "on");
+        }
+        if let Some(token) = var("JKCONFIG_WEB_TOKEN").filter(|t| !t.is_empty()) {
+            self.web.token = Some(token);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_parse_and_env() {
+        let mut settings: Settings = toml::from_str(
+            r##"
+language = "zh"
+
+[theme]
+name = "high-contrast"
+
+[theme.palette]
+highlight = "#005f87"
+
+[web]
+bind = "127.0.0.1"
+cors_origins = ["*"]
+"##,
+        )
+        .unwrap();
+        assert_eq!(settings.language, Some(Lang::Zh));
+        assert_eq!(settings.theme.name, ThemeName::HighContrast);
+        assert!(!settings.theme.accessible);
+        assert_eq!(settings.theme.palette["highlight"], "#005f87");
+        assert_eq!(settings.web.bind, Some([127, 0, 0, 1].into()));
+        assert_eq!(settings.web.cors_origins, ["*"]);
+        assert_eq!(settings.web.token, None);
+
+        settings.apply_env(|key| match key {
+            "JKCONFIG_THEME" => Some("light".into()),
+            "JKCONFIG_ACCESSIBLE" => Some("1".into()),
+            "JKCONFIG_LANG" => Some("en_US.UTF-8".into()),
+            "JKCONFIG_WEB_TOKEN" => Some("s3cret".into()),
+            _ => None,
+        });
+        assert_eq!(settings.theme.name, ThemeName::Light);
+        assert!(settings.theme.accessible);
+        assert_eq!(settings.language, Some(Lang::En));
+        assert_eq!(settings.web.token.as_deref(), Some("s3cret"));
+
+        let mut settings = Settings::default();
+        settings.apply_env(|key| (key == "NO_COLOR").then(|| "1".into()));
+        assert_eq!(settings.theme.name, ThemeName::Terminal);
+    }
+}
@@ -0,0 +1,85 @@
+use cursive::{
+    Cursive,
+    view::{Nameable, Resizable, Scrollable},
+    views::{Dialog, LinearLayout, SelectView, TextView},
+};
+use serde_json::Value;
+
+use crate::{
+    data::{AppData, changes::PendingChange},
+    t,
+    ui::handle_back,
+};
+
+const CHANGE_LIST: &str = "change_list";
+
+/// 显示待保存的修改 - D 键
+///
+/// 列出相对于已加载配置的全部修改（路径: 旧值 → 新值），回车撤销所选修改。
+pub fn show_changes(s: &mut Cursive) {
+    let Some(app) = s.user_data::<AppData>() else {
+        return;
+    };
+    let changes = app.pending_changes();
+    // 占位路径段，让 Esc / Close 走 handle_back 时不会丢失当前菜单路径
+    app.push_field("D");
+
+    let body = if changes.is_empty() {
+        LinearLayout::vertical().child(TextView::new(t!("changes.empty")))
+    } else {
+        let mut list =
+            SelectView::<String>::new().on_submit(|s, key: &String| confirm_revert(s, key));
+        for change in &changes {
+            list.add_item(format_change(change), change.key.clone());
+        }
+        LinearLayout::vertical()
+            .child(list.with_name(CHANGE_LIST).scrollable().max_height(16))
+            .child(TextView::new(format!("\n{}", t!("changes.hint"))))
+    };
+
+    s.add_layer(
+        Dialog::around(body)
+            .title(t!("changes.title", count = changes.len()))
+            .button(t!("btn.close"), handle_back)
+            .min_width(50),
+    );
+}
+
+fn format_change(change: &PendingChange) -> String {
+    format!(
+        "{}: {} → {}",
+        change.key,
+        format_value(&change.old),
+        format_value(&change.new)
+    )
+}
+
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Null => t!("info.unset").into(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn confirm_revert(s: &mut Cursive, key: &str) {
+    let key = key.to_string();
+    s.add_layer(
+        Dialog::text(t!("changes.revert_confirm", key = key))
+            .title(t!("changes.revert_title"))
+            .button(t!("btn.revert"), move |s| revert(s, &key))
+            .button(t!("btn.cancel"), |s| {
+                s.pop_layer();
+            }),
+    );
+}
+
+fn revert(s: &mut Cursive, key: &str) {
+    s.pop_layer();
+    if let Some(app) = s.user_data::<AppData>() {
+        app.revert_change(key);
+    }
+    // 关闭并重新打开列表，同时刷新菜单
+    handle_back(s);
+    show_changes(s);
+}
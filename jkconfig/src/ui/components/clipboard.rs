@@ -0,0 +1,61 @@
+use std::io::Write;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use cursive::{Cursive, views::Dialog};
+
+use crate::{
+    data::AppData,
+    t,
+    ui::components::menu::{menu_flush, menu_selected},
+};
+
+/// 复制选中项的值 - Y 键
+///
+/// 值以 JSON 保存在内部剪贴板，同时通过 OSC 52 写入终端的系统剪贴板。
+pub fn on_copy(s: &mut Cursive) {
+    let Some(selected) = menu_selected(s) else {
+        return;
+    };
+    let key = selected.key();
+    let Some(app) = s.user_data::<AppData>() else {
+        return;
+    };
+    let text = match app.copy(&key) {
+        Ok(value) => serde_json::to_string_pretty(value).unwrap_or_default(),
+        Err(e) => {
+            s.add_layer(
+                Dialog::info(t!("copy.failed", error = format!("{e:#}"))).title(t!("copy.title")),
+            );
+            return;
+        }
+    };
+    copy_to_terminal(&text);
+    info!("Copied {key} to clipboard");
+    s.add_layer(Dialog::info(t!("copy.done", key = key)).title(t!("copy.title")));
+}
+
+/// 用剪贴板内容替换选中项 - V 键
+pub fn on_paste(s: &mut Cursive) {
+    let Some(selected) = menu_selected(s) else {
+        return;
+    };
+    let key = selected.key();
+    let Some(app) = s.user_data::<AppData>() else {
+        return;
+    };
+    if let Err(e) = app.paste(&key) {
+        s.add_layer(
+            Dialog::info(t!("paste.failed", error = format!("{e:#}"))).title(t!("paste.title")),
+        );
+        return;
+    }
+    info!("Pasted clipboard to {key}");
+    menu_flush(s);
+}
+
+/// 通过 OSC 52 转义序列设置终端的系统剪贴板，终端不支持时会被忽略
+fn copy_to_terminal(text: &str) {
+    let mut out = std::io::stdout();
+    let _ = write!(out, "\x1b]52;c;{}\x07", STANDARD.encode(text));
+    let _ = out.flush();
+}
@@ -0,0 +1,131 @@
+use std::path::PathBuf;
+
+use cursive::{
+    Cursive,
+    view::{Nameable, Resizable, Scrollable},
+    views::{Dialog, EditView, LinearLayout, SelectView, TextView},
+};
+use serde_json::Value;
+
+use crate::{
+    data::{AppData, changes::PendingChange},
+    t,
+    ui::handle_back,
+};
+
+const DEFCONFIG_LIST: &str = "defconfig_list";
+const DEFCONFIG_PATH: &str = "defconfig_path";
+
+/// 显示与默认值不同的配置项 - O 键
+///
+/// 类似 `make savedefconfig`：列出所有偏离 schema 默认值的项（路径: 当前值，默认值），
+/// 回车将所选项恢复为默认值，Export 导出仅包含这些项的最小配置文件。
+pub fn show_non_defaults(s: &mut Cursive) {
+    let Some(app) = s.user_data::<AppData>() else {
+        return;
+    };
+    let values = app.non_default_values();
+    // 占位路径段，让 Esc / Close 走 handle_back 时不会丢失当前菜单路径
+    app.push_field("O");
+
+    let body = if values.is_empty() {
+        LinearLayout::vertical().child(TextView::new(t!("defconfig.empty")))
+    } else {
+        let mut list =
+            SelectView::<String>::new().on_submit(|s, key: &String| confirm_reset(s, key));
+        for value in &values {
+            list.add_item(format_value(value), value.key.clone());
+        }
+        LinearLayout::vertical()
+            .child(list.with_name(DEFCONFIG_LIST).scrollable().max_height(16))
+            .child(TextView::new(format!("\n{}", t!("defconfig.hint"))))
+    };
+
+    s.add_layer(
+        Dialog::around(body)
+            .title(t!("defconfig.title", count = values.len()))
+            .button(t!("btn.export"), show_export)
+            .button(t!("btn.close"), handle_back)
+            .min_width(50),
+    );
+}
+
+fn format_value(value: &PendingChange) -> String {
+    let show = |v: &Value| match v {
+        Value::Null => t!("info.unset").to_string(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    t!(
+        "defconfig.item",
+        key = value.key,
+        value = show(&value.new),
+        default = show(&value.old)
+    )
+}
+
+fn confirm_reset(s: &mut Cursive, key: &str) {
+    let key = key.to_string();
+    s.add_layer(
+        Dialog::text(t!("defconfig.reset_confirm", key = key))
+            .title(t!("defconfig.reset_title"))
+            .button(t!("btn.reset"), move |s| reset(s, &key))
+            .button(t!("btn.cancel"), |s| {
+                s.pop_layer();
+            }),
+    );
+}
+
+fn reset(s: &mut Cursive, key: &str) {
+    s.pop_layer();
+    if let Some(app) = s.user_data::<AppData>() {
+        let _ = app.reset_to_default(key);
+    }
+    // 关闭并重新打开列表，同时刷新菜单
+    handle_back(s);
+    show_non_defaults(s);
+}
+
+fn show_export(s: &mut Cursive) {
+    let Some(path) = s.user_data::<AppData>().map(|app| app.defconfig_path()) else {
+        return;
+    };
+    s.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(TextView::new(t!("defconfig.path")))
+                .child(
+                    EditView::new()
+                        .content(path.display().to_string())
+                        .on_submit(export)
+                        .with_name(DEFCONFIG_PATH)
+                        .fixed_width(60),
+                ),
+        )
+        .title(t!("defconfig.export_title"))
+        .button(t!("btn.save"), |s| {
+            let path = s
+                .call_on_name(DEFCONFIG_PATH, |v: &mut EditView| v.get_content())
+                .unwrap_or_default();
+            export(s, &path);
+        })
+        .button(t!("btn.cancel"), |s| {
+            s.pop_layer();
+        }),
+    );
+}
+
+fn export(s: &mut Cursive, path: &str) {
+    let path = PathBuf::from(path.trim());
+    let Some(app) = s.user_data::<AppData>() else {
+        return;
+    };
+    let message = match app.save_defconfig(&path) {
+        Ok(()) => {
+            s.pop_layer();
+            t!("defconfig.exported", path = path.display())
+        }
+        Err(e) => t!("defconfig.export_failed", error = format!("{e:#}")),
+    };
+    s.add_layer(Dialog::info(message).title(t!("defconfig.export_title")));
+}
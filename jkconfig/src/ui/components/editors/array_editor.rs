@@ -7,8 +7,10 @@ use cursive::{
     views::{Dialog, DummyView, EditView, LinearLayout, OnEventView, Panel, SelectView, TextView},
 };
 
+use super::show_constraint_error;
 use crate::{
-    data::{item::ItemType, types::ElementType},
+    data::{AppData, constraint::Constraints, item::ItemType, types::ElementType},
+    t,
     ui::handle_back,
 };
 
@@ -29,23 +31,29 @@ pub fn show_array_edit(s: &mut Cursive, key: &str, title: &str, values: &[String
     // Add "Add new item" option
     let mut add_label = StyledString::new();
     add_label.append_styled("➕ ", ColorStyle::tertiary());
-    add_label.append_styled("Add new item", Style::from(Effect::Italic));
+    add_label.append_styled(t!("array.add"), Style::from(Effect::Italic));
     select.add_item(add_label, usize::MAX);
 
     // Create help text
     let mut help_text = StyledString::new();
     help_text.append_styled("Enter", Style::from(Effect::Bold));
-    help_text.append_plain(" Edit/Add  ");
+    help_text.append_plain(format!(" {}  ", t!("key.edit_add")));
     help_text.append_styled("Del", Style::from(Effect::Bold));
-    help_text.append_plain(" Delete  ");
+    help_text.append_plain(format!(" {}  ", t!("key.delete")));
+    help_text.append_styled("D", Style::from(Effect::Bold));
+    help_text.append_plain(format!(" {}  ", t!("key.duplicate")));
     help_text.append_styled("Esc", Style::from(Effect::Bold));
-    help_text.append_plain(" Back");
+    help_text.append_plain(format!(" {}", t!("key.back")));
+    if let Some(hint) = array_hint(s, key) {
+        help_text.append_plain("\n");
+        help_text.append_styled(t!("info.constraints", hint = hint), ColorStyle::secondary());
+    }
 
     s.add_layer(
         OnEventView::new(
             Dialog::around(
                 LinearLayout::vertical()
-                    .child(TextView::new(format!("📋 Array Editor: {}", title)).center())
+                    .child(TextView::new(t!("array.heading", title = title)).center())
                     .child(DummyView)
                     .child(
                         Panel::new(
@@ -54,21 +62,23 @@ pub fn show_array_edit(s: &mut Cursive, key: &str, title: &str, values: &[String
                                 .scrollable()
                                 .fixed_height(15),
                         )
-                        .title(format!("Items ({})", values.len()))
+                        .title(t!("array.items", count = values.len()))
                         .full_width(),
                     )
                     .child(DummyView)
                     .child(Panel::new(TextView::new(help_text)).full_width()),
             )
-            .title("Array Editor")
-            .button("Done", move |s| {
+            .title(t!("array.title"))
+            .button(t!("btn.done"), move |s| {
                 handle_back(s);
             }),
         )
         .on_event(Key::Enter, move |s| {
             on_enter(s, &key_clone);
         })
-        .on_event(Key::Del, on_delete),
+        .on_event(Key::Del, on_delete)
+        .on_event('d', on_duplicate)
+        .on_event('D', on_duplicate),
     );
 }
 
@@ -110,67 +120,123 @@ fn on_delete(s: &mut Cursive) {
         s.add_layer(
             Dialog::around(
                 LinearLayout::vertical()
-                    .child(TextView::new(
-                        "⚠️  Are you sure you want to delete this item?",
-                    ))
+                    .child(TextView::new(t!("array.delete_confirm")))
                     .child(DummyView)
                     .child(TextView::new(format!("  [{}] {}", idx, value))),
             )
-            .title("Confirm Delete")
-            .button("Yes", move |s| {
-                if let Some(app) = s.user_data::<crate::data::app_data::AppData>()
-                    && let Some(ElementType::Item(item)) = app.current_mut()
-                    && let ItemType::Array(array_item) = &mut item.item_type
-                    && *idx < array_item.values.len()
-                {
-                    array_item.values.remove(*idx);
-                    s.pop_layer(); // Close confirm dialog
-                    refresh_array_view(s);
+            .title(t!("array.delete_title"))
+            .button(t!("btn.yes"), move |s| {
+                if let Some(app) = s.user_data::<crate::data::app_data::AppData>() {
+                    let key = app.key_string();
+                    let removed = app.edit(&key, |elem| {
+                        if let ElementType::Item(item) = elem
+                            && let ItemType::Array(array_item) = &mut item.item_type
+                            && *idx < array_item.values.len()
+                        {
+                            array_item.values.remove(*idx);
+                            true
+                        } else {
+                            false
+                        }
+                    });
+                    if removed == Some(true) {
+                        s.pop_layer(); // Close confirm dialog
+                        refresh_array_view(s);
+                    }
                 }
             })
-            .button("No", |s| {
+            .button(t!("btn.no"), |s| {
                 s.pop_layer();
             }),
         );
     }
 }
 
+/// 在选中元素之后插入一份副本
+fn on_duplicate(s: &mut Cursive) {
+    let selection = s
+        .call_on_name("array_select", |v: &mut SelectView<usize>| v.selection())
+        .unwrap();
+    let Some(idx) = selection.map(|idx| *idx) else {
+        return;
+    };
+    let Some(app) = s.user_data::<AppData>() else {
+        return;
+    };
+    let key = app.key_string();
+    let value = match app.root.get_by_key(&key) {
+        Some(ElementType::Item(item)) => match &item.item_type {
+            ItemType::Array(array_item) if idx < array_item.values.len() => {
+                array_item.values[idx].clone()
+            }
+            _ => return,
+        },
+        _ => return,
+    };
+    if let Err(e) = check_array_edit(s, &key, None, &value) {
+        show_constraint_error(s, &e);
+        return;
+    }
+    if let Some(app) = s.user_data::<AppData>() {
+        app.edit(&key, |elem| {
+            if let ElementType::Item(item) = elem
+                && let ItemType::Array(array_item) = &mut item.item_type
+            {
+                array_item.values.insert(idx + 1, value);
+            }
+        });
+    }
+    refresh_array_view(s);
+}
+
 fn show_add_item_dialog(s: &mut Cursive, key: &str) {
     let key = key.to_string();
     s.add_layer(
         Dialog::around(
             LinearLayout::vertical()
-                .child(TextView::new("➕ Enter new value:"))
+                .child(TextView::new(t!("array.new_value")))
                 .child(DummyView)
                 .child(
                     Panel::new(EditView::new().with_name("new_item_value").fixed_width(48))
-                        .title("Value"),
+                        .title(t!("array.value")),
                 ),
         )
-        .title("Add Item")
-        .button("Add", move |s| {
+        .title(t!("array.add_title"))
+        .button(t!("btn.add"), move |s| {
             let content = s
                 .call_on_name("new_item_value", |v: &mut EditView| v.get_content())
                 .unwrap();
 
             if !content.is_empty() {
-                if let Some(app) = s.user_data::<crate::data::app_data::AppData>()
-                    && let Some(ElementType::Item(item)) = app.root.get_mut_by_key(&key)
-                    && let ItemType::Array(array_item) = &mut item.item_type
-                {
-                    array_item.values.push(content.to_string());
-                    s.pop_layer(); // Close add dialog
-                    refresh_array_view(s);
+                if let Err(e) = check_array_edit(s, &key, None, &content) {
+                    show_constraint_error(s, &e);
+                    return;
+                }
+                if let Some(app) = s.user_data::<crate::data::app_data::AppData>() {
+                    let added = app.edit(&key, |elem| {
+                        if let ElementType::Item(item) = elem
+                            && let ItemType::Array(array_item) = &mut item.item_type
+                        {
+                            array_item.values.push(content.to_string());
+                            true
+                        } else {
+                            false
+                        }
+                    });
+                    if added == Some(true) {
+                        s.pop_layer(); // Close add dialog
+                        refresh_array_view(s);
+                    }
                 }
             } else {
                 s.add_layer(
-                    Dialog::text("⚠️  Value cannot be empty!")
-                        .title("Error")
-                        .dismiss_button("OK"),
+                    Dialog::text(t!("array.empty_value"))
+                        .title(t!("dialog.error"))
+                        .dismiss_button(t!("btn.ok")),
                 );
             }
         })
-        .button("Cancel", |s| {
+        .button(t!("btn.cancel"), |s| {
             s.pop_layer();
         }),
     );
@@ -193,7 +259,7 @@ fn show_edit_item_dialog(s: &mut Cursive, key: &str, idx: usize) {
     s.add_layer(
         Dialog::around(
             LinearLayout::vertical()
-                .child(TextView::new(format!("✏️  Edit item [{}]:", idx)))
+                .child(TextView::new(t!("array.edit_item", idx = idx)))
                 .child(DummyView)
                 .child(
                     Panel::new(
@@ -202,39 +268,103 @@ fn show_edit_item_dialog(s: &mut Cursive, key: &str, idx: usize) {
                             .with_name("edit_item_value")
                             .fixed_width(48),
                     )
-                    .title("Value"),
+                    .title(t!("array.value")),
                 ),
         )
-        .title("Edit Item")
-        .button("Save", move |s| {
+        .title(t!("array.edit_title"))
+        .button(t!("btn.save"), move |s| {
             let content = s
                 .call_on_name("edit_item_value", |v: &mut EditView| v.get_content())
                 .unwrap();
 
             if !content.is_empty() {
-                if let Some(app) = s.user_data::<crate::data::app_data::AppData>()
-                    && let Some(ElementType::Item(item)) = app.root.get_mut_by_key(&key)
-                    && let ItemType::Array(array_item) = &mut item.item_type
-                    && idx < array_item.values.len()
-                {
-                    array_item.values[idx] = content.to_string();
-                    s.pop_layer(); // Close edit dialog
-                    refresh_array_view(s);
+                if let Err(e) = check_array_edit(s, &key, Some(idx), &content) {
+                    show_constraint_error(s, &e);
+                    return;
+                }
+                if let Some(app) = s.user_data::<crate::data::app_data::AppData>() {
+                    let saved = app.edit(&key, |elem| {
+                        if let ElementType::Item(item) = elem
+                            && let ItemType::Array(array_item) = &mut item.item_type
+                            && idx < array_item.values.len()
+                        {
+                            array_item.values[idx] = content.to_string();
+                            true
+                        } else {
+                            false
+                        }
+                    });
+                    if saved == Some(true) {
+                        s.pop_layer(); // Close edit dialog
+                        refresh_array_view(s);
+                    }
                 }
             } else {
                 s.add_layer(
-                    Dialog::text("⚠️  Value cannot be empty!")
-                        .title("Error")
-                        .dismiss_button("OK"),
+                    Dialog::text(t!("array.empty_value"))
+                        .title(t!("dialog.error"))
+                        .dismiss_button(t!("btn.ok")),
                 );
             }
         })
-        .button("Cancel", |s| {
+        .button(t!("btn.cancel"), |s| {
             s.pop_layer();
         }),
     );
 }
 
+/// 数组及其元素的约束提示
+fn array_hint(s: &mut Cursive, key: &str) -> Option<String> {
+    let app = s.user_data::<AppData>()?;
+    let Some(ElementType::Item(item)) = app.root.get_by_key(key) else {
+        return None;
+    };
+    let ItemType::Array(array_item) = &item.item_type else {
+        return None;
+    };
+    let parts: Vec<String> = [
+        item.constraints.describe(),
+        array_item
+            .item_constraints
+            .describe()
+            .map(|d| t!("array.each_item", hint = d)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    (!parts.is_empty()).then(|| parts.join("; "))
+}
+
+/// 校验新增（`idx` 为 None）或修改后的数组；`minItems` 留到保存时检查
+fn check_array_edit(
+    s: &mut Cursive,
+    key: &str,
+    idx: Option<usize>,
+    content: &str,
+) -> Result<(), String> {
+    let Some(app) = s.user_data::<AppData>() else {
+        return Ok(());
+    };
+    let Some(ElementType::Item(item)) = app.root.get_by_key(key) else {
+        return Ok(());
+    };
+    let ItemType::Array(array_item) = &item.item_type else {
+        return Ok(());
+    };
+    array_item.check_element(content)?;
+
+    let mut values = array_item.values.clone();
+    match idx {
+        Some(i) if i < values.len() => values[i] = content.to_string(),
+        _ => values.push(content.to_string()),
+    }
+    Constraints {
+        min_items: None,
+        ..(*item.constraints).clone()
+    }
+    .check_items(&values)
+}
+
 fn refresh_array_view(s: &mut Cursive) {
     // Get current array values
     let values = if let Some(app) = s.user_data::<crate::data::app_data::AppData>()
@@ -259,7 +389,7 @@ fn refresh_array_view(s: &mut Cursive) {
         // Re-add "Add new item" option
         let mut add_label = StyledString::new();
         add_label.append_styled("➕ ", ColorStyle::tertiary());
-        add_label.append_styled("Add new item", Style::from(Effect::Italic));
+        add_label.append_styled(t!("array.add"), Style::from(Effect::Italic));
         view.add_item(add_label, usize::MAX);
     });
 }
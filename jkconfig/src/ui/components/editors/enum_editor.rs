@@ -11,6 +11,7 @@ use crate::{
         item::{EnumItem, ItemType},
         types::ElementType,
     },
+    t,
     ui::handle_back,
 };
 
@@ -31,13 +32,13 @@ pub fn show_enum_select(s: &mut Cursive, title: &str, enum_item: &EnumItem) {
         OnEventView::new(
             Dialog::around(
                 LinearLayout::vertical()
-                    .child(TextView::new(format!("Select: {}", title)))
+                    .child(TextView::new(t!("edit.select", title = title)))
                     .child(DummyView)
                     .child(select.with_name("enum_select").fixed_height(10)),
             )
-            .title("Select Option")
-            .button("OK", on_ok)
-            .button("Cancel", handle_back),
+            .title(t!("edit.select_option"))
+            .button(t!("btn.ok"), on_ok)
+            .button(t!("btn.cancel"), handle_back),
         )
         .on_event(Key::Enter, on_ok),
     );
@@ -51,11 +52,15 @@ fn on_ok(s: &mut Cursive) {
         return;
     };
 
-    if let Some(app) = s.user_data::<crate::data::app_data::AppData>()
-        && let Some(ElementType::Item(item)) = app.current_mut()
-        && let ItemType::Enum(en) = &mut item.item_type
-    {
-        en.value = Some(*selection);
+    if let Some(app) = s.user_data::<crate::data::app_data::AppData>() {
+        let key = app.key_string();
+        app.edit(&key, |elem| {
+            if let ElementType::Item(item) = elem
+                && let ItemType::Enum(en) = &mut item.item_type
+            {
+                en.value = Some(*selection);
+            }
+        });
     }
     handle_back(s);
 }
@@ -82,15 +87,15 @@ pub fn show_list_select(
         OnEventView::new(
             Dialog::around(
                 LinearLayout::vertical()
-                    .child(TextView::new(format!("Select: {}", title)))
+                    .child(TextView::new(t!("edit.select", title = title)))
                     .child(DummyView)
                     .child(select.with_name("list_select").fixed_height(10)),
             )
-            .title("Select Item")
-            .button("OK", move |s| {
+            .title(t!("edit.select_item"))
+            .button(t!("btn.ok"), move |s| {
                 on_list_ok(s, &items1, &path1, on_ok);
             })
-            .button("Cancel", handle_back),
+            .button(t!("btn.cancel"), handle_back),
         )
         .on_event(Key::Enter, move |s| {
             on_list_ok(s, &items2, &path2, on_ok);
@@ -115,7 +120,11 @@ fn on_list_ok(
         return;
     };
 
+    let before = app.root.get_by_key(path).cloned();
     on_ok(app, path, ls[*selection].as_str());
+    if let Some(before) = before {
+        app.record_edit(path, before);
+    }
 
     handle_back(s);
 }
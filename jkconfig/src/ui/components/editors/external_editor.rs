@@ -0,0 +1,140 @@
+use std::{env, fs, io::Write, process::Command};
+
+use anyhow::{Context, bail};
+use cursive::{
+    Cursive,
+    backends::crossterm::crossterm::{
+        cursor,
+        event::{DisableMouseCapture, EnableMouseCapture},
+        execute,
+        terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+    },
+    views::Dialog,
+};
+
+use super::show_constraint_error;
+use crate::{
+    data::{AppData, item::ItemType, types::ElementType},
+    t,
+};
+
+/// 用 `$VISUAL` / `$EDITOR` 编辑 key 对应的字符串或数组项
+///
+/// 暂停 TUI，在临时文件中打开外部编辑器，退出后读回内容并写入配置。
+/// 数组每行一个元素。返回值是否已更新。
+pub fn edit_in_external_editor(s: &mut Cursive, key: &str) -> bool {
+    let Some(ElementType::Item(item)) = s
+        .user_data::<AppData>()
+        .and_then(|app| app.root.get_by_key(key).cloned())
+    else {
+        return false;
+    };
+    let initial = match &item.item_type {
+        ItemType::String { value, default } => value
+            .clone()
+            .or_else(|| default.clone())
+            .unwrap_or_default(),
+        ItemType::Array(array) => array.values.join("\n"),
+        _ => return false,
+    };
+
+    let edited = match run_editor(s, &initial) {
+        Ok(edited) => edited,
+        Err(e) => {
+            s.add_layer(
+                Dialog::info(t!("editor.failed", error = format!("{e:#}")))
+                    .title(t!("editor.title")),
+            );
+            return false;
+        }
+    };
+
+    let mut updated = item;
+    match &mut updated.item_type {
+        ItemType::String { value, .. } => *value = Some(edited),
+        ItemType::Array(array) => {
+            array.values = edited
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .map(String::from)
+                .collect();
+        }
+        _ => unreachable!(),
+    }
+    if let Err(e) = updated.validate() {
+        show_constraint_error(s, &e);
+        return false;
+    }
+
+    info!("Updated {} from external editor", key);
+    if let Some(app) = s.user_data::<AppData>() {
+        app.edit(key, |elem| *elem = ElementType::Item(updated));
+    }
+    true
+}
+
+/// 编辑器命令：`$VISUAL`，其次 `$EDITOR`，都未设置时使用平台默认编辑器
+fn editor_command() -> Vec<String> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|k| env::var(k).ok())
+        .find(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    editor.split_whitespace().map(String::from).collect()
+}
+
+fn run_editor(s: &mut Cursive, initial: &str) -> anyhow::Result<String> {
+    // 放在新建的私有目录中，编辑器替换文件时也不会与其他进程冲突
+    let dir = tempfile::Builder::new().prefix("jkconfig-").tempdir()?;
+    let path = dir.path().join("value.txt");
+    fs::write(&path, initial)?;
+
+    let command = editor_command();
+    suspend_terminal()?;
+    let status = Command::new(&command[0])
+        .args(&command[1..])
+        .arg(&path)
+        .status();
+    resume_terminal()?;
+    // 外部程序覆盖了屏幕，强制完整重绘
+    s.clear();
+
+    let result = (|| {
+        let editor = command.join(" ");
+        let status = status.with_context(|| t!("editor.spawn_failed", editor = editor))?;
+        if !status.success() {
+            bail!(t!("editor.exit_status", editor = editor, status = status));
+        }
+        let mut content = fs::read_to_string(&path)?;
+        // 编辑器通常会在末尾补一个换行
+        if content.ends_with('\n') {
+            content.pop();
+            if content.ends_with('\r') {
+                content.pop();
+            }
+        }
+        Ok(content)
+    })();
+    drop(dir);
+    result
+}
+
+fn suspend_terminal() -> anyhow::Result<()> {
+    let mut out = std::io::stdout();
+    execute!(out, LeaveAlternateScreen, DisableMouseCapture, cursor::Show)?;
+    disable_raw_mode()?;
+    out.flush()?;
+    Ok(())
+}
+
+fn resume_terminal() -> anyhow::Result<()> {
+    enable_raw_mode()?;
+    execute!(
+        std::io::stdout(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        cursor::Hide
+    )?;
+    Ok(())
+}
@@ -3,6 +3,7 @@ use std::{collections::HashMap, path::Path};
 
 use crate::{
     data::{app_data::AppData, item::ItemType, types::ElementType},
+    t,
     ui::{
         components::editors::multi_select_editor::{
             DepItem, ExtendedMultiSelectItem, show_extended_multi_select,
@@ -109,13 +110,12 @@ pub fn show_feature_select(
                 // 显示扩展多选对话框
                 show_extended_multi_select(
                     s,
-                    &format!("Features for {}", package),
+                    &t!("features.title", package = package),
                     &extended_multi_select_item,
                 );
             }
             None => {
-                let mut dialog =
-                    Dialog::info(format!("Package '{}' not found in Cargo.toml", package));
+                let mut dialog = Dialog::info(t!("features.not_found", package = package));
                 dialog
                     .buttons_mut()
                     .next()
@@ -4,8 +4,13 @@ use cursive::{
     views::{Dialog, DummyView, EditView, LinearLayout, TextView},
 };
 
+use super::{constraint_hint, item_constraints, show_constraint_error, spin_control};
 use crate::{
-    data::{item::ItemType, types::ElementType},
+    data::{
+        item::{IntDisplay, ItemType, parse_int},
+        types::ElementType,
+    },
+    t,
     ui::handle_back,
 };
 
@@ -14,48 +19,66 @@ pub fn show_integer_edit(
     s: &mut Cursive,
     key: &str,
     title: &str,
-    value: Option<i64>,
-    default: Option<i64>,
+    value: Option<i128>,
+    default: Option<i128>,
+    display: IntDisplay,
 ) {
-    let initial = value.or(default).map(|v| v.to_string()).unwrap_or_default();
+    let initial = value
+        .or(default)
+        .map(|v| display.format(v))
+        .unwrap_or_default();
     let key = key.to_string();
+    let constraints = item_constraints(s, &key);
+
+    let mut layout =
+        LinearLayout::vertical().child(TextView::new(t!("edit.heading", title = title)));
+    if let Some(hint) = constraint_hint(&constraints) {
+        layout.add_child(hint);
+    }
+    layout.add_child(TextView::new(t!("edit.int_hint")));
+    layout.add_child(TextView::new(t!("edit.spin_hint")));
+    layout.add_child(DummyView);
+    layout.add_child(spin_control(
+        EditView::new()
+            .content(initial)
+            .with_name("edit_value")
+            .fixed_width(30),
+        &key,
+    ));
 
     s.add_layer(
-        Dialog::around(
-            LinearLayout::vertical()
-                .child(TextView::new(format!("Edit: {}", title)))
-                .child(DummyView)
-                .child(
-                    EditView::new()
-                        .content(initial)
-                        .with_name("edit_value")
-                        .fixed_width(30),
-                ),
-        )
-        .title("Edit Integer")
-        .button("OK", move |s| {
-            let content = s
-                .call_on_name("edit_value", |v: &mut EditView| v.get_content())
-                .unwrap();
+        Dialog::around(layout)
+            .title(t!("edit.integer"))
+            .button(t!("btn.ok"), move |s| {
+                let content = s
+                    .call_on_name("edit_value", |v: &mut EditView| v.get_content())
+                    .unwrap();
 
-            match content.parse::<i64>() {
-                Ok(num) => {
-                    info!("Setting integer value for key {}: {}", key, num);
+                match parse_int(&content) {
+                    Ok(num) => {
+                        if let Err(e) = constraints.check_number(num as f64) {
+                            show_constraint_error(s, &e);
+                            return;
+                        }
+                        info!("Setting integer value for key {}: {}", key, num);
 
-                    if let Some(app) = s.user_data::<crate::data::app_data::AppData>()
-                        && let Some(ElementType::Item(item)) = app.root.get_mut_by_key(&key)
-                        && let ItemType::Integer { value, .. } = &mut item.item_type
-                    {
-                        info!("Old value: {:?}", value);
-                        *value = Some(num);
+                        if let Some(app) = s.user_data::<crate::data::app_data::AppData>() {
+                            app.edit(&key, |elem| {
+                                if let ElementType::Item(item) = elem
+                                    && let ItemType::Integer { value, .. } = &mut item.item_type
+                                {
+                                    info!("Old value: {:?}", value);
+                                    *value = Some(num);
+                                }
+                            });
+                        }
+                        handle_back(s);
+                    }
+                    Err(e) => {
+                        s.add_layer(Dialog::info(e).dismiss_button(t!("btn.ok")));
                     }
-                    handle_back(s);
-                }
-                Err(_) => {
-                    s.add_layer(Dialog::info("Invalid integer format!").dismiss_button("Ok"));
                 }
-            }
-        })
-        .button("Cancel", handle_back),
+            })
+            .button(t!("btn.cancel"), handle_back),
     );
 }
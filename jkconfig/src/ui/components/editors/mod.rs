@@ -1,17 +1,106 @@
 pub mod array_editor;
 pub mod enum_editor;
+pub mod external_editor;
 mod feature_select;
 pub mod integer_editor;
 pub mod multi_select_editor;
 pub mod number_editor;
 pub mod oneof_editor;
+pub mod path_picker;
+pub mod slider_editor;
 pub mod string_editor;
 
 pub use array_editor::show_array_edit;
 pub use enum_editor::{show_enum_select, show_list_select};
+pub use external_editor::edit_in_external_editor;
 pub use feature_select::show_feature_select;
 pub use integer_editor::show_integer_edit;
 pub use multi_select_editor::{create_multi_select_from_array_item, show_multi_select};
 pub use number_editor::show_number_edit;
 pub use oneof_editor::show_oneof_dialog;
+pub use path_picker::show_path_picker;
+pub use slider_editor::{has_slider, show_slider_edit};
 pub use string_editor::show_string_edit;
+
+use cursive::{
+    Cursive,
+    event::Key,
+    theme::ColorStyle,
+    view::View,
+    views::{EditView, OnEventView, TextView},
+};
+
+use crate::{
+    data::{
+        AppData,
+        constraint::Constraints,
+        item::{ItemType, parse_int},
+        types::ElementType,
+    },
+    t,
+};
+
+/// 查找指定 key 对应项的 schema 约束
+pub(crate) fn item_constraints(s: &mut Cursive, key: &str) -> Constraints {
+    s.user_data::<AppData>()
+        .and_then(|app| match app.root.get_by_key(key) {
+            Some(ElementType::Item(item)) => Some((*item.constraints).clone()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// 约束提示行，无约束时返回 None
+pub(crate) fn constraint_hint(constraints: &Constraints) -> Option<TextView> {
+    constraints.describe().map(|hint| {
+        TextView::new(t!("info.constraints", hint = hint)).style(ColorStyle::secondary())
+    })
+}
+
+/// 为数值输入框 `edit_value` 加上 ↑/↓ 微调
+pub(crate) fn spin_control<V: View>(view: V, key: &str) -> OnEventView<V> {
+    let up = key.to_string();
+    let down = key.to_string();
+    OnEventView::new(view)
+        .on_event(Key::Up, move |s| spin(s, &up, 1))
+        .on_event(Key::Down, move |s| spin(s, &down, -1))
+}
+
+/// 按 [`Item::step`](crate::data::item::Item::step) 的步长和范围调整输入框中的值
+fn spin(s: &mut Cursive, key: &str, delta: i32) {
+    let Some(ElementType::Item(mut item)) = s
+        .user_data::<AppData>()
+        .and_then(|app| app.root.get_by_key(key).cloned())
+    else {
+        return;
+    };
+    let Some(content) = s.call_on_name("edit_value", |v: &mut EditView| v.get_content()) else {
+        return;
+    };
+    match &mut item.item_type {
+        ItemType::Integer { value, .. } => *value = parse_int(&content).ok(),
+        ItemType::Number { value, .. } => *value = content.trim().parse().ok(),
+        _ => return,
+    }
+    if !item.step(delta) {
+        return;
+    }
+    let text = match item.item_type {
+        ItemType::Integer {
+            value: Some(v),
+            display,
+            ..
+        } => display.format(v),
+        ItemType::Number { value: Some(v), .. } => v.to_string(),
+        _ => return,
+    };
+    s.call_on_name("edit_value", |v: &mut EditView| v.set_content(text));
+}
+
+/// 显示约束校验失败的提示
+pub(crate) fn show_constraint_error(s: &mut Cursive, message: &str) {
+    s.add_layer(
+        cursive::views::Dialog::info(t!("edit.invalid_value", error = message))
+            .title(t!("dialog.error")),
+    );
+}
@@ -5,7 +5,10 @@ use cursive::{
     views::{DummyView, LinearLayout, OnEventView, ScrollView, SelectView, TextView},
 };
 
-use crate::data::{app_data::AppData, item::ItemType, types::ElementType};
+use crate::{
+    data::{app_data::AppData, item::ItemType, types::ElementType},
+    t,
+};
 
 /// 多选项结构体
 #[derive(Debug, Clone)]
@@ -68,9 +71,9 @@ pub fn show_multi_select(s: &mut Cursive, title: &str, multi_select: &MultiSelec
     // 添加所有选项到SelectView，使用更美观的标记
     for (idx, variant) in multi_select.variants.iter().enumerate() {
         let label = if multi_select.selected_indices.contains(&idx) {
-            format!("✓ {}  [已选择]", variant) // 已选中 - 使用对勾符号
+            t!("multi.selected", name = variant) // 已选中 - 使用对勾符号
         } else {
-            format!("○ {}  [未选择]", variant) // 未选中 - 使用圆圈符号
+            t!("multi.unselected", name = variant) // 未选中 - 使用圆圈符号
         };
         select.add_item(label, idx);
     }
@@ -97,10 +100,10 @@ pub fn show_multi_select(s: &mut Cursive, title: &str, multi_select: &MultiSelec
         TextView::new(format!("📋 {}", title)).style(cursive::theme::ColorStyle::title_primary());
 
     // 创建状态栏
-    let status_text = TextView::new(format!(
-        "已选择 {} / {} 项 | Enter: 切换选择 | ESC: 退出",
-        multi_select.selected_indices.len(),
-        multi_select.variants.len()
+    let status_text = TextView::new(t!(
+        "multi.status",
+        selected = multi_select.selected_indices.len(),
+        total = multi_select.variants.len()
     ))
     .style(cursive::theme::ColorStyle::secondary())
     .with_name("status_text");
@@ -119,8 +122,7 @@ pub fn show_multi_select(s: &mut Cursive, title: &str, multi_select: &MultiSelec
         .child(DummyView);
 
     // 创建提示文本
-    let hint_text = TextView::new("💡 提示: 选择后自动保存，无需确认")
-        .style(cursive::theme::ColorStyle::tertiary());
+    let hint_text = TextView::new(t!("multi.hint")).style(cursive::theme::ColorStyle::tertiary());
 
     // 创建全屏对话框容器
     let fullscreen_dialog = cursive::views::Panel::new(
@@ -130,7 +132,7 @@ pub fn show_multi_select(s: &mut Cursive, title: &str, multi_select: &MultiSelec
             .child(hint_text)
             .child(DummyView),
     )
-    .title("🌟 多选界面");
+    .title(t!("multi.title"));
 
     // 添加全屏层
     s.add_fullscreen_layer(
@@ -196,10 +198,17 @@ fn toggle_selection(s: &mut Cursive) {
                 .filter_map(|&idx| variants.get(idx).cloned())
                 .collect();
 
-            if let Some(ElementType::Item(item_mut)) = app.root.get_mut_by_key(&current_key)
-                && let ItemType::Array(array_mut) = &mut item_mut.item_type
-            {
-                array_mut.values = selected_variants.clone();
+            let updated = app.edit(&current_key, |elem| {
+                if let ElementType::Item(item_mut) = elem
+                    && let ItemType::Array(array_mut) = &mut item_mut.item_type
+                {
+                    array_mut.values = selected_variants.clone();
+                    true
+                } else {
+                    false
+                }
+            });
+            if updated == Some(true) {
                 app.needs_save = true;
                 info!(
                     "Multi select updated with {} items selected for key: {}",
@@ -216,9 +225,9 @@ fn toggle_selection(s: &mut Cursive) {
             // 重新添加所有项，更新选中状态（使用新的美观标记）
             for (idx, variant) in variants.iter().enumerate() {
                 let label = if selected_indices.contains(&idx) {
-                    format!("✓ {}  [已选择]", variant) // 已选中 - 使用对勾符号
+                    t!("multi.selected", name = variant) // 已选中 - 使用对勾符号
                 } else {
-                    format!("○ {}  [未选择]", variant) // 未选中 - 使用圆圈符号
+                    t!("multi.unselected", name = variant) // 未选中 - 使用圆圈符号
                 };
                 view.add_item(label, idx);
             }
@@ -229,10 +238,10 @@ fn toggle_selection(s: &mut Cursive) {
 
         // 更新状态栏显示
         s.call_on_name("status_text", |view: &mut TextView| {
-            view.set_content(format!(
-                "已选择 {} / {} 项 | Enter: 切换选择 | ESC: 退出",
-                selected_indices.len(),
-                variants.len()
+            view.set_content(t!(
+                "multi.status",
+                selected = selected_indices.len(),
+                total = variants.len()
             ));
         });
     }
@@ -276,15 +285,15 @@ pub fn show_extended_multi_select(
     // 添加主要特性选项
     for (idx, variant) in extended_multi_select.variants.iter().enumerate() {
         let label = if extended_multi_select.selected_indices.contains(&idx) {
-            format!("✓ {}  [已选择]", variant)
+            t!("multi.selected", name = variant)
         } else {
-            format!("○ {}  [未选择]", variant)
+            t!("multi.unselected", name = variant)
         };
         select.add_item(label, idx);
     }
 
     // 添加分隔符
-    select.add_item("--- 依赖项 Features ---".to_string(), usize::MAX);
+    select.add_item(t!("multi.deps").to_string(), usize::MAX);
 
     // 添加依赖项选项，使用唯一索引
     for (dep_idx, dep) in extended_multi_select.dependencies.iter().enumerate() {
@@ -295,9 +304,13 @@ pub fn show_extended_multi_select(
             .unwrap_or(0);
 
         let label = if selected_count > 0 {
-            format!("📦 {} ({} features selected)", dep.name, selected_count)
+            t!(
+                "multi.dep_selected",
+                name = dep.name,
+                count = selected_count
+            )
         } else {
-            format!("📦 {} (no features selected)", dep.name)
+            t!("multi.dep_none", name = dep.name)
         };
         // 使用 variants.len() + 1 + dep_idx 作为唯一索引
         let unique_dep_index = extended_multi_select.variants.len() + 1 + dep_idx;
@@ -327,10 +340,10 @@ pub fn show_extended_multi_select(
         TextView::new(format!("📋 {}", title)).style(cursive::theme::ColorStyle::title_primary());
 
     // 创建状态栏
-    let status_text = TextView::new(format!(
-        "已选择 {} / {} 项 | Enter: 切换选择/进入依赖项 | ESC: 退出",
-        extended_multi_select.selected_indices.len(),
-        extended_multi_select.variants.len()
+    let status_text = TextView::new(t!(
+        "multi.status_deps",
+        selected = extended_multi_select.selected_indices.len(),
+        total = extended_multi_select.variants.len()
     ))
     .style(cursive::theme::ColorStyle::secondary())
     .with_name("extended_status_text");
@@ -349,8 +362,8 @@ pub fn show_extended_multi_select(
         .child(DummyView);
 
     // 创建提示文本
-    let hint_text = TextView::new("💡 提示: 选择后自动保存，进入依赖项选择后也会自动更新")
-        .style(cursive::theme::ColorStyle::tertiary());
+    let hint_text =
+        TextView::new(t!("multi.hint_deps")).style(cursive::theme::ColorStyle::tertiary());
 
     // 创建全屏对话框容器
     let fullscreen_dialog = cursive::views::Panel::new(
@@ -360,7 +373,7 @@ pub fn show_extended_multi_select(
             .child(hint_text)
             .child(DummyView),
     )
-    .title("🌟 特性与依赖项选择");
+    .title(t!("multi.title_deps"));
 
     // 添加全屏层
     s.add_fullscreen_layer(
@@ -465,10 +478,17 @@ fn toggle_extended_selection(s: &mut Cursive) {
             let all_selected: Vec<String> =
                 selected_variants.into_iter().chain(dep_features).collect();
 
-            if let Some(ElementType::Item(item_mut)) = app.root.get_mut_by_key(&current_key)
-                && let ItemType::Array(array_mut) = &mut item_mut.item_type
-            {
-                array_mut.values = all_selected.clone();
+            let updated = app.edit(&current_key, |elem| {
+                if let ElementType::Item(item_mut) = elem
+                    && let ItemType::Array(array_mut) = &mut item_mut.item_type
+                {
+                    array_mut.values = all_selected.clone();
+                    true
+                } else {
+                    false
+                }
+            });
+            if updated == Some(true) {
                 app.needs_save = true;
                 info!(
                     "Extended multi select updated with {} items selected for key: {}",
@@ -485,15 +505,15 @@ fn toggle_extended_selection(s: &mut Cursive) {
             // 重新添加主要特性
             for (idx, variant) in variants.iter().enumerate() {
                 let label = if selected_indices.contains(&idx) {
-                    format!("✓ {}  [已选择]", variant)
+                    t!("multi.selected", name = variant)
                 } else {
-                    format!("○ {}  [未选择]", variant)
+                    t!("multi.unselected", name = variant)
                 };
                 view.add_item(label, idx);
             }
 
             // 添加分隔符
-            view.add_item("--- 依赖项 Features ---".to_string(), usize::MAX);
+            view.add_item(t!("multi.deps").to_string(), usize::MAX);
 
             // 重新添加依赖项，使用唯一索引
             for (dep_idx, dep) in dependencies.iter().enumerate() {
@@ -503,9 +523,13 @@ fn toggle_extended_selection(s: &mut Cursive) {
                     .unwrap_or(0);
 
                 let label = if selected_count > 0 {
-                    format!("📦 {} ({} features selected)", dep.name, selected_count)
+                    t!(
+                        "multi.dep_selected",
+                        name = dep.name,
+                        count = selected_count
+                    )
                 } else {
-                    format!("📦 {} (no features selected)", dep.name)
+                    t!("multi.dep_none", name = dep.name)
                 };
                 // 使用 variants.len() + 1 + dep_idx 作为唯一索引
                 let unique_dep_index = variants.len() + 1 + dep_idx;
@@ -517,10 +541,10 @@ fn toggle_extended_selection(s: &mut Cursive) {
 
         // 更新状态栏显示
         s.call_on_name("extended_status_text", |view: &mut TextView| {
-            view.set_content(format!(
-                "已选择 {} / {} 项 | Enter: 切换选择/进入依赖项 | ESC: 退出",
-                selected_indices.len(),
-                variants.len()
+            view.set_content(t!(
+                "multi.status_deps",
+                selected = selected_indices.len(),
+                total = variants.len()
             ));
         });
     }
@@ -546,9 +570,9 @@ fn show_dep_features_select(
     // 添加依赖项的features
     for (idx, feature) in dep.features.iter().enumerate() {
         let label = if selected_indices.contains(&idx) {
-            format!("✓ {}  [已选择]", feature)
+            t!("multi.selected", name = feature)
         } else {
-            format!("○ {}  [未选择]", feature)
+            t!("multi.unselected", name = feature)
         };
         select.add_item(label, idx);
     }
@@ -570,14 +594,14 @@ fn show_dep_features_select(
     }
 
     // 创建标题
-    let title_view = TextView::new(format!("📦 {} Features", dep.name))
+    let title_view = TextView::new(t!("multi.dep_title", name = dep.name))
         .style(cursive::theme::ColorStyle::title_primary());
 
     // 创建状态栏
-    let status_text = TextView::new(format!(
-        "已选择 {} / {} 项 | Enter: 切换选择 | ESC: 返回",
-        selected_count,
-        dep.features.len()
+    let status_text = TextView::new(t!(
+        "multi.status_back",
+        selected = selected_count,
+        total = dep.features.len()
     ))
     .style(cursive::theme::ColorStyle::secondary())
     .with_name("dep_status_text");
@@ -596,8 +620,8 @@ fn show_dep_features_select(
         .child(DummyView);
 
     // 创建提示文本
-    let hint_text = TextView::new("💡 提示: 选择后自动更新到主界面，并保存")
-        .style(cursive::theme::ColorStyle::tertiary());
+    let hint_text =
+        TextView::new(t!("multi.hint_dep")).style(cursive::theme::ColorStyle::tertiary());
 
     // 创建对话框
     let dialog = cursive::views::Panel::new(
@@ -607,7 +631,7 @@ fn show_dep_features_select(
             .child(hint_text)
             .child(DummyView),
     )
-    .title("🌟 依赖项特性选择");
+    .title(t!("multi.title_dep"));
 
     s.add_fullscreen_layer(
         OnEventView::new(dialog)
@@ -698,10 +722,17 @@ fn toggle_dep_features_selection(s: &mut Cursive) {
                 .chain(dep_features_selected)
                 .collect();
 
-            if let Some(ElementType::Item(item_mut)) = app.root.get_mut_by_key(&current_key)
-                && let ItemType::Array(array_mut) = &mut item_mut.item_type
-            {
-                array_mut.values = all_selected.clone();
+            let updated = app.edit(&current_key, |elem| {
+                if let ElementType::Item(item_mut) = elem
+                    && let ItemType::Array(array_mut) = &mut item_mut.item_type
+                {
+                    array_mut.values = all_selected.clone();
+                    true
+                } else {
+                    false
+                }
+            });
+            if updated == Some(true) {
                 app.needs_save = true;
                 info!(
                     "Dep features select updated with {} features for {} and total {} items for key: {}",
@@ -714,10 +745,10 @@ fn toggle_dep_features_selection(s: &mut Cursive) {
 
             // 更新状态栏显示
             s.call_on_name("dep_status_text", |view: &mut TextView| {
-                view.set_content(format!(
-                    "已选择 {} / {} 项 | Enter: 切换选择 | ESC: 返回",
-                    selected_indices.len(),
-                    dep_features.len()
+                view.set_content(t!(
+                    "multi.status_back",
+                    selected = selected_indices.len(),
+                    total = dep_features.len()
                 ));
             });
         }
@@ -728,9 +759,9 @@ fn toggle_dep_features_selection(s: &mut Cursive) {
 
             for (idx, feature) in dep_features.iter().enumerate() {
                 let label = if selected_indices.contains(&idx) {
-                    format!("✓ {}  [已选择]", feature)
+                    t!("multi.selected", name = feature)
                 } else {
-                    format!("○ {}  [未选择]", feature)
+                    t!("multi.unselected", name = feature)
                 };
                 view.add_item(label, idx);
             }
@@ -4,8 +4,10 @@ use cursive::{
     views::{Dialog, DummyView, EditView, LinearLayout, TextView},
 };
 
+use super::{constraint_hint, item_constraints, show_constraint_error, spin_control};
 use crate::{
     data::{item::ItemType, types::ElementType},
+    t,
     ui::handle_back,
 };
 
@@ -19,40 +21,55 @@ pub fn show_number_edit(
 ) {
     let initial = value.or(default).map(|v| v.to_string()).unwrap_or_default();
     let key = key.to_string();
+    let constraints = item_constraints(s, &key);
+
+    let mut layout =
+        LinearLayout::vertical().child(TextView::new(t!("edit.heading", title = title)));
+    if let Some(hint) = constraint_hint(&constraints) {
+        layout.add_child(hint);
+    }
+    layout.add_child(TextView::new(t!("edit.spin_hint")));
+    layout.add_child(DummyView);
+    layout.add_child(spin_control(
+        EditView::new()
+            .content(initial)
+            .with_name("edit_value")
+            .fixed_width(30),
+        &key,
+    ));
 
     s.add_layer(
-        Dialog::around(
-            LinearLayout::vertical()
-                .child(TextView::new(format!("Edit: {}", title)))
-                .child(DummyView)
-                .child(
-                    EditView::new()
-                        .content(initial)
-                        .with_name("edit_value")
-                        .fixed_width(30),
-                ),
-        )
-        .title("Edit Number")
-        .button("OK", move |s| {
-            let content = s
-                .call_on_name("edit_value", |v: &mut EditView| v.get_content())
-                .unwrap();
+        Dialog::around(layout)
+            .title(t!("edit.number"))
+            .button(t!("btn.ok"), move |s| {
+                let content = s
+                    .call_on_name("edit_value", |v: &mut EditView| v.get_content())
+                    .unwrap();
 
-            match content.parse::<f64>() {
-                Ok(_num) => {
-                    if let Some(app) = s.user_data::<crate::data::app_data::AppData>()
-                        && let Some(ElementType::Item(item)) = app.root.get_mut_by_key(&key)
-                        && let ItemType::Number { value, .. } = &mut item.item_type
-                    {
-                        *value = Some(_num);
+                match content.parse::<f64>() {
+                    Ok(_num) => {
+                        if let Err(e) = constraints.check_number(_num) {
+                            show_constraint_error(s, &e);
+                            return;
+                        }
+                        if let Some(app) = s.user_data::<crate::data::app_data::AppData>() {
+                            app.edit(&key, |elem| {
+                                if let ElementType::Item(item) = elem
+                                    && let ItemType::Number { value, .. } = &mut item.item_type
+                                {
+                                    *value = Some(_num);
+                                }
+                            });
+                        }
+                        handle_back(s);
+                    }
+                    Err(_) => {
+                        s.add_layer(
+                            Dialog::info(t!("edit.invalid_number")).dismiss_button(t!("btn.ok")),
+                        );
                     }
-                    handle_back(s);
-                }
-                Err(_) => {
-                    s.add_layer(Dialog::info("Invalid number format!").dismiss_button("Ok"));
                 }
-            }
-        })
-        .button("Cancel", handle_back),
+            })
+            .button(t!("btn.cancel"), handle_back),
     );
 }
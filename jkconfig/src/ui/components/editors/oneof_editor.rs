@@ -5,7 +5,7 @@ use cursive::{
     views::{Dialog, DummyView, LinearLayout, OnEventView, SelectView, TextView},
 };
 
-use crate::{data::oneof::OneOf, ui::handle_back};
+use crate::{data::oneof::OneOf, t, ui::handle_back};
 
 /// 显示 OneOf 选择对话框
 pub fn show_oneof_dialog(s: &mut Cursive, one_of: &OneOf) {
@@ -26,13 +26,16 @@ pub fn show_oneof_dialog(s: &mut Cursive, one_of: &OneOf) {
         OnEventView::new(
             Dialog::around(
                 LinearLayout::vertical()
-                    .child(TextView::new(format!("Select variant: {}", one_of.title)))
+                    .child(TextView::new(t!(
+                        "edit.select_variant",
+                        title = one_of.title
+                    )))
                     .child(DummyView)
                     .child(select.with_name("oneof_select").fixed_height(10)),
             )
-            .title("Select One Of")
-            .button("OK", on_ok)
-            .button("Cancel", handle_back),
+            .title(t!("edit.select_oneof"))
+            .button(t!("btn.ok"), on_ok)
+            .button(t!("btn.cancel"), handle_back),
         )
         .on_event(Key::Enter, on_ok),
     );
@@ -45,10 +48,17 @@ fn on_ok(s: &mut Cursive) {
 
     if let Some(idx) = selection
         && let Some(app) = s.user_data::<crate::data::app_data::AppData>()
-        && let Some(current) = app.current_mut()
-        && let crate::data::types::ElementType::OneOf(one_of) = current
+        && matches!(
+            app.current(),
+            Some(crate::data::types::ElementType::OneOf(_))
+        )
     {
-        let _ = one_of.set_selected_index(*idx);
+        let key = app.key_string();
+        app.edit(&key, |current| {
+            if let crate::data::types::ElementType::OneOf(one_of) = current {
+                let _ = one_of.set_selected_index(*idx);
+            }
+        });
 
         handle_back(s);
     }
@@ -0,0 +1,175 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use cursive::{
+    Cursive,
+    theme::ColorStyle,
+    view::{Nameable, Resizable, Scrollable},
+    views::{Dialog, DummyView, LinearLayout, SelectView, TextView},
+};
+
+use super::{show_constraint_error, show_string_edit};
+use crate::{
+    data::{AppData, constraint::PathKind, item::ItemType, types::ElementType},
+    t,
+    ui::handle_back,
+};
+
+const PICKER_LIST: &str = "path_picker_list";
+const PICKER_DIR: &str = "path_picker_dir";
+
+/// 显示文件/目录选择对话框，用于 `format: "path"` 的字符串项
+///
+/// 从配置文件所在目录开始浏览，选中的路径在该目录下时保存为相对路径。
+pub fn show_path_picker(
+    s: &mut Cursive,
+    key: &str,
+    title: &str,
+    value: &Option<String>,
+    default: &Option<String>,
+    kind: PathKind,
+) {
+    let Some(app) = s.user_data::<AppData>() else {
+        return;
+    };
+    let base = app.base_dir();
+    let base = base.canonicalize().unwrap_or(base);
+
+    // 从当前值所在目录开始
+    let current = value.as_ref().or(default.as_ref()).map(|v| base.join(v));
+    let start = current
+        .and_then(|p| {
+            if p.is_dir() {
+                Some(p)
+            } else {
+                p.parent().map(Path::to_path_buf)
+            }
+        })
+        .and_then(|p| p.canonicalize().ok())
+        .filter(|p| p.is_dir())
+        .unwrap_or_else(|| base.clone());
+    let dir = Arc::new(Mutex::new(start.clone()));
+
+    let key = key.to_string();
+    let select = SelectView::<PathBuf>::new()
+        .on_submit({
+            let key = key.clone();
+            let base = base.clone();
+            let dir = dir.clone();
+            move |s, path: &PathBuf| {
+                if path.is_dir() {
+                    *dir.lock().unwrap() = path.clone();
+                    fill(s, path, kind);
+                } else {
+                    choose(s, &key, path, &base);
+                }
+            }
+        })
+        .with_name(PICKER_LIST)
+        .scrollable()
+        .fixed_size((70, 15));
+
+    let hint = match kind {
+        PathKind::File => t!("path.hint_file"),
+        PathKind::Dir => t!("path.hint_dir"),
+        PathKind::Any => t!("path.hint_any"),
+    };
+    let mut dialog = Dialog::around(
+        LinearLayout::vertical()
+            .child(TextView::new(t!("edit.select", title = title)))
+            .child(TextView::new(hint).style(ColorStyle::secondary()))
+            .child(DummyView)
+            .child(TextView::new("").with_name(PICKER_DIR))
+            .child(select),
+    )
+    .title(t!("path.title"));
+
+    if kind != PathKind::File {
+        let key = key.clone();
+        let base = base.clone();
+        let dir = dir.clone();
+        dialog.add_button(t!("btn.select_dir"), move |s| {
+            let dir = dir.lock().unwrap().clone();
+            choose(s, &key, &dir, &base);
+        });
+    }
+    let (title, value, default) = (title.to_string(), value.clone(), default.clone());
+    dialog.add_button(t!("btn.type_path"), move |s| {
+        // 换成普通文本编辑，路径段保持不变
+        s.pop_layer();
+        show_string_edit(s, &key, &title, &value, &default);
+    });
+    dialog.add_button(t!("btn.cancel"), handle_back);
+
+    s.add_layer(dialog);
+    fill(s, &start, kind);
+}
+
+/// 列出 `dir` 下的目录（以及文件，除非只能选目录），隐藏文件不显示
+fn fill(s: &mut Cursive, dir: &Path, kind: PathKind) {
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push((format!("📂 {name}/"), path));
+            } else if kind != PathKind::Dir {
+                files.push((format!("📄 {name}"), path));
+            }
+        }
+    }
+    dirs.sort();
+    files.sort();
+
+    s.call_on_name(PICKER_DIR, |v: &mut TextView| {
+        v.set_content(format!("📍 {}", dir.display()));
+    });
+    s.call_on_name(PICKER_LIST, |v: &mut SelectView<PathBuf>| {
+        v.clear();
+        if let Some(parent) = dir.parent() {
+            v.add_item("📂 ../", parent.to_path_buf());
+        }
+        v.add_all(dirs);
+        v.add_all(files);
+    });
+}
+
+/// 写入选中的路径：位于 `base` 下时保存为相对路径
+fn choose(s: &mut Cursive, key: &str, path: &Path, base: &Path) {
+    let value = match path.strip_prefix(base) {
+        Ok(rel) if rel.as_os_str().is_empty() => ".".to_string(),
+        Ok(rel) => rel.display().to_string(),
+        Err(_) => path.display().to_string(),
+    };
+    let Some(app) = s.user_data::<AppData>() else {
+        return;
+    };
+    let constraints = match app.root.get_by_key(key) {
+        Some(ElementType::Item(item)) => (*item.constraints).clone(),
+        _ => return,
+    };
+    if let Err(e) = constraints
+        .check_str(&value)
+        .and_then(|_| constraints.check_path(&value, base))
+    {
+        show_constraint_error(s, &e);
+        return;
+    }
+    info!("Setting path value for key {}: {}", key, value);
+    app.edit(key, |elem| {
+        if let ElementType::Item(item) = elem
+            && let ItemType::String { value: v, .. } = &mut item.item_type
+        {
+            *v = Some(value);
+        }
+    });
+    handle_back(s);
+}
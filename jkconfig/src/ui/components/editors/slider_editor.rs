@@ -0,0 +1,218 @@
+use cursive::{
+    Cursive,
+    view::{Nameable, Resizable},
+    views::{Dialog, DummyView, LinearLayout, SliderView, TextView},
+};
+
+use super::{constraint_hint, show_constraint_error};
+use crate::{
+    data::{
+        AppData,
+        item::{IntDisplay, Item, ItemType, NUMBER_STEPS},
+        types::ElementType,
+    },
+    t,
+    ui::handle_back,
+};
+
+/// 滑块最多的位置数，更大的整数范围改用输入框
+const MAX_POSITIONS: usize = 51;
+
+/// 有界数值在滑块上的刻度
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Scale {
+    lo: f64,
+    step: f64,
+    positions: usize,
+    /// 整数的显示方式，数字为 None
+    display: Option<IntDisplay>,
+}
+
+impl Scale {
+    fn for_item(item: &Item) -> Option<Self> {
+        let c = &item.constraints;
+        match &item.item_type {
+            ItemType::Integer { display, .. } => {
+                let (Some(lo), Some(hi)) = c.int_bounds() else {
+                    return None;
+                };
+                let step = c.multiple_of.map_or(1, |m| (m as i128).max(1));
+                let positions = usize::try_from((hi - lo) / step + 1).ok()?;
+                (hi > lo && positions <= MAX_POSITIONS).then_some(Self {
+                    lo: lo as f64,
+                    step: step as f64,
+                    positions,
+                    display: Some(*display),
+                })
+            }
+            ItemType::Number { .. } => {
+                let (Some(lo), Some(hi)) = c.number_bounds() else {
+                    return None;
+                };
+                (hi > lo).then_some(Self {
+                    lo,
+                    step: (hi - lo) / NUMBER_STEPS as f64,
+                    positions: NUMBER_STEPS as usize + 1,
+                    display: None,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn value(&self, pos: usize) -> f64 {
+        self.lo + self.step * pos as f64
+    }
+
+    fn position(&self, value: f64) -> usize {
+        let pos = ((value - self.lo) / self.step).round().max(0.0) as usize;
+        pos.min(self.positions - 1)
+    }
+
+    fn label(&self, pos: usize) -> String {
+        match self.display {
+            Some(display) => display.format(self.value(pos) as i128),
+            None => self.value(pos).to_string(),
+        }
+    }
+
+    /// 当前值，未设置时取默认值或下界
+    fn current(&self, item: &Item) -> usize {
+        let value = match &item.item_type {
+            ItemType::Integer { value, default, .. } => value.or(*default).map(|v| v as f64),
+            ItemType::Number { value, default } => value.or(*default),
+            _ => None,
+        };
+        value.map_or(0, |v| self.position(v))
+    }
+}
+
+/// Whether `item` is edited with a slider: an integer with a small range
+/// or a number with both bounds.
+pub fn has_slider(item: &Item) -> bool {
+    Scale::for_item(item).is_some()
+}
+
+/// 显示滑块编辑对话框
+pub fn show_slider_edit(s: &mut Cursive, item: &Item) {
+    let Some(scale) = Scale::for_item(item) else {
+        return;
+    };
+    let key = item.base.key();
+    let constraints = (*item.constraints).clone();
+    let pos = scale.current(item);
+
+    let mut layout =
+        LinearLayout::vertical().child(TextView::new(t!("edit.heading", title = item.base.title)));
+    if let Some(hint) = constraint_hint(&constraints) {
+        layout.add_child(hint);
+    }
+    layout.add_child(TextView::new(t!("edit.slider_hint")));
+    layout.add_child(DummyView);
+    layout.add_child(TextView::new(scale.label(pos)).with_name("slider_value"));
+    let on_enter = {
+        let key = key.clone();
+        let constraints = constraints.clone();
+        move |s: &mut Cursive, pos: usize| apply(s, &key, &constraints, scale, pos)
+    };
+    layout.add_child(
+        SliderView::horizontal(scale.positions)
+            .value(pos)
+            .on_change(move |s, pos| {
+                s.call_on_name("slider_value", |v: &mut TextView| {
+                    v.set_content(scale.label(pos))
+                });
+            })
+            .on_enter(on_enter)
+            .with_name("slider")
+            .min_width(scale.positions + 2),
+    );
+
+    s.add_layer(
+        Dialog::around(layout)
+            .title(t!("edit.slider"))
+            .button(t!("btn.ok"), move |s| {
+                let pos = s
+                    .call_on_name("slider", |v: &mut SliderView| v.get_value())
+                    .unwrap();
+                apply(s, &key, &constraints, scale, pos);
+            })
+            .button(t!("btn.cancel"), handle_back),
+    );
+}
+
+fn apply(
+    s: &mut Cursive,
+    key: &str,
+    constraints: &crate::data::constraint::Constraints,
+    scale: Scale,
+    pos: usize,
+) {
+    let num = scale.value(pos);
+    if let Err(e) = constraints.check_number(num) {
+        show_constraint_error(s, &e);
+        return;
+    }
+    if let Some(app) = s.user_data::<AppData>() {
+        app.edit(key, |elem| {
+            if let ElementType::Item(item) = elem {
+                match &mut item.item_type {
+                    ItemType::Integer { value, .. } => *value = Some(num as i128),
+                    ItemType::Number { value, .. } => *value = Some(num),
+                    _ => {}
+                }
+            }
+        });
+    }
+    handle_back(s);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_scale() {
+        let schema = serde_json::json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Test",
+            "type": "object",
+            "properties": {
+                "smp": {"type": "integer", "minimum": 1, "maximum": 8},
+                "align": {"type": "integer", "minimum": 0, "maximum": 64, "multipleOf": 16},
+                "ratio": {"type": "number", "minimum": 0, "maximum": 2},
+                "port": {"type": "integer", "minimum": 0, "maximum": 65535}
+            }
+        });
+        let app = AppData::new_with_init_and_schema(
+            r#"{"smp": 4, "align": 32}"#,
+            Path::new("a.json"),
+            &schema,
+        )
+        .unwrap();
+        let item = |key: &str| match app.root.get_by_key(key) {
+            Some(ElementType::Item(item)) => item.clone(),
+            _ => panic!("no item {key}"),
+        };
+
+        let smp = item("smp");
+        let scale = Scale::for_item(&smp).unwrap();
+        assert_eq!(scale.positions, 8);
+        assert_eq!(scale.current(&smp), 3);
+        assert_eq!(scale.label(7), "8");
+
+        let align = item("align");
+        let scale = Scale::for_item(&align).unwrap();
+        assert_eq!(scale.positions, 5);
+        assert_eq!(scale.current(&align), 2);
+
+        let scale = Scale::for_item(&item("ratio")).unwrap();
+        assert_eq!(scale.positions, 21);
+        assert_eq!(scale.value(10), 1.0);
+        assert_eq!(scale.position(5.0), 20);
+
+        // 范围太大时用输入框
+        assert!(!has_slider(&item("port")));
+    }
+}
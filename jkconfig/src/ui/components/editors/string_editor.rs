@@ -4,8 +4,10 @@ use cursive::{
     views::{Dialog, DummyView, EditView, LinearLayout, TextView},
 };
 
+use super::{constraint_hint, edit_in_external_editor, item_constraints, show_constraint_error};
 use crate::{
     data::{item::ItemType, types::ElementType},
+    t,
     ui::handle_back,
 };
 
@@ -22,35 +24,59 @@ pub fn show_string_edit(
         .or_else(|| default.clone())
         .unwrap_or_default();
     let key = key.to_string();
+    let constraints = item_constraints(s, &key);
+    let editor_key = key.clone();
+
+    let mut layout =
+        LinearLayout::vertical().child(TextView::new(t!("edit.heading", title = title)));
+    if let Some(hint) = constraint_hint(&constraints) {
+        layout.add_child(hint);
+    }
+    layout.add_child(DummyView);
+    layout.add_child(
+        EditView::new()
+            .content(initial)
+            .with_name("edit_value")
+            .fixed_width(50),
+    );
 
     s.add_layer(
-        Dialog::around(
-            LinearLayout::vertical()
-                .child(TextView::new(format!("Edit: {}", title)))
-                .child(DummyView)
-                .child(
-                    EditView::new()
-                        .content(initial)
-                        .with_name("edit_value")
-                        .fixed_width(50),
-                ),
-        )
-        .title("Edit String")
-        .button("OK", move |s| {
-            let st = s
-                .call_on_name("edit_value", |v: &mut EditView| v.get_content())
-                .unwrap();
-            info!("Setting string value for key {}: {}", key, st);
+        Dialog::around(layout)
+            .title(t!("edit.string"))
+            .button(t!("btn.ok"), move |s| {
+                let st = s
+                    .call_on_name("edit_value", |v: &mut EditView| v.get_content())
+                    .unwrap();
+                let base = s
+                    .user_data::<crate::data::app_data::AppData>()
+                    .map(|app| app.base_dir())
+                    .unwrap_or_default();
+                if let Err(e) = constraints
+                    .check_str(&st)
+                    .and_then(|_| constraints.check_path(&st, &base))
+                {
+                    show_constraint_error(s, &e);
+                    return;
+                }
+                info!("Setting string value for key {}: {}", key, st);
 
-            if let Some(app) = s.user_data::<crate::data::app_data::AppData>()
-                && let Some(ElementType::Item(item)) = app.root.get_mut_by_key(&key)
-                && let ItemType::String { value, .. } = &mut item.item_type
-            {
-                info!("Old value: {:?}", value);
-                *value = Some(st.to_string());
-            }
-            handle_back(s);
-        })
-        .button("Cancel", handle_back),
+                if let Some(app) = s.user_data::<crate::data::app_data::AppData>() {
+                    app.edit(&key, |elem| {
+                        if let ElementType::Item(item) = elem
+                            && let ItemType::String { value, .. } = &mut item.item_type
+                        {
+                            info!("Old value: {:?}", value);
+                            *value = Some(st.to_string());
+                        }
+                    });
+                }
+                handle_back(s);
+            })
+            .button("$EDITOR", move |s| {
+                if edit_in_external_editor(s, &editor_key) {
+                    handle_back(s);
+                }
+            })
+            .button(t!("btn.cancel"), handle_back),
     );
 }
@@ -1,12 +1,51 @@
-use crate::data::{item::ItemType, types::ElementType};
+use crate::{
+    data::{item::ItemType, menu::Menu, types::ElementType},
+    t,
+    ui::theme::accessible,
+};
 
 pub trait ItemDisplay {
     fn icon(&self) -> String;
     fn value(&self) -> String;
 }
 
+impl ElementType {
+    /// 无障碍模式下的纯文本图标：`[x]` 为必填，`<x>` 为可选
+    fn text_icon(&self) -> String {
+        let raw = if self.is_none() {
+            if self.is_required { '!' } else { ' ' }
+        } else {
+            match self {
+                ElementType::Menu(_) => '>',
+                ElementType::OneOf(_) => '|',
+                ElementType::Item(item) => match &item.item_type {
+                    ItemType::String { .. } => 'a',
+                    ItemType::Number { .. } | ItemType::Integer { .. } => '#',
+                    ItemType::Boolean { value, .. } => {
+                        if *value {
+                            'x'
+                        } else {
+                            ' '
+                        }
+                    }
+                    ItemType::Enum(_) => '=',
+                    ItemType::Array(_) => '*',
+                },
+            }
+        };
+        if self.is_required {
+            format!("[{raw}]")
+        } else {
+            format!("<{raw}>")
+        }
+    }
+}
+
 impl ItemDisplay for ElementType {
     fn icon(&self) -> String {
+        if accessible() {
+            return self.text_icon();
+        }
         if self.is_none() {
             if self.is_required {
                 return " ❗ ".into();
@@ -42,27 +81,25 @@ impl ItemDisplay for ElementType {
 
     fn value(&self) -> String {
         match self {
-            ElementType::Menu(_) => String::new(),
-            ElementType::OneOf(one_of) => {
-                if let Some(selected) = one_of.selected() {
-                    let name = match selected {
-                        ElementType::Menu(menu) => menu.struct_name.clone(),
-                        ElementType::Item(item) => match &item.item_type {
-                            ItemType::Enum(enum_item) => {
-                                enum_item.variants.first().cloned().unwrap_or_default()
-                            }
-                            _ => String::new(),
-                        },
-                        _ => String::new(),
-                    };
-
-                    format!("<{name}>")
-                } else {
-                    "<Unset>".to_string()
+            ElementType::Menu(menu) => summary(menu),
+            ElementType::OneOf(one_of) => match (one_of.selected_index, one_of.selected()) {
+                (Some(idx), Some(selected)) => {
+                    let name = one_of.variant_display(idx);
+                    match selected {
+                        ElementType::Menu(menu) if !menu.children.is_empty() => {
+                            format!("<{name}> {}", summary(menu))
+                        }
+                        // 简单类型的分支直接显示值
+                        ElementType::Item(item) if !matches!(item.item_type, ItemType::Enum(_)) => {
+                            selected.value()
+                        }
+                        _ => format!("<{name}>"),
+                    }
                 }
-            }
+                _ => format!("<{}>", t!("summary.unset_variant")),
+            },
             ElementType::Item(item) => match &item.item_type {
-                ItemType::String { value, .. } => value.clone().unwrap_or_default(),
+                ItemType::String { value, .. } => value.as_deref().map(preview).unwrap_or_default(),
                 ItemType::Number { value, .. } => {
                     if let Some(v) = value {
                         v.to_string()
@@ -70,12 +107,8 @@ impl ItemDisplay for ElementType {
                         String::new()
                     }
                 }
-                ItemType::Integer { value, .. } => {
-                    if let Some(v) = value {
-                        v.to_string()
-                    } else {
-                        String::new()
-                    }
+                ItemType::Integer { value, display, .. } => {
+                    value.map(|v| display.format(v)).unwrap_or_default()
                 }
                 ItemType::Boolean { .. } => String::new(),
                 ItemType::Enum(enum_item) => {
@@ -89,10 +122,80 @@ impl ItemDisplay for ElementType {
                     if array_item.values.is_empty() {
                         "[]".to_string()
                     } else {
-                        format!("[{} items]", array_item.values.len())
+                        let count = t!("preview.items", count = array_item.values.len());
+                        preview(&format!("{count} {}", array_item.values.join(", ")))
                     }
                 }
             },
         }
     }
 }
+
+/// 子菜单摘要，如 `{4 fields, 3 set}`
+fn summary(menu: &Menu) -> String {
+    if menu.is_none() {
+        return t!("summary.unset").to_string();
+    }
+    t!(
+        "summary.fields",
+        total = menu.children.len(),
+        set = menu.set_count()
+    )
+}
+
+/// 列表中值预览的最大字符数
+const PREVIEW_LEN: usize = 40;
+
+/// 单行显示并截断过长的值
+fn preview(value: &str) -> String {
+    let line = value.replace('\n', "\\n");
+    if line.chars().count() <= PREVIEW_LEN {
+        return line;
+    }
+    let mut short: String = line.chars().take(PREVIEW_LEN - 1).collect();
+    short.push('…');
+    short
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::AppData;
+    use std::path::Path;
+
+    #[test]
+    fn test_value_previews() {
+        crate::i18n::set_lang(crate::i18n::Lang::En);
+        let schema = serde_json::json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Test",
+            "type": "object",
+            "properties": {
+                "cmdline": {"type": "string"},
+                "args": {"type": "array", "items": {"type": "string"}},
+                "qemu": {
+                    "type": "object",
+                    "properties": {
+                        "smp": {"type": "integer"},
+                        "graphic": {"type": "boolean"}
+                    }
+                }
+            }
+        });
+        let app = AppData::new_with_init_and_schema(
+            r#"{"cmdline": "console=ttyS0 earlycon=sbi root=/dev/vda rw init=/sbin/init",
+                "args": ["-s", "-S"], "qemu": {"graphic": true}}"#,
+            Path::new("a.json"),
+            &schema,
+        )
+        .unwrap();
+        let value = |key: &str| app.root.get_by_key(key).unwrap().value();
+
+        let cmdline = value("cmdline");
+        assert_eq!(cmdline.chars().count(), PREVIEW_LEN);
+        assert!(cmdline.starts_with("console=ttyS0") && cmdline.ends_with('…'));
+        assert_eq!(value("args"), "[2 items] -s, -S");
+        assert_eq!(value("qemu"), "{2 fields, 1 set}");
+        assert_eq!(preview("a\nb"), "a\\nb");
+    }
+}
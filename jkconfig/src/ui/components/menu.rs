@@ -1,6 +1,17 @@
 use crate::{
-    data::{AppData, item::ItemType, menu::Menu, types::ElementType},
-    ui::{components::icon::ItemDisplay, handle_edit},
+    data::{
+        AppData,
+        constraint::missing_required,
+        item::{EnumItem, Item, ItemType},
+        menu::Menu,
+        types::ElementType,
+    },
+    t,
+    ui::{
+        components::icon::ItemDisplay,
+        handle_back, handle_edit,
+        theme::{accessible, error_style},
+    },
 };
 use cursive::{
     Cursive,
@@ -12,7 +23,15 @@ use cursive::{
     views::{Dialog, DummyView, LinearLayout, OnEventView, Panel, SelectView, TextView},
 };
 
-use super::editors::*;
+use super::{
+    changes::show_changes,
+    clipboard::{on_copy, on_paste},
+    defconfig::show_non_defaults,
+    editors::*,
+    mouse::mouse_list,
+    preset::show_presets,
+    search::show_search,
+};
 
 /// 创建菜单视图
 pub fn menu_view(title: &str, path: &str, fields: Vec<ElementType>) -> impl IntoBoxedView {
@@ -38,7 +57,7 @@ pub fn menu_view(title: &str, path: &str, fields: Vec<ElementType>) -> impl Into
         let mut styled = StyledString::new();
         styled.append_styled("📂 ", ColorStyle::tertiary());
         styled.append_styled("/ ", Style::from(Effect::Bold));
-        styled.append_styled("(Root)", ColorStyle::secondary());
+        styled.append_styled(t!("menu.root"), ColorStyle::secondary());
         styled
     } else {
         let mut styled = StyledString::new();
@@ -57,12 +76,16 @@ pub fn menu_view(title: &str, path: &str, fields: Vec<ElementType>) -> impl Into
             .child(DummyView.fixed_height(1))
             .child(title_view)
             .child(DummyView.fixed_height(1))
-            .child(Panel::new(path_view).title("Current Path").full_width())
+            .child(
+                Panel::new(path_view)
+                    .title(t!("menu.current_path"))
+                    .full_width(),
+            )
             .child(DummyView.fixed_height(1))
             // 列表区域占据大部分空间，自动滚动
             .child(
-                Panel::new(select.scrollable())
-                    .title("Items")
+                Panel::new(mouse_list(select).scrollable())
+                    .title(t!("menu.items"))
                     .full_width()
                     .full_height(), // 使用 full_height 让列表占据剩余空间
             )
@@ -70,7 +93,7 @@ pub fn menu_view(title: &str, path: &str, fields: Vec<ElementType>) -> impl Into
             // 帮助区域固定高度，确保完全显示
             .child(
                 Panel::new(help_view)
-                    .title("Keyboard Shortcuts")
+                    .title(t!("menu.shortcuts"))
                     .full_width()
                     .fixed_height(7), // 固定高度确保按键提示完全显示
             )
@@ -83,30 +106,178 @@ pub fn menu_view(title: &str, path: &str, fields: Vec<ElementType>) -> impl Into
     .on_event(Event::Char('C'), on_clear)
     .on_event(Event::Char('h'), on_show_help)
     .on_event(Event::Char('H'), on_show_help)
+    .on_event(Event::Char('/'), show_search)
+    .on_event(Event::Char('u'), on_undo)
+    .on_event(Event::Char('U'), on_undo)
+    .on_event(Event::CtrlChar('r'), on_redo)
+    .on_event(Event::Char('p'), show_presets)
+    .on_event(Event::Char('P'), show_presets)
+    .on_event(Event::Char('d'), show_changes)
+    .on_event(Event::Char('e'), on_external_edit)
+    .on_event(Event::Char('f'), on_fill_defaults)
+    .on_event(Event::Char('F'), on_fill_defaults)
+    .on_event(Event::Char('r'), on_reset_default)
+    .on_event(Event::Char('R'), on_reset_default)
+    .on_event(Event::Char('y'), on_copy)
+    .on_event(Event::Char('Y'), on_copy)
+    .on_event(Event::Char('v'), on_paste)
+    .on_event(Event::Char('V'), on_paste)
+    .on_event(Event::Char('E'), on_external_edit)
+    .on_event(Event::Char('D'), show_changes)
+    .on_event(Event::Char('o'), show_non_defaults)
+    .on_event(Event::Char('O'), show_non_defaults)
+    .on_event(Key::Left, |s| on_step(s, -1))
+    .on_event(Key::Right, |s| on_step(s, 1))
+    .on_event(Event::Char('-'), |s| on_step(s, -1))
+    .on_event(Event::Char('+'), |s| on_step(s, 1))
+}
+
+/// 调整有界数值或切换枚举 - ←/→ 或 -/+ 键
+fn on_step(s: &mut Cursive, delta: i32) {
+    let Some(ElementType::Item(item)) = menu_selected(s) else {
+        return;
+    };
+    if item.is_read_only() || !item.is_steppable() {
+        return;
+    }
+    update_selected(s, |elem| {
+        if let ElementType::Item(item) = elem {
+            item.step(delta);
+        }
+    });
+}
+
+/// 撤销上一次修改 - u 键
+fn on_undo(s: &mut Cursive) {
+    let key = s.user_data::<AppData>().and_then(|app| app.undo());
+    after_history_change(s, key);
+}
+
+/// 重做上一次撤销的修改 - Ctrl+R
+fn on_redo(s: &mut Cursive) {
+    let key = s.user_data::<AppData>().and_then(|app| app.redo());
+    after_history_change(s, key);
+}
+
+/// 撤销/重做后刷新菜单栈，并选中被修改的项
+fn after_history_change(s: &mut Cursive, changed: Option<String>) {
+    let Some(changed) = changed else {
+        return;
+    };
+    info!("History restored key: {}", changed);
+
+    // 当前菜单可能已不存在（例如撤销了 OneOf 的变体选择），逐级退回
+    loop {
+        let Some(app) = s.user_data::<AppData>() else {
+            return;
+        };
+        if app.current_key.is_empty() {
+            break;
+        }
+        let still_menu = match app.current() {
+            Some(ElementType::Menu(_)) => true,
+            Some(ElementType::OneOf(one_of)) => {
+                matches!(one_of.selected(), Some(ElementType::Menu(_)))
+            }
+            _ => false,
+        };
+        if still_menu {
+            break;
+        }
+        app.navigate_back();
+        s.pop_layer();
+    }
+
+    // 刷新栈上所有菜单视图
+    let Some(app) = s.user_data::<AppData>() else {
+        return;
+    };
+    let current_key = app.current_key.clone();
+    for depth in 0..=current_key.len() {
+        menu_select_flush(s, &current_key[..depth].join("."));
+    }
+
+    let path = current_key.join(".");
+    let cb = s.call_on_name(
+        &menu_view_name(&path),
+        |view: &mut SelectView<ElementType>| {
+            let idx = view.iter().position(|(_, elem)| {
+                let key = elem.key();
+                changed == key || changed.starts_with(&format!("{key}."))
+            })?;
+            Some(view.set_selection(idx))
+        },
+    );
+    if let Some(Some(cb)) = cb {
+        cb(s);
+    }
 }
 
 fn on_clear(s: &mut Cursive) {
-    let Some(_selected) = menu_selected(s) else {
+    let Some(selected) = menu_selected(s) else {
         return;
     };
+    if let ElementType::Item(item) = &selected
+        && item.is_read_only()
+    {
+        return;
+    }
 
     update_selected(s, |elem| elem.set_none());
 }
 
+/// 用 schema 默认值填充所有未设置的项
+fn on_fill_defaults(s: &mut Cursive) {
+    s.add_layer(
+        Dialog::text(t!("fill.confirm"))
+            .title(t!("fill.title"))
+            .button(t!("btn.fill"), |s| {
+                s.pop_layer();
+                let changed = s
+                    .user_data::<AppData>()
+                    .is_some_and(|app| app.fill_defaults());
+                menu_flush(s);
+                if !changed {
+                    s.add_layer(Dialog::info(t!("fill.nothing")).title(t!("fill.title")));
+                }
+            })
+            .button(t!("btn.cancel"), |s| {
+                s.pop_layer();
+            }),
+    );
+}
+
+/// 将选中项恢复为 schema 默认值
+fn on_reset_default(s: &mut Cursive) {
+    update_selected(s, ElementType::reset_to_default);
+}
+
+/// 在外部编辑器中编辑选中的字符串或数组项
+fn on_external_edit(s: &mut Cursive) {
+    let Some(ElementType::Item(item)) = menu_selected(s) else {
+        return;
+    };
+    if item.is_read_only() {
+        return;
+    }
+    if edit_in_external_editor(s, &item.base.key()) {
+        menu_flush(s);
+    }
+}
+
 fn update_selected(s: &mut Cursive, f: impl Fn(&mut ElementType)) {
     let Some(selected) = menu_selected(s) else {
         return;
     };
 
     if let Some(app) = s.user_data::<AppData>()
-        && let Some(elem) = app.root.get_mut_by_key(&selected.key())
+        && app.edit(&selected.key(), f).is_some()
     {
-        f(elem);
         menu_flush(s);
     }
 }
 
-fn menu_selected(s: &mut Cursive) -> Option<ElementType> {
+pub(crate) fn menu_selected(s: &mut Cursive) -> Option<ElementType> {
     let mut selected = None;
     let name = menu_view_name(&menu_key(s));
     s.call_on_name(&name, |view: &mut SelectView<ElementType>| {
@@ -136,7 +307,7 @@ fn menu_key(s: &mut Cursive) -> String {
     app.key_string()
 }
 
-fn menu_flush(s: &mut Cursive) {
+pub(crate) fn menu_flush(s: &mut Cursive) {
     let key = menu_key(s);
     menu_select_flush(s, &key);
 }
@@ -201,11 +372,73 @@ pub fn format_item_label(element: &ElementType) -> StyledString {
     label.append_plain(" ");
     label.append_styled(&element.title, ColorStyle::title_secondary());
     label.append_plain("  ");
-    label.append_styled(element.value(), ColorStyle::secondary());
+    match element {
+        ElementType::Item(Item {
+            item_type: ItemType::Enum(e),
+            ..
+        }) if e.is_inline() => label.append(inline_enum(e)),
+        _ => label.append_styled(element.value(), ColorStyle::secondary()),
+    }
+    if let ElementType::Item(item) = element
+        && item.is_read_only()
+    {
+        label.append_plain("  ");
+        label.append_plain(if accessible() {
+            t!("label.read_only")
+        } else {
+            "🔒"
+        });
+    }
+    // 高亮违反 schema 约束的值
+    if let ElementType::Item(item) = element
+        && let Err(e) = item.validate()
+    {
+        label.append_plain("  ");
+        let marker = if accessible() {
+            t!("label.error")
+        } else {
+            "⚠"
+        };
+        label.append_styled(format!("{marker} {e}"), error_style());
+    }
+    // 标出未填写的必填项，以及包含未填写必填项的菜单
+    let missing = if element.is_missing() {
+        t!("label.required").to_string()
+    } else {
+        match missing_required(element).len() {
+            0 => String::new(),
+            n => t!("label.required_missing", count = n),
+        }
+    };
+    if !missing.is_empty() {
+        label.append_plain("  ");
+        label.append_styled(missing, error_style());
+    }
 
     label
 }
 
+/// 在菜单行内显示的单选项，如 `◉ error  ○ warn  ○ info`
+fn inline_enum(e: &EnumItem) -> StyledString {
+    let (on, off) = if accessible() {
+        ("(*)", "( )")
+    } else {
+        ("◉", "○")
+    };
+    let mut text = StyledString::new();
+    for (idx, variant) in e.variants.iter().enumerate() {
+        if idx > 0 {
+            text.append_plain("  ");
+        }
+        if e.value == Some(idx) {
+            text.append_styled(format!("{on} {variant}"), Style::from(Effect::Bold));
+        } else {
+            text.append_styled(format!("{off} {variant}"), ColorStyle::secondary());
+        }
+    }
+    text
+}
+
 /// 创建帮助文本（在底部状态栏显示）
 fn create_help_text() -> StyledString {
     let mut text = StyledString::new();
@@ -214,31 +447,53 @@ fn create_help_text() -> StyledString {
     // 第一行：导航
     text.append_styled("▶ ", ColorStyle::tertiary());
     text.append_styled("↑↓/jk", Style::from(Effect::Bold));
-    text.append_plain(" Move  ");
+    text.append_plain(format!(" {}  ", t!("key.move")));
     text.append_styled("Enter", Style::from(Effect::Bold));
-    text.append_plain(" Select  ");
+    text.append_plain(format!(" {}  ", t!("key.select")));
     text.append_styled("Esc", Style::from(Effect::Bold));
-    text.append_plain(" Back  ");
+    text.append_plain(format!(" {}  ", t!("key.back")));
     text.append_styled("H", Style::from(Effect::Bold));
-    text.append_plain(" Help\n");
+    text.append_plain(format!(" {}  ", t!("key.help")));
+    text.append_styled("/", Style::from(Effect::Bold));
+    text.append_plain(format!(" {}  ", t!("key.search")));
+    text.append_styled("←→", Style::from(Effect::Bold));
+    text.append_plain(format!(" {}\n", t!("key.step")));
 
     // 第二行：编辑
     text.append_styled("▶ ", ColorStyle::tertiary());
     text.append_styled("C", Style::from(Effect::Bold));
-    text.append_plain(" Clear  ");
+    text.append_plain(format!(" {}  ", t!("key.clear")));
+    text.append_styled("R", Style::from(Effect::Bold));
+    text.append_plain(format!(" {}  ", t!("key.default")));
     text.append_styled("M", Style::from(Effect::Bold));
-    text.append_plain(" Toggle  ");
+    text.append_plain(format!(" {}  ", t!("key.toggle")));
     text.append_styled("Tab", Style::from(Effect::Bold));
-    text.append_plain(" Switch\n");
+    text.append_plain(format!(" {}  ", t!("key.switch")));
+    text.append_styled("E", Style::from(Effect::Bold));
+    text.append_plain(format!(" {}  ", t!("key.editor")));
+    text.append_styled("U", Style::from(Effect::Bold));
+    text.append_plain(format!(" {}  ", t!("key.undo")));
+    text.append_styled("Y/V", Style::from(Effect::Bold));
+    text.append_plain(format!(" {}  ", t!("key.copy_paste")));
+    text.append_styled("^R", Style::from(Effect::Bold));
+    text.append_plain(format!(" {}\n", t!("key.redo")));
 
     // 第三行：全局
     text.append_styled("▶ ", ColorStyle::tertiary());
     text.append_styled("S", Style::from(Effect::Bold));
-    text.append_plain(" Save & Exit  ");
+    text.append_plain(format!(" {}  ", t!("key.save_exit")));
     text.append_styled("Q", Style::from(Effect::Bold));
-    text.append_plain(" Quit  ");
+    text.append_plain(format!(" {}  ", t!("key.quit")));
+    text.append_styled("P", Style::from(Effect::Bold));
+    text.append_plain(format!(" {}  ", t!("key.presets")));
+    text.append_styled("D", Style::from(Effect::Bold));
+    text.append_plain(format!(" {}  ", t!("key.changes")));
+    text.append_styled("O", Style::from(Effect::Bold));
+    text.append_plain(format!(" {}  ", t!("key.non_defaults")));
+    text.append_styled("F", Style::from(Effect::Bold));
+    text.append_plain(format!(" {}  ", t!("key.fill")));
     text.append_styled("~", Style::from(Effect::Bold));
-    text.append_plain(" Console");
+    text.append_plain(format!(" {}", t!("key.console")));
 
     text
 }
@@ -256,51 +511,61 @@ fn on_show_help(s: &mut Cursive) {
         ElementType::Menu(menu) => {
             let mut text = StyledString::new();
             text.append_styled(
-                "📁 Menu\n",
+                format!("{}\n", t!("detail.menu")),
                 Style::from(Effect::Bold).combine(ColorStyle::title_primary()),
             );
             text.append_plain("\n");
-            text.append_styled("Title: ", Style::from(Effect::Bold));
+            text.append_styled(t!("detail.title"), Style::from(Effect::Bold));
             text.append_plain(&menu.title);
             text.append_plain("\n\n");
 
             if let Some(help) = &menu.help {
-                text.append_styled("Description:\n", Style::from(Effect::Bold));
+                text.append_styled(
+                    format!("{}\n", t!("detail.description")),
+                    Style::from(Effect::Bold),
+                );
                 text.append_plain(help);
                 text.append_plain("\n\n");
             }
 
             let item_count = menu.children.len();
-            text.append_styled("Items: ", Style::from(Effect::Bold));
-            text.append_plain(format!("{} items\n", item_count));
+            text.append_styled(t!("detail.items"), Style::from(Effect::Bold));
+            text.append_plain(t!("detail.item_count", count = item_count));
+            text.append_plain("\n");
 
             text
         }
         ElementType::OneOf(oneof) => {
             let mut text = StyledString::new();
             text.append_styled(
-                "🔀 OneOf Selector\n",
+                format!("{}\n", t!("detail.oneof")),
                 Style::from(Effect::Bold).combine(ColorStyle::title_primary()),
             );
             text.append_plain("\n");
-            text.append_styled("Property: ", Style::from(Effect::Bold));
+            text.append_styled(t!("detail.property"), Style::from(Effect::Bold));
             text.append_plain(&oneof.title);
             text.append_plain("\n\n");
 
             if let Some(help) = &oneof.help {
-                text.append_styled("Description:\n", Style::from(Effect::Bold));
+                text.append_styled(
+                    format!("{}\n", t!("detail.description")),
+                    Style::from(Effect::Bold),
+                );
                 text.append_plain(help);
                 text.append_plain("\n\n");
             }
 
-            text.append_styled("Current Variant: ", Style::from(Effect::Bold));
+            text.append_styled(t!("detail.current_variant"), Style::from(Effect::Bold));
             if let Some(idx) = oneof.selected_index {
                 text.append_plain(format!("{}\n\n", idx));
             } else {
-                text.append_plain("(none)\n\n");
+                text.append_plain(format!("{}\n\n", t!("detail.none")));
             }
 
-            text.append_styled("Available Variants:\n", Style::from(Effect::Bold));
+            text.append_styled(
+                format!("{}\n", t!("detail.variants")),
+                Style::from(Effect::Bold),
+            );
             for (i, variant) in oneof.variants.iter().enumerate() {
                 let prefix = if Some(i) == oneof.selected_index {
                     "→ "
@@ -326,7 +591,7 @@ fn on_show_help(s: &mut Cursive) {
             text.append_plain("\n");
 
             // 类型信息
-            text.append_styled("Type: ", Style::from(Effect::Bold));
+            text.append_styled(t!("detail.type"), Style::from(Effect::Bold));
             match &item.item_type {
                 ItemType::String { .. } => text.append_plain("String"),
                 ItemType::Integer { .. } => text.append_plain("Integer"),
@@ -339,19 +604,25 @@ fn on_show_help(s: &mut Cursive) {
 
             // 描述
             if let Some(help) = &item.base.help {
-                text.append_styled("Description:\n", Style::from(Effect::Bold));
+                text.append_styled(
+                    format!("{}\n", t!("detail.description")),
+                    Style::from(Effect::Bold),
+                );
                 text.append_plain(help);
                 text.append_plain("\n\n");
             }
 
             // 当前值
-            text.append_styled("Current Value:\n", Style::from(Effect::Bold));
+            text.append_styled(
+                format!("{}\n", t!("detail.current_value")),
+                Style::from(Effect::Bold),
+            );
             match &item.item_type {
                 ItemType::String { value, .. } => {
-                    text.append_plain(value.as_ref().unwrap_or(&"(none)".to_string()));
+                    text.append_plain(value.as_deref().unwrap_or(t!("detail.none")));
                 }
-                ItemType::Integer { value, .. } => {
-                    text.append_plain(format!("{}", value.unwrap_or(0)));
+                ItemType::Integer { value, display, .. } => {
+                    text.append_plain(display.format(value.unwrap_or(0)));
                 }
                 ItemType::Number { value, .. } => {
                     text.append_plain(format!("{}", value.unwrap_or(0.0)));
@@ -364,14 +635,17 @@ fn on_show_help(s: &mut Cursive) {
                         if let Some(variant) = v.variants.get(idx) {
                             text.append_plain(variant);
                         } else {
-                            text.append_plain("(invalid)");
+                            text.append_plain(t!("detail.invalid"));
                         }
                     } else {
-                        text.append_plain("(none)");
+                        text.append_plain(t!("detail.none"));
                     }
                 }
                 ItemType::Array(v) => {
-                    text.append_plain(format!("[{} items]", v.values.len()));
+                    text.append_plain(format!(
+                        "[{}]",
+                        t!("detail.item_count", count = v.values.len())
+                    ));
                 }
             }
             text.append_plain("\n\n");
@@ -380,25 +654,27 @@ fn on_show_help(s: &mut Cursive) {
             match &item.item_type {
                 ItemType::String { default, .. } => {
                     if let Some(default) = default {
-                        text.append_styled("Default: ", Style::from(Effect::Bold));
+                        text.append_styled(t!("detail.default"), Style::from(Effect::Bold));
                         text.append_plain(default);
                         text.append_plain("\n");
                     }
                 }
-                ItemType::Integer { default, .. } => {
+                ItemType::Integer {
+                    default, display, ..
+                } => {
                     if let Some(default) = default {
-                        text.append_styled("Default: ", Style::from(Effect::Bold));
-                        text.append_plain(format!("{}\n", default));
+                        text.append_styled(t!("detail.default"), Style::from(Effect::Bold));
+                        text.append_plain(format!("{}\n", display.format(*default)));
                     }
                 }
                 ItemType::Number { default, .. } => {
                     if let Some(default) = default {
-                        text.append_styled("Default: ", Style::from(Effect::Bold));
+                        text.append_styled(t!("detail.default"), Style::from(Effect::Bold));
                         text.append_plain(format!("{}\n", default));
                     }
                 }
                 ItemType::Boolean { default, .. } => {
-                    text.append_styled("Default: ", Style::from(Effect::Bold));
+                    text.append_styled(t!("detail.default"), Style::from(Effect::Bold));
                     text.append_plain(if *default { "true" } else { "false" });
                     text.append_plain("\n");
                 }
@@ -406,20 +682,23 @@ fn on_show_help(s: &mut Cursive) {
                     if let Some(default_idx) = v.default
                         && let Some(default) = v.variants.get(default_idx)
                     {
-                        text.append_styled("Default: ", Style::from(Effect::Bold));
+                        text.append_styled(t!("detail.default"), Style::from(Effect::Bold));
                         text.append_plain(default);
                         text.append_plain("\n");
                     }
-                    text.append_styled("Options:\n", Style::from(Effect::Bold));
+                    text.append_styled(
+                        format!("{}\n", t!("detail.options")),
+                        Style::from(Effect::Bold),
+                    );
                     for opt in &v.variants {
                         text.append_plain(format!("  • {}\n", opt));
                     }
                 }
                 ItemType::Array(v) => {
-                    text.append_styled("Element Type: ", Style::from(Effect::Bold));
+                    text.append_styled(t!("detail.element_type"), Style::from(Effect::Bold));
                     text.append_plain(format!("{}\n", v.element_type));
                     if !v.default.is_empty() {
-                        text.append_styled("Default: ", Style::from(Effect::Bold));
+                        text.append_styled(t!("detail.default"), Style::from(Effect::Bold));
                         text.append_plain(format!("[{:?}]\n", v.default));
                     }
                 }
@@ -439,23 +718,40 @@ fn on_show_help(s: &mut Cursive) {
                     .max_width(80)
                     .max_height(25),
             )
-            .title("╔═══ Item Details ═══╗")
+            .title(t!("detail.heading"))
             .title_position(HAlign::Center),
         )
-        .dismiss_button("Close")
-        .button("OK", |s| {
+        .dismiss_button(t!("btn.close"))
+        .button(t!("btn.ok"), |s| {
             s.pop_layer();
         }),
     );
 }
 
+/// 信息面板标题行，如 `╔═ Menu ═════`
+fn heading(name: &str) -> String {
+    let fill = 44usize.saturating_sub(name.chars().count());
+    format!("╔═ {name} {}\n", "═".repeat(fill))
+}
+
+fn info_line(text: &mut String, line: impl AsRef<str>) {
+    text.push_str("║ ");
+    text.push_str(line.as_ref());
+    text.push('\n');
+}
+
+fn info_tip(text: &mut String, tip: &str) {
+    text.push_str("║\n║ ");
+    text.push_str(tip);
+}
+
 /// 当选择项改变时更新详细信息
 fn on_select(s: &mut Cursive, item: &ElementType) {
     let detail = match item {
         ElementType::Menu(menu) => {
             let mut text = String::new();
-            text.push_str("╔═ Menu ═══════════════════════════════════════\n");
-            text.push_str(&format!("║ Title: {}\n", menu.title));
+            text.push_str(&heading(t!("info.menu")));
+            info_line(&mut text, t!("info.title", title = menu.title));
             if let Some(help) = &menu.help {
                 text.push_str("║\n");
                 for line in help.lines() {
@@ -463,16 +759,21 @@ fn on_select(s: &mut Cursive, item: &ElementType) {
                 }
             }
             text.push_str("║\n");
-            text.push_str(&format!("║ Contains {} items\n", menu.children.len()));
-            text.push_str("║ Required: ");
-            text.push_str(if menu.is_required { "Yes" } else { "No" });
+            info_line(&mut text, t!("info.contains", count = menu.children.len()));
+            text.push_str("║ ");
+            text.push_str(t!("info.required"));
+            text.push_str(if menu.is_required {
+                t!("btn.yes")
+            } else {
+                t!("btn.no")
+            });
             text.push_str("\n╚═══════════════════════════════════════════════");
             text
         }
         ElementType::OneOf(one_of) => {
             let mut text = String::new();
-            text.push_str("╔═ OneOf ══════════════════════════════════════\n");
-            text.push_str(&format!("║ Title: {}\n", one_of.title));
+            text.push_str(&heading(t!("info.oneof")));
+            info_line(&mut text, t!("info.title", title = one_of.title));
             if let Some(help) = &one_of.help {
                 text.push_str("║\n");
                 for line in help.lines() {
@@ -480,20 +781,23 @@ fn on_select(s: &mut Cursive, item: &ElementType) {
                 }
             }
             text.push_str("║\n");
-            text.push_str(&format!("║ Variants: {}\n", one_of.variants.len()));
+            info_line(
+                &mut text,
+                t!("info.variants", count = one_of.variants.len()),
+            );
             if let Some(selected) = one_of.selected() {
-                text.push_str(&format!("║ Current: {}\n", selected.title));
+                info_line(&mut text, t!("info.current", value = selected.title));
             } else {
-                text.push_str("║ Current: <Unset>\n");
+                info_line(&mut text, t!("info.current", value = t!("info.unset")));
             }
-            text.push_str("║ Tip: Press Tab to switch variants\n");
+            info_line(&mut text, t!("info.tip_tab"));
             text.push_str("╚═══════════════════════════════════════════════");
             text
         }
         ElementType::Item(item) => {
             let mut text = String::new();
-            text.push_str("╔═ Item ═══════════════════════════════════════\n");
-            text.push_str(&format!("║ Name: {}\n", item.base.title));
+            text.push_str(&heading(t!("info.item")));
+            info_line(&mut text, t!("info.name", name = item.base.title));
 
             if let Some(help) = &item.base.help {
                 text.push_str("║\n");
@@ -502,90 +806,102 @@ fn on_select(s: &mut Cursive, item: &ElementType) {
                 }
             }
             text.push_str("║\n");
+            if let Some(hint) = item.constraints.describe() {
+                info_line(&mut text, t!("info.constraints", hint = hint));
+            }
+            if let Err(e) = item.validate() {
+                info_line(&mut text, t!("info.invalid", error = e));
+            }
 
             match &item.item_type {
                 ItemType::Boolean { value, default } => {
-                    text.push_str("║ Type: Boolean\n");
-                    text.push_str(&format!(
-                        "║ Current: {}\n",
-                        if *value { "✓ True" } else { "✗ False" }
-                    ));
-                    text.push_str(&format!(
-                        "║ Default: {}\n",
-                        if *default { "True" } else { "False" }
-                    ));
-                    text.push_str("║\n║ Tip: Press Enter to toggle");
+                    info_line(&mut text, format!("{}Boolean", t!("detail.type")));
+                    let current = if *value {
+                        format!("✓ {}", t!("info.true"))
+                    } else {
+                        format!("✗ {}", t!("info.false"))
+                    };
+                    info_line(&mut text, t!("info.current", value = current));
+                    let default = if *default {
+                        t!("info.true")
+                    } else {
+                        t!("info.false")
+                    };
+                    info_line(&mut text, t!("info.default", value = default));
+                    info_tip(&mut text, t!("info.tip_toggle"));
                 }
                 ItemType::String { value, default } => {
-                    text.push_str("║ Type: String\n");
-                    text.push_str(&format!(
-                        "║ Current: {}\n",
-                        value
-                            .as_ref()
-                            .map(|v| format!("\"{}\"", v))
-                            .unwrap_or_else(|| "<Empty>".to_string())
-                    ));
+                    info_line(&mut text, format!("{}String", t!("detail.type")));
+                    let current = value
+                        .as_ref()
+                        .map(|v| format!("\"{}\"", v))
+                        .unwrap_or_else(|| t!("info.empty").to_string());
+                    info_line(&mut text, t!("info.current", value = current));
                     if let Some(d) = default {
-                        text.push_str(&format!("║ Default: \"{}\"\n", d));
+                        info_line(&mut text, t!("info.default", value = format!("\"{d}\"")));
                     }
-                    text.push_str("║\n║ Tip: Press Enter to edit");
+                    info_tip(&mut text, t!("info.tip_edit"));
                 }
                 ItemType::Number { value, default } => {
-                    text.push_str("║ Type: Number (float)\n");
-                    text.push_str(&format!(
-                        "║ Current: {}\n",
-                        value
-                            .map(|v| v.to_string())
-                            .unwrap_or_else(|| "<Empty>".to_string())
-                    ));
+                    info_line(
+                        &mut text,
+                        format!("{}{}", t!("detail.type"), t!("type.number_float")),
+                    );
+                    let current = value
+                        .map(|v| v.to_string())
+                        .unwrap_or_else(|| t!("info.empty").to_string());
+                    info_line(&mut text, t!("info.current", value = current));
                     if let Some(d) = default {
-                        text.push_str(&format!("║ Default: {}\n", d));
+                        info_line(&mut text, t!("info.default", value = d));
                     }
-                    text.push_str("║\n║ Tip: Press Enter to edit");
+                    info_tip(&mut text, t!("info.tip_edit"));
                 }
-                ItemType::Integer { value, default } => {
-                    text.push_str("║ Type: Integer\n");
-                    text.push_str(&format!(
-                        "║ Current: {}\n",
-                        value
-                            .map(|v| v.to_string())
-                            .unwrap_or_else(|| "<Empty>".to_string())
-                    ));
+                ItemType::Integer {
+                    value,
+                    default,
+                    display,
+                } => {
+                    info_line(&mut text, format!("{}Integer", t!("detail.type")));
+                    let current = value
+                        .map(|v| display.format(v))
+                        .unwrap_or_else(|| t!("info.empty").to_string());
+                    info_line(&mut text, t!("info.current", value = current));
                     if let Some(d) = default {
-                        text.push_str(&format!("║ Default: {}\n", d));
+                        info_line(&mut text, t!("info.default", value = display.format(*d)));
                     }
-                    text.push_str("║\n║ Tip: Press Enter to edit");
+                    info_tip(&mut text, t!("info.tip_edit"));
                 }
                 ItemType::Enum(enum_item) => {
-                    text.push_str("║ Type: Enum\n");
-                    text.push_str(&format!("║ Options: {}\n", enum_item.variants.join(", ")));
-                    if let Some(val) = enum_item.value_str() {
-                        text.push_str(&format!("║ Current: {}\n", val));
-                    } else {
-                        text.push_str("║ Current: <Unset>\n");
-                    }
-                    text.push_str("║\n║ Tip: Press Enter to select");
+                    info_line(&mut text, format!("{}Enum", t!("detail.type")));
+                    let options = enum_item.variants.join(", ");
+                    info_line(&mut text, t!("info.options", options = options));
+                    let current = enum_item.value_str().unwrap_or(t!("info.unset"));
+                    info_line(&mut text, t!("info.current", value = current));
+                    info_tip(&mut text, t!("info.tip_select"));
                 }
                 ItemType::Array(array_item) => {
-                    text.push_str("║ Type: Array\n");
-                    text.push_str(&format!("║ Element Type: {}\n", array_item.element_type));
-                    text.push_str(&format!("║ Count: {}\n", array_item.values.len()));
+                    info_line(&mut text, format!("{}Array", t!("detail.type")));
+                    let ty = &array_item.element_type;
+                    info_line(&mut text, t!("info.element_type", ty = ty));
+                    let count = array_item.values.len();
+                    info_line(&mut text, t!("info.count", count = count));
                     if !array_item.values.is_empty() {
-                        text.push_str("║ Values:\n");
+                        info_line(&mut text, t!("info.values"));
                         let max_display = 5;
                         for (idx, val) in array_item.values.iter().take(max_display).enumerate() {
                             text.push_str(&format!("║   [{}] {}\n", idx, val));
                         }
                         if array_item.values.len() > max_display {
-                            text.push_str(&format!(
-                                "║   ... and {} more\n",
-                                array_item.values.len() - max_display
-                            ));
+                            let more = array_item.values.len() - max_display;
+                            info_line(&mut text, format!("  {}", t!("info.more", count = more)));
                         }
                     } else {
-                        text.push_str("║ Values: <Empty>\n");
+                        info_line(
+                            &mut text,
+                            format!("{} {}", t!("info.values"), t!("info.empty")),
+                        );
                     }
-                    text.push_str("║\n║ Tip: Enter=Edit, Del=Delete item");
+                    info_tip(&mut text, t!("info.tip_array"));
                 }
             }
             text.push_str("\n╚═══════════════════════════════════════════════");
@@ -640,11 +956,11 @@ fn enter_elem(s: &mut Cursive, elem: &ElementType) {
             info!("Handling Menu: {}", menu.title);
             // 进入子菜单
             if menu.is_none() {
-                if let Some(ElementType::Menu(m)) =
-                    s.user_data::<AppData>().unwrap().root.get_mut_by_key(&key)
-                {
-                    m.is_set = true;
-                }
+                s.user_data::<AppData>().unwrap().edit(&key, |elem| {
+                    if let ElementType::Menu(m) = elem {
+                        m.is_set = true;
+                    }
+                });
                 handle_edit(s);
             } else {
                 enter_menu(s, menu);
@@ -665,26 +981,62 @@ fn enter_elem(s: &mut Cursive, elem: &ElementType) {
         }
         ElementType::Item(item) => {
             info!("Handling Item: {}", item.base.key());
+            if item.is_read_only() {
+                show_read_only(s, item);
+                return;
+            }
             // 根据类型显示编辑对话框
             match &item.item_type {
                 ItemType::Boolean { .. } => {
                     // Boolean 类型直接切换
-                    if let Some(ElementType::Item(b)) =
-                        s.user_data::<AppData>().unwrap().root.get_mut_by_key(&key)
-                        && let ItemType::Boolean { value, .. } = &mut b.item_type
-                    {
-                        *value = !*value;
-                    }
+                    s.user_data::<AppData>().unwrap().edit(&key, |elem| {
+                        if let ElementType::Item(b) = elem
+                            && let ItemType::Boolean { value, .. } = &mut b.item_type
+                        {
+                            *value = !*value;
+                        }
+                    });
                     handle_edit(s);
                 }
-                ItemType::String { value, default } => {
-                    show_string_edit(s, &item.base.key(), &item.base.title, value, default);
+                ItemType::String { value, default } => match item.constraints.path {
+                    Some(kind) => show_path_picker(
+                        s,
+                        &item.base.key(),
+                        &item.base.title,
+                        value,
+                        default,
+                        kind,
+                    ),
+                    None => show_string_edit(s, &item.base.key(), &item.base.title, value, default),
+                },
+                ItemType::Integer { .. } | ItemType::Number { .. } if has_slider(item) => {
+                    show_slider_edit(s, item);
                 }
                 ItemType::Number { value, default } => {
                     show_number_edit(s, &item.base.key(), &item.base.title, *value, *default);
                 }
-                ItemType::Integer { value, default } => {
-                    show_integer_edit(s, &item.base.key(), &item.base.title, *value, *default);
+                ItemType::Integer {
+                    value,
+                    default,
+                    display,
+                } => {
+                    show_integer_edit(
+                        s,
+                        &item.base.key(),
+                        &item.base.title,
+                        *value,
+                        *default,
+                        *display,
+                    );
+                }
+                ItemType::Enum(enum_item) if enum_item.is_inline() => {
+                    // 选项较少时在菜单内直接切换
+                    s.user_data::<AppData>().unwrap().edit(&key, |elem| {
+                        if let ElementType::Item(item) = elem {
+                            item.step(1);
+                        }
+                    });
+                    handle_edit(s);
                 }
                 ItemType::Enum(enum_item) => {
                     show_enum_select(s, &item.base.title, enum_item);
@@ -697,6 +1049,24 @@ fn enter_elem(s: &mut Cursive, elem: &ElementType) {
     }
 }
 
+/// 只读字段仅展示当前值
+fn show_read_only(s: &mut Cursive, item: &Item) {
+    let value = item.as_json();
+    let mut text = format!(
+        "{}\n\n{}",
+        item.base.title,
+        t!("readonly.current", value = value)
+    );
+    if let Some(help) = &item.base.help {
+        text.push_str(&format!("\n\n{help}"));
+    }
+    s.add_layer(
+        Dialog::text(text)
+            .title(t!("readonly.title"))
+            .button(t!("btn.ok"), handle_back),
+    );
+}
+
 pub fn enter_key(s: &mut Cursive, key: &str) {
     if let Some(app) = s.user_data::<AppData>()
         && let Some(item) = app.root.get_by_key(key).cloned()
@@ -1,3 +1,9 @@
+pub mod changes;
+pub mod clipboard;
+pub mod defconfig;
 pub mod editors;
 pub(crate) mod icon;
 pub mod menu;
+pub mod mouse;
+pub mod preset;
+pub mod search;
@@ -0,0 +1,50 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use cursive::{
+    Vec2, View,
+    event::{Event, EventResult, EventTrigger, MouseButton, MouseEvent},
+    views::OnEventView,
+};
+
+use crate::ui::handle_back;
+
+/// 两次单击被视为双击的最大间隔
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// 为菜单列表加上鼠标操作：单击选中，双击进入，右键返回上一级
+///
+/// `SelectView` 默认在单击已选中的行时直接提交，这里拦截第一次松开，
+/// 只有在同一位置快速第二次松开时才交给列表处理。滚轮由外层的
+/// `ScrollView` 处理。
+pub fn mouse_list<V: View>(view: V) -> OnEventView<V> {
+    let last_click: Arc<Mutex<Option<(Instant, Vec2)>>> = Arc::default();
+    OnEventView::new(view).on_pre_event_inner(EventTrigger::mouse(), move |_, event| {
+        let Event::Mouse {
+            event, position, ..
+        } = event
+        else {
+            return None;
+        };
+        match event {
+            MouseEvent::Release(MouseButton::Left) => {
+                let mut last = last_click.lock().unwrap();
+                let double = matches!(
+                    *last,
+                    Some((at, pos)) if pos == *position && at.elapsed() < DOUBLE_CLICK
+                );
+                if double {
+                    *last = None;
+                    None
+                } else {
+                    *last = Some((Instant::now(), *position));
+                    Some(EventResult::consumed())
+                }
+            }
+            MouseEvent::Press(MouseButton::Right) => Some(EventResult::with_cb(handle_back)),
+            _ => None,
+        }
+    })
+}
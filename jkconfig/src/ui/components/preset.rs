@@ -0,0 +1,137 @@
+use cursive::{
+    Cursive,
+    view::{Nameable, Resizable, Scrollable},
+    views::{Dialog, DummyView, EditView, LinearLayout, SelectView, TextView},
+};
+
+use crate::{
+    data::AppData,
+    t,
+    ui::{components::menu::menu_select_flush, handle_back},
+};
+
+const PRESET_LIST: &str = "preset_list";
+const PRESET_NAME: &str = "preset_name";
+
+/// 显示预设对话框 - P 键
+///
+/// 列出已保存的预设，回车应用所选预设，或将当前配置另存为新预设。
+pub fn show_presets(s: &mut Cursive) {
+    let Some(app) = s.user_data::<AppData>() else {
+        return;
+    };
+    let presets = match app.list_presets() {
+        Ok(presets) => presets,
+        Err(e) => {
+            s.add_layer(
+                Dialog::info(t!("preset.read_failed", error = format!("{e:#}")))
+                    .title(t!("preset.title")),
+            );
+            return;
+        }
+    };
+    let dir = app.presets_dir();
+    // 占位路径段，让 Esc / Cancel 走 handle_back 时不会丢失当前菜单路径
+    app.push_field("P");
+
+    let mut list = SelectView::<String>::new().on_submit(|s, name: &String| confirm_apply(s, name));
+    for name in presets {
+        list.add_item(name.clone(), name);
+    }
+    let body = if list.is_empty() {
+        LinearLayout::vertical().child(TextView::new(t!("preset.empty")))
+    } else {
+        LinearLayout::vertical().child(list.with_name(PRESET_LIST).scrollable().max_height(12))
+    };
+
+    s.add_layer(
+        Dialog::around(
+            body.child(DummyView)
+                .child(TextView::new(t!("preset.dir", dir = dir.display()))),
+        )
+        .title(t!("preset.title"))
+        .button(t!("btn.save_as"), show_save_as)
+        .button(t!("btn.cancel"), handle_back)
+        .min_width(50),
+    );
+}
+
+fn confirm_apply(s: &mut Cursive, name: &str) {
+    let name = name.to_string();
+    s.add_layer(
+        Dialog::text(t!("preset.apply_confirm", name = name))
+            .title(t!("preset.apply_title"))
+            .button(t!("btn.apply"), move |s| apply(s, &name))
+            .button(t!("btn.cancel"), |s| {
+                s.pop_layer();
+            }),
+    );
+}
+
+fn apply(s: &mut Cursive, name: &str) {
+    let Some(app) = s.user_data::<AppData>() else {
+        return;
+    };
+    if let Err(e) = app.apply_preset(name) {
+        s.add_layer(
+            Dialog::info(t!("preset.apply_failed", error = format!("{e:#}")))
+                .title(t!("preset.apply_title")),
+        );
+        return;
+    }
+    // 菜单树已整体替换，回到根菜单并刷新
+    while s.screen().len() > 1 {
+        s.pop_layer();
+    }
+    menu_select_flush(s, "");
+    s.add_layer(Dialog::info(t!("preset.applied", name = name)).title(t!("preset.title")));
+}
+
+fn show_save_as(s: &mut Cursive) {
+    s.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(TextView::new(t!("preset.name")))
+                .child(
+                    EditView::new()
+                        .on_submit(save_as)
+                        .with_name(PRESET_NAME)
+                        .fixed_width(40),
+                ),
+        )
+        .title(t!("preset.save_title"))
+        .button(t!("btn.save"), |s| {
+            let name = s
+                .call_on_name(PRESET_NAME, |v: &mut EditView| v.get_content())
+                .unwrap_or_default();
+            save_as(s, &name);
+        })
+        .button(t!("btn.cancel"), |s| {
+            s.pop_layer();
+        }),
+    );
+}
+
+fn save_as(s: &mut Cursive, name: &str) {
+    let name = name.trim().to_string();
+    let Some(app) = s.user_data::<AppData>() else {
+        return;
+    };
+    match app.save_preset(&name) {
+        Ok(path) => {
+            // 关闭名称输入框和预设列表
+            s.pop_layer();
+            handle_back(s);
+            s.add_layer(
+                Dialog::info(t!("preset.saved", name = name, path = path.display()))
+                    .title(t!("preset.title")),
+            );
+        }
+        Err(e) => {
+            s.add_layer(
+                Dialog::info(t!("preset.save_failed", error = format!("{e:#}")))
+                    .title(t!("preset.save_title")),
+            );
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use cursive::{
+    Cursive,
+    theme::ColorStyle,
+    utils::markup::StyledString,
+    view::{Nameable, Resizable, Scrollable},
+    views::{Dialog, DummyView, EditView, LinearLayout, SelectView, TextView},
+};
+
+use crate::{
+    data::{
+        AppData,
+        search::{MatchField, SearchHit},
+        types::ElementType,
+    },
+    t,
+    ui::{
+        components::menu::{enter_menu, menu_view_name},
+        handle_back,
+    },
+};
+
+const SEARCH_INPUT: &str = "search_input";
+const SEARCH_RESULTS: &str = "search_results";
+
+/// 显示全局搜索对话框 - `/` 键
+pub fn show_search(s: &mut Cursive) {
+    // 占位路径段，让 Esc / Cancel 走 handle_back 时不会丢失当前菜单路径
+    if let Some(app) = s.user_data::<AppData>() {
+        app.push_field("/");
+    }
+
+    let results = SelectView::<String>::new()
+        .on_submit(|s, key: &String| jump_to(s, key))
+        .with_name(SEARCH_RESULTS)
+        .scrollable()
+        .fixed_size((70, 15));
+
+    s.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(TextView::new(t!("search.prompt")))
+                .child(
+                    EditView::new()
+                        .on_edit(|s, query, _| update_results(s, query))
+                        .on_submit(|s, _| {
+                            let key = s
+                                .call_on_name(SEARCH_RESULTS, |v: &mut SelectView<String>| {
+                                    v.selection().map(|k| k.to_string())
+                                })
+                                .flatten();
+                            if let Some(key) = key {
+                                jump_to(s, &key);
+                            } else {
+                                s.focus_name(SEARCH_RESULTS).ok();
+                            }
+                        })
+                        .with_name(SEARCH_INPUT)
+                        .fixed_width(70),
+                )
+                .child(DummyView)
+                .child(results),
+        )
+        .title(t!("search.title"))
+        .button(t!("btn.cancel"), handle_back),
+    );
+}
+
+fn update_results(s: &mut Cursive, query: &str) {
+    let hits = s
+        .user_data::<AppData>()
+        .map(|app| app.root.search(query))
+        .unwrap_or_default();
+
+    s.call_on_name(SEARCH_RESULTS, |view: &mut SelectView<String>| {
+        view.clear();
+        for hit in hits {
+            let label = format_hit(&hit);
+            view.add_item(label, hit.key);
+        }
+    });
+}
+
+fn format_hit(hit: &SearchHit) -> StyledString {
+    let mut label = StyledString::new();
+    label.append_styled(&hit.key, ColorStyle::title_secondary());
+    label.append_plain("  ");
+    label.append_styled(&hit.title, ColorStyle::secondary());
+    if hit.matched == MatchField::Description {
+        label.append_plain("  ");
+        label.append_styled(t!("search.in_description"), ColorStyle::tertiary());
+    }
+    label
+}
+
+/// 关闭搜索框，重建从根到目标父菜单的菜单栈，并选中目标项
+pub(crate) fn jump_to(s: &mut Cursive, key: &str) {
+    // 回到根菜单
+    while s.screen().len() > 1 {
+        s.pop_layer();
+    }
+
+    let Some(app) = s.user_data::<AppData>() else {
+        return;
+    };
+    app.current_key.clear();
+
+    let segments: Vec<&str> = key.split('.').collect();
+    let mut parent = String::new();
+    for segment in &segments[..segments.len() - 1] {
+        if !parent.is_empty() {
+            parent.push('.');
+        }
+        parent.push_str(segment);
+
+        let Some(app) = s.user_data::<AppData>() else {
+            return;
+        };
+        let menu = match app.root.get_by_key(&parent) {
+            Some(ElementType::Menu(menu)) => menu.clone(),
+            Some(ElementType::OneOf(one_of)) => match one_of.selected() {
+                Some(ElementType::Menu(menu)) => menu.clone(),
+                _ => break,
+            },
+            _ => break,
+        };
+        app.enter(&parent);
+        enter_menu(s, &menu);
+    }
+
+    let parent = s
+        .user_data::<AppData>()
+        .map(|app| app.key_string())
+        .unwrap_or_default();
+    let cb = s.call_on_name(
+        &menu_view_name(&parent),
+        |view: &mut SelectView<ElementType>| {
+            let idx = view.iter().position(|(_, elem)| elem.key() == key)?;
+            Some(view.set_selection(idx))
+        },
+    );
+    if let Some(Some(cb)) = cb {
+        cb(s);
+    }
+}
@@ -1,8 +1,13 @@
 use cursive::{Cursive, views::Dialog};
 
-use crate::{data::AppData, ui::components::menu::menu_select_flush};
+use crate::{
+    data::{AppData, app_data::format_violations},
+    t,
+    ui::components::{menu::menu_select_flush, search::jump_to},
+};
 
 pub mod components;
+pub mod theme;
 
 pub fn handle_back(siv: &mut Cursive) {
     if let Some(app) = siv.user_data::<AppData>() {
@@ -36,10 +41,13 @@ pub fn enter_submenu(siv: &mut Cursive, key: &str) {
 pub fn handle_quit(siv: &mut Cursive) {
     enter_submenu(siv, "_");
     siv.add_layer(
-        Dialog::text("Quit without saving?")
-            .title("Quit")
-            .button("Back", handle_back)
-            .button("Quit", |s| {
+        Dialog::text(t!("quit.confirm"))
+            .title(t!("dialog.quit"))
+            .button(t!("btn.back"), handle_back)
+            .button(t!("btn.quit"), |s| {
+                if let Some(app) = s.user_data::<AppData>() {
+                    app.needs_save = false;
+                }
                 s.quit();
             }),
     );
@@ -47,15 +55,32 @@ pub fn handle_quit(siv: &mut Cursive) {
 
 /// 处理保存 - S键
 pub fn handle_save(siv: &mut Cursive) {
+    // 存在违反 schema 约束的值或未填写的必填项时拒绝保存
+    let (violations, missing) = siv
+        .user_data::<AppData>()
+        .map(|app| (app.root.validate(), app.root.missing_required()))
+        .unwrap_or_default();
+    if !violations.is_empty() {
+        // 优先跳转到第一个未填写的必填项
+        let first = missing.first().unwrap_or(&violations[0].key).to_string();
+        siv.add_layer(
+            Dialog::text(format_violations(&violations))
+                .title(t!("save.blocked"))
+                .button(t!("btn.goto"), move |s| jump_to(s, &first))
+                .dismiss_button(t!("btn.ok")),
+        );
+        return;
+    }
+
     siv.add_layer(
-        Dialog::text("Save and exit?")
-            .title("Save")
-            .button("Ok", |s| {
+        Dialog::text(t!("save.confirm"))
+            .title(t!("dialog.save"))
+            .button(t!("btn.ok"), |s| {
                 let app = s.user_data::<AppData>().unwrap();
                 app.needs_save = true;
                 s.quit();
             })
-            .button("Cancel", |s| {
+            .button(t!("btn.cancel"), |s| {
                 s.pop_layer();
             }),
     );
@@ -0,0 +1,219 @@
+//! Color themes for the TUI.
+//!
+//! A theme is picked by name (see [`ThemeName`]) and can be tweaked per
+//! palette color from the user settings file. Accessible mode swaps the
+//! emoji icons and color-only cues for plain text markers.
+
+use std::{
+    collections::BTreeMap,
+    env,
+    sync::{
+        RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use cursive::{
+    Cursive,
+    theme::{BaseColor, BorderStyle, Color, Effect, Palette, PaletteColor, Style, Theme},
+};
+use serde::{Deserialize, Serialize};
+
+static ACCESSIBLE: AtomicBool = AtomicBool::new(false);
+static ERROR_COLOR: RwLock<Color> = RwLock::new(Color::Dark(BaseColor::Red));
+
+/// Built-in color schemes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    /// Light or dark from `COLORFGBG`, classic when it is not set.
+    #[default]
+    Auto,
+    /// The blue dialog look.
+    Classic,
+    Dark,
+    Light,
+    HighContrast,
+    /// The terminal's own foreground and background colors.
+    Terminal,
+}
+
+impl ThemeName {
+    /// Parse a theme name as written in the settings file.
+    pub fn parse(name: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(name.trim().to_lowercase())).ok()
+    }
+
+    /// Resolve [`ThemeName::Auto`] from the `COLORFGBG` value set by many
+    /// terminals (`"fg;bg"`, where bg 7 or 15 is a light background).
+    pub fn resolve(self, colorfgbg: Option<&str>) -> Self {
+        if self != ThemeName::Auto {
+            return self;
+        }
+        match colorfgbg.and_then(|v| v.rsplit(';').next()) {
+            Some("7" | "15") => ThemeName::Light,
+            Some(bg) if bg.parse::<u8>().is_ok() => ThemeName::Dark,
+            _ => ThemeName::Classic,
+        }
+    }
+}
+
+/// The `[theme]` table of the settings file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeSettings {
+    pub name: ThemeName,
+    /// Text markers instead of emoji and color-only signaling.
+    pub accessible: bool,
+    /// Palette overrides, e.g. `highlight = "#005f87"` or `error = "light red"`.
+    pub palette: BTreeMap<String, String>,
+}
+
+impl ThemeSettings {
+    /// Build the cursive theme. Returns the theme and the color used for
+    /// errors; unknown colors are skipped with a warning.
+    pub fn build(&self) -> (Theme, Color) {
+        let name = self.name.resolve(env::var("COLORFGBG").ok().as_deref());
+        let (mut theme, mut error) = builtin(name);
+        for (key, value) in &self.palette {
+            let Some(color) = Color::parse(value.trim()) else {
+                warn!("Unknown color `{value}` for theme key `{key}`");
+                continue;
+            };
+            if key == "error" {
+                error = color;
+            } else {
+                theme.palette.set_color(key, color);
+            }
+        }
+        (theme, error)
+    }
+
+    /// Apply the theme and accessible mode to `siv`.
+    pub fn apply(&self, siv: &mut Cursive) {
+        let (theme, error) = self.build();
+        siv.set_theme(theme);
+        *ERROR_COLOR.write().unwrap() = error;
+        ACCESSIBLE.store(self.accessible, Ordering::Relaxed);
+    }
+}
+
+/// Whether accessible mode is on.
+pub fn accessible() -> bool {
+    ACCESSIBLE.load(Ordering::Relaxed)
+}
+
+/// Style for errors and missing values; bold as well in accessible mode.
+pub fn error_style() -> Style {
+    let style = Style::from(*ERROR_COLOR.read().unwrap());
+    if accessible() {
+        style.combine(Effect::Bold)
+    } else {
+        style
+    }
+}
+
+fn builtin(name: ThemeName) -> (Theme, Color) {
+    use BaseColor::*;
+    use Color::{Dark, Light};
+    use PaletteColor::*;
+
+    let colors = match name {
+        ThemeName::Auto | ThemeName::Classic => {
+            return (Theme::retro(), Dark(Red));
+        }
+        ThemeName::Terminal => return (Theme::terminal_default(), Dark(Red)),
+        ThemeName::Dark => [
+            (Background, Dark(Black)),
+            (Shadow, Dark(Black)),
+            (View, Dark(Black)),
+            (Primary, Light(White)),
+            (Secondary, Light(Cyan)),
+            (Tertiary, Light(Yellow)),
+            (TitlePrimary, Light(Cyan)),
+            (TitleSecondary, Light(Yellow)),
+            (Highlight, Dark(Cyan)),
+            (HighlightInactive, Dark(Blue)),
+            (HighlightText, Light(White)),
+        ],
+        ThemeName::Light => [
+            (Background, Light(White)),
+            (Shadow, Light(Black)),
+            (View, Light(White)),
+            (Primary, Dark(Black)),
+            (Secondary, Dark(Blue)),
+            (Tertiary, Dark(Magenta)),
+            (TitlePrimary, Dark(Blue)),
+            (TitleSecondary, Dark(Magenta)),
+            (Highlight, Dark(Blue)),
+            (HighlightInactive, Light(Black)),
+            (HighlightText, Light(White)),
+        ],
+        ThemeName::HighContrast => [
+            (Background, Dark(Black)),
+            (Shadow, Dark(Black)),
+            (View, Dark(Black)),
+            (Primary, Light(White)),
+            (Secondary, Light(White)),
+            (Tertiary, Light(Yellow)),
+            (TitlePrimary, Light(Yellow)),
+            (TitleSecondary, Light(White)),
+            (Highlight, Light(Yellow)),
+            (HighlightInactive, Light(White)),
+            (HighlightText, Dark(Black)),
+        ],
+    };
+    let mut palette = Palette::retro();
+    for (key, color) in colors {
+        palette[key] = color;
+    }
+    let error = match name {
+        ThemeName::Dark | ThemeName::HighContrast => Light(Red),
+        _ => Dark(Red),
+    };
+    let theme = Theme {
+        shadow: false,
+        borders: BorderStyle::Simple,
+        palette,
+    };
+    (theme, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_name_and_overrides() {
+        assert_eq!(
+            ThemeName::parse("High-Contrast"),
+            Some(ThemeName::HighContrast)
+        );
+        assert_eq!(ThemeName::parse("solarized"), None);
+        assert_eq!(ThemeName::Auto.resolve(Some("0;15")), ThemeName::Light);
+        assert_eq!(ThemeName::Auto.resolve(Some("15;0")), ThemeName::Dark);
+        assert_eq!(ThemeName::Auto.resolve(None), ThemeName::Classic);
+        assert_eq!(ThemeName::Dark.resolve(Some("0;15")), ThemeName::Dark);
+
+        let settings = ThemeSettings {
+            name: ThemeName::Light,
+            accessible: false,
+            palette: [
+                ("highlight".to_string(), "#005f87".to_string()),
+                ("error".to_string(), "light magenta".to_string()),
+                ("view".to_string(), "not a color".to_string()),
+            ]
+            .into(),
+        };
+        let (theme, error) = settings.build();
+        assert_eq!(
+            theme.palette[PaletteColor::Highlight],
+            Color::Rgb(0, 0x5f, 0x87)
+        );
+        assert_eq!(
+            theme.palette[PaletteColor::View],
+            Color::Light(BaseColor::White)
+        );
+        assert_eq!(error, Color::Light(BaseColor::Magenta));
+    }
+}
@@ -0,0 +1,205 @@
+//! 访问控制
+//!
+//! 令牌认证与跨域（CORS）配置，在局域网中暴露 Web 编辑器时使用
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{
+        HeaderMap, HeaderValue, Method, StatusCode,
+        header::{AUTHORIZATION, CONTENT_TYPE, COOKIE, SET_COOKIE, WWW_AUTHENTICATE},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use super::handlers::ApiError;
+
+/// 保存令牌的 cookie 名
+const COOKIE_NAME: &str = "jkconfig_token";
+
+/// 令牌认证状态
+#[derive(Clone)]
+pub struct Auth {
+    token: Arc<str>,
+    /// 通过 HTTPS 提供服务时 cookie 只随 HTTPS 发送
+    secure: bool,
+}
+
+/// 请求携带令牌的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Credential {
+    Header,
+    Cookie,
+    /// `?token=`，浏览器首次打开页面时使用，之后改用 cookie
+    Query,
+}
+
+impl Auth {
+    /// 令牌原样写入 URL 和 cookie，只接受无需转义的字符
+    pub fn new(token: &str, secure: bool) -> anyhow::Result<Self> {
+        if !token
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"._~-".contains(&b))
+        {
+            anyhow::bail!("web token may only contain letters, digits, `.`, `_`, `~` and `-`");
+        }
+        Ok(Self {
+            token: token.into(),
+            secure,
+        })
+    }
+
+    /// 找出请求中与令牌一致的凭据
+    fn check(&self, headers: &HeaderMap, query: Option<&str>) -> Option<Credential> {
+        let bearer = headers
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        if bearer.is_some_and(|t| self.matches(t.trim())) {
+            return Some(Credential::Header);
+        }
+        let mut cookie = headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(';'))
+            .filter_map(|c| c.trim().strip_prefix(COOKIE_NAME)?.strip_prefix('='));
+        if cookie.any(|t| self.matches(t)) {
+            return Some(Credential::Cookie);
+        }
+        let mut param = query
+            .into_iter()
+            .flat_map(|q| q.split('&'))
+            .filter_map(|p| p.strip_prefix("token="));
+        if param.any(|t| self.matches(t)) {
+            return Some(Credential::Query);
+        }
+        None
+    }
+
+    /// 逐字节比较全部内容，耗时与不匹配的位置无关
+    fn matches(&self, token: &str) -> bool {
+        let (a, b) = (self.token.as_bytes(), token.as_bytes());
+        a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+    }
+
+    fn cookie(&self) -> String {
+        let secure = if self.secure { "; Secure" } else { "" };
+        format!(
+            "{COOKIE_NAME}={}; Path=/; HttpOnly; SameSite=Strict{secure}",
+            self.token
+        )
+    }
+}
+
+/// 认证中间件，`/api/health` 之外的请求都需要令牌
+pub async fn require_token(State(auth): State<Auth>, request: Request, next: Next) -> Response {
+    if request.uri().path() == "/api/health" {
+        return next.run(request).await;
+    }
+    match auth.check(request.headers(), request.uri().query()) {
+        Some(Credential::Query) => {
+            let mut response = next.run(request).await;
+            if let Ok(cookie) = HeaderValue::from_str(&auth.cookie()) {
+                response.headers_mut().append(SET_COOKIE, cookie);
+            }
+            response
+        }
+        Some(_) => next.run(request).await,
+        None => {
+            let mut response = ApiError {
+                status: StatusCode::UNAUTHORIZED,
+                message: "missing or invalid token".into(),
+            }
+            .into_response();
+            response
+                .headers_mut()
+                .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            response
+        }
+    }
+}
+
+/// 按允许的来源创建 CORS 层，`*` 允许任意来源；列表为空时不跨域
+pub fn cors_layer(origins: &[String]) -> anyhow::Result<Option<CorsLayer>> {
+    if origins.is_empty() {
+        return Ok(None);
+    }
+    let allow = if origins.iter().any(|o| o == "*") {
+        AllowOrigin::any()
+    } else {
+        let origins = origins
+            .iter()
+            .map(|o| {
+                HeaderValue::from_str(o.trim_end_matches('/'))
+                    .map_err(|_| anyhow::anyhow!("invalid CORS origin `{o}`"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        AllowOrigin::list(origins)
+    };
+    Ok(Some(
+        CorsLayer::new()
+            .allow_origin(allow)
+            .allow_methods([Method::GET, Method::PUT, Method::POST])
+            .allow_headers([AUTHORIZATION, CONTENT_TYPE]),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(name: axum::http::HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_check_token() {
+        let auth = Auth::new("s3cret", false).unwrap();
+        let none = HeaderMap::new();
+        assert_eq!(
+            auth.check(&headers(AUTHORIZATION, "Bearer s3cret"), None),
+            Some(Credential::Header)
+        );
+        assert_eq!(
+            auth.check(&headers(COOKIE, "theme=dark; jkconfig_token=s3cret"), None),
+            Some(Credential::Cookie)
+        );
+        assert_eq!(
+            auth.check(&none, Some("x=1&token=s3cret")),
+            Some(Credential::Query)
+        );
+        assert_eq!(
+            auth.check(&headers(AUTHORIZATION, "Bearer s3cre"), None),
+            None
+        );
+        assert_eq!(auth.check(&none, Some("token=s3cret0")), None);
+        assert_eq!(auth.check(&none, None), None);
+        assert!(Auth::new("t", true).unwrap().cookie().ends_with("; Secure"));
+    }
+
+    #[test]
+    fn test_token_charset() {
+        assert!(Auth::new("Ab0.-_~", false).is_ok());
+        for token in ["a+b", "50%", "a&token=b", "a;b", "a b"] {
+            assert!(Auth::new(token, false).is_err(), "{token}");
+        }
+    }
+
+    #[test]
+    fn test_cors_layer() {
+        assert!(cors_layer(&[]).unwrap().is_none());
+        assert!(cors_layer(&["*".into()]).unwrap().is_some());
+        assert!(
+            cors_layer(&["https://lab.example.com/".into()])
+                .unwrap()
+                .is_some()
+        );
+        assert!(cors_layer(&["bad\norigin".into()]).is_err());
+    }
+}
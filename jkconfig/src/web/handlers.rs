@@ -2,13 +2,45 @@
 //!
 //! 处理各种HTTP请求的函数
 
+use std::convert::Infallible;
+
 use axum::{
-    extract::State,
-    response::{Html, Json},
+    extract::{Path, State},
+    http::StatusCode,
+    response::{
+        Html, IntoResponse, Json, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
 };
-use serde_json::json;
+use futures_util::Stream;
+use serde_json::{Value, json};
+use tokio::sync::broadcast::error::RecvError;
+
+use super::server::{AppState, Change};
+
+/// API错误，以 `{"error": "..."}` 的形式返回
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, err: anyhow::Error) -> Self {
+        Self {
+            status,
+            message: format!("{err:#}"),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(json!({ "error": self.message }))).into_response()
+    }
+}
 
-use super::server::AppState;
+type ApiResult = Result<Json<Value>, ApiError>;
 
 /// 根路径处理器 - 返回Hello World页面
 pub async fn root_handler() -> Html<&'static str> {
@@ -16,23 +48,245 @@ pub async fn root_handler() -> Html<&'static str> {
 }
 
 /// API处理器 - 返回配置信息
-pub async fn api_config_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+pub async fn api_config_handler(State(state): State<AppState>) -> Json<Value> {
+    let app_data = state.app_data.lock().await;
     Json(json!({
-        "title": state.app_data.root.title,
+        "title": app_data.root.title,
+        "config": app_data.config,
+        "needs_save": app_data.needs_save,
         "message": "jkconfig Web API",
         "version": "0.1.1"
     }))
 }
 
 /// 健康检查处理器
-pub async fn health_check() -> Json<serde_json::Value> {
+pub async fn health_check() -> Json<Value> {
     Json(json!({
         "status": "ok",
         "timestamp": chrono::Utc::now().to_rfc3339()
     }))
 }
 
+/// 返回生成菜单所用的 JSON Schema
+pub async fn schema_handler(State(state): State<AppState>) -> Json<Value> {
+    Json(state.app_data.lock().await.schema.clone())
+}
+
+/// 返回当前的全部配置值
+pub async fn values_handler(State(state): State<AppState>) -> Json<Value> {
+    Json(state.app_data.lock().await.root.as_json())
+}
+
+/// 读取单个配置项，路径与配置文件中的键一致（如 `system.Cargo.features`）
+pub async fn get_value_handler(
+    State(state): State<AppState>,
+    Path(path): Path<String>,
+) -> ApiResult {
+    let path = path.replace('/', ".");
+    let app_data = state.app_data.lock().await;
+    let value = app_data
+        .get_value(&path)
+        .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, e))?;
+    Ok(Json(json!({ "path": path, "value": value })))
+}
+
+/// 修改单个配置项
+///
+/// 请求体为 JSON 值；字符串按命令行形式解析（与 `set` 子命令相同）。
+/// 不满足 Schema 约束或字段只读时返回 400，配置保持不变。
+pub async fn set_value_handler(
+    State(state): State<AppState>,
+    Path(path): Path<String>,
+    Json(body): Json<Value>,
+) -> ApiResult {
+    let path = path.replace('/', ".");
+    let raw = match body {
+        Value::String(s) => s,
+        other => other.to_string(),
+    };
+    let value = {
+        let mut app_data = state.app_data.lock().await;
+        if app_data.root.resolve_path(&path).is_err() {
+            return Err(ApiError::new(
+                StatusCode::NOT_FOUND,
+                anyhow::anyhow!("unknown config key `{path}`"),
+            ));
+        }
+        app_data
+            .set_value(&path, &raw)
+            .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?;
+        app_data
+            .get_value(&path)
+            .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e))?
+    };
+    state.notify(Change::Value {
+        path: path.clone(),
+        value: value.clone(),
+    });
+    Ok(Json(json!({ "path": path, "value": value })))
+}
+
+/// 校验全部配置，返回不满足约束的配置项
+pub async fn validate_handler(State(state): State<AppState>) -> Json<Value> {
+    let violations = state.app_data.lock().await.root.validate();
+    Json(json!({
+        "valid": violations.is_empty(),
+        "violations": violations
+            .iter()
+            .map(|v| json!({ "key": v.key, "message": v.message }))
+            .collect::<Vec<_>>(),
+    }))
+}
+
+/// 将配置写入文件
+pub async fn save_handler(State(state): State<AppState>) -> ApiResult {
+    {
+        let mut app_data = state.app_data.lock().await;
+        app_data
+            .save()
+            .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?;
+        app_data.mark_saved();
+    }
+    state.notify(Change::Saved);
+    Ok(Json(json!({ "saved": true })))
+}
+
+/// 列出已保存的预设
+pub async fn list_presets_handler(State(state): State<AppState>) -> ApiResult {
+    let presets = state
+        .app_data
+        .lock()
+        .await
+        .list_presets()
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(json!({ "presets": presets })))
+}
+
+/// 将当前配置保存为预设
+pub async fn save_preset_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> ApiResult {
+    let path = state
+        .app_data
+        .lock()
+        .await
+        .save_preset(&name)
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?;
+    Ok(Json(json!({ "name": name, "path": path })))
+}
+
+/// 应用预设，替换全部配置值
+pub async fn apply_preset_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> ApiResult {
+    let values = {
+        let mut app_data = state.app_data.lock().await;
+        app_data
+            .apply_preset(&name)
+            .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?;
+        app_data.root.as_json()
+    };
+    state.notify(Change::PresetApplied(name));
+    Ok(Json(values))
+}
+
+/// 配置变更推送（Server-Sent Events）
+///
+/// 事件类型：`change`（数据为 `{"path", "value"}`）、`saved`、`preset`，
+/// 以及订阅者落后时的 `lagged`；收到 `preset` 或 `lagged` 后应重新拉取
+/// `/api/values`。
+pub async fn events_handler(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let rx = state.changes.subscribe();
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        let event = match rx.recv().await {
+            Ok(change) => change_event(&change),
+            Err(RecvError::Lagged(n)) => Event::default().event("lagged").data(n.to_string()),
+            Err(RecvError::Closed) => return None,
+        };
+        Some((Ok(event), rx))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+fn change_event(change: &Change) -> Event {
+    match change {
+        Change::Value { path, value } => Event::default()
+            .event("change")
+            .data(json!({ "path": path, "value": value }).to_string()),
+        Change::Saved => Event::default().event("saved").data("{}"),
+        Change::PresetApplied(name) => Event::default()
+            .event("preset")
+            .data(json!({ "name": name }).to_string()),
+    }
+}
+
 /// 静态文件处理器
 pub async fn static_handler() -> Html<&'static str> {
     Html(include_str!("../../web/static/index.html"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::AppData;
+
+    fn state() -> AppState {
+        let schema = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "Test",
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"},
+                "level": {"type": "integer", "maximum": 8}
+            }
+        });
+        let app_data =
+            AppData::new_with_init_and_schema("", std::path::Path::new("a.json"), &schema).unwrap();
+        AppState::new(app_data)
+    }
+
+    #[tokio::test]
+    async fn test_set_and_get_value() {
+        let state = state();
+        let mut rx = state.changes.subscribe();
+
+        let Json(resp) =
+            set_value_handler(State(state.clone()), Path("level".into()), Json(json!(4)))
+                .await
+                .unwrap();
+        assert_eq!(resp["value"], 4);
+        assert_eq!(
+            rx.recv().await.unwrap(),
+            Change::Value {
+                path: "level".into(),
+                value: json!(4)
+            }
+        );
+
+        let Json(resp) = get_value_handler(State(state.clone()), Path("level".into()))
+            .await
+            .unwrap();
+        assert_eq!(resp["value"], 4);
+        let Json(values) = values_handler(State(state)).await;
+        assert_eq!(values, json!({"level": 4}));
+    }
+
+    #[tokio::test]
+    async fn test_set_value_errors() {
+        let state = state();
+        let err = set_value_handler(State(state.clone()), Path("level".into()), Json(json!(9)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        let err = set_value_handler(State(state.clone()), Path("nope".into()), Json(json!(1)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+        let Json(values) = values_handler(State(state)).await;
+        assert_eq!(values, json!({}));
+    }
+}
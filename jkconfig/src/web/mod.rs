@@ -2,6 +2,8 @@
 //!
 //! 提供基于axum的Web服务器功能，用于替代TUI界面提供配置编辑功能
 
+#[cfg(feature = "web")]
+pub mod auth;
 #[cfg(feature = "web")]
 pub mod handlers;
 #[cfg(feature = "web")]
@@ -11,4 +13,4 @@ pub mod server;
 
 // 重新导出server模块的run_server函数
 #[cfg(feature = "web")]
-pub use server::run_server;
+pub use server::{run_server, serve};
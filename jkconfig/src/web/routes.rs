@@ -2,11 +2,18 @@
 //!
 //! 定义和配置所有HTTP路由
 
-use axum::{Router, routing::get};
+use axum::{
+    Router,
+    routing::{get, post, put},
+};
 use tower_http::services::ServeDir;
 
 use super::{
-    handlers::{api_config_handler, health_check, root_handler, static_handler},
+    handlers::{
+        api_config_handler, apply_preset_handler, events_handler, get_value_handler, health_check,
+        list_presets_handler, root_handler, save_handler, save_preset_handler, schema_handler,
+        set_value_handler, static_handler, validate_handler, values_handler,
+    },
     server::AppState,
 };
 
@@ -18,6 +25,18 @@ pub fn create_routes(state: AppState) -> Router {
         // API路由
         .route("/api/config", get(api_config_handler))
         .route("/api/health", get(health_check))
+        .route("/api/schema", get(schema_handler))
+        .route("/api/values", get(values_handler))
+        .route(
+            "/api/values/{*path}",
+            get(get_value_handler).put(set_value_handler),
+        )
+        .route("/api/validate", get(validate_handler))
+        .route("/api/save", post(save_handler))
+        .route("/api/events", get(events_handler))
+        .route("/api/presets", get(list_presets_handler))
+        .route("/api/presets/{name}", put(save_preset_handler))
+        .route("/api/presets/{name}/apply", post(apply_preset_handler))
         // 静态文件服务
         .nest_service("/static", ServeDir::new("web/static"))
         // 备用路由 - 处理SPA路由
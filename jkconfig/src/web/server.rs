@@ -2,35 +2,141 @@
 //!
 //! 负责启动和配置axum Web服务器
 
-use std::net::SocketAddr;
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::Path,
+    sync::Arc,
+};
 
-use super::routes::create_routes;
-use crate::data::AppData;
+use axum::{Router, middleware};
+use serde_json::Value;
+use tokio::sync::{Mutex, broadcast};
 
-/// 运行Web服务器
+use super::{
+    auth::{Auth, cors_layer, require_token},
+    routes::create_routes,
+};
+use crate::{
+    data::AppData,
+    settings::{Settings, WebSettings},
+    t,
+};
+
+/// 变更通知通道容量，订阅者落后太多时会丢失旧事件
+const CHANGE_CHANNEL_CAPACITY: usize = 64;
+
+/// 运行Web服务器，认证、TLS 与跨域按用户设置的 `[web]` 配置
 pub async fn run_server(app_data: AppData, port: u16) -> anyhow::Result<()> {
-    // 创建应用状态
-    let state = AppState { app_data };
+    serve(app_data, port, &Settings::load().web).await
+}
+
+/// 按 `options` 运行Web服务器
+pub async fn serve(app_data: AppData, port: u16, options: &WebSettings) -> anyhow::Result<()> {
+    let tls = match (&options.tls_cert, &options.tls_key) {
+        (Some(cert), Some(key)) => Some((cert.as_path(), key.as_path())),
+        (None, None) => None,
+        _ => anyhow::bail!("TLS needs both `tls_cert` and `tls_key`"),
+    };
 
     // 创建路由
-    let app = create_routes(state);
+    let mut app = create_routes(AppState::new(app_data));
+    if let Some(token) = options.token.as_deref().filter(|t| !t.is_empty()) {
+        let auth = Auth::new(token, tls.is_some())?;
+        app = app.layer(middleware::from_fn_with_state(auth, require_token));
+    }
+    // CORS 在认证之外，预检请求不带令牌
+    if let Some(cors) = cors_layer(&options.cors_origins)? {
+        app = app.layer(cors);
+    }
 
     // 绑定地址
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let ip = options.bind.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    let addr = SocketAddr::new(ip, port);
 
-    println!("🚀 Web服务器启动成功！");
-    println!("📍 访问地址: http://localhost:{}", port);
-    println!("⏹️  按 Ctrl+C 停止服务器");
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    let host = match ip {
+        _ if ip.is_unspecified() => "localhost".to_string(),
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => format!("[{ip}]"),
+    };
+    let query = match &options.token {
+        Some(token) if !token.is_empty() => format!("/?token={token}"),
+        _ => String::new(),
+    };
+    println!("{}", t!("web.started"));
+    println!(
+        "{}",
+        t!(
+            "web.address",
+            url = format!("{scheme}://{host}:{port}{query}")
+        )
+    );
+    if options.token.as_deref().is_none_or(str::is_empty) && !ip.is_loopback() {
+        println!("{}", t!("web.no_token"));
+    }
+    println!("{}", t!("web.stop"));
 
     // 启动服务器
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    match tls {
+        Some((cert, key)) => serve_tls(app, addr, cert, key).await,
+        None => {
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            axum::serve(listener, app).await?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "web-tls")]
+async fn serve_tls(app: Router, addr: SocketAddr, cert: &Path, key: &Path) -> anyhow::Result<()> {
+    use axum_server::tls_rustls::RustlsConfig;
 
+    let config = RustlsConfig::from_pem_file(cert, key)
+        .await
+        .map_err(|e| anyhow::anyhow!("load {} / {}: {e}", cert.display(), key.display()))?;
+    axum_server::bind_rustls(addr, config)
+        .serve(app.into_make_service())
+        .await?;
     Ok(())
 }
 
+#[cfg(not(feature = "web-tls"))]
+async fn serve_tls(_: Router, _: SocketAddr, _: &Path, _: &Path) -> anyhow::Result<()> {
+    anyhow::bail!("{}", t!("web.tls_unsupported"))
+}
+
+/// 配置变更事件，通过 `/api/events` 推送
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// `path` 处的值被修改为 `value`
+    Value { path: String, value: Value },
+    /// 配置已写入文件
+    Saved,
+    /// 应用了预设，全部配置值已替换
+    PresetApplied(String),
+}
+
 /// 应用状态
+///
+/// 所有请求共享同一份 [`AppData`]，修改后通过 `changes` 广播给订阅者。
 #[derive(Clone)]
 pub struct AppState {
-    pub app_data: AppData,
+    pub app_data: Arc<Mutex<AppData>>,
+    pub changes: broadcast::Sender<Change>,
+}
+
+impl AppState {
+    /// 用已加载的配置数据创建状态
+    pub fn new(app_data: AppData) -> Self {
+        let (changes, _) = broadcast::channel(CHANGE_CHANNEL_CAPACITY);
+        Self {
+            app_data: Arc::new(Mutex::new(app_data)),
+            changes,
+        }
+    }
+
+    /// 广播一次变更，没有订阅者时忽略
+    pub fn notify(&self, change: Change) {
+        let _ = self.changes.send(change);
+    }
 }
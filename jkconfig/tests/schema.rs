@@ -436,3 +436,264 @@ mod menu_root_get_mut_by_key_tests {
         }
     }
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+struct Limits {
+    #[schemars(range(min = 1, max = 10))]
+    level: u32,
+    #[schemars(length(max = 4), regex(pattern = r"^[a-z]+$"))]
+    name: String,
+    #[schemars(length(max = 2))]
+    tags: Vec<String>,
+}
+
+#[test]
+fn test_validate_constraints() {
+    let schema = schema_for!(Limits);
+    let mut menu = MenuRoot::try_from(schema.as_value()).unwrap();
+
+    menu.update_by_value(&serde_json::json!({"level": 5, "name": "abc", "tags": ["a"]}))
+        .unwrap();
+    assert!(menu.validate().is_empty());
+
+    // Out-of-range values still load, but are reported
+    menu.update_by_value(
+        &serde_json::json!({"level": 11, "name": "ABCDE", "tags": ["a", "b", "c"]}),
+    )
+    .unwrap();
+    let keys: Vec<_> = menu.validate().into_iter().map(|v| v.key).collect();
+    assert_eq!(keys, ["level", "name", "tags"]);
+}
+
+#[test]
+fn test_yaml_config_roundtrip() {
+    use jkconfig::data::{AppData, format::ConfigFormat};
+    use std::path::Path;
+
+    let schema = schema_for!(Limits);
+    let yaml = "# board limits\nlevel: 3\nname: abc\ntags:\n  - x\n  - y\n";
+    let app = AppData::new_with_init_and_schema(yaml, Path::new("limits.yml"), schema.as_value())
+        .unwrap();
+
+    let value = app.root.as_json();
+    assert_eq!(
+        value,
+        serde_json::json!({"level": 3, "name": "abc", "tags": ["x", "y"]})
+    );
+
+    // Keys are written in schema order
+    let out = ConfigFormat::Yaml.to_string(&value).unwrap();
+    assert_eq!(out, "level: 3\nname: abc\ntags:\n  - x\n  - y\n");
+    let typed: Limits = ConfigFormat::Yaml.parse_typed(&out).unwrap();
+    assert_eq!(typed.tags, ["x", "y"]);
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+struct Common {
+    name: String,
+    debug: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(untagged)]
+enum Source {
+    Path { path: String },
+    Url { url: String, sha256: Option<String> },
+    Plain(u32),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+enum Mode {
+    A { x: u32 },
+    B { y: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+struct Composed {
+    #[serde(flatten)]
+    common: Common,
+    source: Source,
+    #[serde(flatten)]
+    mode: Mode,
+    opt: Option<Common>,
+}
+
+fn roundtrip_composed(origin: &Composed) -> MenuRoot {
+    let schema = schema_for!(Composed);
+    let mut menu = MenuRoot::try_from(schema.as_value()).unwrap();
+    menu.update_by_value(&serde_json::to_value(origin).unwrap())
+        .unwrap();
+    let actual: Composed = serde_json::from_value(menu.as_json()).unwrap();
+    assert_eq!(&actual, origin);
+    menu
+}
+
+#[test]
+fn test_flatten_and_untagged_roundtrip() {
+    let menu = roundtrip_composed(&Composed {
+        common: Common {
+            name: "k".into(),
+            debug: true,
+        },
+        source: Source::Url {
+            url: "http://x".into(),
+            sha256: None,
+        },
+        mode: Mode::B { y: "b".into() },
+        opt: None,
+    });
+    // Flattened struct fields become direct children
+    assert!(menu.get_by_key("name").is_some());
+    assert!(menu.get_by_key("debug").is_some());
+
+    roundtrip_composed(&Composed {
+        common: Common {
+            name: "k".into(),
+            debug: false,
+        },
+        source: Source::Plain(7),
+        mode: Mode::A { x: 1 },
+        opt: Some(Common {
+            name: "o".into(),
+            debug: true,
+        }),
+    });
+    roundtrip_composed(&Composed {
+        common: Common {
+            name: "k".into(),
+            debug: false,
+        },
+        source: Source::Path { path: "/a".into() },
+        mode: Mode::A { x: 2 },
+        opt: None,
+    });
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+struct TwoFlattened {
+    #[serde(flatten)]
+    mode: Mode,
+    #[serde(flatten)]
+    source: Source,
+}
+
+#[test]
+fn test_all_of_flattened_enums() {
+    let schema = schema_for!(TwoFlattened);
+    let mut menu = MenuRoot::try_from(schema.as_value()).unwrap();
+    let origin = TwoFlattened {
+        mode: Mode::A { x: 3 },
+        source: Source::Path { path: "/p".into() },
+    };
+    menu.update_by_value(&serde_json::to_value(&origin).unwrap())
+        .unwrap();
+    let actual: TwoFlattened = serde_json::from_value(menu.as_json()).unwrap();
+    assert_eq!(actual, origin);
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+struct Addresses {
+    #[schemars(extend("x-display" = "hex"))]
+    load_addr: u64,
+    #[schemars(extend("x-display" = "size"))]
+    ram_size: u64,
+}
+
+#[test]
+fn test_hex_and_size_integers_roundtrip() {
+    use jkconfig::data::{AppData, item::ItemType, types::ElementType};
+    use std::path::Path;
+
+    let schema = schema_for!(Addresses);
+    let mut app = AppData::new_with_init_and_schema(
+        // TOML integers are i64, so values above i64::MAX need JSON/YAML
+        r#"{"load_addr": 18446462600880324608, "ram_size": 536870912}"#,
+        Path::new("a.json"),
+        schema.as_value(),
+    )
+    .unwrap();
+
+    let Some(ElementType::Item(item)) = app.root.get_by_key("load_addr") else {
+        panic!("load_addr is not an item");
+    };
+    let ItemType::Integer { value, display, .. } = &item.item_type else {
+        panic!("load_addr is not an integer");
+    };
+    assert_eq!(display.format(value.unwrap()), "0xffff000080000000");
+
+    app.set_value("ram_size", "1G").unwrap();
+    let typed: Addresses = serde_json::from_value(app.root.as_json()).unwrap();
+    assert_eq!(
+        typed,
+        Addresses {
+            load_addr: 0xffff_0000_8000_0000,
+            ram_size: 1 << 30,
+        }
+    );
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+struct Versioned {
+    #[schemars(extend("const" = 2))]
+    version: u32,
+    #[schemars(extend("readOnly" = true))]
+    generated_by: Option<String>,
+    name: String,
+}
+
+#[test]
+fn test_const_and_read_only_fields() {
+    use jkconfig::data::AppData;
+    use std::path::Path;
+
+    let schema = schema_for!(Versioned);
+    // An outdated version in the file is replaced by the const
+    let mut app = AppData::new_with_init_and_schema(
+        "version = 1\ngenerated_by = \"ostool\"\nname = \"a\"\n",
+        Path::new("a.toml"),
+        schema.as_value(),
+    )
+    .unwrap();
+
+    assert!(app.set_value("version", "3").is_err());
+    assert!(app.set_value("generated_by", "me").is_err());
+    app.set_value("name", "b").unwrap();
+
+    let typed: Versioned = serde_json::from_value(app.root.as_json()).unwrap();
+    assert_eq!(
+        typed,
+        Versioned {
+            version: 2,
+            generated_by: Some("ostool".into()),
+            name: "b".into(),
+        }
+    );
+
+    // A fresh tree emits the const without it ever being set
+    let menu = MenuRoot::try_from(schema.as_value()).unwrap();
+    assert_eq!(menu.as_json()["version"], 2);
+}
+
+/// Unit variants with doc comments become `oneOf` of `const` strings.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+enum Level {
+    /// Most verbose
+    Trace,
+    /// Default
+    Info,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+struct WithLevel {
+    level: Option<Level>,
+}
+
+#[test]
+fn test_documented_enum_value() {
+    let schema = schema_for!(WithLevel);
+    let mut menu = MenuRoot::try_from(schema.as_value()).unwrap();
+    menu.update_by_value(&serde_json::json!({ "level": "Info" }))
+        .unwrap();
+    let typed: WithLevel = serde_json::from_value(menu.as_json()).unwrap();
+    assert_eq!(typed.level, Some(Level::Info));
+}
@@ -0,0 +1,484 @@
+//! Boot-time benchmarking, `ostool bench boot`.
+//!
+//! The kernel is booted several times, in QEMU or on the board of
+//! `.uboot.toml`, and every boot is timestamped at the milestones of
+//! `.bench.toml`, regexes matched against the console output in order:
+//!
+//! ```toml
+//! runs = 10
+//! timeout = 60
+//!
+//! [[milestones]]
+//! name = "uboot"
+//! regex = "^U-Boot \\d{4}"
+//!
+//! [[milestones]]
+//! name = "kernel"
+//! regex = "Starting kernel"
+//!
+//! [[milestones]]
+//! name = "init"
+//! regex = "init process started"
+//! ```
+//!
+//! A phase is named after the milestone ending it and lasts from the
+//! previous milestone, or from the start of the boot. Min, median and max
+//! are printed per phase and for the whole boot. The medians can be saved as
+//! a baseline; later runs compare against it and fail on regressions.
+//!
+//! On hardware a boot starts with `board_reset_cmd` and only the serial
+//! console is watched: the board boots whatever its own `bootcmd` loads.
+
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use clap::ValueEnum;
+use colored::Colorize;
+use jkconfig::t;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ctx::AppContext,
+    exit::Failure,
+    run::{
+        qemu::{BootEnd, Watch, boot_qemu, load_qemu_config},
+        uboot::load_uboot_config,
+    },
+};
+
+const DEFAULT_RUNS: usize = 5;
+const DEFAULT_TIMEOUT: u64 = 60;
+const DEFAULT_THRESHOLD: f64 = 10.0;
+const TOTAL: &str = "total";
+
+/// Boot benchmark settings, `.bench.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct BenchConfig {
+    /// Number of boots, 5 when unset.
+    pub runs: Option<usize>,
+    /// Seconds a single boot may take to reach the last milestone, 60 when unset.
+    pub timeout: Option<u64>,
+    /// Milestones in the order they appear on the console.
+    #[serde(default)]
+    pub milestones: Vec<Milestone>,
+}
+
+/// A point of the boot recognised on the console.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct Milestone {
+    pub name: String,
+    /// Regex matched against each console line.
+    pub regex: String,
+}
+
+/// Where to boot.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BenchTarget {
+    #[default]
+    Qemu,
+    Uboot,
+}
+
+impl BenchTarget {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Qemu => "qemu",
+            Self::Uboot => "uboot",
+        }
+    }
+}
+
+/// Arguments for benchmarking the boot.
+#[derive(Debug, Clone, Default)]
+pub struct BenchBootArgs {
+    pub target: BenchTarget,
+    /// Optional path to the build configuration file, used for QEMU.
+    pub build_config: Option<PathBuf>,
+    /// Optional path to the QEMU or U-Boot configuration file.
+    pub runner_config: Option<PathBuf>,
+    /// Optional path to the benchmark configuration file.
+    pub bench_config: Option<PathBuf>,
+    /// Number of boots, overriding the config.
+    pub runs: Option<usize>,
+    /// Baseline file, `.ostool/bench/boot-<target>.json` when unset.
+    pub baseline: Option<PathBuf>,
+    /// Write this run's results as the new baseline.
+    pub save_baseline: bool,
+    /// Median increase in percent that counts as a regression, 10 when unset.
+    pub threshold: Option<f64>,
+}
+
+/// Min, median and max of one phase, in seconds.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PhaseStats {
+    pub name: String,
+    pub min: f64,
+    pub median: f64,
+    pub max: f64,
+}
+
+/// Stored results of a benchmark.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Baseline {
+    pub runs: usize,
+    pub phases: Vec<PhaseStats>,
+}
+
+/// Timestamps the milestones of one boot.
+struct Timeline<'a> {
+    milestones: &'a [(String, Regex)],
+    last: Instant,
+    phases: Vec<Duration>,
+}
+
+impl<'a> Timeline<'a> {
+    fn new(milestones: &'a [(String, Regex)], start: Instant) -> Self {
+        Self {
+            milestones,
+            last: start,
+            phases: Vec::new(),
+        }
+    }
+
+    /// Feeds a line seen at `now`; returns whether the last milestone was reached.
+    fn feed(&mut self, line: &str, now: Instant) -> bool {
+        if let Some((name, regex)) = self.milestones.get(self.phases.len())
+            && regex.is_match(line)
+        {
+            let phase = now - self.last;
+            println!("  {name}: +{:.3}s", phase.as_secs_f64());
+            self.phases.push(phase);
+            self.last = now;
+        }
+        self.done()
+    }
+
+    fn done(&self) -> bool {
+        self.phases.len() == self.milestones.len()
+    }
+}
+
+/// Boots the kernel `runs` times and reports the time per phase.
+///
+/// # Errors
+///
+/// Returns an error if the configuration is missing or invalid, the kernel
+/// cannot be built or booted, no boot reached the last milestone, or a
+/// phase regressed against the baseline.
+pub async fn bench_boot(mut ctx: AppContext, args: BenchBootArgs) -> anyhow::Result<()> {
+    let bench_path = args
+        .bench_config
+        .clone()
+        .unwrap_or_else(|| ctx.paths.config_file(".bench.toml"));
+    let config = load_bench_config(&ctx, &bench_path)?;
+    let milestones = config
+        .milestones
+        .iter()
+        .map(|m| {
+            Regex::new(&m.regex)
+                .map(|r| (m.name.clone(), r))
+                .map_err(|e| anyhow!("milestone `{}` regex error: {e}", m.name))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let runs = args.runs.or(config.runs).unwrap_or(DEFAULT_RUNS).max(1);
+    let timeout = Duration::from_secs(config.timeout.unwrap_or(DEFAULT_TIMEOUT));
+
+    let mut samples = Vec::new();
+    match args.target {
+        BenchTarget::Qemu => {
+            ctx.build_for_run(args.build_config.clone()).await?;
+            let qemu = load_qemu_config(&ctx, args.runner_config.clone()).await?;
+            for run in 1..=runs {
+                println!("{}", t!("bench.run", run = run, runs = runs).bold());
+                let start = Instant::now();
+                let mut timeline = Timeline::new(&milestones, start);
+                let end = boot_qemu(ctx.clone(), qemu.clone(), None, timeout, |line| {
+                    if timeline.feed(line, Instant::now()) {
+                        Watch::Stop
+                    } else {
+                        Watch::Continue
+                    }
+                })
+                .await?;
+                match end {
+                    BootEnd::Stopped => samples.push(timeline.phases),
+                    BootEnd::TimedOut => incomplete(&timeline, t!("bench.timed_out")),
+                    BootEnd::Failed { line, .. } | BootEnd::Crashed { line, .. } => {
+                        incomplete(&timeline, &line)
+                    }
+                    BootEnd::Exited(status) => {
+                        incomplete(&timeline, &t!("test.exited", status = status))
+                    }
+                }
+            }
+        }
+        BenchTarget::Uboot => {
+            let uboot = load_uboot_config(&ctx, args.runner_config.clone()).await?;
+            if !uboot.can_reset() {
+                bail!(t!("bench.no_reset_cmd"));
+            }
+            for run in 1..=runs {
+                println!("{}", t!("bench.run", run = run, runs = runs).bold());
+                let (mut rx, _tx) = uboot.open_console()?;
+                uboot.reset_board(&ctx)?;
+                let start = Instant::now();
+                let mut timeline = Timeline::new(&milestones, start);
+                watch_serial(&mut rx, &mut timeline, start + timeout)?;
+                if timeline.done() {
+                    samples.push(timeline.phases);
+                } else {
+                    incomplete(&timeline, t!("bench.timed_out"));
+                }
+            }
+            uboot.power_off_board(&ctx)?;
+        }
+    }
+
+    if samples.is_empty() {
+        return Err(Failure::Boot.error(t!("bench.no_complete_run")));
+    }
+    let names = milestones.iter().map(|(name, _)| name.as_str());
+    let current = Baseline {
+        runs: samples.len(),
+        phases: summarize(names, &samples),
+    };
+
+    let baseline_path = args.baseline.clone().unwrap_or_else(|| {
+        ctx.paths
+            .workspace
+            .join(".ostool/bench")
+            .join(format!("boot-{}.json", args.target.name()))
+    });
+    let baseline = if !args.save_baseline && baseline_path.exists() {
+        let content = fs::read_to_string(&baseline_path)
+            .with_context(|| format!("Failed to read {}", baseline_path.display()))?;
+        Some(serde_json::from_str::<Baseline>(&content)?)
+    } else {
+        None
+    };
+    let threshold = args.threshold.unwrap_or(DEFAULT_THRESHOLD);
+    print_report(&current, baseline.as_ref(), runs, threshold);
+
+    if args.save_baseline {
+        save_baseline(&baseline_path, &current)?;
+        println!(
+            "{}",
+            t!("bench.baseline_saved", path = baseline_path.display())
+        );
+    }
+    if let Some(baseline) = &baseline {
+        let regressed = regressions(&current, baseline, threshold);
+        if !regressed.is_empty() {
+            return Err(Failure::Test.error(t!("bench.regressed", phases = regressed.join(", "))));
+        }
+    }
+    Ok(())
+}
+
+fn load_bench_config(ctx: &AppContext, path: &Path) -> anyhow::Result<BenchConfig> {
+    if !path.exists() {
+        bail!(t!("bench.no_milestones", path = path.display()));
+    }
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let config: BenchConfig = ctx.parse_config(path, &content)?;
+    if config.milestones.is_empty() {
+        bail!(t!("bench.no_milestones", path = path.display()));
+    }
+    Ok(config)
+}
+
+/// Feeds the serial console to `timeline` until the last milestone or `deadline`.
+fn watch_serial(
+    rx: &mut Box<dyn Read + Send>,
+    timeline: &mut Timeline<'_>,
+    deadline: Instant,
+) -> anyhow::Result<()> {
+    let mut line = Vec::new();
+    let mut buf = [0u8; 256];
+    while Instant::now() < deadline {
+        let n = match rx.read(&mut buf) {
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let now = Instant::now();
+        for &byte in &buf[..n] {
+            if byte != b'\n' {
+                line.push(byte);
+                continue;
+            }
+            let text = String::from_utf8_lossy(&line);
+            if timeline.feed(text.trim_end(), now) {
+                return Ok(());
+            }
+            line.clear();
+        }
+    }
+    Ok(())
+}
+
+fn incomplete(timeline: &Timeline<'_>, reason: &str) {
+    let missing = &timeline.milestones[timeline.phases.len()].0;
+    println!(
+        "{}",
+        t!("bench.incomplete", milestone = missing, reason = reason).yellow()
+    );
+}
+
+/// Computes the stats of every phase and of the whole boot.
+fn summarize<'a>(
+    names: impl Iterator<Item = &'a str>,
+    samples: &[Vec<Duration>],
+) -> Vec<PhaseStats> {
+    let mut phases = names
+        .enumerate()
+        .map(|(i, name)| stats(name, samples.iter().map(|s| s[i])))
+        .collect::<Vec<_>>();
+    phases.push(stats(TOTAL, samples.iter().map(|s| s.iter().sum())));
+    phases
+}
+
+fn stats(name: &str, samples: impl Iterator<Item = Duration>) -> PhaseStats {
+    let mut secs = samples.map(|d| d.as_secs_f64()).collect::<Vec<_>>();
+    secs.sort_by(f64::total_cmp);
+    let mid = secs.len() / 2;
+    let median = if secs.len() % 2 == 0 {
+        (secs[mid - 1] + secs[mid]) / 2.0
+    } else {
+        secs[mid]
+    };
+    PhaseStats {
+        name: name.to_string(),
+        min: secs[0],
+        median,
+        max: secs[secs.len() - 1],
+    }
+}
+
+/// Median change of `current` against `baseline` in percent.
+fn change(current: &PhaseStats, baseline: &Baseline) -> Option<f64> {
+    let base = baseline.phases.iter().find(|p| p.name == current.name)?;
+    (base.median > 0.0).then(|| (current.median - base.median) / base.median * 100.0)
+}
+
+/// Names of the phases whose median grew by more than `threshold` percent.
+fn regressions(current: &Baseline, baseline: &Baseline, threshold: f64) -> Vec<String> {
+    current
+        .phases
+        .iter()
+        .filter(|p| change(p, baseline).is_some_and(|c| c > threshold))
+        .map(|p| p.name.clone())
+        .collect()
+}
+
+fn print_report(current: &Baseline, baseline: Option<&Baseline>, runs: usize, threshold: f64) {
+    println!(
+        "\n{}",
+        t!("bench.summary", completed = current.runs, runs = runs).bold()
+    );
+    println!(
+        "{:<16} {:>10} {:>10} {:>10} {:>10} {:>9}",
+        "phase", "min", "median", "max", "baseline", "change"
+    );
+    for phase in &current.phases {
+        let base = baseline.and_then(|b| b.phases.iter().find(|p| p.name == phase.name));
+        let (base, change) = match (base, baseline.and_then(|b| change(phase, b))) {
+            (Some(base), Some(c)) => {
+                let text = format!("{c:+.1}%");
+                let text = if c > threshold {
+                    text.red()
+                } else if c < -threshold {
+                    text.green()
+                } else {
+                    text.normal()
+                };
+                (format!("{:.3}s", base.median), text)
+            }
+            _ => ("-".to_string(), "-".normal()),
+        };
+        println!(
+            "{:<16} {:>9.3}s {:>9.3}s {:>9.3}s {:>10} {:>9}",
+            phase.name, phase.min, phase.median, phase.max, base, change
+        );
+    }
+}
+
+fn save_baseline(path: &Path, baseline: &Baseline) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(baseline)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeline() {
+        let milestones = [
+            ("uboot".to_string(), Regex::new("^U-Boot").unwrap()),
+            ("kernel".to_string(), Regex::new("Starting kernel").unwrap()),
+        ];
+        let start = Instant::now();
+        let mut timeline = Timeline::new(&milestones, start);
+        // 顺序匹配，先出现的后续里程碑不计入
+        assert!(!timeline.feed("Starting kernel", start + Duration::from_secs(1)));
+        assert!(!timeline.feed("U-Boot 2024.01", start + Duration::from_secs(2)));
+        assert!(timeline.feed("## Starting kernel ...", start + Duration::from_secs(5)));
+        assert_eq!(
+            timeline.phases,
+            [Duration::from_secs(2), Duration::from_secs(3)]
+        );
+    }
+
+    #[test]
+    fn test_summarize_and_regressions() {
+        let ms = Duration::from_millis;
+        let samples = [
+            vec![ms(1000), ms(3000)],
+            vec![ms(1400), ms(2000)],
+            vec![ms(1200), ms(2600)],
+            vec![ms(1100), ms(2500)],
+        ];
+        let current = Baseline {
+            runs: samples.len(),
+            phases: summarize(["uboot", "kernel"].into_iter(), &samples),
+        };
+        let uboot = &current.phases[0];
+        assert_eq!((uboot.min, uboot.max), (1.0, 1.4));
+        assert!((uboot.median - 1.15).abs() < 1e-9);
+        let total = &current.phases[2];
+        assert_eq!(total.name, TOTAL);
+        assert!((total.median - 3.7).abs() < 1e-9);
+
+        let baseline = Baseline {
+            runs: 4,
+            phases: vec![
+                PhaseStats {
+                    name: "uboot".into(),
+                    min: 1.0,
+                    median: 1.0,
+                    max: 1.0,
+                },
+                PhaseStats {
+                    name: "kernel".into(),
+                    min: 2.5,
+                    median: 2.6,
+                    max: 3.0,
+                },
+            ],
+        };
+        assert_eq!(regressions(&current, &baseline, 10.0), ["uboot"]);
+        assert!(regressions(&current, &baseline, 20.0).is_empty());
+    }
+}
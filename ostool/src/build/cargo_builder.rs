@@ -6,12 +6,22 @@
 
 use std::{
     collections::HashMap,
+    io::{BufRead, BufReader},
     path::{Path, PathBuf},
+    process::Stdio,
 };
 
 use colored::Colorize;
-
-use crate::{build::config::Cargo, ctx::AppContext, utils::Command};
+use tokio::fs;
+
+use crate::{
+    build::{config::Cargo, runner},
+    cache,
+    ctx::{AppContext, OFFLINE_ENV},
+    exit::{self, Failure},
+    logging,
+    utils::Command,
+};
 
 /// A builder for constructing and executing Cargo commands.
 ///
@@ -77,6 +87,29 @@ impl<'a> CargoBuilder<'a> {
         }
     }
 
+    /// Creates a new `CargoBuilder` for building the test binaries with
+    /// `cargo test --no-run`; see [`Self::execute_test`].
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The application context.
+    /// * `config` - The Cargo build configuration.
+    /// * `config_path` - Optional path to the configuration file.
+    pub fn test(ctx: &'a mut AppContext, config: &'a Cargo, config_path: Option<PathBuf>) -> Self {
+        Self {
+            ctx,
+            config,
+            command: "test".to_string(),
+            extra_args: vec![
+                "--no-run".to_string(),
+                "--message-format=json-render-diagnostics".to_string(),
+            ],
+            extra_envs: HashMap::new(),
+            skip_objcopy: true,
+            config_path,
+        }
+    }
+
     /// Returns `true` if this builder is configured for `cargo run`.
     pub fn is_run(&self) -> bool {
         self.command == "run"
@@ -154,6 +187,31 @@ impl<'a> CargoBuilder<'a> {
         Ok(())
     }
 
+    /// Executes the configured `cargo test --no-run` command.
+    ///
+    /// Runs the pre- and post-build commands like [`Self::execute`] and
+    /// returns the name and path of every test binary built.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any step of the build process fails.
+    pub async fn execute_test(mut self) -> anyhow::Result<Vec<(String, PathBuf)>> {
+        self.run_pre_build_cmds()?;
+
+        let mut cmd = self.build_cargo_command().await?;
+        cmd.stdout(Stdio::piped());
+        cmd.print_cmd();
+        let mut child = cmd.spawn()?;
+        let binaries = test_binaries(BufReader::new(child.stdout.take().unwrap()));
+        let status = child.wait()?;
+        if !status.success() {
+            anyhow::bail!("failed with status: {status}");
+        }
+
+        self.run_post_build_cmds()?;
+        Ok(binaries)
+    }
+
     fn run_pre_build_cmds(&mut self) -> anyhow::Result<()> {
         for cmd in &self.config.pre_build_cmds {
             self.ctx.shell_run_cmd(cmd)?;
@@ -163,8 +221,28 @@ impl<'a> CargoBuilder<'a> {
 
     async fn run_cargo(&mut self) -> anyhow::Result<()> {
         let mut cmd = self.build_cargo_command().await?;
-        cmd.run()?;
-        Ok(())
+        if !self.is_run() {
+            return cmd.run();
+        }
+
+        cmd.print_cmd();
+        // Ctrl+C 交给 cargo 启动的 cargo-osrun，由它清理后以 130 退出
+        let status = {
+            let _pass = exit::pass_through();
+            cmd.status()?
+        };
+        if status.success() {
+            return Ok(());
+        }
+        // cargo run 以 runner 的退出码退出，编译失败时为 101
+        match Failure::from_status(status) {
+            Some(failure) => Err(failure.into()),
+            None if exit::interrupted() => Err(Failure::Aborted.into()),
+            None if status.code() == Some(101) => {
+                Err(anyhow!("failed with status: {status}").context(Failure::Build))
+            }
+            None => bail!("failed with status: {status}"),
+        }
     }
 
     async fn build_cargo_command(&mut self) -> anyhow::Result<Command> {
@@ -172,21 +250,37 @@ impl<'a> CargoBuilder<'a> {
 
         cmd.arg(&self.command);
 
-        for (k, v) in &self.config.env {
-            println!("{}", format!("{k}={v}").cyan());
+        let show = log_enabled!(log::Level::Info);
+        for (k, v) in self.config.env.iter().chain(&self.extra_envs) {
+            if show {
+                println!("{}", format!("{k}={v}").cyan());
+            }
             cmd.env(k, v);
         }
-        for (k, v) in &self.extra_envs {
-            println!("{}", format!("{k}={v}").cyan());
+        for (k, v) in logging::child_env() {
             cmd.env(k, v);
         }
+        if self.ctx.offline {
+            // cargo 本身和经由 cargo 启动的 cargo-osrun 都不联网
+            cmd.env("CARGO_NET_OFFLINE", "true");
+            cmd.env(OFFLINE_ENV, "1");
+        }
 
         // Extra config
-        if let Some(extra_config_path) = self.cargo_extra_config().await? {
+        let extra_config = self.cargo_extra_config().await?;
+        if let Some(extra_config_path) = &extra_config {
             cmd.arg("--config");
             cmd.arg(extra_config_path.display().to_string());
         }
 
+        // cargo run 交给 cargo-osrun
+        if self.is_run()
+            && let Some(runner_config) = self.runner_config(extra_config.as_deref()).await?
+        {
+            cmd.arg("--config");
+            cmd.arg(runner_config.display().to_string());
+        }
+
         // Package and target
         cmd.arg("-p");
         cmd.arg(&self.config.package);
@@ -222,10 +316,6 @@ impl<'a> CargoBuilder<'a> {
             cmd.arg(arg);
         }
 
-        if self.is_run() && self.ctx.debug {
-            cmd.arg("--debug");
-        }
-
         Ok(cmd)
     }
 
@@ -243,6 +333,18 @@ impl<'a> CargoBuilder<'a> {
             self.ctx.objcopy_output_bin()?;
         }
 
+        if let Some(uimage) = &self.config.uimage
+            && !self.skip_objcopy
+        {
+            self.ctx.output_uimage(uimage)?;
+        }
+
+        if let Some(android_boot) = &self.config.android_boot
+            && !self.skip_objcopy
+        {
+            self.ctx.output_android_boot(android_boot)?;
+        }
+
         Ok(())
     }
 
@@ -294,14 +396,18 @@ impl<'a> CargoBuilder<'a> {
             // Convert GitHub URL to raw content URL if needed
             let download_url = Self::convert_to_raw_url(s);
 
-            // Download to temp directory
-            match self.download_config_to_temp(&download_url).await {
-                Ok(path) => Ok(Some(path)),
-                Err(e) => {
-                    eprintln!("Failed to download config from {}: {}", s, e);
-                    Err(e)
-                }
+            // Download to the user cache, or use the cached copy offline
+            if !self.ctx.offline {
+                info!("Downloading cargo config from: {}", download_url);
             }
+            let path = cache::fetch(
+                cache::CARGO_CONFIG,
+                &download_url,
+                self.config.extra_config_sha256.as_deref(),
+                self.ctx.offline,
+            )
+            .await?;
+            Ok(Some(path))
         } else {
             // It's a local path
             let extra = Path::new(s);
@@ -322,6 +428,25 @@ impl<'a> CargoBuilder<'a> {
         }
     }
 
+    /// Writes the cargo config making `cargo-osrun` the runner of the
+    /// target, `None` if `extra_config` already sets a runner.
+    async fn runner_config(&self, extra_config: Option<&Path>) -> anyhow::Result<Option<PathBuf>> {
+        let target = &self.config.target;
+        if let Some(extra) = extra_config
+            && let Ok(content) = fs::read_to_string(extra).await
+            && runner::sets_runner(&content, target)
+        {
+            debug!("Runner of {target} set by {}", extra.display());
+            return Ok(None);
+        }
+        let dir = self.ctx.paths.build_dir().join("ostool");
+        fs::create_dir_all(&dir).await?;
+        let path = dir.join("runner.toml");
+        let content = runner::cargo_config(target, &runner::runner_command())?;
+        fs::write(&path, content).await?;
+        Ok(Some(path))
+    }
+
     /// Convert GitHub URL to raw content URL
     /// Supports:
     /// - https://github.com/user/repo/blob/branch/path/file -> https://raw.githubusercontent.com/user/repo/branch/path/file
@@ -338,69 +463,45 @@ impl<'a> CargoBuilder<'a> {
             let converted = url
                 .replace("github.com", "raw.githubusercontent.com")
                 .replace("/blob/", "/");
-            println!("Converting GitHub URL to raw: {} -> {}", url, converted);
+            debug!("Converting GitHub URL to raw: {} -> {}", url, converted);
             return converted;
         }
 
         // Not a GitHub URL or already in correct format
         url.to_string()
     }
+}
 
-    async fn download_config_to_temp(&self, url: &str) -> anyhow::Result<PathBuf> {
-        use std::time::SystemTime;
-
-        println!("Downloading cargo config from: {}", url);
-
-        // Get system temp directory
-        let temp_dir = std::env::temp_dir();
-
-        // Generate filename with timestamp
-        let timestamp = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-
-        // Extract filename from URL or use default
-        let url_path = url.split('/').next_back().unwrap_or("config.toml");
-        let filename = format!("cargo_config_{}_{}", timestamp, url_path);
-        let target_path = temp_dir.join(filename);
-
-        // Create reqwest client
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .build()
-            .map_err(|e| anyhow::anyhow!("Failed to create HTTP client: {}", e))?;
-
-        // Build request with User-Agent for GitHub
-        let mut request = client.get(url);
-
-        if url.contains("github.com") || url.contains("githubusercontent.com") {
-            // GitHub requires User-Agent
-            request = request.header("User-Agent", "ostool-cargo-downloader");
-        }
-
-        // Download the file
-        let response = request
-            .send()
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to download from {}: {}", url, e))?;
-
-        if !response.status().is_success() {
-            return Err(anyhow::anyhow!("HTTP error {}: {}", response.status(), url));
-        }
-
-        let content = response
-            .bytes()
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to read response body: {}", e))?;
-
-        // Write to temp file
-        tokio::fs::write(&target_path, content)
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to write to temp file: {}", e))?;
-
-        println!("Config downloaded to: {}", target_path.display());
+/// Test binaries in the `--message-format=json` output of `cargo test
+/// --no-run`, as target name and path.
+fn test_binaries(output: impl BufRead) -> Vec<(String, PathBuf)> {
+    output
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| {
+            let msg: serde_json::Value = serde_json::from_str(&line).ok()?;
+            if msg["reason"] != "compiler-artifact" || msg["profile"]["test"] != true {
+                return None;
+            }
+            let name = msg["target"]["name"].as_str()?;
+            let path = msg["executable"].as_str()?;
+            Some((name.to_string(), PathBuf::from(path)))
+        })
+        .collect()
+}
 
-        Ok(target_path)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_test_binaries() {
+        let output = r#"{"reason":"compiler-artifact","target":{"name":"log"},"profile":{"test":false},"executable":null}
+{"reason":"compiler-artifact","target":{"name":"kernel"},"profile":{"test":true},"executable":"/t/deps/kernel-1f2e"}
+{"reason":"build-finished","success":true}"#;
+        assert_eq!(
+            test_binaries(output.as_bytes()),
+            vec![("kernel".to_string(), PathBuf::from("/t/deps/kernel-1f2e"))]
+        );
     }
 }
@@ -14,19 +14,31 @@
 //! to_bin = true
 //! ```
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::{run::rules::ConsoleRule, task::TaskStep};
+
 /// Root build configuration structure.
 ///
 /// This is the top-level configuration that specifies which build system
 /// to use (Cargo or custom shell commands).
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub struct BuildConfig {
+    /// Layout version of the file, see [`migrate`](crate::migrate).
+    pub version: Option<u32>,
     /// The build system configuration.
     pub system: BuildSystem,
+    /// Rules judging the console output of every runner, checked before
+    /// the runner's own `fail_regex` and `success_regex`.
+    #[serde(default)]
+    pub console_rules: Vec<ConsoleRule>,
+    /// Named sequences of ostool subcommands, shell commands and other
+    /// tasks, run with `ostool task <name>`.
+    #[serde(default)]
+    pub tasks: BTreeMap<String, Vec<TaskStep>>,
 }
 
 /// Specifies the build system to use.
@@ -50,6 +62,10 @@ pub struct Custom {
     pub elf_path: String,
     /// Whether to convert the ELF to raw binary format.
     pub to_bin: bool,
+    /// Package the raw binary as a legacy U-Boot image (uImage).
+    pub uimage: Option<UImage>,
+    /// Package the raw binary as an Android boot image (`boot.img`).
+    pub android_boot: Option<AndroidBoot>,
 }
 
 /// Configuration for Cargo-based builds.
@@ -73,6 +89,8 @@ pub struct Cargo {
     /// Can be a local path or a URL (including GitHub URLs which are
     /// automatically converted to raw content URLs).
     pub extra_config: Option<String>,
+    /// SHA-256 the `extra_config` download must match, pinning its content.
+    pub extra_config_sha256: Option<String>,
     /// Additional Cargo command-line arguments.
     pub args: Vec<String>,
     /// Shell commands to run before the build.
@@ -83,6 +101,77 @@ pub struct Cargo {
     pub post_build_cmds: Vec<String>,
     /// Whether to convert the ELF to raw binary format after building.
     pub to_bin: bool,
+    /// Package the raw binary as a legacy U-Boot image (uImage) after building.
+    pub uimage: Option<UImage>,
+    /// Package the raw binary as an Android boot image (`boot.img`) after building.
+    pub android_boot: Option<AndroidBoot>,
+}
+
+/// Legacy U-Boot image (uImage) packaging options.
+///
+/// The raw binary is wrapped in a 64-byte header and written next to it
+/// with the `.uimg` extension, for bootloaders without FIT support.
+#[derive(Default, Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct UImage {
+    /// Load address, e.g. "0x80200000".
+    pub load_addr: String,
+    /// Entry point address, defaults to the load address.
+    pub entry_addr: Option<String>,
+    /// Image name stored in the header, defaults to the binary file name.
+    pub name: Option<String>,
+    /// Operating system field (linux, u-boot, rtems, ...), defaults to "linux".
+    pub os: Option<String>,
+    /// Whether to gzip-compress the payload.
+    #[serde(default)]
+    pub compression: bool,
+}
+
+/// Android boot image (`boot.img`) packaging options.
+///
+/// For boards whose bootloader expects an Android boot image instead of a
+/// FIT image. The image is written next to the binary with the `.boot.img`
+/// extension. Addresses follow `mkbootimg`: `base` plus a per-section offset.
+#[derive(Default, Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct AndroidBoot {
+    /// Boot image header version.
+    #[serde(default)]
+    pub header_version: AndroidBootVersion,
+    /// Kernel command line.
+    pub cmdline: Option<String>,
+    /// Ramdisk file path.
+    pub ramdisk: Option<String>,
+    /// Device tree blob path (header v2 only).
+    pub dtb: Option<String>,
+    /// Physical base address, defaults to "0x10000000".
+    pub base: Option<String>,
+    /// Kernel offset from the base address, defaults to "0x8000".
+    pub kernel_offset: Option<String>,
+    /// Flash page size (header v2 only), defaults to 2048.
+    pub page_size: Option<u32>,
+    /// Product name stored in the header (header v2 only).
+    pub name: Option<String>,
+}
+
+/// Android boot image header version.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub enum AndroidBootVersion {
+    /// Version 2: load addresses and DTB in the boot image.
+    #[default]
+    V2,
+    /// Version 3: kernel and ramdisk only.
+    V3,
+    /// Version 4: version 3 with a boot signature section.
+    V4,
+}
+
+impl From<AndroidBootVersion> for fitimage::BootImageVersion {
+    fn from(value: AndroidBootVersion) -> Self {
+        match value {
+            AndroidBootVersion::V2 => fitimage::BootImageVersion::V2,
+            AndroidBootVersion::V3 => fitimage::BootImageVersion::V3,
+            AndroidBootVersion::V4 => fitimage::BootImageVersion::V4,
+        }
+    }
 }
 
 /// Dependency configuration for feature management.
@@ -6,7 +6,8 @@
 //! - Configuring build options via TOML configuration files
 //! - Running pre-build and post-build shell commands
 //! - Automatic feature detection and configuration
-//! - Multiple runner types (QEMU, U-Boot)
+//! - Multiple runner types (QEMU, U-Boot), reached through the
+//!   [`runner`] protocol of `cargo osrun`
 //!
 //! # Example
 //!
@@ -26,8 +27,10 @@ use crate::{
     build::{
         cargo_builder::CargoBuilder,
         config::{Cargo, Custom},
+        runner::RunnerOptions,
     },
     ctx::AppContext,
+    exit::Failure,
 };
 
 /// Cargo builder implementation for building projects.
@@ -36,6 +39,9 @@ pub mod cargo_builder;
 /// Build configuration types and structures.
 pub mod config;
 
+/// The `cargo osrun` runner protocol.
+pub mod runner;
+
 /// Specifies the type of runner to use after building.
 ///
 /// This enum determines how the built artifact will be executed,
@@ -49,11 +55,33 @@ pub enum CargoRunnerKind {
         debug: bool,
         /// Whether to dump the device tree blob.
         dtb_dump: bool,
+        /// Snapshot to save once `snapshot.save_regex` matches.
+        save_snapshot: Option<String>,
+        /// Snapshot to start from.
+        load_snapshot: Option<String>,
     },
     /// Run the built artifact on real hardware via U-Boot.
     Uboot {
         /// Optional path to U-Boot configuration file.
         uboot_config: Option<PathBuf>,
+        /// Serial port overriding the configured one.
+        port: Option<String>,
+        /// Whether to bridge the kernel gdbstub on the serial console.
+        debug: bool,
+        /// Whether to watch the console for CI instead of a terminal.
+        test: bool,
+        /// Seconds of a test run, overriding the config.
+        timeout: Option<u64>,
+    },
+    /// Run the built artifact on real hardware loaded via OpenOCD.
+    Openocd {
+        /// Optional path to OpenOCD configuration file.
+        openocd_config: Option<PathBuf>,
+    },
+    /// Flash the built artifact to a microcontroller via probe-rs.
+    ProbeRs {
+        /// Optional path to probe-rs configuration file.
+        probe_rs_config: Option<PathBuf>,
     },
 }
 
@@ -69,12 +97,10 @@ impl AppContext {
     /// Returns an error if the build process fails.
     pub async fn build_with_config(&mut self, config: &config::BuildConfig) -> anyhow::Result<()> {
         match &config.system {
-            config::BuildSystem::Custom(custom) => self.build_custom(custom)?,
-            config::BuildSystem::Cargo(cargo) => {
-                self.cargo_build(cargo).await?;
-            }
+            config::BuildSystem::Custom(custom) => self.build_custom(custom),
+            config::BuildSystem::Cargo(cargo) => self.cargo_build(cargo).await,
         }
-        Ok(())
+        .context(Failure::Build)
     }
 
     /// Builds the project from the specified configuration file path.
@@ -93,10 +119,33 @@ impl AppContext {
     /// Returns an error if the configuration cannot be loaded or the build fails.
     pub async fn build(&mut self, config_path: Option<PathBuf>) -> anyhow::Result<()> {
         let build_config = self.prepare_build_config(config_path, false).await?;
-        println!("Build configuration: {:?}", build_config);
+        debug!("Build configuration: {:?}", build_config);
         self.build_with_config(&build_config).await
     }
 
+    /// Builds the project for booting from the specified configuration file
+    /// path.
+    ///
+    /// Unlike [`Self::build`], a custom build also records its `elf_path`
+    /// and converts it to BIN when `to_bin` is set, so the output of either
+    /// build system can be booted right away.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration cannot be loaded or the build
+    /// fails.
+    pub async fn build_for_run(&mut self, config_path: Option<PathBuf>) -> anyhow::Result<()> {
+        let config = self.prepare_build_config(config_path, false).await?;
+        self.build_with_config(&config).await?;
+        if let config::BuildSystem::Custom(custom) = &config.system {
+            self.set_elf_path(custom.elf_path.clone().into()).await;
+            if custom.to_bin {
+                self.objcopy_output_bin()?;
+            }
+        }
+        Ok(())
+    }
+
     /// Executes a custom build using shell commands.
     ///
     /// # Arguments
@@ -126,6 +175,26 @@ impl AppContext {
             .await
     }
 
+    /// Builds the test binaries of the project with `cargo test --no-run`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the target name and path of every test binary.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the Cargo build fails.
+    pub async fn cargo_test_build(
+        &mut self,
+        config: &Cargo,
+    ) -> anyhow::Result<Vec<(String, PathBuf)>> {
+        let config_path = self.build_config_path.clone();
+        CargoBuilder::test(self, config, config_path)
+            .execute_test()
+            .await
+            .context(Failure::Build)
+    }
+
     /// Builds and runs the project using Cargo with the specified runner.
     ///
     /// # Arguments
@@ -180,45 +249,16 @@ impl AppContext {
             .map(normalize)
             .transpose()?;
 
-        let mut builder = CargoBuilder::run(self, config, build_config_path);
-
-        builder = builder.arg("--");
-
-        if let Some(build_dir) = build_dir {
-            builder = builder
-                .arg("--build-dir")
-                .arg(build_dir.display().to_string())
-        }
-
-        if let Some(bin_dir) = bin_dir {
-            builder = builder.arg("--bin-dir").arg(bin_dir.display().to_string())
-        }
+        let mut options = RunnerOptions::new(runner);
+        options.debug |= self.debug;
+        options.build_dir = build_dir;
+        options.bin_dir = bin_dir;
 
-        match runner {
-            CargoRunnerKind::Qemu {
-                qemu_config,
-                debug,
-                dtb_dump,
-            } => {
-                if let Some(cfg) = qemu_config {
-                    builder = builder.arg("--config").arg(cfg.display().to_string());
-                }
-
-                builder = builder.debug(*debug);
-
-                if *dtb_dump {
-                    builder = builder.arg("--dtb-dump");
-                }
-                builder = builder.arg("qemu");
-            }
-            CargoRunnerKind::Uboot { uboot_config } => {
-                if let Some(cfg) = uboot_config {
-                    builder = builder.arg("--config").arg(cfg.display().to_string());
-                }
-                builder = builder.arg("uboot");
-            }
-        }
-
-        builder.execute().await
+        CargoBuilder::run(self, config, build_config_path)
+            .debug(options.debug)
+            .arg("--")
+            .args(options.to_args())
+            .execute()
+            .await
     }
 }
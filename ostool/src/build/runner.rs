@@ -0,0 +1,538 @@
+//! The `cargo osrun` runner protocol.
+//!
+//! `ostool run` builds the kernel with `cargo run`, and cargo hands the ELF
+//! to the runner of the target, `cargo-osrun`, which boots it with the
+//! QEMU, U-Boot, OpenOCD or probe-rs runner. ostool sets that runner in a
+//! cargo config it generates next to the build output, unless the build's
+//! `extra_config` already names one:
+//!
+//! ```toml
+//! [target.aarch64-unknown-none]
+//! runner = ["/home/me/.cargo/bin/cargo-osrun", "osrun"]
+//! ```
+//!
+//! The arguments are stable, so a crate may as well set
+//! `runner = "cargo osrun"` in its own `.cargo/config.toml`. Cargo starts
+//! the runner as
+//!
+//! ```text
+//! cargo-osrun osrun <ELF> [TEST] [OPTIONS] [VERB]
+//! ```
+//!
+//! - `--build-dir <DIR>`, `--bin-dir <DIR>`: output directories of the build
+//! - `-c, --config <FILE>`: config of the runner, `.qemu.toml`,
+//!   `.uboot.toml`, ... of the config directory by default
+//! - `--port <PORT>`: serial port instead of the configured one
+//! - `--debug`: the ELF is a debug build; QEMU waits for GDB
+//! - `--dtb-dump`: dump the device tree QEMU generates
+//! - `--to-bin`: convert the ELF to a raw binary first
+//! - `--show-output`, `-v`, `-q`
+//! - `VERB`: `qemu` (the default), `uboot`, `openocd` or `probe-rs`, after
+//!   all options; arguments following it are ignored
+//!
+//! The workspace comes from `WORKSPACE_FOLDER` (the manifest directory
+//! without it), offline mode from `OSTOOL_OFFLINE` and the config directory
+//! from `OSTOOL_CONFIG_DIR`, all set by ostool for the cargo it starts.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+use clap::{Args, Parser, Subcommand};
+
+use crate::{
+    build::CargoRunnerKind,
+    ctx::{AppContext, OutputConfig, PathConfig},
+    logging::{self, LogOptions},
+    run::{
+        openocd::{self, RunOpenOcdArgs},
+        probe_rs::{self, RunProbeRsArgs},
+        qemu,
+        uboot::{self, RunUbootArgs},
+    },
+};
+
+/// Runner the ELF is handed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Verb {
+    #[default]
+    Qemu,
+    Uboot,
+    Openocd,
+    ProbeRs,
+}
+
+impl Verb {
+    /// The verb as passed on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Verb::Qemu => "qemu",
+            Verb::Uboot => "uboot",
+            Verb::Openocd => "openocd",
+            Verb::ProbeRs => "probe-rs",
+        }
+    }
+}
+
+/// Arguments of `cargo-osrun`, as cargo passes them.
+#[derive(Debug, Clone, Parser)]
+#[command(name = "cargo-osrun")]
+pub struct RunnerArgs {
+    /// `osrun` when started as `cargo osrun`
+    pub program: PathBuf,
+
+    /// Path to the binary to run on the device
+    pub elf: PathBuf,
+
+    /// Test name
+    pub test_name: Option<String>,
+
+    /// Objcopy elf to binary before running
+    #[arg(long("to-bin"))]
+    pub to_bin: bool,
+
+    /// Enable verbose output
+    #[arg(short)]
+    pub verbose: bool,
+
+    /// Enable quiet output (no output except errors)
+    #[arg(short)]
+    pub quiet: bool,
+
+    /// Path to the runner configuration file
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    #[arg(long("show-output"))]
+    pub show_output: bool,
+
+    #[arg(long)]
+    pub no_run: bool,
+
+    #[arg(long)]
+    pub debug: bool,
+
+    /// Dump DTB file
+    #[arg(long)]
+    pub dtb_dump: bool,
+
+    /// Serial port to use instead of the configured one
+    #[arg(long)]
+    pub port: Option<String>,
+
+    /// Watch the U-Boot console without a terminal until a success or fail
+    /// pattern matches or the timeout passes
+    #[arg(long)]
+    pub test: bool,
+
+    /// Seconds of a U-Boot test run
+    #[arg(long)]
+    pub timeout: Option<u64>,
+
+    /// Save the QEMU VM as this snapshot once `snapshot.save_regex` matches
+    #[arg(long)]
+    pub save_snapshot: Option<String>,
+
+    /// Start QEMU from this snapshot
+    #[arg(long)]
+    pub load_snapshot: Option<String>,
+
+    #[arg(long)]
+    pub build_dir: Option<PathBuf>,
+
+    #[arg(long)]
+    pub bin_dir: Option<PathBuf>,
+
+    /// Arguments to be run, such as the test harness flags after `cargo test --`
+    #[arg(allow_hyphen_values = true, trailing_var_arg = true)]
+    pub runner_args: Vec<String>,
+
+    #[command(subcommand)]
+    command: Option<VerbCommand>,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum VerbCommand {
+    Qemu(Rest),
+    Uboot(Rest),
+    Openocd(Rest),
+    ProbeRs(Rest),
+}
+
+/// Arguments after the verb, accepted and ignored.
+#[derive(Debug, Clone, Args)]
+struct Rest {
+    #[arg(allow_hyphen_values = true)]
+    args: Vec<String>,
+}
+
+impl RunnerArgs {
+    /// Runner named by the verb, QEMU without one.
+    pub fn verb(&self) -> Verb {
+        match self.command {
+            None | Some(VerbCommand::Qemu(_)) => Verb::Qemu,
+            Some(VerbCommand::Uboot(_)) => Verb::Uboot,
+            Some(VerbCommand::Openocd(_)) => Verb::Openocd,
+            Some(VerbCommand::ProbeRs(_)) => Verb::ProbeRs,
+        }
+    }
+}
+
+/// Options ostool passes to `cargo-osrun` after `cargo run --`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunnerOptions {
+    pub build_dir: Option<PathBuf>,
+    pub bin_dir: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+    pub port: Option<String>,
+    pub debug: bool,
+    pub dtb_dump: bool,
+    pub test: bool,
+    pub timeout: Option<u64>,
+    pub save_snapshot: Option<String>,
+    pub load_snapshot: Option<String>,
+    pub verb: Verb,
+}
+
+impl RunnerOptions {
+    /// Options selecting the runner of `kind`.
+    pub fn new(kind: &CargoRunnerKind) -> Self {
+        match kind {
+            CargoRunnerKind::Qemu {
+                qemu_config,
+                debug,
+                dtb_dump,
+                save_snapshot,
+                load_snapshot,
+            } => Self {
+                config: qemu_config.clone(),
+                debug: *debug,
+                dtb_dump: *dtb_dump,
+                save_snapshot: save_snapshot.clone(),
+                load_snapshot: load_snapshot.clone(),
+                verb: Verb::Qemu,
+                ..Default::default()
+            },
+            CargoRunnerKind::Uboot {
+                uboot_config,
+                port,
+                debug,
+                test,
+                timeout,
+            } => Self {
+                config: uboot_config.clone(),
+                port: port.clone(),
+                debug: *debug,
+                test: *test,
+                timeout: *timeout,
+                verb: Verb::Uboot,
+                ..Default::default()
+            },
+            CargoRunnerKind::Openocd { openocd_config } => Self {
+                config: openocd_config.clone(),
+                verb: Verb::Openocd,
+                ..Default::default()
+            },
+            CargoRunnerKind::ProbeRs { probe_rs_config } => Self {
+                config: probe_rs_config.clone(),
+                verb: Verb::ProbeRs,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// The arguments, with the verb last.
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        let paths = [
+            ("--build-dir", &self.build_dir),
+            ("--bin-dir", &self.bin_dir),
+            ("--config", &self.config),
+        ];
+        for (flag, path) in paths {
+            if let Some(path) = path {
+                args.push(flag.to_string());
+                args.push(path.display().to_string());
+            }
+        }
+        if let Some(port) = &self.port {
+            args.push("--port".to_string());
+            args.push(port.clone());
+        }
+        if self.debug {
+            args.push("--debug".to_string());
+        }
+        if self.dtb_dump {
+            args.push("--dtb-dump".to_string());
+        }
+        if self.test {
+            args.push("--test".to_string());
+        }
+        if let Some(timeout) = self.timeout {
+            args.push("--timeout".to_string());
+            args.push(timeout.to_string());
+        }
+        let snapshots = [
+            ("--save-snapshot", &self.save_snapshot),
+            ("--load-snapshot", &self.load_snapshot),
+        ];
+        for (flag, name) in snapshots {
+            if let Some(name) = name {
+                args.push(flag.to_string());
+                args.push(name.clone());
+            }
+        }
+        args.push(self.verb.name().to_string());
+        args
+    }
+}
+
+/// Command cargo starts as the runner: the `cargo-osrun` installed next to
+/// the running ostool, or `cargo osrun` from `PATH`.
+pub fn runner_command() -> Vec<String> {
+    let sibling = env::current_exe().ok().and_then(|exe| {
+        let path = exe.with_file_name(format!("cargo-osrun{}", env::consts::EXE_SUFFIX));
+        path.is_file().then_some(path)
+    });
+    match sibling {
+        // 与 `cargo osrun` 的参数一致，第一个参数为 `osrun`
+        Some(path) => vec![path.display().to_string(), "osrun".to_string()],
+        None => vec!["cargo".to_string(), "osrun".to_string()],
+    }
+}
+
+/// Key of `target` in the `[target]` table of a cargo config: the triple,
+/// or the file stem of a target spec `.json`.
+fn target_key(target: &str) -> &str {
+    let path = Path::new(target);
+    match path.extension() {
+        Some(ext) if ext == "json" => path.file_stem().and_then(|s| s.to_str()).unwrap_or(target),
+        _ => target,
+    }
+}
+
+/// Cargo config setting `command` as the runner of `target`.
+///
+/// # Errors
+///
+/// Returns an error if the config cannot be serialized.
+pub fn cargo_config(target: &str, command: &[String]) -> anyhow::Result<String> {
+    let mut runner = toml::Table::new();
+    runner.insert(
+        "runner".to_string(),
+        toml::Value::Array(command.iter().cloned().map(toml::Value::from).collect()),
+    );
+    let mut targets = toml::Table::new();
+    targets.insert(target_key(target).to_string(), runner.into());
+    let mut root = toml::Table::new();
+    root.insert("target".to_string(), targets.into());
+    Ok(format!(
+        "# Generated by ostool for `cargo run`\n{}",
+        toml::to_string(&root)?
+    ))
+}
+
+/// Whether the cargo config `content` sets a runner for `target`, directly
+/// or through a `cfg(...)` key ostool cannot evaluate.
+pub fn sets_runner(content: &str, target: &str) -> bool {
+    let Ok(config) = content.parse::<toml::Table>() else {
+        return false;
+    };
+    let Some(targets) = config.get("target").and_then(|t| t.as_table()) else {
+        return false;
+    };
+    targets.iter().any(|(key, value)| {
+        (key == target_key(target) || key.starts_with("cfg(")) && value.get("runner").is_some()
+    })
+}
+
+/// Runs `cargo-osrun`.
+///
+/// # Errors
+///
+/// Returns an error if it is not started by cargo or the runner fails.
+pub async fn run(args: RunnerArgs) -> anyhow::Result<()> {
+    let mut log = LogOptions::from_env();
+    log.verbose = u8::from(args.verbose);
+    log.quiet = args.quiet;
+    logging::init(&log)?;
+
+    crate::utils::apply_lang_env();
+
+    debug!("Parsed arguments: {:#?}", args);
+
+    if args.no_run {
+        exit(0);
+    }
+
+    if env::var("CARGO").is_err() {
+        eprintln!("This binary may only be called via `cargo ndk-runner`.");
+        exit(1);
+    }
+
+    let manifest_dir: PathBuf = env::var("CARGO_MANIFEST_DIR")?.into();
+
+    let workspace_folder = match env::var("WORKSPACE_FOLDER") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => manifest_dir.clone(),
+    };
+
+    let output_config = OutputConfig {
+        build_dir: args.build_dir.clone(),
+        bin_dir: args.bin_dir.clone(),
+    };
+
+    let mut app = AppContext {
+        paths: PathConfig {
+            workspace: workspace_folder,
+            manifest: manifest_dir,
+            config: output_config,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    app.load_overrides(&[])?;
+    app.set_offline(false);
+    app.set_config_dir(None)?;
+
+    app.set_elf_path(args.elf.clone()).await;
+    app.objcopy_elf()?;
+
+    app.debug = args.debug;
+    if args.to_bin {
+        app.objcopy_output_bin()?;
+    }
+
+    match args.verb() {
+        Verb::Uboot => {
+            uboot::run_uboot(
+                app,
+                RunUbootArgs {
+                    config: args.config,
+                    show_output: args.show_output,
+                    port: args.port,
+                    test: args.test,
+                    timeout: args.timeout,
+                },
+            )
+            .await?;
+        }
+        Verb::Openocd => {
+            openocd::run_openocd(
+                app,
+                RunOpenOcdArgs {
+                    config: args.config,
+                },
+            )
+            .await?;
+        }
+        Verb::ProbeRs => {
+            probe_rs::run_probe_rs(
+                app,
+                RunProbeRsArgs {
+                    config: args.config,
+                },
+            )
+            .await?;
+        }
+        Verb::Qemu => {
+            qemu::run_qemu(
+                app,
+                qemu::RunQemuArgs {
+                    qemu_config: args.config,
+                    dtb_dump: args.dtb_dump,
+                    show_output: args.show_output,
+                    save_snapshot: args.save_snapshot,
+                    load_snapshot: args.load_snapshot,
+                },
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[String]) -> RunnerArgs {
+        let argv = ["cargo-osrun", "osrun", "target/kernel"];
+        RunnerArgs::try_parse_from(
+            argv.iter()
+                .map(|s| s.to_string())
+                .chain(args.iter().cloned()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_options_roundtrip() {
+        let options = RunnerOptions {
+            build_dir: Some("/w/target".into()),
+            config: Some("boards/a.uboot.toml".into()),
+            port: Some("/dev/ttyUSB1".into()),
+            debug: true,
+            test: true,
+            timeout: Some(90),
+            verb: Verb::Uboot,
+            ..Default::default()
+        };
+        let args = parse(&options.to_args());
+        assert_eq!(args.elf, PathBuf::from("target/kernel"));
+        assert_eq!(args.verb(), Verb::Uboot);
+        assert_eq!(args.build_dir, Some("/w/target".into()));
+        assert_eq!(args.config, Some("boards/a.uboot.toml".into()));
+        assert_eq!(args.port.as_deref(), Some("/dev/ttyUSB1"));
+        assert!(args.debug);
+        assert!(args.test);
+        assert_eq!(args.timeout, Some(90));
+
+        let qemu = RunnerOptions::new(&CargoRunnerKind::Qemu {
+            qemu_config: None,
+            debug: false,
+            dtb_dump: true,
+            save_snapshot: None,
+            load_snapshot: Some("booted".into()),
+        });
+        assert_eq!(
+            qemu.to_args(),
+            ["--dtb-dump", "--load-snapshot", "booted", "qemu"]
+        );
+        let args = parse(&qemu.to_args());
+        assert_eq!(args.verb(), Verb::Qemu);
+        assert_eq!(args.test_name, None);
+        assert!(args.dtb_dump);
+
+        // 没有动词时为 QEMU，动词之后的参数被忽略
+        assert_eq!(parse(&[]).verb(), Verb::Qemu);
+        let args = parse(&["probe-rs".to_string(), "--chip".to_string()]);
+        assert_eq!(args.verb(), Verb::ProbeRs);
+
+        // `cargo test -- mytest --exact`
+        let args = parse(&["mytest".to_string(), "--exact".to_string()]);
+        assert_eq!(args.test_name.as_deref(), Some("mytest"));
+        assert_eq!(args.runner_args, ["--exact"]);
+        assert_eq!(args.verb(), Verb::Qemu);
+    }
+
+    #[test]
+    fn test_cargo_config() {
+        let command = ["cargo".to_string(), "osrun".to_string()];
+        let config = cargo_config("aarch64-unknown-none", &command).unwrap();
+        assert!(config.contains("[target.aarch64-unknown-none]"));
+        assert!(config.contains(r#"runner = ["cargo", "osrun"]"#));
+        assert!(sets_runner(&config, "aarch64-unknown-none"));
+        assert!(!sets_runner(&config, "riscv64gc-unknown-none-elf"));
+
+        let spec = cargo_config("targets/x86_64-kernel.json", &command).unwrap();
+        assert!(sets_runner(&spec, "x86_64-kernel"));
+
+        let cfg = "[target.'cfg(target_os = \"none\")']\nrunner = \"cargo osrun\"\n";
+        assert!(sets_runner(cfg, "aarch64-unknown-none"));
+        assert!(!sets_runner("[build]\ntarget = \"x\"\n", "x"));
+    }
+}
@@ -0,0 +1,536 @@
+//! User-level cache of downloaded artifacts, `ostool cache`.
+//!
+//! Downloaded cargo configs, OVMF firmware and template files are kept in
+//! one directory instead of timestamped files in the system temp directory:
+//! `$XDG_CACHE_HOME/ostool` (falling back to `~/.cache/ostool`, or
+//! `%LOCALAPPDATA%\ostool` on Windows), or the directory named by
+//! `OSTOOL_CACHE_DIR`.
+//!
+//! Every entry lives at `<kind>/<key>` and is listed in `manifest.toml`
+//! with its source, size and when it was created and last used, so
+//! `ostool cache ls` can account for the space and
+//! `ostool cache clean --older-than 30d` can drop what is no longer used.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use colored::Colorize;
+use jkconfig::t;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{progress, utils::human_size};
+
+const MANIFEST: &str = "manifest.toml";
+
+/// Downloaded cargo configs of `extra_config`.
+pub const CARGO_CONFIG: &str = "cargo-config";
+/// OVMF firmware releases.
+pub const OVMF: &str = "ovmf";
+/// Templates fetched from a URL.
+pub const TEMPLATES: &str = "templates";
+
+/// A cached artifact.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Kind of artifact, the first path component, e.g. `ovmf`.
+    pub kind: String,
+    /// Name within the kind, the second path component.
+    pub key: String,
+    /// Where the artifact came from, usually a URL.
+    pub source: Option<String>,
+    /// Size in bytes when last stored.
+    pub size: u64,
+    /// Unix time of creation.
+    pub created: u64,
+    /// Unix time of the last use.
+    pub used: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    entries: Vec<Entry>,
+}
+
+/// The cache directory and its manifest.
+#[derive(Debug)]
+pub struct Cache {
+    dir: PathBuf,
+    manifest: Manifest,
+}
+
+impl Cache {
+    /// Location of the cache directory.
+    pub fn dir() -> PathBuf {
+        if let Some(dir) = env::var_os("OSTOOL_CACHE_DIR") {
+            return dir.into();
+        }
+        let base = if cfg!(windows) {
+            env::var_os("LOCALAPPDATA").map(PathBuf::from)
+        } else {
+            env::var_os("XDG_CACHE_HOME")
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        };
+        base.unwrap_or_else(env::temp_dir).join("ostool")
+    }
+
+    /// Opens the cache; a missing or unreadable manifest starts empty.
+    pub fn open() -> Self {
+        Self::open_at(Self::dir())
+    }
+
+    fn open_at(dir: PathBuf) -> Self {
+        let path = dir.join(MANIFEST);
+        let manifest = match std::fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content).unwrap_or_else(|e| {
+                warn!("Ignoring {}: {e}", path.display());
+                Manifest::default()
+            }),
+            Err(_) => Manifest::default(),
+        };
+        Self { dir, manifest }
+    }
+
+    /// Path of the entry `kind`/`key`, whether or not it exists.
+    pub fn path(&self, kind: &str, key: &str) -> PathBuf {
+        self.dir.join(kind).join(key)
+    }
+
+    /// Path of the entry if it is present, marking it used.
+    pub fn lookup(&mut self, kind: &str, key: &str) -> Option<PathBuf> {
+        let path = self.path(kind, key);
+        if !path.exists() {
+            return None;
+        }
+        let now = now();
+        match self.find(kind, key) {
+            Some(entry) => entry.used = now,
+            None => self.manifest.entries.push(Entry {
+                kind: kind.to_string(),
+                key: key.to_string(),
+                source: None,
+                size: size_of(&path),
+                created: now,
+                used: now,
+            }),
+        }
+        if let Err(e) = self.save() {
+            debug!("Failed to update the cache manifest: {e:#}");
+        }
+        Some(path)
+    }
+
+    /// Records the entry after its contents were written to
+    /// [`path`](Self::path), and returns that path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manifest cannot be written.
+    pub fn insert(
+        &mut self,
+        kind: &str,
+        key: &str,
+        source: Option<&str>,
+    ) -> anyhow::Result<PathBuf> {
+        let path = self.path(kind, key);
+        let size = size_of(&path);
+        let now = now();
+        let source = source.map(str::to_string);
+        match self.find(kind, key) {
+            Some(entry) => {
+                entry.size = size;
+                entry.used = now;
+                if source.is_some() {
+                    entry.source = source;
+                }
+            }
+            None => self.manifest.entries.push(Entry {
+                kind: kind.to_string(),
+                key: key.to_string(),
+                source,
+                size,
+                created: now,
+                used: now,
+            }),
+        }
+        self.save()?;
+        Ok(path)
+    }
+
+    /// Entries of the manifest, by kind and key.
+    pub fn entries(&self) -> &[Entry] {
+        &self.manifest.entries
+    }
+
+    /// Removes the entries not used within `older_than`, or everything in
+    /// the cache directory when `None`, and returns the count and bytes
+    /// freed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an entry or the manifest cannot be written.
+    pub fn clean(&mut self, older_than: Option<Duration>) -> anyhow::Result<(usize, u64)> {
+        let Some(age) = older_than else {
+            let count = self.manifest.entries.len();
+            let size = size_of(&self.dir);
+            if self.dir.exists() {
+                std::fs::remove_dir_all(&self.dir)?;
+            }
+            self.manifest = Manifest::default();
+            return Ok((count, size));
+        };
+        let cutoff = now().saturating_sub(age.as_secs());
+        let (old, kept) = std::mem::take(&mut self.manifest.entries)
+            .into_iter()
+            .partition::<Vec<_>, _>(|e| e.used < cutoff);
+        self.manifest.entries = kept;
+        let mut freed = 0;
+        for entry in &old {
+            let path = self.path(&entry.kind, &entry.key);
+            freed += size_of(&path);
+            remove(&path)?;
+        }
+        self.save()?;
+        Ok((old.len(), freed))
+    }
+
+    fn find(&mut self, kind: &str, key: &str) -> Option<&mut Entry> {
+        self.manifest
+            .entries
+            .iter_mut()
+            .find(|e| e.kind == kind && e.key == key)
+    }
+
+    fn save(&mut self) -> anyhow::Result<()> {
+        self.manifest
+            .entries
+            .sort_by(|a, b| (&a.kind, &a.key).cmp(&(&b.kind, &b.key)));
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(
+            self.dir.join(MANIFEST),
+            toml::to_string_pretty(&self.manifest)?,
+        )?;
+        Ok(())
+    }
+}
+
+/// Cache key of a downloaded URL: a hash of the URL, so different URLs
+/// never collide, followed by its file name for readability.
+pub fn url_key(url: &str) -> String {
+    let hash = format!("{:x}", Sha256::digest(url.as_bytes()));
+    let name = url
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "._-".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    format!("{}-{name}", &hash[..16])
+}
+
+/// Downloads `url` into the entry `kind`/[`url_key`], checking its SHA-256
+/// against `sha256` when pinned, and returns its path.
+///
+/// Offline, or when the download fails, the cached copy is used instead,
+/// checked against the pin as well.
+///
+/// # Errors
+///
+/// Returns an error if the download fails with no usable cached copy, or
+/// the content does not match the pin.
+pub async fn fetch(
+    kind: &str,
+    url: &str,
+    sha256: Option<&str>,
+    offline: bool,
+) -> anyhow::Result<PathBuf> {
+    let mut cache = Cache::open();
+    let key = url_key(url);
+    if offline {
+        let path = cache
+            .lookup(kind, &key)
+            .ok_or_else(|| anyhow!(t!("cache.offline_missing", url = url)))?;
+        verify(url, &std::fs::read(&path)?, sha256)?;
+        return Ok(path);
+    }
+
+    let data = match download(url).await {
+        Ok(data) => data,
+        Err(e) => {
+            let Some(path) = cache.lookup(kind, &key) else {
+                return Err(e);
+            };
+            warn!("{e:#}");
+            warn!("{}", t!("cache.stale", path = path.display()));
+            verify(url, &std::fs::read(&path)?, sha256)?;
+            return Ok(path);
+        }
+    };
+    verify(url, &data, sha256)?;
+    let path = cache.path(kind, &key);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, data)?;
+    cache.insert(kind, &key, Some(url))
+}
+
+async fn download(url: &str) -> anyhow::Result<Vec<u8>> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let mut resp = client
+        .get(url)
+        .header("User-Agent", "ostool")
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .with_context(|| format!("Failed to download {url}"))?;
+    let name = url.rsplit('/').next().unwrap_or(url);
+    let pb = progress::bytes(t!("progress.download", name = name), resp.content_length());
+    let mut data = Vec::new();
+    while let Some(chunk) = resp.chunk().await? {
+        data.extend_from_slice(&chunk);
+        pb.inc(chunk.len() as u64);
+    }
+    pb.finish();
+    Ok(data)
+}
+
+/// Checks `data` downloaded from `url` against the pinned SHA-256.
+///
+/// # Errors
+///
+/// Returns an error naming both hashes if they differ.
+pub fn verify(url: &str, data: &[u8], sha256: Option<&str>) -> anyhow::Result<()> {
+    let Some(expected) = sha256 else {
+        return Ok(());
+    };
+    let actual = format!("{:x}", Sha256::digest(data));
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        bail!(t!(
+            "cache.hash_mismatch",
+            url = url,
+            expected = expected,
+            actual = actual
+        ));
+    }
+    Ok(())
+}
+
+/// Parses an age such as `30d`, `12h`, `90m`, `2w` or `45s`.
+///
+/// # Errors
+///
+/// Returns a message if the number or unit is not valid; used as a clap
+/// value parser.
+pub fn parse_age(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let num: u64 = num
+        .parse()
+        .map_err(|_| format!("invalid age `{s}`, e.g. 30d"))?;
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" | "" => 3600,
+        "d" => 24 * 3600,
+        "w" => 7 * 24 * 3600,
+        _ => return Err(format!("invalid unit `{unit}`, use s, m, h, d or w")),
+    };
+    Ok(Duration::from_secs(num * secs))
+}
+
+/// Prints the cache entries and their total size, `ostool cache ls`.
+pub fn list() {
+    let cache = Cache::open();
+    let dir = Cache::dir();
+    if cache.entries().is_empty() {
+        println!("{}", t!("cache.empty", dir = dir.display()));
+        return;
+    }
+    let now = now();
+    for entry in cache.entries() {
+        println!(
+            "{:<14} {:<40} {:>10} {:>10}  {}",
+            entry.kind.bold(),
+            entry.key,
+            human_size(entry.size),
+            ago(now.saturating_sub(entry.used)),
+            entry.source.as_deref().unwrap_or("-").dimmed()
+        );
+    }
+    let total = cache.entries().iter().map(|e| e.size).sum::<u64>();
+    println!(
+        "{}",
+        t!(
+            "cache.total",
+            count = cache.entries().len(),
+            size = human_size(total),
+            dir = dir.display()
+        )
+    );
+}
+
+/// Removes old cache entries, `ostool cache clean`.
+///
+/// Leftovers of ostool versions that downloaded into the system temp
+/// directory are removed as well.
+///
+/// # Errors
+///
+/// Returns an error if removing fails.
+pub fn clean(older_than: Option<Duration>) -> anyhow::Result<()> {
+    let mut cache = Cache::open();
+    let (count, mut freed) = cache.clean(older_than)?;
+    for path in legacy_temp_files() {
+        freed += size_of(&path);
+        remove(&path)?;
+    }
+    println!(
+        "{}",
+        t!("cache.cleaned", count = count, size = human_size(freed)).green()
+    );
+    Ok(())
+}
+
+/// Files the temp-directory downloads of earlier versions left behind.
+fn legacy_temp_files() -> Vec<PathBuf> {
+    let tmp = env::temp_dir();
+    let mut paths = std::fs::read_dir(&tmp)
+        .map(|dir| {
+            dir.flatten()
+                .map(|e| e.path())
+                .filter(|p| {
+                    p.file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| n.starts_with("cargo_config_"))
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let ovmf = tmp.join("ostool").join("ovmf");
+    // 缓存目录本身可能退回到 temp_dir/ostool，此时 ovmf 是正常的缓存项
+    if ovmf.exists() && Cache::dir() != tmp.join("ostool") {
+        paths.push(ovmf);
+    }
+    paths
+}
+
+fn remove(path: &Path) -> std::io::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => std::fs::remove_file(path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Size of a file, or of everything below a directory.
+fn size_of(path: &Path) -> u64 {
+    let Ok(meta) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    std::fs::read_dir(path)
+        .map(|dir| dir.flatten().map(|e| size_of(&e.path())).sum())
+        .unwrap_or(0)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn ago(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_age_and_key() {
+        assert_eq!(parse_age("30d"), Ok(Duration::from_secs(30 * 86400)));
+        assert_eq!(parse_age("2w"), Ok(Duration::from_secs(14 * 86400)));
+        assert_eq!(parse_age("90m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_age("12"), Ok(Duration::from_secs(12 * 3600)));
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("3y").is_err());
+
+        let key = url_key("https://example.com/cfg/config.toml?x=1");
+        assert!(key.ends_with("-config.toml_x_1"));
+        assert_eq!(key.len(), 16 + 1 + "config.toml_x_1".len());
+        assert_ne!(key, url_key("https://example.org/cfg/config.toml?x=1"));
+        assert_eq!(ago(59), "59s");
+        assert_eq!(ago(7200), "2h");
+
+        let hash = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert!(verify("u", b"hello", None).is_ok());
+        assert!(verify("u", b"hello", Some(hash)).is_ok());
+        assert!(verify("u", b"hello", Some(&hash.to_uppercase())).is_ok());
+        assert!(verify("u", b"hello!", Some(hash)).is_err());
+    }
+
+    #[test]
+    fn test_insert_lookup_clean() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("cache");
+        let mut cache = Cache::open_at(dir.clone());
+        assert!(cache.lookup(OVMF, "r1").is_none());
+
+        let path = cache.path(CARGO_CONFIG, "k");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, [0u8; 100]).unwrap();
+        cache
+            .insert(CARGO_CONFIG, "k", Some("https://x/k"))
+            .unwrap();
+        let ovmf = cache.path(OVMF, "r1");
+        std::fs::create_dir_all(ovmf.join("x64")).unwrap();
+        std::fs::write(ovmf.join("x64/code.fd"), [0u8; 50]).unwrap();
+        assert_eq!(cache.lookup(OVMF, "r1"), Some(ovmf.clone()));
+
+        let mut cache = Cache::open_at(dir.clone());
+        assert_eq!(cache.entries().len(), 2);
+        assert_eq!(cache.entries()[0].size, 100);
+        assert_eq!(cache.entries()[1].size, 50);
+        assert_eq!(cache.entries()[0].source.as_deref(), Some("https://x/k"));
+
+        cache.find(OVMF, "r1").unwrap().used -= 10 * 86400;
+        assert_eq!(
+            cache.clean(Some(Duration::from_secs(86400))).unwrap(),
+            (1, 50)
+        );
+        assert!(!ovmf.exists() && path.exists());
+        assert_eq!(Cache::open_at(dir.clone()).entries().len(), 1);
+
+        let (count, _) = cache.clean(None).unwrap();
+        assert_eq!(count, 1);
+        assert!(!dir.exists());
+    }
+}
@@ -4,7 +4,10 @@
 //! for the ostool application, including paths, build configuration, and
 //! architecture information.
 
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use anyhow::anyhow;
 use cargo_metadata::Metadata;
@@ -12,14 +15,35 @@ use colored::Colorize;
 use cursive::Cursive;
 use jkconfig::{
     ElemHock,
-    data::{app_data::AppData, item::ItemType, types::ElementType},
+    data::{app_data::AppData, item::ItemType, overrides::Overrides, types::ElementType},
+    t,
     ui::components::editors::{show_feature_select, show_list_select},
 };
 
 use object::{Architecture, Object};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
 use tokio::fs;
 
-use crate::build::config::BuildConfig;
+use crate::{
+    build::config::{AndroidBoot, BuildConfig, UImage},
+    migrate, progress,
+    settings::Settings,
+    utils::parse_int,
+};
+
+/// Prefix of environment variables overriding config values, e.g.
+/// `OSTOOL_CFG__system__Cargo__target=riscv64gc-unknown-none-elf`.
+pub const CONFIG_ENV_PREFIX: &str = "OSTOOL_CFG__";
+
+/// Environment variable enabling offline mode, set for the runners started
+/// through cargo so they inherit `--offline`.
+pub const OFFLINE_ENV: &str = "OSTOOL_OFFLINE";
+
+/// Environment variable naming the directory of the config files, set for
+/// the commands ostool starts so the runners started through cargo use the
+/// same directory.
+pub const CONFIG_DIR_ENV: &str = "OSTOOL_CONFIG_DIR";
 
 /// Configuration for output directories.
 ///
@@ -39,6 +63,16 @@ pub struct OutputArtifacts {
     pub elf: Option<PathBuf>,
     /// Path to the converted binary file.
     pub bin: Option<PathBuf>,
+    /// Path to the packaged legacy U-Boot image.
+    pub uimage: Option<PathBuf>,
+    /// Path to the generated U-Boot boot script (`boot.scr`).
+    pub boot_script: Option<PathBuf>,
+    /// Path to the packaged Android boot image.
+    pub android_boot: Option<PathBuf>,
+    /// Path to the assembled disk image.
+    pub disk_image: Option<PathBuf>,
+    /// Path to the packaged FIT image.
+    pub fit: Option<PathBuf>,
 }
 
 /// Path configuration grouping all path-related fields.
@@ -48,6 +82,9 @@ pub struct PathConfig {
     pub workspace: PathBuf,
     /// Cargo manifest directory.
     pub manifest: PathBuf,
+    /// Directory holding the config files when they are kept outside the
+    /// workspace, e.g. in a shared repository of board configs.
+    pub config_dir: Option<PathBuf>,
     /// Output directory configuration.
     pub config: OutputConfig,
     /// Generated build artifacts.
@@ -69,6 +106,16 @@ impl PathConfig {
     pub fn bin_dir(&self) -> Option<PathBuf> {
         self.config.bin_dir.clone()
     }
+
+    /// Directory of the config files: `config_dir`, or the workspace.
+    pub fn configs(&self) -> &Path {
+        self.config_dir.as_deref().unwrap_or(&self.workspace)
+    }
+
+    /// Default path of the config file `name`, e.g. `.uboot.toml`.
+    pub fn config_file(&self, name: &str) -> PathBuf {
+        self.configs().join(name)
+    }
 }
 
 /// The main application context holding all state.
@@ -88,9 +135,80 @@ pub struct AppContext {
     pub build_config: Option<BuildConfig>,
     /// Path to the build configuration file.
     pub build_config_path: Option<PathBuf>,
+    /// Overrides applied on top of every loaded config file.
+    pub overrides: Overrides,
+    /// Whether network access is forbidden; downloads come from the cache.
+    pub offline: bool,
 }
 
 impl AppContext {
+    /// Collects config overrides from `OSTOOL_CFG__*` environment variables
+    /// followed by `path=value` command-line arguments, which take precedence.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an argument is not of the form `path=value`, or
+    /// if an override names a key that none of the config files has.
+    pub fn load_overrides(&mut self, set_args: &[String]) -> anyhow::Result<()> {
+        let mut overrides = Overrides::from_env(CONFIG_ENV_PREFIX);
+        for arg in set_args {
+            overrides.push_arg(arg)?;
+        }
+        // Each config file only applies the overrides it has keys for, so
+        // check against all of them up front
+        overrides.check(&config_schemas())?;
+        self.overrides = overrides;
+        Ok(())
+    }
+
+    /// Enables offline mode if `offline` is set or `OSTOOL_OFFLINE` is set
+    /// to anything but `0`.
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline =
+            offline || std::env::var(OFFLINE_ENV).is_ok_and(|v| !v.is_empty() && v != "0");
+    }
+
+    /// Sets the directory of the config files: `dir` if given, otherwise
+    /// `OSTOOL_CONFIG_DIR`, otherwise the one the user settings give for the
+    /// workspace. Without any the config files are in the workspace.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory does not exist.
+    pub fn set_config_dir(&mut self, dir: Option<PathBuf>) -> anyhow::Result<()> {
+        let dir = dir
+            .or_else(|| {
+                std::env::var_os(CONFIG_DIR_ENV)
+                    .filter(|v| !v.is_empty())
+                    .map(PathBuf::from)
+            })
+            .or_else(|| Settings::load().config_dir(&self.paths.workspace));
+        if let Some(dir) = &dir
+            && !dir.is_dir()
+        {
+            anyhow::bail!(t!("config_dir.missing", path = dir.display()));
+        }
+        self.paths.config_dir = dir.map(|dir| std::path::absolute(&dir).unwrap_or(dir));
+        Ok(())
+    }
+
+    /// Parses config file content with the overrides applied.
+    ///
+    /// The file itself is not modified.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the content cannot be parsed or an override does
+    /// not resolve or validate.
+    pub fn parse_config<C: JsonSchema + DeserializeOwned>(
+        &self,
+        path: &Path,
+        content: &str,
+    ) -> anyhow::Result<C> {
+        let schema = schemars::schema_for!(C);
+        self.overrides.load(path, content, schema.as_value())
+    }
+
     /// Executes a shell command in the current context.
     ///
     /// The command is run in the manifest directory with the `KERNEL_ELF`
@@ -104,6 +222,23 @@ impl AppContext {
     ///
     /// Returns an error if the command fails to execute.
     pub fn shell_run_cmd(&self, cmd: &str) -> anyhow::Result<()> {
+        self.shell_run_cmd_with_env(cmd, &[])
+    }
+
+    /// Like [`Self::shell_run_cmd`], with extra environment variables.
+    ///
+    /// Their values are passed as they are, without variable substitution,
+    /// so they can carry untrusted text that must not become part of the
+    /// command line.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command fails to execute.
+    pub fn shell_run_cmd_with_env(
+        &self,
+        cmd: &str,
+        vars: &[(String, String)],
+    ) -> anyhow::Result<()> {
         let mut command = match std::env::consts::OS {
             "windows" => {
                 let mut command = self.command("powershell");
@@ -122,6 +257,9 @@ impl AppContext {
         if let Some(elf) = &self.paths.artifacts.elf {
             command.env("KERNEL_ELF", elf.display().to_string());
         }
+        for (key, value) in vars {
+            std::process::Command::env(&mut command, key, value);
+        }
 
         command.run()?;
 
@@ -134,9 +272,13 @@ impl AppContext {
     /// variable substitution support.
     pub fn command(&self, program: &str) -> crate::utils::Command {
         let this = self.clone();
-        crate::utils::Command::new(program, &self.paths.manifest, move |s| {
+        let mut cmd = crate::utils::Command::new(program, &self.paths.manifest, move |s| {
             this.value_replace_with_var(s)
-        })
+        });
+        if let Some(dir) = &self.paths.config_dir {
+            cmd.env(CONFIG_DIR_ENV, dir);
+        }
+        cmd
     }
 
     /// Gets the Cargo metadata for the current workspace.
@@ -160,14 +302,14 @@ impl AppContext {
         let binary_data = match fs::read(path).await {
             Ok(data) => data,
             Err(e) => {
-                println!("Failed to read ELF file: {e}");
+                warn!("Failed to read ELF file: {e}");
                 return;
             }
         };
         let file = match object::File::parse(binary_data.as_slice()) {
             Ok(f) => f,
             Err(e) => {
-                println!("Failed to parse ELF file: {e}");
+                warn!("Failed to parse ELF file: {e}");
                 return;
             }
         };
@@ -202,16 +344,18 @@ impl AppContext {
                 .to_string()
                 + ".elf",
         );
-        println!(
-            "{}",
-            format!(
-                "Stripping ELF file...\r\n  original elf: {}\r\n  stripped elf: {}",
-                elf_path.display(),
-                stripped_elf_path.display()
-            )
-            .bold()
-            .purple()
-        );
+        if log_enabled!(log::Level::Info) {
+            println!(
+                "{}",
+                format!(
+                    "Stripping ELF file...\r\n  original elf: {}\r\n  stripped elf: {}",
+                    elf_path.display(),
+                    stripped_elf_path.display()
+                )
+                .bold()
+                .purple()
+            );
+        }
 
         let mut objcopy = self.command("rust-objcopy");
 
@@ -241,9 +385,9 @@ impl AppContext {
     ///
     /// Returns an error if no ELF file is set or `rust-objcopy` fails.
     pub fn objcopy_output_bin(&mut self) -> anyhow::Result<PathBuf> {
-        if self.paths.artifacts.bin.is_some() {
-            debug!("BIN file already exists: {:?}", self.paths.artifacts.bin);
-            return Ok(self.paths.artifacts.bin.as_ref().unwrap().clone());
+        if let Some(bin) = &self.paths.artifacts.bin {
+            debug!("BIN file already exists: {:?}", bin);
+            return Ok(bin.clone());
         }
 
         let elf_path = self
@@ -271,16 +415,18 @@ impl AppContext {
             std::fs::create_dir_all(parent)?;
         }
 
-        println!(
-            "{}",
-            format!(
-                "Converting ELF to BIN format...\r\n  elf: {}\r\n  bin: {}",
-                elf_path.display(),
-                bin_path.display()
-            )
-            .bold()
-            .purple()
-        );
+        if log_enabled!(log::Level::Info) {
+            println!(
+                "{}",
+                format!(
+                    "Converting ELF to BIN format...\r\n  elf: {}\r\n  bin: {}",
+                    elf_path.display(),
+                    bin_path.display()
+                )
+                .bold()
+                .purple()
+            );
+        }
 
         let mut objcopy = self.command("rust-objcopy");
 
@@ -294,12 +440,160 @@ impl AppContext {
             .arg(&elf_path)
             .arg(&bin_path);
 
-        objcopy.run()?;
+        objcopy.print_cmd();
+        let pb = progress::spinner(t!("progress.objcopy"));
+        let status = objcopy.status()?;
+        pb.finish();
+        if !status.success() {
+            bail!("failed with status: {status}");
+        }
         self.paths.artifacts.bin = Some(bin_path.clone());
 
         Ok(bin_path)
     }
 
+    /// Packages the raw binary as a legacy U-Boot image (uImage).
+    ///
+    /// Converts the ELF to binary first if needed. The image is written
+    /// next to the binary with the `.uimg` extension.
+    ///
+    /// # Returns
+    ///
+    /// Returns the path to the generated image.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the binary cannot be produced, the configuration
+    /// is invalid, or the architecture has no uImage code.
+    pub fn output_uimage(&mut self, config: &UImage) -> anyhow::Result<PathBuf> {
+        let bin_path = self.objcopy_output_bin()?;
+
+        let load_addr = parse_int(&config.load_addr)
+            .ok_or(anyhow!("Invalid uimage load_addr: {}", config.load_addr))?;
+        let entry_addr = match &config.entry_addr {
+            Some(addr) => parse_int(addr).ok_or(anyhow!("Invalid uimage entry_addr: {addr}"))?,
+            None => load_addr,
+        };
+        let os = config.os.as_deref().unwrap_or("linux");
+        let os = fitimage::ImageOs::from_name(os).ok_or(anyhow!("Unsupported uimage os: {os}"))?;
+        let arch = self.image_arch()?;
+        let name = config.name.clone().unwrap_or_else(|| {
+            bin_path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+
+        let data = std::fs::read(&bin_path)?;
+        let image = fitimage::UImageConfig::new(name, data)
+            .with_os(os)
+            .with_arch(arch)
+            .with_compression(config.compression)
+            .with_load_address(
+                u32::try_from(load_addr)
+                    .map_err(|_| anyhow!("uImage load_addr exceeds 32 bits"))?,
+            )
+            .with_entry_point(
+                u32::try_from(entry_addr)
+                    .map_err(|_| anyhow!("uImage entry_addr exceeds 32 bits"))?,
+            )
+            .build()?;
+
+        let uimage_path = bin_path.with_extension("uimg");
+        std::fs::write(&uimage_path, image)?;
+        if log_enabled!(log::Level::Info) {
+            println!(
+                "{}",
+                format!("uImage created: {}", uimage_path.display())
+                    .bold()
+                    .purple()
+            );
+        }
+        self.paths.artifacts.uimage = Some(uimage_path.clone());
+
+        Ok(uimage_path)
+    }
+
+    /// Packages the raw binary as an Android boot image (`boot.img`).
+    ///
+    /// Converts the ELF to binary first if needed. The image is written
+    /// next to the binary with the `.boot.img` extension.
+    ///
+    /// # Returns
+    ///
+    /// Returns the path to the generated image.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the binary, ramdisk or DTB cannot be read, or the
+    /// configuration does not fit the selected header version.
+    pub fn output_android_boot(&mut self, config: &AndroidBoot) -> anyhow::Result<PathBuf> {
+        let bin_path = self.objcopy_output_bin()?;
+        let read = |path: &String| {
+            let path = self.value_replace_with_var(path);
+            std::fs::read(&path).map_err(|e| anyhow!("Failed to read {path}: {e}"))
+        };
+
+        let mut image = fitimage::AndroidBootImage::new(std::fs::read(&bin_path)?)
+            .with_version(config.header_version.into());
+        if let Some(cmdline) = &config.cmdline {
+            image = image.with_cmdline(cmdline);
+        }
+        if let Some(ramdisk) = &config.ramdisk {
+            image = image.with_ramdisk(read(ramdisk)?);
+        }
+        if let Some(dtb) = &config.dtb {
+            image = image.with_dtb(read(dtb)?);
+        }
+        if let Some(base) = &config.base {
+            image = image
+                .with_base(parse_int(base).ok_or(anyhow!("Invalid android_boot base: {base}"))?);
+        }
+        if let Some(offset) = &config.kernel_offset {
+            image = image.with_kernel_offset(
+                parse_int(offset).ok_or(anyhow!("Invalid android_boot kernel_offset: {offset}"))?,
+            );
+        }
+        if let Some(page_size) = config.page_size {
+            image = image.with_page_size(page_size);
+        }
+        if let Some(name) = &config.name {
+            image = image.with_name(name);
+        }
+
+        let boot_path = bin_path.with_extension("boot.img");
+        std::fs::write(&boot_path, image.build()?)?;
+        if log_enabled!(log::Level::Info) {
+            println!(
+                "{}",
+                format!("Android boot image created: {}", boot_path.display())
+                    .bold()
+                    .purple()
+            );
+        }
+        self.paths.artifacts.android_boot = Some(boot_path.clone());
+
+        Ok(boot_path)
+    }
+
+    /// Maps the target architecture to its U-Boot image header code.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the architecture is unknown or has no U-Boot code.
+    pub fn image_arch(&self) -> anyhow::Result<fitimage::ImageArch> {
+        Ok(match self.arch {
+            Some(Architecture::Aarch64) => fitimage::ImageArch::Arm64,
+            Some(Architecture::Arm) => fitimage::ImageArch::Arm,
+            Some(Architecture::Riscv32 | Architecture::Riscv64) => fitimage::ImageArch::Riscv,
+            Some(Architecture::X86_64) => fitimage::ImageArch::X86_64,
+            Some(Architecture::I386) => fitimage::ImageArch::X86,
+            Some(Architecture::Mips) => fitimage::ImageArch::Mips,
+            Some(Architecture::Mips64) => fitimage::ImageArch::Mips64,
+            other => bail!("Architecture {other:?} is not supported by U-Boot images"),
+        })
+    }
+
     /// Loads and prepares the build configuration.
     ///
     /// This method loads the build configuration from a TOML file. If `menu` is
@@ -321,16 +615,14 @@ impl AppContext {
     ) -> anyhow::Result<BuildConfig> {
         let config_path = match config_path {
             Some(path) => path,
-            None => self.paths.workspace.join(".build.toml"),
+            None => self.paths.config_file(".build.toml"),
         };
         self.build_config_path = Some(config_path.clone());
+        migrate::upgrade_file(&config_path, migrate::BUILD)?;
 
-        let Some(c): Option<BuildConfig> = jkconfig::run(
-            config_path,
-            menu,
-            &[self.ui_hock_feature_select(), self.ui_hock_pacage_select()],
-        )
-        .await?
+        let Some(c): Option<BuildConfig> =
+            jkconfig::run_with_overrides(config_path, menu, &self.ui_hocks(), &self.overrides)
+                .await?
         else {
             anyhow::bail!("No build configuration obtained");
         };
@@ -341,8 +633,9 @@ impl AppContext {
 
     /// Replaces variable placeholders in a string.
     ///
-    /// Currently supports `${workspaceFolder}` which is replaced with the
-    /// workspace directory path.
+    /// Supports `${workspaceFolder}`, replaced with the workspace directory
+    /// path, and `${configDir}`, replaced with the directory of the config
+    /// files (see [`PathConfig::configs`]).
     pub fn value_replace_with_var<S>(&self, value: S) -> String
     where
         S: AsRef<std::ffi::OsStr>,
@@ -352,24 +645,38 @@ impl AppContext {
             "${workspaceFolder}",
             format!("{}", self.paths.workspace.display()).as_ref(),
         )
+        .replace(
+            "${configDir}",
+            format!("{}", self.paths.configs().display()).as_ref(),
+        )
     }
 
     /// Returns UI hooks for the configuration editor.
     ///
     /// These hooks provide interactive selection dialogs for features and packages.
     pub fn ui_hocks(&self) -> Vec<ElemHock> {
-        vec![self.ui_hock_feature_select(), self.ui_hock_pacage_select()]
+        self.ui_hocks_at("")
+    }
+
+    /// Like [`Self::ui_hocks`], for a build config mounted under `prefix`
+    /// (e.g. `"build."`) in a combined menu.
+    pub fn ui_hocks_at(&self, prefix: &str) -> Vec<ElemHock> {
+        vec![
+            self.ui_hock_feature_select(prefix),
+            self.ui_hock_pacage_select(prefix),
+        ]
     }
 
-    fn ui_hock_feature_select(&self) -> ElemHock {
-        let path = "system.features";
+    fn ui_hock_feature_select(&self, prefix: &str) -> ElemHock {
+        let path = format!("{prefix}system.features");
+        let package_path = format!("{prefix}system.package");
         let cargo_toml = self.paths.workspace.join("Cargo.toml");
         ElemHock {
-            path: path.to_string(),
+            path,
             callback: Arc::new(move |siv: &mut Cursive, _path: &str| {
                 let mut package = String::new();
                 if let Some(app) = siv.user_data::<AppData>()
-                    && let Some(pkg) = app.root.get_by_key("system.package")
+                    && let Some(pkg) = app.root.get_by_key(&package_path)
                     && let ElementType::Item(item) = pkg
                     && let ItemType::String { value: Some(v), .. } = &item.item_type
                 {
@@ -382,8 +689,8 @@ impl AppContext {
         }
     }
 
-    fn ui_hock_pacage_select(&self) -> ElemHock {
-        let path = "system.package";
+    fn ui_hock_pacage_select(&self, prefix: &str) -> ElemHock {
+        let path = format!("{prefix}system.package");
         let cargo_toml = self.paths.workspace.join("Cargo.toml");
 
         ElemHock {
@@ -407,6 +714,33 @@ impl AppContext {
     }
 }
 
+/// Schemas of every config file loaded through [`AppContext::parse_config`]
+/// or [`AppContext::prepare_build_config`].
+fn config_schemas() -> Vec<serde_json::Value> {
+    use crate::{
+        bench::BenchConfig,
+        disk::image::DiskImageConfig,
+        remote::agent::AgentConfig,
+        run::{
+            boards::BoardsConfig, gdb::GdbConfig, openocd::OpenOcdConfig,
+            probe_rs::ProbeRsConfig, qemu::QemuConfig, uboot::UbootConfig,
+        },
+    };
+
+    vec![
+        schemars::schema_for!(BuildConfig).to_value(),
+        schemars::schema_for!(QemuConfig).to_value(),
+        schemars::schema_for!(UbootConfig).to_value(),
+        schemars::schema_for!(BoardsConfig).to_value(),
+        schemars::schema_for!(GdbConfig).to_value(),
+        schemars::schema_for!(OpenOcdConfig).to_value(),
+        schemars::schema_for!(ProbeRsConfig).to_value(),
+        schemars::schema_for!(DiskImageConfig).to_value(),
+        schemars::schema_for!(BenchConfig).to_value(),
+        schemars::schema_for!(AgentConfig).to_value(),
+    ]
+}
+
 fn on_package_selected(app: &mut AppData, path: &str, selected: &str) {
     let ElementType::Item(item) = app.root.get_mut_by_key(path).unwrap() else {
         panic!("Not an item element");
@@ -416,3 +750,21 @@ fn on_package_selected(app: &mut AppData, path: &str, selected: &str) {
     };
     *value = Some(selected.to_string());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_checked_against_every_config_file() {
+        let mut overrides = Overrides::default();
+        overrides.push("system.Cargo.target", "riscv64gc-unknown-none-elf");
+        overrides.push("args", "[]");
+        overrides.push("baud_rate", "115200");
+        overrides.check(&config_schemas()).unwrap();
+
+        overrides.push("baudrate", "115200");
+        let err = overrides.check(&config_schemas()).unwrap_err();
+        assert!(err.to_string().ends_with("`baudrate`"), "{err}");
+    }
+}
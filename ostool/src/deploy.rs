@@ -0,0 +1,265 @@
+//! SD card deployment through an SD mux, `ostool deploy`.
+//!
+//! Boards whose SD card sits in an SDWire or a USB-SD-Mux get the card
+//! written without anyone touching it. The board is powered off, the card
+//! is switched to the host, the disk image is written and verified with
+//! [`crate::flash`], and the card is switched back to the board before it
+//! is powered on through its reset command.
+//!
+//! The mux is configured per board in `.boards.toml`:
+//!
+//! ```toml
+//! [[boards]]
+//! name = "rk3568-a"
+//! config = "boards/rk3568-a.uboot.toml"
+//!
+//! [boards.sd_mux]
+//! kind = "sdwire"
+//! id = "sdwire-17"
+//! device = "/dev/disk/by-id/usb-SAMSUNG_sdwire-17-0:0"
+//! ```
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use jkconfig::t;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ctx::AppContext,
+    disk::image::{build_image, load_disk_config},
+    flash::{self, FlashArgs},
+    run::{
+        boards::{BoardProfile, load_boards_config},
+        uboot::load_uboot_config,
+    },
+    utils::find_program,
+};
+
+/// SD card multiplexer of a board.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct SdMuxConfig {
+    pub kind: SdMuxKind,
+    /// SDWire serial number (`sd-mux-ctrl --device-serial`) or USB-SD-Mux
+    /// SCSI generic device, e.g. `/dev/sg0`
+    pub id: String,
+    /// Block device of the card on the host, preferably a stable
+    /// `/dev/disk/by-id` path
+    pub device: String,
+    /// Seconds to wait for the card to show up on the host, 15 when unset
+    pub timeout: Option<u64>,
+}
+
+/// Kind of SD card multiplexer.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SdMuxKind {
+    /// SDWire, switched with `sd-mux-ctrl`.
+    Sdwire,
+    /// Pengutronix USB-SD-Mux, switched with `usbsdmux`.
+    Usbsdmux,
+}
+
+/// Side the card is connected to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Host,
+    Board,
+}
+
+impl SdMuxConfig {
+    /// Program and arguments switching the card to `side`.
+    pub fn switch_cmd(&self, side: Side) -> (&'static str, Vec<String>) {
+        match self.kind {
+            SdMuxKind::Sdwire => (
+                "sd-mux-ctrl",
+                vec![
+                    format!("--device-serial={}", self.id),
+                    match side {
+                        Side::Host => "--ts",
+                        Side::Board => "--dut",
+                    }
+                    .to_string(),
+                ],
+            ),
+            SdMuxKind::Usbsdmux => (
+                "usbsdmux",
+                vec![
+                    self.id.clone(),
+                    match side {
+                        Side::Host => "host",
+                        Side::Board => "dut",
+                    }
+                    .to_string(),
+                ],
+            ),
+        }
+    }
+}
+
+/// Arguments for deploying to a board.
+#[derive(Debug, Clone, Default)]
+pub struct DeployArgs {
+    /// Board to deploy to; may be left out when only one board has an SD mux.
+    pub board: Option<String>,
+    /// Image to write; built from `.disk.toml` when unset.
+    pub image: Option<PathBuf>,
+    /// Optional path to the build configuration file.
+    pub build_config: Option<PathBuf>,
+    /// Optional path to the disk image configuration file.
+    pub disk_config: Option<PathBuf>,
+    /// Optional path to the boards file.
+    pub boards_config: Option<PathBuf>,
+    /// Skip reading back.
+    pub no_verify: bool,
+}
+
+/// Writes the image to the board's SD card and boots the board from it.
+///
+/// # Errors
+///
+/// Returns an error if the board has no SD mux, a tool is missing, the card
+/// does not show up, or writing fails. The card is switched back to the
+/// board even when writing fails.
+pub async fn deploy(mut ctx: AppContext, args: DeployArgs) -> anyhow::Result<()> {
+    let boards = load_boards_config(&ctx, args.boards_config.clone()).await?;
+    let board = pick(&boards.boards, args.board.as_deref())?;
+    let mux = board.sd_mux.clone().unwrap();
+    let (tool, _) = mux.switch_cmd(Side::Host);
+    if find_program(tool).is_none() {
+        bail!(t!("deploy.missing_tool", tool = tool));
+    }
+    let uboot = load_uboot_config(&ctx, Some(ctx.paths.config_file(&board.config))).await?;
+
+    let image = match &args.image {
+        Some(image) => image.clone(),
+        None => {
+            let config = load_disk_config(&ctx, args.disk_config.clone()).await?;
+            ctx.build_for_run(args.build_config.clone()).await?;
+            build_image(&mut ctx, &config).await?
+        }
+    };
+
+    println!("{}", t!("deploy.start", board = board.name).bold());
+    uboot.power_off_board(&ctx)?;
+    switch(&ctx, &mux, Side::Host)?;
+    let res = async {
+        let device = wait_for_device(&mux)?;
+        flash::flash(
+            ctx.clone(),
+            FlashArgs {
+                device,
+                image: Some(image),
+                yes: true,
+                no_verify: args.no_verify,
+                ..Default::default()
+            },
+        )
+        .await
+    }
+    .await;
+    // 写入失败也要把卡切回板子，免得下次启动找不到卡
+    switch(&ctx, &mux, Side::Board)?;
+    res?;
+
+    if uboot.can_reset() {
+        uboot.reset_board(&ctx)?;
+    } else {
+        warn!("{}", t!("deploy.no_reset", board = board.name));
+    }
+    println!("{}", t!("deploy.done", board = board.name).green().bold());
+    Ok(())
+}
+
+/// Picks the board named `name`, or the only board with an SD mux.
+fn pick<'a>(boards: &'a [BoardProfile], name: Option<&str>) -> anyhow::Result<&'a BoardProfile> {
+    let board = match name {
+        Some(name) => boards
+            .iter()
+            .find(|b| b.name == name)
+            .ok_or_else(|| anyhow!(t!("boards.unknown", name = name)))?,
+        None => {
+            let mut muxed = boards.iter().filter(|b| b.sd_mux.is_some());
+            match (muxed.next(), muxed.next()) {
+                (Some(board), None) => board,
+                (None, _) => bail!(t!("deploy.no_mux_boards")),
+                (Some(_), Some(_)) => bail!(t!("deploy.which_board")),
+            }
+        }
+    };
+    if board.sd_mux.is_none() {
+        bail!(t!("deploy.no_mux", board = board.name));
+    }
+    Ok(board)
+}
+
+fn switch(ctx: &AppContext, mux: &SdMuxConfig, side: Side) -> anyhow::Result<()> {
+    let (program, args) = mux.switch_cmd(side);
+    ctx.command(program).args(args).run()
+}
+
+/// Waits for the card to appear on the host and resolves its device node.
+fn wait_for_device(mux: &SdMuxConfig) -> anyhow::Result<PathBuf> {
+    let timeout = mux.timeout.unwrap_or(15);
+    let deadline = Instant::now() + Duration::from_secs(timeout);
+    let path = Path::new(&mux.device);
+    // by-id 等符号链接在卡枚举完成后才出现
+    while Instant::now() < deadline {
+        if let Ok(device) = path.canonicalize() {
+            return Ok(device);
+        }
+        std::thread::sleep(Duration::from_millis(500));
+    }
+    bail!(t!("deploy.no_card", device = mux.device, secs = timeout))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(name: &str, mux: bool) -> BoardProfile {
+        BoardProfile {
+            name: name.to_string(),
+            config: format!("boards/{name}.uboot.toml"),
+            sd_mux: mux.then(|| SdMuxConfig {
+                kind: SdMuxKind::Usbsdmux,
+                id: "/dev/sg0".to_string(),
+                device: "/dev/sda".to_string(),
+                timeout: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_switch_cmd() {
+        let mut mux = board("a", true).sd_mux.unwrap();
+        assert_eq!(
+            mux.switch_cmd(Side::Host),
+            ("usbsdmux", vec!["/dev/sg0".to_string(), "host".to_string()])
+        );
+        mux.kind = SdMuxKind::Sdwire;
+        mux.id = "sdwire-17".to_string();
+        assert_eq!(
+            mux.switch_cmd(Side::Board),
+            (
+                "sd-mux-ctrl",
+                vec!["--device-serial=sdwire-17".to_string(), "--dut".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn test_pick() {
+        let boards = [board("a", false), board("b", true)];
+        assert_eq!(pick(&boards, None).unwrap().name, "b");
+        assert_eq!(pick(&boards, Some("b")).unwrap().name, "b");
+        assert!(pick(&boards, Some("a")).is_err());
+        assert!(pick(&boards, Some("c")).is_err());
+        assert!(pick(&[board("a", true), board("b", true)], None).is_err());
+        assert!(pick(&[board("a", false)], None).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+//! GUID partition tables.
+//!
+//! Writes the protective MBR, the primary table at the start of the disk and
+//! the backup table at its end, with 512-byte sectors and the usual 128
+//! entries.
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use jkconfig::t;
+use uuid::{Uuid, uuid};
+
+use super::{SECTOR, allocate};
+
+/// EFI system partition, FAT.
+pub const EFI_SYSTEM: Uuid = uuid!("C12A7328-F81F-11D2-BA4B-00A0C93EC93B");
+/// Microsoft basic data, FAT or exFAT.
+pub const BASIC_DATA: Uuid = uuid!("EBD0A0A2-B9E5-4433-87C0-68B6B72699C7");
+/// Linux filesystem.
+pub const LINUX_FS: Uuid = uuid!("0FC63DAF-8483-4772-8E79-3D69D8477DE4");
+
+/// Legacy BIOS bootable, the flag U-Boot's distro boot looks for.
+pub const ATTR_LEGACY_BOOT: u64 = 1 << 2;
+
+const SIGNATURE: &[u8; 8] = b"EFI PART";
+const REVISION: u32 = 0x0001_0000;
+const HEADER_SIZE: u32 = 92;
+const ENTRIES: u32 = 128;
+const ENTRY_SIZE: u32 = 128;
+/// Sectors of the partition entry array.
+const ENTRY_SECTORS: u64 = (ENTRIES * ENTRY_SIZE) as u64 / SECTOR;
+
+/// A partition of the table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Partition {
+    pub name: String,
+    pub type_guid: Uuid,
+    pub guid: Uuid,
+    pub first_lba: u64,
+    /// Last sector, inclusive.
+    pub last_lba: u64,
+    pub attributes: u64,
+}
+
+impl Partition {
+    /// Byte offset on the disk.
+    pub fn offset(&self) -> u64 {
+        self.first_lba * SECTOR
+    }
+
+    /// Size in bytes.
+    pub fn size(&self) -> u64 {
+        (self.last_lba - self.first_lba + 1) * SECTOR
+    }
+}
+
+/// A partition table for a disk of `sectors` sectors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gpt {
+    pub disk_guid: Uuid,
+    pub sectors: u64,
+    pub partitions: Vec<Partition>,
+}
+
+impl Gpt {
+    /// An empty table for a disk of `size` bytes.
+    pub fn new(size: u64) -> Self {
+        Self {
+            disk_guid: Uuid::new_v4(),
+            sectors: size / SECTOR,
+            partitions: Vec::new(),
+        }
+    }
+
+    /// First sector partitions may use.
+    pub fn first_usable(&self) -> u64 {
+        2 + ENTRY_SECTORS
+    }
+
+    /// Last sector partitions may use, before the backup table.
+    pub fn last_usable(&self) -> u64 {
+        self.sectors.saturating_sub(2 + ENTRY_SECTORS)
+    }
+
+    /// Appends an aligned partition of `size` bytes, or of the rest of the
+    /// disk when `size` is `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the partition does not fit.
+    pub fn add(
+        &mut self,
+        name: &str,
+        type_guid: Uuid,
+        size: Option<u64>,
+    ) -> anyhow::Result<&mut Partition> {
+        let next = self
+            .partitions
+            .last()
+            .map_or(self.first_usable(), |p| p.last_lba + 1);
+        let Some((first_lba, last_lba)) = allocate(next, self.last_usable(), size) else {
+            bail!(t!("disk.no_space", name = name));
+        };
+        self.partitions.push(Partition {
+            name: name.to_string(),
+            type_guid,
+            guid: Uuid::new_v4(),
+            first_lba,
+            last_lba,
+            attributes: 0,
+        });
+        Ok(self.partitions.last_mut().unwrap())
+    }
+
+    /// Writes the protective MBR and both tables.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    pub fn write(&self, disk: &mut (impl Write + Seek)) -> io::Result<()> {
+        let entries = self.entries();
+        let entries_crc = crc32fast::hash(&entries);
+        let last = self.sectors - 1;
+        let backup_entries = last - ENTRY_SECTORS;
+
+        disk.seek(SeekFrom::Start(0))?;
+        disk.write_all(&self.protective_mbr())?;
+        disk.write_all(&self.header(1, last, 2, entries_crc))?;
+        disk.write_all(&entries)?;
+
+        disk.seek(SeekFrom::Start(backup_entries * SECTOR))?;
+        disk.write_all(&entries)?;
+        disk.write_all(&self.header(last, 1, backup_entries, entries_crc))?;
+        disk.flush()
+    }
+
+    /// Reads the primary table of a disk of `size` bytes, checking its CRCs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no valid table.
+    pub fn read(disk: &mut (impl Read + Seek), size: u64) -> anyhow::Result<Self> {
+        let mut header = [0u8; SECTOR as usize];
+        disk.seek(SeekFrom::Start(SECTOR))?;
+        disk.read_exact(&mut header)?;
+        if &header[..8] != SIGNATURE {
+            bail!(t!("disk.no_gpt"));
+        }
+        let mut check = header[..HEADER_SIZE as usize].to_vec();
+        check[16..20].fill(0);
+        if crc32fast::hash(&check) != u32_at(&header, 16) {
+            bail!(t!("disk.bad_crc", what = "header"));
+        }
+
+        let count = u32_at(&header, 80);
+        let entry_size = u32_at(&header, 84) as usize;
+        let mut entries = vec![0u8; count as usize * entry_size];
+        disk.seek(SeekFrom::Start(u64_at(&header, 72) * SECTOR))?;
+        disk.read_exact(&mut entries)?;
+        if crc32fast::hash(&entries) != u32_at(&header, 88) {
+            bail!(t!("disk.bad_crc", what = "entries"));
+        }
+
+        let partitions = entries
+            .chunks(entry_size)
+            .filter(|e| e[..16].iter().any(|&b| b != 0))
+            .map(|e| {
+                let name = e[56..128]
+                    .chunks(2)
+                    .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                    .take_while(|&c| c != 0)
+                    .collect::<Vec<_>>();
+                Partition {
+                    name: String::from_utf16_lossy(&name),
+                    type_guid: guid_at(e, 0),
+                    guid: guid_at(e, 16),
+                    first_lba: u64_at(e, 32),
+                    last_lba: u64_at(e, 40),
+                    attributes: u64_at(e, 48),
+                }
+            })
+            .collect();
+        Ok(Self {
+            disk_guid: guid_at(&header, 56),
+            sectors: size / SECTOR,
+            partitions,
+        })
+    }
+
+    fn protective_mbr(&self) -> [u8; SECTOR as usize] {
+        let mut mbr = [0u8; SECTOR as usize];
+        let entry = &mut mbr[446..462];
+        entry[1..4].copy_from_slice(&[0x00, 0x02, 0x00]);
+        entry[4] = 0xee;
+        entry[5..8].copy_from_slice(&[0xff, 0xff, 0xff]);
+        entry[8..12].copy_from_slice(&1u32.to_le_bytes());
+        let sectors = (self.sectors - 1).min(u32::MAX as u64) as u32;
+        entry[12..16].copy_from_slice(&sectors.to_le_bytes());
+        mbr[510] = 0x55;
+        mbr[511] = 0xaa;
+        mbr
+    }
+
+    fn header(
+        &self,
+        current: u64,
+        backup: u64,
+        entries_lba: u64,
+        entries_crc: u32,
+    ) -> [u8; SECTOR as usize] {
+        let mut h = [0u8; SECTOR as usize];
+        h[..8].copy_from_slice(SIGNATURE);
+        h[8..12].copy_from_slice(&REVISION.to_le_bytes());
+        h[12..16].copy_from_slice(&HEADER_SIZE.to_le_bytes());
+        h[24..32].copy_from_slice(&current.to_le_bytes());
+        h[32..40].copy_from_slice(&backup.to_le_bytes());
+        h[40..48].copy_from_slice(&self.first_usable().to_le_bytes());
+        h[48..56].copy_from_slice(&self.last_usable().to_le_bytes());
+        h[56..72].copy_from_slice(&self.disk_guid.to_bytes_le());
+        h[72..80].copy_from_slice(&entries_lba.to_le_bytes());
+        h[80..84].copy_from_slice(&ENTRIES.to_le_bytes());
+        h[84..88].copy_from_slice(&ENTRY_SIZE.to_le_bytes());
+        h[88..92].copy_from_slice(&entries_crc.to_le_bytes());
+        let crc = crc32fast::hash(&h[..HEADER_SIZE as usize]);
+        h[16..20].copy_from_slice(&crc.to_le_bytes());
+        h
+    }
+
+    fn entries(&self) -> Vec<u8> {
+        let mut entries = vec![0u8; (ENTRIES * ENTRY_SIZE) as usize];
+        for (p, e) in self
+            .partitions
+            .iter()
+            .zip(entries.chunks_mut(ENTRY_SIZE as usize))
+        {
+            e[..16].copy_from_slice(&p.type_guid.to_bytes_le());
+            e[16..32].copy_from_slice(&p.guid.to_bytes_le());
+            e[32..40].copy_from_slice(&p.first_lba.to_le_bytes());
+            e[40..48].copy_from_slice(&p.last_lba.to_le_bytes());
+            e[48..56].copy_from_slice(&p.attributes.to_le_bytes());
+            for (c, out) in p.name.encode_utf16().take(36).zip(e[56..].chunks_mut(2)) {
+                out.copy_from_slice(&c.to_le_bytes());
+            }
+        }
+        entries
+    }
+}
+
+fn u32_at(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(buf[at..at + 4].try_into().unwrap())
+}
+
+fn u64_at(buf: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(buf[at..at + 8].try_into().unwrap())
+}
+
+fn guid_at(buf: &[u8], at: usize) -> Uuid {
+    Uuid::from_bytes_le(buf[at..at + 16].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::disk::ALIGN;
+
+    #[test]
+    fn test_layout_and_roundtrip() {
+        let size = 64 * 1024 * 1024;
+        let mut gpt = Gpt::new(size);
+        gpt.add("boot", EFI_SYSTEM, Some(16 * 1024 * 1024))
+            .unwrap()
+            .attributes = ATTR_LEGACY_BOOT;
+        gpt.add("rootfs", LINUX_FS, None).unwrap();
+        assert_eq!(gpt.partitions[0].first_lba, 2048);
+        assert_eq!(gpt.partitions[0].size(), 16 * 1024 * 1024);
+        assert_eq!(gpt.partitions[1].first_lba, 2048 + 32768);
+        assert_eq!((gpt.partitions[1].last_lba + 1) % ALIGN, 0);
+        assert!(gpt.partitions[1].last_lba <= gpt.last_usable());
+        assert!(gpt.add("more", LINUX_FS, None).is_err());
+
+        let mut disk = Cursor::new(vec![0u8; size as usize]);
+        gpt.write(&mut disk).unwrap();
+        let bytes = disk.get_ref();
+        assert_eq!(&bytes[510..512], &[0x55, 0xaa]);
+        assert_eq!(bytes[446 + 4], 0xee);
+        let backup = size as usize - SECTOR as usize;
+        assert_eq!(&bytes[backup..backup + 8], SIGNATURE);
+
+        assert_eq!(Gpt::read(&mut disk, size).unwrap(), gpt);
+
+        disk.get_mut()[2 * SECTOR as usize] ^= 1;
+        assert!(Gpt::read(&mut disk, size).is_err());
+        assert!(Gpt::read(&mut Cursor::new(vec![0u8; 4096]), 4096).is_err());
+    }
+}
@@ -0,0 +1,309 @@
+//! Bootable disk images from build artifacts, `ostool mkimage`.
+//!
+//! The image is described by `.disk.toml` in the workspace:
+//!
+//! ```toml
+//! output = "target/disk.img"
+//! table = "gpt"
+//! boot_size = 64
+//! kernel_name = "Image"
+//! boot_files = ["board.dtb"]
+//! esp_dir = "esp"
+//! rootfs = "rootfs.tar.gz"
+//!
+//! [boot_script]
+//! commands = ["load virtio 0:1 ${kernel_addr_r} Image", "booti ${kernel_addr_r} - ${fdtcontroladdr}"]
+//! ```
+//!
+//! The boot partition gets the kernel, the boot files, the contents of
+//! `esp_dir` and the packaged boot script; the rootfs partition is only
+//! made when a rootfs tarball is given. With `disk_image = true` in
+//! `.qemu.toml` the image is rebuilt before every QEMU launch and attached
+//! as a virtio-blk drive.
+
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use colored::Colorize;
+use jkconfig::t;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{DEFAULT_BOOT_SIZE, Layout, PartitionTable};
+use crate::{ctx::AppContext, run::uboot::BootScript};
+
+const MIB: u64 = 1024 * 1024;
+
+/// Disk image settings, `.disk.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct DiskImageConfig {
+    /// Output file relative to the workspace, `target/disk.img` when unset
+    #[schemars(extend("format" = "file-path"))]
+    pub output: Option<String>,
+    #[serde(default)]
+    pub table: PartitionTable,
+    /// Image size in MiB, fitted to the contents when unset
+    pub size: Option<u64>,
+    /// Size of the FAT boot partition in MiB, 256 when unset
+    pub boot_size: Option<u64>,
+    /// File name of the kernel in the boot partition, that of the build
+    /// output when unset
+    pub kernel_name: Option<String>,
+    /// Files copied to the root of the boot partition, e.g. DTBs
+    #[serde(default)]
+    pub boot_files: Vec<String>,
+    /// Directory whose contents are copied to the boot partition, e.g. an
+    /// ESP tree with `EFI/BOOT/BOOTAA64.EFI`
+    #[schemars(extend("format" = "dir-path"))]
+    pub esp_dir: Option<String>,
+    /// Rootfs tarball (`.tar`, `.tar.gz`, `.tar.xz`) for an ext4 partition
+    /// after the boot partition
+    #[schemars(extend("format" = "file-path"))]
+    pub rootfs: Option<String>,
+    /// Boot script packaged into the boot partition
+    pub boot_script: Option<BootScript>,
+}
+
+/// Arguments for building a disk image.
+#[derive(Debug, Clone, Default)]
+pub struct MkimageArgs {
+    /// Optional path to the build configuration file.
+    pub build_config: Option<PathBuf>,
+    /// Optional path to the disk image configuration file.
+    pub disk_config: Option<PathBuf>,
+    /// Output file, overriding the config.
+    pub output: Option<PathBuf>,
+}
+
+/// Builds the kernel and assembles the disk image.
+///
+/// # Errors
+///
+/// Returns an error if the configuration is missing or invalid, the build
+/// fails, or the image cannot be assembled.
+pub async fn mkimage(mut ctx: AppContext, args: MkimageArgs) -> anyhow::Result<PathBuf> {
+    let mut config = load_disk_config(&ctx, args.disk_config.clone()).await?;
+    if let Some(output) = &args.output {
+        config.output = Some(output.display().to_string());
+    }
+    ctx.build_for_run(args.build_config.clone()).await?;
+    build_image(&mut ctx, &config).await
+}
+
+/// Loads the disk image configuration, by default `.disk.toml` in the
+/// workspace.
+///
+/// # Errors
+///
+/// Returns an error if the file is missing or invalid.
+pub async fn load_disk_config(
+    ctx: &AppContext,
+    path: Option<PathBuf>,
+) -> anyhow::Result<DiskImageConfig> {
+    let path = path.unwrap_or_else(|| ctx.paths.config_file(".disk.toml"));
+    if !path.exists() {
+        bail!(t!("disk.no_config", path = path.display()));
+    }
+    let content = tokio::fs::read_to_string(&path).await?;
+    ctx.parse_config(&path, &content)
+}
+
+/// Assembles the image from the kernel already built into `ctx`.
+///
+/// # Errors
+///
+/// Returns an error if an input is missing, a host tool fails, or the
+/// contents do not fit.
+pub async fn build_image(
+    ctx: &mut AppContext,
+    config: &DiskImageConfig,
+) -> anyhow::Result<PathBuf> {
+    let workspace = ctx.paths.workspace.clone();
+    let kernel = ctx
+        .paths
+        .artifacts
+        .bin
+        .clone()
+        .or_else(|| ctx.paths.artifacts.elf.clone())
+        .ok_or_else(|| anyhow!("elf not exist"))?;
+    let output = workspace.join(config.output.as_deref().unwrap_or("target/disk.img"));
+
+    let staging = tempfile::Builder::new().prefix("ostool-disk-").tempdir()?;
+    let boot = staging.path().join("boot");
+    std::fs::create_dir_all(&boot)?;
+    let res = async {
+        // 展开 rootfs 最耗时，与收集启动分区内容同时进行
+        let rootfs = config.rootfs.as_ref().map(|tarball| {
+            let tarball = workspace.join(tarball);
+            let dir = staging.path().join("rootfs");
+            tokio::task::spawn_blocking(move || unpack(&tarball, &dir).map(|()| dir))
+        });
+
+        // 先把启动分区的内容收集到一个目录，便于重命名内核和展开 ESP
+        let boot_files = async {
+            let kernel_name = match &config.kernel_name {
+                Some(name) => name.clone(),
+                None => file_name(&kernel)?,
+            };
+            std::fs::copy(&kernel, boot.join(kernel_name))?;
+            for file in &config.boot_files {
+                let path = workspace.join(file);
+                std::fs::copy(&path, boot.join(file_name(&path)?))
+                    .with_context(|| format!("Failed to copy {}", path.display()))?;
+            }
+            if let Some(script) = &config.boot_script {
+                std::fs::write(boot.join(script.file_name()), script.build(ctx).await?)?;
+            }
+            let mut boot_files = std::fs::read_dir(&boot)?
+                .map(|e| e.map(|e| e.path()))
+                .collect::<Result<Vec<_>, _>>()?;
+            if let Some(esp) = &config.esp_dir {
+                let esp = workspace.join(esp);
+                for entry in std::fs::read_dir(&esp)
+                    .with_context(|| format!("Failed to read {}", esp.display()))?
+                {
+                    boot_files.push(entry?.path());
+                }
+            }
+            boot_files.sort();
+            anyhow::Ok(boot_files)
+        }
+        .await;
+
+        // 出错时也要等展开结束，之后才能删除临时目录
+        let rootfs_dir = match rootfs {
+            Some(task) => Some(task.await??),
+            None => None,
+        };
+        let boot_files = boot_files?;
+
+        let boot_size = config.boot_size.unwrap_or(DEFAULT_BOOT_SIZE);
+        let size = match config.size {
+            Some(size) => size * MIB,
+            None => fit_size(boot_size, rootfs_dir.as_deref().map(dir_size)),
+        };
+        if let Some(parent) = output.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // 稀疏文件，未写入的部分不占磁盘空间
+        let file = File::create(&output)?;
+        file.set_len(size)?;
+        drop(file);
+
+        let layout = Layout {
+            table: config.table,
+            boot_size,
+            boot_files,
+            rootfs: rootfs_dir.is_some(),
+            rootfs_dir,
+        };
+        super::partition(ctx, &output, size, &layout)?;
+        anyhow::Ok(())
+    }
+    .await;
+    drop(staging);
+    res?;
+
+    println!(
+        "{}",
+        t!("disk.image_ok", path = output.display()).green().bold()
+    );
+    ctx.paths.artifacts.disk_image = Some(output.clone());
+    Ok(output)
+}
+
+/// Image size for the boot partition and a rootfs of `rootfs` bytes, with
+/// room for the tables and the filesystem overhead.
+fn fit_size(boot_size: u64, rootfs: Option<u64>) -> u64 {
+    let rootfs = rootfs.map_or(0, |bytes| {
+        // ext4 的元数据和日志约占两成，再留些余量
+        (bytes + bytes / 4).div_ceil(MIB).max(32) + 16
+    });
+    (boot_size + rootfs + 2) * MIB
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|e| match e.file_type() {
+            Ok(t) if t.is_dir() => 4096 + dir_size(&e.path()),
+            _ => 4096 + e.metadata().map_or(0, |m| m.len()),
+        })
+        .sum()
+}
+
+/// Unpacks a tarball, plain or compressed with gzip or xz, into `dir`.
+fn unpack(tarball: &Path, dir: &Path) -> anyhow::Result<()> {
+    let file =
+        File::open(tarball).with_context(|| format!("Failed to open {}", tarball.display()))?;
+    let name = tarball.to_string_lossy();
+    let reader: Box<dyn Read> = if name.ends_with(".gz") || name.ends_with(".tgz") {
+        Box::new(flate2::read::GzDecoder::new(file))
+    } else if name.ends_with(".xz") {
+        let mut data = Vec::new();
+        lzma_rs::xz_decompress(&mut BufReader::new(file), &mut data)
+            .map_err(|e| anyhow!("Failed to decompress {}: {e}", tarball.display()))?;
+        Box::new(std::io::Cursor::new(data))
+    } else {
+        Box::new(file)
+    };
+    std::fs::create_dir_all(dir)?;
+    let mut archive = tar::Archive::new(reader);
+    archive.set_preserve_permissions(true);
+    archive
+        .unpack(dir)
+        .with_context(|| format!("Failed to unpack {}", tarball.display()))
+}
+
+fn file_name(path: &Path) -> anyhow::Result<String> {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| anyhow!("Invalid file path: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_size() {
+        assert_eq!(fit_size(64, None), 66 * MIB);
+        // 小 rootfs 至少 32 MiB
+        assert_eq!(fit_size(64, Some(MIB)), (64 + 48 + 2) * MIB);
+        assert_eq!(fit_size(64, Some(400 * MIB)), (64 + 500 + 16 + 2) * MIB);
+    }
+
+    #[test]
+    fn test_unpack_gzip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        let tarball = dir.join("rootfs.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            File::create(&tarball).unwrap(),
+            flate2::Compression::fast(),
+        ));
+        let data = b"hello";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "etc/motd", &data[..])
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        unpack(&tarball, &dir.join("rootfs")).unwrap();
+        assert_eq!(
+            std::fs::read(dir.join("rootfs/etc/motd")).unwrap(),
+            b"hello"
+        );
+        assert!(dir_size(&dir.join("rootfs")) >= 5);
+    }
+}
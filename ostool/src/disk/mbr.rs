@@ -0,0 +1,164 @@
+//! MBR (DOS) partition tables, for boot ROMs and firmware without GPT
+//! support.
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use jkconfig::t;
+
+use super::{SECTOR, allocate};
+
+/// FAT32 with LBA addressing.
+pub const FAT32_LBA: u8 = 0x0c;
+/// Linux native.
+pub const LINUX: u8 = 0x83;
+
+/// A primary partition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Partition {
+    pub bootable: bool,
+    pub kind: u8,
+    pub first_lba: u32,
+    pub sectors: u32,
+}
+
+impl Partition {
+    /// Byte offset on the disk.
+    pub fn offset(&self) -> u64 {
+        self.first_lba as u64 * SECTOR
+    }
+
+    /// Size in bytes.
+    pub fn size(&self) -> u64 {
+        self.sectors as u64 * SECTOR
+    }
+}
+
+/// A partition table for a disk of `sectors` sectors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mbr {
+    pub disk_id: u32,
+    pub sectors: u64,
+    pub partitions: Vec<Partition>,
+}
+
+impl Mbr {
+    /// An empty table for a disk of `size` bytes.
+    pub fn new(size: u64) -> Self {
+        Self {
+            disk_id: uuid::Uuid::new_v4().as_fields().0,
+            sectors: size / SECTOR,
+            partitions: Vec::new(),
+        }
+    }
+
+    /// Appends an aligned partition of `size` bytes, or of the rest of the
+    /// disk when `size` is `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the partition does not fit or the table is full.
+    pub fn add(
+        &mut self,
+        name: &str,
+        kind: u8,
+        size: Option<u64>,
+    ) -> anyhow::Result<&mut Partition> {
+        let next = self
+            .partitions
+            .last()
+            .map_or(1, |p| p.first_lba as u64 + p.sectors as u64);
+        // 扇区号只有 32 位
+        let last = (self.sectors - 1).min(u32::MAX as u64);
+        let Some((first_lba, last_lba)) =
+            allocate(next, last, size).filter(|_| self.partitions.len() < 4)
+        else {
+            bail!(t!("disk.no_space", name = name));
+        };
+        self.partitions.push(Partition {
+            bootable: false,
+            kind,
+            first_lba: first_lba as u32,
+            sectors: (last_lba - first_lba + 1) as u32,
+        });
+        Ok(self.partitions.last_mut().unwrap())
+    }
+
+    /// Writes the table to the first sector.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    pub fn write(&self, disk: &mut (impl Write + Seek)) -> io::Result<()> {
+        let mut mbr = [0u8; SECTOR as usize];
+        mbr[440..444].copy_from_slice(&self.disk_id.to_le_bytes());
+        for (p, e) in self.partitions.iter().zip(mbr[446..510].chunks_mut(16)) {
+            e[0] = if p.bootable { 0x80 } else { 0 };
+            // 只用 LBA，CHS 填最大值
+            e[1..4].copy_from_slice(&[0xfe, 0xff, 0xff]);
+            e[4] = p.kind;
+            e[5..8].copy_from_slice(&[0xfe, 0xff, 0xff]);
+            e[8..12].copy_from_slice(&p.first_lba.to_le_bytes());
+            e[12..16].copy_from_slice(&p.sectors.to_le_bytes());
+        }
+        mbr[510] = 0x55;
+        mbr[511] = 0xaa;
+        disk.seek(SeekFrom::Start(0))?;
+        disk.write_all(&mbr)?;
+        disk.flush()
+    }
+
+    /// Reads the table of a disk of `size` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no valid table, or it is a GPT's
+    /// protective MBR.
+    pub fn read(disk: &mut (impl Read + Seek), size: u64) -> anyhow::Result<Self> {
+        let mut mbr = [0u8; SECTOR as usize];
+        disk.seek(SeekFrom::Start(0))?;
+        disk.read_exact(&mut mbr)?;
+        if mbr[510..512] != [0x55, 0xaa] || mbr[446 + 4] == 0xee {
+            bail!(t!("disk.no_mbr"));
+        }
+        let partitions = mbr[446..510]
+            .chunks(16)
+            .filter(|e| e[4] != 0)
+            .map(|e| Partition {
+                bootable: e[0] == 0x80,
+                kind: e[4],
+                first_lba: u32::from_le_bytes(e[8..12].try_into().unwrap()),
+                sectors: u32::from_le_bytes(e[12..16].try_into().unwrap()),
+            })
+            .collect();
+        Ok(Self {
+            disk_id: u32::from_le_bytes(mbr[440..444].try_into().unwrap()),
+            sectors: size / SECTOR,
+            partitions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_layout_and_roundtrip() {
+        let size = 64 * 1024 * 1024;
+        let mut mbr = Mbr::new(size);
+        mbr.add("boot", FAT32_LBA, Some(16 * 1024 * 1024))
+            .unwrap()
+            .bootable = true;
+        mbr.add("rootfs", LINUX, None).unwrap();
+        assert_eq!(mbr.partitions[0].first_lba, 2048);
+        assert_eq!(mbr.partitions[1].offset(), 17 * 1024 * 1024);
+        assert_eq!(mbr.partitions[1].size(), 47 * 1024 * 1024);
+
+        let mut disk = Cursor::new(vec![0u8; 4096]);
+        mbr.write(&mut disk).unwrap();
+        assert_eq!(Mbr::read(&mut disk, size).unwrap(), mbr);
+        assert!(Mbr::read(&mut Cursor::new(vec![0u8; 4096]), size).is_err());
+    }
+}
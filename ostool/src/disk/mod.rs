@@ -0,0 +1,207 @@
+//! Boot media layouts: a partition table with a FAT boot partition and an
+//! optional ext4 rootfs.
+//!
+//! The table is written by ostool itself; the filesystems are made with the
+//! host's `mkfs.vfat` (dosfstools), `mkfs.ext4` (e2fsprogs) and `mcopy`
+//! (mtools), all pointed at the partition's offset on the whole disk, so the
+//! same code works on a device and on an image file without partition
+//! device nodes or root-only loop mounts.
+
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use jkconfig::t;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{ctx::AppContext, utils::find_program};
+
+/// GUID partition tables.
+pub mod gpt;
+
+/// Bootable disk images from build artifacts, `ostool mkimage`.
+pub mod image;
+
+/// MBR partition tables.
+pub mod mbr;
+
+use gpt::Gpt;
+use mbr::Mbr;
+
+/// Sector size of the partition tables.
+pub const SECTOR: u64 = 512;
+/// Partitions start on 1 MiB boundaries.
+pub const ALIGN: u64 = 2048;
+/// Default size of the boot partition, in MiB.
+pub const DEFAULT_BOOT_SIZE: u64 = 256;
+
+/// Kind of partition table.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PartitionTable {
+    #[default]
+    Gpt,
+    /// For boot ROMs and firmware that only read MBR.
+    Mbr,
+}
+
+/// What goes on a partitioned disk.
+#[derive(Debug, Clone, Default)]
+pub struct Layout {
+    pub table: PartitionTable,
+    /// Size of the FAT boot partition in MiB.
+    pub boot_size: u64,
+    /// Files and directories copied to the root of the boot partition.
+    pub boot_files: Vec<PathBuf>,
+    /// Make an ext4 rootfs of the rest of the disk.
+    pub rootfs: bool,
+    /// Directory the rootfs is populated from.
+    pub rootfs_dir: Option<PathBuf>,
+}
+
+/// Where a partition is on the disk, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub offset: u64,
+    pub size: u64,
+}
+
+/// Partitions the disk at `path`, `size` bytes, and makes the filesystems.
+///
+/// Returns the boot partition and the rootfs, if any.
+///
+/// # Errors
+///
+/// Returns an error if a host tool is missing or fails, or the disk is too
+/// small for the layout.
+pub fn partition(
+    ctx: &AppContext,
+    path: &Path,
+    size: u64,
+    layout: &Layout,
+) -> anyhow::Result<Vec<Region>> {
+    require("mkfs.vfat", "dosfstools")?;
+    if layout.rootfs {
+        require("mkfs.ext4", "e2fsprogs")?;
+    }
+    if !layout.boot_files.is_empty() {
+        require("mcopy", "mtools")?;
+    }
+
+    let boot_size = Some(layout.boot_size * 1024 * 1024);
+    let rest = layout.rootfs.then_some(None);
+    let mut disk = File::options().read(true).write(true).open(path)?;
+    let regions = match layout.table {
+        PartitionTable::Gpt => {
+            let mut gpt = Gpt::new(size);
+            let boot = gpt.add("boot", gpt::EFI_SYSTEM, boot_size)?;
+            boot.attributes = gpt::ATTR_LEGACY_BOOT;
+            if let Some(rest) = rest {
+                gpt.add("rootfs", gpt::LINUX_FS, rest)?;
+            }
+            gpt.write(&mut disk)?;
+            gpt_regions(&gpt)
+        }
+        PartitionTable::Mbr => {
+            let mut mbr = Mbr::new(size);
+            mbr.add("boot", mbr::FAT32_LBA, boot_size)?.bootable = true;
+            if let Some(rest) = rest {
+                mbr.add("rootfs", mbr::LINUX, rest)?;
+            }
+            mbr.write(&mut disk)?;
+            mbr_regions(&mbr)
+        }
+    };
+    disk.sync_all()?;
+    drop(disk);
+
+    let disk = path.display().to_string();
+    let boot = regions[0];
+    ctx.command("mkfs.vfat")
+        .args(["-I", "-F", "32", "-n", "BOOT", "--offset"])
+        .arg((boot.offset / SECTOR).to_string())
+        .arg(&disk)
+        .arg((boot.size / 1024).to_string())
+        .run()?;
+    if let Some(rootfs) = regions.get(1) {
+        let mut cmd = ctx.command("mkfs.ext4");
+        cmd.args(["-F", "-q", "-L", "rootfs", "-E"])
+            .arg(format!("offset={}", rootfs.offset));
+        if let Some(dir) = &layout.rootfs_dir {
+            cmd.arg("-d").arg(dir);
+        }
+        cmd.arg(&disk)
+            .arg(format!("{}k", rootfs.size / 1024))
+            .run()?;
+    }
+
+    for file in &layout.boot_files {
+        let name = file
+            .file_name()
+            .ok_or_else(|| anyhow!("Invalid file path: {}", file.display()))?;
+        // 分区偏移处的 FAT 几何参数与整盘不符，需跳过 mtools 的检查
+        ctx.command("mcopy")
+            .env("MTOOLS_SKIP_CHECK", "1")
+            .args(["-s", "-o", "-i"])
+            .arg(format!("{disk}@@{}", boot.offset))
+            .arg(file)
+            .arg(format!("::/{}", name.to_string_lossy()))
+            .run()?;
+    }
+    Ok(regions)
+}
+
+/// Reads the partitions of a disk of `size` bytes back, from a GPT or MBR.
+///
+/// # Errors
+///
+/// Returns an error if there is no valid partition table.
+pub fn read_regions(path: &Path, size: u64) -> anyhow::Result<Vec<Region>> {
+    let mut disk = File::open(path)?;
+    match Gpt::read(&mut disk, size) {
+        Ok(gpt) => Ok(gpt_regions(&gpt)),
+        Err(_) => Ok(mbr_regions(&Mbr::read(&mut disk, size)?)),
+    }
+}
+
+fn gpt_regions(gpt: &Gpt) -> Vec<Region> {
+    gpt.partitions
+        .iter()
+        .map(|p| Region {
+            offset: p.offset(),
+            size: p.size(),
+        })
+        .collect()
+}
+
+fn mbr_regions(mbr: &Mbr) -> Vec<Region> {
+    mbr.partitions
+        .iter()
+        .map(|p| Region {
+            offset: p.offset(),
+            size: p.size(),
+        })
+        .collect()
+}
+
+/// First and last sector of a partition of `size` bytes starting at or
+/// after `next`, or of the rest up to `last` when `size` is `None`; `None`
+/// if it does not fit.
+fn allocate(next: u64, last: u64, size: Option<u64>) -> Option<(u64, u64)> {
+    let first = next.div_ceil(ALIGN) * ALIGN;
+    let end = match size {
+        Some(size) => first + size.div_ceil(SECTOR) - 1,
+        // 末尾向下对齐，免得最后一个分区的大小不是整 MiB
+        None => ((last + 1) / ALIGN * ALIGN).checked_sub(1)?,
+    };
+    (first <= end && end <= last).then_some((first, end))
+}
+
+fn require(tool: &str, package: &str) -> anyhow::Result<()> {
+    if find_program(tool).is_none() {
+        bail!(t!("disk.missing_tool", tool = tool, package = package));
+    }
+    Ok(())
+}
@@ -0,0 +1,320 @@
+//! Exit codes and cleanup on Ctrl+C.
+//!
+//! `ostool` and `cargo-osrun` exit with:
+//!
+//! | Code | Meaning |
+//! |------|---------|
+//! | 0    | Success |
+//! | 1    | Any other error, e.g. a bad configuration or a missing tool |
+//! | 2    | Invalid command-line arguments |
+//! | 3    | [`Failure::Build`]: the build failed |
+//! | 4    | [`Failure::Boot`]: the kernel did not boot or its run failed |
+//! | 5    | [`Failure::Test`]: tests or checks failed |
+//! | 130  | [`Failure::Aborted`]: Ctrl+C, `SIGTERM` or `SIGHUP` |
+//!
+//! Errors carry their kind as a [`Failure`] somewhere in their chain, see
+//! [`code`]. On Ctrl+C the hooks registered with [`on_abort`] run, newest
+//! first, e.g. to ask QEMU to quit over QMP, then the terminal is restored
+//! and the process exits with 130. Serial ports, sockets and the TFTP
+//! server thread go with the process. A second Ctrl+C exits at once.
+//!
+//! While a child process that handles Ctrl+C itself is in the foreground,
+//! e.g. GDB or the runner `cargo run` starts, [`pass_through`] leaves it to
+//! the child and only records it, see [`interrupted`].
+//!
+//! Programs embedding ostool as a library call [`embed`] first: signals are
+//! then theirs to handle and ostool never exits the process, running the
+//! abort hooks only when the program calls [`cleanup`].
+
+use std::{
+    fmt,
+    process::ExitStatus,
+    sync::{
+        Mutex, Once,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    },
+};
+
+use colored::Colorize;
+use jkconfig::t;
+
+/// Exit code of errors without a [`Failure`] kind.
+pub const ERROR: i32 = 1;
+
+/// Kind of a failed run, deciding the exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// The build failed.
+    Build,
+    /// The kernel did not boot or its run failed: a fail pattern matched,
+    /// the runner exited with an error or a timeout passed.
+    Boot,
+    /// Tests or checks, such as a boot-time regression, failed.
+    Test,
+    /// The user pressed Ctrl+C or the process was told to terminate.
+    Aborted,
+}
+
+impl Failure {
+    /// Exit code of the kind.
+    pub const fn code(self) -> i32 {
+        match self {
+            Failure::Build => 3,
+            Failure::Boot => 4,
+            Failure::Test => 5,
+            Failure::Aborted => 130,
+        }
+    }
+
+    /// The kind an exit code stands for.
+    pub fn from_code(code: i32) -> Option<Self> {
+        [Self::Build, Self::Boot, Self::Test, Self::Aborted]
+            .into_iter()
+            .find(|f| f.code() == code)
+    }
+
+    /// The kind of a child `ostool` or `cargo-osrun` exiting with `status`;
+    /// being killed by `SIGINT`, `SIGTERM` or `SIGHUP` counts as aborted.
+    pub fn from_status(status: ExitStatus) -> Option<Self> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            if matches!(status.signal(), Some(1 | 2 | 15)) {
+                return Some(Self::Aborted);
+            }
+        }
+        status.code().and_then(Self::from_code)
+    }
+
+    /// An error of this kind with `msg` as its message.
+    pub fn error(self, msg: impl fmt::Display + Send + Sync + 'static) -> anyhow::Error {
+        anyhow::Error::new(self).context(msg)
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            Failure::Build => t!("exit.build"),
+            Failure::Boot => t!("exit.boot"),
+            Failure::Test => t!("exit.test"),
+            Failure::Aborted => t!("exit.aborted"),
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// Exit code for `err`.
+pub fn code(err: &anyhow::Error) -> i32 {
+    err.downcast_ref::<Failure>()
+        .map(|f| f.code())
+        .unwrap_or(ERROR)
+}
+
+/// Prints the error of `result`, like returning it from `main` would, and
+/// exits with its code.
+pub fn finish(result: anyhow::Result<()>) -> ! {
+    let code = match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            code(&e)
+        }
+    };
+    std::process::exit(code)
+}
+
+type Hook = Box<dyn FnOnce() + Send>;
+
+static HOOKS: Mutex<Vec<(u64, Hook)>> = Mutex::new(Vec::new());
+static NEXT_HOOK: AtomicU64 = AtomicU64::new(0);
+static PASS_THROUGH: AtomicUsize = AtomicUsize::new(0);
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static ABORTING: AtomicBool = AtomicBool::new(false);
+static EMBEDDED: AtomicBool = AtomicBool::new(false);
+static INSTALLED: Once = Once::new();
+
+/// Installs the signal handler and a panic hook restoring the terminal.
+///
+/// Does nothing outside a Tokio runtime, when already installed or after
+/// [`embed`]; [`on_abort`] and [`pass_through`] install it as well.
+pub fn install() {
+    if EMBEDDED.load(Ordering::Acquire) {
+        return;
+    }
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        return;
+    };
+    INSTALLED.call_once(|| {
+        runtime.spawn(listen());
+        let prev = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            prev(info);
+        }));
+    });
+}
+
+async fn listen() {
+    loop {
+        let interactive = wait_signal().await;
+        if ABORTING.load(Ordering::Acquire) {
+            std::process::exit(Failure::Aborted.code());
+        }
+        INTERRUPTED.store(true, Ordering::Release);
+        if interactive && PASS_THROUGH.load(Ordering::Acquire) > 0 {
+            continue;
+        }
+        // 在线程中清理，清理卡住时再按一次 Ctrl+C 仍能退出
+        std::thread::spawn(|| abort());
+    }
+}
+
+/// Waits for a signal; `true` for Ctrl+C, `false` for `SIGTERM`/`SIGHUP`.
+async fn wait_signal() -> bool {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        if let (Ok(mut term), Ok(mut hup)) = (
+            signal(SignalKind::terminate()),
+            signal(SignalKind::hangup()),
+        ) {
+            return tokio::select! {
+                _ = tokio::signal::ctrl_c() => true,
+                _ = term.recv() => false,
+                _ = hup.recv() => false,
+            };
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+    true
+}
+
+/// Leaves signals to the program embedding ostool, so that no signal
+/// handler or panic hook is installed and the process is never exited.
+///
+/// Has no effect once [`install`] ran.
+pub fn embed() {
+    EMBEDDED.store(true, Ordering::Release);
+}
+
+/// Runs the abort hooks, newest first, and restores the terminal, e.g.
+/// from the Ctrl+C handler of a program that called [`embed`].
+pub fn cleanup() {
+    let hooks = std::mem::take(&mut *HOOKS.lock().unwrap_or_else(|e| e.into_inner()));
+    for (_, hook) in hooks.into_iter().rev() {
+        hook();
+    }
+    restore_terminal();
+}
+
+/// Runs the abort hooks, restores the terminal and exits with 130.
+pub fn abort() -> ! {
+    ABORTING.store(true, Ordering::Release);
+    cleanup();
+    eprintln!("\r\n{}", t!("exit.aborted").yellow());
+    std::process::exit(Failure::Aborted.code())
+}
+
+/// Whether Ctrl+C was pressed, also while passed through to a child.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Acquire)
+}
+
+/// Leaves the terminal in raw mode no longer, including modes a child such
+/// as QEMU set and did not restore.
+pub fn restore_terminal() {
+    let _ = crossterm::terminal::disable_raw_mode();
+    // 使用 stty 命令恢复终端回显 (最可靠的方法)
+    #[cfg(unix)]
+    if std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+        use std::process::Stdio;
+        let _ = std::process::Command::new("stty")
+            .arg("echo")
+            .arg("icanon")
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+/// Runs `hook` on abort until the returned guard is dropped.
+#[must_use = "the hook is removed when the guard is dropped"]
+pub fn on_abort(hook: impl FnOnce() + Send + 'static) -> AbortHook {
+    install();
+    let id = NEXT_HOOK.fetch_add(1, Ordering::Relaxed);
+    HOOKS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push((id, Box::new(hook)));
+    AbortHook(id)
+}
+
+/// Guard of a hook registered with [`on_abort`].
+pub struct AbortHook(u64);
+
+impl Drop for AbortHook {
+    fn drop(&mut self) {
+        HOOKS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|(id, _)| *id != self.0);
+    }
+}
+
+/// Leaves Ctrl+C to the foreground child until the returned guard is
+/// dropped.
+#[must_use = "Ctrl+C aborts again when the guard is dropped"]
+pub fn pass_through() -> PassThrough {
+    install();
+    INTERRUPTED.store(false, Ordering::Release);
+    PASS_THROUGH.fetch_add(1, Ordering::AcqRel);
+    PassThrough(())
+}
+
+/// Guard returned by [`pass_through`].
+pub struct PassThrough(());
+
+impl Drop for PassThrough {
+    fn drop(&mut self) {
+        PASS_THROUGH.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes() {
+        for f in [
+            Failure::Build,
+            Failure::Boot,
+            Failure::Test,
+            Failure::Aborted,
+        ] {
+            assert_eq!(Failure::from_code(f.code()), Some(f));
+        }
+        assert_eq!(Failure::from_code(ERROR), None);
+
+        let err = Failure::Boot.error("Fail pattern matched: panic");
+        assert_eq!(err.to_string(), "Fail pattern matched: panic");
+        assert_eq!(code(&err.context("board a")), 4);
+
+        let err: anyhow::Error = anyhow::anyhow!("failed with status: 101");
+        assert_eq!(code(&err.context(Failure::Build)), 3);
+        assert_eq!(code(&anyhow::anyhow!("no config")), ERROR);
+    }
+
+    #[test]
+    fn test_hooks() {
+        let hook = on_abort(|| {});
+        let id = hook.0;
+        assert!(HOOKS.lock().unwrap().iter().any(|(i, _)| *i == id));
+        drop(hook);
+        assert!(!HOOKS.lock().unwrap().iter().any(|(i, _)| *i == id));
+    }
+}
@@ -0,0 +1,199 @@
+//! FIT image utilities.
+//!
+//! Backs the `ostool fit` subcommands, which build images from `.its`
+//! sources, including SPL `u-boot.itb` style firmware images, and audit
+//! images already flashed to or loaded by a board using the same crate that
+//! created them.
+
+use std::path::Path;
+
+use colored::Colorize;
+use fitimage::{
+    FitImageBuilder, FitImageConfig, FitImageReader,
+    fit::{FdtNode, FitChange, FitDiff, HashStatus},
+};
+
+/// Builds a FIT image from an `.its` source, like `mkimage -f`.
+///
+/// Firmware images for SPL, with `firmware` and `loadables` in their
+/// configurations, are built the same way as kernel images.
+///
+/// # Errors
+///
+/// Returns an error if the source cannot be parsed, a referenced file
+/// cannot be read, or the image cannot be written.
+pub fn build(its: &Path, output: &Path) -> anyhow::Result<()> {
+    let config = FitImageConfig::from_its_file(its)?;
+    let data = FitImageBuilder::new().build(config)?;
+    std::fs::write(output, &data)?;
+    info!("Built {} ({} bytes)", output.display(), data.len());
+    Ok(())
+}
+
+/// Prints the description, images and configurations of a FIT image.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or is not a valid FIT image.
+pub fn inspect(path: &Path) -> anyhow::Result<()> {
+    let reader = FitImageReader::open(path)?;
+
+    println!("{} {}", "FIT image:".bold(), path.display());
+    println!(
+        "  Description: {}",
+        reader.description().unwrap_or("<none>")
+    );
+    if let Some(ts) = reader.root().property_u64("timestamp") {
+        println!("  Timestamp:   {ts}");
+    }
+    println!("  Total size:  {} bytes", reader.header().totalsize);
+
+    println!("{}", "Images:".bold());
+    for image in reader.images() {
+        let size = reader
+            .image_data(&image.name)
+            .map(|d| d.len().to_string())
+            .unwrap_or_else(|_| "?".into());
+        println!("  {} ({size} bytes)", image.name.green());
+        print_image_props(image);
+    }
+
+    println!("{}", "Configurations:".bold());
+    let default = reader.default_config();
+    for conf in reader.configurations() {
+        let mark = if Some(conf.name.as_str()) == default {
+            " (default)"
+        } else {
+            ""
+        };
+        println!("  {}{mark}", conf.name.green());
+        for prop in &conf.properties {
+            let refs = conf.property_strings(&prop.name).join(", ");
+            println!("    {:<12} {refs}", prop.name);
+        }
+        print_signatures(conf);
+    }
+    Ok(())
+}
+
+fn print_image_props(image: &FdtNode) {
+    for key in ["description", "type", "arch", "os", "compression"] {
+        if let Some(value) = image.property_str(key) {
+            println!("    {key:<12} {value}");
+        }
+    }
+    for key in ["load", "entry"] {
+        if let Some(value) = image.property_u64(key) {
+            println!("    {key:<12} {value:#x}");
+        }
+    }
+    for hash in image.children.iter().filter(|c| c.name.starts_with("hash")) {
+        println!(
+            "    {:<12} {} {}",
+            hash.name,
+            hash.property_str("algo").unwrap_or("?"),
+            hash.property("value").map(hex_string).unwrap_or_default()
+        );
+    }
+    print_signatures(image);
+}
+
+fn print_signatures(node: &FdtNode) {
+    for sig in node
+        .children
+        .iter()
+        .filter(|c| c.name.starts_with("signature"))
+    {
+        println!(
+            "    {:<12} {} key `{}`",
+            sig.name,
+            sig.property_str("algo").unwrap_or("?"),
+            sig.property_str("key-name-hint").unwrap_or("?")
+        );
+    }
+}
+
+fn hex_string(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Recomputes every hash of a FIT image and reports mismatches.
+///
+/// # Errors
+///
+/// Returns an error if the image cannot be parsed or any check fails.
+pub fn verify(path: &Path) -> anyhow::Result<()> {
+    let reader = FitImageReader::open(path)?;
+    let report = reader.verify();
+
+    for check in &report.checks {
+        let status = match &check.status {
+            HashStatus::Ok => "OK".green(),
+            HashStatus::Mismatch {
+                expected,
+                calculated,
+            } => format!("MISMATCH expected {expected}, got {calculated}").red(),
+            HashStatus::Unsupported(algo) => format!("unsupported algo `{algo}`").yellow(),
+            HashStatus::MissingValue => "missing value".red(),
+        };
+        println!("{}/{} {}: {status}", check.image, check.node, check.algo);
+    }
+    for image in &report.unhashed {
+        println!("{image}: {}", "no hash node".yellow());
+    }
+    for err in &report.errors {
+        println!("{}", err.red());
+    }
+
+    if !report.is_ok() {
+        bail!("FIT image verification failed: {}", path.display());
+    }
+    println!("{}", "FIT image verified".green());
+    Ok(())
+}
+
+/// Writes the stored payload of `component` to `output`.
+///
+/// `component` is an image node name or an image type such as `kernel`.
+///
+/// # Errors
+///
+/// Returns an error if the image cannot be parsed, the component does not
+/// exist, or the output file cannot be written.
+pub fn extract(path: &Path, component: &str, output: &Path) -> anyhow::Result<()> {
+    let reader = FitImageReader::open(path)?;
+    reader.extract(component, output)?;
+    info!("Extracted `{component}` to {}", output.display());
+    Ok(())
+}
+
+/// Prints the structural differences between two FIT images.
+///
+/// # Errors
+///
+/// Returns an error if either image cannot be read or parsed.
+pub fn diff(old: &Path, new: &Path) -> anyhow::Result<()> {
+    let diff = FitDiff::compare(&FitImageReader::open(old)?, &FitImageReader::open(new)?);
+
+    println!("{} {}", "---".red(), old.display());
+    println!("{} {}", "+++".green(), new.display());
+    for change in &diff.changes {
+        let line = change.to_string();
+        match change {
+            FitChange::NodeAdded { .. } => println!("{}", line.green()),
+            FitChange::NodeRemoved { .. } => println!("{}", line.red()),
+            _ => println!("{}", line.yellow()),
+        }
+    }
+
+    if diff.is_empty() {
+        println!("{}", "FIT images are structurally identical".green());
+    }
+    println!(
+        "Total size: {} -> {} bytes ({:+})",
+        diff.old_size,
+        diff.new_size,
+        diff.size_delta()
+    );
+    Ok(())
+}
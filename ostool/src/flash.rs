@@ -0,0 +1,405 @@
+//! Writing images to SD cards and USB sticks, `ostool flash`.
+//!
+//! Replaces the hand-typed `dd`: before anything is written the target is
+//! checked to be a whole, unmounted, removable disk large enough for the
+//! image, and the user confirms by typing its name. The image is written
+//! with progress and read back to compare checksums.
+//!
+//! With `--partition` the disk gets a GPT with a FAT boot partition holding
+//! the image (by default the built kernel) and an ext4 rootfs, see
+//! [`crate::disk`].
+
+use std::{
+    fs::File,
+    io::{self, BufRead, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use colored::Colorize;
+use jkconfig::t;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    ctx::AppContext,
+    disk::{self, Layout},
+    progress,
+    utils::human_size,
+};
+
+const CHUNK: usize = 4 * 1024 * 1024;
+/// Disks larger than this are refused without `--force`; removable media
+/// this big is rare, a system disk is not.
+const MAX_SIZE: u64 = 256 * 1024 * 1024 * 1024;
+
+/// Arguments for flashing.
+#[derive(Debug, Clone, Default)]
+pub struct FlashArgs {
+    /// Target disk, e.g. `/dev/sdb`.
+    pub device: PathBuf,
+    /// Image to write; the built kernel when unset.
+    pub image: Option<PathBuf>,
+    /// Optional path to the build configuration file.
+    pub build_config: Option<PathBuf>,
+    /// Partition the disk and copy the image to its boot partition instead
+    /// of writing it raw.
+    pub partition: Option<Layout>,
+    /// Skip the confirmation.
+    pub yes: bool,
+    /// Allow non-removable and very large disks.
+    pub force: bool,
+    /// Skip reading back.
+    pub no_verify: bool,
+}
+
+/// What is known about the target disk.
+#[derive(Debug, Clone, Default)]
+pub struct Device {
+    pub path: PathBuf,
+    /// Kernel name, e.g. `sdb`.
+    pub name: String,
+    pub size: u64,
+    pub removable: bool,
+    /// Vendor and model, when known.
+    pub model: String,
+    /// A partition rather than a whole disk.
+    pub partition: bool,
+    /// Mounted filesystems on the disk or its partitions.
+    pub mounted: Vec<String>,
+    /// A regular file standing in for a disk.
+    pub file: bool,
+}
+
+impl Device {
+    /// Looks the disk at `path` up in sysfs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `path` is neither a block device nor a file.
+    pub fn inspect(path: &Path) -> anyhow::Result<Self> {
+        let path = path
+            .canonicalize()
+            .with_context(|| t!("flash.no_device", path = path.display()))?;
+        let meta = std::fs::metadata(&path)?;
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if meta.is_file() {
+            return Ok(Self {
+                size: meta.len(),
+                removable: true,
+                file: true,
+                path,
+                name,
+                ..Default::default()
+            });
+        }
+        if !is_block_device(&meta) {
+            bail!(t!("flash.not_block", path = path.display()));
+        }
+
+        let sys = Path::new("/sys/class/block").join(&name);
+        let read = |file: &str| {
+            std::fs::read_to_string(sys.join(file))
+                .map(|s| s.trim().to_string())
+                .unwrap_or_default()
+        };
+        let size = match read("size").parse::<u64>() {
+            Ok(sectors) => sectors * 512,
+            Err(_) => {
+                let mut file = File::open(&path)?;
+                io::Seek::seek(&mut file, io::SeekFrom::End(0))?
+            }
+        };
+        // USB 读卡器常把 removable 报成 0，按总线再判断一次
+        let usb = sys
+            .canonicalize()
+            .is_ok_and(|p| p.to_string_lossy().contains("/usb"));
+        let model = format!("{} {}", read("device/vendor"), read("device/model"))
+            .trim()
+            .to_string();
+        let mut names = vec![name.clone()];
+        if let Ok(entries) = std::fs::read_dir(&sys) {
+            names.extend(
+                entries
+                    .flatten()
+                    .map(|e| e.file_name().to_string_lossy().to_string())
+                    .filter(|n| n.starts_with(&name)),
+            );
+        }
+        let mounts = std::fs::read_to_string("/proc/mounts").unwrap_or_default();
+        Ok(Self {
+            size,
+            removable: read("removable") == "1" || usb,
+            model,
+            partition: sys.join("partition").exists(),
+            mounted: mounted(&mounts, &names),
+            file: false,
+            path,
+            name,
+        })
+    }
+
+    /// Refuses disks that are unsafe to overwrite with `len` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first failed check.
+    pub fn check(&self, len: u64, force: bool) -> anyhow::Result<()> {
+        if self.partition {
+            bail!(t!("flash.is_partition", path = self.path.display()));
+        }
+        if !self.mounted.is_empty() {
+            bail!(t!(
+                "flash.mounted",
+                path = self.path.display(),
+                mounts = self.mounted.join(", ")
+            ));
+        }
+        if !force && !self.removable {
+            bail!(t!("flash.not_removable", path = self.path.display()));
+        }
+        if !force && self.size > MAX_SIZE {
+            bail!(t!(
+                "flash.too_large",
+                path = self.path.display(),
+                size = human_size(self.size)
+            ));
+        }
+        if len > self.size {
+            bail!(t!(
+                "flash.image_too_big",
+                image = human_size(len),
+                size = human_size(self.size)
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Writes the image to the disk, see the module documentation.
+///
+/// # Errors
+///
+/// Returns an error if a safety check fails, the user declines, or
+/// writing or verification fails.
+pub async fn flash(mut ctx: AppContext, args: FlashArgs) -> anyhow::Result<()> {
+    let mut device = Device::inspect(&args.device)?;
+    let image = match &args.image {
+        Some(image) => image.clone(),
+        None => {
+            ctx.build_for_run(args.build_config.clone()).await?;
+            ctx.objcopy_output_bin()?
+        }
+    };
+    let len = std::fs::metadata(&image)
+        .with_context(|| format!("Failed to read {}", image.display()))?
+        .len();
+    if device.file && device.size == 0 {
+        // 空文件当作恰好放得下镜像的盘
+        device.size = len;
+    }
+    let needed = match &args.partition {
+        Some(layout) => (layout.boot_size + 2) * 1024 * 1024,
+        None => len,
+    };
+    device.check(needed, args.force)?;
+    if !args.yes {
+        confirm(&device, &image)?;
+    }
+
+    match args.partition {
+        Some(mut layout) => {
+            layout.boot_files.insert(0, image);
+            let regions = disk::partition(&ctx, &device.path, device.size, &layout)?;
+            if !args.no_verify {
+                drop_cache(&File::open(&device.path)?);
+                if disk::read_regions(&device.path, device.size)? != regions {
+                    bail!(t!("flash.verify_failed", path = device.path.display()));
+                }
+            }
+        }
+        None => {
+            let hash = write(&image, &device.path, len)?;
+            if !args.no_verify {
+                verify(&device.path, len, &hash)?;
+            }
+        }
+    }
+    println!(
+        "{}",
+        t!("flash.done", path = device.path.display())
+            .green()
+            .bold()
+    );
+    Ok(())
+}
+
+fn confirm(device: &Device, image: &Path) -> anyhow::Result<()> {
+    println!("{}", t!("flash.target").bold());
+    println!("  {}  {}", "device".dimmed(), device.path.display());
+    if !device.model.is_empty() {
+        println!("  {}   {}", "model".dimmed(), device.model);
+    }
+    println!("  {}    {}", "size".dimmed(), human_size(device.size));
+    println!("  {}   {}", "image".dimmed(), image.display());
+    println!("{}", t!("flash.erase_warning").red().bold());
+    if !io::stdin().is_terminal() {
+        bail!(t!("flash.need_yes"));
+    }
+    print!("{}", t!("flash.confirm", name = device.name));
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    if answer.trim() != device.name {
+        bail!(t!("flash.aborted"));
+    }
+    Ok(())
+}
+
+/// Writes the image and returns its SHA-256.
+fn write(image: &Path, target: &Path, len: u64) -> anyhow::Result<Vec<u8>> {
+    let mut input = File::open(image)?;
+    let mut output = File::options()
+        .write(true)
+        .open(target)
+        .with_context(|| t!("flash.open_failed", path = target.display()))?;
+    let pb = progress::bytes(t!("flash.writing"), Some(len));
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; CHUNK];
+    loop {
+        let n = input.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        output.write_all(&buf[..n])?;
+        hasher.update(&buf[..n]);
+        pb.inc(n as u64);
+    }
+    pb.set_message(t!("flash.syncing"));
+    output.sync_all()?;
+    pb.finish();
+    Ok(hasher.finalize().to_vec())
+}
+
+/// Reads the first `len` bytes back and compares their SHA-256.
+fn verify(target: &Path, len: u64, expected: &[u8]) -> anyhow::Result<()> {
+    let mut input = File::open(target)?;
+    drop_cache(&input);
+    let pb = progress::bytes(t!("flash.verifying"), Some(len));
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; CHUNK];
+    let mut left = len;
+    while left > 0 {
+        let n = input.read(&mut buf[..left.min(CHUNK as u64) as usize])?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        left -= n as u64;
+        pb.inc(n as u64);
+    }
+    pb.finish();
+    if left > 0 || hasher.finalize()[..] != *expected {
+        bail!(t!("flash.verify_failed", path = target.display()));
+    }
+    println!("{}", t!("flash.verified").green());
+    Ok(())
+}
+
+/// Drops the page cache of the disk so reading back hits the medium.
+#[cfg(target_os = "linux")]
+fn drop_cache(file: &File) {
+    use std::os::fd::AsRawFd;
+    // SAFETY: 只对有效的文件描述符提出建议，不涉及内存
+    unsafe {
+        libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn drop_cache(_file: &File) {}
+
+#[cfg(unix)]
+fn is_block_device(meta: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    meta.file_type().is_block_device()
+}
+
+#[cfg(not(unix))]
+fn is_block_device(_meta: &std::fs::Metadata) -> bool {
+    false
+}
+
+/// Mount points of `/proc/mounts` whose source is one of `names`.
+fn mounted(mounts: &str, names: &[String]) -> Vec<String> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let source = fields.next()?;
+            let target = fields.next()?;
+            let source = Path::new(source)
+                .canonicalize()
+                .unwrap_or_else(|_| PathBuf::from(source));
+            let name = source.file_name()?.to_string_lossy();
+            (source.starts_with("/dev") && names.iter().any(|n| *n == name))
+                .then(|| target.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mounted() {
+        let mounts = "/dev/sda2 / ext4 rw 0 0\n\
+                      /dev/sdb1 /media/boot vfat rw 0 0\n\
+                      tmpfs /tmp tmpfs rw 0 0\n";
+        let names = ["sdb".to_string(), "sdb1".to_string(), "sdb2".to_string()];
+        assert_eq!(mounted(mounts, &names), ["/media/boot"]);
+        assert!(mounted(mounts, &["sdc".to_string()]).is_empty());
+    }
+
+    #[test]
+    fn test_check() {
+        let gib = 1024 * 1024 * 1024;
+        let device = Device {
+            path: "/dev/sdb".into(),
+            name: "sdb".into(),
+            size: 32 * gib,
+            removable: true,
+            ..Default::default()
+        };
+        assert!(device.check(gib, false).is_ok());
+        assert!(device.check(64 * gib, true).is_err());
+
+        let fixed = Device {
+            removable: false,
+            ..device.clone()
+        };
+        assert!(fixed.check(gib, false).is_err());
+        assert!(fixed.check(gib, true).is_ok());
+
+        let huge = Device {
+            size: 2048 * gib,
+            ..device.clone()
+        };
+        assert!(huge.check(gib, false).is_err());
+
+        let busy = Device {
+            mounted: vec!["/media/boot".into()],
+            ..device.clone()
+        };
+        assert!(busy.check(gib, true).is_err());
+
+        let part = Device {
+            partition: true,
+            ..device
+        };
+        assert!(part.check(gib, true).is_err());
+    }
+}
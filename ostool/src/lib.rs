@@ -13,39 +13,117 @@
 //! - **TFTP Server**: Built-in TFTP server for network booting
 //! - **Menu Configuration**: TUI-based configuration editor (like Linux kernel's menuconfig)
 //! - **Serial Terminal**: Interactive serial terminal for device communication
+//! - **Kernel Tests**: `cargo test` for bare-metal kernels, booted in QEMU
+//! - **Debugging**: GDB/LLDB attached to QEMU or OpenOCD with project init commands
+//! - **Remote Boards**: Serial ports and power switches shared by `ostool agent`
+//! - **Plugins**: External runners and packagers over JSON-RPC
+//! - **Boot Benchmarks**: Boot-time per phase over repeated boots, against a baseline
+//! - **Flashing**: Checked and verified writes of images to SD cards and USB sticks
+//! - **Disk Images**: GPT/MBR images with a FAT boot partition and ext4 rootfs, for QEMU and boards
+//! - **SD Muxes**: Hands-off SD card deployment through SDWire or USB-SD-Mux
+//! - **Cache**: Downloads kept in one user-level directory, with `ostool cache ls/clean`
+//! - **Bug Reports**: Versions, redacted configs and last-run logs in one tarball
+//! - **Exit Codes**: Distinct codes for build, boot and test failures, with cleanup on Ctrl+C
+//! - **Tasks**: Named sequences of ostool steps and shell commands in `.build.toml`
+//! - **Library API**: Build, package and run from a `cargo xtask` binary, with results as values
 //!
 //! ## Modules
 //!
+//! - [`bench`] - Boot-time benchmarking
 //! - [`build`] - Build system configuration and Cargo integration
+//! - [`cache`] - User-level cache of downloaded artifacts
 //! - [`ctx`] - Application context and state management
+//! - [`deploy`] - SD card deployment through an SD mux
+//! - [`disk`] - Partition tables, filesystems and disk images for boot media
+//! - [`exit`] - Exit codes and cleanup on Ctrl+C
+//! - [`fit`] - FIT image inspection, verification and extraction
+//! - [`flash`] - Writing images to removable media
+//! - [`logging`] - Logging setup shared by the binaries
 //! - [`menuconfig`] - TUI-based menu configuration
-//! - [`run`] - QEMU, TFTP, and U-Boot runners
+//! - [`migrate`] - Layout versions and upgrades of the config files
+//! - [`pipeline`] - Build and run pipeline for `cargo xtask` binaries
+//! - [`plugin`] - Runner and packager plugins
+//! - [`progress`] - Progress bars of transfers, downloads and conversions
+//! - [`remote`] - Remote board agent and client
+//! - [`report`] - Bug-report bundles and last-run records
+//! - [`run`] - QEMU, TFTP, U-Boot, GDB and test runners
+//! - [`settings`] - User-level settings
 //! - [`sterm`] - Serial terminal implementation
+//! - [`task`] - Project tasks of `.build.toml`
+//! - [`template`] - Config templates for common boards
 //! - [`utils`] - Common utilities and helper functions
 //!
 //! ## Example
 //!
 //! ```rust,no_run
-//! // ostool is primarily used as a CLI tool
-//! // See the binary targets for usage examples
+//! use ostool::{ctx::AppContext, pipeline::{Pipeline, QemuOptions}};
+//!
+//! # async fn xtask(ctx: AppContext) -> anyhow::Result<()> {
+//! let built = Pipeline::new(ctx).build().await?;
+//! let run = built.run_qemu(QemuOptions::default()).await?;
+//! println!("passed: {}", run.passed());
+//! # Ok(())
+//! # }
 //! ```
 
 #![cfg(not(target_os = "none"))]
 
+/// Boot-time benchmarking over repeated boots.
+pub mod bench;
+
 /// Build system configuration and Cargo integration.
 ///
 /// Provides functionality for configuring and executing Cargo builds
 /// with custom options and target specifications.
 pub mod build;
 
+/// User-level cache of downloaded artifacts.
+pub mod cache;
+
 /// Application context and state management.
 pub mod ctx;
 
+/// SD card deployment through an SD mux.
+pub mod deploy;
+
+/// Partition tables, filesystems and disk images for boot media.
+pub mod disk;
+
+/// Exit codes and cleanup on Ctrl+C.
+pub mod exit;
+
+/// FIT image inspection, verification and extraction.
+pub mod fit;
+
+/// Writing images to SD cards and USB sticks.
+pub mod flash;
+
+/// Logging setup with verbosity levels, module filters and a log file.
+pub mod logging;
+
 /// TUI-based menu configuration system.
 ///
 /// Similar to Linux kernel's menuconfig, allows users to configure
 /// build options through an interactive terminal interface.
 pub mod menuconfig;
+/// Layout versions of the config files and upgrades of older ones.
+pub mod migrate;
+
+/// Build and run pipeline for embedding ostool in other programs.
+pub mod pipeline;
+
+/// Runner and packager plugins.
+pub mod plugin;
+
+/// Progress bars shared by transfers, downloads and conversions, with log
+/// lines instead when the output is not a terminal.
+pub mod progress;
+
+/// Remote board agent and client.
+pub mod remote;
+
+/// Bug-report bundles and records of the last run.
+pub mod report;
 
 /// Runtime execution modules for QEMU, TFTP, and U-Boot.
 ///
@@ -53,15 +131,27 @@ pub mod menuconfig;
 /// running TFTP servers, and communicating with U-Boot.
 pub mod run;
 
+/// User-level settings, such as remembered serial devices.
+pub mod settings;
+
 /// Serial terminal implementation.
 ///
 /// Provides an interactive serial terminal for communication
 /// with embedded devices and development boards.
 pub mod sterm;
 
+/// Project tasks of `.build.toml`, `ostool task`.
+pub mod task;
+
+/// Config templates for common boards.
+pub mod template;
+
 /// Common utilities and helper functions.
 pub mod utils;
 
+/// VS Code tasks and launch configurations, `ostool gen vscode`.
+pub mod vscode;
+
 #[macro_use]
 extern crate log;
 #[macro_use]
@@ -0,0 +1,182 @@
+//! Logging setup shared by `ostool` and `cargo-osrun`.
+//!
+//! The level comes from `-v`/`-vv` or `--quiet` and applies to the
+//! workspace crates (ostool, uboot_shell, jkconfig, fitimage); other crates
+//! stay at `info` so `-vv` does not drown the console in HTTP traces.
+//! `RUST_LOG` and `--log module=level` refine it per module, e.g.
+//! `--log uboot_shell=trace` to debug the serial protocol, and
+//! `--log-file` appends every record to a file as well.
+//!
+//! Status lines such as the commands being run are printed only while
+//! `info` is enabled for their module, so `--quiet` leaves errors,
+//! warnings and the program's actual output. Records are written above
+//! the bars of [`crate::progress`] instead of through them.
+//!
+//! Runners started through cargo inherit the settings via
+//! [`child_env`].
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+    sync::OnceLock,
+};
+
+use anyhow::Context;
+use log::LevelFilter;
+
+use crate::progress;
+
+/// Filter spec passed on to child processes.
+pub const LOG_ENV: &str = "OSTOOL_LOG";
+/// Log file passed on to child processes.
+pub const LOG_FILE_ENV: &str = "OSTOOL_LOG_FILE";
+
+/// Crates whose level `-v` and `--quiet` change.
+const CRATES: [&str; 5] = [
+    "ostool",
+    "cargo_osrun",
+    "uboot_shell",
+    "jkconfig",
+    "fitimage",
+];
+
+static CHILD_ENV: OnceLock<Vec<(&'static str, String)>> = OnceLock::new();
+
+/// How to log.
+#[derive(Debug, Clone, Default)]
+pub struct LogOptions {
+    /// Number of `-v` flags: 1 for `debug`, 2 or more for `trace`.
+    pub verbose: u8,
+    /// Only warnings and errors.
+    pub quiet: bool,
+    /// Per-module directives such as `uboot_shell=trace`, applied last.
+    pub filters: Vec<String>,
+    /// File every record is appended to as well.
+    pub file: Option<PathBuf>,
+}
+
+impl LogOptions {
+    /// Options inherited from a parent ostool through [`child_env`].
+    pub fn from_env() -> Self {
+        Self {
+            filters: std::env::var(LOG_ENV).into_iter().collect(),
+            file: std::env::var_os(LOG_FILE_ENV).map(PathBuf::from),
+            ..Default::default()
+        }
+    }
+
+    /// Filter spec of the options, without `RUST_LOG`.
+    pub fn spec(&self) -> String {
+        let level = match (self.quiet, self.verbose) {
+            (true, _) => LevelFilter::Warn,
+            (false, 0) => LevelFilter::Info,
+            (false, 1) => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        };
+        let mut spec = vec![level.min(LevelFilter::Info).to_string().to_lowercase()];
+        if level != LevelFilter::Info {
+            let level = level.to_string().to_lowercase();
+            spec.extend(CRATES.iter().map(|c| format!("{c}={level}")));
+        }
+        spec.extend(self.filters.iter().cloned());
+        spec.join(",")
+    }
+}
+
+/// Installs the logger.
+///
+/// # Errors
+///
+/// Returns an error if the log file cannot be opened.
+pub fn init(options: &LogOptions) -> anyhow::Result<()> {
+    let spec = options.spec();
+    let mut builder = env_logger::builder();
+    builder.parse_filters(&spec);
+    // RUST_LOG 仍然可用，命令行的 --log 优先
+    if let Ok(env) = std::env::var("RUST_LOG") {
+        builder.parse_filters(&env);
+        builder.parse_filters(&options.filters.join(","));
+    }
+
+    let mut child = vec![(LOG_ENV, spec)];
+    let mut file = None;
+    if let Some(path) = &options.file {
+        file = Some(
+            File::options()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open log file {}", path.display()))?,
+        );
+        child.push((LOG_FILE_ENV, path.display().to_string()));
+    }
+    // 进度条显示时日志要先擦掉进度条再写，否则会写进进度条那一行
+    if file.is_some() || progress::is_drawn() {
+        if file.is_none() && std::env::var_os("RUST_LOG_STYLE").is_none() {
+            builder.write_style(env_logger::WriteStyle::Always);
+        }
+        builder.target(env_logger::Target::Pipe(Box::new(Tee(file))));
+    }
+    let _ = CHILD_ENV.set(child);
+    builder.init();
+    Ok(())
+}
+
+/// Environment passing the logging options on to a child process.
+pub fn child_env() -> &'static [(&'static str, String)] {
+    CHILD_ENV.get().map(Vec::as_slice).unwrap_or_default()
+}
+
+/// Writes log records to stderr above the progress bars, and to the log
+/// file if any.
+struct Tee(Option<File>);
+
+impl Write for Tee {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        progress::suspend(|| {
+            let _ = io::stderr().write_all(buf);
+        });
+        if let Some(file) = &mut self.0 {
+            file.write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let _ = io::stderr().flush();
+        match &mut self.0 {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec() {
+        assert_eq!(LogOptions::default().spec(), "info");
+        let quiet = LogOptions {
+            quiet: true,
+            verbose: 2,
+            ..Default::default()
+        };
+        assert!(quiet.spec().starts_with("warn,ostool=warn,"));
+        let verbose = LogOptions {
+            verbose: 1,
+            filters: vec!["uboot_shell=trace".to_string()],
+            ..Default::default()
+        };
+        let spec = verbose.spec();
+        assert!(spec.starts_with("info,ostool=debug,cargo_osrun=debug,"));
+        assert!(spec.ends_with(",uboot_shell=trace"));
+        let trace = LogOptions {
+            verbose: 3,
+            ..Default::default()
+        };
+        assert!(trace.spec().contains("jkconfig=trace"));
+    }
+}
@@ -1,14 +1,30 @@
 use std::{env::current_dir, path::PathBuf};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::*;
 
 use log::info;
 use ostool::{
+    bench::{BenchBootArgs, BenchTarget},
     build::{self, CargoRunnerKind},
     ctx::AppContext,
+    deploy::DeployArgs,
+    disk::{DEFAULT_BOOT_SIZE, Layout, image::MkimageArgs},
+    flash::FlashArgs,
     menuconfig::{MenuConfigHandler, MenuConfigMode},
-    run::{qemu::RunQemuArgs, uboot::RunUbootArgs},
+    plugin::RunPluginArgs,
+    remote::agent::RunAgentArgs,
+    run::{
+        boards::RunBoardsArgs,
+        gdb::RunGdbArgs,
+        openocd::RunOpenOcdArgs,
+        probe_rs::RunProbeRsArgs,
+        qemu::RunQemuArgs,
+        test::RunTestArgs,
+        uboot::{RunUbootArgs, UbootEnvAction, UbootEnvArgs},
+    },
+    task::RunTaskArgs,
+    template::Template,
 };
 
 #[derive(Parser)]
@@ -16,6 +32,29 @@ use ostool::{
 struct Cli {
     #[arg(short, long)]
     workdir: Option<PathBuf>,
+    /// Directory holding the config files (`.build.toml`, `.qemu.toml`,
+    /// `.uboot.toml`, ...) instead of the workspace
+    #[arg(long, value_name = "DIR", global = true)]
+    config_dir: Option<PathBuf>,
+    /// Override a config value without modifying the file, e.g.
+    /// `--set system.Cargo.target=riscv64gc-unknown-none-elf`; may be repeated
+    #[arg(long = "set", value_name = "PATH=VALUE", global = true)]
+    set: Vec<String>,
+    /// Forbid network access: downloads come from the cache or fail
+    #[arg(long, global = true)]
+    offline: bool,
+    /// More log output: -v for debug, -vv for trace
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+    /// Only print warnings, errors and the command's own output
+    #[arg(long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Per-module log level, e.g. `uboot_shell=trace`; may be repeated
+    #[arg(long = "log", value_name = "MODULE=LEVEL", global = true)]
+    log_filters: Vec<String>,
+    /// Append the log to this file as well
+    #[arg(long, value_name = "PATH", global = true)]
+    log_file: Option<PathBuf>,
     #[command(subcommand)]
     command: SubCommands,
 }
@@ -29,9 +68,311 @@ enum SubCommands {
     },
     Run(RunArgs),
     Menuconfig {
-        /// Menu configuration mode (qemu or uboot)
+        /// Menu configuration mode (qemu, uboot, gdb, or all for every file in one menu)
         #[arg(value_enum)]
         mode: Option<MenuConfigMode>,
+        /// Start from a template: a built-in name, a directory or a URL
+        /// holding build.toml/qemu.toml/uboot.toml
+        #[arg(long, value_name = "NAME")]
+        template: Option<String>,
+        /// List the built-in templates
+        #[arg(long)]
+        list_templates: bool,
+    },
+    /// Inspect, verify or extract FIT images
+    #[command(subcommand)]
+    Fit(FitSubCommands),
+    /// Read or write configuration values without the TUI
+    Config(ConfigArgs),
+    /// Attach a debugger to a running QEMU gdbstub or OpenOCD target
+    Gdb(GdbArgs),
+    /// Build the kernel tests and run them in QEMU
+    Test(TestArgs),
+    /// Build once and boot on several boards in parallel
+    Boards(BoardsArgs),
+    /// Save the board's U-Boot environment to a file or restore it from one
+    #[command(subcommand)]
+    UbootEnv(UbootEnvSubCommands),
+    /// Serve the serial ports and power switches of local boards to remote clients
+    Agent {
+        /// Path to the agent configuration file, default to '.agent.toml'
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    /// List or run runner and packager plugins
+    #[command(subcommand)]
+    Plugin(PluginSubCommands),
+    /// Benchmark the kernel
+    #[command(subcommand)]
+    Bench(BenchSubCommands),
+    /// Write an image to an SD card or USB stick, with safety checks and verification
+    Flash(FlashCliArgs),
+    /// Build the kernel and assemble a bootable disk image as described by '.disk.toml'
+    Mkimage {
+        /// Path to the build configuration file
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        /// Path to the disk image configuration file, default to '.disk.toml'
+        #[arg(short, long)]
+        disk_config: Option<PathBuf>,
+        /// Output file, default to `output` or 'target/disk.img'
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Write the disk image to a board's SD card through its SD mux and boot it
+    Deploy(DeployCliArgs),
+    /// List or clean the cache of downloaded artifacts
+    #[command(subcommand)]
+    Cache(CacheSubCommands),
+    /// Pack tool versions, redacted configs and the last run's logs into a tarball for bug reports
+    Report {
+        /// Output file, default to `ostool-report-<time>.tar.gz`
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Run a task of the `[tasks]` table in '.build.toml', or list the tasks
+    Task {
+        /// Task name; the tasks are listed when omitted
+        name: Option<String>,
+        /// Path to the build configuration file
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    /// Generate IDE configurations for the project
+    #[command(subcommand)]
+    Gen(GenSubCommands),
+}
+
+#[derive(Subcommand, Debug)]
+enum GenSubCommands {
+    /// Write '.vscode/tasks.json' and 'launch.json' entries for debugging the kernel in QEMU
+    Vscode {
+        /// Path to the build configuration file
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        /// Path to the qemu configuration file, default to '.qemu.toml'
+        #[arg(short, long)]
+        qemu_config: Option<PathBuf>,
+        /// Overwrite files that cannot be merged, e.g. JSON with comments
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum UbootEnvSubCommands {
+    /// Save the whole environment to the snapshot file
+    Save(UbootEnvCliArgs),
+    /// Put the environment of the snapshot file back and `saveenv` it
+    Restore(UbootEnvCliArgs),
+}
+
+#[derive(Args, Debug)]
+struct UbootEnvCliArgs {
+    /// Snapshot file, default to `env_snapshot` of the uboot configuration
+    file: Option<PathBuf>,
+    /// Path to the uboot configuration file, default to '.uboot.toml'
+    #[arg(short, long)]
+    uboot_config: Option<PathBuf>,
+    /// Serial port to use instead of the configured one
+    #[arg(long)]
+    port: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheSubCommands {
+    /// List the cached artifacts with their size and last use
+    Ls,
+    /// Remove cached artifacts
+    Clean {
+        /// Only remove what was not used for this long, e.g. '30d', '12h' or '2w'
+        #[arg(long, value_name = "AGE", value_parser = ostool::cache::parse_age)]
+        older_than: Option<std::time::Duration>,
+    },
+}
+
+#[derive(Args, Debug)]
+struct DeployCliArgs {
+    /// Board of '.boards.toml', may be left out when only one board has an SD mux
+    board: Option<String>,
+    /// Image to write, default to the disk image of '.disk.toml'
+    #[arg(long)]
+    image: Option<PathBuf>,
+    /// Path to the build configuration file
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+    /// Path to the disk image configuration file, default to '.disk.toml'
+    #[arg(short, long)]
+    disk_config: Option<PathBuf>,
+    /// Path to the boards file, default to '.boards.toml'
+    #[arg(short, long)]
+    boards_config: Option<PathBuf>,
+    /// Do not read the card back after writing
+    #[arg(long)]
+    no_verify: bool,
+}
+
+#[derive(Args, Debug)]
+struct FlashCliArgs {
+    /// Target disk, e.g. /dev/sdb
+    #[arg(short, long)]
+    device: PathBuf,
+    /// Image to write, default to the built kernel
+    image: Option<PathBuf>,
+    /// Path to the build configuration file
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+    /// Create a GPT with a FAT boot partition holding the image and an ext4 rootfs
+    #[arg(long)]
+    partition: bool,
+    /// Size of the boot partition in MiB
+    #[arg(long, value_name = "MIB", default_value_t = DEFAULT_BOOT_SIZE, requires = "partition")]
+    boot_size: u64,
+    /// Extra file for the boot partition, e.g. a DTB or boot.scr; may be repeated
+    #[arg(long, value_name = "PATH", requires = "partition")]
+    boot_file: Vec<PathBuf>,
+    /// Do not ask for confirmation
+    #[arg(short, long)]
+    yes: bool,
+    /// Allow disks that are not removable or unusually large
+    #[arg(long)]
+    force: bool,
+    /// Do not read the disk back after writing
+    #[arg(long)]
+    no_verify: bool,
+}
+
+#[derive(Args, Debug)]
+struct BoardsArgs {
+    /// Path to the build configuration file
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+    /// Path to the boards file, default to '.boards.toml'
+    #[arg(short, long)]
+    boards_config: Option<PathBuf>,
+    /// Only run this board; may be repeated
+    #[arg(long, value_name = "NAME")]
+    only: Vec<String>,
+    /// Seconds each board may take, default to `timeout` or 300
+    #[arg(long)]
+    timeout: Option<u64>,
+}
+
+#[derive(Subcommand, Debug)]
+enum PluginSubCommands {
+    /// List the plugins found in '.ostool/plugins' and on PATH
+    List,
+    /// Build the kernel and hand it to a plugin
+    Run {
+        /// Plugin name, the executable is 'ostool-plugin-<name>'
+        name: String,
+        /// Path to the build configuration file
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        /// Arguments passed on to the plugin
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum BenchSubCommands {
+    /// Boot repeatedly and report the time per boot phase
+    Boot(BenchBootCliArgs),
+}
+
+#[derive(Args, Debug)]
+struct BenchBootCliArgs {
+    /// Where to boot
+    #[arg(value_enum, default_value = "qemu")]
+    target: BenchTarget,
+    /// Path to the build configuration file
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+    /// Path to the qemu or uboot configuration file, default to '.qemu.toml' or '.uboot.toml'
+    #[arg(short, long)]
+    runner_config: Option<PathBuf>,
+    /// Path to the benchmark configuration file, default to '.bench.toml'
+    #[arg(short, long)]
+    bench_config: Option<PathBuf>,
+    /// Number of boots, default to `runs` or 5
+    #[arg(short = 'n', long)]
+    runs: Option<usize>,
+    /// Baseline file, default to '.ostool/bench/boot-<target>.json'
+    #[arg(long, value_name = "PATH")]
+    baseline: Option<PathBuf>,
+    /// Save the results as the new baseline instead of comparing
+    #[arg(long)]
+    save_baseline: bool,
+    /// Median increase in percent that fails the run, default to 10
+    #[arg(long)]
+    threshold: Option<f64>,
+}
+
+#[derive(Args, Debug)]
+struct ConfigArgs {
+    /// Configuration to access (qemu, uboot, gdb or all); the build configuration when omitted
+    #[arg(short, long, value_enum)]
+    mode: Option<MenuConfigMode>,
+    /// Path to the configuration file
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+    #[command(subcommand)]
+    command: ConfigSubCommands,
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigSubCommands {
+    /// Print the value at a dotted path, e.g. `system.Cargo.features`
+    Get {
+        /// Dotted path as it appears in the configuration file
+        path: String,
+    },
+    /// Validate a value against the schema and write it to the file
+    Set {
+        /// Dotted path as it appears in the configuration file
+        path: String,
+        /// New value; arrays take a JSON array or a comma-separated list
+        value: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum FitSubCommands {
+    /// Build a FIT image from an .its source, like `mkimage -f`
+    Build {
+        /// Path to the .its source; /incbin/ paths are relative to it
+        its: PathBuf,
+        /// Output file path
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Print images and configurations of a FIT image
+    Inspect {
+        /// Path to the FIT image
+        image: PathBuf,
+    },
+    /// Recompute all hashes and check configuration references
+    Verify {
+        /// Path to the FIT image
+        image: PathBuf,
+    },
+    /// Write the payload of one component to a file
+    Extract {
+        /// Path to the FIT image
+        image: PathBuf,
+        /// Image node name or type (kernel, fdt, ramdisk)
+        component: String,
+        /// Output file path
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Compare two FIT images and report structural differences
+    Diff {
+        /// Old FIT image
+        old: PathBuf,
+        /// New FIT image
+        new: PathBuf,
     },
 }
 
@@ -48,6 +389,10 @@ struct RunArgs {
 enum RunSubCommands {
     Qemu(QemuArgs),
     Uboot(UbootArgs),
+    /// Load and run over JTAG/SWD through OpenOCD
+    Openocd(OpenocdArgs),
+    /// Flash and run a microcontroller through probe-rs, showing RTT/defmt output
+    ProbeRs(ProbeRsArgs),
 }
 
 #[derive(Args, Debug, Default)]
@@ -60,6 +405,55 @@ pub struct QemuArgs {
     /// Dump DTB file
     #[arg(long)]
     dtb_dump: bool,
+    /// Save the VM as this snapshot once `save_regex` of `[snapshot]` matches
+    #[arg(long, value_name = "NAME")]
+    save_snapshot: Option<String>,
+    /// Start from this snapshot instead of booting
+    #[arg(long, value_name = "NAME", conflicts_with = "save_snapshot")]
+    load_snapshot: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct GdbArgs {
+    /// Path to the build configuration file
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+    /// Path to the gdb configuration file, default to '.gdb.toml'
+    #[arg(short, long)]
+    gdb_config: Option<PathBuf>,
+    /// ELF to load symbols from, default to the build output
+    #[arg(long)]
+    elf: Option<PathBuf>,
+    /// Use the release build output instead of the debug one
+    #[arg(long)]
+    release: bool,
+}
+
+#[derive(Args, Debug)]
+struct TestArgs {
+    /// Path to the build configuration file
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+    /// Path to the qemu configuration file, default to '.qemu.toml'
+    #[arg(short, long)]
+    qemu_config: Option<PathBuf>,
+    /// Boot every test on its own instead of the whole suite at once
+    #[arg(long)]
+    per_test: bool,
+    /// Seconds a single test may take, default to `test.timeout` or 60
+    #[arg(long)]
+    timeout: Option<u64>,
+    /// Print the serial output of passing tests too
+    #[arg(long)]
+    show_output: bool,
+    /// Write a JUnit XML report to this file
+    #[arg(long, value_name = "PATH")]
+    junit: Option<PathBuf>,
+    /// Write a JSON report to this file
+    #[arg(long, value_name = "PATH")]
+    json: Option<PathBuf>,
+    /// Only run the tests whose name contains this string
+    filter: Option<String>,
 }
 
 #[derive(Args, Debug)]
@@ -67,22 +461,64 @@ pub struct UbootArgs {
     /// Path to the uboot configuration file, default to '.uboot.toml'
     #[arg(short, long)]
     uboot_config: Option<PathBuf>,
+    /// Serial port to use instead of the configured one
+    #[arg(long)]
+    port: Option<String>,
+    /// Serve the kernel gdbstub on the serial console at localhost:1234
+    #[arg(short, long)]
+    debug: bool,
+    /// Watch the console without a terminal until a success or fail pattern
+    /// matches or the timeout passes, exiting non-zero on failure
+    #[arg(long)]
+    test: bool,
+    /// Seconds of a test run, default to `test_timeout` of the config or 300
+    #[arg(long, requires = "test")]
+    timeout: Option<u64>,
+}
+
+#[derive(Args, Debug)]
+pub struct OpenocdArgs {
+    /// Path to the openocd configuration file, default to '.openocd.toml'
+    #[arg(short, long)]
+    openocd_config: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct ProbeRsArgs {
+    /// Path to the probe-rs configuration file, default to '.probe-rs.toml'
+    #[arg(short, long)]
+    probe_rs_config: Option<PathBuf>,
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
+    ostool::exit::install();
+    ostool::exit::finish(run(Cli::parse()).await)
+}
+
+async fn run(cli: Cli) -> Result<()> {
     #[cfg(not(feature = "ui-log"))]
-    {
-        env_logger::builder()
-            .filter_level(log::LevelFilter::Info)
-            .parse_default_env()
-            .init();
-    }
+    ostool::logging::init(&ostool::logging::LogOptions {
+        verbose: cli.verbose,
+        quiet: cli.quiet,
+        filters: cli.log_filters.clone(),
+        file: cli.log_file.clone(),
+    })?;
 
-    let cli = Cli::parse();
+    ostool::utils::apply_lang_env();
 
     let pwd = current_dir()?;
 
+    // 任务中的 ostool 步骤沿用这些全局参数
+    let mut global_args = cli
+        .set
+        .iter()
+        .flat_map(|s| ["--set".to_string(), s.clone()])
+        .collect::<Vec<_>>();
+    if cli.offline {
+        global_args.push("--offline".to_string());
+    }
+
     let workspace_folder = match cli.workdir {
         Some(dir) => dir,
         None => pwd.clone(),
@@ -96,6 +532,9 @@ async fn main() -> Result<()> {
         },
         ..Default::default()
     };
+    ctx.load_overrides(&cli.set)?;
+    ctx.set_offline(cli.offline);
+    ctx.set_config_dir(cli.config_dir)?;
 
     match cli.command {
         SubCommands::Build { config } => {
@@ -110,15 +549,28 @@ async fn main() -> Result<()> {
                             qemu_config: qemu_args.qemu_config,
                             debug: qemu_args.debug,
                             dtb_dump: qemu_args.dtb_dump,
+                            save_snapshot: qemu_args.save_snapshot,
+                            load_snapshot: qemu_args.load_snapshot,
                         },
                         RunSubCommands::Uboot(uboot_args) => CargoRunnerKind::Uboot {
                             uboot_config: uboot_args.uboot_config,
+                            port: uboot_args.port,
+                            debug: uboot_args.debug,
+                            test: uboot_args.test,
+                            timeout: uboot_args.timeout,
+                        },
+                        RunSubCommands::Openocd(openocd_args) => CargoRunnerKind::Openocd {
+                            openocd_config: openocd_args.openocd_config,
+                        },
+                        RunSubCommands::ProbeRs(probe_rs_args) => CargoRunnerKind::ProbeRs {
+                            probe_rs_config: probe_rs_args.probe_rs_config,
                         },
                     };
                     ctx.cargo_run(&config, &kind).await?;
                 }
                 build::config::BuildSystem::Custom(custom_cfg) => {
-                    ctx.shell_run_cmd(&custom_cfg.build_cmd)?;
+                    ctx.shell_run_cmd(&custom_cfg.build_cmd)
+                        .context(ostool::exit::Failure::Build)?;
                     ctx.set_elf_path(custom_cfg.elf_path.clone().into()).await;
                     info!(
                         "ELF {:?}: {}",
@@ -130,6 +582,14 @@ async fn main() -> Result<()> {
                         ctx.objcopy_output_bin()?;
                     }
 
+                    if let Some(uimage) = &custom_cfg.uimage {
+                        ctx.output_uimage(uimage)?;
+                    }
+
+                    if let Some(android_boot) = &custom_cfg.android_boot {
+                        ctx.output_android_boot(android_boot)?;
+                    }
+
                     match args.command {
                         RunSubCommands::Qemu(qemu_args) => {
                             ostool::run::qemu::run_qemu(
@@ -138,16 +598,40 @@ async fn main() -> Result<()> {
                                     qemu_config: qemu_args.qemu_config,
                                     dtb_dump: qemu_args.dtb_dump,
                                     show_output: true,
+                                    save_snapshot: qemu_args.save_snapshot,
+                                    load_snapshot: qemu_args.load_snapshot,
                                 },
                             )
                             .await?;
                         }
                         RunSubCommands::Uboot(uboot_args) => {
+                            ctx.debug |= uboot_args.debug;
                             ostool::run::uboot::run_uboot(
                                 ctx,
                                 RunUbootArgs {
                                     config: uboot_args.uboot_config,
                                     show_output: true,
+                                    port: uboot_args.port,
+                                    test: uboot_args.test,
+                                    timeout: uboot_args.timeout,
+                                },
+                            )
+                            .await?;
+                        }
+                        RunSubCommands::Openocd(openocd_args) => {
+                            ostool::run::openocd::run_openocd(
+                                ctx,
+                                RunOpenOcdArgs {
+                                    config: openocd_args.openocd_config,
+                                },
+                            )
+                            .await?;
+                        }
+                        RunSubCommands::ProbeRs(probe_rs_args) => {
+                            ostool::run::probe_rs::run_probe_rs(
+                                ctx,
+                                RunProbeRsArgs {
+                                    config: probe_rs_args.probe_rs_config,
                                 },
                             )
                             .await?;
@@ -156,8 +640,210 @@ async fn main() -> Result<()> {
                 }
             }
         }
-        SubCommands::Menuconfig { mode } => {
-            MenuConfigHandler::handle_menuconfig(&mut ctx, mode).await?;
+        SubCommands::Menuconfig {
+            mode,
+            template,
+            list_templates,
+        } => {
+            if list_templates {
+                ostool::template::list();
+                return Ok(());
+            }
+            let template = match template {
+                Some(name) => Some(Template::load(&name, ctx.offline).await?),
+                None => None,
+            };
+            MenuConfigHandler::handle_menuconfig(&mut ctx, mode, template.as_ref()).await?;
+        }
+        SubCommands::Fit(cmd) => match cmd {
+            FitSubCommands::Build { its, output } => ostool::fit::build(&its, &output)?,
+            FitSubCommands::Inspect { image } => ostool::fit::inspect(&image)?,
+            FitSubCommands::Verify { image } => ostool::fit::verify(&image)?,
+            FitSubCommands::Extract {
+                image,
+                component,
+                output,
+            } => ostool::fit::extract(&image, &component, &output)?,
+            FitSubCommands::Diff { old, new } => ostool::fit::diff(&old, &new)?,
+        },
+        SubCommands::Config(args) => match args.command {
+            ConfigSubCommands::Get { path } => {
+                MenuConfigHandler::get_value(&ctx, args.mode, args.config, &path)?
+            }
+            ConfigSubCommands::Set { path, value } => {
+                MenuConfigHandler::set_value(&ctx, args.mode, args.config, &path, &value)?
+            }
+        },
+        SubCommands::Gdb(args) => {
+            ostool::run::gdb::run_gdb(
+                ctx,
+                RunGdbArgs {
+                    build_config: args.config,
+                    gdb_config: args.gdb_config,
+                    elf: args.elf,
+                    release: args.release,
+                },
+            )
+            .await?;
+        }
+        SubCommands::Test(args) => {
+            let reports = ostool::run::test::run_tests(
+                ctx,
+                RunTestArgs {
+                    build_config: args.config,
+                    qemu_config: args.qemu_config,
+                    filter: args.filter,
+                    per_test: args.per_test,
+                    timeout: args.timeout,
+                    show_output: args.show_output,
+                    junit: args.junit,
+                    json: args.json,
+                },
+            )
+            .await?;
+            ostool::run::test::ensure_passed(&reports)?;
+        }
+        SubCommands::Boards(args) => {
+            let results = ostool::run::boards::run_boards(
+                ctx,
+                RunBoardsArgs {
+                    build_config: args.config,
+                    boards_config: args.boards_config,
+                    only: args.only,
+                    timeout: args.timeout,
+                },
+            )
+            .await?;
+            ostool::run::boards::ensure_passed(&results)?;
+        }
+        SubCommands::UbootEnv(cmd) => {
+            let (action, args) = match cmd {
+                UbootEnvSubCommands::Save(args) => (UbootEnvAction::Save, args),
+                UbootEnvSubCommands::Restore(args) => (UbootEnvAction::Restore, args),
+            };
+            ostool::run::uboot::uboot_env(
+                ctx,
+                UbootEnvArgs {
+                    config: args.uboot_config,
+                    action,
+                    file: args.file,
+                    port: args.port,
+                },
+            )
+            .await?;
+        }
+        SubCommands::Agent { config } => {
+            ostool::remote::agent::run_agent(ctx, RunAgentArgs { config }).await?;
+        }
+        SubCommands::Plugin(PluginSubCommands::List) => ostool::plugin::list(&ctx),
+        SubCommands::Cache(CacheSubCommands::Ls) => ostool::cache::list(),
+        SubCommands::Cache(CacheSubCommands::Clean { older_than }) => {
+            ostool::cache::clean(older_than)?;
+        }
+        SubCommands::Report { output } => {
+            ostool::report::report(&ctx, ostool::report::ReportArgs { output })?;
+        }
+        SubCommands::Gen(GenSubCommands::Vscode {
+            config,
+            qemu_config,
+            force,
+        }) => {
+            ostool::vscode::gen_vscode(
+                ctx,
+                ostool::vscode::GenVscodeArgs {
+                    build_config: config,
+                    qemu_config,
+                    force,
+                },
+            )
+            .await?;
+        }
+        SubCommands::Task { name, config } => {
+            ostool::task::run_task(
+                ctx,
+                RunTaskArgs {
+                    name,
+                    build_config: config,
+                    global_args,
+                },
+            )
+            .await?;
+        }
+        SubCommands::Plugin(PluginSubCommands::Run { name, config, args }) => {
+            ostool::plugin::run_plugin(
+                ctx,
+                RunPluginArgs {
+                    name,
+                    build_config: config,
+                    args,
+                },
+            )
+            .await?;
+        }
+        SubCommands::Bench(BenchSubCommands::Boot(args)) => {
+            ostool::bench::bench_boot(
+                ctx,
+                BenchBootArgs {
+                    target: args.target,
+                    build_config: args.config,
+                    runner_config: args.runner_config,
+                    bench_config: args.bench_config,
+                    runs: args.runs,
+                    baseline: args.baseline,
+                    save_baseline: args.save_baseline,
+                    threshold: args.threshold,
+                },
+            )
+            .await?;
+        }
+        SubCommands::Flash(args) => {
+            ostool::flash::flash(
+                ctx,
+                FlashArgs {
+                    device: args.device,
+                    image: args.image,
+                    build_config: args.config,
+                    partition: args.partition.then_some(Layout {
+                        boot_size: args.boot_size,
+                        boot_files: args.boot_file,
+                        rootfs: true,
+                        ..Default::default()
+                    }),
+                    yes: args.yes,
+                    force: args.force,
+                    no_verify: args.no_verify,
+                },
+            )
+            .await?;
+        }
+        SubCommands::Mkimage {
+            config,
+            disk_config,
+            output,
+        } => {
+            ostool::disk::image::mkimage(
+                ctx,
+                MkimageArgs {
+                    build_config: config,
+                    disk_config,
+                    output,
+                },
+            )
+            .await?;
+        }
+        SubCommands::Deploy(args) => {
+            ostool::deploy::deploy(
+                ctx,
+                DeployArgs {
+                    board: args.board,
+                    image: args.image,
+                    build_config: args.config,
+                    disk_config: args.disk_config,
+                    boards_config: args.boards_config,
+                    no_verify: args.no_verify,
+                },
+            )
+            .await?;
         }
     }
 
@@ -170,6 +856,8 @@ impl From<QemuArgs> for RunQemuArgs {
             qemu_config: value.qemu_config,
             dtb_dump: value.dtb_dump,
             show_output: true,
+            save_snapshot: value.save_snapshot,
+            load_snapshot: value.load_snapshot,
         }
     }
 }
@@ -179,6 +867,9 @@ impl From<UbootArgs> for RunUbootArgs {
         RunUbootArgs {
             config: value.uboot_config,
             show_output: true,
+            port: value.port,
+            test: value.test,
+            timeout: value.timeout,
         }
     }
 }
@@ -7,15 +7,29 @@
 //! - Build settings (`.build.toml`)
 //! - QEMU settings (`.qemu.toml`)
 //! - U-Boot settings (`.uboot.toml`)
+//! - Debugger settings (`.gdb.toml`)
+//!
+//! or all of them at once, each file as a top-level submenu of one session.
+//! A [`Template`] can pre-populate the editor.
+//!
+//! Values can also be read and written without the TUI through
+//! [`MenuConfigHandler::get_value`] and [`MenuConfigHandler::set_value`].
+
+use std::path::PathBuf;
 
 use anyhow::Result;
 use clap::ValueEnum;
+use jkconfig::{ConfigFile, MultiConfig, data::AppData, t};
 use log::info;
+use schemars::JsonSchema;
 use tokio::fs;
 
+use crate::build::config::BuildConfig;
 use crate::ctx::AppContext;
+use crate::run::gdb::GdbConfig;
 use crate::run::qemu::QemuConfig;
 use crate::run::uboot::UbootConfig;
+use crate::template::{FILES, Template};
 
 /// Menu configuration mode selector.
 #[derive(ValueEnum, Clone, Debug)]
@@ -24,6 +38,10 @@ pub enum MenuConfigMode {
     Qemu,
     /// Configure U-Boot runner settings.
     Uboot,
+    /// Configure `ostool gdb` settings.
+    Gdb,
+    /// Configure build, QEMU, U-Boot and GDB settings in one menu.
+    All,
 }
 
 /// Handler for menu configuration operations.
@@ -37,6 +55,8 @@ impl MenuConfigHandler {
     /// * `ctx` - The application context.
     /// * `mode` - Optional mode specifying which configuration to edit.
     ///   If `None`, shows the default build configuration menu.
+    /// * `template` - Optional template whose files pre-populate the editor;
+    ///   nothing is written unless the user saves.
     ///
     /// # Errors
     ///
@@ -44,79 +64,268 @@ impl MenuConfigHandler {
     pub async fn handle_menuconfig(
         ctx: &mut AppContext,
         mode: Option<MenuConfigMode>,
+        template: Option<&Template>,
     ) -> Result<()> {
+        if let Some(template) = template {
+            println!("{}", t!("menuconfig.template", name = template.name));
+        }
         match mode {
             Some(MenuConfigMode::Qemu) => {
-                Self::handle_qemu_config(ctx).await?;
+                Self::handle_qemu_config(ctx, template).await?;
             }
             Some(MenuConfigMode::Uboot) => {
-                Self::handle_uboot_config(ctx).await?;
+                Self::handle_uboot_config(ctx, template).await?;
+            }
+            Some(MenuConfigMode::Gdb) => {
+                Self::handle_gdb_config(ctx, template).await?;
+            }
+            Some(MenuConfigMode::All) => {
+                Self::handle_all_config(ctx, template).await?;
             }
             None => {
                 // 默认模式：显示当前构建配置
-                Self::handle_default_config(ctx).await?;
+                Self::handle_default_config(ctx, template).await?;
             }
         }
         Ok(())
     }
 
-    async fn handle_default_config(ctx: &mut AppContext) -> Result<()> {
-        ctx.prepare_build_config(None, true).await?;
+    async fn handle_default_config(
+        ctx: &mut AppContext,
+        template: Option<&Template>,
+    ) -> Result<()> {
+        let Some(template) = template else {
+            ctx.prepare_build_config(None, true).await?;
+            return Ok(());
+        };
+        let path = ctx.paths.config_file(".build.toml");
+        let init = template.require(".build.toml")?;
+        if let Some(c) =
+            jkconfig::run_with_init::<BuildConfig>(&path, init, &ctx.ui_hocks()).await?
+        {
+            ctx.build_config_path = Some(path);
+            ctx.build_config = Some(c);
+        }
+        Ok(())
+    }
 
+    async fn handle_all_config(ctx: &mut AppContext, template: Option<&Template>) -> Result<()> {
+        info!("{}", t!("menuconfig.all"));
+        let mut multi = Self::multi_config(ctx, ctx.build_config_path.clone())?;
+        if let Some(template) = template {
+            for (file, name) in multi.files.iter_mut().zip(FILES) {
+                if let Some(init) = template.file(name) {
+                    file.init = Some(init.to_string());
+                }
+            }
+        }
+        let written = jkconfig::run_multi(&multi, &ctx.ui_hocks_at("build.")).await?;
+        if written.is_empty() {
+            println!("\n{}", t!("menuconfig.unchanged"));
+        }
+        for path in written {
+            println!("{}", t!("menuconfig.saved", path = path.display()));
+        }
         Ok(())
     }
 
-    async fn handle_qemu_config(ctx: &mut AppContext) -> Result<()> {
-        info!("配置 QEMU 运行参数");
-        let config_path = ctx.paths.workspace.join(".qemu.toml");
+    /// The build, QEMU, U-Boot and GDB configs of the workspace as one tree,
+    /// under `build`, `qemu`, `uboot` and `gdb`. `build` overrides the path of the
+    /// build config.
+    fn multi_config(ctx: &AppContext, build: Option<PathBuf>) -> Result<MultiConfig> {
+        let dir = ctx.paths.configs();
+        let path = |name: &str| PathBuf::from(ctx.value_replace_with_var(dir.join(name)));
+        Ok(MultiConfig::new("ostool", dir.join(".menuconfig.toml"))
+            .with_file(ConfigFile::new(
+                "build",
+                build.unwrap_or_else(|| path(".build.toml")),
+                schema_of::<BuildConfig>()?,
+            ))
+            .with_file(ConfigFile::new(
+                "qemu",
+                path(".qemu.toml"),
+                schema_of::<QemuConfig>()?,
+            ))
+            .with_file(ConfigFile::new(
+                "uboot",
+                path(".uboot.toml"),
+                schema_of::<UbootConfig>()?,
+            ))
+            .with_file(ConfigFile::new(
+                "gdb",
+                path(".gdb.toml"),
+                schema_of::<GdbConfig>()?,
+            )))
+    }
+
+    async fn handle_qemu_config(ctx: &mut AppContext, template: Option<&Template>) -> Result<()> {
+        info!("{}", t!("menuconfig.qemu"));
+        let config_path = ctx.paths.config_file(".qemu.toml");
         if config_path.exists() {
-            println!("\n当前 U-Boot 配置文件: {}", config_path.display());
+            println!(
+                "\n{}",
+                t!("menuconfig.current", path = config_path.display())
+            );
             // 这里可以读取并显示当前的 U-Boot 配置
         } else {
-            println!("\n未找到 U-Boot 配置文件，将使用默认配置");
+            println!("\n{}", t!("menuconfig.not_found"));
         }
 
-        let config = jkconfig::run::<QemuConfig>(config_path, true, &[]).await?;
+        let config = match template {
+            Some(template) => {
+                jkconfig::run_with_init::<QemuConfig>(
+                    config_path,
+                    template.require(".qemu.toml")?,
+                    &[],
+                )
+                .await?
+            }
+            None => jkconfig::run::<QemuConfig>(config_path, true, &[]).await?,
+        };
 
         if let Some(c) = config {
             fs::write(
-                ctx.value_replace_with_var(ctx.paths.workspace.join(".qemu.toml")),
+                ctx.value_replace_with_var(ctx.paths.config_file(".qemu.toml")),
                 toml::to_string_pretty(&c)?,
             )
             .await?;
-            println!("\nQEMU 配置已保存到 .qemu.toml");
+            println!("\n{}", t!("menuconfig.qemu_saved"));
         } else {
-            println!("\n未更改 QEMU 配置");
+            println!("\n{}", t!("menuconfig.qemu_unchanged"));
         }
 
         Ok(())
     }
 
-    async fn handle_uboot_config(ctx: &mut AppContext) -> Result<()> {
-        info!("配置 U-Boot 运行参数");
+    async fn handle_uboot_config(ctx: &mut AppContext, template: Option<&Template>) -> Result<()> {
+        info!("{}", t!("menuconfig.uboot"));
 
-        println!("=== U-Boot 配置模式 ===");
+        println!("{}", t!("menuconfig.uboot_mode"));
 
         // 检查是否存在 U-Boot 配置文件
-        let uboot_config_path = ctx.paths.workspace.join(".uboot.toml");
+        let uboot_config_path = ctx.paths.config_file(".uboot.toml");
         if uboot_config_path.exists() {
-            println!("\n当前 U-Boot 配置文件: {}", uboot_config_path.display());
+            println!(
+                "\n{}",
+                t!("menuconfig.current", path = uboot_config_path.display())
+            );
             // 这里可以读取并显示当前的 U-Boot 配置
         } else {
-            println!("\n未找到 U-Boot 配置文件，将使用默认配置");
+            println!("\n{}", t!("menuconfig.not_found"));
         }
-        let config = jkconfig::run::<UbootConfig>(uboot_config_path, true, &[]).await?;
+        let config = match template {
+            Some(template) => {
+                jkconfig::run_with_init::<UbootConfig>(
+                    uboot_config_path,
+                    template.require(".uboot.toml")?,
+                    &[],
+                )
+                .await?
+            }
+            None => jkconfig::run::<UbootConfig>(uboot_config_path, true, &[]).await?,
+        };
         if let Some(c) = config {
             fs::write(
-                ctx.value_replace_with_var(ctx.paths.workspace.join(".uboot.toml")),
+                ctx.value_replace_with_var(ctx.paths.config_file(".uboot.toml")),
                 toml::to_string_pretty(&c)?,
             )
             .await?;
-            println!("\nU-Boot 配置已保存到 .uboot.toml");
+            println!("\n{}", t!("menuconfig.uboot_saved"));
+        } else {
+            println!("\n{}", t!("menuconfig.uboot_unchanged"));
+        }
+
+        Ok(())
+    }
+
+    async fn handle_gdb_config(ctx: &mut AppContext, template: Option<&Template>) -> Result<()> {
+        info!("{}", t!("menuconfig.gdb"));
+        let config_path = ctx.paths.config_file(".gdb.toml");
+        let config = match template {
+            Some(template) => {
+                jkconfig::run_with_init::<GdbConfig>(
+                    config_path,
+                    template.require(".gdb.toml")?,
+                    &[],
+                )
+                .await?
+            }
+            None => jkconfig::run::<GdbConfig>(config_path, true, &[]).await?,
+        };
+        if config.is_some() {
+            println!("\n{}", t!("menuconfig.gdb_saved"));
         } else {
-            println!("\n未更改 U-Boot 配置");
+            println!("\n{}", t!("menuconfig.gdb_unchanged"));
+        }
+        Ok(())
+    }
+
+    /// Prints the value at a dotted path (e.g. `system.Cargo.features`) of a
+    /// configuration file. Strings are printed raw, everything else as JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be loaded or the path is unknown.
+    pub fn get_value(
+        ctx: &AppContext,
+        mode: Option<MenuConfigMode>,
+        config: Option<PathBuf>,
+        path: &str,
+    ) -> Result<()> {
+        let app = Self::load(ctx, mode, config)?;
+        match app.get_value(path)? {
+            serde_json::Value::String(s) => println!("{s}"),
+            value => println!("{value}"),
         }
+        Ok(())
+    }
 
+    /// Sets the value at a dotted path of a configuration file, validating it
+    /// against the schema before writing the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is unknown, the value does not match the
+    /// schema, or the file cannot be written.
+    pub fn set_value(
+        ctx: &AppContext,
+        mode: Option<MenuConfigMode>,
+        config: Option<PathBuf>,
+        path: &str,
+        value: &str,
+    ) -> Result<()> {
+        if let Some(MenuConfigMode::All) = mode {
+            let multi = Self::multi_config(ctx, config)?;
+            let mut app = multi.load()?;
+            app.set_value(path, value)?;
+            multi.save(&app)?;
+            info!("{path} = {}", app.get_value(path)?);
+            return Ok(());
+        }
+        let mut app = Self::load(ctx, mode, config)?;
+        app.set_value(path, value)?;
+        app.save()?;
+        info!("{path} = {}", app.get_value(path)?);
         Ok(())
     }
+
+    fn load(
+        ctx: &AppContext,
+        mode: Option<MenuConfigMode>,
+        config: Option<PathBuf>,
+    ) -> Result<AppData> {
+        let (default_name, schema) = match mode {
+            None => (".build.toml", schema_of::<BuildConfig>()?),
+            Some(MenuConfigMode::Qemu) => (".qemu.toml", schema_of::<QemuConfig>()?),
+            Some(MenuConfigMode::Uboot) => (".uboot.toml", schema_of::<UbootConfig>()?),
+            Some(MenuConfigMode::Gdb) => (".gdb.toml", schema_of::<GdbConfig>()?),
+            Some(MenuConfigMode::All) => return Self::multi_config(ctx, config)?.load(),
+        };
+        let path = config.unwrap_or_else(|| ctx.paths.config_file(default_name));
+        AppData::new_with_schema(Some(path), &schema)
+    }
+}
+
+fn schema_of<C: JsonSchema>() -> Result<serde_json::Value> {
+    Ok(serde_json::to_value(schemars::schema_for!(C))?)
 }
@@ -0,0 +1,80 @@
+//! Layout versions of the ostool config files.
+//!
+//! `.build.toml`, `.qemu.toml` and `.uboot.toml` carry a top-level
+//! `version`. When a field is renamed or a section moved, the change is
+//! appended to the file's migrations here with the next version, and files
+//! written for an older layout are upgraded when loaded, instead of failing
+//! to deserialize:
+//!
+//! ```rust,ignore
+//! pub const UBOOT: &[Migration] = &[Migration {
+//!     version: 2,
+//!     changes: &[Change::Move { from: "serial", to: "serial.port" }],
+//! }];
+//! ```
+//!
+//! The upgraded file is written back, keeping the comments of a TOML file,
+//! and the changes are printed. A file without `version` has version 1.
+
+use std::path::Path;
+
+use colored::Colorize;
+use jkconfig::{data::migrate, t};
+
+pub use jkconfig::data::migrate::{Change, Migration};
+
+/// Migrations of `.build.toml`, oldest first.
+pub const BUILD: &[Migration] = &[];
+/// Migrations of `.qemu.toml`, oldest first.
+pub const QEMU: &[Migration] = &[];
+/// Migrations of `.uboot.toml`, oldest first.
+pub const UBOOT: &[Migration] = &[];
+
+/// Current layout version of a config file with `migrations`.
+pub fn current_version(migrations: &[Migration]) -> u32 {
+    migrate::current_version(migrations)
+}
+
+/// Upgrades the config file at `path` with `content` to the current layout,
+/// rewriting the file and printing the changes, and returns the content to
+/// parse.
+///
+/// # Errors
+///
+/// Returns an error if the content cannot be parsed, was written for a
+/// newer ostool, or the file cannot be written.
+pub fn upgrade(path: &Path, content: &str, migrations: &[Migration]) -> anyhow::Result<String> {
+    let upgraded = migrate::migrate_content(path, content, migrations)
+        .map_err(|e| anyhow!(t!("migrate.failed", path = path.display(), error = e)))?;
+    let Some((upgraded, report)) = upgraded else {
+        return Ok(content.to_string());
+    };
+    std::fs::write(path, &upgraded)?;
+    println!(
+        "{}",
+        t!(
+            "migrate.upgraded",
+            path = path.display(),
+            version = current_version(migrations)
+        )
+        .yellow()
+    );
+    for change in report {
+        println!("  {change}");
+    }
+    Ok(upgraded)
+}
+
+/// Like [`upgrade`] for a file that may not exist yet.
+///
+/// # Errors
+///
+/// See [`upgrade`].
+pub fn upgrade_file(path: &Path, migrations: &[Migration]) -> anyhow::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let content = std::fs::read_to_string(path)?;
+    upgrade(path, &content, migrations)?;
+    Ok(())
+}
@@ -0,0 +1,264 @@
+//! Build and run pipeline for embedding ostool in other programs.
+//!
+//! Projects driving their kernel from a `cargo xtask` binary use
+//! [`Pipeline`] instead of spawning the `ostool` CLI:
+//!
+//! ```rust,no_run
+//! use ostool::{ctx::AppContext, pipeline::{FitOptions, Pipeline, QemuOptions}};
+//!
+//! # async fn xtask(ctx: AppContext) -> anyhow::Result<()> {
+//! let built = Pipeline::new(ctx)
+//!     .build()
+//!     .await?
+//!     .package_fit(FitOptions {
+//!         kernel_load_addr: 0x4008_0000,
+//!         ..Default::default()
+//!     })
+//!     .await?;
+//! let run = built.run_qemu(QemuOptions::default()).await?;
+//! assert!(run.passed(), "{:?}", run.end);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Results come back as values. Progress goes through the `log` crate, so
+//! nothing is printed but the output of the build commands unless the
+//! program installs a logger. [`Pipeline::new`] calls [`exit::embed`]:
+//! ostool then installs no signal handler and never exits the process.
+
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    ctx::{AppContext, OutputArtifacts},
+    exit,
+    run::{
+        qemu::{self, BootEnd, Watch},
+        rules::{ConsoleRules, RuleAction},
+        uboot::{FitSpec, make_fit_image},
+    },
+};
+
+/// Default of [`QemuOptions::timeout`].
+pub const DEFAULT_QEMU_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Entry point of the pipeline: what to build.
+pub struct Pipeline {
+    ctx: AppContext,
+    build_config: Option<PathBuf>,
+}
+
+impl Pipeline {
+    /// A pipeline building the project of `ctx.paths`.
+    pub fn new(ctx: AppContext) -> Self {
+        exit::embed();
+        Self {
+            ctx,
+            build_config: None,
+        }
+    }
+
+    /// Builds with this configuration file instead of `.build.toml`.
+    pub fn build_config(mut self, path: impl Into<PathBuf>) -> Self {
+        self.build_config = Some(path.into());
+        self
+    }
+
+    /// Builds the kernel, converting it to BIN when the configuration asks
+    /// for it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`exit::Failure::Build`] if the build
+    /// fails, or another error if the configuration cannot be loaded.
+    pub async fn build(mut self) -> anyhow::Result<Built> {
+        self.ctx.build_for_run(self.build_config).await?;
+        Ok(Built { ctx: self.ctx })
+    }
+}
+
+/// A built kernel, ready to be packaged or run.
+pub struct Built {
+    ctx: AppContext,
+}
+
+impl Built {
+    /// Context holding the configuration and artifacts of the build.
+    pub fn ctx(&self) -> &AppContext {
+        &self.ctx
+    }
+
+    /// Gives the context back, e.g. for the runners of [`crate::run`].
+    pub fn into_ctx(self) -> AppContext {
+        self.ctx
+    }
+
+    /// Files the build and packaging steps produced.
+    pub fn artifacts(&self) -> &OutputArtifacts {
+        &self.ctx.paths.artifacts
+    }
+
+    /// Packages the kernel, converted to BIN if the build did not, into a
+    /// FIT image, recorded as [`OutputArtifacts::fit`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the kernel cannot be converted or the FIT image
+    /// cannot be made.
+    pub async fn package_fit(mut self, opts: FitOptions) -> anyhow::Result<Self> {
+        let kernel = match self.ctx.paths.artifacts.bin.clone() {
+            Some(bin) => bin,
+            None => self.ctx.objcopy_output_bin()?,
+        };
+        let output = opts
+            .output
+            .unwrap_or_else(|| kernel.with_file_name("image.fit"));
+        let spec = FitSpec {
+            kernel,
+            dtb: opts.dtb,
+            dtbo: opts.dtbo,
+            kernel_load_addr: opts.kernel_load_addr,
+            kernel_entry_addr: opts.kernel_entry_addr.unwrap_or(opts.kernel_load_addr),
+            fdt_load_addr: opts.fdt_load_addr,
+            ..Default::default()
+        };
+        make_fit_image(&self.ctx, &spec, &output).await?;
+        self.ctx.paths.artifacts.fit = Some(output);
+        Ok(self)
+    }
+
+    /// Boots the kernel in QEMU until a success or failure rule matches,
+    /// QEMU exits or no output comes for [`QemuOptions::timeout`].
+    ///
+    /// Success comes from the `success` rules of `console_rules` and the
+    /// `success_regex` of the QEMU configuration, failure from the
+    /// `failure` rules and `fail_regex`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the QEMU configuration cannot be loaded, a rule
+    /// is invalid or QEMU cannot be started; how the kernel did is in the
+    /// returned [`QemuRun`].
+    pub async fn run_qemu(&self, opts: QemuOptions) -> anyhow::Result<QemuRun> {
+        let config = qemu::load_qemu_config(&self.ctx, opts.config).await?;
+        let mut success = ConsoleRules::for_runner(&self.ctx, &config.success_regex, &[])?
+            .only(RuleAction::Success);
+        let mut lines = Vec::new();
+        let started = Instant::now();
+        let end = qemu::boot_qemu(
+            self.ctx.clone(),
+            config,
+            opts.append,
+            opts.timeout,
+            |line| {
+                lines.push(line.to_string());
+                match success.check(line) {
+                    Some(_) => Watch::Stop,
+                    None => Watch::ResetTimeout,
+                }
+            },
+        )
+        .await?;
+        Ok(QemuRun {
+            end,
+            lines,
+            elapsed: started.elapsed(),
+        })
+    }
+}
+
+/// How [`Built::package_fit`] makes the FIT image.
+#[derive(Debug, Clone, Default)]
+pub struct FitOptions {
+    /// Address the kernel is loaded to.
+    pub kernel_load_addr: u64,
+    /// Entry point of the kernel, default to `kernel_load_addr`.
+    pub kernel_entry_addr: Option<u64>,
+    /// Device tree blob.
+    pub dtb: Option<PathBuf>,
+    /// Device tree overlays applied on top of `dtb`, in order.
+    pub dtbo: Vec<PathBuf>,
+    /// Address the device tree is loaded to.
+    pub fdt_load_addr: Option<u64>,
+    /// Output file, default to `image.fit` next to the kernel.
+    pub output: Option<PathBuf>,
+}
+
+/// How [`Built::run_qemu`] boots the kernel.
+#[derive(Debug, Clone)]
+pub struct QemuOptions {
+    /// QEMU configuration file, default to `.qemu.toml`.
+    pub config: Option<PathBuf>,
+    /// Extra kernel command line.
+    pub append: Option<String>,
+    /// Longest QEMU may go without output.
+    pub timeout: Duration,
+}
+
+impl Default for QemuOptions {
+    fn default() -> Self {
+        Self {
+            config: None,
+            append: None,
+            timeout: DEFAULT_QEMU_TIMEOUT,
+        }
+    }
+}
+
+/// Outcome of [`Built::run_qemu`].
+#[derive(Debug)]
+pub struct QemuRun {
+    /// How the run ended.
+    pub end: BootEnd,
+    /// Console lines of QEMU and the kernel.
+    pub lines: Vec<String>,
+    /// Time from launch to the end of the run.
+    pub elapsed: Duration,
+}
+
+impl QemuRun {
+    /// Whether a success rule matched, or QEMU exited by itself with a
+    /// success status, as `ostool run qemu` judges it.
+    pub fn passed(&self) -> bool {
+        match &self.end {
+            BootEnd::Stopped => true,
+            BootEnd::Exited(status) => status.success(),
+            BootEnd::Failed { .. } | BootEnd::TimedOut | BootEnd::Crashed { .. } => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use object::Architecture;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_package_fit() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        let kernel = dir.join("kernel.bin");
+        std::fs::write(&kernel, [0x1f, 0x20, 0x03, 0xd5].repeat(256)).unwrap();
+
+        let mut ctx = AppContext {
+            arch: Some(Architecture::Aarch64),
+            ..Default::default()
+        };
+        ctx.paths.artifacts.bin = Some(kernel);
+        let built = Built { ctx }
+            .package_fit(FitOptions {
+                kernel_load_addr: 0x4008_0000,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let fit = built.artifacts().fit.clone().unwrap();
+        assert_eq!(fit, dir.join("image.fit"));
+        let data = std::fs::read(&fit).unwrap();
+        assert_eq!(data[..4], [0xd0, 0x0d, 0xfe, 0xed]);
+    }
+}
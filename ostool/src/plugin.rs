@@ -0,0 +1,321 @@
+//! Runner and packager plugins, `ostool plugin`.
+//!
+//! A plugin is an executable named `ostool-plugin-<name>`, found in
+//! `.ostool/plugins` of the workspace or on `PATH`, so vendor flash tools
+//! and image formats can be added without changing ostool. It speaks
+//! JSON-RPC 2.0 on stdin/stdout, one message per line; its stderr goes to
+//! the terminal.
+//!
+//! ostool first calls `describe`, answered with the plugin's kind:
+//!
+//! ```json
+//! {"name": "rkflash", "kind": "runner", "description": "Flash with rkdeveloptool"}
+//! ```
+//!
+//! After building, `ostool plugin run <name>` calls `run` for a runner or
+//! `package` for a packager, with the build output (`bin` only with
+//! `to_bin`) and the plugin's table of `.plugins.toml` as parameters:
+//!
+//! ```json
+//! {"workspace": "/ws", "manifest": "/ws/kernel", "elf": "/ws/target/.../kernel",
+//!  "bin": "/ws/target/.../kernel.bin", "arch": "aarch64", "args": ["--slot", "a"],
+//!  "config": {"loader": "rk356x_loader.bin"}}
+//! ```
+//!
+//! A packager answers with `{"outputs": ["kernel.ota"]}`, a runner with any
+//! result. While working, a plugin may send `log` notifications,
+//! `{"level": "info", "message": "..."}`.
+
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+use anyhow::Context;
+use colored::Colorize;
+use jkconfig::t;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::ctx::AppContext;
+
+/// File name prefix of plugin executables.
+pub const PREFIX: &str = "ostool-plugin-";
+
+/// What a plugin does.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PluginKind {
+    /// Boots or flashes the kernel, called with `run`.
+    Runner,
+    /// Packs the kernel into an image, called with `package`.
+    Packager,
+}
+
+/// The answer to `describe`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PluginInfo {
+    pub name: String,
+    pub kind: PluginKind,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Arguments for running a plugin.
+#[derive(Debug, Clone, Default)]
+pub struct RunPluginArgs {
+    pub name: String,
+    /// Optional path to the build configuration file.
+    pub build_config: Option<PathBuf>,
+    /// Arguments passed on to the plugin.
+    pub args: Vec<String>,
+}
+
+/// A running plugin process.
+struct Session {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+}
+
+impl Session {
+    fn start(path: &Path, dir: &Path) -> anyhow::Result<Self> {
+        let mut child = Command::new(path)
+            .current_dir(dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .with_context(|| format!("Failed to start {}", path.display()))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Ok(Self {
+            child,
+            stdin,
+            stdout,
+            next_id: 1,
+        })
+    }
+
+    /// Calls `method` and waits for its result, printing `log` notifications.
+    fn call(&mut self, method: &str, params: Value) -> anyhow::Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        writeln!(self.stdin, "{request}")?;
+        self.stdin.flush()?;
+
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.stdout.read_line(&mut line)? == 0 {
+                bail!(t!("plugin.exited", method = method));
+            }
+            let message: Value = serde_json::from_str(line.trim())
+                .with_context(|| t!("plugin.bad_message", line = line.trim()))?;
+            if message.get("id").is_none() {
+                notify(&message);
+                continue;
+            }
+            if message["id"] != id {
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                let text = error["message"].as_str().unwrap_or("unknown error");
+                bail!(t!("plugin.failed", method = method, error = text));
+            }
+            return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    fn finish(mut self) {
+        drop(self.stdin);
+        let _ = self.child.wait();
+    }
+}
+
+fn notify(message: &Value) {
+    if message["method"] != "log" {
+        return;
+    }
+    let text = message["params"]["message"].as_str().unwrap_or_default();
+    match message["params"]["level"].as_str().unwrap_or("info") {
+        "error" => error!("{text}"),
+        "warn" => warn!("{text}"),
+        "debug" => debug!("{text}"),
+        _ => info!("{text}"),
+    }
+}
+
+/// Finds the plugin executables, by name; the workspace shadows `PATH`.
+pub fn discover(workspace: &Path) -> BTreeMap<String, PathBuf> {
+    let mut dirs = vec![workspace.join(".ostool/plugins")];
+    if let Some(paths) = std::env::var_os("PATH") {
+        dirs.extend(std::env::split_paths(&paths));
+    }
+    let mut plugins = BTreeMap::new();
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(name) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.strip_prefix(PREFIX))
+            else {
+                continue;
+            };
+            if path.is_file() && !plugins.contains_key(name) {
+                plugins.insert(name.to_string(), path);
+            }
+        }
+    }
+    plugins
+}
+
+/// Asks the plugin at `path` what it is.
+///
+/// # Errors
+///
+/// Returns an error if the plugin cannot be started or answers wrongly.
+pub fn describe(path: &Path, dir: &Path) -> anyhow::Result<PluginInfo> {
+    let mut session = Session::start(path, dir)?;
+    let info = session.call("describe", json!({}));
+    session.finish();
+    Ok(serde_json::from_value(info?)?)
+}
+
+/// Prints the plugins with their kind and description.
+pub fn list(ctx: &AppContext) {
+    let plugins = discover(&ctx.paths.workspace);
+    if plugins.is_empty() {
+        println!("{}", t!("plugin.none", prefix = PREFIX));
+        return;
+    }
+    for (name, path) in plugins {
+        match describe(&path, &ctx.paths.workspace) {
+            Ok(info) => println!(
+                "{:<20} {:<9} {}",
+                name.bold(),
+                format!("{:?}", info.kind).to_lowercase(),
+                info.description
+            ),
+            Err(e) => println!("{:<20} {}", name.bold(), format!("{e:#}").red()),
+        }
+        println!("{:<20} {}", "", path.display().to_string().dimmed());
+    }
+}
+
+/// Builds the kernel and hands it to the plugin `args.name`.
+///
+/// # Errors
+///
+/// Returns an error if the plugin is not found, the build fails, or the
+/// plugin reports an error.
+pub async fn run_plugin(mut ctx: AppContext, args: RunPluginArgs) -> anyhow::Result<()> {
+    let plugins = discover(&ctx.paths.workspace);
+    let Some(path) = plugins.get(&args.name) else {
+        bail!(t!("plugin.not_found", name = args.name, prefix = PREFIX));
+    };
+    let config = plugin_config(&ctx, &args.name)?;
+
+    ctx.build_for_run(args.build_config.clone()).await?;
+
+    let mut session = Session::start(path, &ctx.paths.workspace)?;
+    let res = (|| {
+        let info: PluginInfo = serde_json::from_value(session.call("describe", json!({}))?)?;
+        let params = json!({
+            "workspace": ctx.paths.workspace,
+            "manifest": ctx.paths.manifest,
+            "elf": ctx.paths.artifacts.elf,
+            "bin": ctx.paths.artifacts.bin,
+            "arch": ctx.arch.map(|a| format!("{a:?}").to_lowercase()),
+            "args": args.args,
+            "config": config,
+        });
+        match info.kind {
+            PluginKind::Runner => {
+                session.call("run", params)?;
+            }
+            PluginKind::Packager => {
+                let result = session.call("package", params)?;
+                for output in result["outputs"].as_array().into_iter().flatten() {
+                    if let Some(output) = output.as_str() {
+                        println!("{}", t!("plugin.output", path = output).green());
+                    }
+                }
+            }
+        }
+        anyhow::Ok(())
+    })();
+    session.finish();
+    res
+}
+
+/// The plugin's table of `.plugins.toml`, `null` when there is none.
+fn plugin_config(ctx: &AppContext, name: &str) -> anyhow::Result<Value> {
+    let path = ctx.paths.config_file(".plugins.toml");
+    if !path.exists() {
+        return Ok(Value::Null);
+    }
+    let content = crate::utils::replace_env_placeholders(&std::fs::read_to_string(&path)?)?;
+    let mut tables: BTreeMap<String, toml::Value> =
+        toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(match tables.remove(name) {
+        Some(table) => serde_json::to_value(table)?,
+        None => Value::Null,
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    #[test]
+    fn test_describe_and_call() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        let plugins = dir.join(".ostool/plugins");
+        std::fs::create_dir_all(&plugins).unwrap();
+        let path = plugins.join("ostool-plugin-ota");
+        std::fs::write(
+            &path,
+            r#"#!/bin/sh
+read request
+echo '{"jsonrpc":"2.0","id":1,"result":{"name":"ota","kind":"packager","description":"OTA image"}}'
+read request
+echo '{"jsonrpc":"2.0","method":"log","params":{"level":"info","message":"packing"}}'
+case "$request" in
+  *'"args":["fast"]'*) echo '{"jsonrpc":"2.0","id":2,"result":{"outputs":["kernel.ota"]}}' ;;
+  *) echo '{"jsonrpc":"2.0","id":2,"error":{"code":1,"message":"bad args"}}' ;;
+esac
+"#,
+        )
+        .unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert_eq!(discover(&dir).get("ota"), Some(&path));
+        let info = describe(&path, &dir).unwrap();
+        assert_eq!(info.kind, PluginKind::Packager);
+        assert_eq!(info.description, "OTA image");
+
+        let mut session = Session::start(&path, &dir).unwrap();
+        session.call("describe", json!({})).unwrap();
+        let result = session.call("package", json!({"args": ["fast"]})).unwrap();
+        assert_eq!(result["outputs"][0], "kernel.ota");
+        session.finish();
+
+        let mut session = Session::start(&path, &dir).unwrap();
+        session.call("describe", json!({})).unwrap();
+        let err = session.call("package", json!({"args": []})).unwrap_err();
+        assert!(err.to_string().contains("bad args"));
+        session.finish();
+    }
+}
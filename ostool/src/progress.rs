@@ -0,0 +1,240 @@
+//! Progress bars of transfers, downloads and conversions.
+//!
+//! All bars are drawn on stderr through one [`MultiProgress`], so the bars
+//! of parallel steps stack instead of overwriting each other, and log
+//! records and [`println`] lines go above them instead of through them.
+//!
+//! Bars are only drawn when both stdout and stderr are terminals. Otherwise,
+//! as in CI logs or behind `| tee`, a bar logs its position every
+//! [`LOG_INTERVAL`] and once more when it finishes.
+
+use std::{
+    borrow::Cow,
+    io::IsTerminal,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use jkconfig::t;
+
+use crate::utils::human_size;
+
+/// How often a bar logs its position when it is not drawn.
+pub const LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+const BAR_TEMPLATE: &str = "{msg:12} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})";
+const BYTES_TEMPLATE: &str = "{spinner:.green} {msg} [{elapsed_precise}] {bytes} ({bytes_per_sec})";
+const SPINNER_TEMPLATE: &str = "{spinner:.green} {msg} [{elapsed_precise}]";
+
+static MULTI: LazyLock<MultiProgress> = LazyLock::new(|| {
+    if std::io::stdout().is_terminal() && std::io::stderr().is_terminal() {
+        MultiProgress::new()
+    } else {
+        MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+    }
+});
+
+/// Whether bars are drawn on the terminal.
+pub fn is_drawn() -> bool {
+    !MULTI.is_hidden()
+}
+
+/// Runs `f` with the bars cleared, for output written directly to the
+/// terminal, e.g. by a logger.
+pub fn suspend<R>(f: impl FnOnce() -> R) -> R {
+    MULTI.suspend(f)
+}
+
+/// Prints a line to stdout above the bars.
+pub fn println(line: impl AsRef<str>) {
+    if is_drawn() {
+        let _ = MULTI.println(line);
+    } else {
+        println!("{}", line.as_ref());
+    }
+}
+
+/// A bar of `len` bytes, or a byte counter while the length is unknown.
+pub fn bytes(message: impl Into<Cow<'static, str>>, len: Option<u64>) -> Progress {
+    let bar = match len {
+        Some(len) => ProgressBar::new(len).with_style(style(BAR_TEMPLATE)),
+        None => ProgressBar::no_length().with_style(style(BYTES_TEMPLATE)),
+    };
+    Progress::new(bar.with_message(message), Kind::Bytes)
+}
+
+/// A spinner for a step whose progress is not known, e.g. objcopy.
+pub fn spinner(message: impl Into<Cow<'static, str>>) -> Progress {
+    let bar = ProgressBar::new_spinner()
+        .with_style(style(SPINNER_TEMPLATE))
+        .with_message(message);
+    let progress = Progress::new(bar, Kind::Spinner);
+    progress.bar.enable_steady_tick(Duration::from_millis(100));
+    progress
+}
+
+/// A bar that is neither drawn nor logged, e.g. while several boards
+/// share the terminal.
+pub fn hidden() -> Progress {
+    Progress {
+        bar: ProgressBar::hidden(),
+        kind: Kind::Spinner,
+        last_log: None,
+    }
+}
+
+fn style(template: &str) -> ProgressStyle {
+    ProgressStyle::with_template(template)
+        .unwrap()
+        .progress_chars("#>-")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Bytes,
+    Spinner,
+}
+
+/// A progress bar, or its log lines when bars are not drawn.
+///
+/// The bar is cleared when dropped; [`Progress::finish`] logs the final
+/// line as well.
+pub struct Progress {
+    bar: ProgressBar,
+    kind: Kind,
+    /// Time of the last log line, `None` while the bar is drawn.
+    last_log: Option<Mutex<Instant>>,
+}
+
+impl Progress {
+    fn new(bar: ProgressBar, kind: Kind) -> Self {
+        let drawn = is_drawn();
+        Self {
+            bar: MULTI.add(bar),
+            kind,
+            last_log: (!drawn).then(|| Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Sets the total, turning a byte counter into a bar.
+    pub fn set_length(&self, len: u64) {
+        if self.bar.length().is_none() && self.kind == Kind::Bytes {
+            self.bar.set_style(style(BAR_TEMPLATE));
+        }
+        self.bar.set_length(len);
+    }
+
+    /// Sets the current position.
+    pub fn set_position(&self, pos: u64) {
+        self.bar.set_position(pos);
+        self.log_periodically();
+    }
+
+    /// Advances the position by `delta`.
+    pub fn inc(&self, delta: u64) {
+        self.bar.inc(delta);
+        self.log_periodically();
+    }
+
+    /// Replaces the message, e.g. when a step moves to its next phase.
+    pub fn set_message(&self, message: impl Into<Cow<'static, str>>) {
+        self.bar.set_message(message);
+    }
+
+    /// Clears the bar, logging how the step went if bars are not drawn.
+    pub fn finish(self) {
+        if self.last_log.is_some() {
+            info!("{}", self.status(true));
+        }
+    }
+
+    fn log_periodically(&self) {
+        let Some(last) = &self.last_log else {
+            return;
+        };
+        let mut last = last.lock().unwrap();
+        if last.elapsed() >= LOG_INTERVAL {
+            *last = Instant::now();
+            info!("{}", self.status(false));
+        }
+    }
+
+    fn status(&self, finished: bool) -> String {
+        let done = match self.kind {
+            Kind::Bytes => Some(bytes_done(self.bar.position(), self.bar.length())),
+            Kind::Spinner => None,
+        };
+        status_line(&self.bar.message(), done, self.bar.elapsed(), finished)
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+/// `1.0 MiB / 4.0 MiB (25%)`, or `1.0 MiB` without a length.
+fn bytes_done(pos: u64, len: Option<u64>) -> String {
+    match len {
+        Some(len) if len > 0 => format!(
+            "{} / {} ({}%)",
+            human_size(pos),
+            human_size(len),
+            pos.min(len) * 100 / len
+        ),
+        _ => human_size(pos),
+    }
+}
+
+fn status_line(message: &str, done: Option<String>, elapsed: Duration, finished: bool) -> String {
+    let elapsed = format!("{:.1}s", elapsed.as_secs_f64());
+    let state = if finished {
+        t!("progress.finished", elapsed = elapsed)
+    } else {
+        t!("progress.running", elapsed = elapsed)
+    };
+    match done {
+        Some(done) => format!("{message}: {done}, {state}"),
+        None => format!("{message}: {state}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_line() {
+        assert_eq!(
+            bytes_done(1 << 20, Some(4 << 20)),
+            "1.0 MiB / 4.0 MiB (25%)"
+        );
+        assert_eq!(bytes_done(1536, None), "1.5 KiB");
+        assert_eq!(bytes_done(0, Some(0)), "0 B");
+
+        let line = status_line(
+            "Downloading",
+            Some(bytes_done(2 << 20, Some(4 << 20))),
+            Duration::from_millis(5250),
+            false,
+        );
+        assert!(
+            line.starts_with("Downloading: 2.0 MiB / 4.0 MiB (50%), "),
+            "{line}"
+        );
+        assert!(line.contains("5.2s") || line.contains("5.3s"), "{line}");
+        assert!(status_line("objcopy", None, Duration::ZERO, true).starts_with("objcopy: "));
+    }
+
+    #[test]
+    fn test_length_set_later() {
+        let progress = hidden();
+        progress.set_length(100);
+        progress.set_position(40);
+        progress.inc(10);
+        assert_eq!(progress.bar.position(), 50);
+        assert_eq!(progress.bar.length(), Some(100));
+    }
+}
@@ -0,0 +1,346 @@
+//! `ostool agent`, serving the serial ports and power switches of the local
+//! boards to remote clients.
+//!
+//! The agent is configured by `.agent.toml`:
+//!
+//! ```toml
+//! listen = "0.0.0.0:7878"
+//! token = "${env:OSTOOL_AGENT_TOKEN}"
+//! tls_cert = "agent.crt"
+//! tls_key = "agent.key"
+//!
+//! [[boards]]
+//! name = "rk3568-a"
+//! serial = "/dev/ttyUSB0"
+//! baud_rate = 1500000
+//! reset_cmd = "usbrelay RELAY_1=1 && sleep 1 && usbrelay RELAY_1=0"
+//! power_off_cmd = "usbrelay RELAY_1=1"
+//! ```
+//!
+//! Every client must present the token. A board's console serves one
+//! client at a time.
+
+use std::{
+    collections::HashSet,
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use jkconfig::t;
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{Action, Conn, HANDSHAKE_TIMEOUT, Reply, Request, pump};
+use crate::{ctx::AppContext, sterm::ports, utils::replace_env_placeholders};
+
+/// How long a console request waits for the previous session to end.
+const RELEASE_GRACE: Duration = Duration::from_secs(1);
+
+/// Agent settings, `.agent.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct AgentConfig {
+    /// Address to listen on, `host:port`
+    pub listen: String,
+    /// Token every client must present
+    pub token: String,
+    /// TLS certificate chain (PEM); plain TCP when unset
+    #[schemars(extend("format" = "file-path"))]
+    pub tls_cert: Option<String>,
+    /// TLS private key (PEM)
+    #[schemars(extend("format" = "file-path"))]
+    pub tls_key: Option<String>,
+    #[serde(default)]
+    pub boards: Vec<AgentBoard>,
+}
+
+/// A board attached to the agent.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct AgentBoard {
+    pub name: String,
+    /// Serial console device
+    pub serial: String,
+    pub baud_rate: u32,
+    /// Shell command resetting the board
+    pub reset_cmd: Option<String>,
+    /// Shell command powering the board off
+    pub power_off_cmd: Option<String>,
+}
+
+/// Arguments for running the agent.
+#[derive(Debug, Clone, Default)]
+pub struct RunAgentArgs {
+    /// Optional path to the agent configuration file.
+    pub config: Option<PathBuf>,
+}
+
+struct State {
+    ctx: AppContext,
+    config: AgentConfig,
+    tls: Option<Arc<ServerConfig>>,
+    /// Boards whose console is in use.
+    busy: Mutex<HashSet<String>>,
+}
+
+/// Serves the boards of the agent configuration until interrupted.
+///
+/// # Errors
+///
+/// Returns an error if the configuration is missing or invalid, or the
+/// address cannot be bound.
+pub async fn run_agent(ctx: AppContext, args: RunAgentArgs) -> anyhow::Result<()> {
+    let path = args
+        .config
+        .unwrap_or_else(|| ctx.paths.config_file(".agent.toml"));
+    if !path.exists() {
+        bail!(t!("agent.no_config", path = path.display()));
+    }
+    let content = replace_env_placeholders(&tokio::fs::read_to_string(&path).await?)?;
+    let config: AgentConfig = ctx.parse_config(&path, &content)?;
+    if config.token.trim().is_empty() {
+        bail!(t!("agent.no_token"));
+    }
+    let tls = match (&config.tls_cert, &config.tls_key) {
+        (Some(cert), Some(key)) => Some(Arc::new(server_config(cert, key)?)),
+        (None, None) => {
+            warn!("{}", t!("agent.no_tls"));
+            None
+        }
+        _ => bail!(t!("agent.tls_incomplete")),
+    };
+
+    let listener = TcpListener::bind(&config.listen)
+        .with_context(|| format!("Failed to listen on {}", config.listen))?;
+    println!(
+        "{}",
+        t!(
+            "agent.listening",
+            addr = config.listen,
+            boards = config
+                .boards
+                .iter()
+                .map(|b| b.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    );
+    let state = Arc::new(State {
+        ctx,
+        config,
+        tls,
+        busy: Mutex::new(HashSet::new()),
+    });
+    tokio::task::spawn_blocking(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let state = state.clone();
+            thread::spawn(move || {
+                let peer = stream
+                    .peer_addr()
+                    .map(|a| a.to_string())
+                    .unwrap_or_default();
+                if let Err(e) = handle(&state, stream) {
+                    warn!("{peer}: {e:#}");
+                }
+            });
+        }
+    })
+    .await?;
+    Ok(())
+}
+
+fn server_config(cert: &str, key: &str) -> anyhow::Result<ServerConfig> {
+    let pem = std::fs::read(cert).with_context(|| format!("Failed to read {cert}"))?;
+    let certs = rustls_pemfile::certs(&mut pem.as_slice()).collect::<Result<Vec<_>, _>>()?;
+    let pem = std::fs::read(key).with_context(|| format!("Failed to read {key}"))?;
+    let key = rustls_pemfile::private_key(&mut pem.as_slice())?
+        .ok_or_else(|| anyhow!("no private key in {key}"))?;
+    Ok(
+        ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)?,
+    )
+}
+
+fn handle(state: &State, tcp: TcpStream) -> anyhow::Result<()> {
+    let peer = tcp.peer_addr()?;
+    tcp.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    tcp.set_nodelay(true)?;
+    let mut conn = match &state.tls {
+        Some(tls) => Conn::Server(Box::new(StreamOwned::new(
+            ServerConnection::new(tls.clone())?,
+            tcp,
+        ))),
+        None => Conn::Plain(tcp),
+    };
+
+    let request: Request = conn.read_json()?;
+    if !token_matches(&request.token, &state.config.token) {
+        conn.write_json(&Reply {
+            error: Some(t!("agent.bad_token").to_string()),
+        })?;
+        bail!(t!("agent.bad_token"));
+    }
+    let Some(board) = state.config.boards.iter().find(|b| b.name == request.board) else {
+        let error = t!("agent.unknown_board", name = request.board);
+        conn.write_json(&Reply {
+            error: Some(error.clone()),
+        })?;
+        bail!(error);
+    };
+    info!("{peer}: {:?} {}", request.action, board.name);
+
+    let cmd = match request.action {
+        Action::Serial => return serve_serial(state, board, conn),
+        Action::Reset => &board.reset_cmd,
+        Action::PowerOff => &board.power_off_cmd,
+    };
+    let res = match cmd {
+        Some(cmd) if !cmd.trim().is_empty() => state.ctx.shell_run_cmd(cmd),
+        _ => Ok(()),
+    };
+    conn.write_json(&Reply {
+        error: res.as_ref().err().map(|e| format!("{e:#}")),
+    })?;
+    conn.close();
+    res
+}
+
+fn serve_serial(state: &State, board: &AgentBoard, mut conn: Conn) -> anyhow::Result<()> {
+    // 刚断开的会话需要片刻才能释放串口，重连时稍等
+    let deadline = Instant::now() + RELEASE_GRACE;
+    while !state.busy.lock().unwrap().insert(board.name.clone()) {
+        if Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+            continue;
+        }
+        conn.write_json(&Reply {
+            error: Some(t!("agent.busy", name = board.name)),
+        })?;
+        return Ok(());
+    }
+    let res = bridge(board, conn);
+    state.busy.lock().unwrap().remove(&board.name);
+    res
+}
+
+fn bridge(board: &AgentBoard, mut conn: Conn) -> anyhow::Result<()> {
+    let port = serialport::new(ports::normalize(&board.serial), board.baud_rate)
+        .timeout(Duration::from_millis(10))
+        .open();
+    let mut port = match port {
+        Ok(port) => port,
+        Err(e) => {
+            let error = format!("Failed to open serial port {}: {e}", board.serial);
+            conn.write_json(&Reply {
+                error: Some(error.clone()),
+            })?;
+            bail!(error);
+        }
+    };
+    let mut reader = port.try_clone()?;
+    conn.write_json(&Reply::default())?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let (tx, rx) = mpsc::channel();
+    let reader_thread = {
+        let stop = stop.clone();
+        thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            while !stop.load(Ordering::Acquire) {
+                match reader.read(&mut buf) {
+                    Ok(0) => {}
+                    Ok(n) => {
+                        if tx.send(buf[..n].to_vec()).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) if e.kind() == ErrorKind::TimedOut => {}
+                    Err(_) => break,
+                }
+            }
+        })
+    };
+    let res = pump(conn, rx, |data| port.write_all(data));
+    stop.store(true, Ordering::Release);
+    let _ = reader_thread.join();
+    res.map_err(Into::into)
+}
+
+/// Compares tokens in time independent of where they differ.
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remote::{self, RemoteConfig};
+
+    #[test]
+    fn test_token_matches() {
+        assert!(token_matches("s3cret", "s3cret"));
+        assert!(!token_matches("s3cret", "s3cres"));
+        assert!(!token_matches("s3cre", "s3cret"));
+    }
+
+    #[test]
+    fn test_reset_over_tcp() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        let marker = dir.join("reset");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut ctx = AppContext::default();
+        ctx.paths.manifest = dir.clone();
+        let state = Arc::new(State {
+            ctx,
+            config: AgentConfig {
+                listen: addr.to_string(),
+                token: "s3cret".into(),
+                boards: vec![AgentBoard {
+                    name: "a".into(),
+                    serial: "/dev/null".into(),
+                    baud_rate: 115200,
+                    reset_cmd: Some(format!("touch {}", marker.display())),
+                    power_off_cmd: None,
+                }],
+                ..Default::default()
+            },
+            tls: None,
+            busy: Mutex::new(HashSet::new()),
+        });
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let _ = handle(&state, stream);
+            }
+        });
+
+        let remote = |board: &str, token: &str| RemoteConfig {
+            agent: addr.to_string(),
+            board: board.into(),
+            token: token.into(),
+            ..Default::default()
+        };
+        remote::power(&remote("a", "s3cret"), Action::Reset).unwrap();
+        assert!(marker.exists());
+        remote::power(&remote("a", "s3cret"), Action::PowerOff).unwrap();
+        assert!(remote::power(&remote("a", "wrong"), Action::Reset).is_err());
+        assert!(remote::power(&remote("b", "s3cret"), Action::Reset).is_err());
+    }
+}
@@ -0,0 +1,358 @@
+//! Remote board access through `ostool agent`.
+//!
+//! An agent runs on the machine the boards are attached to and owns their
+//! serial ports and power switches. Clients connect over TCP, optionally
+//! TLS, and send one JSON line with the access token, the board and an
+//! action; the agent answers with one JSON line. For [`Action::Serial`] the
+//! connection then carries the raw console bytes in both directions, so the
+//! U-Boot runner and [`SerialTerm`](crate::sterm::SerialTerm) work on it
+//! like on a local port.
+//!
+//! A board is reached remotely by adding a `[remote]` section to its
+//! `.uboot.toml`:
+//!
+//! ```toml
+//! [remote]
+//! agent = "lab.example.com:7878"
+//! board = "rk3568-a"
+//! token = "${env:OSTOOL_AGENT_TOKEN}"
+//! tls = true
+//! ```
+
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{
+        Arc,
+        mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError},
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::Context;
+use jkconfig::t;
+use rustls::{
+    ClientConfig, ClientConnection, RootCertStore, ServerConnection, StreamOwned,
+    pki_types::ServerName,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+/// The agent serving boards to remote clients.
+pub mod agent;
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Read timeout of the pump, bounding the latency of outgoing data.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// Read timeout of the console, like a local serial port.
+const READ_TIMEOUT: Duration = Duration::from_millis(200);
+const MAX_LINE: usize = 64 * 1024;
+
+/// A board reached through an agent, the `[remote]` section of a runner config.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct RemoteConfig {
+    /// Agent address, `host:port`
+    pub agent: String,
+    /// Board name on the agent
+    pub board: String,
+    /// Access token of the agent, e.g. `${env:OSTOOL_AGENT_TOKEN}`
+    pub token: String,
+    /// Connect with TLS
+    #[serde(default)]
+    pub tls: bool,
+    /// CA certificate (PEM) verifying the agent, e.g. its self-signed
+    /// certificate; the system web PKI roots when unset
+    #[schemars(extend("format" = "file-path"))]
+    pub ca_cert: Option<String>,
+}
+
+/// What a client asks the agent for.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Stream the serial console.
+    Serial,
+    /// Run the board's reset command.
+    Reset,
+    /// Run the board's power off command.
+    PowerOff,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Request {
+    token: String,
+    board: String,
+    action: Action,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Reply {
+    error: Option<String>,
+}
+
+/// Connection between client and agent.
+enum Conn {
+    Plain(TcpStream),
+    Client(Box<StreamOwned<ClientConnection, TcpStream>>),
+    Server(Box<StreamOwned<ServerConnection, TcpStream>>),
+}
+
+impl Conn {
+    fn tcp(&self) -> &TcpStream {
+        match self {
+            Self::Plain(s) => s,
+            Self::Client(s) => &s.sock,
+            Self::Server(s) => &s.sock,
+        }
+    }
+
+    /// Ends the connection, telling a TLS peer it is complete.
+    fn close(mut self) {
+        match &mut self {
+            Self::Plain(_) => {}
+            Self::Client(s) => s.conn.send_close_notify(),
+            Self::Server(s) => s.conn.send_close_notify(),
+        }
+        let _ = self.flush();
+    }
+
+    fn read_json<T: DeserializeOwned>(&mut self) -> anyhow::Result<T> {
+        let mut line = Vec::new();
+        let mut byte = [0u8; 1];
+        loop {
+            if self.read(&mut byte)? == 0 {
+                bail!(t!("remote.closed"));
+            }
+            if byte[0] == b'\n' {
+                break;
+            }
+            if line.len() >= MAX_LINE {
+                bail!("handshake line too long");
+            }
+            line.push(byte[0]);
+        }
+        Ok(serde_json::from_slice(&line)?)
+    }
+
+    fn write_json(&mut self, value: &impl Serialize) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(value)?;
+        line.push(b'\n');
+        self.write_all(&line)?;
+        self.flush()?;
+        Ok(())
+    }
+}
+
+impl Read for Conn {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(s) => s.read(buf),
+            Self::Client(s) => s.read(buf),
+            Self::Server(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Conn {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(s) => s.write(buf),
+            Self::Client(s) => s.write(buf),
+            Self::Server(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(s) => s.flush(),
+            Self::Client(s) => s.flush(),
+            Self::Server(s) => s.flush(),
+        }
+    }
+}
+
+/// Opens the serial console of the board through its agent.
+///
+/// Returns the console as reader and writer; reads time out like a local
+/// serial port.
+///
+/// # Errors
+///
+/// Returns an error if the agent cannot be reached or refuses the request.
+pub fn open_serial(
+    remote: &RemoteConfig,
+) -> anyhow::Result<(Box<dyn Read + Send>, Box<dyn Write + Send>)> {
+    let conn = connect(remote, Action::Serial)?;
+    let (in_tx, in_rx) = mpsc::channel::<Vec<u8>>();
+    let (out_tx, out_rx) = mpsc::channel::<Vec<u8>>();
+    thread::spawn(move || {
+        let res = pump(conn, out_rx, |data| {
+            in_tx
+                .send(data.to_vec())
+                .map_err(|_| io::Error::from(ErrorKind::BrokenPipe))
+        });
+        if let Err(e) = res {
+            debug!("remote console closed: {e}");
+        }
+    });
+    Ok((
+        Box::new(ChannelReader {
+            rx: in_rx,
+            buf: Vec::new(),
+            pos: 0,
+        }),
+        Box::new(ChannelWriter { tx: out_tx }),
+    ))
+}
+
+/// Asks the agent to reset or power off the board.
+///
+/// # Errors
+///
+/// Returns an error if the agent cannot be reached or the command fails.
+pub fn power(remote: &RemoteConfig, action: Action) -> anyhow::Result<()> {
+    connect(remote, action)?.close();
+    Ok(())
+}
+
+fn connect(remote: &RemoteConfig, action: Action) -> anyhow::Result<Conn> {
+    let addr = remote
+        .agent
+        .to_socket_addrs()
+        .with_context(|| t!("remote.bad_address", agent = remote.agent))?
+        .next()
+        .ok_or_else(|| anyhow!(t!("remote.bad_address", agent = remote.agent)))?;
+    let tcp = TcpStream::connect_timeout(&addr, HANDSHAKE_TIMEOUT)
+        .with_context(|| t!("remote.connect_failed", agent = remote.agent))?;
+    tcp.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    tcp.set_nodelay(true)?;
+
+    let mut conn = if remote.tls {
+        let host = remote
+            .agent
+            .rsplit_once(':')
+            .map_or(remote.agent.as_str(), |(host, _)| host)
+            .trim_start_matches('[')
+            .trim_end_matches(']');
+        let name = ServerName::try_from(host.to_string())?;
+        let tls = ClientConnection::new(Arc::new(client_config(remote)?), name)?;
+        Conn::Client(Box::new(StreamOwned::new(tls, tcp)))
+    } else {
+        Conn::Plain(tcp)
+    };
+
+    conn.write_json(&Request {
+        token: remote.token.clone(),
+        board: remote.board.clone(),
+        action,
+    })?;
+    let reply: Reply = conn.read_json()?;
+    if let Some(error) = reply.error {
+        bail!(t!("remote.refused", agent = remote.agent, error = error));
+    }
+    Ok(conn)
+}
+
+fn client_config(remote: &RemoteConfig) -> anyhow::Result<ClientConfig> {
+    let mut roots = RootCertStore::empty();
+    match &remote.ca_cert {
+        Some(path) => {
+            let pem = std::fs::read(path).with_context(|| format!("Failed to read {path}"))?;
+            for cert in rustls_pemfile::certs(&mut pem.as_slice()) {
+                roots.add(cert?)?;
+            }
+        }
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+    Ok(
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth(),
+    )
+}
+
+/// Copies `outgoing` to the connection and the connection to `deliver`
+/// until either side closes.
+fn pump(
+    mut conn: Conn,
+    outgoing: Receiver<Vec<u8>>,
+    mut deliver: impl FnMut(&[u8]) -> io::Result<()>,
+) -> io::Result<()> {
+    conn.tcp().set_read_timeout(Some(POLL_INTERVAL))?;
+    let mut buf = [0u8; 4096];
+    loop {
+        let mut wrote = false;
+        loop {
+            match outgoing.try_recv() {
+                Ok(data) => {
+                    conn.write_all(&data)?;
+                    wrote = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    conn.close();
+                    return Ok(());
+                }
+            }
+        }
+        if wrote {
+            conn.flush()?;
+        }
+        match conn.read(&mut buf) {
+            Ok(0) => {
+                conn.close();
+                return Ok(());
+            }
+            Ok(n) => deliver(&buf[..n])?,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+struct ChannelReader {
+    rx: Receiver<Vec<u8>>,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.buf.len() {
+            self.buf = match self.rx.recv_timeout(READ_TIMEOUT) {
+                Ok(data) => data,
+                Err(RecvTimeoutError::Timeout) => return Err(ErrorKind::TimedOut.into()),
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(io::Error::new(
+                        ErrorKind::ConnectionAborted,
+                        t!("remote.closed"),
+                    ));
+                }
+            };
+            self.pos = 0;
+        }
+        let n = out.len().min(self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+struct ChannelWriter {
+    tx: Sender<Vec<u8>>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.tx
+            .send(buf.to_vec())
+            .map_err(|_| io::Error::new(ErrorKind::BrokenPipe, t!("remote.closed")))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
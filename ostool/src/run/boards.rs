@@ -0,0 +1,328 @@
+//! Parallel runs on several boards, `ostool boards`.
+//!
+//! The kernel is built once and booted on every board of `.boards.toml` at
+//! the same time. Each board has its own U-Boot configuration with its
+//! serial port, DTB and network settings:
+//!
+//! ```toml
+//! # Seconds each board may take to match a `success_regex`
+//! timeout = 300
+//!
+//! [[boards]]
+//! name = "rk3568-a"
+//! config = "boards/rk3568-a.uboot.toml"
+//!
+//! [[boards]]
+//! name = "rk3568-b"
+//! config = "boards/rk3568-b.uboot.toml"
+//! ```
+//!
+//! Every board gets its own FIT image, `image-<name>.fit`, so boards loading
+//! over TFTP fetch their own file from the one server started for all of
+//! them. Console lines are interleaved as they arrive, prefixed with the
+//! board name, and a pass/fail summary is printed at the end.
+
+use std::{
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+use colored::{Color, Colorize};
+use jkconfig::t;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ctx::AppContext,
+    deploy::SdMuxConfig,
+    exit::Failure,
+    run::{
+        tftp,
+        uboot::{BoardRun, UbootConfig, load_uboot_config, run_board},
+    },
+};
+
+const DEFAULT_TIMEOUT: u64 = 300;
+const COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Yellow,
+    Color::Blue,
+    Color::Green,
+    Color::BrightRed,
+];
+
+/// Boards of a parallel run, `.boards.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct BoardsConfig {
+    /// Seconds each board may take to match a `success_regex`, 300 when unset.
+    pub timeout: Option<u64>,
+    #[serde(default)]
+    pub boards: Vec<BoardProfile>,
+}
+
+/// One board of a parallel run.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct BoardProfile {
+    /// Board name, unique within the file.
+    pub name: String,
+    /// U-Boot configuration of the board, relative to the directory of the
+    /// config files, see [`PathConfig::configs`](crate::ctx::PathConfig::configs).
+    #[schemars(extend("format" = "file-path"))]
+    pub config: String,
+    /// SD card multiplexer for `ostool deploy`.
+    pub sd_mux: Option<SdMuxConfig>,
+}
+
+/// Arguments for a parallel run.
+#[derive(Debug, Clone, Default)]
+pub struct RunBoardsArgs {
+    /// Optional path to the build configuration file.
+    pub build_config: Option<PathBuf>,
+    /// Optional path to the boards file.
+    pub boards_config: Option<PathBuf>,
+    /// Only run these boards; all when empty.
+    pub only: Vec<String>,
+    /// Seconds each board may take, overriding the config.
+    pub timeout: Option<u64>,
+}
+
+/// Result of one board.
+#[derive(Debug)]
+pub struct BoardResult {
+    pub name: String,
+    /// Why the board failed, if it did.
+    pub error: Option<String>,
+    pub duration: Duration,
+}
+
+/// Builds the kernel once and boots it on all boards in parallel.
+///
+/// Failed boards do not make this fail; see [`ensure_passed`].
+///
+/// # Errors
+///
+/// Returns an error if the boards file or a board configuration is missing
+/// or invalid, or the build fails.
+pub async fn run_boards(
+    mut ctx: AppContext,
+    args: RunBoardsArgs,
+) -> anyhow::Result<Vec<BoardResult>> {
+    let config = load_boards_config(&ctx, args.boards_config.clone()).await?;
+    let boards = select(&config.boards, &args.only)?;
+    let timeout = Duration::from_secs(args.timeout.or(config.timeout).unwrap_or(DEFAULT_TIMEOUT));
+
+    let mut configs = Vec::new();
+    for board in &boards {
+        let path = ctx.paths.config_file(&board.config);
+        if !path.exists() {
+            bail!(t!(
+                "boards.config_missing",
+                name = board.name,
+                path = path.display()
+            ));
+        }
+        configs.push(load_uboot_config(&ctx, Some(path)).await?);
+    }
+    check_serial_ports(&boards, &configs)?;
+
+    ctx.build_for_run(args.build_config.clone()).await?;
+    ctx.objcopy_output_bin()?;
+    if configs
+        .iter()
+        .any(|c| c.net.as_ref().is_some_and(|n| n.tftp_dir.is_none()))
+    {
+        tftp::run_tftp_server(&ctx)?;
+    }
+
+    let handles = boards
+        .iter()
+        .zip(configs)
+        .enumerate()
+        .map(|(i, (board, config))| {
+            let ctx = ctx.clone();
+            let run = BoardRun {
+                name: board.name.clone(),
+                prefix: format!("[{}]", board.name)
+                    .color(COLORS[i % COLORS.len()])
+                    .to_string(),
+                timeout,
+            };
+            // 每块板子的串口和 U-Boot 交互都是阻塞的，各用一个线程
+            thread::spawn(move || {
+                let start = Instant::now();
+                let name = run.name.clone();
+                let res = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .map_err(anyhow::Error::from)
+                    .and_then(|rt| rt.block_on(run_board(ctx, config, run)));
+                BoardResult {
+                    name,
+                    error: res.err().map(|e| format!("{e:#}")),
+                    duration: start.elapsed(),
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let results = handles
+        .into_iter()
+        .zip(&boards)
+        .map(|(handle, board)| {
+            handle.join().unwrap_or_else(|_| BoardResult {
+                name: board.name.clone(),
+                error: Some("panicked".to_string()),
+                duration: Duration::ZERO,
+            })
+        })
+        .collect::<Vec<_>>();
+    print_summary(&results);
+    Ok(results)
+}
+
+/// Loads the boards file, by default `.boards.toml` in the workspace.
+///
+/// # Errors
+///
+/// Returns an error if the file is missing or invalid.
+pub async fn load_boards_config(
+    ctx: &AppContext,
+    path: Option<PathBuf>,
+) -> anyhow::Result<BoardsConfig> {
+    let path = path.unwrap_or_else(|| ctx.paths.config_file(".boards.toml"));
+    if !path.exists() {
+        bail!(t!("boards.no_boards", path = path.display()));
+    }
+    let content = tokio::fs::read_to_string(&path).await?;
+    ctx.parse_config(&path, &content)
+}
+
+/// Fails if any board failed.
+///
+/// # Errors
+///
+/// Returns an error naming the failed boards.
+pub fn ensure_passed(results: &[BoardResult]) -> anyhow::Result<()> {
+    let failed = results
+        .iter()
+        .filter(|r| r.error.is_some())
+        .map(|r| r.name.as_str())
+        .collect::<Vec<_>>();
+    if !failed.is_empty() {
+        return Err(Failure::Boot.error(t!("boards.failed", boards = failed.join(", "))));
+    }
+    Ok(())
+}
+
+/// Picks the boards named in `only`, or all of them.
+fn select(boards: &[BoardProfile], only: &[String]) -> anyhow::Result<Vec<BoardProfile>> {
+    for (i, board) in boards.iter().enumerate() {
+        if boards[..i].iter().any(|b| b.name == board.name) {
+            bail!(t!("boards.duplicate", name = board.name));
+        }
+    }
+    if let Some(name) = only.iter().find(|n| !boards.iter().any(|b| &b.name == *n)) {
+        bail!(t!("boards.unknown", name = name));
+    }
+    let selected = boards
+        .iter()
+        .filter(|b| only.is_empty() || only.contains(&b.name))
+        .cloned()
+        .collect::<Vec<_>>();
+    if selected.is_empty() {
+        bail!(t!("boards.empty"));
+    }
+    Ok(selected)
+}
+
+fn check_serial_ports(boards: &[BoardProfile], configs: &[UbootConfig]) -> anyhow::Result<()> {
+    // 远程板子以 agent 和板名区分
+    let console = |c: &UbootConfig| match &c.remote {
+        Some(remote) => format!("{}/{}", remote.agent, remote.board),
+        None => c.serial.clone(),
+    };
+    for (i, config) in configs.iter().enumerate() {
+        if let Some(j) = configs[..i]
+            .iter()
+            .position(|c| console(c) == console(config))
+        {
+            bail!(t!(
+                "boards.shared_serial",
+                serial = console(config),
+                a = boards[j].name,
+                b = boards[i].name
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn print_summary(results: &[BoardResult]) {
+    let passed = results.iter().filter(|r| r.error.is_none()).count();
+    println!(
+        "\n{}",
+        t!(
+            "boards.summary",
+            passed = passed,
+            failed = results.len() - passed
+        )
+        .bold()
+    );
+    let width = results.iter().map(|r| r.name.len()).max().unwrap_or(0);
+    for result in results {
+        let status = match result.error {
+            None => "PASS".green(),
+            Some(_) => "FAIL".red(),
+        };
+        println!(
+            "  {:<width$}  {status}  {:>7.1}s  {}",
+            result.name,
+            result.duration.as_secs_f64(),
+            result.error.as_deref().unwrap_or_default(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(name: &str) -> BoardProfile {
+        BoardProfile {
+            name: name.to_string(),
+            config: format!("boards/{name}.uboot.toml"),
+            sd_mux: None,
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let boards = [profile("a"), profile("b"), profile("c")];
+        assert_eq!(select(&boards, &[]).unwrap().len(), 3);
+        let only = select(&boards, &["c".to_string(), "a".to_string()]).unwrap();
+        assert_eq!(
+            only.iter().map(|b| b.name.as_str()).collect::<Vec<_>>(),
+            ["a", "c"]
+        );
+        assert!(select(&boards, &["d".to_string()]).is_err());
+        assert!(select(&[profile("a"), profile("a")], &[]).is_err());
+        assert!(select(&[], &[]).is_err());
+    }
+
+    #[test]
+    fn test_shared_serial_port() {
+        let boards = [profile("a"), profile("b")];
+        let config = |serial: &str| UbootConfig {
+            serial: serial.to_string(),
+            ..Default::default()
+        };
+        assert!(
+            check_serial_ports(&boards, &[config("/dev/ttyUSB0"), config("/dev/ttyUSB1")]).is_ok()
+        );
+        assert!(
+            check_serial_ports(&boards, &[config("/dev/ttyUSB0"), config("/dev/ttyUSB0")]).is_err()
+        );
+    }
+}
@@ -0,0 +1,365 @@
+//! Crash triage of headless QEMU boots.
+//!
+//! With a `[test.crash]` section in `.qemu.toml`, `ostool test` starts QEMU
+//! with a gdbstub. When a console line matches a panic pattern, the VM is
+//! frozen over QMP before it can reboot or scroll the evidence away, and a
+//! debugger loaded with the kernel ELF collects a crash bundle:
+//!
+//! ```text
+//! target/crash/<binary>-<time>/
+//!   crash.txt      matching line, ELF and QEMU command
+//!   console.log    last console lines before the crash
+//!   registers.txt  `info registers`
+//!   backtrace.txt  `bt`, symbolized with the ELF
+//!   mem-<name>.bin dumps of the configured memory regions
+//! ```
+//!
+//! ```toml
+//! [test.crash]
+//! panic_regex = ["panicked at", "Unhandled exception"]
+//! memory = [{ name = "stack", start = "0x40800000", size = "0x10000" }]
+//! ```
+
+use std::{
+    collections::VecDeque,
+    io::Read,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use jkconfig::t;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ctx::AppContext,
+    run::{
+        gdb::{Debugger, DebuggerKind, host_arch},
+        qmp::{self, Qmp},
+    },
+    utils::{find_program, parse_int},
+};
+
+const DEFAULT_PANIC_REGEX: &str = "panicked at";
+const DEFAULT_DIR: &str = "target/crash";
+/// Console lines kept for `console.log`.
+const CONSOLE_LINES: usize = 200;
+/// How long the debugger may take to collect the bundle.
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Printed by the debugger between the sections of its output.
+const SECTION_MARK: &str = "@@ostool-crash ";
+
+/// Crash triage, the `[test.crash]` section of `.qemu.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct CrashConfig {
+    /// Regexes marking a kernel crash; `panicked at` when empty.
+    #[serde(default)]
+    pub panic_regex: Vec<String>,
+    /// Directory the crash bundles are written to, relative to the
+    /// workspace; `target/crash` when unset.
+    pub dir: Option<String>,
+    /// Memory regions dumped into the bundle.
+    #[serde(default)]
+    pub memory: Vec<MemoryDump>,
+}
+
+/// A memory region dumped into the crash bundle.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct MemoryDump {
+    /// Name of the dump, written to `mem-<name>.bin`.
+    pub name: String,
+    /// Start address, e.g. `0x40800000`.
+    pub start: String,
+    /// Size in bytes, e.g. `0x10000`.
+    pub size: String,
+}
+
+/// Crash triage set up for one QEMU boot.
+pub(crate) struct Triage {
+    patterns: Vec<Regex>,
+    dir: PathBuf,
+    /// Memory dumps as `(name, start, size)`.
+    memory: Vec<(String, u64, u64)>,
+    /// Address of the gdbstub.
+    gdb: SocketAddr,
+    console: VecDeque<String>,
+}
+
+impl Triage {
+    /// Prepares triage as configured, picking a free port for the gdbstub.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a pattern or memory region is invalid.
+    pub(crate) fn new(ctx: &AppContext, config: &CrashConfig) -> anyhow::Result<Self> {
+        let patterns = if config.panic_regex.is_empty() {
+            vec![Regex::new(DEFAULT_PANIC_REGEX)?]
+        } else {
+            config
+                .panic_regex
+                .iter()
+                .map(|p| Regex::new(p).map_err(|e| anyhow!("panic regex error: {e}")))
+                .collect::<anyhow::Result<_>>()?
+        };
+        let memory = config
+            .memory
+            .iter()
+            .map(|m| {
+                let start = parse_int(&m.start)
+                    .ok_or_else(|| anyhow!(t!("crash.invalid_region", value = m.start)))?;
+                let size = parse_int(&m.size)
+                    .ok_or_else(|| anyhow!(t!("crash.invalid_region", value = m.size)))?;
+                Ok((m.name.clone(), start, size))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            patterns,
+            dir: ctx
+                .paths
+                .workspace
+                .join(config.dir.as_deref().unwrap_or(DEFAULT_DIR)),
+            memory,
+            gdb: qmp::free_addr()?,
+            console: VecDeque::new(),
+        })
+    }
+
+    /// The `-gdb` value starting the gdbstub without halting the VM.
+    pub(crate) fn gdb_arg(&self) -> String {
+        format!("tcp:{}", self.gdb)
+    }
+
+    /// Records a console line and returns whether it marks a crash.
+    pub(crate) fn check(&mut self, line: &str) -> bool {
+        if self.console.len() == CONSOLE_LINES {
+            self.console.pop_front();
+        }
+        self.console.push_back(line.to_string());
+        self.patterns.iter().any(|p| p.is_match(line))
+    }
+
+    /// Freezes the VM and writes the crash bundle for `line`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no gdb is installed for the kernel or the bundle
+    /// cannot be written; what the debugger could not read is noted in the
+    /// bundle instead.
+    pub(crate) fn capture(
+        &self,
+        ctx: &AppContext,
+        qmp: Option<SocketAddr>,
+        command: &str,
+        line: &str,
+    ) -> anyhow::Result<PathBuf> {
+        // 先冻结虚拟机，免得内核重启或继续输出
+        if let Some(addr) = qmp
+            && let Err(e) = Qmp::connect(addr).and_then(|mut qmp| qmp.stop())
+        {
+            warn!("QMP stop: {e:#}");
+        }
+
+        let elf = ctx
+            .paths
+            .artifacts
+            .elf
+            .clone()
+            .ok_or_else(|| anyhow!("elf not exist"))?;
+        let arch = ctx
+            .arch
+            .ok_or_else(|| anyhow!(t!("gdb.unknown_arch", path = elf.display())))?;
+        let debugger = Debugger::detect(arch, host_arch(), |p| find_program(p).is_some())
+            .filter(|d| d.kind == DebuggerKind::Gdb)
+            .ok_or_else(|| anyhow!(t!("crash.no_gdb", arch = format!("{arch:?}"))))?;
+
+        let name = elf
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "kernel".to_string());
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let bundle = self.dir.join(format!("{name}-{now}"));
+        std::fs::create_dir_all(&bundle)?;
+
+        std::fs::write(
+            bundle.join("crash.txt"),
+            format!("line: {line}\nelf: {}\nqemu: {command}\n", elf.display()),
+        )?;
+        let mut console = self.console.iter().cloned().collect::<Vec<_>>().join("\n");
+        console.push('\n');
+        std::fs::write(bundle.join("console.log"), console)?;
+
+        let output = self.run_gdb(ctx, &debugger, &elf, &bundle)?;
+        let parts = sections(&output);
+        for (section, file) in [
+            ("registers", "registers.txt"),
+            ("backtrace", "backtrace.txt"),
+        ] {
+            let text = parts
+                .iter()
+                .find(|(name, _)| name == section)
+                .map_or(output.as_str(), |(_, text)| text.as_str());
+            std::fs::write(bundle.join(file), text)?;
+        }
+        Ok(bundle)
+    }
+
+    /// Runs gdb in batch mode against the gdbstub and returns its output.
+    fn run_gdb(
+        &self,
+        ctx: &AppContext,
+        debugger: &Debugger,
+        elf: &Path,
+        bundle: &Path,
+    ) -> anyhow::Result<String> {
+        let mut cmd = ctx.command(&debugger.program);
+        if let Some(gdb) = &debugger.rust_gdb {
+            cmd.env("RUST_GDB", gdb);
+        }
+        cmd.args(gdb_args(elf, self.gdb, &self.memory, bundle));
+        cmd.stdin(Stdio::null());
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+        let mut child = cmd.spawn()?;
+        let mut stdout = child.stdout.take().unwrap();
+        let reader = std::thread::spawn(move || {
+            let mut output = String::new();
+            let _ = stdout.read_to_string(&mut output);
+            output
+        });
+
+        let deadline = Instant::now() + CAPTURE_TIMEOUT;
+        while child.try_wait()?.is_none() {
+            if Instant::now() > deadline {
+                let _ = child.kill();
+                warn!("{}", t!("crash.gdb_timeout"));
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        let _ = child.wait();
+        Ok(reader.join().unwrap_or_default())
+    }
+}
+
+/// Arguments of a batch gdb session connecting to `gdb`, printing
+/// registers and backtrace as sections and dumping `memory` to `bundle`.
+fn gdb_args(
+    elf: &Path,
+    gdb: SocketAddr,
+    memory: &[(String, u64, u64)],
+    bundle: &Path,
+) -> Vec<String> {
+    let mut cmds = vec![
+        "set pagination off".to_string(),
+        "set confirm off".to_string(),
+        format!("target remote {gdb}"),
+        format!("echo {SECTION_MARK}registers\\n"),
+        "info registers".to_string(),
+        format!("echo {SECTION_MARK}backtrace\\n"),
+        "bt".to_string(),
+    ];
+    for (name, start, size) in memory {
+        let file = bundle.join(format!("mem-{name}.bin"));
+        cmds.push(format!(
+            "dump binary memory {} {start:#x} {:#x}",
+            file.display(),
+            start + size
+        ));
+    }
+    cmds.push("detach".to_string());
+
+    let mut args = vec!["-q".to_string(), "-batch".to_string()];
+    args.push(elf.display().to_string());
+    for cmd in cmds {
+        args.push("-ex".to_string());
+        args.push(cmd);
+    }
+    args
+}
+
+/// Splits debugger output at the section marks into `(name, text)`.
+fn sections(output: &str) -> Vec<(String, String)> {
+    let mut sections: Vec<(String, String)> = Vec::new();
+    for line in output.lines() {
+        if let Some(name) = line.strip_prefix(SECTION_MARK) {
+            sections.push((name.trim().to_string(), String::new()));
+        } else if let Some((_, text)) = sections.last_mut() {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gdb_args() {
+        let args = gdb_args(
+            Path::new("kernel"),
+            "127.0.0.1:4321".parse().unwrap(),
+            &[("stack".to_string(), 0x4080_0000, 0x1000)],
+            Path::new("bundle"),
+        );
+        assert_eq!(&args[..3], ["-q", "-batch", "kernel"]);
+        let cmds = args.iter().skip(3).step_by(2).collect::<Vec<_>>();
+        assert!(cmds.iter().all(|flag| *flag == "-ex"));
+        let cmds = args.iter().skip(4).step_by(2).collect::<Vec<_>>();
+        assert!(cmds.contains(&&"target remote 127.0.0.1:4321".to_string()));
+        let dump = Path::new("bundle").join("mem-stack.bin");
+        assert!(cmds.contains(&&format!(
+            "dump binary memory {} 0x40800000 0x40801000",
+            dump.display()
+        )));
+        assert_eq!(cmds.last().unwrap().as_str(), "detach");
+    }
+
+    #[test]
+    fn test_sections() {
+        let output = format!(
+            "Remote debugging using :1234\n{SECTION_MARK}registers\nx0 0x1 1\nx1 0x2 2\n\
+             {SECTION_MARK}backtrace\n#0  core::panicking::panic ()\n"
+        );
+        let parts = sections(&output);
+        assert_eq!(
+            parts,
+            [
+                ("registers".to_string(), "x0 0x1 1\nx1 0x2 2\n".to_string()),
+                (
+                    "backtrace".to_string(),
+                    "#0  core::panicking::panic ()\n".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_keeps_recent_lines() {
+        let ctx = AppContext::default();
+        let mut triage = Triage::new(&ctx, &CrashConfig::default()).unwrap();
+        for i in 0..CONSOLE_LINES + 5 {
+            assert!(!triage.check(&format!("line {i}")));
+        }
+        assert_eq!(triage.console.len(), CONSOLE_LINES);
+        assert_eq!(triage.console.front().unwrap(), "line 5");
+        assert!(triage.check("panicked at src/main.rs:10:5"));
+
+        let config = CrashConfig {
+            memory: vec![MemoryDump {
+                name: "stack".to_string(),
+                start: "stack".to_string(),
+                size: "0x10".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert!(Triage::new(&ctx, &config).is_err());
+    }
+}
@@ -0,0 +1,778 @@
+//! defmt frame decoding for console output.
+//!
+//! Kernels logging through [defmt](https://defmt.ferrous-systems.com) send
+//! compact binary frames instead of text: an index into a string table kept
+//! in the `.defmt` section of the ELF, followed by the arguments. When the
+//! kernel ELF has such a section, the QEMU and U-Boot runners pass the
+//! console through [`DefmtReader`], which replaces every frame with its
+//! decoded line, e.g. `0.000120 INFO  heap ready: 64 MiB`, and leaves plain
+//! text as it is.
+//!
+//! Frames are expected in defmt's default rzCOBS encoding, ended by a zero
+//! byte, as written by `defmt-serial` or a UART logger over `defmt::Encoder`.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, Read},
+    path::Path,
+};
+
+use jkconfig::t;
+use object::{Object, ObjectSection, ObjectSymbol};
+use serde::Deserialize;
+
+use crate::ctx::AppContext;
+
+/// Longest frame looked for; longer runs of bytes are passed on as text.
+const MAX_FRAME: usize = 4096;
+
+/// Wraps `rx` in a [`DefmtReader`] when the kernel ELF has a defmt table.
+pub fn wrap(ctx: &AppContext, rx: Box<dyn Read + Send>) -> Box<dyn Read + Send> {
+    let Some(elf) = &ctx.paths.artifacts.elf else {
+        return rx;
+    };
+    match Table::load(elf) {
+        Ok(Some(table)) => {
+            info!(
+                "{}",
+                t!("defmt.enabled", count = table.len(), path = elf.display())
+            );
+            Box::new(DefmtReader::new(rx, table))
+        }
+        Ok(None) => rx,
+        Err(e) => {
+            warn!("{}", t!("defmt.table_error", path = elf.display(), err = e));
+            rx
+        }
+    }
+}
+
+/// Format strings of the `.defmt` section of an ELF.
+#[derive(Debug, Default)]
+pub struct Table {
+    entries: HashMap<u16, Entry>,
+    timestamp: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Entry {
+    tag: String,
+    data: String,
+}
+
+impl Table {
+    /// Reads the table of the ELF at `path`; `None` if it has no `.defmt`
+    /// section.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> anyhow::Result<Option<Self>> {
+        let data = std::fs::read(path)?;
+        Self::parse(&data)
+    }
+
+    /// Reads the table of an ELF image; `None` if it has no `.defmt`
+    /// section.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image cannot be parsed.
+    pub fn parse(elf: &[u8]) -> anyhow::Result<Option<Self>> {
+        let file = object::File::parse(elf)?;
+        let Some(section) = file.section_by_name(".defmt") else {
+            return Ok(None);
+        };
+        let mut table = Table::default();
+        for symbol in file.symbols() {
+            if symbol.section_index() != Some(section.index()) {
+                continue;
+            }
+            // 符号名是 JSON，地址即字符串索引
+            let Ok(entry) = serde_json::from_str::<Entry>(symbol.name()?) else {
+                continue;
+            };
+            if entry.tag == "defmt_timestamp" {
+                table.timestamp = Some(entry.data.clone());
+            }
+            table.entries.insert(symbol.address() as u16, entry);
+        }
+        Ok(Some(table))
+    }
+
+    /// Number of strings in the table.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the table has no strings.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Decodes a log frame, already taken out of its rzCOBS encoding, to a
+    /// line of text; `None` if it is not a valid frame of this table.
+    pub fn decode_frame(&self, frame: &[u8]) -> Option<String> {
+        let mut d = Decoder {
+            table: self,
+            buf: frame,
+            pos: 0,
+        };
+        let entry = self.entries.get(&d.istr()?)?;
+        let level = match entry.tag.as_str() {
+            "defmt_println" => None,
+            "defmt_trace" => Some("TRACE"),
+            "defmt_debug" => Some("DEBUG"),
+            "defmt_info" => Some("INFO "),
+            "defmt_warn" => Some("WARN "),
+            "defmt_error" => Some("ERROR"),
+            _ => return None,
+        };
+        let mut line = String::new();
+        if let Some(ts) = &self.timestamp {
+            line.push_str(&d.format(ts)?);
+            line.push(' ');
+        }
+        if let Some(level) = level {
+            line.push_str(level);
+            line.push(' ');
+        }
+        line.push_str(&d.format(&entry.data)?);
+        // rzCOBS 解码后末尾可能多出填充的零
+        d.buf[d.pos..].iter().all(|&b| b == 0).then_some(line)
+    }
+}
+
+/// Undoes the rzCOBS encoding of a frame, without its zero terminator.
+///
+/// The result may end in extra zeros the encoding pads with.
+pub fn rzcobs_decode(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len() * 2);
+    let mut bytes = data.iter().rev().copied();
+    while let Some(x) = bytes.next() {
+        match x {
+            0 => return None,
+            0x01..=0x7f => {
+                for i in (0..7).rev() {
+                    if x & (1 << i) != 0 {
+                        out.push(0);
+                    } else {
+                        out.push(bytes.next()?);
+                    }
+                }
+            }
+            0x80..=0xfe => {
+                out.push(0);
+                for _ in 0..(x & 0x7f) + 7 {
+                    out.push(bytes.next()?);
+                }
+            }
+            0xff => {
+                for _ in 0..134 {
+                    out.push(bytes.next()?);
+                }
+            }
+        }
+    }
+    out.reverse();
+    Some(out)
+}
+
+/// Reader replacing the defmt frames of `inner` by their decoded lines.
+///
+/// Bytes before the first zero byte, and runs between zero bytes that do
+/// not decode, are passed on unchanged. Text following frames is held
+/// until its line ends or `inner` times out.
+pub struct DefmtReader<R> {
+    inner: R,
+    table: Table,
+    /// Bytes since the last zero byte, once one was seen.
+    pending: Option<Vec<u8>>,
+    out: VecDeque<u8>,
+}
+
+impl<R: Read> DefmtReader<R> {
+    /// Decodes the frames of `inner` with `table`.
+    pub fn new(inner: R, table: Table) -> Self {
+        Self {
+            inner,
+            table,
+            pending: None,
+            out: VecDeque::new(),
+        }
+    }
+
+    fn feed(&mut self, data: &[u8]) {
+        for &b in data {
+            match &mut self.pending {
+                None if b == 0 => self.pending = Some(Vec::new()),
+                None => self.out.push_back(b),
+                Some(pending) if b == 0 => {
+                    if !pending.is_empty() {
+                        let run = std::mem::take(pending);
+                        self.emit(run);
+                    }
+                }
+                Some(pending) => {
+                    pending.push(b);
+                    let text_line =
+                        b == b'\n' && is_text(pending) && decode(&self.table, pending).is_none();
+                    if text_line || pending.len() > MAX_FRAME {
+                        self.flush();
+                    }
+                }
+            }
+        }
+    }
+
+    fn emit(&mut self, run: Vec<u8>) {
+        match decode(&self.table, &run) {
+            Some(line) => {
+                self.out.extend(line.bytes());
+                self.out.push_back(b'\n');
+            }
+            None => self.out.extend(run),
+        }
+    }
+
+    /// Passes held bytes on as text; the next frame may follow them
+    /// without a zero byte.
+    fn flush(&mut self) {
+        if let Some(pending) = &mut self.pending {
+            self.out.extend(pending.drain(..));
+        }
+    }
+}
+
+impl<R: Read> Read for DefmtReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut chunk = [0u8; 1024];
+        while self.out.is_empty() {
+            match self.inner.read(&mut chunk) {
+                Ok(0) => {
+                    self.flush();
+                    if self.out.is_empty() {
+                        return Ok(0);
+                    }
+                }
+                Ok(n) => self.feed(&chunk[..n]),
+                // 串口空闲时放出未结束的文本
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                    self.flush();
+                    if self.out.is_empty() {
+                        return Err(e);
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        let n = buf.len().min(self.out.len());
+        for (dst, src) in buf.iter_mut().zip(self.out.drain(..n)) {
+            *dst = src;
+        }
+        Ok(n)
+    }
+}
+
+fn decode(table: &Table, run: &[u8]) -> Option<String> {
+    table.decode_frame(&rzcobs_decode(run)?)
+}
+
+fn is_text(data: &[u8]) -> bool {
+    std::str::from_utf8(data).is_ok_and(|s| {
+        s.chars()
+            .all(|c| !c.is_control() || matches!(c, '\r' | '\n' | '\t' | '\x1b'))
+    })
+}
+
+/// Type of a format parameter, `{=u8}`, `{=[?]}`, `{=0..4}` and so on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ty {
+    Uint(usize),
+    Int(usize),
+    F32,
+    F64,
+    Bool,
+    Char,
+    Str,
+    IStr,
+    Bytes,
+    ByteArray(usize),
+    Format,
+    FormatSlice,
+    FormatArray(usize),
+    /// Bits `start..end` of an integer shared by the parameters of an index.
+    Bits(u32, u32),
+    /// Text of `core::fmt`, ended by `0xff`.
+    Fmt,
+}
+
+impl Ty {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s.trim() {
+            "" | "?" => Ty::Format,
+            "u8" => Ty::Uint(1),
+            "u16" => Ty::Uint(2),
+            "u32" | "usize" => Ty::Uint(4),
+            "u64" => Ty::Uint(8),
+            "u128" => Ty::Uint(16),
+            "i8" => Ty::Int(1),
+            "i16" => Ty::Int(2),
+            "i32" | "isize" => Ty::Int(4),
+            "i64" => Ty::Int(8),
+            "i128" => Ty::Int(16),
+            "f32" => Ty::F32,
+            "f64" => Ty::F64,
+            "bool" => Ty::Bool,
+            "char" => Ty::Char,
+            "str" => Ty::Str,
+            "istr" => Ty::IStr,
+            "[u8]" => Ty::Bytes,
+            "[?]" => Ty::FormatSlice,
+            "__internal_Debug" | "__internal_Display" => Ty::Fmt,
+            s => {
+                if let Some(array) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                    let (elem, len) = array.split_once(';')?;
+                    let len = len.trim().parse().ok()?;
+                    return match elem.trim() {
+                        "u8" => Some(Ty::ByteArray(len)),
+                        "?" => Some(Ty::FormatArray(len)),
+                        _ => None,
+                    };
+                }
+                let (start, end) = s.split_once("..")?;
+                let start = start.parse().ok()?;
+                let end = match end.strip_prefix('=') {
+                    Some(end) => end.parse::<u32>().ok()? + 1,
+                    None => end.parse().ok()?,
+                };
+                if start >= end || end > 64 {
+                    return None;
+                }
+                Ty::Bits(start, end)
+            }
+        })
+    }
+}
+
+#[derive(Debug)]
+enum Piece<'a> {
+    Text(String),
+    Param { index: usize, ty: Ty, hint: &'a str },
+}
+
+/// Splits a defmt format string into text and parameters.
+fn parse_format(fmt: &str) -> Option<Vec<Piece<'_>>> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut next = 0;
+    let mut rest = fmt;
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            text.push(c);
+            rest = &rest[2..];
+            continue;
+        }
+        if c != '{' {
+            text.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let end = rest.find('}')?;
+        let param = &rest[1..end];
+        rest = &rest[end + 1..];
+        let (param, hint) = param.split_once(':').unwrap_or((param, ""));
+        let (index, ty) = param.split_once('=').unwrap_or((param, ""));
+        let index = if index.is_empty() {
+            next += 1;
+            next - 1
+        } else {
+            index.parse().ok()?
+        };
+        if !text.is_empty() {
+            pieces.push(Piece::Text(std::mem::take(&mut text)));
+        }
+        pieces.push(Piece::Param {
+            index,
+            ty: Ty::parse(ty)?,
+            hint,
+        });
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Some(pieces)
+}
+
+#[derive(Debug)]
+enum Value {
+    Uint(u128),
+    Int(i128),
+    F32(f32),
+    F64(f64),
+    Bool(bool),
+    Char(char),
+    Bytes(Vec<u8>),
+    Text(String),
+}
+
+struct Decoder<'a> {
+    table: &'a Table,
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl Decoder<'_> {
+    fn bytes(&mut self, n: usize) -> Option<&[u8]> {
+        let bytes = self.buf.get(self.pos..self.pos + n)?;
+        self.pos += n;
+        Some(bytes)
+    }
+
+    fn uint(&mut self, n: usize) -> Option<u128> {
+        let bytes = self.bytes(n)?;
+        Some(
+            bytes
+                .iter()
+                .rev()
+                .fold(0u128, |acc, &b| (acc << 8) | b as u128),
+        )
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        self.uint(4).map(|n| n as usize)
+    }
+
+    fn istr(&mut self) -> Option<u16> {
+        self.uint(2).map(|n| n as u16)
+    }
+
+    fn entry(&mut self) -> Option<Entry> {
+        let index = self.istr()?;
+        self.table.entries.get(&index).cloned()
+    }
+
+    /// Decodes the arguments of `fmt` and renders it.
+    fn format(&mut self, fmt: &str) -> Option<String> {
+        let pieces = parse_format(fmt)?;
+        let count = pieces
+            .iter()
+            .filter_map(|p| match p {
+                Piece::Param { index, .. } => Some(index + 1),
+                Piece::Text(_) => None,
+            })
+            .max()
+            .unwrap_or(0);
+        // 参数按索引顺序编码，同一索引的位域共用一个整数
+        let mut values = Vec::with_capacity(count);
+        for i in 0..count {
+            let params = pieces.iter().filter_map(|p| match p {
+                Piece::Param { index, ty, .. } if *index == i => Some(*ty),
+                _ => None,
+            });
+            let mut ty = None;
+            let mut bits = 0;
+            for param in params {
+                if let Ty::Bits(_, end) = param {
+                    bits = bits.max(end);
+                }
+                ty.get_or_insert(param);
+            }
+            let value = match ty? {
+                Ty::Bits(..) => Value::Uint(self.uint(bits.div_ceil(8).next_power_of_two() as _)?),
+                ty => self.value(ty)?,
+            };
+            values.push(value);
+        }
+
+        let mut out = String::new();
+        for piece in &pieces {
+            match piece {
+                Piece::Text(text) => out.push_str(text),
+                Piece::Param { index, ty, hint } => {
+                    let value = &values[*index];
+                    match (ty, value) {
+                        (Ty::Bits(start, end), Value::Uint(v)) => {
+                            let v = (v >> start) & ((1u128 << (end - start)) - 1);
+                            out.push_str(&render(&Value::Uint(v), hint));
+                        }
+                        _ => out.push_str(&render(value, hint)),
+                    }
+                }
+            }
+        }
+        Some(out)
+    }
+
+    fn value(&mut self, ty: Ty) -> Option<Value> {
+        Some(match ty {
+            Ty::Uint(n) => Value::Uint(self.uint(n)?),
+            Ty::Int(n) => {
+                let shift = 128 - n as u32 * 8;
+                Value::Int(((self.uint(n)? << shift) as i128) >> shift)
+            }
+            Ty::F32 => Value::F32(f32::from_bits(self.uint(4)? as u32)),
+            Ty::F64 => Value::F64(f64::from_bits(self.uint(8)? as u64)),
+            Ty::Bool => Value::Bool(self.uint(1)? != 0),
+            Ty::Char => Value::Char(char::from_u32(self.uint(4)? as u32)?),
+            Ty::Str => {
+                let len = self.len()?;
+                Value::Text(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+            }
+            Ty::IStr => Value::Text(self.entry()?.data),
+            Ty::Bytes => {
+                let len = self.len()?;
+                Value::Bytes(self.bytes(len)?.to_vec())
+            }
+            Ty::ByteArray(len) => Value::Bytes(self.bytes(len)?.to_vec()),
+            Ty::Format => {
+                let entry = self.entry()?;
+                Value::Text(self.format_data(&entry)?)
+            }
+            Ty::FormatSlice => {
+                let len = self.len()?;
+                Value::Text(self.format_list(len)?)
+            }
+            Ty::FormatArray(len) => Value::Text(self.format_list(len)?),
+            Ty::Fmt => {
+                let len = self.buf[self.pos..].iter().position(|&b| b == 0xff)?;
+                let text = String::from_utf8_lossy(self.bytes(len)?).into_owned();
+                self.pos += 1;
+                Value::Text(text)
+            }
+            Ty::Bits(..) => return None,
+        })
+    }
+
+    /// `len` values sharing one format string, as `[a, b]`.
+    fn format_list(&mut self, len: usize) -> Option<String> {
+        let entry = self.entry()?;
+        let mut items = Vec::with_capacity(len.min(MAX_FRAME));
+        for _ in 0..len {
+            items.push(self.format_data(&entry)?);
+        }
+        Some(format!("[{}]", items.join(", ")))
+    }
+
+    /// The data of a `Format` value, after its format string.
+    fn format_data(&mut self, entry: &Entry) -> Option<String> {
+        if entry.tag == "defmt_derived" && entry.data.contains('|') {
+            // 枚举先编码变体序号
+            let variants = entry.data.split('|').collect::<Vec<_>>();
+            let n = if variants.len() > 256 { 2 } else { 1 };
+            let variant = variants.get(self.uint(n)? as usize)?;
+            return self.format(variant);
+        }
+        self.format(&entry.data)
+    }
+}
+
+/// Renders `value` with a display hint such as `x`, `#010x`, `a` or `us`.
+fn render(value: &Value, hint: &str) -> String {
+    let alt = hint.starts_with('#');
+    let hint = hint.trim_start_matches('#');
+    let digits = hint.len() - hint.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let width = hint[..digits].parse::<usize>().unwrap_or(0);
+    let kind = &hint[digits..];
+    match value {
+        Value::Uint(v) => render_int(*v, false, kind, alt, width),
+        Value::Int(v) if *v < 0 && matches!(kind, "" | "?") => format!("{v:0width$}"),
+        Value::Int(v) => render_int(v.unsigned_abs(), *v < 0, kind, alt, width),
+        Value::F32(v) => v.to_string(),
+        Value::F64(v) => v.to_string(),
+        Value::Bool(v) => v.to_string(),
+        Value::Char(v) => match kind {
+            "?" => format!("{v:?}"),
+            _ => v.to_string(),
+        },
+        Value::Bytes(bytes) if kind == "a" => {
+            let escaped = bytes
+                .iter()
+                .flat_map(|&b| std::ascii::escape_default(b))
+                .map(char::from)
+                .collect::<String>();
+            format!("b\"{escaped}\"")
+        }
+        Value::Bytes(bytes) => {
+            let items = bytes
+                .iter()
+                .map(|&b| render_int(b as u128, false, kind, alt, width))
+                .collect::<Vec<_>>();
+            format!("[{}]", items.join(", "))
+        }
+        Value::Text(text) => match kind {
+            "?" => format!("{text:?}"),
+            _ => text.clone(),
+        },
+    }
+}
+
+fn render_int(v: u128, neg: bool, kind: &str, alt: bool, width: usize) -> String {
+    let sign = if neg { "-" } else { "" };
+    match kind {
+        "x" if alt => format!("{sign}{v:#0width$x}"),
+        "x" => format!("{sign}{v:0width$x}"),
+        "X" if alt => format!("{sign}{v:#0width$X}"),
+        "X" => format!("{sign}{v:0width$X}"),
+        "b" if alt => format!("{sign}{v:#0width$b}"),
+        "b" => format!("{sign}{v:0width$b}"),
+        "o" if alt => format!("{sign}{v:#0width$o}"),
+        "o" => format!("{sign}{v:0width$o}"),
+        // 时间戳以秒显示
+        "us" => format!("{sign}{}.{:06}", v / 1_000_000, v % 1_000_000),
+        "ms" => format!("{sign}{}.{:03}", v / 1_000, v % 1_000),
+        _ => format!("{sign}{v:0width$}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// rzCOBS encoder of defmt, for building test frames.
+    fn encode(data: &[u8]) -> Vec<u8> {
+        let (mut out, mut run, mut zeros) = (vec![], 0u8, 0u8);
+        for &byte in data {
+            if run < 7 {
+                if byte == 0 {
+                    zeros |= 1 << run;
+                } else {
+                    out.push(byte);
+                }
+                run += 1;
+                if run == 7 && zeros != 0 {
+                    out.push(zeros);
+                    (run, zeros) = (0, 0);
+                }
+            } else if byte == 0 {
+                out.push((run - 7) | 0x80);
+                (run, zeros) = (0, 0);
+            } else {
+                out.push(byte);
+                run += 1;
+                if run == 134 {
+                    out.push(0xff);
+                    (run, zeros) = (0, 0);
+                }
+            }
+        }
+        match run {
+            0 => {}
+            1..=6 => out.push((zeros | (0xff << run)) & 0x7f),
+            _ => out.push((run - 7) | 0x80),
+        }
+        out.push(0);
+        out
+    }
+
+    fn table() -> Table {
+        let mut table = Table::default();
+        for (index, tag, data) in [
+            (1, "defmt_info", "heap ready: {=u32} MiB"),
+            (
+                2,
+                "defmt_error",
+                "{0=0..4:b} {0=4..8} bad {1=i16} at {2=u32:#010x}, {3=?}",
+            ),
+            (3, "defmt_derived", "None|Some({=u8})"),
+            (4, "defmt_println", "{=str} {=[u8]:x} {=bool} {{ok}}"),
+            (5, "defmt_timestamp", "{=u64:us}"),
+        ] {
+            table.entries.insert(
+                index,
+                Entry {
+                    tag: tag.to_string(),
+                    data: data.to_string(),
+                },
+            );
+        }
+        table
+    }
+
+    #[test]
+    fn test_rzcobs() {
+        for data in [
+            &[][..],
+            &[0],
+            &[1, 0, 0, 2],
+            &[0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0, 9],
+            &[0xaa; 300],
+        ] {
+            let encoded = encode(data);
+            let decoded = rzcobs_decode(&encoded[..encoded.len() - 1]).unwrap();
+            assert_eq!(&decoded[..data.len()], data);
+            assert!(decoded[data.len()..].iter().all(|&b| b == 0));
+        }
+        assert_eq!(rzcobs_decode(&[0x01, 0x02, 0x00]), None);
+    }
+
+    #[test]
+    fn test_decode_frame() {
+        let mut table = table();
+        let frame = [&[1, 0][..], &64u32.to_le_bytes()].concat();
+        assert_eq!(
+            table.decode_frame(&frame).unwrap(),
+            "INFO  heap ready: 64 MiB"
+        );
+
+        let frame = [
+            &[4, 0][..],
+            &2u32.to_le_bytes(),
+            b"hi",
+            &2u32.to_le_bytes(),
+            &[0xab, 0x01],
+            &[1],
+        ]
+        .concat();
+        assert_eq!(table.decode_frame(&frame).unwrap(), "hi [ab, 1] true {ok}");
+        assert_eq!(table.decode_frame(&frame[..frame.len() - 1]), None);
+
+        table.timestamp = Some("{=u64:us}".to_string());
+        let frame = [
+            &[1, 0][..],
+            &1_500_000u64.to_le_bytes(),
+            &8u32.to_le_bytes(),
+        ]
+        .concat();
+        assert_eq!(
+            table.decode_frame(&frame).unwrap(),
+            "1.500000 INFO  heap ready: 8 MiB"
+        );
+    }
+
+    #[test]
+    fn test_bitfields_and_enums() {
+        let table = table();
+        let frame = [
+            &[2, 0][..],
+            &[0xa5],
+            &(-3i16).to_le_bytes(),
+            &0xbeefu32.to_le_bytes(),
+            &[3, 0, 1, 7],
+        ]
+        .concat();
+        assert_eq!(
+            table.decode_frame(&frame).unwrap(),
+            "ERROR 101 10 bad -3 at 0x0000beef, Some(7)"
+        );
+    }
+
+    #[test]
+    fn test_reader() {
+        let frame = [&[1, 0][..], &64u32.to_le_bytes()].concat();
+        let mut input = b"U-Boot 2024.01\r\n".to_vec();
+        input.push(0);
+        input.extend(encode(&frame));
+        input.extend(b"panicked at main.rs\n");
+        input.extend(encode(&frame));
+
+        let mut out = String::new();
+        DefmtReader::new(input.as_slice(), table())
+            .read_to_string(&mut out)
+            .unwrap();
+        assert_eq!(
+            out,
+            "U-Boot 2024.01\r\nINFO  heap ready: 64 MiB\npanicked at main.rs\nINFO  heap ready: 64 MiB\n"
+        );
+    }
+}
@@ -0,0 +1,371 @@
+//! Images left in the RAM of U-Boot boards by earlier runs.
+//!
+//! After a transfer the U-Boot runner records what it loaded in
+//! `.ostool/deployed/<board>.toml` of the workspace: where the FIT image
+//! went with its CRC32, digests of the FIT image, and a device tree loaded
+//! on its own. On the next run:
+//!
+//! - an unchanged FIT image is not sent again; the boot commands run on the
+//!   image still in RAM,
+//! - when only the device tree changed, just the DTB is sent to
+//!   `$fdt_addr_r` and booted with `bootm <fit> - <fdt>`,
+//! - anything else transfers the whole FIT image.
+//!
+//! The digests cover every node and property of the FIT image except the
+//! build `timestamp`, so a changed kernel, overlay, ramdisk, compression or
+//! configuration property all count as a change. U-Boot's `crc32` confirms
+//! an image is still in RAM before it is reused, so a power cycle or a board
+//! that cleared its memory falls back to a full transfer. Device trees with
+//! overlays are always sent inside the FIT image, where U-Boot applies the
+//! overlays.
+
+use std::path::{Path, PathBuf};
+
+use fitimage::{FitImageReader, fit::FdtNode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Directory of the deploy records, relative to the workspace.
+pub const DEPLOYED_DIR: &str = ".ostool/deployed";
+
+/// Data loaded to one address of the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Blob {
+    pub addr: u64,
+    pub size: u64,
+    /// CRC32 as U-Boot's `crc32` prints it.
+    pub crc32: u32,
+}
+
+impl Blob {
+    pub fn new(addr: u64, data: &[u8]) -> Self {
+        Self {
+            addr,
+            size: data.len() as u64,
+            crc32: crc32fast::hash(data),
+        }
+    }
+}
+
+/// What a board holds in RAM after a run, or what this run boots.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deployed {
+    /// The FIT image.
+    pub fit: Blob,
+    /// SHA-256 of the FIT image the board boots, without its `timestamp`.
+    pub image: String,
+    /// Like `image`, leaving out the device tree of the configuration.
+    pub without_dtb: String,
+    /// Device tree loaded on its own, booted instead of the one in `fit`.
+    ///
+    /// For the current build: where the DTB would go in a DTB-only deploy,
+    /// `None` when it has overlays or U-Boot has no `$fdt_addr_r`.
+    pub fdt: Option<Blob>,
+}
+
+impl Deployed {
+    /// Describes a build: the FIT image `fit_data` loaded at `fit_addr`,
+    /// made with `dtbs`, the device tree followed by its overlays.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `fit_data` is not a FIT image.
+    pub fn new(
+        fit_addr: u64,
+        fit_data: &[u8],
+        dtbs: &[Vec<u8>],
+        fdt_addr: Option<u64>,
+    ) -> anyhow::Result<Self> {
+        let reader = FitImageReader::parse(fit_data)?;
+        // 配置中的第一个 fdt 是基础设备树，其后是覆盖层
+        let dtb_node = reader
+            .default_config()
+            .and_then(|name| reader.configurations().iter().find(|c| c.name == name))
+            .and_then(|conf| conf.property_strings("fdt").first().copied());
+        let fdt = match (dtbs, fdt_addr) {
+            ([dtb], Some(addr)) => Some(Blob::new(addr, dtb)),
+            _ => None,
+        };
+        Ok(Self {
+            fit: Blob::new(fit_addr, fit_data),
+            image: digest(reader.root(), None),
+            without_dtb: digest(reader.root(), dtb_node),
+            fdt,
+        })
+    }
+
+    /// Record of `board`, `None` if there is none or it cannot be read.
+    pub fn load(workspace: &Path, board: &str) -> Option<Self> {
+        let content = std::fs::read_to_string(path(workspace, board)).ok()?;
+        toml::from_str(&content).ok()
+    }
+
+    /// Records what `board` holds after this run.
+    pub fn save(&self, workspace: &Path, board: &str) -> anyhow::Result<()> {
+        let path = path(workspace, board);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Removes the record of `board`, e.g. before a transfer that may fail.
+    pub fn forget(workspace: &Path, board: &str) {
+        let _ = std::fs::remove_file(path(workspace, board));
+    }
+}
+
+/// How a run gets its image onto the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Plan {
+    /// Transfer the whole FIT image.
+    Full,
+    /// Boot the FIT image in RAM, with the device tree loaded on its own
+    /// by an earlier DTB-only deploy if there is one.
+    Reuse { fit: Blob, fdt: Option<Blob> },
+    /// Send only the device tree and boot it with the FIT image in RAM.
+    Dtb { fit: Blob, fdt: Blob },
+}
+
+impl Plan {
+    /// Compares the build `current` with what the board held after the
+    /// last run.
+    pub fn new(last: Option<&Deployed>, current: &Deployed) -> Self {
+        let Some(last) = last else {
+            return Plan::Full;
+        };
+        if last.fit.addr != current.fit.addr || last.without_dtb != current.without_dtb {
+            return Plan::Full;
+        }
+        if last.image == current.image {
+            return Plan::Reuse {
+                fit: last.fit,
+                fdt: last.fdt,
+            };
+        }
+        match current.fdt {
+            // 设备树不能与内存中的 FIT image 重叠
+            Some(fdt) if !overlaps(&last.fit, &fdt) => Plan::Dtb { fit: last.fit, fdt },
+            _ => Plan::Full,
+        }
+    }
+
+    /// Images the board must still hold for this plan.
+    pub fn resident(&self) -> Vec<Blob> {
+        match *self {
+            Plan::Full => Vec::new(),
+            Plan::Reuse { fit, fdt } => std::iter::once(fit).chain(fdt).collect(),
+            Plan::Dtb { fit, .. } => vec![fit],
+        }
+    }
+
+    /// What the board holds once this plan has loaded the build `current`,
+    /// `None` if the record stays as it is.
+    pub fn record(&self, current: &Deployed) -> Option<Deployed> {
+        match *self {
+            // 设备树在 FIT image 里
+            Plan::Full => Some(Deployed {
+                fdt: None,
+                ..current.clone()
+            }),
+            Plan::Reuse { .. } => None,
+            Plan::Dtb { fit, .. } => Some(Deployed {
+                fit,
+                ..current.clone()
+            }),
+        }
+    }
+
+    /// Boot command for the images of a reuse or DTB-only deploy.
+    pub fn bootcmd(&self) -> Option<String> {
+        match *self {
+            Plan::Full => None,
+            Plan::Reuse { fit, fdt: None } => Some(format!("bootm {:#x}", fit.addr)),
+            Plan::Reuse {
+                fit,
+                fdt: Some(fdt),
+            }
+            | Plan::Dtb { fit, fdt } => Some(format!("bootm {:#x} - {:#x}", fit.addr, fdt.addr)),
+        }
+    }
+}
+
+/// SHA-256 over the nodes and properties of a FIT image in blob order,
+/// without the root `timestamp` and, if given, the contents of the image
+/// node `skip_image`.
+fn digest(root: &FdtNode, skip_image: Option<&str>) -> String {
+    fn walk(hasher: &mut Sha256, node: &FdtNode, path: &str, skip: Option<&str>) {
+        hasher.update((node.name.len() as u64).to_le_bytes());
+        hasher.update(&node.name);
+        if skip == Some(path) {
+            return;
+        }
+        for prop in &node.properties {
+            if path.is_empty() && prop.name == "timestamp" {
+                continue;
+            }
+            hasher.update((prop.name.len() as u64).to_le_bytes());
+            hasher.update(&prop.name);
+            hasher.update((prop.value.len() as u64).to_le_bytes());
+            hasher.update(&prop.value);
+        }
+        hasher.update((node.children.len() as u64).to_le_bytes());
+        for child in &node.children {
+            walk(hasher, child, &format!("{path}/{}", child.name), skip);
+        }
+    }
+
+    let skip = skip_image.map(|name| format!("/images/{name}"));
+    let mut hasher = Sha256::new();
+    walk(&mut hasher, root, "", skip.as_deref());
+    format!("{:x}", hasher.finalize())
+}
+
+fn overlaps(a: &Blob, b: &Blob) -> bool {
+    a.addr < b.addr + b.size && b.addr < a.addr + a.size
+}
+
+fn path(workspace: &Path, board: &str) -> PathBuf {
+    workspace.join(DEPLOYED_DIR).join(format!("{board}.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use fitimage::{ComponentConfig, FitImageBuilder, FitImageConfig, fit::Timestamp};
+
+    use super::*;
+
+    /// FIT image of `kernel` with the device tree `dtbs[0]` and overlays.
+    fn fit(kernel: ComponentConfig, dtbs: &[&[u8]], timestamp: u32) -> Vec<u8> {
+        let mut config = FitImageConfig::new("test")
+            .with_timestamp(Timestamp::Fixed(timestamp))
+            .with_kernel(kernel)
+            .with_fdt(ComponentConfig::new("fdt", dtbs[0].to_vec()));
+        for (i, dtbo) in dtbs[1..].iter().enumerate() {
+            config = config.with_overlay(ComponentConfig::new(
+                format!("fdt-overlay-{}", i + 1),
+                dtbo.to_vec(),
+            ));
+        }
+        FitImageBuilder::new().build(config).unwrap()
+    }
+
+    fn kernel(data: &[u8]) -> ComponentConfig {
+        ComponentConfig::new("kernel", data.to_vec()).with_load_address(0x8020_0000)
+    }
+
+    fn deployed(kernel: ComponentConfig, dtbs: &[&[u8]]) -> Deployed {
+        let dtbs_data: Vec<_> = dtbs.iter().map(|d| d.to_vec()).collect();
+        let fit = fit(kernel, dtbs, 1);
+        Deployed::new(0x9000_0000, &fit, &dtbs_data, Some(0x8300_0000)).unwrap()
+    }
+
+    fn build(kernel_data: &[u8], dtb: &[u8]) -> Deployed {
+        deployed(kernel(kernel_data), &[dtb])
+    }
+
+    /// Record after a full transfer of `build(kernel, dtb)`.
+    fn transferred(kernel: &[u8], dtb: &[u8]) -> Deployed {
+        let current = build(kernel, dtb);
+        Plan::Full.record(&current).unwrap()
+    }
+
+    #[test]
+    fn test_plan() {
+        assert_eq!(Plan::new(None, &build(b"kernel", b"dtb")), Plan::Full);
+
+        let first = transferred(b"kernel", b"dtb");
+        assert_eq!(first.fdt, None);
+        let plan = Plan::new(Some(&first), &build(b"kernel", b"dtb"));
+        assert_eq!(
+            plan,
+            Plan::Reuse {
+                fit: first.fit,
+                fdt: None
+            }
+        );
+        assert_eq!(plan.bootcmd().unwrap(), "bootm 0x90000000");
+        assert_eq!(plan.record(&first), None);
+
+        let dtb = build(b"kernel", b"dtb2");
+        let plan = Plan::new(Some(&first), &dtb);
+        assert_eq!(
+            plan,
+            Plan::Dtb {
+                fit: first.fit,
+                fdt: dtb.fdt.unwrap()
+            }
+        );
+        assert_eq!(plan.bootcmd().unwrap(), "bootm 0x90000000 - 0x83000000");
+        assert_eq!(plan.resident(), [first.fit]);
+
+        assert_eq!(
+            Plan::new(Some(&first), &build(b"kernel2", b"dtb")),
+            Plan::Full
+        );
+
+        // 设备树带覆盖层时只能整体传输
+        let overlays = deployed(kernel(b"kernel"), &[b"dtb", b"dtbo"]);
+        assert_eq!(overlays.fdt, None);
+        assert_eq!(Plan::new(Some(&first), &overlays), Plan::Full);
+    }
+
+    #[test]
+    fn test_any_change_in_the_fit_image() {
+        let first = transferred(b"kernel", b"dtb");
+
+        // 只有时间戳不同时仍复用
+        let dtbs = [b"dtb".to_vec()];
+        let rebuilt = fit(kernel(b"kernel"), &[b"dtb"], 2);
+        let rebuilt = Deployed::new(0x9000_0000, &rebuilt, &dtbs, Some(0x8300_0000)).unwrap();
+        assert_ne!(rebuilt.fit, first.fit);
+        assert!(matches!(
+            Plan::new(Some(&first), &rebuilt),
+            Plan::Reuse { .. }
+        ));
+
+        for changed in [
+            kernel(b"kernel").with_compression(true),
+            kernel(b"kernel").with_entry_point(0x8020_0000),
+            ComponentConfig::new("kernel", b"kernel".to_vec()).with_load_address(0x8040_0000),
+        ] {
+            let current = deployed(changed, &[b"dtb"]);
+            assert_eq!(Plan::new(Some(&first), &current), Plan::Full);
+        }
+    }
+
+    #[test]
+    fn test_reuse_after_dtb_deploy() {
+        let first = transferred(b"kernel", b"dtb");
+        let current = build(b"kernel", b"dtb2");
+        let after = Plan::new(Some(&first), &current).record(&current).unwrap();
+        assert_eq!(after.fit, first.fit);
+
+        let plan = Plan::new(Some(&after), &build(b"kernel", b"dtb2"));
+        assert_eq!(
+            plan,
+            Plan::Reuse {
+                fit: first.fit,
+                fdt: current.fdt
+            }
+        );
+        assert_eq!(plan.bootcmd().unwrap(), "bootm 0x90000000 - 0x83000000");
+        assert_eq!(plan.resident().len(), 2);
+
+        // 改回原来的设备树时再只发送设备树
+        let back = build(b"kernel", b"dtb");
+        assert!(matches!(Plan::new(Some(&after), &back), Plan::Dtb { .. }));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(Deployed::load(dir.path(), "uboot"), None);
+        let deployed = transferred(b"kernel", b"dtb");
+        deployed.save(dir.path(), "uboot").unwrap();
+        assert_eq!(Deployed::load(dir.path(), "uboot"), Some(deployed));
+        Deployed::forget(dir.path(), "uboot");
+        assert_eq!(Deployed::load(dir.path(), "uboot"), None);
+    }
+}
@@ -0,0 +1,621 @@
+//! Debugger frontend for QEMU and OpenOCD targets.
+//!
+//! `ostool gdb` picks a debugger for the architecture of the kernel ELF
+//! (`rust-gdb`, `gdb-multiarch`, a cross `gdb` or `lldb`), loads its symbols,
+//! connects to the target and runs the init commands of the project, so
+//! nobody has to keep a `.gdbinit` of their own.
+//!
+//! `ostool run qemu -d` writes the same setup to `target/qemu.gdbinit`
+//! for any gdb frontend, and with `auto_attach` starts the debugger in
+//! another terminal once QEMU waits for it.
+//!
+//! # Configuration
+//!
+//! GDB configuration is stored in `.gdb.toml` files:
+//!
+//! ```toml
+//! breakpoints = ["rust_main"]
+//! init_cmds = ["continue"]
+//! auto_attach = true
+//! terminal = ["tmux", "split-window", "-h"]
+//!
+//! [target.Qemu]
+//! address = "localhost:1234"
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use jkconfig::t;
+use object::Architecture;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::{build::config::BuildSystem, ctx::AppContext, exit, utils::find_program};
+
+/// Debugger script of `ostool run qemu -d`, relative to the build directory.
+pub const QEMU_SCRIPT: &str = "qemu.gdbinit";
+
+/// GDB configuration structure.
+///
+/// This configuration is typically loaded from a `.gdb.toml` file.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct GdbConfig {
+    /// Debugger executable; picked from the ELF architecture when unset.
+    pub debugger: Option<String>,
+    /// Target to connect to.
+    #[serde(default)]
+    pub target: GdbTarget,
+    /// Breakpoints set after connecting, e.g. `rust_main` or
+    /// `src/main.rs:42`.
+    #[serde(default)]
+    pub breakpoints: Vec<String>,
+    /// Commands run after connecting and setting the breakpoints, e.g.
+    /// `continue`.
+    #[serde(default)]
+    pub init_cmds: Vec<String>,
+    /// Additional debugger command-line arguments.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Start the debugger when `ostool run qemu -d` launches QEMU.
+    #[serde(default)]
+    pub auto_attach: bool,
+    /// Command opening the debugger in another terminal for `auto_attach`,
+    /// e.g. `["tmux", "split-window", "-h"]`; a tmux split inside tmux when
+    /// empty.
+    #[serde(default)]
+    pub terminal: Vec<String>,
+}
+
+/// Target the debugger connects to.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub enum GdbTarget {
+    /// QEMU gdbstub, as started by `ostool run qemu -d`.
+    Qemu {
+        /// `host:port` of the gdbstub.
+        address: String,
+    },
+    /// OpenOCD GDB server of a hardware debug probe.
+    OpenOcd {
+        /// `host:port` of the GDB server.
+        address: String,
+        /// Reset and halt the target after connecting.
+        reset_halt: bool,
+        /// Load the ELF into target memory after connecting.
+        load: bool,
+    },
+}
+
+impl GdbTarget {
+    /// `host:port` of the target.
+    pub fn address(&self) -> &str {
+        match self {
+            Self::Qemu { address } | Self::OpenOcd { address, .. } => address,
+        }
+    }
+}
+
+impl Default for GdbTarget {
+    fn default() -> Self {
+        Self::Qemu {
+            address: "localhost:1234".to_string(),
+        }
+    }
+}
+
+/// Arguments for starting a debugger.
+#[derive(Debug, Clone, Default)]
+pub struct RunGdbArgs {
+    /// Optional path to the build configuration file, used to locate the ELF.
+    pub build_config: Option<PathBuf>,
+    /// Optional path to GDB configuration file.
+    pub gdb_config: Option<PathBuf>,
+    /// ELF to load symbols from instead of the build output.
+    pub elf: Option<PathBuf>,
+    /// Whether to use the release build output.
+    pub release: bool,
+}
+
+/// Command-line flavor of a debugger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebuggerKind {
+    Gdb,
+    Lldb,
+}
+
+/// A debugger executable and how to drive it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Debugger {
+    /// Executable name or path.
+    pub program: String,
+    pub kind: DebuggerKind,
+    /// `gdb` that `rust-gdb` should wrap, passed as `RUST_GDB`.
+    pub rust_gdb: Option<String>,
+}
+
+impl Debugger {
+    fn new(program: &str) -> Self {
+        let kind = if program.contains("lldb") {
+            DebuggerKind::Lldb
+        } else {
+            DebuggerKind::Gdb
+        };
+        Self {
+            program: program.to_string(),
+            kind,
+            rust_gdb: None,
+        }
+    }
+
+    /// Picks a debugger for `arch`, using `exists` to look up executables.
+    ///
+    /// A native target uses `gdb`, a foreign one `gdb-multiarch` or a cross
+    /// toolchain `gdb`, wrapped by `rust-gdb` for its pretty printers when
+    /// available. `lldb` is the last resort as it handles every architecture.
+    pub fn detect(
+        arch: Architecture,
+        host: Architecture,
+        exists: impl Fn(&str) -> bool,
+    ) -> Option<Self> {
+        let gdb = if arch == host {
+            exists("gdb").then_some("gdb")
+        } else {
+            ["gdb-multiarch"]
+                .iter()
+                .chain(cross_gdbs(arch))
+                .copied()
+                .find(|p| exists(p))
+        };
+        if let Some(gdb) = gdb {
+            if !exists("rust-gdb") {
+                return Some(Self::new(gdb));
+            }
+            return Some(Self {
+                rust_gdb: (gdb != "gdb").then(|| gdb.to_string()),
+                ..Self::new("rust-gdb")
+            });
+        }
+
+        if !exists("lldb") {
+            return None;
+        }
+        Some(Self::new(if exists("rust-lldb") {
+            "rust-lldb"
+        } else {
+            "lldb"
+        }))
+    }
+
+    /// Command-line arguments loading `elf` and connecting to `target`.
+    pub fn args(&self, elf: &Path, config: &GdbConfig) -> Vec<String> {
+        let (flag, mut cmds) = match self.kind {
+            DebuggerKind::Gdb => ("-ex", gdb_connect(&config.target)),
+            DebuggerKind::Lldb => ("-o", lldb_connect(&config.target)),
+        };
+        cmds.extend(config.breakpoints.iter().map(|b| match self.kind {
+            DebuggerKind::Gdb => format!("break {b}"),
+            DebuggerKind::Lldb => format!("b {b}"),
+        }));
+        cmds.extend(config.init_cmds.iter().cloned());
+
+        let mut args = Vec::new();
+        if self.kind == DebuggerKind::Gdb {
+            args.push("-q".to_string());
+        }
+        args.push(elf.display().to_string());
+        for cmd in cmds {
+            args.push(flag.to_string());
+            args.push(cmd);
+        }
+        args.extend(config.args.iter().cloned());
+        args
+    }
+}
+
+/// `.gdbinit` script loading `elf` for `arch`, connecting to the QEMU
+/// gdbstub of `config` and setting its breakpoints and init commands.
+pub fn qemu_script(elf: &Path, arch: Architecture, config: &GdbConfig) -> String {
+    let mut lines = vec!["# Generated by `ostool run qemu -d`".to_string()];
+    if let Some(name) = gdb_arch(arch) {
+        lines.push(format!("set architecture {name}"));
+    }
+    lines.push(format!("file \"{}\"", elf.display()));
+    lines.extend(gdb_connect(&qemu_target(config)));
+    lines.extend(config.breakpoints.iter().map(|b| format!("break {b}")));
+    lines.extend(config.init_cmds.iter().cloned());
+    lines.join("\n") + "\n"
+}
+
+/// The QEMU target of `config`, the default gdbstub if it is set up for
+/// OpenOCD.
+pub(crate) fn qemu_target(config: &GdbConfig) -> GdbTarget {
+    match &config.target {
+        target @ GdbTarget::Qemu { .. } => target.clone(),
+        GdbTarget::OpenOcd { .. } => GdbTarget::default(),
+    }
+}
+
+/// gdb's `set architecture` name for `arch`.
+pub(crate) fn gdb_arch(arch: Architecture) -> Option<&'static str> {
+    Some(match arch {
+        Architecture::Aarch64 => "aarch64",
+        Architecture::Arm => "arm",
+        Architecture::Riscv64 => "riscv:rv64",
+        Architecture::Riscv32 => "riscv:rv32",
+        Architecture::LoongArch64 => "Loongarch64",
+        Architecture::X86_64 => "i386:x86-64",
+        Architecture::I386 => "i386",
+        _ => return None,
+    })
+}
+
+fn gdb_connect(target: &GdbTarget) -> Vec<String> {
+    match target {
+        GdbTarget::Qemu { address } => vec![format!("target remote {address}")],
+        GdbTarget::OpenOcd {
+            address,
+            reset_halt,
+            load,
+        } => {
+            let mut cmds = vec![format!("target extended-remote {address}")];
+            if *reset_halt {
+                cmds.push("monitor reset halt".to_string());
+            }
+            if *load {
+                cmds.push("load".to_string());
+            }
+            cmds
+        }
+    }
+}
+
+fn lldb_connect(target: &GdbTarget) -> Vec<String> {
+    match target {
+        GdbTarget::Qemu { address } => vec![format!("gdb-remote {address}")],
+        GdbTarget::OpenOcd {
+            address,
+            reset_halt,
+            load,
+        } => {
+            let mut cmds = vec![format!("gdb-remote {address}")];
+            if *reset_halt {
+                cmds.push("process plugin packet monitor reset halt".to_string());
+            }
+            if *load {
+                warn!("{}", t!("gdb.lldb_no_load"));
+            }
+            cmds
+        }
+    }
+}
+
+/// Cross toolchain debuggers for `arch`, most common first.
+fn cross_gdbs(arch: Architecture) -> &'static [&'static str] {
+    match arch {
+        Architecture::Aarch64 => &["aarch64-none-elf-gdb", "aarch64-linux-gnu-gdb"],
+        Architecture::Arm => &["arm-none-eabi-gdb", "arm-linux-gnueabihf-gdb"],
+        Architecture::Riscv64 => &[
+            "riscv64-unknown-elf-gdb",
+            "riscv64-elf-gdb",
+            "riscv64-linux-gnu-gdb",
+        ],
+        Architecture::Riscv32 => &["riscv32-unknown-elf-gdb", "riscv64-unknown-elf-gdb"],
+        Architecture::LoongArch64 => &["loongarch64-linux-gnu-gdb"],
+        Architecture::X86_64 => &["x86_64-elf-gdb", "x86_64-linux-gnu-gdb"],
+        _ => &[],
+    }
+}
+
+/// Architecture of the machine ostool runs on.
+pub(crate) fn host_arch() -> Architecture {
+    match std::env::consts::ARCH {
+        "x86_64" => Architecture::X86_64,
+        "x86" => Architecture::I386,
+        "aarch64" => Architecture::Aarch64,
+        "arm" => Architecture::Arm,
+        "riscv64" => Architecture::Riscv64,
+        "loongarch64" => Architecture::LoongArch64,
+        _ => Architecture::Unknown,
+    }
+}
+
+/// Loads the GDB configuration of `path`, `None` if it is missing.
+pub(crate) async fn load_config(
+    ctx: &AppContext,
+    path: &Path,
+) -> anyhow::Result<Option<GdbConfig>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)
+        .await
+        .map_err(|_| anyhow!("can not open config file: {}", path.display()))?;
+    Ok(Some(ctx.parse_config(path, &content)?))
+}
+
+/// Debugger for `arch`, the one of `config` if set.
+pub(crate) fn pick_debugger(config: &GdbConfig, arch: Architecture) -> anyhow::Result<Debugger> {
+    match &config.debugger {
+        Some(program) => Ok(Debugger::new(program)),
+        None => Debugger::detect(arch, host_arch(), |p| find_program(p).is_some())
+            .ok_or_else(|| anyhow!(t!("gdb.not_found", arch = format!("{arch:?}")))),
+    }
+}
+
+/// Writes [`QEMU_SCRIPT`] for a QEMU waiting for a debugger and, with
+/// `auto_attach`, returns the command starting the debugger in another
+/// terminal.
+///
+/// # Errors
+///
+/// Returns an error if `.gdb.toml` is invalid or the script cannot be
+/// written.
+pub(crate) async fn prepare_qemu(
+    ctx: &AppContext,
+) -> anyhow::Result<Option<std::process::Command>> {
+    let (Some(elf), Some(arch)) = (&ctx.paths.artifacts.elf, ctx.arch) else {
+        return Ok(None);
+    };
+    let config = load_config(ctx, &ctx.paths.config_file(".gdb.toml"))
+        .await?
+        .unwrap_or_default();
+
+    let script = ctx.paths.build_dir().join(QEMU_SCRIPT);
+    if let Some(dir) = script.parent() {
+        fs::create_dir_all(dir).await?;
+    }
+    fs::write(&script, qemu_script(elf, arch, &config)).await?;
+    println!("{}", t!("gdb.script", path = script.display()));
+
+    if !config.auto_attach {
+        return Ok(None);
+    }
+    let debugger = pick_debugger(&config, arch)?;
+    let mut terminal = config.terminal.clone();
+    if terminal.is_empty() {
+        if std::env::var_os("TMUX").is_none() {
+            warn!("{}", t!("gdb.no_terminal"));
+            return Ok(None);
+        }
+        terminal = vec!["tmux".to_string(), "split-window".to_string()];
+    }
+
+    let mut cmd = std::process::Command::new(&terminal[0]);
+    cmd.args(&terminal[1..]);
+    // 新终端不一定继承 ostool 的环境变量
+    if let Some(gdb) = &debugger.rust_gdb {
+        cmd.arg("env").arg(format!("RUST_GDB={gdb}"));
+    }
+    cmd.arg(&debugger.program);
+    match debugger.kind {
+        DebuggerKind::Gdb => {
+            cmd.arg("-q").arg("-x").arg(&script).args(&config.args);
+        }
+        DebuggerKind::Lldb => {
+            let config = GdbConfig {
+                target: qemu_target(&config),
+                ..config.clone()
+            };
+            cmd.args(debugger.args(elf, &config));
+        }
+    }
+    println!("{}", t!("gdb.attaching", debugger = debugger.program));
+    Ok(Some(cmd))
+}
+
+/// Starts a debugger on the kernel ELF and connects it to the target.
+///
+/// The ELF is the build output of the build configuration unless given in
+/// `args`; it is not rebuilt.
+///
+/// # Errors
+///
+/// Returns an error if the ELF cannot be found, no debugger is installed
+/// for its architecture, or the debugger fails.
+pub async fn run_gdb(mut ctx: AppContext, args: RunGdbArgs) -> anyhow::Result<()> {
+    let config_path = match args.gdb_config.clone() {
+        Some(path) => path,
+        None => ctx.paths.config_file(".gdb.toml"),
+    };
+
+    let config = match load_config(&ctx, &config_path).await? {
+        Some(config) => {
+            info!("Using GDB config file: {}", config_path.display());
+            config
+        }
+        None => {
+            let config = GdbConfig::default();
+            fs::write(&config_path, toml::to_string_pretty(&config)?).await?;
+            config
+        }
+    };
+
+    let elf = match args.elf.clone() {
+        Some(elf) => elf,
+        None => locate_elf(&mut ctx, &args).await?,
+    };
+    let elf = elf
+        .canonicalize()
+        .map_err(|_| anyhow!(t!("gdb.elf_missing", path = elf.display())))?;
+    ctx.set_elf_path(elf.clone()).await;
+    let arch = ctx
+        .arch
+        .ok_or_else(|| anyhow!(t!("gdb.unknown_arch", path = elf.display())))?;
+
+    let debugger = pick_debugger(&config, arch)?;
+    println!(
+        "{}",
+        t!(
+            "gdb.using",
+            debugger = debugger.program,
+            arch = format!("{arch:?}")
+        )
+    );
+
+    let mut cmd = ctx.command(&debugger.program);
+    if let Some(gdb) = &debugger.rust_gdb {
+        cmd.env("RUST_GDB", gdb);
+    }
+    cmd.args(debugger.args(&elf, &config));
+
+    // Ctrl+C 由调试器用来中断目标，ostool 自身不应因此退出
+    let _pass = exit::pass_through();
+    cmd.run()
+}
+
+/// Path of the ELF the build configuration produces.
+pub(crate) async fn locate_elf(ctx: &mut AppContext, args: &RunGdbArgs) -> anyhow::Result<PathBuf> {
+    let config = ctx
+        .prepare_build_config(args.build_config.clone(), false)
+        .await?;
+    match config.system {
+        BuildSystem::Cargo(cargo) => {
+            let dir = ctx.paths.build_dir().join(&cargo.target);
+            let debug = dir.join("debug").join(&cargo.package);
+            if !args.release && debug.exists() {
+                return Ok(debug);
+            }
+            Ok(dir.join("release").join(&cargo.package))
+        }
+        BuildSystem::Custom(custom) => {
+            let elf = PathBuf::from(ctx.value_replace_with_var(&custom.elf_path));
+            Ok(if elf.is_relative() {
+                ctx.paths.manifest.join(elf)
+            } else {
+                elf
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_debugger() {
+        let installed = |list: &'static [&'static str]| move |p: &str| list.contains(&p);
+
+        // Native target
+        let d = Debugger::detect(
+            Architecture::X86_64,
+            Architecture::X86_64,
+            installed(&["gdb", "rust-gdb"]),
+        )
+        .unwrap();
+        assert_eq!(d, Debugger::new("rust-gdb"));
+
+        // rust-gdb alone cannot run
+        let d = Debugger::detect(
+            Architecture::X86_64,
+            Architecture::X86_64,
+            installed(&["rust-gdb", "lldb"]),
+        )
+        .unwrap();
+        assert_eq!(d, Debugger::new("lldb"));
+
+        // Foreign target: rust-gdb wrapping gdb-multiarch
+        let d = Debugger::detect(
+            Architecture::Aarch64,
+            Architecture::X86_64,
+            installed(&["gdb", "rust-gdb", "gdb-multiarch"]),
+        )
+        .unwrap();
+        assert_eq!(d.program, "rust-gdb");
+        assert_eq!(d.rust_gdb.as_deref(), Some("gdb-multiarch"));
+
+        // Cross toolchain, then lldb; a native gdb is no use
+        let d = Debugger::detect(
+            Architecture::Riscv64,
+            Architecture::X86_64,
+            installed(&["gdb", "riscv64-unknown-elf-gdb", "lldb"]),
+        )
+        .unwrap();
+        assert_eq!(d, Debugger::new("riscv64-unknown-elf-gdb"));
+        let d = Debugger::detect(
+            Architecture::Riscv64,
+            Architecture::X86_64,
+            installed(&["gdb", "lldb"]),
+        )
+        .unwrap();
+        assert_eq!(d.kind, DebuggerKind::Lldb);
+        assert!(
+            Debugger::detect(
+                Architecture::Riscv64,
+                Architecture::X86_64,
+                installed(&["gdb"])
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_debugger_args() {
+        let config = GdbConfig {
+            target: GdbTarget::OpenOcd {
+                address: "localhost:3333".to_string(),
+                reset_halt: true,
+                load: true,
+            },
+            init_cmds: vec!["break rust_main".to_string()],
+            ..Default::default()
+        };
+        let elf = Path::new("kernel");
+        assert_eq!(
+            Debugger::new("gdb-multiarch").args(elf, &config),
+            [
+                "-q",
+                "kernel",
+                "-ex",
+                "target extended-remote localhost:3333",
+                "-ex",
+                "monitor reset halt",
+                "-ex",
+                "load",
+                "-ex",
+                "break rust_main",
+            ]
+        );
+
+        let config = GdbConfig {
+            breakpoints: vec!["main.rs:42".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            Debugger::new("lldb").args(elf, &config),
+            [
+                "kernel",
+                "-o",
+                "gdb-remote localhost:1234",
+                "-o",
+                "b main.rs:42"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_qemu_script() {
+        let config = GdbConfig {
+            target: GdbTarget::OpenOcd {
+                address: "localhost:3333".to_string(),
+                reset_halt: true,
+                load: false,
+            },
+            breakpoints: vec!["rust_main".to_string()],
+            init_cmds: vec!["continue".to_string()],
+            ..Default::default()
+        };
+        let script = qemu_script(Path::new("/w/kernel"), Architecture::Riscv64, &config);
+        assert_eq!(
+            script.lines().skip(1).collect::<Vec<_>>(),
+            [
+                "set architecture riscv:rv64",
+                "file \"/w/kernel\"",
+                "target remote localhost:1234",
+                "break rust_main",
+                "continue",
+            ]
+        );
+    }
+}
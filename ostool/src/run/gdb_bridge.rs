@@ -0,0 +1,368 @@
+//! GDB remote protocol bridge over the serial console.
+//!
+//! A kernel gdbstub on real hardware usually shares the UART with the
+//! console. `ostool run uboot --debug` listens on [`DEFAULT_ADDRESS`], the
+//! address `ostool gdb` connects to by default, and splits the bytes the
+//! board sends:
+//!
+//! - packets `$...#xx` with a valid checksum go to the connected debugger,
+//! - `+`/`-` acknowledgements go to it too while it waits for one,
+//! - everything else is console output for the terminal.
+//!
+//! What the debugger sends, including the `0x03` interrupt, is written to
+//! the serial port between the keys typed into the terminal. Bytes that
+//! look like the start of a packet are held until the packet ends, the
+//! port goes quiet or [`MAX_PACKET`] bytes are reached, so a `$ ` shell
+//! prompt still shows up.
+
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+
+use jkconfig::t;
+
+/// Address the bridge listens on, the default target of `ostool gdb`.
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:1234";
+
+/// Longest packet looked for; longer runs of bytes are passed on as text.
+pub const MAX_PACKET: usize = 16 * 1024;
+
+type Tx = Box<dyn Write + Send>;
+type Rx = Box<dyn Read + Send>;
+
+/// Where the demultiplexer is within a packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Text,
+    /// After `$`, up to `#`.
+    Packet,
+    /// After `#`, with the checksum digits seen so far.
+    Checksum(u8),
+}
+
+/// Splits the bytes of the serial port into console text and packets of
+/// the gdbstub.
+#[derive(Debug)]
+pub struct Demux {
+    state: State,
+    /// Bytes of the packet being received, from `$` on.
+    held: Vec<u8>,
+    console: VecDeque<u8>,
+    gdb: Vec<u8>,
+    /// Set while the debugger waits for the acknowledgement of a packet.
+    awaiting_ack: Arc<AtomicBool>,
+}
+
+impl Demux {
+    /// Creates a demultiplexer; `awaiting_ack` is set by the writer of the
+    /// debugger's packets.
+    pub fn new(awaiting_ack: Arc<AtomicBool>) -> Self {
+        Self {
+            state: State::Text,
+            held: Vec::new(),
+            console: VecDeque::new(),
+            gdb: Vec::new(),
+            awaiting_ack,
+        }
+    }
+
+    /// Sorts received `data` into console text and debugger bytes.
+    pub fn feed(&mut self, data: &[u8]) {
+        for &b in data {
+            match (self.state, b) {
+                (State::Text, b'$') | (State::Packet, b'$') => {
+                    self.flush();
+                    self.held.push(b);
+                    self.state = State::Packet;
+                }
+                (State::Text, b'+' | b'-') if self.awaiting_ack.swap(false, Ordering::AcqRel) => {
+                    self.gdb.push(b);
+                }
+                (State::Text, _) => self.console.push_back(b),
+                (State::Packet, b'#') => {
+                    self.held.push(b);
+                    self.state = State::Checksum(0);
+                }
+                (State::Packet, _) => {
+                    self.held.push(b);
+                    if self.held.len() > MAX_PACKET {
+                        self.flush();
+                    }
+                }
+                (State::Checksum(n), _) => {
+                    self.held.push(b);
+                    if !b.is_ascii_hexdigit() {
+                        self.flush();
+                    } else if n == 0 {
+                        self.state = State::Checksum(1);
+                    } else if is_packet(&self.held) {
+                        self.gdb.append(&mut self.held);
+                        self.state = State::Text;
+                    } else {
+                        self.flush();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Passes held bytes on as console text.
+    pub fn flush(&mut self) {
+        self.console.extend(self.held.drain(..));
+        self.state = State::Text;
+    }
+
+    /// Takes the bytes for the debugger.
+    pub fn take_gdb(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.gdb)
+    }
+}
+
+/// Whether `data` is `$...#xx` with a matching checksum.
+fn is_packet(data: &[u8]) -> bool {
+    let [b'$', body @ .., b'#', hi, lo] = data else {
+        return false;
+    };
+    let sum = body.iter().fold(0u8, |acc, &b| acc.wrapping_add(b));
+    let hex = |b: u8| (b as char).to_digit(16);
+    matches!((hex(*hi), hex(*lo)), (Some(hi), Some(lo)) if (hi * 16 + lo) as u8 == sum)
+}
+
+/// Debugger connection shared by the reader and the accepting thread.
+type Client = Arc<Mutex<Option<TcpStream>>>;
+
+/// Listening socket of the bridge.
+pub struct GdbBridge {
+    listener: TcpListener,
+}
+
+impl GdbBridge {
+    /// Listens on `addr`, e.g. [`DEFAULT_ADDRESS`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address cannot be bound.
+    pub fn bind(addr: &str) -> anyhow::Result<Self> {
+        let listener = TcpListener::bind(addr).map_err(|e| anyhow!("gdb bridge on {addr}: {e}"))?;
+        Ok(Self { listener })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Starts serving debuggers, returning the serial port for the terminal:
+    /// `rx` without the gdbstub's packets and a `tx` shared with the
+    /// debugger.
+    pub fn attach(self, tx: Tx, rx: Rx) -> (Tx, Rx) {
+        let tx = Arc::new(Mutex::new(tx));
+        let client = Client::default();
+        let awaiting_ack = Arc::new(AtomicBool::new(false));
+        thread::spawn({
+            let tx = tx.clone();
+            let client = client.clone();
+            let awaiting_ack = awaiting_ack.clone();
+            move || self.serve(tx, client, awaiting_ack)
+        });
+        let reader = BridgeReader {
+            inner: rx,
+            demux: Demux::new(awaiting_ack),
+            client,
+        };
+        (Box::new(SharedTx(tx)), Box::new(reader))
+    }
+
+    /// Accepts one debugger at a time and copies what it sends to `tx`.
+    fn serve(self, tx: Arc<Mutex<Tx>>, client: Client, awaiting_ack: Arc<AtomicBool>) {
+        for stream in self.listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            let Ok(writer) = stream.try_clone() else {
+                continue;
+            };
+            let peer = stream
+                .peer_addr()
+                .map(|a| a.to_string())
+                .unwrap_or_default();
+            print!("\r\n{}\r\n", t!("gdb_bridge.connected", peer = peer));
+            *client.lock().unwrap() = Some(writer);
+
+            let mut buf = [0u8; 1024];
+            loop {
+                let n = match stream.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => n,
+                };
+                let data = &buf[..n];
+                if data.contains(&b'$') {
+                    awaiting_ack.store(true, Ordering::Release);
+                }
+                let mut tx = tx.lock().unwrap();
+                if tx.write_all(data).and_then(|_| tx.flush()).is_err() {
+                    break;
+                }
+            }
+            client.lock().unwrap().take();
+            awaiting_ack.store(false, Ordering::Release);
+            print!("\r\n{}\r\n", t!("gdb_bridge.disconnected"));
+        }
+    }
+}
+
+/// Serial port writer shared by the terminal and the debugger.
+struct SharedTx(Arc<Mutex<Tx>>);
+
+impl Write for SharedTx {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.lock().unwrap().flush()
+    }
+}
+
+/// Serial port reader handing the gdbstub's packets to the debugger.
+struct BridgeReader {
+    inner: Rx,
+    demux: Demux,
+    client: Client,
+}
+
+impl BridgeReader {
+    fn forward(&mut self) {
+        let data = self.demux.take_gdb();
+        if data.is_empty() {
+            return;
+        }
+        let mut client = self.client.lock().unwrap();
+        // 没有调试器连接时丢弃，连接后 GDB 会重新询问停止原因
+        if let Some(stream) = client.as_mut()
+            && stream.write_all(&data).is_err()
+        {
+            client.take();
+        }
+    }
+}
+
+impl Read for BridgeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut chunk = [0u8; 1024];
+        while self.demux.console.is_empty() {
+            match self.inner.read(&mut chunk) {
+                Ok(0) => {
+                    self.demux.flush();
+                    if self.demux.console.is_empty() {
+                        return Ok(0);
+                    }
+                }
+                Ok(n) => {
+                    self.demux.feed(&chunk[..n]);
+                    self.forward();
+                }
+                // 串口空闲时放出未结束的包，例如 `$ ` 提示符
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                    self.demux.flush();
+                    if self.demux.console.is_empty() {
+                        return Err(e);
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        let n = buf.len().min(self.demux.console.len());
+        for (dst, src) in buf.iter_mut().zip(self.demux.console.drain(..n)) {
+            *dst = src;
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn console(demux: &mut Demux) -> String {
+        String::from_utf8(demux.console.drain(..).collect()).unwrap()
+    }
+
+    #[test]
+    fn test_demux() {
+        let awaiting_ack = Arc::new(AtomicBool::new(false));
+        let mut demux = Demux::new(awaiting_ack.clone());
+        demux.feed(b"[ 1.0] panic\r\n$T05thread:01;#07");
+        demux.feed(b"more+\r\n");
+        assert_eq!(console(&mut demux), "[ 1.0] panic\r\nmore+\r\n");
+        assert_eq!(demux.take_gdb(), b"$T05thread:01;#07");
+
+        // 调试器发出包后，下一个 `+` 是应答
+        awaiting_ack.store(true, Ordering::Release);
+        demux.feed(b"+$OK#9a+");
+        assert_eq!(demux.take_gdb(), b"+$OK#9a");
+        assert_eq!(console(&mut demux), "+");
+
+        // 校验和不对的是普通文本，空闲时放出未结束的 `$ `
+        demux.feed(b"cost $5#00\n$ ");
+        assert_eq!(console(&mut demux), "cost $5#00\n");
+        demux.flush();
+        assert_eq!(console(&mut demux), "$ ");
+        assert!(demux.take_gdb().is_empty());
+    }
+
+    #[test]
+    fn test_bridge() {
+        let (board_tx, board_rx) = std::sync::mpsc::channel::<Vec<u8>>();
+        struct Port(std::sync::mpsc::Receiver<Vec<u8>>);
+        impl Read for Port {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                match self.0.recv_timeout(std::time::Duration::from_millis(50)) {
+                    Ok(data) => {
+                        buf[..data.len()].copy_from_slice(&data);
+                        Ok(data.len())
+                    }
+                    Err(_) => Err(io::ErrorKind::TimedOut.into()),
+                }
+            }
+        }
+        let written = Arc::new(Mutex::new(Vec::new()));
+        struct Sink(Arc<Mutex<Vec<u8>>>);
+        impl Write for Sink {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let bridge = GdbBridge::bind("127.0.0.1:0").unwrap();
+        let addr = bridge.local_addr().unwrap();
+        let (mut tx, mut rx) =
+            bridge.attach(Box::new(Sink(written.clone())), Box::new(Port(board_rx)));
+        let mut gdb = TcpStream::connect(addr).unwrap();
+        gdb.write_all(b"$?#3f").unwrap();
+        tx.write_all(b"ls\r").unwrap();
+        while written.lock().unwrap().len() < 8 {
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+        let written = written.lock().unwrap().clone();
+        assert!(written == b"$?#3fls\r" || written == b"ls\r$?#3f");
+
+        board_tx.send(b"+$S05#b8boot\n".to_vec()).unwrap();
+        let mut buf = [0u8; 64];
+        let n = rx.read(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"boot\n");
+        let mut reply = [0u8; 8];
+        gdb.read_exact(&mut reply).unwrap();
+        assert_eq!(&reply, b"+$S05#b8");
+    }
+}
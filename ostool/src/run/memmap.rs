@@ -0,0 +1,313 @@
+//! Memory map check before loading a kernel with U-Boot.
+//!
+//! The FIT image, the kernel unpacked from it and the device tree must each
+//! lie in one DRAM bank and must not overlap each other or the memory U-Boot
+//! relocated itself to. A load breaking this hangs the board or corrupts
+//! U-Boot far from the cause, so the U-Boot runner checks the addresses
+//! before any transfer and fails with the memory map instead:
+//!
+//! ```text
+//! 0x40000000-0xbfffffff  DRAM     2.0 GiB
+//! 0x40200000-0x40cfffff  kernel   11.0 MiB   <- kernel overlaps FIT
+//! 0x40800000-0x40dfffff  FIT      6.0 MiB
+//! 0xbdf00000-0xbfffffff  U-Boot   33.0 MiB
+//! ```
+//!
+//! DRAM banks come from `dram` in `.uboot.toml`, or else from `bdinfo`,
+//! which also tells where U-Boot lives.
+
+use std::fmt::Write as _;
+
+use jkconfig::t;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{human_size, parse_int};
+
+/// Stack space kept below U-Boot's lowest reported address.
+const STACK_RESERVE: u64 = 0x10_0000;
+
+/// DRAM bank of the board.
+#[derive(Default, Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct DramBank {
+    /// Start address, e.g. `0x40000000`
+    pub start: String,
+    /// Size in bytes, e.g. `0x80000000`
+    pub size: String,
+}
+
+impl DramBank {
+    /// The bank as a region.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the start or size is not a number.
+    pub fn region(&self) -> anyhow::Result<Region> {
+        let start = parse_int(&self.start)
+            .ok_or_else(|| anyhow!(t!("memmap.invalid_bank", value = self.start)))?;
+        let size = parse_int(&self.size)
+            .ok_or_else(|| anyhow!(t!("memmap.invalid_bank", value = self.size)))?;
+        Ok(Region::new("DRAM", start, size))
+    }
+}
+
+/// A named range of memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    /// Name shown in the memory map, e.g. `kernel`.
+    pub name: String,
+    /// First address.
+    pub start: u64,
+    /// Size in bytes.
+    pub size: u64,
+}
+
+impl Region {
+    /// A region of `size` bytes at `start`.
+    pub fn new(name: impl Into<String>, start: u64, size: u64) -> Self {
+        Self {
+            name: name.into(),
+            start,
+            size,
+        }
+    }
+
+    /// First address past the region.
+    pub fn end(&self) -> u64 {
+        self.start.saturating_add(self.size)
+    }
+
+    fn overlaps(&self, other: &Region) -> bool {
+        self.start < other.end() && other.start < self.end()
+    }
+
+    fn contains(&self, other: &Region) -> bool {
+        self.start <= other.start && other.end() <= self.end()
+    }
+}
+
+/// What `bdinfo` tells about the board's memory.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BoardInfo {
+    /// DRAM banks.
+    pub dram: Vec<Region>,
+    /// U-Boot with its heap, device tree copy and stack, up to the end of
+    /// its DRAM bank.
+    pub uboot: Option<Region>,
+}
+
+impl BoardInfo {
+    /// Parses the output of `bdinfo`; lines it does not know are skipped.
+    pub fn parse(bdinfo: &str) -> Self {
+        let mut dram = Vec::<(Option<u64>, Option<u64>)>::new();
+        let mut lowest = None::<u64>;
+        let mut relocaddr = None;
+        for line in bdinfo.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.split_whitespace().next().and_then(parse_int);
+            match key.trim() {
+                "DRAM bank" => dram.push((None, None)),
+                "-> start" => {
+                    if let Some(bank) = dram.last_mut() {
+                        bank.0 = value;
+                    }
+                }
+                "-> size" => {
+                    if let Some(bank) = dram.last_mut() {
+                        bank.1 = value;
+                    }
+                }
+                key @ ("relocaddr" | "sp start" | "irq_sp" | "new_fdt") => {
+                    // U-Boot 在 DRAM 顶端：代码、堆、设备树副本、栈依次向下
+                    if let Some(value) = value.filter(|v| *v != 0) {
+                        lowest = Some(lowest.map_or(value, |l| l.min(value)));
+                        if key == "relocaddr" {
+                            relocaddr = Some(value);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let dram = dram
+            .into_iter()
+            .filter_map(|bank| match bank {
+                (Some(start), Some(size)) if size > 0 => Some(Region::new("DRAM", start, size)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let uboot = relocaddr.zip(lowest).and_then(|(relocaddr, lowest)| {
+            let bank = dram
+                .iter()
+                .find(|bank| bank.contains(&Region::new("", relocaddr, 1)))?;
+            let start = lowest.saturating_sub(STACK_RESERVE).max(bank.start);
+            Some(Region::new("U-Boot", start, bank.end() - start))
+        });
+        Self { dram, uboot }
+    }
+
+    /// Checks that every load lies in one DRAM bank and overlaps neither
+    /// another load nor U-Boot.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error listing the problems, followed by the memory map.
+    pub fn check(&self, loads: &[Region]) -> anyhow::Result<()> {
+        let problems = self.problems(loads);
+        if problems.is_empty() {
+            return Ok(());
+        }
+        let mut msg = t!("memmap.failed").to_string();
+        for (_, problem) in &problems {
+            write!(msg, "\n  - {problem}")?;
+        }
+        write!(msg, "\n\n{}", self.diagram(loads, &problems))?;
+        bail!(msg)
+    }
+
+    /// Problems as the load they are marked at in the diagram and the
+    /// message.
+    fn problems(&self, loads: &[Region]) -> Vec<(usize, String)> {
+        let mut problems = Vec::new();
+        for (i, load) in loads.iter().enumerate() {
+            if !self.dram.is_empty() && !self.dram.iter().any(|bank| bank.contains(load)) {
+                problems.push((i, t!("memmap.outside_dram", name = load.name)));
+            }
+            if let Some(uboot) = self.uboot.as_ref().filter(|u| u.overlaps(load)) {
+                problems.push((
+                    i,
+                    t!("memmap.overlap", name = load.name, other = uboot.name),
+                ));
+            }
+            for other in &loads[..i] {
+                if other.overlaps(load) {
+                    problems.push((
+                        i,
+                        t!("memmap.overlap", name = load.name, other = other.name),
+                    ));
+                }
+            }
+        }
+        problems
+    }
+
+    fn diagram(&self, loads: &[Region], problems: &[(usize, String)]) -> String {
+        let mut rows = self
+            .dram
+            .iter()
+            .chain(&self.uboot)
+            .map(|region| (region, None))
+            .chain(loads.iter().enumerate().map(|(i, load)| (load, Some(i))))
+            .collect::<Vec<_>>();
+        rows.sort_by_key(|(region, _)| (region.start, region.name != "DRAM"));
+        let mut out = String::new();
+        for (region, load) in rows {
+            let mut line = format!(
+                "{:#010x}-{:#010x}  {:<8} {}",
+                region.start,
+                region.end().saturating_sub(1),
+                region.name,
+                human_size(region.size)
+            );
+            let marks = problems
+                .iter()
+                .filter(|(i, _)| Some(*i) == load)
+                .map(|(_, problem)| problem.as_str())
+                .collect::<Vec<_>>();
+            if !marks.is_empty() {
+                write!(line, "   <- {}", marks.join("; ")).unwrap();
+            }
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BDINFO: &str = "bdinfo
+boot_params = 0x0000000000000000
+DRAM bank   = 0x0000000000000000
+-> start    = 0x0000000040000000
+-> size     = 0x0000000080000000
+DRAM bank   = 0x0000000000000001
+-> start    = 0x0000000000000000
+-> size     = 0x0000000000000000
+flashstart  = 0x0000000000000000
+relocaddr   = 0x00000000bff5b000
+reloc off   = 0x00000000bf75b000
+Build       = 64-bit
+fdt_blob    = 0x00000000bdf24e10
+new_fdt     = 0x00000000bdf24e10
+fdt_size    = 0x0000000000009000
+irq_sp      = 0x00000000bdf24e00
+sp start    = 0x00000000bdf24e00
+";
+
+    #[test]
+    fn test_parse_bdinfo() {
+        let info = BoardInfo::parse(BDINFO);
+        assert_eq!(info.dram, [Region::new("DRAM", 0x4000_0000, 0x8000_0000)]);
+        let uboot = info.uboot.unwrap();
+        assert_eq!(uboot.start, 0xbdf2_4e00 - STACK_RESERVE);
+        assert_eq!(uboot.end(), 0xc000_0000);
+        assert_eq!(
+            BoardInfo::parse("Unknown command 'bdinfo'"),
+            BoardInfo::default()
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let info = BoardInfo::parse(BDINFO);
+        let ok = [
+            Region::new("FIT", 0x4200_0000, 0x60_0000),
+            Region::new("kernel", 0x4020_0000, 0xb0_0000),
+            Region::new("fdt", 0x4400_0000, 0x1_0000),
+        ];
+        info.check(&ok).unwrap();
+
+        let bad = [
+            Region::new("FIT", 0x4080_0000, 0x60_0000),
+            Region::new("kernel", 0x4020_0000, 0xb0_0000),
+            Region::new("fdt", 0xbe00_0000, 0x1_0000),
+            Region::new("initrd", 0xc000_0000, 0x1000),
+        ];
+        let problems = info.problems(&bad);
+        assert_eq!(
+            problems.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        let msg = info.check(&bad).unwrap_err().to_string();
+        let map = msg.split("\n\n").nth(1).unwrap();
+        let names = map
+            .lines()
+            .map(|line| line.split_whitespace().nth(1).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["DRAM", "kernel", "FIT", "U-Boot", "fdt", "initrd"]);
+        assert!(map.lines().nth(1).unwrap().contains("<- "), "{map}");
+        assert!(!map.lines().nth(2).unwrap().contains("<- "), "{map}");
+
+        // 不知道 DRAM 时只检查相互重叠
+        BoardInfo::default().check(&ok[..1]).unwrap();
+    }
+
+    #[test]
+    fn test_dram_bank() {
+        let bank = DramBank {
+            start: "0x8000_0000".to_string(),
+            size: "0x4000_0000".to_string(),
+        };
+        assert_eq!(bank.region().unwrap().end(), 0xc000_0000);
+        let bank = DramBank {
+            start: "ram".to_string(),
+            size: "1".to_string(),
+        };
+        assert!(bank.region().is_err());
+    }
+}
@@ -1,20 +1,77 @@
-//! Runtime execution modules for QEMU, TFTP, and U-Boot.
+//! Runtime execution modules for QEMU, TFTP, U-Boot, OpenOCD, probe-rs, GDB,
+//! kernel tests and parallel board runs.
 //!
 //! This module contains implementations for running operating systems
 //! in various environments:
 //!
+//! - [`boards`] - Build once and boot on several boards in parallel
+//! - [`crash`] - Crash bundles captured through the QEMU gdbstub
+//! - [`deployed`] - Images left in board RAM, reused by U-Boot runs
+//! - [`defmt`] - Decoding defmt frames in the console output
+//! - [`gdb`] - Debugger attached to QEMU or OpenOCD
+//! - [`gdb_bridge`] - GDB port for a kernel gdbstub on the serial console
+//! - [`memmap`] - Load addresses checked against DRAM and U-Boot
+//! - [`openocd`] - Loading over JTAG/SWD for boards without a bootloader
+//! - [`probe_rs`] - Flashing microcontrollers with RTT/defmt output
 //! - [`qemu`] - Running in QEMU emulator with UEFI support
+//! - [`qmp`] - QEMU Machine Protocol client
+//! - [`rules`] - Console rules judging the output of every runner
+//! - [`snapshot`] - QEMU VM snapshots skipping long boots
+//! - [`test`] - Kernel test harness on top of QEMU
 //! - [`tftp`] - TFTP server for network booting
 //! - [`uboot`] - U-Boot bootloader integration via serial/YMODEM
+//! - [`usb`] - USB DFU and fastboot transfer for U-Boot
+
+/// Parallel runs on several U-Boot boards.
+pub mod boards;
+
+/// Crash triage of QEMU test boots.
+pub mod crash;
+
+/// Records of the images loaded to U-Boot boards.
+pub mod deployed;
+
+/// defmt frame decoding for console output.
+pub mod defmt;
+
+/// Debugger frontend for QEMU and OpenOCD targets.
+pub mod gdb;
+
+/// GDB remote protocol bridge over the serial console.
+pub mod gdb_bridge;
+
+/// Memory map check of the U-Boot loads.
+pub mod memmap;
+
+/// OpenOCD runner loading the kernel through a debug probe.
+pub mod openocd;
+
+/// probe-rs runner for microcontrollers.
+pub mod probe_rs;
 
 /// QEMU emulator runner with UEFI/OVMF support.
 pub mod qemu;
 
+/// QEMU Machine Protocol client.
+pub mod qmp;
+
+/// Console rules shared by the runners.
+pub mod rules;
+
+/// QEMU VM snapshots.
+pub mod snapshot;
+
+/// Kernel test harness on top of QEMU.
+pub mod test;
+
 /// TFTP server for network booting.
 pub mod tftp;
 
 /// U-Boot bootloader integration.
 pub mod uboot;
 
+/// USB DFU and fastboot transfer for U-Boot.
+pub mod usb;
+
 /// OVMF prebuilt firmware downloader (internal).
 mod ovmf_prebuilt;
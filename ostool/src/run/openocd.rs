@@ -0,0 +1,447 @@
+//! OpenOCD/JTAG runner for boards without a bootloader.
+//!
+//! `ostool run openocd` starts OpenOCD with the interface and target scripts
+//! of the board, loads the kernel ELF through OpenOCD's TCL port, optionally
+//! sets a hardware breakpoint and resumes the target at the ELF entry.
+//! OpenOCD's output, which carries the kernel's semihosting console, is
+//! shown in the terminal until a `success_regex`, `fail_regex` or project
+//! `console_rules` entry ends the run, OpenOCD exits or Ctrl+C is pressed.
+//!
+//! # Configuration
+//!
+//! OpenOCD configuration is stored in `.openocd.toml` files:
+//!
+//! ```toml
+//! scripts = ["interface/cmsis-dap.cfg", "target/stm32f4x.cfg"]
+//! commands = ["adapter speed 4000"]
+//! semihosting = true
+//! breakpoint = "rust_main"
+//! success_regex = ["All tests passed"]
+//! ```
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    path::{Path, PathBuf},
+    process::{Child, Stdio},
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use jkconfig::t;
+use object::{Object, ObjectSymbol};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::{fs, sync::mpsc};
+
+use crate::{
+    ctx::AppContext,
+    exit::{self, Failure},
+    run::rules::ConsoleRules,
+    utils::{find_program, parse_int, replace_env_placeholders},
+};
+
+/// Default port of the OpenOCD TCL server.
+pub const DEFAULT_TCL_PORT: u16 = 6666;
+/// Default port of the OpenOCD GDB server.
+pub const DEFAULT_GDB_PORT: u16 = 3333;
+
+/// End of a TCL server message.
+const EOM: u8 = 0x1a;
+/// Prefix of the result of a failed command, see [`TclClient::exec`].
+const ERROR_PREFIX: &str = "ostool-error: ";
+
+/// OpenOCD configuration structure.
+///
+/// This configuration is typically loaded from a `.openocd.toml` file.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct OpenOcdConfig {
+    /// OpenOCD executable, `openocd` when unset
+    pub openocd: Option<String>,
+    /// Interface and target scripts, passed with `-f`
+    #[serde(default)]
+    pub scripts: Vec<String>,
+    /// Extra configuration commands, passed with `-c` after the scripts
+    #[serde(default)]
+    pub commands: Vec<String>,
+    /// Port of the TCL server, 6666 when unset
+    pub tcl_port: Option<u16>,
+    /// Port of the GDB server, 3333 when unset
+    pub gdb_port: Option<u16>,
+    /// Enable ARM/RISC-V semihosting so the kernel console goes through
+    /// the debug probe
+    #[serde(default)]
+    pub semihosting: bool,
+    /// Hardware breakpoint set before resuming, a symbol or an address
+    pub breakpoint: Option<String>,
+    #[serde(default)]
+    pub success_regex: Vec<String>,
+    #[serde(default)]
+    pub fail_regex: Vec<String>,
+}
+
+impl OpenOcdConfig {
+    fn tcl_port(&self) -> u16 {
+        self.tcl_port.unwrap_or(DEFAULT_TCL_PORT)
+    }
+
+    fn gdb_port(&self) -> u16 {
+        self.gdb_port.unwrap_or(DEFAULT_GDB_PORT)
+    }
+
+    /// Command-line arguments of OpenOCD.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec![
+            "-c".to_string(),
+            format!("tcl_port {}", self.tcl_port()),
+            "-c".to_string(),
+            format!("gdb_port {}", self.gdb_port()),
+        ];
+        for script in &self.scripts {
+            args.push("-f".to_string());
+            args.push(script.clone());
+        }
+        for cmd in &self.commands {
+            args.push("-c".to_string());
+            args.push(cmd.clone());
+        }
+        args
+    }
+
+    /// TCL commands loading `elf` and starting it at `entry`, stopping at
+    /// `breakpoint` if set.
+    pub fn session(&self, elf: &Path, entry: u64, breakpoint: Option<u64>) -> Vec<String> {
+        let mut cmds = vec!["reset halt".to_string()];
+        if self.semihosting {
+            cmds.push("arm semihosting enable".to_string());
+        }
+        cmds.push(format!("load_image {{{}}} 0 elf", elf.display()));
+        if let Some(addr) = breakpoint {
+            cmds.push(format!("bp {addr:#x} 4 hw"));
+        }
+        cmds.push(format!("resume {entry:#x}"));
+        cmds
+    }
+}
+
+/// Arguments for running on a board through OpenOCD.
+#[derive(Debug, Clone, Default)]
+pub struct RunOpenOcdArgs {
+    /// Optional path to the OpenOCD configuration file.
+    pub config: Option<PathBuf>,
+}
+
+/// Loads the built kernel through OpenOCD and shows its console.
+///
+/// # Errors
+///
+/// Returns an error if OpenOCD is missing or fails, a TCL command fails,
+/// the breakpoint symbol is unknown, or a `fail_regex` pattern matches.
+pub async fn run_openocd(ctx: AppContext, args: RunOpenOcdArgs) -> anyhow::Result<()> {
+    let (config_path, config) = load_openocd_config(&ctx, args.config).await?;
+    if config.scripts.is_empty() {
+        bail!(t!("openocd.no_scripts", path = config_path.display()));
+    }
+    let program = config.openocd.clone().unwrap_or("openocd".to_string());
+    if find_program(&program).is_none() {
+        bail!(t!("openocd.not_found", program = program));
+    }
+    let mut rules = ConsoleRules::for_runner(&ctx, &config.success_regex, &config.fail_regex)?;
+
+    let elf = ctx
+        .paths
+        .artifacts
+        .elf
+        .clone()
+        .ok_or_else(|| anyhow!("elf not exist"))?;
+    // OpenOCD 的工作目录不一定与 ostool 相同
+    let elf = elf.canonicalize().unwrap_or(elf);
+    let data = fs::read(&elf).await?;
+    let file = object::File::parse(&*data)?;
+    let breakpoint = match &config.breakpoint {
+        Some(bp) => Some(
+            resolve(&file, bp)
+                .ok_or_else(|| anyhow!(t!("openocd.no_symbol", name = bp, path = elf.display())))?,
+        ),
+        None => None,
+    };
+    let session = config.session(&elf, file.entry(), breakpoint);
+
+    let mut cmd = ctx.command(&program);
+    cmd.args(config.args());
+    cmd.stdin(Stdio::null());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+    cmd.print_cmd();
+    let mut child = cmd.spawn()?;
+    // Ctrl+C 结束会话，随后照常关闭 OpenOCD
+    let _pass = exit::pass_through();
+
+    let res = async {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        forward(&mut child, tx);
+
+        let mut tcl = TclClient::connect(config.tcl_port(), &mut child)?;
+        for cmd in &session {
+            println!("{}", format!("> {cmd}").dimmed());
+            tcl.exec(cmd)?;
+        }
+        println!(
+            "{}",
+            t!("openocd.running", entry = format!("{:#x}", file.entry())).green()
+        );
+
+        let mut line = Vec::new();
+        loop {
+            let output = tokio::select! {
+                output = rx.recv() => output,
+                _ = tokio::signal::ctrl_c() => return Err(Failure::Aborted.into()),
+            };
+            match output {
+                Some(Output::Console(bytes)) => {
+                    let mut stdout = std::io::stdout();
+                    stdout.write_all(&bytes)?;
+                    stdout.flush()?;
+                    for byte in bytes {
+                        if byte != b'\n' {
+                            line.push(byte);
+                            continue;
+                        }
+                        let text = String::from_utf8_lossy(&line).trim_end().to_string();
+                        line.clear();
+                        match rules.check(&text) {
+                            Some(verdict) if verdict.success => {
+                                println!("\r\n{}", t!("openocd.success_matched").green());
+                                return Ok(());
+                            }
+                            Some(verdict) => {
+                                println!("\r\n{}", t!("openocd.fail_matched").red());
+                                return Err(Failure::Boot.error(verdict.message));
+                            }
+                            None => {}
+                        }
+                    }
+                }
+                Some(Output::Log(text)) => {
+                    eprintln!("{}", text.dimmed());
+                    // OpenOCD 在目标停下时打印 "halted due to breakpoint"
+                    if breakpoint.is_some() && text.contains("due to breakpoint") {
+                        println!(
+                            "{}",
+                            t!("openocd.halted", port = config.gdb_port()).yellow()
+                        );
+                    }
+                }
+                None => {
+                    let status = child.wait()?;
+                    return Err(Failure::Boot.error(t!("openocd.exited", status = status)));
+                }
+            }
+        }
+    }
+    .await;
+    let _ = child.kill();
+    let _ = child.wait();
+    rules.print_captures();
+    res
+}
+
+/// Loads the OpenOCD configuration, by default from `.openocd.toml` in the
+/// workspace.
+///
+/// A missing file is created empty, to be filled with the board's scripts.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, parsed or created.
+pub async fn load_openocd_config(
+    ctx: &AppContext,
+    path: Option<PathBuf>,
+) -> anyhow::Result<(PathBuf, OpenOcdConfig)> {
+    let path = path.unwrap_or_else(|| ctx.paths.config_file(".openocd.toml"));
+    let config = if path.exists() {
+        info!("{}", t!("openocd.using_config", path = path.display()));
+        let content = fs::read_to_string(&path)
+            .await
+            .map_err(|_| anyhow!("can not open config file: {}", path.display()))?;
+        let content = ctx.value_replace_with_var(replace_env_placeholders(&content)?);
+        ctx.parse_config(&path, &content)?
+    } else {
+        let config = OpenOcdConfig::default();
+        fs::write(&path, toml::to_string_pretty(&config)?).await?;
+        config
+    };
+    Ok((path, config))
+}
+
+/// Address of `breakpoint`, a number or a symbol of `file`.
+fn resolve(file: &object::File, breakpoint: &str) -> Option<u64> {
+    parse_int(breakpoint).or_else(|| {
+        file.symbols()
+            .find(|s| s.name() == Ok(breakpoint))
+            .map(|s| s.address())
+    })
+}
+
+/// Output of the OpenOCD process.
+enum Output {
+    /// Raw stdout, where the semihosting console goes.
+    Console(Vec<u8>),
+    /// A line of the OpenOCD log on stderr.
+    Log(String),
+}
+
+/// Sends OpenOCD's stdout and stderr to `tx`, each read on a thread.
+fn forward(child: &mut Child, tx: mpsc::UnboundedSender<Output>) {
+    if let Some(mut stdout) = child.stdout.take() {
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 1024];
+            while let Ok(n @ 1..) = stdout.read(&mut buf) {
+                if tx.send(Output::Console(buf[..n].to_vec())).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    if let Some(stderr) = child.stderr.take() {
+        std::thread::spawn(move || {
+            for line in BufReader::new(stderr).lines() {
+                let Ok(line) = line else { break };
+                if tx.send(Output::Log(line)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+/// Client of the OpenOCD TCL server.
+///
+/// Commands and results are terminated by `0x1a`.
+pub struct TclClient {
+    stream: TcpStream,
+}
+
+impl TclClient {
+    /// Connects to the server on `port` of localhost, waiting up to ten
+    /// seconds for OpenOCD to start listening.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `openocd` exits or the port never opens.
+    pub fn connect(port: u16, openocd: &mut Child) -> anyhow::Result<Self> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            if let Ok(stream) = TcpStream::connect(("127.0.0.1", port)) {
+                return Ok(Self { stream });
+            }
+            if let Some(status) = openocd.try_wait()? {
+                bail!(t!("openocd.exited", status = status));
+            }
+            if Instant::now() > deadline {
+                bail!(t!("openocd.connect_failed", port = port));
+            }
+            std::thread::sleep(Duration::from_millis(200));
+        }
+    }
+
+    /// Runs `cmd` and returns its result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection fails or the command raises a
+    /// TCL error.
+    pub fn exec(&mut self, cmd: &str) -> anyhow::Result<String> {
+        // 服务端不区分正常结果和错误信息，用 catch 给错误加上前缀
+        let wrapped = format!(
+            "if {{[catch {{{cmd}}} ostool_res]}} {{set ostool_res \"{ERROR_PREFIX}$ostool_res\"}} else {{set ostool_res}}"
+        );
+        self.stream.write_all(wrapped.as_bytes())?;
+        self.stream.write_all(&[EOM])?;
+
+        let mut reply = Vec::new();
+        let mut buf = [0u8; 1024];
+        loop {
+            let n = self.stream.read(&mut buf)?;
+            if n == 0 {
+                bail!(t!("openocd.closed"));
+            }
+            reply.extend_from_slice(&buf[..n]);
+            if reply.last() == Some(&EOM) {
+                reply.pop();
+                break;
+            }
+        }
+        let reply = String::from_utf8_lossy(&reply).to_string();
+        match reply.strip_prefix(ERROR_PREFIX) {
+            Some(err) => bail!(t!("openocd.command_failed", cmd = cmd, err = err.trim())),
+            None => Ok(reply),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_args_and_session() {
+        let config = OpenOcdConfig {
+            scripts: vec!["interface/cmsis-dap.cfg".to_string()],
+            commands: vec!["adapter speed 4000".to_string()],
+            semihosting: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.args(),
+            [
+                "-c",
+                "tcl_port 6666",
+                "-c",
+                "gdb_port 3333",
+                "-f",
+                "interface/cmsis-dap.cfg",
+                "-c",
+                "adapter speed 4000",
+            ]
+        );
+        assert_eq!(
+            config.session(Path::new("/k/kernel"), 0x8000_0000, Some(0x8000_1000)),
+            [
+                "reset halt",
+                "arm semihosting enable",
+                "load_image {/k/kernel} 0 elf",
+                "bp 0x80001000 4 hw",
+                "resume 0x80000000",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tcl_exec() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            for reply in ["halted", "ostool-error: unknown command"] {
+                let mut cmd = Vec::new();
+                reader.read_until(EOM, &mut cmd).unwrap();
+                assert!(String::from_utf8_lossy(&cmd).contains("{reset halt}"));
+                stream.write_all(reply.as_bytes()).unwrap();
+                stream.write_all(&[EOM]).unwrap();
+            }
+        });
+
+        let mut tcl = TclClient {
+            stream: TcpStream::connect(addr).unwrap(),
+        };
+        assert_eq!(tcl.exec("reset halt").unwrap(), "halted");
+        let err = tcl.exec("reset halt").unwrap_err().to_string();
+        assert!(err.contains("unknown command"));
+        server.join().unwrap();
+    }
+}
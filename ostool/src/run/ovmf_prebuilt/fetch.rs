@@ -1,5 +1,5 @@
 use super::{Error, Source};
-use indicatif::{ProgressBar, ProgressStyle};
+use crate::progress;
 use sha2::{Digest, Sha256};
 use std::fs;
 use std::io::{self, Cursor, Read};
@@ -80,33 +80,8 @@ fn download_url(url: &str) -> Result<Vec<u8>, Error> {
         .and_then(|s| s.to_str().ok())
         .and_then(|s| s.parse::<u64>().ok());
 
-    // Create progress bar
-    let progress = if let Some(total) = content_length {
-        let pb = ProgressBar::new(total);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
-                .unwrap()
-                .progress_chars("#>-"),
-        );
-        pb.set_message(format!(
-            "Downloading {}",
-            url.split('/').next_back().unwrap_or("file")
-        ));
-        pb
-    } else {
-        let pb = ProgressBar::new_spinner();
-        pb.set_style(
-            ProgressStyle::default_spinner()
-                .template("{msg} {spinner:.green} [{elapsed_precise}] {bytes} ({bytes_per_sec})")
-                .unwrap(),
-        );
-        pb.set_message(format!(
-            "Downloading {}",
-            url.split('/').next_back().unwrap_or("file")
-        ));
-        pb
-    };
+    let name = url.split('/').next_back().unwrap_or("file");
+    let progress = progress::bytes(format!("Downloading {name}"), content_length);
 
     let mut data = Vec::with_capacity(MAX_DOWNLOAD_SIZE_IN_BYTES);
     let mut reader = resp
@@ -124,14 +99,11 @@ fn download_url(url: &str) -> Result<Vec<u8>, Error> {
                 data.extend_from_slice(&buffer[..n]);
                 progress.inc(n as u64);
             }
-            Err(e) => {
-                progress.finish_and_clear();
-                return Err(Error::Download(e));
-            }
+            Err(e) => return Err(Error::Download(e)),
         }
     }
 
-    progress.finish_with_message(format!("Downloaded {} bytes", data.len()));
+    progress.finish();
     info!("received {} bytes", data.len());
 
     Ok(data)
@@ -0,0 +1,265 @@
+//! probe-rs runner for Cortex-M and RISC-V microcontrollers.
+//!
+//! `ostool run probe-rs` hands the kernel ELF to `probe-rs run`, which
+//! flashes it over SWD/JTAG, resets the chip and attaches to its RTT
+//! channels, decoding defmt frames when the ELF has a `.defmt` section. The
+//! output is shown in the terminal and matched against `success_regex`,
+//! `fail_regex` and the project's `console_rules` like the QEMU runner does.
+//!
+//! # Configuration
+//!
+//! probe-rs configuration is stored in `.probe-rs.toml` files:
+//!
+//! ```toml
+//! chip = "STM32F411CEUx"
+//! protocol = "swd"
+//! success_regex = ["All tests passed"]
+//! ```
+
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use colored::Colorize;
+use jkconfig::t;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::{
+    ctx::AppContext,
+    exit::{self, Failure},
+    run::rules::ConsoleRules,
+    utils::{find_program, replace_env_placeholders},
+};
+
+/// probe-rs configuration structure.
+///
+/// This configuration is typically loaded from a `.probe-rs.toml` file.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct ProbeRsConfig {
+    /// probe-rs executable, `probe-rs` when unset
+    pub probe_rs: Option<String>,
+    /// Target chip, see `probe-rs chip list`
+    pub chip: String,
+    /// Probe to use when several are connected, `VID:PID[:SERIAL]`
+    pub probe: Option<String>,
+    /// Debug protocol, picked by probe-rs when unset
+    pub protocol: Option<ProbeProtocol>,
+    /// Protocol speed in kHz
+    pub speed: Option<u32>,
+    /// Hold the chip in reset while attaching, for firmware that disables
+    /// the debug port
+    #[serde(default)]
+    pub connect_under_reset: bool,
+    /// Additional `probe-rs run` arguments
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub success_regex: Vec<String>,
+    #[serde(default)]
+    pub fail_regex: Vec<String>,
+}
+
+/// Debug protocol of the probe.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProbeProtocol {
+    Swd,
+    Jtag,
+}
+
+impl ProbeRsConfig {
+    /// `probe-rs` arguments flashing and running `elf`.
+    pub fn args(&self, elf: &Path) -> Vec<String> {
+        let mut args = vec!["run".to_string(), "--chip".to_string(), self.chip.clone()];
+        if let Some(probe) = &self.probe {
+            args.push("--probe".to_string());
+            args.push(probe.clone());
+        }
+        if let Some(protocol) = self.protocol {
+            args.push("--protocol".to_string());
+            args.push(
+                match protocol {
+                    ProbeProtocol::Swd => "swd",
+                    ProbeProtocol::Jtag => "jtag",
+                }
+                .to_string(),
+            );
+        }
+        if let Some(speed) = self.speed {
+            args.push("--speed".to_string());
+            args.push(speed.to_string());
+        }
+        if self.connect_under_reset {
+            args.push("--connect-under-reset".to_string());
+        }
+        args.extend(self.args.iter().cloned());
+        args.push(elf.display().to_string());
+        args
+    }
+}
+
+/// Arguments for running on a microcontroller through probe-rs.
+#[derive(Debug, Clone, Default)]
+pub struct RunProbeRsArgs {
+    /// Optional path to the probe-rs configuration file.
+    pub config: Option<PathBuf>,
+}
+
+/// Flashes the built kernel with probe-rs and shows its RTT output.
+///
+/// # Errors
+///
+/// Returns an error if probe-rs is missing or fails, or a `fail_regex`
+/// pattern matches.
+pub async fn run_probe_rs(ctx: AppContext, args: RunProbeRsArgs) -> anyhow::Result<()> {
+    let (config_path, config) = load_probe_rs_config(&ctx, args.config).await?;
+    if config.chip.is_empty() {
+        bail!(t!("probe_rs.no_chip", path = config_path.display()));
+    }
+    let program = config.probe_rs.clone().unwrap_or("probe-rs".to_string());
+    if find_program(&program).is_none() {
+        bail!(t!("probe_rs.not_found", program = program));
+    }
+    let mut rules = ConsoleRules::for_runner(&ctx, &config.success_regex, &config.fail_regex)?;
+
+    let elf = ctx
+        .paths
+        .artifacts
+        .elf
+        .clone()
+        .ok_or_else(|| anyhow!("elf not exist"))?;
+    let elf = elf.canonicalize().unwrap_or(elf);
+
+    let mut cmd = ctx.command(&program);
+    cmd.args(config.args(&elf));
+    cmd.stdout(Stdio::piped());
+    cmd.print_cmd();
+    let mut child = cmd.spawn()?;
+
+    // Ctrl+C 交给 probe-rs 处理，它会在退出前释放探针
+    let _pass = exit::pass_through();
+
+    let mut result = None;
+    let mut line = Vec::new();
+    let mut stdout = child.stdout.take().unwrap();
+    let mut buf = [0u8; 1024];
+    while result.is_none() {
+        let n = match stdout.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        let mut out = std::io::stdout();
+        out.write_all(&buf[..n])?;
+        out.flush()?;
+        for &byte in &buf[..n] {
+            if byte != b'\n' {
+                line.push(byte);
+                continue;
+            }
+            let text = String::from_utf8_lossy(&line).trim_end().to_string();
+            line.clear();
+            match rules.check(&text) {
+                Some(verdict) if verdict.success => {
+                    println!("\r\n{}", t!("probe_rs.success_matched").green());
+                    result = Some(Ok(()));
+                    break;
+                }
+                Some(verdict) => {
+                    println!("\r\n{}", t!("probe_rs.fail_matched").red());
+                    result = Some(Err(Failure::Boot.error(verdict.message)));
+                    break;
+                }
+                None => {}
+            }
+        }
+    }
+    if result.is_some() {
+        let _ = child.kill();
+    }
+    let status = child.wait()?;
+    rules.print_captures();
+    match result {
+        Some(res) => res,
+        None if exit::interrupted() => Err(Failure::Aborted.into()),
+        None if status.success() => Ok(()),
+        None => Err(Failure::Boot.error(t!("probe_rs.exited", status = status))),
+    }
+}
+
+/// Loads the probe-rs configuration, by default from `.probe-rs.toml` in
+/// the workspace.
+///
+/// A missing file is created empty, to be filled with the chip.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, parsed or created.
+pub async fn load_probe_rs_config(
+    ctx: &AppContext,
+    path: Option<PathBuf>,
+) -> anyhow::Result<(PathBuf, ProbeRsConfig)> {
+    let path = path.unwrap_or_else(|| ctx.paths.config_file(".probe-rs.toml"));
+    let config = if path.exists() {
+        info!("{}", t!("probe_rs.using_config", path = path.display()));
+        let content = fs::read_to_string(&path)
+            .await
+            .map_err(|_| anyhow!("can not open config file: {}", path.display()))?;
+        let content = ctx.value_replace_with_var(replace_env_placeholders(&content)?);
+        ctx.parse_config(&path, &content)?
+    } else {
+        let config = ProbeRsConfig::default();
+        fs::write(&path, toml::to_string_pretty(&config)?).await?;
+        config
+    };
+    Ok((path, config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args() {
+        let config: ProbeRsConfig = toml::from_str(
+            r#"
+            chip = "STM32F411CEUx"
+            probe = "0483:374b"
+            protocol = "swd"
+            speed = 4000
+            connect_under_reset = true
+            args = ["--no-location"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.args(Path::new("/k/kernel")),
+            [
+                "run",
+                "--chip",
+                "STM32F411CEUx",
+                "--probe",
+                "0483:374b",
+                "--protocol",
+                "swd",
+                "--speed",
+                "4000",
+                "--connect-under-reset",
+                "--no-location",
+                "/k/kernel",
+            ]
+        );
+
+        let config = ProbeRsConfig {
+            chip: "esp32c3".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.args(Path::new("k")),
+            ["run", "--chip", "esp32c3", "k"]
+        );
+    }
+}
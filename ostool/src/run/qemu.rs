@@ -18,27 +18,63 @@
 //! to_bin = true
 //! success_regex = ["All tests passed"]
 //! fail_regex = ["PANIC", "FAILED"]
+//! # Attach the disk image of `.disk.toml` as a virtio-blk drive
+//! disk_image = false
+//!
+//! # More virtio-blk drives, created empty when missing
+//! [[disks]]
+//! path = "target/data.qcow2"
+//! format = "qcow2"
+//! create_mib = 64
+//!
+//! # virtio-net with user-mode networking, `ssh -p 2222 localhost` reaches
+//! # the guest's port 22
+//! [net]
+//! hostfwd = [{ host = 2222, guest = 22 }]
+//!
+//! # VM snapshots, see `snapshot`
+//! [snapshot]
+//! save_regex = "login:"
 //! ```
 
 use std::{
-    ffi::OsString,
-    io::{BufReader, Read},
-    path::PathBuf,
-    process::{Child, Stdio},
+    io::{BufRead, BufReader, Read},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process::{Child, ExitStatus, Stdio},
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, RecvTimeoutError},
+    },
+    time::{Duration, Instant},
 };
 
 use anyhow::anyhow;
 use colored::Colorize;
-use crossterm::terminal::disable_raw_mode;
+use jkconfig::t;
 use object::Architecture;
+use regex::Regex;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::io::{self, Write};
 use tokio::fs;
 
 use crate::{
+    cache::{self, Cache},
     ctx::AppContext,
+    disk::image::{build_image, load_disk_config},
+    exit::{self, AbortHook, Failure},
+    migrate,
+    report::LastRun,
+    run::crash::{CrashConfig, Triage},
+    run::defmt,
+    run::gdb,
     run::ovmf_prebuilt::{Arch, FileType, Prebuilt, Source},
+    run::qmp::{self, Qmp},
+    run::rules::ConsoleRules,
+    run::snapshot::{self, Records, SnapshotConfig},
+    run::test::TestConfig,
+    utils::Command,
 };
 
 /// QEMU configuration structure.
@@ -46,6 +82,8 @@ use crate::{
 /// This configuration is typically loaded from a `.qemu.toml` file.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
 pub struct QemuConfig {
+    /// Layout version of the file, see [`migrate`](crate::migrate).
+    pub version: Option<u32>,
     /// Additional QEMU command-line arguments.
     pub args: Vec<String>,
     /// Whether to use UEFI boot via OVMF firmware.
@@ -56,6 +94,180 @@ pub struct QemuConfig {
     pub success_regex: Vec<String>,
     /// Regex patterns that indicate failed execution.
     pub fail_regex: Vec<String>,
+    /// Test markers and timeout for `ostool test`.
+    pub test: Option<TestConfig>,
+    /// Build the disk image of `.disk.toml` before launch and attach it as a
+    /// virtio-blk drive.
+    #[serde(default)]
+    pub disk_image: bool,
+    /// Disk images attached as virtio-blk drives, after that of `disk_image`.
+    #[serde(default)]
+    pub disks: Vec<QemuDisk>,
+    /// virtio-net network device of the guest.
+    pub net: Option<QemuNet>,
+    /// VM snapshots saved and loaded with `--save-snapshot` and
+    /// `--load-snapshot`.
+    pub snapshot: Option<SnapshotConfig>,
+}
+
+/// Backend of the guest's network device.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum NetMode {
+    /// User-mode networking (SLIRP), no privileges needed.
+    #[default]
+    User,
+    /// A host tap interface, e.g. on a bridge.
+    Tap,
+}
+
+/// Protocol of a forwarded port.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    #[default]
+    Tcp,
+    Udp,
+}
+
+/// Host port forwarded to the guest in user mode.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct PortForward {
+    #[serde(default)]
+    pub protocol: Protocol,
+    /// Port on the host
+    pub host: u16,
+    /// Port in the guest
+    pub guest: u16,
+}
+
+/// virtio-net network device of the guest.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct QemuNet {
+    #[serde(default)]
+    pub mode: NetMode,
+    /// Host ports forwarded to the guest, user mode only
+    #[serde(default)]
+    pub hostfwd: Vec<PortForward>,
+    /// Tap interface, e.g. `tap0`; QEMU creates one when unset
+    pub ifname: Option<String>,
+    /// MAC address of the guest, e.g. `52:54:00:12:34:56`
+    pub mac: Option<String>,
+}
+
+impl QemuNet {
+    /// `-netdev` and `-device` arguments, with the device on the PCI bus if
+    /// `pci`.
+    fn args(&self, pci: bool) -> [String; 4] {
+        let mut netdev = match self.mode {
+            NetMode::User => {
+                let mut netdev = "user,id=net0".to_string();
+                for fwd in &self.hostfwd {
+                    let protocol = match fwd.protocol {
+                        Protocol::Tcp => "tcp",
+                        Protocol::Udp => "udp",
+                    };
+                    netdev += &format!(",hostfwd={protocol}::{}-:{}", fwd.host, fwd.guest);
+                }
+                netdev
+            }
+            // 接口由用户事先配置好，不运行 qemu-ifup 脚本
+            NetMode::Tap => "tap,id=net0,script=no,downscript=no".to_string(),
+        };
+        if let (NetMode::Tap, Some(ifname)) = (self.mode, &self.ifname) {
+            netdev += &format!(",ifname={ifname}");
+        }
+        let mut device = if pci {
+            "virtio-net-pci,netdev=net0".to_string()
+        } else {
+            "virtio-net-device,netdev=net0".to_string()
+        };
+        if let Some(mac) = &self.mac {
+            device += &format!(",mac={mac}");
+        }
+        ["-netdev".to_string(), netdev, "-device".to_string(), device]
+    }
+}
+
+/// Format of a disk image.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DiskFormat {
+    #[default]
+    Raw,
+    Qcow2,
+}
+
+impl DiskFormat {
+    fn name(self) -> &'static str {
+        match self {
+            DiskFormat::Raw => "raw",
+            DiskFormat::Qcow2 => "qcow2",
+        }
+    }
+}
+
+/// Disk image attached as a virtio-blk drive.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct QemuDisk {
+    /// Image file, relative to the workspace
+    #[schemars(extend("format" = "file-path"))]
+    pub path: String,
+    #[serde(default)]
+    pub format: DiskFormat,
+    /// Size in MiB of an empty image created when the file is missing
+    pub create_mib: Option<u64>,
+    /// Attach the image read-only
+    #[serde(default)]
+    pub readonly: bool,
+}
+
+impl QemuDisk {
+    /// Creates an empty image at `path` if it is missing and `create_mib`
+    /// is set.
+    fn prepare(&self, ctx: &AppContext, path: &Path) -> anyhow::Result<()> {
+        if path.exists() {
+            return Ok(());
+        }
+        let Some(size) = self.create_mib else {
+            bail!("disk image {} does not exist", path.display());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        info!(
+            "{}",
+            t!("qemu.disk_created", path = path.display(), size = size)
+        );
+        match self.format {
+            // 稀疏文件，未写入的部分不占磁盘空间
+            DiskFormat::Raw => std::fs::File::create(path)?.set_len(size << 20)?,
+            DiskFormat::Qcow2 => ctx
+                .command("qemu-img")
+                .args(["create", "-f", "qcow2"])
+                .arg(path)
+                .arg(format!("{size}M"))
+                .run()?,
+        }
+        Ok(())
+    }
+
+    /// `-drive` and `-device` arguments attaching the image at `path` as
+    /// `disk<index>` to a `device` such as `virtio-blk-device`.
+    fn args(&self, path: &Path, index: usize, device: &str) -> [String; 4] {
+        // -drive 的参数中逗号要写成两个
+        let file = path.display().to_string().replace(',', ",,");
+        let readonly = if self.readonly { ",readonly=on" } else { "" };
+        [
+            "-drive".to_string(),
+            format!(
+                "file={file},format={},if=none,id=disk{index}{readonly}",
+                self.format.name()
+            ),
+            "-device".to_string(),
+            format!("{device},drive=disk{index}"),
+        ]
+    }
 }
 
 /// Arguments for running QEMU.
@@ -67,6 +279,10 @@ pub struct RunQemuArgs {
     pub dtb_dump: bool,
     /// Whether to show QEMU output.
     pub show_output: bool,
+    /// Snapshot to save once `snapshot.save_regex` matches.
+    pub save_snapshot: Option<String>,
+    /// Snapshot to start from.
+    pub load_snapshot: Option<String>,
 }
 
 /// Runs the operating system in QEMU.
@@ -83,10 +299,34 @@ pub struct RunQemuArgs {
 ///
 /// Returns an error if QEMU fails to start or exits with an error.
 pub async fn run_qemu(ctx: AppContext, args: RunQemuArgs) -> anyhow::Result<()> {
-    // Build logic will be implemented here
-    let config_path = match args.qemu_config.clone() {
+    let config = load_qemu_config(&ctx, args.qemu_config.clone()).await?;
+
+    let mut runner = QemuRunner::new(ctx, config);
+    runner.dtbdump = args.dtb_dump;
+    runner.save_snapshot = args.save_snapshot;
+    runner.load_snapshot = args.load_snapshot;
+    runner.run().await?;
+    Ok(())
+}
+
+/// Loads the QEMU configuration, by default from `.qemu.toml` in the
+/// manifest directory.
+///
+/// A missing file is created with defaults for the detected architecture.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, parsed or created.
+pub async fn load_qemu_config(
+    ctx: &AppContext,
+    qemu_config: Option<PathBuf>,
+) -> anyhow::Result<QemuConfig> {
+    let config_path = match qemu_config {
         Some(path) => path,
-        None => ctx.paths.manifest.join(".qemu.toml"),
+        None => match &ctx.paths.config_dir {
+            Some(dir) => dir.join(".qemu.toml"),
+            None => ctx.paths.manifest.join(".qemu.toml"),
+        },
     };
 
     info!("Using QEMU config file: {}", config_path.display());
@@ -95,10 +335,12 @@ pub async fn run_qemu(ctx: AppContext, args: RunQemuArgs) -> anyhow::Result<()>
         let config_content = fs::read_to_string(&config_path)
             .await
             .map_err(|_| anyhow!("can not open config file: {}", config_path.display()))?;
-        let config: QemuConfig = toml::from_str(&config_content)?;
+        let config_content = migrate::upgrade(&config_path, &config_content, migrate::QEMU)?;
+        let config: QemuConfig = ctx.parse_config(&config_path, &config_content)?;
         config
     } else {
         let mut config = QemuConfig {
+            version: Some(migrate::current_version(migrate::QEMU)),
             to_bin: true,
             ..Default::default()
         };
@@ -119,17 +361,211 @@ pub async fn run_qemu(ctx: AppContext, args: RunQemuArgs) -> anyhow::Result<()>
         fs::write(&config_path, toml::to_string_pretty(&config)?).await?;
         config
     };
+    Ok(config)
+}
+
+/// What a [`boot_qemu`] output callback asks for next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Watch {
+    /// Keep waiting for output.
+    Continue,
+    /// Keep waiting, restarting the timeout.
+    ResetTimeout,
+    /// Stop QEMU.
+    Stop,
+}
+
+/// How a [`boot_qemu`] run ended.
+#[derive(Debug)]
+pub enum BootEnd {
+    /// The callback asked to stop.
+    Stopped,
+    /// A `failure` console rule or `fail_regex` pattern matched the line.
+    Failed { pattern: String, line: String },
+    /// No output made the callback reset the timeout in time.
+    TimedOut,
+    /// QEMU exited by itself.
+    Exited(ExitStatus),
+    /// A `[test.crash]` panic pattern matched the line; `bundle` is where
+    /// the crash was captured, if it could be.
+    Crashed {
+        line: String,
+        bundle: Option<PathBuf>,
+    },
+}
+
+/// Boots the kernel once in QEMU without a terminal, passing each output
+/// line to `on_line`.
+///
+/// `append` is added to the kernel command line (`-append`). QEMU is
+/// stopped when `on_line` asks for it, a `failure` console rule or
+/// `fail_regex` pattern matches, or `timeout` passes without `on_line`
+/// resetting it.
+///
+/// # Errors
+///
+/// Returns an error if QEMU cannot be started.
+pub async fn boot_qemu(
+    ctx: AppContext,
+    config: QemuConfig,
+    append: Option<String>,
+    timeout: Duration,
+    on_line: impl FnMut(&str) -> Watch,
+) -> anyhow::Result<BootEnd> {
+    boot_qemu_triaged(ctx, config, append, timeout, None, on_line).await
+}
+
+/// [`boot_qemu`] that also watches for the panic patterns of `crash`,
+/// capturing a crash bundle through the gdbstub before stopping QEMU.
+///
+/// # Errors
+///
+/// Returns an error if QEMU cannot be started or `crash` is invalid.
+pub(crate) async fn boot_qemu_triaged(
+    ctx: AppContext,
+    config: QemuConfig,
+    append: Option<String>,
+    timeout: Duration,
+    crash: Option<&CrashConfig>,
+    mut on_line: impl FnMut(&str) -> Watch,
+) -> anyhow::Result<BootEnd> {
+    // 调试模式下 gdbstub 归用户所有
+    let mut triage = match crash {
+        Some(crash) if !ctx.debug => Some(Triage::new(&ctx, crash)?),
+        _ => None,
+    };
+    let mut runner = QemuRunner::new(ctx, config);
+    runner.append = append;
+    runner.gdb = triage.as_ref().map(Triage::gdb_arg);
+    runner.prepare_rules()?;
+    let mut cmd = runner.command().await?;
+    cmd.stdin(Stdio::null());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+    cmd.print_cmd();
+    let cmd_string = cmd.cmd_string();
+    let mut child = cmd.spawn()?;
+    let outputs = [
+        child
+            .stdout
+            .take()
+            .map(|o| defmt::wrap(&runner.ctx, Box::new(o))),
+        child
+            .stderr
+            .take()
+            .map(|e| Box::new(e) as Box<dyn Read + Send>),
+    ];
+    let qemu = QemuProcess::new(child, runner.qmp);
+    let _abort = qemu.stop_on_abort();
+    runner.attach_debugger();
 
-    let mut runner = QemuRunner {
-        ctx,
-        config,
-        args: vec![],
-        dtbdump: args.dtb_dump,
-        success_regex: vec![],
-        fail_regex: vec![],
+    let (tx, rx) = mpsc::channel();
+    for out in outputs.into_iter().flatten() {
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            for line in BufReader::new(out).split(b'\n') {
+                let Ok(line) = line else { break };
+                let line = String::from_utf8_lossy(&line).trim_end().to_string();
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(tx);
+
+    let mut deadline = Instant::now() + timeout;
+    let end = loop {
+        let line = match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => break BootEnd::TimedOut,
+            Err(RecvTimeoutError::Disconnected) => return Ok(BootEnd::Exited(qemu.wait()?)),
+        };
+        if let Some(triage) = &mut triage
+            && triage.check(&line)
+        {
+            eprintln!("{}", t!("crash.capturing").yellow());
+            let bundle = match triage.capture(&runner.ctx, runner.qmp, &cmd_string, &line) {
+                Ok(bundle) => {
+                    eprintln!("{}", t!("crash.saved", path = bundle.display()));
+                    Some(bundle)
+                }
+                Err(e) => {
+                    warn!("{}", t!("crash.capture_failed", error = format!("{e:#}")));
+                    None
+                }
+            };
+            break BootEnd::Crashed { line, bundle };
+        }
+        // 是否成功由调用方的 on_line 判断
+        if let Some(verdict) = runner.rules.check(&line)
+            && !verdict.success
+        {
+            break BootEnd::Failed {
+                pattern: verdict.pattern,
+                line,
+            };
+        }
+        match on_line(&line) {
+            Watch::Continue => {}
+            Watch::ResetTimeout => deadline = Instant::now() + timeout,
+            Watch::Stop => break BootEnd::Stopped,
+        }
     };
-    runner.run().await?;
-    Ok(())
+    qemu.stop();
+    Ok(end)
+}
+
+/// How long QEMU gets to quit over QMP before it is killed.
+const QUIT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A running QEMU, shared with its abort hook.
+#[derive(Clone)]
+struct QemuProcess {
+    child: Arc<Mutex<Child>>,
+    qmp: Option<SocketAddr>,
+}
+
+impl QemuProcess {
+    fn new(child: Child, qmp: Option<SocketAddr>) -> Self {
+        Self {
+            child: Arc::new(Mutex::new(child)),
+            qmp,
+        }
+    }
+
+    /// Asks QEMU to quit over QMP, killing it if it does not in time.
+    fn stop(&self) {
+        if let Some(addr) = self.qmp
+            && Qmp::connect(addr).and_then(Qmp::quit).is_ok()
+        {
+            let deadline = Instant::now() + QUIT_TIMEOUT;
+            while Instant::now() < deadline {
+                if let Ok(Some(_)) = self.child.lock().unwrap().try_wait() {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        }
+        let mut child = self.child.lock().unwrap();
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+
+    /// Waits for QEMU to exit without keeping [`Self::stop`] from it.
+    fn wait(&self) -> io::Result<ExitStatus> {
+        loop {
+            if let Some(status) = self.child.lock().unwrap().try_wait()? {
+                return Ok(status);
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    fn stop_on_abort(&self) -> AbortHook {
+        let qemu = self.clone();
+        exit::on_abort(move || qemu.stop())
+    }
 }
 
 struct QemuRunner {
@@ -137,14 +573,110 @@ struct QemuRunner {
     config: QemuConfig,
     args: Vec<String>,
     dtbdump: bool,
-    success_regex: Vec<regex::Regex>,
-    fail_regex: Vec<regex::Regex>,
+    /// Extra kernel command line.
+    append: Option<String>,
+    /// Address of QEMU's QMP socket, unless the config sets up its own.
+    qmp: Option<SocketAddr>,
+    /// `-gdb` value of the gdbstub used for crash triage.
+    gdb: Option<String>,
+    rules: ConsoleRules,
+    save_snapshot: Option<String>,
+    load_snapshot: Option<String>,
+    /// Debugger to start once QEMU waits for it, see [`gdb::prepare_qemu`].
+    debugger: Option<std::process::Command>,
 }
 
 impl QemuRunner {
+    fn new(ctx: AppContext, config: QemuConfig) -> Self {
+        Self {
+            ctx,
+            config,
+            args: vec![],
+            dtbdump: false,
+            append: None,
+            qmp: None,
+            gdb: None,
+            rules: ConsoleRules::default(),
+            save_snapshot: None,
+            load_snapshot: None,
+            debugger: None,
+        }
+    }
+
     async fn run(&mut self) -> anyhow::Result<()> {
-        self.preper_regex()?;
+        self.prepare_rules()?;
+        let mut save = self.prepare_save()?;
+        let mut cmd = self.command().await?;
+        cmd.stdout(Stdio::piped());
+        cmd.print_cmd();
+        let mut last_run = LastRun::start(&self.ctx, "qemu");
+        last_run.command(&cmd);
+        let mut child = cmd.spawn()?;
+        let stdout = BufReader::new(defmt::wrap(
+            &self.ctx,
+            Box::new(child.stdout.take().unwrap()),
+        ));
+        let qemu = QemuProcess::new(child, self.qmp);
+        let _abort = qemu.stop_on_abort();
+        self.attach_debugger();
 
+        let mut qemu_result: Option<anyhow::Result<()>> = None;
+
+        let mut line_buf = Vec::new();
+
+        for byte in stdout.bytes() {
+            let byte = match byte {
+                Ok(b) => b,
+                Err(e) => {
+                    warn!("stdout: {:?}", e);
+                    continue;
+                }
+            };
+            let _ = std::io::stdout().write_all(&[byte]);
+            let _ = std::io::stdout().flush();
+            last_run.console(&[byte]);
+
+            line_buf.push(byte);
+            if byte != b'\n' {
+                continue;
+            }
+
+            let line = String::from_utf8_lossy(&line_buf).to_string();
+            line_buf.clear();
+
+            if let Some((name, regex)) = &save
+                && regex.is_match(&line)
+            {
+                match self.save_vm(name) {
+                    Ok(()) => println!("\r\n{}", t!("qemu.snapshot_saved", name = name).green()),
+                    Err(e) => warn!("{}", t!("qemu.snapshot_failed", name = name, error = e)),
+                }
+                save = None;
+            }
+            self.check_output(&line, &qemu, &mut qemu_result);
+        }
+
+        let status = qemu.wait()?;
+        self.rules.print_captures();
+        if let Some(res) = qemu_result {
+            res?;
+        } else if !status.success() {
+            return Err(Failure::Boot.error(format!("QEMU exited with {status}")));
+        }
+        Ok(())
+    }
+
+    /// Starts the debugger of `auto_attach` in another terminal.
+    fn attach_debugger(&mut self) {
+        if let Some(mut debugger) = self.debugger.take()
+            && let Err(e) = debugger.spawn()
+        {
+            warn!("{}", t!("gdb.attach_failed", error = e));
+        }
+    }
+
+    /// The QEMU command for the kernel, without stdio set up.
+    async fn command(&mut self) -> anyhow::Result<Command> {
         if self.config.to_bin {
             self.ctx.objcopy_output_bin()?;
         }
@@ -163,26 +695,27 @@ impl QemuRunner {
             self.args.push(arg.clone());
         }
 
-        #[allow(unused_mut)]
-        let mut qemu_executable = format!("qemu-system-{}", arch);
-
-        #[cfg(windows)]
-        {
-            println!("{}", "Checking for QEMU executable on Windows...".blue());
-            // Windows 特殊处理
-            let msys2 =
-                PathBuf::from("C:\\msys64\\ucrt64\\bin").join(format!("{qemu_executable}.exe"));
-
-            if msys2.exists() {
-                println!("Using QEMU executable from MSYS2: {}", msys2.display());
-                qemu_executable = msys2.to_string_lossy().to_string();
-            }
-        }
+        // Windows 上由 Command 到 MSYS2 中查找
+        let qemu_executable = format!("qemu-system-{}", arch);
 
         let mut cmd = self.ctx.command(&qemu_executable);
 
-        for arg in &self.config.args {
+        let mut append = self.append.clone();
+        let mut args = self.config.args.iter();
+        while let Some(arg) = args.next() {
             cmd.arg(arg);
+            // 合并到已有的内核命令行，而不是再传一个 -append 覆盖它
+            if arg == "-append"
+                && let Some(value) = args.next()
+            {
+                match append.take() {
+                    Some(extra) => cmd.arg(format!("{value} {extra}")),
+                    None => cmd.arg(value),
+                };
+            }
+        }
+        if let Some(append) = append {
+            cmd.arg("-append").arg(append);
         }
 
         if self.dtbdump {
@@ -195,8 +728,19 @@ impl QemuRunner {
             cmd.arg("-machine").arg(machine);
         }
 
+        if !self.config.args.iter().any(|a| a == "-qmp") {
+            self.qmp = qmp::free_addr().ok();
+            if let Some(addr) = self.qmp {
+                cmd.arg("-qmp").arg(qmp::listen_arg(addr));
+            }
+        }
+
         if self.ctx.debug {
             cmd.arg("-s").arg("-S");
+            println!("{}", t!("qemu.gdb_hint").yellow());
+            self.debugger = gdb::prepare_qemu(&self.ctx).await?;
+        } else if let Some(gdb) = &self.gdb {
+            cmd.arg("-gdb").arg(gdb);
         }
 
         if let Some(bios) = self.bios().await? {
@@ -208,50 +752,117 @@ impl QemuRunner {
         } else if let Some(elf_path) = &self.ctx.paths.artifacts.elf {
             cmd.arg("-kernel").arg(elf_path);
         }
-        cmd.stdout(Stdio::piped());
-        cmd.print_cmd();
-        let mut child = cmd.spawn()?;
-
-        let mut qemu_result: Option<anyhow::Result<()>> = None;
-
-        let stdout = BufReader::new(child.stdout.take().unwrap());
-        let mut line_buf = Vec::new();
 
-        for byte in stdout.bytes() {
-            let byte = match byte {
-                Ok(b) => b,
-                Err(e) => {
-                    println!("stdout: {:?}", e);
-                    continue;
-                }
-            };
-            let _ = std::io::stdout().write_all(&[byte]);
-            let _ = std::io::stdout().flush();
-
-            line_buf.push(byte);
-            if byte != b'\n' {
-                continue;
+        // x86_64 和 LoongArch 的 virt 机器只有 PCI 总线
+        let pci = matches!(arch.as_str(), "x86_64" | "loongarch64");
+        let device = if pci {
+            "virtio-blk-pci"
+        } else {
+            "virtio-blk-device"
+        };
+        if self.config.disk_image {
+            let config = load_disk_config(&self.ctx, None).await?;
+            let image = build_image(&mut self.ctx, &config).await?;
+            cmd.arg("-drive")
+                .arg(format!(
+                    "file={},format=raw,if=none,id=disk0",
+                    image.display()
+                ))
+                .arg("-device")
+                .arg(format!("{device},drive=disk0"));
+        }
+        let first = usize::from(self.config.disk_image);
+        for (i, disk) in self.config.disks.iter().enumerate() {
+            let path = self.ctx.paths.workspace.join(&disk.path);
+            disk.prepare(&self.ctx, &path)?;
+            cmd.args(disk.args(&path, first + i, device));
+        }
+        // 没有 [snapshot] 时 --load-snapshot 使用默认镜像
+        let snapshot = match (&self.config.snapshot, &self.load_snapshot) {
+            (Some(config), _) => Some(config.clone()),
+            (None, Some(_)) => Some(SnapshotConfig::default()),
+            (None, None) => None,
+        };
+        if let Some(config) = snapshot {
+            self.snapshot_args(&mut cmd, &config)?;
+        }
+        if let Some(net) = &self.config.net {
+            if !net.hostfwd.is_empty() && net.mode == NetMode::Tap {
+                warn!("{}", t!("qemu.hostfwd_tap"));
             }
-
-            let line = String::from_utf8_lossy(&line_buf).to_string();
-
-            self.check_output(&line, &mut child, &mut qemu_result)?;
+            cmd.args(net.args(pci));
         }
+        Ok(cmd)
+    }
 
-        let out = child.wait_with_output()?;
-        if let Some(res) = qemu_result {
-            res?;
-        } else if !out.status.success() {
-            unsafe {
-                return Err(anyhow::anyhow!(
-                    "{}",
-                    OsString::from_encoded_bytes_unchecked(out.stderr).to_string_lossy()
-                ));
+    /// 快照镜像、`-snapshot` 和 `-loadvm` 参数
+    fn snapshot_args(&self, cmd: &mut Command, config: &SnapshotConfig) -> anyhow::Result<()> {
+        let image = config.image_path(&self.ctx.paths.workspace);
+        let disk = QemuDisk {
+            format: DiskFormat::Qcow2,
+            create_mib: Some(1),
+            ..Default::default()
+        };
+        disk.prepare(&self.ctx, &image)?;
+        cmd.args(snapshot::drive_args(&image));
+        if config.discard_writes {
+            cmd.arg("-snapshot");
+        }
+        let Some(name) = &self.load_snapshot else {
+            return Ok(());
+        };
+        let hash = snapshot::kernel_hash(&self.kernel_path()?)?;
+        match Records::load(&self.ctx.paths.workspace).matches(name, &hash) {
+            Some(true) => {
+                info!("{}", t!("qemu.snapshot_loading", name = name));
+                cmd.arg("-loadvm").arg(name);
             }
+            Some(false) => warn!("{}", t!("qemu.snapshot_stale", name = name)),
+            None => bail!(t!("qemu.snapshot_missing", name = name)),
         }
         Ok(())
     }
 
+    /// 检查 `--save-snapshot` 的前提，返回快照名和触发保存的正则
+    fn prepare_save(&self) -> anyhow::Result<Option<(String, Regex)>> {
+        let Some(name) = &self.save_snapshot else {
+            return Ok(None);
+        };
+        let Some(regex) = self
+            .config
+            .snapshot
+            .as_ref()
+            .and_then(|s| s.save_regex.as_ref())
+        else {
+            bail!(t!("qemu.snapshot_no_regex"));
+        };
+        if self.config.args.iter().any(|a| a == "-qmp") {
+            bail!(t!("qemu.snapshot_own_qmp"));
+        }
+        let regex = Regex::new(regex).map_err(|e| anyhow!("save_regex `{regex}`: {e}"))?;
+        Ok(Some((name.clone(), regex)))
+    }
+
+    /// 通过 QMP 保存快照并记下当前内核
+    fn save_vm(&self, name: &str) -> anyhow::Result<()> {
+        let addr = self.qmp.ok_or_else(|| anyhow!("no QMP socket"))?;
+        Qmp::connect(addr)?.save_vm(name)?;
+        let hash = snapshot::kernel_hash(&self.kernel_path()?)?;
+        let mut records = Records::load(&self.ctx.paths.workspace);
+        records.insert(name, hash);
+        records.save(&self.ctx.paths.workspace)
+    }
+
+    /// 传给 `-kernel` 的文件
+    fn kernel_path(&self) -> anyhow::Result<PathBuf> {
+        let artifacts = &self.ctx.paths.artifacts;
+        artifacts
+            .bin
+            .clone()
+            .or_else(|| artifacts.elf.clone())
+            .ok_or_else(|| anyhow!("no kernel to snapshot"))
+    }
+
     fn detect_arch(&self) -> anyhow::Result<String> {
         if let Some(arch) = &self.ctx.arch {
             return Ok(format!("{:?}", arch).to_lowercase());
@@ -275,12 +886,19 @@ impl QemuRunner {
             self.ctx.arch.as_ref().ok_or_else(|| {
                 anyhow::anyhow!("Cannot determine architecture for OVMF preparation")
             })?;
-        let tmp = std::env::temp_dir();
-        let bios_dir = tmp.join("ostool").join("ovmf");
+        let mut cache = Cache::open();
+        let bios_dir = cache.path(cache::OVMF, Source::LATEST.tag);
+        // 固件由 Source 中的 sha256 固定，离线时只能用校验过的缓存
+        let cached = std::fs::read_to_string(bios_dir.join("sha256"))
+            .is_ok_and(|hash| hash == Source::LATEST.sha256);
+        if self.ctx.offline && !cached {
+            bail!(t!("cache.offline_missing", url = Source::LATEST.tag));
+        }
         fs::create_dir_all(&bios_dir).await?;
 
-        println!("Preparing OVMF firmware for architecture: {:?}", arch);
+        info!("Preparing OVMF firmware for architecture: {:?}", arch);
         let prebuilt = Prebuilt::fetch(Source::LATEST, &bios_dir)?;
+        cache.insert(cache::OVMF, Source::LATEST.tag, None)?;
         let arch = match arch {
             Architecture::X86_64 => Arch::X64,
             Architecture::Aarch64 => Arch::Aarch64,
@@ -296,81 +914,144 @@ impl QemuRunner {
     }
 
     fn check_output(
-        &self,
+        &mut self,
         out: &str,
-        child: &mut Child,
+        qemu: &QemuProcess,
         res: &mut Option<anyhow::Result<()>>,
-    ) -> anyhow::Result<()> {
-        // // Process QEMU output line here
-        // println!("{}", line);
-
-        for regex in &self.fail_regex {
-            if regex.is_match(out) {
-                *res = Some(Err(anyhow!(
-                    "Detected failure pattern '{}' in QEMU output.",
-                    regex.as_str()
-                )));
-
-                self.kill_qemu(child)?;
-                return Ok(());
-            }
-        }
-
-        for regex in &self.success_regex {
-            if regex.is_match(out) {
-                *res = Some(Ok(()));
-                println!(
-                    "{}",
-                    format!(
-                        "Detected success pattern '{}' in QEMU output, terminating QEMU.",
-                        regex.as_str()
-                    )
-                    .green()
-                );
-                self.kill_qemu(child)?;
-                return Ok(());
-            }
+    ) {
+        let Some(verdict) = self.rules.check(out) else {
+            return;
+        };
+        if verdict.success {
+            *res = Some(Ok(()));
+            println!(
+                "{}",
+                format!(
+                    "Detected success pattern '{}' in QEMU output, terminating QEMU.",
+                    verdict.pattern
+                )
+                .green()
+            );
+        } else {
+            *res = Some(Err(Failure::Boot.error(format!(
+                "Detected failure pattern '{}' in QEMU output: {}",
+                verdict.pattern, verdict.message
+            ))));
         }
-
-        Ok(())
+        self.kill_qemu(qemu);
     }
 
-    fn kill_qemu(&self, child: &mut Child) -> anyhow::Result<()> {
-        child.kill()?;
+    fn kill_qemu(&self, qemu: &QemuProcess) {
+        qemu.stop();
 
         // 尝试恢复终端状态
-        let _ = disable_raw_mode();
-
-        // 使用 stty 命令恢复终端回显 (最可靠的方法)
-        let _ = std::process::Command::new("stty")
-            .arg("echo")
-            .arg("icanon")
-            .status();
+        exit::restore_terminal();
 
         // 刷新输出
         let _ = io::stdout().flush();
         println!();
+    }
 
+    fn prepare_rules(&mut self) -> anyhow::Result<()> {
+        self.rules = ConsoleRules::for_runner(
+            &self.ctx,
+            &self.config.success_regex,
+            &self.config.fail_regex,
+        )?;
         Ok(())
     }
+}
 
-    fn preper_regex(&mut self) -> anyhow::Result<()> {
-        // Prepare regex patterns if needed
-        // Compile success regex patterns
-        for pattern in self.config.success_regex.iter() {
-            // Compile and store the regex
-            let regex =
-                regex::Regex::new(pattern).map_err(|e| anyhow!("success regex error: {e}"))?;
-            self.success_regex.push(regex);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        // Compile fail regex patterns
-        for pattern in self.config.fail_regex.iter() {
-            // Compile and store the regex
-            let regex = regex::Regex::new(pattern).map_err(|e| anyhow!("fail regex error: {e}"))?;
-            self.fail_regex.push(regex);
-        }
+    #[test]
+    fn test_disk_args() {
+        let config: QemuConfig = toml::from_str(
+            r#"
+            args = []
+            uefi = false
+            to_bin = true
+            success_regex = []
+            fail_regex = []
 
-        Ok(())
+            [[disks]]
+            path = "target/data,1.qcow2"
+            format = "qcow2"
+            readonly = true
+            "#,
+        )
+        .unwrap();
+        let disk = &config.disks[0];
+        assert_eq!(disk.create_mib, None);
+        assert_eq!(
+            disk.args(Path::new("/w/target/data,1.qcow2"), 1, "virtio-blk-pci"),
+            [
+                "-drive",
+                "file=/w/target/data,,1.qcow2,format=qcow2,if=none,id=disk1,readonly=on",
+                "-device",
+                "virtio-blk-pci,drive=disk1",
+            ]
+        );
+
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        let path = dir.join("raw.img");
+        let raw = QemuDisk {
+            path: "raw.img".into(),
+            create_mib: Some(2),
+            ..Default::default()
+        };
+        raw.prepare(&AppContext::default(), &path).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 2 << 20);
+        let missing = QemuDisk::default();
+        assert!(
+            missing
+                .prepare(&AppContext::default(), &dir.join("none"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_net_args() {
+        let user = QemuNet {
+            hostfwd: vec![
+                PortForward {
+                    host: 2222,
+                    guest: 22,
+                    ..Default::default()
+                },
+                PortForward {
+                    protocol: Protocol::Udp,
+                    host: 5353,
+                    guest: 53,
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            user.args(false),
+            [
+                "-netdev",
+                "user,id=net0,hostfwd=tcp::2222-:22,hostfwd=udp::5353-:53",
+                "-device",
+                "virtio-net-device,netdev=net0",
+            ]
+        );
+        let tap = QemuNet {
+            mode: NetMode::Tap,
+            ifname: Some("tap0".into()),
+            mac: Some("52:54:00:12:34:56".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            tap.args(true)[1..],
+            [
+                "tap,id=net0,script=no,downscript=no,ifname=tap0",
+                "-device",
+                "virtio-net-pci,netdev=net0,mac=52:54:00:12:34:56",
+            ]
+        );
     }
 }
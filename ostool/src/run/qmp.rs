@@ -0,0 +1,440 @@
+//! Minimal client of the QEMU Machine Protocol.
+//!
+//! QEMU is started with a QMP socket on a free local TCP port (see
+//! [`listen_arg`]) so it can be asked to quit: unlike a kill, that lets it
+//! restore the terminal and flush its disk images. It also saves VM
+//! snapshots, see [`snapshot`](super::snapshot).
+//!
+//! Besides raw commands with [`Qmp::execute_with`], [`Qmp`] has typed
+//! helpers to pause and resume the VM, query its CPUs and registers,
+//! inject an NMI, hot-add and remove devices and power the guest down.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+/// How long to wait for QEMU to answer.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long saving a snapshot may take, writing all of the guest's RAM.
+const SAVEVM_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A free local address for QEMU's QMP socket.
+///
+/// # Errors
+///
+/// Returns an error if no local port can be bound.
+pub fn free_addr() -> std::io::Result<SocketAddr> {
+    TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?.local_addr()
+}
+
+/// The `-qmp` value making QEMU listen on `addr` without waiting for it.
+pub fn listen_arg(addr: SocketAddr) -> String {
+    format!("tcp:{addr},server=on,wait=off")
+}
+
+/// A virtual CPU, as `query-cpus-fast` reports it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CpuInfo {
+    /// Index of the CPU, as `-smp` numbers them.
+    pub cpu_index: u32,
+    /// QOM path of the CPU object.
+    pub qom_path: String,
+    /// Host thread running the CPU.
+    pub thread_id: u64,
+    /// Target architecture, e.g. `riscv64`.
+    #[serde(default)]
+    pub target: String,
+}
+
+/// Run state of the VM, as `query-status` reports it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct VmStatus {
+    /// Whether the CPUs are running.
+    pub running: bool,
+    /// Run state, e.g. `running`, `paused` or `shutdown`.
+    pub status: String,
+}
+
+/// A QMP connection in command mode.
+pub struct Qmp {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Qmp {
+    /// Connects to QEMU and leaves capabilities negotiation.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if QEMU does not answer.
+    pub fn connect(addr: SocketAddr) -> anyhow::Result<Self> {
+        let stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        let mut qmp = Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        };
+        // 先收到问候消息，再进入命令模式
+        let greeting = qmp.read()?;
+        if greeting.get("QMP").is_none() {
+            bail!("unexpected QMP greeting: {greeting}");
+        }
+        qmp.execute("qmp_capabilities")?;
+        Ok(qmp)
+    }
+
+    /// Runs `command` and returns its result, skipping events.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if QEMU reports one or the connection fails.
+    pub fn execute(&mut self, command: &str) -> anyhow::Result<Value> {
+        self.execute_with(command, json!({}))
+    }
+
+    /// Runs `command` with `arguments` and returns its result, skipping
+    /// events.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if QEMU reports one or the connection fails.
+    pub fn execute_with(&mut self, command: &str, arguments: Value) -> anyhow::Result<Value> {
+        let mut line = json!({ "execute": command, "arguments": arguments }).to_string();
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())?;
+        loop {
+            let reply = self.read()?;
+            if let Some(ret) = reply.get("return") {
+                return Ok(ret.clone());
+            }
+            if let Some(err) = reply.get("error") {
+                bail!(
+                    "QMP {command}: {}",
+                    err["desc"].as_str().unwrap_or_default()
+                );
+            }
+        }
+    }
+
+    /// Runs the human monitor command `command_line`, on CPU `cpu` if
+    /// given, and returns its output.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if QEMU reports one or the connection fails.
+    pub fn hmp(&mut self, command_line: &str, cpu: Option<u32>) -> anyhow::Result<String> {
+        let mut arguments = json!({ "command-line": command_line });
+        if let Some(cpu) = cpu {
+            arguments["cpu-index"] = json!(cpu);
+        }
+        let output = self.execute_with("human-monitor-command", arguments)?;
+        Ok(output.as_str().unwrap_or_default().to_string())
+    }
+
+    /// Pauses the VM.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if QEMU reports one or the connection fails.
+    pub fn stop(&mut self) -> anyhow::Result<()> {
+        self.execute("stop")?;
+        Ok(())
+    }
+
+    /// Resumes the VM after [`Self::stop`] or `-S`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if QEMU reports one or the connection fails.
+    pub fn cont(&mut self) -> anyhow::Result<()> {
+        self.execute("cont")?;
+        Ok(())
+    }
+
+    /// Run state of the VM.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if QEMU reports one or the connection fails.
+    pub fn status(&mut self) -> anyhow::Result<VmStatus> {
+        Ok(serde_json::from_value(self.execute("query-status")?)?)
+    }
+
+    /// The virtual CPUs of the VM.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if QEMU reports one or the connection fails.
+    pub fn cpus(&mut self) -> anyhow::Result<Vec<CpuInfo>> {
+        Ok(serde_json::from_value(self.execute("query-cpus-fast")?)?)
+    }
+
+    /// Registers of CPU `cpu` as the monitor's `info registers` prints
+    /// them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if QEMU reports one or the connection fails.
+    pub fn registers(&mut self, cpu: u32) -> anyhow::Result<String> {
+        self.hmp("info registers", Some(cpu))
+    }
+
+    /// Injects a non-maskable interrupt into all CPUs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the machine has no NMI or the connection fails.
+    pub fn inject_nmi(&mut self) -> anyhow::Result<()> {
+        self.execute("inject-nmi")?;
+        Ok(())
+    }
+
+    /// Hot-adds a `driver` device named `id`, with the extra properties
+    /// in `props` such as `{"drive": "disk1"}`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if QEMU cannot add it or the connection fails.
+    pub fn device_add(&mut self, driver: &str, id: &str, props: Value) -> anyhow::Result<()> {
+        let mut arguments = json!({ "driver": driver, "id": id });
+        if let (Some(arguments), Value::Object(props)) = (arguments.as_object_mut(), props) {
+            arguments.extend(props);
+        }
+        self.execute_with("device_add", arguments)?;
+        Ok(())
+    }
+
+    /// Asks the guest to release the device `id`; it is gone once the
+    /// guest has acknowledged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no such device or the connection fails.
+    pub fn device_del(&mut self, id: &str) -> anyhow::Result<()> {
+        self.execute_with("device_del", json!({ "id": id }))?;
+        Ok(())
+    }
+
+    /// Presses the power button of the VM and waits up to `timeout` for
+    /// the guest to shut down, returning whether it did.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if QEMU reports one or the connection fails.
+    pub fn powerdown(&mut self, timeout: Duration) -> anyhow::Result<bool> {
+        self.execute("system_powerdown")?;
+        self.wait_event("SHUTDOWN", timeout)
+    }
+
+    /// Saves the VM as snapshot `name` with the monitor's `savevm`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if QEMU cannot save it, e.g. without a qcow2 image.
+    pub fn save_vm(&mut self, name: &str) -> anyhow::Result<()> {
+        self.writer.set_read_timeout(Some(SAVEVM_TIMEOUT))?;
+        let output = self.hmp(&format!("savevm {name}"), None);
+        self.writer.set_read_timeout(Some(TIMEOUT))?;
+        // 监视器命令成功时没有输出
+        match output?.trim() {
+            "" => Ok(()),
+            err => bail!("savevm {name}: {err}"),
+        }
+    }
+
+    /// Asks QEMU to quit.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if QEMU cannot be reached.
+    pub fn quit(mut self) -> anyhow::Result<()> {
+        self.execute("quit")?;
+        Ok(())
+    }
+
+    /// Reads until the event `name`, returning `false` if it does not
+    /// come within `timeout`.
+    fn wait_event(&mut self, name: &str, timeout: Duration) -> anyhow::Result<bool> {
+        let deadline = Instant::now() + timeout;
+        let found = loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                break Ok(false);
+            }
+            self.writer.set_read_timeout(Some(left))?;
+            match self.read() {
+                Ok(event) if event["event"] == name => break Ok(true),
+                Ok(_) => {}
+                Err(e) => match e.downcast_ref::<std::io::Error>().map(|e| e.kind()) {
+                    Some(std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {
+                        break Ok(false);
+                    }
+                    _ => break Err(e),
+                },
+            }
+        };
+        self.writer.set_read_timeout(Some(TIMEOUT))?;
+        found
+    }
+
+    fn read(&mut self) -> anyhow::Result<Value> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            bail!("QMP connection closed");
+        }
+        Ok(serde_json::from_str(&line)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    /// Fake QEMU answering every command with `replies` in turn, `{}` once
+    /// they run out, and returning the commands it got.
+    fn fake_qemu(replies: &'static [&'static str]) -> (SocketAddr, thread::JoinHandle<Vec<Value>>) {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let qemu = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            writeln!(
+                writer,
+                r#"{{"QMP": {{"version": {{}}, "capabilities": []}}}}"#
+            )
+            .unwrap();
+            let mut commands = Vec::new();
+            let mut line = String::new();
+            let mut replies = replies.iter();
+            while reader.read_line(&mut line).unwrap() > 0 {
+                commands.push(serde_json::from_str(&line).unwrap());
+                line.clear();
+                let reply = replies.next().copied().unwrap_or(r#"{"return": {}}"#);
+                writeln!(writer, "{reply}").unwrap();
+            }
+            commands
+        });
+        (addr, qemu)
+    }
+
+    #[test]
+    fn test_save_vm() {
+        let (addr, qemu) = fake_qemu(&[
+            r#"{"return": {}}"#,
+            r#"{"return": ""}"#,
+            r#"{"return": "Error: No block device can accept snapshots\r\n"}"#,
+        ]);
+        let mut qmp = Qmp::connect(addr).unwrap();
+        qmp.save_vm("booted").unwrap();
+        let err = qmp.save_vm("again").unwrap_err();
+        assert!(err.to_string().contains("No block device"));
+        drop(qmp);
+        let commands = qemu.join().unwrap();
+        assert_eq!(commands[1]["execute"], "human-monitor-command");
+        assert_eq!(commands[1]["arguments"]["command-line"], "savevm booted");
+    }
+
+    #[test]
+    fn test_commands() {
+        let (addr, qemu) = fake_qemu(&[
+            r#"{"return": {}}"#,
+            r#"{"return": {}}"#,
+            r#"{"return": {"running": false, "status": "paused"}}"#,
+            r#"{"return": [{"cpu-index": 0, "qom-path": "/machine/soc/harts[0]", "thread-id": 42, "target": "riscv64"}]}"#,
+            r#"{"return": "pc 0000000080200000\r\n"}"#,
+            r#"{"return": {}}"#,
+            r#"{"return": {}}"#,
+            r#"{"return": {}}"#,
+            r#"{"return": {}}"#,
+            r#"{"return": {}}
+{"event": "POWERDOWN"}
+{"event": "SHUTDOWN", "data": {"guest": true}}"#,
+        ]);
+        let mut qmp = Qmp::connect(addr).unwrap();
+        qmp.stop().unwrap();
+        assert!(!qmp.status().unwrap().running);
+        let cpus = qmp.cpus().unwrap();
+        assert_eq!(cpus[0].cpu_index, 0);
+        assert_eq!(cpus[0].thread_id, 42);
+        assert!(qmp.registers(0).unwrap().starts_with("pc "));
+        qmp.cont().unwrap();
+        qmp.inject_nmi().unwrap();
+        qmp.device_add("virtio-blk-device", "disk9", json!({ "drive": "d9" }))
+            .unwrap();
+        qmp.device_del("disk9").unwrap();
+        assert!(qmp.powerdown(Duration::from_secs(1)).unwrap());
+        drop(qmp);
+
+        let commands = qemu.join().unwrap();
+        let names: Vec<_> = commands.iter().map(|c| c["execute"].clone()).collect();
+        assert_eq!(
+            names,
+            [
+                "qmp_capabilities",
+                "stop",
+                "query-status",
+                "query-cpus-fast",
+                "human-monitor-command",
+                "cont",
+                "inject-nmi",
+                "device_add",
+                "device_del",
+                "system_powerdown",
+            ]
+        );
+        assert_eq!(commands[4]["arguments"]["cpu-index"], 0);
+        assert_eq!(
+            commands[7]["arguments"],
+            json!({ "driver": "virtio-blk-device", "id": "disk9", "drive": "d9" })
+        );
+    }
+
+    #[test]
+    fn test_powerdown_timeout() {
+        let (addr, qemu) = fake_qemu(&[]);
+        let mut qmp = Qmp::connect(addr).unwrap();
+        assert!(!qmp.powerdown(Duration::from_millis(100)).unwrap());
+        drop(qmp);
+        qemu.join().unwrap();
+    }
+
+    #[test]
+    fn test_quit() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let qemu = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            writeln!(
+                writer,
+                r#"{{"QMP": {{"version": {{}}, "capabilities": []}}}}"#
+            )
+            .unwrap();
+            let mut commands = Vec::new();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 0 {
+                let cmd: Value = serde_json::from_str(&line).unwrap();
+                line.clear();
+                commands.push(cmd["execute"].as_str().unwrap().to_string());
+                writeln!(writer, r#"{{"event": "STOP"}}"#).unwrap();
+                writeln!(writer, r#"{{"return": {{}}}}"#).unwrap();
+            }
+            commands
+        });
+
+        assert_eq!(listen_arg(addr), format!("tcp:{addr},server=on,wait=off"));
+        Qmp::connect(addr).unwrap().quit().unwrap();
+        assert_eq!(qemu.join().unwrap(), ["qmp_capabilities", "quit"]);
+    }
+}
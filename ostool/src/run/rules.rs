@@ -0,0 +1,482 @@
+//! Console rules shared by the runners.
+//!
+//! `console_rules` in `.build.toml` declares once per project how the
+//! console lines of QEMU, U-Boot, OpenOCD and probe-rs runs are judged:
+//!
+//! ```toml
+//! [[console_rules]]
+//! regex = "All tests passed"
+//! action = "success"
+//!
+//! [[console_rules]]
+//! regex = "panicked at (.*)"
+//! action = "failure"
+//! message = "kernel panic at $1"
+//!
+//! [[console_rules]]
+//! regex = "WARN"
+//! action = "warn"
+//!
+//! [[console_rules]]
+//! regex = "boot time: (\\d+) ms"
+//! action = "capture"
+//! message = "boot time $1 ms"
+//!
+//! [[console_rules]]
+//! regex = "login:"
+//! action = "send"
+//! message = "root"
+//!
+//! [[console_rules]]
+//! regex = "network up at (\\S+)"
+//! action = "run"
+//! message = 'ping -c 1 "$OSTOOL_MATCH_1"'
+//! ```
+//!
+//! The rules are checked in order, followed by the `fail_regex` and
+//! `success_regex` of the runner's own config. The first `success` or
+//! `failure` rule that matches a line ends the run. `send` and `run` rules
+//! are carried out by runners that can write to the console, the U-Boot
+//! runner in its terminal and with `--test`, and ignored by the others.
+//! They are also checked against prompts that do not end with a newline,
+//! such as `login: `.
+//!
+//! The command of a `run` rule is not expanded: the console text it
+//! matched reaches it only through the `OSTOOL_MATCH_<n>` and
+//! `OSTOOL_MATCH_<name>` environment variables, so whatever the target
+//! prints cannot become part of a host command line.
+
+use colored::Colorize;
+use jkconfig::t;
+use regex::{Captures, Regex};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::ctx::AppContext;
+
+/// Prefix of the environment variables carrying the capture groups of a
+/// `run` rule.
+const MATCH_ENV: &str = "OSTOOL_MATCH";
+
+/// What happens when a console rule matches a line.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    /// The run succeeded and stops.
+    #[default]
+    Success,
+    /// The run failed and stops.
+    Failure,
+    /// A warning is printed and the run goes on.
+    Warn,
+    /// The message, or else the first capture group, is recorded and
+    /// listed when the run ends.
+    Capture,
+    /// The message is typed into the console, followed by Enter.
+    Send,
+    /// The message is run as a host shell command in the background, with
+    /// the capture groups in `OSTOOL_MATCH_<n>` environment variables.
+    Run,
+}
+
+/// Input for the console or the host asked for by a `send` or `run` rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reaction {
+    /// Text to type into the console, without the Enter.
+    Send(String),
+    /// Host shell command to run, with the capture groups of the match as
+    /// environment variables.
+    Run {
+        cmd: String,
+        vars: Vec<(String, String)>,
+    },
+}
+
+impl Reaction {
+    fn run(cmd: &str, regex: &Regex, caps: &Captures) -> Self {
+        let mut vars = Vec::new();
+        for (i, name) in regex.capture_names().enumerate() {
+            let Some(group) = caps.get(i) else {
+                continue;
+            };
+            vars.push((format!("{MATCH_ENV}_{i}"), group.as_str().to_string()));
+            if let Some(name) = name {
+                vars.push((format!("{MATCH_ENV}_{name}"), group.as_str().to_string()));
+            }
+        }
+        Reaction::Run {
+            cmd: cmd.to_string(),
+            vars,
+        }
+    }
+}
+
+/// A regex matched against every console line.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct ConsoleRule {
+    /// Regex matched against each console line
+    pub regex: String,
+    /// What to do when it matches
+    pub action: RuleAction,
+    /// Message shown on a match; `$1` or `${name}` insert capture groups,
+    /// except in the command of a `run` rule, which gets them as
+    /// `OSTOOL_MATCH_1` or `OSTOOL_MATCH_name` environment variables
+    pub message: Option<String>,
+}
+
+impl ConsoleRule {
+    fn new(regex: &str, action: RuleAction) -> Self {
+        Self {
+            regex: regex.to_string(),
+            action,
+            message: None,
+        }
+    }
+}
+
+/// A `success` or `failure` rule that matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verdict {
+    /// Whether the run succeeded.
+    pub success: bool,
+    /// Regex of the rule.
+    pub pattern: String,
+    /// The rule's message, or a default one naming the line.
+    pub message: String,
+}
+
+/// Compiled console rules of a run.
+#[derive(Debug, Default)]
+pub struct ConsoleRules {
+    rules: Vec<(Regex, ConsoleRule)>,
+    captures: Vec<String>,
+    reactions: Vec<Reaction>,
+    /// Last prompt and the `send` and `run` rules that matched it.
+    prompt: Option<(String, Vec<usize>)>,
+}
+
+impl ConsoleRules {
+    /// Compiles `rules`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a regex is invalid.
+    pub fn new(rules: impl IntoIterator<Item = ConsoleRule>) -> anyhow::Result<Self> {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let regex = Regex::new(&rule.regex)
+                    .map_err(|e| anyhow!("console rule `{}`: {e}", rule.regex))?;
+                Ok((regex, rule))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            rules,
+            captures: vec![],
+            reactions: vec![],
+            prompt: None,
+        })
+    }
+
+    /// The project's `console_rules` followed by a runner's `fail_regex`
+    /// and `success_regex`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a regex is invalid.
+    pub fn for_runner(
+        ctx: &AppContext,
+        success_regex: &[String],
+        fail_regex: &[String],
+    ) -> anyhow::Result<Self> {
+        let project = ctx
+            .build_config
+            .as_ref()
+            .map(|c| c.console_rules.clone())
+            .unwrap_or_default();
+        let fail = fail_regex
+            .iter()
+            .map(|r| ConsoleRule::new(r, RuleAction::Failure));
+        let success = success_regex
+            .iter()
+            .map(|r| ConsoleRule::new(r, RuleAction::Success));
+        Self::new(project.into_iter().chain(fail).chain(success))
+    }
+
+    /// Keeps only the rules with `action`.
+    pub fn only(mut self, action: RuleAction) -> Self {
+        self.rules.retain(|(_, rule)| rule.action == action);
+        self
+    }
+
+    /// Checks a console line against the rules in order, printing
+    /// warnings and recording captures and reactions, until a `success` or
+    /// `failure` rule matches.
+    pub fn check(&mut self, line: &str) -> Option<Verdict> {
+        let line = line.trim_end();
+        // 提示符补全成整行后，已对提示符响应过的规则不再响应
+        let answered = match self.prompt.take() {
+            Some((prompt, rules)) if line.starts_with(prompt.trim_end()) => rules,
+            _ => vec![],
+        };
+        for (i, (regex, rule)) in self.rules.iter().enumerate() {
+            if answered.contains(&i) {
+                continue;
+            }
+            let Some(caps) = regex.captures(line) else {
+                continue;
+            };
+            let message = rule.message.as_ref().map(|m| {
+                let mut message = String::new();
+                caps.expand(m, &mut message);
+                message
+            });
+            match rule.action {
+                RuleAction::Warn => {
+                    let message = message.unwrap_or_else(|| line.to_string());
+                    warn!("{}", t!("rules.warn", message = message));
+                }
+                RuleAction::Capture => {
+                    let message = message.unwrap_or_else(|| {
+                        caps.get(1).or(caps.get(0)).unwrap().as_str().to_string()
+                    });
+                    info!("{}", t!("rules.captured", message = message));
+                    self.captures.push(message);
+                }
+                RuleAction::Send => {
+                    self.reactions
+                        .push(Reaction::Send(message.unwrap_or_default()));
+                }
+                RuleAction::Run => {
+                    if let Some(cmd) = &rule.message {
+                        self.reactions.push(Reaction::run(cmd, regex, &caps));
+                    }
+                }
+                RuleAction::Success | RuleAction::Failure => {
+                    let success = rule.action == RuleAction::Success;
+                    let message = message.unwrap_or_else(|| {
+                        if success {
+                            format!("Success pattern matched: {line}")
+                        } else {
+                            format!("Fail pattern matched: {line}")
+                        }
+                    });
+                    return Some(Verdict {
+                        success,
+                        pattern: rule.regex.clone(),
+                        message,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Checks an unfinished line, such as `login: `, against the `send` and
+    /// `run` rules. They do not react again once the line is finished.
+    pub fn check_prompt(&mut self, prompt: &str) {
+        // 回显逐字到达时提示符不断变长
+        let mut matched = match self.prompt.take() {
+            Some((last, rules)) if prompt.starts_with(&last) => rules,
+            _ => vec![],
+        };
+        for (i, (regex, rule)) in self.rules.iter().enumerate() {
+            if matched.contains(&i) || !matches!(rule.action, RuleAction::Send | RuleAction::Run) {
+                continue;
+            }
+            let Some(caps) = regex.captures(prompt) else {
+                continue;
+            };
+            match (rule.action, &rule.message) {
+                (RuleAction::Run, Some(cmd)) => {
+                    self.reactions.push(Reaction::run(cmd, regex, &caps))
+                }
+                (RuleAction::Run, None) => continue,
+                (_, message) => {
+                    let mut text = String::new();
+                    if let Some(message) = message {
+                        caps.expand(message, &mut text);
+                    }
+                    self.reactions.push(Reaction::Send(text));
+                }
+            }
+            matched.push(i);
+        }
+        self.prompt = Some((prompt.to_string(), matched));
+    }
+
+    /// Takes the reactions of the `send` and `run` rules that matched since
+    /// the last call.
+    pub fn take_reactions(&mut self) -> Vec<Reaction> {
+        std::mem::take(&mut self.reactions)
+    }
+
+    /// Values recorded by `capture` rules so far.
+    pub fn captures(&self) -> &[String] {
+        &self.captures
+    }
+
+    /// Lists the captured values, if any.
+    pub fn print_captures(&self) {
+        if self.captures.is_empty() {
+            return;
+        }
+        println!("{}", t!("rules.captures").bold());
+        for capture in &self.captures {
+            println!("  {capture}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(regex: &str, action: RuleAction, message: Option<&str>) -> ConsoleRule {
+        ConsoleRule {
+            regex: regex.to_string(),
+            action,
+            message: message.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_check_in_order() {
+        let mut rules = ConsoleRules::new([
+            rule("boot time: (\\d+) ms", RuleAction::Capture, None),
+            rule("WARN", RuleAction::Warn, None),
+            rule(
+                "panicked at (?<at>.*)",
+                RuleAction::Failure,
+                Some("panic at ${at}"),
+            ),
+            rule("panicked", RuleAction::Success, None),
+            rule("All tests passed", RuleAction::Success, None),
+        ])
+        .unwrap();
+
+        assert_eq!(rules.check("boot time: 42 ms\r\n"), None);
+        assert_eq!(rules.check("WARN: low memory"), None);
+        assert_eq!(rules.captures(), ["42"]);
+
+        let verdict = rules.check("panicked at src/main.rs:3").unwrap();
+        assert!(!verdict.success);
+        assert_eq!(verdict.message, "panic at src/main.rs:3");
+
+        let verdict = rules.check("All tests passed").unwrap();
+        assert!(verdict.success);
+        assert_eq!(verdict.pattern, "All tests passed");
+    }
+
+    #[test]
+    fn test_reactions() {
+        let mut rules = ConsoleRules::new([
+            rule("login:", RuleAction::Send, Some("root")),
+            rule("Password:", RuleAction::Send, None),
+            rule(
+                "up at (\\S+)",
+                RuleAction::Run,
+                Some("ping \"$OSTOOL_MATCH_1\""),
+            ),
+            rule("login:", RuleAction::Success, None),
+        ])
+        .unwrap();
+        assert!(rules.check("Password:").is_none());
+        assert!(rules.check("eth0 up at 10.0.0.2").is_none());
+        // 先记下输入，再由 success 规则结束
+        assert!(rules.check("buildroot login:").unwrap().success);
+        assert_eq!(
+            rules.take_reactions(),
+            [
+                Reaction::Send(String::new()),
+                Reaction::Run {
+                    cmd: "ping \"$OSTOOL_MATCH_1\"".into(),
+                    vars: vec![
+                        ("OSTOOL_MATCH_0".into(), "up at 10.0.0.2".into()),
+                        ("OSTOOL_MATCH_1".into(), "10.0.0.2".into()),
+                    ],
+                },
+                Reaction::Send("root".into()),
+            ]
+        );
+        assert!(rules.take_reactions().is_empty());
+    }
+
+    #[test]
+    fn test_run_keeps_matched_text_out_of_command() {
+        let line = "up at 10.0.0.2; touch pwned $(touch pwned2)";
+        let cmd = "printf '%s' \"$OSTOOL_MATCH_addr\" > addr";
+        let mut rules =
+            ConsoleRules::new([rule("up at (?<addr>.*)", RuleAction::Run, Some(cmd))]).unwrap();
+        assert!(rules.check(line).is_none());
+        let reactions = rules.take_reactions();
+        let [Reaction::Run { cmd: run, vars }] = reactions.as_slice() else {
+            panic!("{reactions:?}");
+        };
+        assert_eq!(run, cmd);
+        let addr = "10.0.0.2; touch pwned $(touch pwned2)";
+        assert!(vars.contains(&("OSTOOL_MATCH_1".into(), addr.into())));
+        assert!(vars.contains(&("OSTOOL_MATCH_addr".into(), addr.into())));
+
+        #[cfg(unix)]
+        {
+            let dir = tempfile::tempdir().unwrap();
+            let mut ctx = AppContext::default();
+            ctx.paths.manifest = dir.path().to_path_buf();
+            ctx.shell_run_cmd_with_env(run, vars).unwrap();
+            assert_eq!(
+                std::fs::read_to_string(dir.path().join("addr")).unwrap(),
+                addr
+            );
+            assert!(!dir.path().join("pwned").exists());
+            assert!(!dir.path().join("pwned2").exists());
+        }
+    }
+
+    #[test]
+    fn test_prompt() {
+        let mut rules = ConsoleRules::new([
+            rule("login:", RuleAction::Send, Some("root")),
+            rule("login:", RuleAction::Warn, None),
+        ])
+        .unwrap();
+        rules.check_prompt("buildroot ");
+        rules.check_prompt("buildroot login: ");
+        rules.check_prompt("buildroot login: ro");
+        assert_eq!(rules.take_reactions(), [Reaction::Send("root".into())]);
+        // 回显的输入补全了提示符
+        assert!(rules.check("buildroot login: root\n").is_none());
+        assert!(rules.take_reactions().is_empty());
+        // 再次出现的提示符重新响应
+        assert!(rules.check("buildroot login:").is_none());
+        assert_eq!(rules.take_reactions(), [Reaction::Send("root".into())]);
+    }
+
+    #[test]
+    fn test_runner_regex_after_project_rules() {
+        let mut ctx = AppContext::default();
+        let mut rules =
+            ConsoleRules::for_runner(&ctx, &["done".to_string()], &["done!".to_string()]).unwrap();
+        assert!(!rules.check("done!").unwrap().success);
+
+        let config = toml::from_str(
+            r#"
+            [system.Custom]
+            build_cmd = "make"
+            elf_path = "kernel.elf"
+            to_bin = false
+
+            [[console_rules]]
+            regex = "done!"
+            action = "success"
+            "#,
+        )
+        .unwrap();
+        ctx.build_config = Some(config);
+        let mut rules =
+            ConsoleRules::for_runner(&ctx, &["done".to_string()], &["done!".to_string()]).unwrap();
+        assert!(rules.check("done!").unwrap().success);
+
+        assert!(ConsoleRules::new([rule("(", RuleAction::Warn, None)]).is_err());
+    }
+}
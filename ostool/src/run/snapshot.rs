@@ -0,0 +1,150 @@
+//! QEMU VM snapshots skipping long boot sequences.
+//!
+//! With a `[snapshot]` section in `.qemu.toml`, QEMU gets a qcow2 image
+//! that holds the saved VM states; it is created when missing and not
+//! visible to the guest:
+//!
+//! ```toml
+//! [snapshot]
+//! save_regex = "login:"
+//! ```
+//!
+//! `ostool run qemu --save-snapshot booted` boots as usual and saves the
+//! VM as `booted` over QMP (`savevm`) once a console line matches
+//! `save_regex`. `ostool run qemu --load-snapshot booted` starts QEMU with
+//! `-loadvm booted`, right where the snapshot was taken.
+//!
+//! A snapshot holds the RAM of the kernel it was saved with. The SHA-256
+//! of that kernel is recorded in `.ostool/snapshots.toml` of the
+//! workspace; after a rebuild the snapshot is not loaded and QEMU boots
+//! the new kernel instead.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Image holding the snapshots when `image` is unset, relative to the
+/// workspace.
+pub const DEFAULT_IMAGE: &str = "target/qemu-snapshots.qcow2";
+
+/// Record of the kernels the snapshots were saved with, relative to the
+/// workspace.
+pub const RECORD_FILE: &str = ".ostool/snapshots.toml";
+
+/// VM snapshots, the `[snapshot]` section of `.qemu.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct SnapshotConfig {
+    /// qcow2 image holding the VM states, `target/qemu-snapshots.qcow2`
+    /// when unset
+    #[schemars(extend("format" = "file-path"))]
+    pub image: Option<String>,
+    /// Console line after which `--save-snapshot` saves the VM, e.g. a
+    /// shell prompt
+    pub save_regex: Option<String>,
+    /// Run QEMU with `-snapshot`: writes to the disks are discarded when it
+    /// exits
+    #[serde(default)]
+    pub discard_writes: bool,
+}
+
+impl SnapshotConfig {
+    /// Path of the image holding the VM states.
+    pub fn image_path(&self, workspace: &Path) -> PathBuf {
+        workspace.join(self.image.as_deref().unwrap_or(DEFAULT_IMAGE))
+    }
+}
+
+/// `-drive` arguments attaching the snapshot image at `path` without a
+/// guest device; `snapshot=off` keeps it writable under `-snapshot`.
+pub fn drive_args(path: &Path) -> [String; 2] {
+    // -drive 的参数中逗号要写成两个
+    let file = path.display().to_string().replace(',', ",,");
+    [
+        "-drive".to_string(),
+        format!("file={file},format=qcow2,if=none,id=snapshots,snapshot=off"),
+    ]
+}
+
+/// SHA-256 of the kernel file at `path`.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read.
+pub fn kernel_hash(path: &Path) -> std::io::Result<String> {
+    let data = std::fs::read(path)?;
+    Ok(format!("{:x}", Sha256::digest(&data)))
+}
+
+/// Kernels the snapshots were saved with, by snapshot name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Records {
+    #[serde(default)]
+    kernels: BTreeMap<String, String>,
+}
+
+impl Records {
+    /// Records of the workspace, empty if there are none or they cannot be
+    /// read.
+    pub fn load(workspace: &Path) -> Self {
+        std::fs::read_to_string(workspace.join(RECORD_FILE))
+            .ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the records to the workspace.
+    pub fn save(&self, workspace: &Path) -> anyhow::Result<()> {
+        let path = workspace.join(RECORD_FILE);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Notes that snapshot `name` was saved with the kernel `hash`.
+    pub fn insert(&mut self, name: &str, hash: String) {
+        self.kernels.insert(name.to_string(), hash);
+    }
+
+    /// Whether snapshot `name` was saved with the kernel `hash`; `None`
+    /// if it was never saved.
+    pub fn matches(&self, name: &str, hash: &str) -> Option<bool> {
+        self.kernels.get(name).map(|h| h == hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        let mut records = Records::load(&dir);
+        assert_eq!(records.matches("booted", "aa"), None);
+        records.insert("booted", "aa".into());
+        records.save(&dir).unwrap();
+
+        let records = Records::load(&dir);
+        assert_eq!(records.matches("booted", "aa"), Some(true));
+        assert_eq!(records.matches("booted", "bb"), Some(false));
+    }
+
+    #[test]
+    fn test_drive_args() {
+        let config = SnapshotConfig::default();
+        let path = config.image_path(Path::new("/w"));
+        assert_eq!(path, Path::new("/w/target/qemu-snapshots.qcow2"));
+        assert_eq!(
+            drive_args(&path)[1],
+            "file=/w/target/qemu-snapshots.qcow2,format=qcow2,if=none,id=snapshots,snapshot=off"
+        );
+    }
+}
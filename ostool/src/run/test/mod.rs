@@ -0,0 +1,522 @@
+//! Kernel test harness, `cargo test` for bare-metal kernels.
+//!
+//! `ostool test` builds the test binaries of the package with `cargo test
+//! --no-run` (a `custom_test_frameworks` runner), boots each of them in QEMU
+//! and reads the results from the serial output. The markers follow libtest
+//! unless configured in the `[test]` section of `.qemu.toml`:
+//!
+//! ```text
+//! test mm::alloc ... ok
+//! test sched::yield_now ... FAILED
+//! test result: FAILED. 1 passed; 1 failed; 0 ignored
+//! ```
+//!
+//! Like libtest, the kernel takes a name filter, `--exact` and `--list`
+//! (printing `<name>: test` per test) from its command line. With
+//! `--per-test` the tests are listed first and every one is booted on its
+//! own, so a crash or hang only fails the test that caused it.
+//!
+//! The results can also be written as JUnit XML and JSON, see [`report`].
+//! With a `[test.crash]` section a panicking kernel is frozen and a crash
+//! bundle captured through the gdbstub, see [`crash`](crate::run::crash).
+
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use jkconfig::t;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    build::config::BuildSystem,
+    ctx::AppContext,
+    exit::Failure,
+    run::{
+        crash::CrashConfig,
+        qemu::{BootEnd, QemuConfig, Watch, boot_qemu, boot_qemu_triaged, load_qemu_config},
+    },
+};
+
+/// JUnit XML and JSON test reports.
+pub mod report;
+
+const DEFAULT_TIMEOUT: u64 = 60;
+const DEFAULT_CASE_REGEX: &str = r"test (?P<name>\S+) \.\.\. (?P<result>ok|FAILED|ignored)\b";
+const DEFAULT_DONE_REGEX: &str = r"test result: ";
+const LIST_REGEX: &str = r"^(?P<name>\S+): test$";
+const LIST_END_REGEX: &str = r"^\d+ tests?, \d+ benchmarks?$";
+
+/// Test markers and timeout, the `[test]` section of a runner config.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct TestConfig {
+    /// Seconds a single test may take before it is failed, 60 when unset.
+    pub timeout: Option<u64>,
+    /// Regex matching a finished test, with `name` and `result` groups;
+    /// libtest's `test <name> ... ok` when unset. `ok`/`pass` and
+    /// `ignored`/`skip` results count as passed and ignored, any other
+    /// as failed.
+    pub case_regex: Option<String>,
+    /// Regex matching the end of the suite; libtest's `test result:` when unset.
+    pub done_regex: Option<String>,
+    /// Crash triage through the QEMU gdbstub, off when unset.
+    pub crash: Option<CrashConfig>,
+}
+
+/// Arguments for running the kernel tests.
+#[derive(Debug, Clone, Default)]
+pub struct RunTestArgs {
+    /// Optional path to the build configuration file.
+    pub build_config: Option<PathBuf>,
+    /// Optional path to QEMU configuration file.
+    pub qemu_config: Option<PathBuf>,
+    /// Only run the tests whose name contains this string.
+    pub filter: Option<String>,
+    /// Boot every test on its own instead of the whole suite at once.
+    pub per_test: bool,
+    /// Seconds a single test may take, overriding the config.
+    pub timeout: Option<u64>,
+    /// Print the serial output of passing tests too.
+    pub show_output: bool,
+    /// Write a JUnit XML report to this path.
+    pub junit: Option<PathBuf>,
+    /// Write a JSON report to this path.
+    pub json: Option<PathBuf>,
+}
+
+/// Result of a single test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+impl Outcome {
+    fn from_result(result: &str) -> Self {
+        match result.to_ascii_lowercase().as_str() {
+            "ok" | "pass" | "passed" => Self::Passed,
+            "ignored" | "skip" | "skipped" => Self::Ignored,
+            _ => Self::Failed,
+        }
+    }
+
+    fn label(self) -> colored::ColoredString {
+        match self {
+            Self::Passed => "ok".green(),
+            Self::Failed => "FAILED".red(),
+            Self::Ignored => "ignored".yellow(),
+        }
+    }
+}
+
+/// A finished test.
+#[derive(Debug, Clone)]
+pub struct TestCase {
+    pub name: String,
+    pub outcome: Outcome,
+    /// Time since the previous test finished, or since boot.
+    pub duration: Duration,
+    /// Serial output printed while the test ran.
+    pub output: String,
+}
+
+/// Results of one test binary.
+#[derive(Debug, Clone, Default)]
+pub struct SuiteReport {
+    /// Cargo target name of the test binary.
+    pub name: String,
+    pub cases: Vec<TestCase>,
+    /// Why the suite did not finish (timeout, crash), if it did not.
+    pub error: Option<String>,
+    /// Serial output after the last finished test.
+    pub output: String,
+    pub duration: Duration,
+}
+
+impl SuiteReport {
+    /// Number of tests with `outcome`.
+    pub fn count(&self, outcome: Outcome) -> usize {
+        self.cases.iter().filter(|c| c.outcome == outcome).count()
+    }
+
+    /// Whether the suite finished and no test failed.
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.count(Outcome::Failed) == 0
+    }
+}
+
+enum Marker {
+    Case { name: String, outcome: Outcome },
+    Done,
+    Other,
+}
+
+struct Markers {
+    case: Regex,
+    done: Regex,
+}
+
+impl Markers {
+    fn new(config: &TestConfig) -> anyhow::Result<Self> {
+        let case = config.case_regex.as_deref().unwrap_or(DEFAULT_CASE_REGEX);
+        let case = Regex::new(case).map_err(|e| anyhow!("test case regex error: {e}"))?;
+        if case
+            .capture_names()
+            .flatten()
+            .filter(|n| *n == "name" || *n == "result")
+            .count()
+            != 2
+        {
+            bail!("test case regex needs `name` and `result` groups: {case}");
+        }
+        let done = config.done_regex.as_deref().unwrap_or(DEFAULT_DONE_REGEX);
+        let done = Regex::new(done).map_err(|e| anyhow!("test done regex error: {e}"))?;
+        Ok(Self { case, done })
+    }
+
+    fn parse(&self, line: &str) -> Marker {
+        if let Some(caps) = self.case.captures(line) {
+            return Marker::Case {
+                name: caps["name"].to_string(),
+                outcome: Outcome::from_result(&caps["result"]),
+            };
+        }
+        if self.done.is_match(line) {
+            return Marker::Done;
+        }
+        Marker::Other
+    }
+}
+
+/// Everything one boot needs.
+struct Boot<'a> {
+    ctx: &'a AppContext,
+    config: &'a QemuConfig,
+    markers: &'a Markers,
+    crash: Option<&'a CrashConfig>,
+    timeout: Duration,
+    show_output: bool,
+}
+
+impl Boot<'_> {
+    /// Boots the test binary with `append` on the kernel command line and
+    /// collects the finished tests.
+    async fn run(&self, append: Option<String>) -> anyhow::Result<SuiteReport> {
+        let start = Instant::now();
+        let mut last = start;
+        let mut report = SuiteReport::default();
+        let end = boot_qemu_triaged(
+            self.ctx.clone(),
+            self.config.clone(),
+            append,
+            self.timeout,
+            self.crash,
+            |line| {
+                if self.show_output {
+                    println!("{line}");
+                }
+                match self.markers.parse(line) {
+                    Marker::Case { name, outcome } => {
+                        if !self.show_output {
+                            println!("test {name} ... {}", outcome.label());
+                        }
+                        report.cases.push(TestCase {
+                            name,
+                            outcome,
+                            duration: last.elapsed(),
+                            output: std::mem::take(&mut report.output),
+                        });
+                        last = Instant::now();
+                        Watch::ResetTimeout
+                    }
+                    Marker::Done => Watch::Stop,
+                    Marker::Other => {
+                        report.output.push_str(line);
+                        report.output.push('\n');
+                        Watch::Continue
+                    }
+                }
+            },
+        )
+        .await?;
+
+        report.error = match end {
+            BootEnd::Stopped => None,
+            BootEnd::TimedOut => Some(t!("test.timed_out", secs = self.timeout.as_secs())),
+            BootEnd::Failed { pattern, line } => {
+                report.output.push_str(&line);
+                report.output.push('\n');
+                Some(t!("test.fail_pattern", pattern = pattern))
+            }
+            BootEnd::Exited(status) => Some(t!("test.exited", status = status)),
+            BootEnd::Crashed { line, bundle } => {
+                report.output.push_str(&line);
+                report.output.push('\n');
+                Some(match bundle {
+                    Some(bundle) => t!("test.crashed", path = bundle.display()),
+                    None => t!("test.crashed_no_bundle").to_string(),
+                })
+            }
+        };
+        report.duration = start.elapsed();
+        Ok(report)
+    }
+
+    /// Boots the test binary with `--list` and collects the test names.
+    async fn list(&self, filter: Option<&str>) -> anyhow::Result<Vec<String>> {
+        let list = Regex::new(LIST_REGEX)?;
+        let list_end = Regex::new(LIST_END_REGEX)?;
+        let mut names = Vec::new();
+        let append = match filter {
+            Some(filter) => format!("--list {filter}"),
+            None => "--list".to_string(),
+        };
+        let end = boot_qemu(
+            self.ctx.clone(),
+            self.config.clone(),
+            Some(append),
+            self.timeout,
+            |line| {
+                if self.show_output {
+                    println!("{line}");
+                }
+                if let Some(caps) = list.captures(line) {
+                    names.push(caps["name"].to_string());
+                    return Watch::ResetTimeout;
+                }
+                if list_end.is_match(line) || self.markers.done.is_match(line) {
+                    return Watch::Stop;
+                }
+                Watch::Continue
+            },
+        )
+        .await?;
+        if names.is_empty()
+            && let BootEnd::TimedOut | BootEnd::Failed { .. } = end
+        {
+            bail!(t!("test.list_failed"));
+        }
+        names.retain(|name| filter.is_none_or(|f| name.contains(f)));
+        Ok(names)
+    }
+
+    /// Boots every test of the binary on its own.
+    async fn run_each(&self, filter: Option<&str>) -> anyhow::Result<SuiteReport> {
+        let start = Instant::now();
+        let mut report = SuiteReport::default();
+        for name in self.list(filter).await? {
+            let boot = self.run(Some(format!("{name} --exact"))).await?;
+            let case = boot.cases.into_iter().find(|c| c.name == name);
+            let case = match (case, boot.error) {
+                (Some(case), _) => case,
+                (None, error) => {
+                    let error = error.unwrap_or_else(|| t!("test.no_result").to_string());
+                    println!("test {name} ... {}", Outcome::Failed.label());
+                    TestCase {
+                        name,
+                        outcome: Outcome::Failed,
+                        duration: boot.duration,
+                        output: format!("{}{error}\n", boot.output),
+                    }
+                }
+            };
+            report.cases.push(case);
+        }
+        report.duration = start.elapsed();
+        Ok(report)
+    }
+}
+
+/// Builds the test binaries and runs them in QEMU, printing the results
+/// like libtest.
+///
+/// Failed tests do not make this fail; see [`ensure_passed`]. The reports
+/// requested in `args` are written once all suites ran.
+///
+/// # Errors
+///
+/// Returns an error if the build is not a Cargo build or fails, no test
+/// binary is built, or QEMU cannot be started.
+pub async fn run_tests(mut ctx: AppContext, args: RunTestArgs) -> anyhow::Result<Vec<SuiteReport>> {
+    let build = ctx
+        .prepare_build_config(args.build_config.clone(), false)
+        .await?;
+    let BuildSystem::Cargo(cargo) = &build.system else {
+        bail!(t!("test.needs_cargo"));
+    };
+    let binaries = ctx.cargo_test_build(cargo).await?;
+    if binaries.is_empty() {
+        bail!(t!("test.no_binaries"));
+    }
+
+    let mut reports = Vec::new();
+    for (name, path) in binaries {
+        let mut ctx = ctx.clone();
+        ctx.paths.artifacts = Default::default();
+        ctx.set_elf_path(path.clone()).await;
+
+        let config = load_qemu_config(&ctx, args.qemu_config.clone()).await?;
+        let test_config = config.test.clone().unwrap_or_default();
+        let markers = Markers::new(&test_config)?;
+        let timeout = args
+            .timeout
+            .or(test_config.timeout)
+            .unwrap_or(DEFAULT_TIMEOUT);
+        let boot = Boot {
+            ctx: &ctx,
+            config: &config,
+            markers: &markers,
+            crash: test_config.crash.as_ref(),
+            timeout: Duration::from_secs(timeout),
+            show_output: args.show_output,
+        };
+
+        println!(
+            "\n{}",
+            t!("test.running", name = name, path = path.display()).bold()
+        );
+        let mut report = if args.per_test {
+            boot.run_each(args.filter.as_deref()).await?
+        } else {
+            boot.run(args.filter.clone()).await?
+        };
+        report.name = name;
+        print_summary(&report, args.show_output);
+        reports.push(report);
+    }
+    report::write(&reports, args.junit.as_deref(), args.json.as_deref())?;
+    Ok(reports)
+}
+
+fn print_summary(report: &SuiteReport, show_output: bool) {
+    let failures = report
+        .cases
+        .iter()
+        .filter(|c| c.outcome == Outcome::Failed && !show_output)
+        .collect::<Vec<_>>();
+    if !failures.is_empty() || report.error.is_some() {
+        println!("\nfailures:\n");
+        for case in failures {
+            println!("---- {} ----\n{}", case.name, case.output);
+        }
+        if let Some(error) = &report.error {
+            if !show_output {
+                println!("---- {} ----\n{}", report.name, report.output);
+            }
+            println!("{}\n", error.red());
+        }
+    }
+    println!(
+        "test result: {}. {} passed; {} failed; {} ignored; finished in {:.2}s",
+        if report.passed() {
+            "ok".green()
+        } else {
+            "FAILED".red()
+        },
+        report.count(Outcome::Passed),
+        report.count(Outcome::Failed),
+        report.count(Outcome::Ignored),
+        report.duration.as_secs_f64()
+    );
+}
+
+/// Fails when a suite did not finish or a test failed.
+///
+/// # Errors
+///
+/// Returns an error naming the failed suites.
+pub fn ensure_passed(reports: &[SuiteReport]) -> anyhow::Result<()> {
+    let failed = reports
+        .iter()
+        .filter(|r| !r.passed())
+        .map(|r| r.name.as_str())
+        .collect::<Vec<_>>();
+    if !failed.is_empty() {
+        return Err(Failure::Test.error(t!("test.failed", suites = failed.join(", "))));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markers() {
+        let markers = Markers::new(&TestConfig::default()).unwrap();
+        let case = |line| match markers.parse(line) {
+            Marker::Case { name, outcome } => Some((name, outcome)),
+            _ => None,
+        };
+        assert_eq!(
+            case("[ 0.1] test mm::alloc ... ok"),
+            Some(("mm::alloc".to_string(), Outcome::Passed))
+        );
+        assert_eq!(
+            case("test sched::yield_now ... FAILED"),
+            Some(("sched::yield_now".to_string(), Outcome::Failed))
+        );
+        assert_eq!(case("test sched::yield_now ... "), None);
+        assert!(matches!(
+            markers.parse("test result: ok. 2 passed"),
+            Marker::Done
+        ));
+
+        let markers = Markers::new(&TestConfig {
+            case_regex: Some(r"\[(?P<result>PASS|SKIP|FAIL)\] (?P<name>\S+)".to_string()),
+            done_regex: Some("ALL TESTS DONE".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(matches!(
+            markers.parse("[SKIP] fs::mount"),
+            Marker::Case {
+                outcome: Outcome::Ignored,
+                ..
+            }
+        ));
+        assert!(matches!(markers.parse("ALL TESTS DONE"), Marker::Done));
+
+        // Both groups are required
+        assert!(
+            Markers::new(&TestConfig {
+                case_regex: Some(r"ok (?P<name>\S+)".to_string()),
+                ..Default::default()
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_ensure_passed() {
+        let case = |outcome| TestCase {
+            name: "t".to_string(),
+            outcome,
+            duration: Duration::ZERO,
+            output: String::new(),
+        };
+        let ok = SuiteReport {
+            name: "a".to_string(),
+            cases: vec![case(Outcome::Passed), case(Outcome::Ignored)],
+            ..Default::default()
+        };
+        assert!(ensure_passed(std::slice::from_ref(&ok)).is_ok());
+
+        let failed = SuiteReport {
+            name: "b".to_string(),
+            cases: vec![case(Outcome::Failed)],
+            ..Default::default()
+        };
+        let hung = SuiteReport {
+            name: "c".to_string(),
+            error: Some("timed out".to_string()),
+            ..Default::default()
+        };
+        let err = ensure_passed(&[ok, failed, hung]).unwrap_err().to_string();
+        assert!(err.contains("b, c"), "{err}");
+    }
+}
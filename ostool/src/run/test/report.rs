@@ -0,0 +1,219 @@
+//! JUnit XML and JSON test reports.
+//!
+//! CI systems such as GitLab and Jenkins display JUnit XML natively. Every
+//! test binary becomes a `<testsuite>`, every test a `<testcase>` with its
+//! duration and serial output; a suite that did not finish gets an extra
+//! `<testcase>` named after it with an `<error>`. Console color codes are
+//! stripped from the output.
+
+use std::{fmt::Write, fs, path::Path, sync::LazyLock, time::Duration};
+
+use anyhow::Context;
+use regex::Regex;
+use serde_json::{Value, json};
+
+use super::{Outcome, SuiteReport};
+
+/// Renders `reports` as JUnit XML.
+pub fn junit(reports: &[SuiteReport]) -> String {
+    let total = |f: fn(&SuiteReport) -> usize| reports.iter().map(f).sum::<usize>();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"ostool\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{}\">",
+        total(|r| r.cases.len() + errors(r)),
+        total(|r| r.count(Outcome::Failed)),
+        total(errors),
+        total(|r| r.count(Outcome::Ignored)),
+        secs(reports.iter().map(|r| r.duration).sum()),
+    );
+    for report in reports {
+        let suite = escape(&report.name);
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{suite}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{}\">",
+            report.cases.len() + errors(report),
+            report.count(Outcome::Failed),
+            errors(report),
+            report.count(Outcome::Ignored),
+            secs(report.duration),
+        );
+        for case in &report.cases {
+            let _ = write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{suite}\" time=\"{}\">",
+                escape(&case.name),
+                secs(case.duration),
+            );
+            match case.outcome {
+                Outcome::Passed => {}
+                Outcome::Failed => xml.push_str("<failure message=\"test failed\"/>"),
+                Outcome::Ignored => xml.push_str("<skipped/>"),
+            }
+            system_out(&mut xml, &case.output);
+            xml.push_str("</testcase>\n");
+        }
+        if let Some(error) = &report.error {
+            let _ = write!(
+                xml,
+                "    <testcase name=\"{suite}\" classname=\"{suite}\" time=\"0\"><error message=\"{}\"/>",
+                escape(error),
+            );
+            system_out(&mut xml, &report.output);
+            xml.push_str("</testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// Renders `reports` as JSON.
+pub fn json(reports: &[SuiteReport]) -> Value {
+    let suites = reports
+        .iter()
+        .map(|report| {
+            let cases = report
+                .cases
+                .iter()
+                .map(|case| {
+                    json!({
+                        "name": case.name,
+                        "outcome": match case.outcome {
+                            Outcome::Passed => "passed",
+                            Outcome::Failed => "failed",
+                            Outcome::Ignored => "ignored",
+                        },
+                        "duration": case.duration.as_secs_f64(),
+                        "output": case.output,
+                    })
+                })
+                .collect::<Vec<_>>();
+            json!({
+                "name": report.name,
+                "passed": report.passed(),
+                "error": report.error,
+                "duration": report.duration.as_secs_f64(),
+                "output": report.output,
+                "cases": cases,
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "passed": reports.iter().all(SuiteReport::passed),
+        "suites": suites,
+    })
+}
+
+/// Writes the JUnit XML and JSON reports to the given paths.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be written.
+pub fn write(
+    reports: &[SuiteReport],
+    junit_path: Option<&Path>,
+    json_path: Option<&Path>,
+) -> anyhow::Result<()> {
+    if let Some(path) = junit_path {
+        fs::write(path, junit(reports))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    if let Some(path) = json_path {
+        fs::write(path, serde_json::to_string_pretty(&json(reports))?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+fn errors(report: &SuiteReport) -> usize {
+    usize::from(report.error.is_some())
+}
+
+fn secs(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64())
+}
+
+fn system_out(xml: &mut String, output: &str) {
+    static ANSI: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\x1b\[[0-9;?]*[A-Za-z]").unwrap());
+    if !output.is_empty() {
+        let output = ANSI.replace_all(output, "");
+        let _ = write!(xml, "<system-out>{}</system-out>", escape(&output));
+    }
+}
+
+/// Escapes `s` for XML text and attributes, dropping the control
+/// characters XML 1.0 cannot hold.
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if c < ' ' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run::test::TestCase;
+
+    fn reports() -> Vec<SuiteReport> {
+        let case = |name: &str, outcome, output: &str| TestCase {
+            name: name.to_string(),
+            outcome,
+            duration: Duration::from_millis(1500),
+            output: output.to_string(),
+        };
+        vec![SuiteReport {
+            name: "kernel".to_string(),
+            cases: vec![
+                case("mm::alloc", Outcome::Passed, ""),
+                case(
+                    "fs::<root>",
+                    Outcome::Failed,
+                    "\x1b[31mpanic\x1b[0m at \"a & b\"\n",
+                ),
+                case("net", Outcome::Ignored, ""),
+            ],
+            error: Some("No test finished within 60s".to_string()),
+            output: "hang\n".to_string(),
+            duration: Duration::from_secs(3),
+        }]
+    }
+
+    #[test]
+    fn test_junit() {
+        let xml = junit(&reports());
+        assert!(xml.contains(
+            "<testsuite name=\"kernel\" tests=\"4\" failures=\"1\" errors=\"1\" skipped=\"1\" time=\"3.000\">"
+        ));
+        assert!(xml.contains(
+            "<testcase name=\"mm::alloc\" classname=\"kernel\" time=\"1.500\"></testcase>"
+        ));
+        assert!(xml.contains(
+            "<testcase name=\"fs::&lt;root&gt;\" classname=\"kernel\" time=\"1.500\"><failure message=\"test failed\"/><system-out>panic at &quot;a &amp; b&quot;\n</system-out></testcase>"
+        ));
+        assert!(xml.contains("<skipped/>"));
+        assert!(xml.contains(
+            "<error message=\"No test finished within 60s\"/><system-out>hang\n</system-out>"
+        ));
+    }
+
+    #[test]
+    fn test_json() {
+        let value = json(&reports());
+        assert_eq!(value["passed"], false);
+        assert_eq!(value["suites"][0]["cases"][1]["outcome"], "failed");
+        assert_eq!(value["suites"][0]["cases"][1]["duration"], 1.5);
+        assert_eq!(value["suites"][0]["error"], "No test finished within 60s");
+    }
+}
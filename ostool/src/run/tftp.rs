@@ -15,6 +15,7 @@
 use std::net::{IpAddr, Ipv4Addr};
 
 use colored::Colorize as _;
+use jkconfig::t;
 use tftpd::{Config, Server};
 
 use crate::ctx::AppContext;
@@ -53,15 +54,17 @@ pub fn run_tftp_server(app: &AppContext) -> anyhow::Result<()> {
     config.port = 69;
     config.ip_address = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
 
-    std::thread::spawn(move || {
-        let mut server = Server::new(&config)
-                .inspect_err(|e| {
-                    println!("{}", e);
-                    println!("{}","TFTP server 启动失败：{e:?}。若权限不足，尝试执行 `sudo setcap cap_net_bind_service=+eip $(which cargo-osrun)&&sudo setcap cap_net_bind_service=+eip $(which ostool)` 并重启终端".red());
-                    std::process::exit(1);
-                }).unwrap();
-        server.listen();
-    });
+    // 在当前线程绑定端口，失败时作为错误返回，而不是在后台线程中直接退出进程
+    let mut server = Server::new(&config).map_err(|e| {
+        println!("{}", e);
+        println!(
+            "{}",
+            t!("tftp.start_failed", error = format!("{e:?}")).red()
+        );
+        anyhow!("{e}")
+    })?;
+    // 服务线程随进程一同结束，Ctrl+C 时端口随之释放
+    std::thread::spawn(move || server.listen());
 
     Ok(())
 }
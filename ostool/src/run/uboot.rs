@@ -1,41 +1,65 @@
 use std::{
+    io::{Read, Write},
     path::{Path, PathBuf},
     sync::{Arc, Mutex},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use anyhow::Context;
 use byte_unit::Byte;
 use colored::Colorize;
-use fitimage::{ComponentConfig, FitImageBuilder, FitImageConfig};
-use indicatif::{ProgressBar, ProgressState, ProgressStyle};
-use jkconfig::data::app_data::default_schema_by_init;
+use fitimage::{
+    ComponentConfig, FitImageBuilder, FitImageConfig, HashAlgorithm, ImageOs, ImageType,
+    ScriptFormat, ScriptImage, fit::FitArch,
+};
+use jkconfig::{data::app_data::default_schema_by_init, t};
 use log::{info, warn};
 use network_interface::{Addr, NetworkInterface, NetworkInterfaceConfig};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tokio::fs;
-use uboot_shell::UbootShell;
-
-use crate::{ctx::AppContext, run::tftp, sterm::SerialTerm, utils::replace_env_placeholders};
-
-/// FIT image 生成相关的错误消息常量
-mod errors {
-    pub const KERNEL_READ_ERROR: &str = "读取 kernel 文件失败";
-    pub const DTB_READ_ERROR: &str = "读取 DTB 文件失败";
-    pub const FIT_BUILD_ERROR: &str = "构建 FIT image 失败";
-    pub const FIT_SAVE_ERROR: &str = "保存 FIT image 失败";
-    pub const DIR_ERROR: &str = "无法获取 kernel 文件目录";
-}
+use uboot_shell::{Console, EnvSnapshot, MemCompare, UbootShell};
+
+use crate::{
+    ctx::AppContext,
+    exit::Failure,
+    migrate, progress,
+    remote::{self, Action, RemoteConfig},
+    report::LastRun,
+    run::{
+        defmt,
+        deployed::{Blob, Deployed, Plan},
+        gdb_bridge::{self, GdbBridge},
+        memmap::{self, BoardInfo, Region},
+        rules::{ConsoleRules, Reaction},
+        tftp, usb,
+    },
+    sterm::{LineHook, SerialTerm, SessionLog, SessionLogConfig, ports},
+    utils::replace_env_placeholders,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct UbootConfig {
+    /// Layout version of the file, see [`migrate`](crate::migrate).
+    pub version: Option<u32>,
     /// Serial console device
     /// e.g., /dev/ttyUSB0 on linux, COM3 on Windows
     pub serial: String,
     pub baud_rate: String,
+    #[schemars(extend("format" = "file-path"))]
     pub dtb_file: Option<String>,
+    /// Device tree overlays applied on top of `dtb_file`, in order
+    #[serde(default)]
+    pub dtbo_files: Vec<String>,
+    /// Initial ramdisk packed into the FIT image, loaded to
+    /// `$ramdisk_addr_r` when U-Boot has it
+    #[schemars(extend("format" = "file-path"))]
+    pub ramdisk_file: Option<String>,
+    /// Add CRC32 and SHA-1 hashes to the FIT image and check them with
+    /// `iminfo` on the board before `bootm`
+    #[serde(default)]
+    pub verify_hash: bool,
     /// Kernel load address
     /// if not specified, use U-Boot env variable 'loadaddr'
     pub kernel_load_addr: Option<String>,
@@ -53,6 +77,44 @@ pub struct UbootConfig {
     pub success_regex: Vec<String>,
     pub fail_regex: Vec<String>,
     pub uboot_cmd: Option<Vec<String>>,
+    /// Boot script (`boot.scr`) generated next to the FIT image
+    pub boot_script: Option<BootScript>,
+    /// Board reached through `ostool agent` instead of a local serial port;
+    /// `serial`, `baud_rate` and the board commands are then the agent's
+    pub remote: Option<RemoteConfig>,
+    /// Transfer over USB DFU or fastboot instead of YMODEM/TFTP; the board's
+    /// USB port must be connected to this machine
+    pub usb: Option<usb::UsbConfig>,
+    /// Load the FIT image a second time behind the first and compare both
+    /// copies with `cmp.b` before booting
+    #[serde(default)]
+    pub verify_load: bool,
+    /// DRAM banks of the board, checked against the load addresses; read
+    /// from `bdinfo` when empty
+    #[serde(default)]
+    pub dram: Vec<memmap::DramBank>,
+    /// Skip checking the load addresses against DRAM and U-Boot before
+    /// loading
+    #[serde(default)]
+    pub skip_memory_check: bool,
+    /// Host file the board's whole U-Boot environment is saved to before
+    /// ostool changes it
+    #[schemars(extend("format" = "file-path"))]
+    pub env_snapshot: Option<String>,
+    /// After the run, reset the board and put the environment saved to
+    /// `env_snapshot` back with `saveenv`; needs `board_reset_cmd` or
+    /// `remote`
+    #[serde(default)]
+    pub restore_env: bool,
+    /// Always transfer the whole FIT image, even when the board still holds
+    /// it from the last run, see [`deployed`](crate::run::deployed)
+    #[serde(default)]
+    pub always_transfer: bool,
+    /// Copy the serial console of each run to a log file
+    pub session_log: Option<SessionLogConfig>,
+    /// Seconds `ostool run uboot --test` waits for a `success_regex`, 300
+    /// when unset
+    pub test_timeout: Option<u64>,
 }
 
 impl UbootConfig {
@@ -64,6 +126,72 @@ impl UbootConfig {
         self.addr_int(self.fit_load_addr.as_ref())
     }
 
+    /// Opens the serial console, locally or through the agent of `remote`.
+    pub fn open_console(&self) -> anyhow::Result<(Box<dyn Read + Send>, Box<dyn Write + Send>)> {
+        if let Some(remote) = &self.remote {
+            info!(
+                "Opening serial port of {} on agent {}",
+                remote.board, remote.agent
+            );
+            return remote::open_serial(remote);
+        }
+        let baud_rate = self.baud_rate_int()?;
+        info!("Opening serial port: {} @ {}", self.serial, baud_rate);
+        let rx = serialport::new(ports::normalize(&self.serial), baud_rate)
+            .timeout(Duration::from_millis(200))
+            .open()
+            .map_err(|e| anyhow!("Failed to open serial port: {e}"))?;
+        let tx = rx
+            .try_clone()
+            .map_err(|e| anyhow!("Failed to clone serial port: {e}"))?;
+        Ok((Box::new(rx), Box::new(tx)))
+    }
+
+    /// Whether the board can be reset, locally or through the agent.
+    pub fn can_reset(&self) -> bool {
+        self.remote.is_some()
+            || self
+                .board_reset_cmd
+                .as_ref()
+                .is_some_and(|cmd| !cmd.trim().is_empty())
+    }
+
+    /// Resets the board with `board_reset_cmd` or through the agent.
+    pub fn reset_board(&self, ctx: &AppContext) -> anyhow::Result<()> {
+        if let Some(remote) = &self.remote {
+            return remote::power(remote, Action::Reset);
+        }
+        if let Some(cmd) = &self.board_reset_cmd
+            && !cmd.trim().is_empty()
+        {
+            ctx.shell_run_cmd(cmd)?;
+        }
+        Ok(())
+    }
+
+    /// Powers the board off with `board_power_off_cmd` or through the agent.
+    ///
+    /// Returns whether there was a way to power it off.
+    pub fn power_off_board(&self, ctx: &AppContext) -> anyhow::Result<bool> {
+        if let Some(remote) = &self.remote {
+            remote::power(remote, Action::PowerOff)?;
+            return Ok(true);
+        }
+        match &self.board_power_off_cmd {
+            Some(cmd) if !cmd.trim().is_empty() => {
+                ctx.shell_run_cmd(cmd)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    pub fn baud_rate_int(&self) -> anyhow::Result<u32> {
+        self.baud_rate
+            .parse::<u32>()
+            .with_context(|| anyhow!("baud_rate is not valid int"))
+    }
+
     fn addr_int(&self, addr_str: Option<&String>) -> Option<u64> {
         addr_str.as_ref().and_then(|addr_str| {
             if addr_str.starts_with("0x") || addr_str.starts_with("0X") {
@@ -81,20 +209,215 @@ pub struct Net {
     pub board_ip: Option<String>,
     pub gatewayip: Option<String>,
     pub netmask: Option<String>,
+    #[schemars(extend("format" = "dir-path"))]
     pub tftp_dir: Option<String>,
 }
 
+/// U-Boot boot script, packaged like `mkimage -T script`
+#[derive(Default, Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct BootScript {
+    /// Script commands, one per line
+    #[serde(default)]
+    pub commands: Vec<String>,
+    /// Text script file, used instead of `commands` when set
+    #[schemars(extend("format" = "file-path"))]
+    pub file: Option<String>,
+    /// Output file name, defaults to `boot.scr`
+    pub output: Option<String>,
+    /// Image format
+    #[serde(default)]
+    pub format: BootScriptFormat,
+}
+
+#[derive(Default, Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BootScriptFormat {
+    /// Legacy image, `mkimage -T script`
+    #[default]
+    Legacy,
+    /// FIT image with a `script` image node
+    Fit,
+}
+
+impl BootScript {
+    /// Output file name, `boot.scr` unless configured.
+    pub fn file_name(&self) -> &str {
+        self.output.as_deref().unwrap_or("boot.scr")
+    }
+
+    /// Packages the script for the kernel's architecture.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the script file cannot be read or packaged.
+    pub async fn build(&self, ctx: &AppContext) -> anyhow::Result<Vec<u8>> {
+        let image = if let Some(ref file) = self.file {
+            let path = ctx.paths.workspace.join(file);
+            let text = fs::read_to_string(&path).await.map_err(|e| {
+                anyhow!(
+                    "{} {}: {}",
+                    t!("uboot.script_read_error"),
+                    path.display(),
+                    e
+                )
+            })?;
+            ScriptImage::new(text)
+        } else {
+            ScriptImage::from_commands(&self.commands)
+        };
+
+        image
+            .with_format(self.format.into())
+            .with_arch(ctx.image_arch()?)
+            .build()
+            .map_err(|e| anyhow!("{}: {}", t!("uboot.script_build_error"), e))
+    }
+}
+
+impl From<BootScriptFormat> for ScriptFormat {
+    fn from(format: BootScriptFormat) -> Self {
+        match format {
+            BootScriptFormat::Legacy => ScriptFormat::Legacy,
+            BootScriptFormat::Fit => ScriptFormat::Fit,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RunUbootArgs {
     pub config: Option<PathBuf>,
     pub show_output: bool,
+    /// Serial port overriding the configured one.
+    pub port: Option<String>,
+    /// Watch the console without a terminal until a `success_regex` or
+    /// `fail_regex` matches or the test timeout passes, for CI.
+    pub test: bool,
+    /// Seconds of a test run, overriding `test_timeout`.
+    pub timeout: Option<u64>,
 }
 
 pub async fn run_uboot(ctx: AppContext, args: RunUbootArgs) -> anyhow::Result<()> {
-    // Build logic will be implemented here
-    let config_path = match args.config.clone() {
+    let mut config = load_uboot_config(&ctx, args.config.clone()).await?;
+    select_port(&mut config, args.port)?;
+
+    let test = args.test.then(|| {
+        let secs = args
+            .timeout
+            .or(config.test_timeout)
+            .unwrap_or(DEFAULT_TEST_TIMEOUT);
+        Duration::from_secs(secs)
+    });
+    let mut runner = Runner {
+        ctx,
+        config,
+        rules: ConsoleRules::default(),
+        board: None,
+        test,
+    };
+    runner.run().await?;
+    Ok(())
+}
+
+/// What [`uboot_env`] does with the snapshot file.
+#[derive(Debug, Clone, Copy)]
+pub enum UbootEnvAction {
+    /// Save the board's environment to the file.
+    Save,
+    /// Put the environment of the file back on the board.
+    Restore,
+}
+
+#[derive(Debug, Clone)]
+pub struct UbootEnvArgs {
+    pub config: Option<PathBuf>,
+    pub action: UbootEnvAction,
+    /// Snapshot file overriding `env_snapshot`.
+    pub file: Option<PathBuf>,
+    /// Serial port overriding the configured one.
+    pub port: Option<String>,
+}
+
+/// Saves the board's U-Boot environment to a file or restores it from
+/// one, leaving the board in the U-Boot shell.
+///
+/// The board is reset first if it can be; otherwise it must already sit
+/// in U-Boot or be reset by hand.
+///
+/// # Errors
+///
+/// Returns an error if no snapshot file is given or configured, the file
+/// cannot be accessed, or a U-Boot command fails.
+pub async fn uboot_env(ctx: AppContext, args: UbootEnvArgs) -> anyhow::Result<()> {
+    let mut config = load_uboot_config(&ctx, args.config).await?;
+    select_port(&mut config, args.port)?;
+    let path = args
+        .file
+        .or_else(|| config.env_snapshot.as_ref().map(PathBuf::from))
+        .ok_or_else(|| anyhow!(t!("uboot_env.no_snapshot")))?;
+
+    println!("{}", t!("uboot.waiting"));
+    let mut uboot = connect(&ctx, &config)?;
+    match args.action {
+        UbootEnvAction::Save => save_env(&mut uboot, &path),
+        UbootEnvAction::Restore => restore_env(&mut uboot, &path),
+    }
+}
+
+/// Uses `port` as the serial port, or resolves the configured one.
+fn select_port(config: &mut UbootConfig, port: Option<String>) -> anyhow::Result<()> {
+    if let Some(port) = port {
+        config.serial = port;
+    } else if config.remote.is_none() {
+        config.serial = ports::resolve(&config.serial, config.baud_rate_int()?)?;
+    }
+    Ok(())
+}
+
+/// One board of a parallel run.
+pub(crate) struct BoardRun {
+    /// Board name, also used for the FIT image name.
+    pub name: String,
+    /// Prefix of every console line.
+    pub prefix: String,
+    /// Time the kernel may take to match a `success_regex`.
+    pub timeout: Duration,
+}
+
+/// Boots the kernel on one board of a parallel run without a terminal.
+///
+/// The console is printed line by line with the board prefix until a
+/// `success_regex` or `fail_regex` matches or the timeout passes. The
+/// TFTP server must already be running.
+pub(crate) async fn run_board(
+    ctx: AppContext,
+    config: UbootConfig,
+    board: BoardRun,
+) -> anyhow::Result<()> {
+    let mut runner = Runner {
+        ctx,
+        config,
+        rules: ConsoleRules::default(),
+        board: Some(board),
+        test: None,
+    };
+    runner.run().await
+}
+
+/// Loads the U-Boot configuration, by default from `.uboot.toml` in the
+/// workspace, and writes its JSON schema next to it.
+///
+/// A missing file is created with a default serial port.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, parsed or created.
+pub async fn load_uboot_config(
+    ctx: &AppContext,
+    config: Option<PathBuf>,
+) -> anyhow::Result<UbootConfig> {
+    let config_path = match config {
         Some(path) => path,
-        None => ctx.paths.workspace.join(".uboot.toml"),
+        None => ctx.paths.config_file(".uboot.toml"),
     };
 
     let schema_path = default_schema_by_init(&config_path);
@@ -108,17 +431,19 @@ pub async fn run_uboot(ctx: AppContext, args: RunUbootArgs) -> anyhow::Result<()
     // let app_data = AppData::new(Some(&config_path), Some(schema_path))?;
 
     let config = if config_path.exists() {
-        println!("Using U-Boot config: {}", config_path.display());
+        info!("{}", t!("uboot.using_config", path = config_path.display()));
         let mut config_content = fs::read_to_string(&config_path)
             .await
             .map_err(|_| anyhow!("can not open config file: {}", config_path.display()))?;
 
-        config_content = replace_env_placeholders(&config_content)?;
+        config_content = migrate::upgrade(&config_path, &config_content, migrate::UBOOT)?;
+        config_content = ctx.value_replace_with_var(replace_env_placeholders(&config_content)?);
 
-        let config: UbootConfig = toml::from_str(&config_content)?;
+        let config: UbootConfig = ctx.parse_config(&config_path, &config_content)?;
         config
     } else {
         let config = UbootConfig {
+            version: Some(migrate::current_version(migrate::UBOOT)),
             serial: "/dev/ttyUSB0".to_string(),
             baud_rate: "115200".into(),
             ..Default::default()
@@ -127,29 +452,244 @@ pub async fn run_uboot(ctx: AppContext, args: RunUbootArgs) -> anyhow::Result<()
         fs::write(&config_path, toml::to_string_pretty(&config)?).await?;
         config
     };
+    Ok(config)
+}
 
-    let baud_rate = config
-        .baud_rate
-        .parse::<u32>()
-        .with_context(|| anyhow!("baud_rate is not valid int"))?;
+/// Components of a FIT image made by [`make_fit_image`].
+#[derive(Debug, Clone, Default)]
+pub struct FitSpec {
+    /// Kernel image, compressed into the FIT image.
+    pub kernel: PathBuf,
+    /// Device tree blob.
+    pub dtb: Option<PathBuf>,
+    /// Device tree overlays applied on top of `dtb`, in order.
+    pub dtbo: Vec<PathBuf>,
+    /// Address the kernel is loaded to.
+    pub kernel_load_addr: u64,
+    /// Entry point of the kernel.
+    pub kernel_entry_addr: u64,
+    /// Address the device tree is loaded to, U-Boot picks one when unset.
+    pub fdt_load_addr: Option<u64>,
+    /// Initial ramdisk.
+    pub ramdisk: Option<PathBuf>,
+    /// Address the ramdisk is loaded to, used in place when unset.
+    pub ramdisk_load_addr: Option<u64>,
+    /// Hashes added to every component, checked by U-Boot.
+    pub hashes: Vec<HashAlgorithm>,
+}
 
-    let mut runner = Runner {
-        ctx,
-        config,
-        baud_rate,
-        success_regex: vec![],
-        fail_regex: vec![],
+/// Makes a FIT image with one configuration holding the kernel, device
+/// tree, overlays and ramdisk of `spec`, and writes it to `output_path`.
+///
+/// # Errors
+///
+/// Returns an error if a component cannot be read, overlays come without a
+/// device tree, or the image cannot be built or written.
+pub async fn make_fit_image(
+    ctx: &AppContext,
+    spec: &FitSpec,
+    output_path: &Path,
+) -> anyhow::Result<()> {
+    info!("Making FIT image...");
+    let kernel_path = &spec.kernel;
+    // 读取 kernel 数据
+    let kernel_data = fs::read(kernel_path).await.map_err(|e| {
+        anyhow!(
+            "{} {}: {}",
+            t!("uboot.kernel_read_error"),
+            kernel_path.display(),
+            e
+        )
+    })?;
+
+    info!(
+        "kernel: {} (size: {:.2})",
+        kernel_path.display(),
+        Byte::from(kernel_data.len())
+    );
+
+    // U-Boot 尚未为 LoongArch 分配架构编号，使用自定义属性值
+    let arch = match ctx.arch {
+        Some(object::Architecture::LoongArch64) => FitArch::custom("loongarch64"),
+        _ => ctx.image_arch()?.into(),
     };
-    runner.run().await?;
+
+    // 创建配置，与 test.its 文件中的参数一致
+    let mut config = FitImageConfig::new("Various kernels, ramdisks and FDT blobs").with_kernel(
+        ComponentConfig::new("kernel", kernel_data)
+            .with_description("This kernel")
+            .with_type(ImageType::Kernel)
+            .with_arch(arch.clone())
+            .with_os(ImageOs::Linux)
+            .with_compression(true)
+            .with_load_address(spec.kernel_load_addr)
+            .with_entry_point(spec.kernel_entry_addr),
+    );
+    let mut fdt_name = None;
+    let mut ramdisk_name = None;
+
+    // 处理 DTB 文件
+    if let Some(dtb_path) = &spec.dtb {
+        match fs::read(dtb_path).await {
+            Ok(data) => {
+                let size = format!("{:.2}", Byte::from(data.len()));
+                info!(
+                    "{}",
+                    t!("uboot.dtb_loaded", path = dtb_path.display(), size = size)
+                );
+                fdt_name = Some("fdt");
+
+                // Can not compress DTB, U-Boot will not accept it
+                let mut fdt_config = ComponentConfig::new("fdt", data.clone())
+                    .with_description("This fdt")
+                    .with_type(ImageType::FlatDt)
+                    .with_arch(arch.clone());
+
+                if let Some(addr) = spec.fdt_load_addr {
+                    fdt_config = fdt_config.with_load_address(addr);
+                }
+
+                config = config.with_fdt(fdt_config);
+            }
+            Err(e) => {
+                return Err(anyhow!(
+                    "{} {}: {}",
+                    t!("uboot.dtb_read_error"),
+                    dtb_path.display(),
+                    e
+                ));
+            }
+        }
+    } else {
+        warn!("{}", t!("uboot.no_dtb"));
+    }
+
+    let mut overlay_names = Vec::new();
+    if !spec.dtbo.is_empty() && fdt_name.is_none() {
+        bail!(t!("uboot.dtbo_needs_dtb"));
+    }
+    for (i, dtbo) in spec.dtbo.iter().enumerate() {
+        let data = fs::read(dtbo)
+            .await
+            .map_err(|e| anyhow!("{} {}: {}", t!("uboot.dtb_read_error"), dtbo.display(), e))?;
+        let size = format!("{:.2}", Byte::from(data.len()));
+        info!(
+            "{}",
+            t!("uboot.dtbo_loaded", path = dtbo.display(), size = size)
+        );
+        let name = format!("fdt-overlay-{}", i + 1);
+        config = config.with_overlay(
+            ComponentConfig::new(&name, data)
+                .with_description(dtbo.display().to_string())
+                .with_type(ImageType::FlatDt)
+                .with_arch(arch.clone()),
+        );
+        overlay_names.push(name);
+    }
+
+    if let Some(ramdisk) = &spec.ramdisk {
+        let data = fs::read(ramdisk).await.map_err(|e| {
+            anyhow!(
+                "{} {}: {}",
+                t!("uboot.ramdisk_read_error"),
+                ramdisk.display(),
+                e
+            )
+        })?;
+        let size = format!("{:.2}", Byte::from(data.len()));
+        info!(
+            "{}",
+            t!(
+                "uboot.ramdisk_loaded",
+                path = ramdisk.display(),
+                size = size
+            )
+        );
+        // ramdisk 通常已自行压缩
+        let mut ramdisk_config = ComponentConfig::new("ramdisk", data)
+            .with_description(ramdisk.display().to_string())
+            .with_type(ImageType::Ramdisk)
+            .with_arch(arch.clone())
+            .with_os(ImageOs::Linux);
+        if let Some(addr) = spec.ramdisk_load_addr {
+            ramdisk_config = ramdisk_config.with_load_address(addr);
+        }
+        config = config.with_ramdisk(ramdisk_config);
+        ramdisk_name = Some("ramdisk");
+    }
+
+    if !spec.hashes.is_empty() {
+        let with_hashes = |mut component: ComponentConfig| {
+            for algorithm in &spec.hashes {
+                component = component.with_hash(algorithm.clone());
+            }
+            component
+        };
+        config.kernel = config.kernel.map(with_hashes);
+        config.fdt = config.fdt.map(with_hashes);
+        config.overlays = config.overlays.into_iter().map(with_hashes).collect();
+        config.ramdisk = config.ramdisk.map(with_hashes);
+    }
+
+    config = config
+        .with_default_config("config-ostool")
+        .with_configuration(
+            "config-ostool",
+            "ostool configuration",
+            Some("kernel"),
+            fdt_name,
+            ramdisk_name,
+        )
+        .with_configuration_overlays("config-ostool", overlay_names);
+
+    // 使用新的 mkimage API 构建 FIT image
+    let mut builder = FitImageBuilder::new();
+    let fit_data = builder
+        .build(config)
+        .map_err(|e| anyhow!("{}: {}", t!("uboot.fit_build_error"), e))?;
+
+    // 保存到文件
+    fs::write(output_path, fit_data).await.map_err(|e| {
+        anyhow!(
+            "{} {}: {}",
+            t!("uboot.fit_save_error"),
+            output_path.display(),
+            e
+        )
+    })?;
+
+    info!("FIT image ok: {}", output_path.display());
     Ok(())
 }
 
+/// Puts `iminfo` before the `bootm` of `bootcmd`, so U-Boot checks the
+/// hashes of the FIT image and does not boot it if one is wrong.
+fn check_hashes(bootcmd: &str) -> String {
+    let Some(pos) = bootcmd.rfind("bootm") else {
+        return bootcmd.to_string();
+    };
+    let (before, bootm) = bootcmd.split_at(pos);
+    // 只检查 FIT image，`bootm <fit> - <fdt>` 后面的参数不是镜像
+    match bootm.split_whitespace().nth(1) {
+        Some(addr) => format!("{before}iminfo {addr} && {bootm}"),
+        None => format!("{before}iminfo && {bootm}"),
+    }
+}
+
+/// `--test` 运行默认的超时秒数
+const DEFAULT_TEST_TIMEOUT: u64 = 300;
+
+/// 校验加载时单次 TFTP/DHCP 下载的超时
+const LOAD_TIMEOUT: Duration = Duration::from_secs(120);
+
 struct Runner {
     ctx: AppContext,
     config: UbootConfig,
-    success_regex: Vec<regex::Regex>,
-    fail_regex: Vec<regex::Regex>,
-    baud_rate: u32,
+    rules: ConsoleRules,
+    /// 多板并行运行时的板子，`None` 时进入交互终端
+    board: Option<BoardRun>,
+    /// 测试模式的超时，此时不进入交互终端
+    test: Option<Duration>,
 }
 
 impl Runner {
@@ -169,132 +709,76 @@ impl Runner {
         kernel_load_addr: u64,
         kernel_entry_addr: u64,
         fdt_load_addr: Option<u64>,
-        _ramfs_load_addr: Option<u64>,
+        ramfs_load_addr: Option<u64>,
     ) -> anyhow::Result<PathBuf> {
-        info!("Making FIT image...");
-        // 生成压缩的 FIT image
-        let output_dir = kernel_path
-            .parent()
-            .and_then(|p| p.to_str())
-            .ok_or(anyhow!(errors::DIR_ERROR))?;
-
-        // 读取 kernel 数据
-        let kernel_data = fs::read(kernel_path).await.map_err(|e| {
-            anyhow!(
-                "{} {}: {}",
-                errors::KERNEL_READ_ERROR,
-                kernel_path.display(),
-                e
-            )
-        })?;
-
-        info!(
-            "kernel: {} (size: {:.2})",
-            kernel_path.display(),
-            Byte::from(kernel_data.len())
-        );
-
-        let arch = match self.ctx.arch.as_ref().unwrap() {
-            object::Architecture::Aarch64 => "arm64",
-            object::Architecture::Arm => "arm",
-            object::Architecture::LoongArch64 => "loongarch64",
-            _ => todo!(),
+        let output_dir = kernel_path.parent().ok_or(anyhow!(t!("uboot.dir_error")))?;
+        // 多板并行时每块板子的 DTB 不同，使用各自的文件名
+        let fit_name = match &self.board {
+            Some(board) => format!("image-{}.fit", board.name),
+            None => "image.fit".to_string(),
         };
+        let output_path = output_dir.join(fit_name);
+        let spec = FitSpec {
+            kernel: kernel_path.to_path_buf(),
+            dtb: dtb_path.map(Path::to_path_buf),
+            dtbo: self.config.dtbo_files.iter().map(PathBuf::from).collect(),
+            kernel_load_addr,
+            kernel_entry_addr,
+            fdt_load_addr,
+            ramdisk: self.config.ramdisk_file.as_ref().map(PathBuf::from),
+            ramdisk_load_addr: ramfs_load_addr,
+            hashes: if self.config.verify_hash {
+                vec![HashAlgorithm::Crc32, HashAlgorithm::Sha1]
+            } else {
+                Vec::new()
+            },
+        };
+        make_fit_image(&self.ctx, &spec, &output_path).await?;
+        Ok(output_path)
+    }
 
-        // 创建配置，与 test.its 文件中的参数一致
-        let mut config = FitImageConfig::new("Various kernels, ramdisks and FDT blobs")
-            .with_kernel(
-                ComponentConfig::new("kernel", kernel_data)
-                    .with_description("This kernel")
-                    .with_type("kernel")
-                    .with_arch(arch)
-                    .with_os("linux")
-                    .with_compression(true)
-                    .with_load_address(kernel_load_addr)
-                    .with_entry_point(kernel_entry_addr),
-            );
-        let mut fdt_name = None;
-
-        // 处理 DTB 文件
-        if let Some(dtb_path) = dtb_path {
-            match fs::read(dtb_path).await {
-                Ok(data) => {
-                    info!(
-                        "已读取 DTB 文件: {} (大小: {:.2})",
-                        dtb_path.display(),
-                        Byte::from(data.len())
-                    );
-                    fdt_name = Some("fdt");
-
-                    // Can not compress DTB, U-Boot will not accept it
-                    let mut fdt_config = ComponentConfig::new("fdt", data.clone())
-                        .with_description("This fdt")
-                        .with_type("flat_dt")
-                        .with_arch(arch);
-
-                    if let Some(addr) = fdt_load_addr {
-                        fdt_config = fdt_config.with_load_address(addr);
-                    }
-
-                    config = config.with_fdt(fdt_config);
-                }
-                Err(e) => {
-                    return Err(anyhow!(
-                        "{} {}: {}",
-                        errors::DTB_READ_ERROR,
-                        dtb_path.display(),
-                        e
-                    ));
-                }
-            }
-        } else {
-            warn!("未指定 DTB 文件，将生成仅包含 kernel 的 FIT image");
+    /// 根据配置生成 U-Boot 启动脚本 (boot.scr)
+    ///
+    /// 脚本写入 `output_dir`，若配置了 `net.tftp_dir` 则同时复制到该目录。
+    async fn generate_boot_script(
+        &mut self,
+        script: &BootScript,
+        output_dir: &Path,
+    ) -> anyhow::Result<PathBuf> {
+        let data = script.build(&self.ctx).await?;
+        let name = script.file_name();
+        let output_path = output_dir.join(name);
+        fs::write(&output_path, &data).await?;
+        info!("Boot script ok: {}", output_path.display());
+
+        if let Some(tftp_dir) = self.config.net.as_ref().and_then(|n| n.tftp_dir.as_ref()) {
+            let tftp_path = Path::new(tftp_dir).join(name);
+            fs::write(&tftp_path, &data).await?;
+            info!("Boot script copied to: {}", tftp_path.display());
         }
 
-        config = config
-            .with_default_config("config-ostool")
-            .with_configuration(
-                "config-ostool",
-                "ostool configuration",
-                Some("kernel"),
-                fdt_name,
-                None::<String>,
-            );
-
-        // 使用新的 mkimage API 构建 FIT image
-        let mut builder = FitImageBuilder::new();
-        let fit_data = builder
-            .build(config)
-            .map_err(|e| anyhow!("{}: {}", errors::FIT_BUILD_ERROR, e))?;
-
-        // 保存到文件
-        let output_path = Path::new(output_dir).join("image.fit");
-        fs::write(&output_path, fit_data).await.map_err(|e| {
-            anyhow!(
-                "{} {}: {}",
-                errors::FIT_SAVE_ERROR,
-                output_path.display(),
-                e
-            )
-        })?;
-
-        info!("FIT image ok: {}", output_path.display());
+        self.ctx.paths.artifacts.boot_script = Some(output_path.clone());
         Ok(output_path)
     }
 
     async fn run(&mut self) -> anyhow::Result<()> {
-        let res = self._run().await;
-        if let Some(ref cmd) = self.config.board_power_off_cmd
-            && !cmd.trim().is_empty()
+        let mut res = self._run().await;
+        if self.config.restore_env
+            && let Err(e) = self.restore_env()
         {
-            let _ = self.ctx.shell_run_cmd(cmd);
+            warn!("{e:#}");
+            if res.is_ok() {
+                res = Err(e);
+            }
+        }
+        if let Ok(true) = self.config.power_off_board(&self.ctx) {
             info!("Board powered off");
         }
         res
     }
 
     async fn _run(&mut self) -> anyhow::Result<()> {
-        self.preper_regex()?;
+        self.prepare_rules()?;
         self.ctx.objcopy_output_bin()?;
 
         let kernel = self
@@ -302,7 +786,7 @@ impl Runner {
             .paths
             .artifacts
             .bin
-            .as_ref()
+            .clone()
             .ok_or(anyhow!("bin not exist"))?;
 
         info!("Starting U-Boot runner...");
@@ -318,39 +802,22 @@ impl Runner {
             .and_then(|net| net.tftp_dir.as_ref())
             .is_some();
 
-        if !is_tftp && let Some(ip) = ip_string.as_ref() {
+        if !is_tftp
+            && self.board.is_none()
+            && let Some(ip) = ip_string.as_ref()
+        {
             info!("TFTP server IP: {}", ip);
             tftp::run_tftp_server(&self.ctx)?;
         }
 
-        info!(
-            "Opening serial port: {} @ {}",
-            self.config.serial, self.baud_rate
-        );
-
-        let rx = serialport::new(&self.config.serial, self.baud_rate as _)
-            .timeout(Duration::from_millis(200))
-            .open()
-            .map_err(|e| anyhow!("Failed to open serial port: {e}"))?;
-        let tx = rx
-            .try_clone()
-            .map_err(|e| anyhow!("Failed to clone serial port: {e}"))?;
-
-        println!("Waiting for board on power or reset...");
-        let handle: thread::JoinHandle<anyhow::Result<UbootShell>> = thread::spawn(move || {
-            let uboot = UbootShell::new(tx, rx)?;
-            Ok(uboot)
-        });
-
-        if let Some(cmd) = self.config.board_reset_cmd.clone()
-            && !cmd.trim().is_empty()
-        {
-            self.ctx.shell_run_cmd(&cmd)?;
-        }
+        println!("{}", t!("uboot.waiting"));
+        let mut uboot = connect(&self.ctx, &self.config)?;
 
         let mut net_ok = false;
 
-        let mut uboot = handle.join().unwrap()?;
+        if let Some(path) = &self.config.env_snapshot {
+            save_env(&mut uboot, Path::new(path))?;
+        }
         uboot.set_env("autoload", "yes")?;
 
         if let Some(ref cmds) = self.config.uboot_cmd {
@@ -376,7 +843,8 @@ impl Runner {
             let device_list = output.strip_prefix("net list").unwrap_or(&output).trim();
 
             if device_list.is_empty() {
-                let _ = uboot.cmd("bootdev hunt ethernet");
+                // 探测网卡可能超过默认的 5 秒读超时
+                let _ = uboot.cmd_with_timeout("bootdev hunt ethernet", Duration::from_secs(30));
             }
 
             info!("Board network ok");
@@ -437,7 +905,7 @@ impl Runner {
         let dtb_path = dtb.as_ref().map(Path::new);
         let fitimage = self
             .generate_fit_image(
-                kernel,
+                &kernel,
                 dtb_path,
                 kernel_entry,
                 kernel_entry,
@@ -446,6 +914,43 @@ impl Runner {
             )
             .await?;
 
+        if !self.config.skip_memory_check {
+            let mut loads = vec![Region::new(
+                "FIT",
+                fit_loadaddr,
+                fs::metadata(&fitimage).await?.len(),
+            )];
+            if self.config.verify_load {
+                let copy = Self::copy_addr(fit_loadaddr, loads[0].size);
+                loads.push(Region::new("FIT copy", copy, loads[0].size));
+            }
+            loads.push(Region::new(
+                "kernel",
+                kernel_entry,
+                fs::metadata(&kernel).await?.len(),
+            ));
+            if let (Some(addr), Some(dtb)) = (fdt_load_addr, &dtb) {
+                let mut size = fs::metadata(dtb).await?.len();
+                for dtbo in &self.config.dtbo_files {
+                    size += fs::metadata(dtbo).await?.len();
+                }
+                loads.push(Region::new("fdt", addr, size));
+            }
+            if let (Some(addr), Some(ramdisk)) = (ramfs_load_addr, &self.config.ramdisk_file) {
+                loads.push(Region::new(
+                    "ramdisk",
+                    addr,
+                    fs::metadata(ramdisk).await?.len(),
+                ));
+            }
+            self.check_memory(&mut uboot, &loads)?;
+        }
+
+        if let Some(script) = self.config.boot_script.clone() {
+            let output_dir = fitimage.parent().ok_or(anyhow!(t!("uboot.dir_error")))?;
+            self.generate_boot_script(&script, output_dir).await?;
+        }
+
         let fitname = if is_tftp {
             let tftp_dir = self
                 .config
@@ -469,20 +974,119 @@ impl Runner {
             name.to_string()
         };
 
-        let bootcmd =
-            if let Some(ref board_ip) = self.config.net.as_ref().and_then(|e| e.board_ip.clone()) {
-                uboot.set_env("ipaddr", board_ip)?;
+        let last_name = match &self.board {
+            Some(board) => format!("uboot-{}", board.name),
+            None => "uboot".to_string(),
+        };
+        let mut dtbs = Vec::new();
+        if let Some(dtb) = &dtb {
+            dtbs.push(fs::read(dtb).await?);
+            for dtbo in &self.config.dtbo_files {
+                dtbs.push(fs::read(dtbo).await?);
+            }
+        }
+        let current = Deployed::new(
+            fit_loadaddr,
+            &fs::read(&fitimage).await?,
+            &dtbs,
+            fdt_load_addr,
+        )?;
+        let workspace = self.ctx.paths.workspace.clone();
+        // `bootm <fit> - <fdt>` 会跳过 ramdisk
+        let plan = if self.config.usb.is_some()
+            || self.config.always_transfer
+            || self.config.ramdisk_file.is_some()
+        {
+            Plan::Full
+        } else {
+            let last = Deployed::load(&workspace, &last_name);
+            Self::plan_deploy(&mut uboot, last.as_ref(), &current)
+        };
+
+        let bootcmd = if let Plan::Reuse { fit, .. } = plan {
+            println!(
+                "{}",
+                t!("uboot.image_reused", addr = format!("{:#x}", fit.addr)).green()
+            );
+            plan.bootcmd().unwrap()
+        } else if let Plan::Dtb { fdt, .. } = plan {
+            println!(
+                "{}",
+                t!("uboot.dtb_only", addr = format!("{:#x}", fdt.addr)).green()
+            );
+            // 记录先删掉，传输失败时下次整体传输
+            Deployed::forget(&workspace, &last_name);
+            let dtb = dtb.as_ref().map(Path::new).unwrap();
+            self.load_dtb(&mut uboot, fdt, dtb, &fitimage, net_ok)
+                .await?;
+            if let Some(record) = plan.record(&current) {
+                record.save(&workspace, &last_name)?;
+            }
+            plan.bootcmd().unwrap()
+        } else if let Some(ref usb) = self.config.usb {
+            if self.config.remote.is_some() {
+                bail!(t!("usb.remote"));
+            }
+            usb::load(&self.ctx, usb, &mut uboot, fit_loadaddr, &fitimage)?;
+            "bootm".to_string()
+        } else if let Some(ref board_ip) = self.config.net.as_ref().and_then(|e| e.board_ip.clone())
+        {
+            uboot.set_env("ipaddr", board_ip)?;
+            if self.config.verify_load {
+                self.verify_load(&mut uboot, fit_loadaddr, &fitimage, |uboot, addr| {
+                    let pb = self.spinner(format!("TFTP {fitname}"));
+                    uboot.cmd_with_timeout(&format!("tftp {addr:#x} {fitname}"), LOAD_TIMEOUT)?;
+                    pb.finish();
+                    Ok(())
+                })?;
+                format!("bootm {fit_loadaddr:#x}")
+            } else {
                 format!("tftp {fitname} && bootm",)
-            } else if net_ok {
+            }
+        } else if net_ok {
+            if self.config.verify_load {
+                self.verify_load(&mut uboot, fit_loadaddr, &fitimage, |uboot, addr| {
+                    let pb = self.spinner(format!("DHCP {fitname}"));
+                    uboot.cmd_with_timeout(&format!("dhcp {addr:#x} {fitname}"), LOAD_TIMEOUT)?;
+                    pb.finish();
+                    Ok(())
+                })?;
+                format!("bootm {fit_loadaddr:#x}")
+            } else {
                 format!("dhcp {fitname} && bootm",)
+            }
+        } else {
+            info!("No TFTP config, using loady to upload FIT image...");
+            if self.config.verify_load {
+                self.verify_load(&mut uboot, fit_loadaddr, &fitimage, |uboot, addr| {
+                    self.uboot_loady(uboot, addr as usize, &fitimage);
+                    Ok(())
+                })?;
+                format!("bootm {fit_loadaddr:#x}")
             } else {
-                info!("No TFTP config, using loady to upload FIT image...");
-                Self::uboot_loady(&mut uboot, fit_loadaddr as usize, fitimage);
+                self.uboot_loady(&mut uboot, fit_loadaddr as usize, fitimage);
                 "bootm".to_string()
-            };
+            }
+        };
+        if plan == Plan::Full
+            && let Some(record) = plan.record(&current)
+        {
+            record.save(&workspace, &last_name)?;
+        }
+        let bootcmd = if self.config.verify_hash {
+            check_hashes(&bootcmd)
+        } else {
+            bootcmd
+        };
+
+        let mut last_run = LastRun::start(&self.ctx, &last_name);
+        last_run.note(&format!(
+            "serial {} @ {}\nbootcmd: {bootcmd}",
+            self.config.serial, self.config.baud_rate
+        ));
 
         info!("Booting kernel with command: {}", bootcmd);
-        uboot.cmd_without_reply(&bootcmd)?;
+        let console = uboot.boot(&bootcmd)?;
         // if self.config.net.is_some() {
         //     info!("TFTP upload FIT image to board...");
         //     let filename = fitimage.file_name().unwrap().to_str().unwrap();
@@ -496,40 +1100,95 @@ impl Runner {
         //     uboot.cmd_without_reply("bootm")?;
         // }
 
-        let tx = uboot.tx.take().unwrap();
-        let rx = uboot.rx.take().unwrap();
-
-        drop(uboot);
+        let (tx, rx) = if self.ctx.debug && self.board.is_none() {
+            let bridge = GdbBridge::bind(gdb_bridge::DEFAULT_ADDRESS)?;
+            println!(
+                "{}",
+                t!("gdb_bridge.listening", addr = gdb_bridge::DEFAULT_ADDRESS).green()
+            );
+            bridge.attach(console.tx, console.rx)
+        } else {
+            (console.tx, console.rx)
+        };
+        // U-Boot 的命令已执行完，之后是内核输出
+        let rx = defmt::wrap(&self.ctx, rx);
+
+        let session_log = match &self.config.session_log {
+            Some(config) => {
+                let log = SessionLog::create(&workspace, &last_name, config)?;
+                println!("{}", t!("sterm.logging", path = log.path().display()));
+                Some(log)
+            }
+            None => None,
+        };
 
-        println!("{}", "Interacting with U-Boot shell...".green());
+        let mut rules = std::mem::take(&mut self.rules);
+        if let Some(board) = &self.board {
+            return Self::watch_console(
+                &self.ctx,
+                &board.prefix,
+                board.timeout,
+                Console { tx, rx },
+                session_log,
+                &mut last_run,
+                &mut rules,
+            );
+        }
+        if let Some(timeout) = self.test {
+            let res = Self::watch_console(
+                &self.ctx,
+                "",
+                timeout,
+                Console { tx, rx },
+                session_log,
+                &mut last_run,
+                &mut rules,
+            );
+            rules.print_captures();
+            return res;
+        }
 
-        let success_regex = self.success_regex.clone();
-        let fail_regex = self.fail_regex.clone();
+        println!("{}", t!("uboot.interacting").green());
 
         let res = Arc::new(Mutex::<Option<anyhow::Result<()>>>::new(None));
         let res_clone = res.clone();
-        let mut shell = SerialTerm::new(tx, rx, move |h, line| {
-            for regex in success_regex.iter() {
-                if regex.is_match(line) {
-                    println!("{}", "\r\n=== SUCCESS PATTERN MATCHED ===".green());
-                    h.stop();
-                    let mut res_lock = res_clone.lock().unwrap();
-                    *res_lock = Some(Ok(()));
-                    return;
-                }
-            }
-
-            for regex in fail_regex.iter() {
-                if regex.is_match(line) {
-                    println!("{}", "\r\n=== FAIL PATTERN MATCHED ===".red());
-                    h.stop();
-                    let mut res_lock = res_clone.lock().unwrap();
-                    *res_lock = Some(Err(anyhow!("Fail pattern matched: {}", line)));
-                    return;
-                }
+        let rules = Arc::new(Mutex::new(rules));
+        let rules_clone = rules.clone();
+        let rules_prompt = rules.clone();
+        let ctx = self.ctx.clone();
+        let ctx_prompt = self.ctx.clone();
+        let mut shell = SerialTerm::new(tx, rx);
+        if let Some(log) = session_log {
+            shell.set_log(log);
+        }
+        shell.add_hook(LineHook::new("last-run", move |_, line| {
+            last_run.line(line)
+        }));
+        let hook = LineHook::new("rules", move |h, line| {
+            let mut rules = rules_clone.lock().unwrap();
+            let verdict = rules.check(line);
+            Self::react(&ctx, rules.take_reactions(), |text| h.send(text));
+            let Some(verdict) = verdict else {
+                return;
+            };
+            h.stop();
+            let mut res_lock = res_clone.lock().unwrap();
+            if verdict.success {
+                println!("\r\n{}", t!("uboot.success_matched").green());
+                *res_lock = Some(Ok(()));
+            } else {
+                println!("\r\n{}", t!("uboot.fail_matched").red());
+                *res_lock = Some(Err(Failure::Boot.error(verdict.message)));
             }
+        })
+        .on_prompt(move |h, prompt| {
+            let mut rules = rules_prompt.lock().unwrap();
+            rules.check_prompt(prompt);
+            Self::react(&ctx_prompt, rules.take_reactions(), |text| h.send(text));
         });
+        shell.add_hook(hook);
         shell.run().await?;
+        rules.lock().unwrap().print_captures();
         {
             let mut res_lock = res.lock().unwrap();
             if let Some(result) = res_lock.take() {
@@ -539,23 +1198,224 @@ impl Runner {
         Ok(())
     }
 
-    fn preper_regex(&mut self) -> anyhow::Result<()> {
-        // Prepare regex patterns if needed
-        // Compile success regex patterns
-        for pattern in self.config.success_regex.iter() {
-            // Compile and store the regex
-            let regex =
-                regex::Regex::new(pattern).map_err(|e| anyhow!("success regex error: {e}"))?;
-            self.success_regex.push(regex);
+    /// 逐行打印带前缀（如板名）的控制台输出，直到匹配成功/失败规则或超时
+    ///
+    /// `send` 规则的输入写回控制台，未换行的提示符同样会检查
+    fn watch_console(
+        ctx: &AppContext,
+        prefix: &str,
+        timeout: Duration,
+        console: Console,
+        mut session_log: Option<SessionLog>,
+        last_run: &mut LastRun,
+        rules: &mut ConsoleRules,
+    ) -> anyhow::Result<()> {
+        let Console { mut tx, mut rx } = console;
+        let deadline = Instant::now() + timeout;
+        let mut line = Vec::new();
+        let mut buf = [0u8; 256];
+        let mut input = Vec::new();
+        while Instant::now() < deadline {
+            let n = match rx.read(&mut buf) {
+                Ok(0) => return Err(Failure::Boot.error("serial port closed")),
+                Ok(n) => n,
+                Err(e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
+                Err(e) => return Err(e.into()),
+            };
+            if let Some(log) = &mut session_log {
+                log.write(&buf[..n])?;
+            }
+            for &byte in &buf[..n] {
+                if byte != b'\n' {
+                    line.push(byte);
+                    continue;
+                }
+                let text = String::from_utf8_lossy(&line).trim_end().to_string();
+                line.clear();
+                if prefix.is_empty() {
+                    println!("{text}");
+                } else {
+                    println!("{prefix} {text}");
+                }
+                last_run.line(&text);
+                let verdict = rules.check(&text);
+                Self::react(ctx, rules.take_reactions(), |text| {
+                    input.extend_from_slice(text.as_bytes())
+                });
+                match verdict {
+                    Some(verdict) if verdict.success => return Ok(()),
+                    Some(verdict) => return Err(Failure::Boot.error(verdict.message)),
+                    None => {}
+                }
+            }
+            if !line.is_empty() {
+                rules.check_prompt(&String::from_utf8_lossy(&line));
+                Self::react(ctx, rules.take_reactions(), |text| {
+                    input.extend_from_slice(text.as_bytes())
+                });
+            }
+            if !input.is_empty() {
+                tx.write_all(&input)?;
+                tx.flush()?;
+                input.clear();
+            }
+        }
+        Err(Failure::Boot.error(t!("boards.timed_out", secs = timeout.as_secs())))
+    }
+
+    /// 用 `load` 把 FIT image 加载到 `fit_loadaddr` 及其后方各一份，
+    /// 用 `cmp.b` 比较两份内容，不一致时报错
+    ///
+    /// 加载命令会把默认镜像地址改为第二份，启动时需显式指定 `fit_loadaddr`
+    fn verify_load(
+        &self,
+        uboot: &mut UbootShell,
+        fit_loadaddr: u64,
+        fitimage: &Path,
+        mut load: impl FnMut(&mut UbootShell, u64) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let size = std::fs::metadata(fitimage)?.len();
+        let copy_addr = Self::copy_addr(fit_loadaddr, size);
+        load(uboot, fit_loadaddr)?;
+        info!(
+            "{}",
+            t!("uboot.verify_loading", addr = format!("{copy_addr:#x}"))
+        );
+        load(uboot, copy_addr)?;
+        match uboot.verify_mem(fit_loadaddr as usize, copy_addr as usize, size as usize)? {
+            MemCompare::Same => {
+                info!("{}", t!("uboot.verify_ok"));
+                Ok(())
+            }
+            MemCompare::Differ(offset) => {
+                Err(Failure::Boot.error(t!("uboot.verify_failed", offset = format!("{offset:#x}"))))
+            }
+        }
+    }
+
+    /// Plans the transfer against what the board held after the last run,
+    /// checking with `crc32` that those images are still in RAM.
+    fn plan_deploy(uboot: &mut UbootShell, last: Option<&Deployed>, current: &Deployed) -> Plan {
+        let plan = Plan::new(last, current);
+        for blob in plan.resident() {
+            if uboot.mem_crc32(blob.addr as usize, blob.size as usize) != Some(blob.crc32) {
+                info!(
+                    "{}",
+                    t!("uboot.image_gone", addr = format!("{:#x}", blob.addr))
+                );
+                return Plan::Full;
+            }
+        }
+        plan
+    }
+
+    /// Sends only the device tree to `fdt.addr`, over the same way as the
+    /// FIT image, and checks it arrived.
+    async fn load_dtb(
+        &self,
+        uboot: &mut UbootShell,
+        fdt: Blob,
+        dtb: &Path,
+        fitimage: &Path,
+        net_ok: bool,
+    ) -> anyhow::Result<()> {
+        // 与 FIT image 放在一起，由同一个 TFTP 服务器提供
+        let local = fitimage.with_extension("dtb");
+        fs::copy(dtb, &local).await?;
+        let mut name = local
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or(anyhow!("Invalid dtb filename"))?
+            .to_string();
+        if let Some(tftp_dir) = self.config.net.as_ref().and_then(|n| n.tftp_dir.as_ref()) {
+            let tftp_path = Path::new(tftp_dir).join(&name);
+            fs::copy(dtb, &tftp_path).await?;
+            name = tftp_path.display().to_string();
+        }
+
+        if let Some(board_ip) = self.config.net.as_ref().and_then(|e| e.board_ip.clone()) {
+            uboot.set_env("ipaddr", board_ip)?;
+            uboot.cmd_with_timeout(&format!("tftp {:#x} {name}", fdt.addr), LOAD_TIMEOUT)?;
+        } else if net_ok {
+            uboot.cmd_with_timeout(&format!("dhcp {:#x} {name}", fdt.addr), LOAD_TIMEOUT)?;
+        } else {
+            self.uboot_loady(uboot, fdt.addr as usize, &local);
+        }
+
+        if uboot.mem_crc32(fdt.addr as usize, fdt.size as usize) != Some(fdt.crc32) {
+            return Err(Failure::Boot.error(t!("uboot.dtb_verify_failed")));
+        }
+        Ok(())
+    }
+
+    /// Address of the second copy of `verify_load`.
+    fn copy_addr(fit_loadaddr: u64, size: u64) -> u64 {
+        // 第二份放在第一份之后，按 1 MiB 对齐
+        (fit_loadaddr + size).next_multiple_of(0x10_0000)
+    }
+
+    /// Checks `loads` against the configured DRAM banks, or those `bdinfo`
+    /// reports, and against U-Boot's own memory.
+    fn check_memory(&self, uboot: &mut UbootShell, loads: &[Region]) -> anyhow::Result<()> {
+        let mut info = match uboot.cmd("bdinfo") {
+            Ok(output) => BoardInfo::parse(&output),
+            Err(e) => {
+                debug!("bdinfo: {e}");
+                BoardInfo::default()
+            }
+        };
+        if !self.config.dram.is_empty() {
+            info.dram = self
+                .config
+                .dram
+                .iter()
+                .map(memmap::DramBank::region)
+                .collect::<anyhow::Result<_>>()?;
+        }
+        if info.dram.is_empty() {
+            info!("{}", t!("memmap.no_dram"));
+        }
+        info.check(loads)
+    }
+
+    /// Resets the board back into U-Boot and restores the environment saved
+    /// to `env_snapshot`.
+    fn restore_env(&self) -> anyhow::Result<()> {
+        let path = self
+            .config
+            .env_snapshot
+            .as_ref()
+            .ok_or_else(|| anyhow!(t!("uboot_env.no_snapshot")))?;
+        if !self.config.can_reset() {
+            bail!(t!("uboot_env.no_reset"));
         }
+        let mut uboot = connect(&self.ctx, &self.config)?;
+        restore_env(&mut uboot, Path::new(path))
+    }
 
-        // Compile fail regex patterns
-        for pattern in self.config.fail_regex.iter() {
-            // Compile and store the regex
-            let regex = regex::Regex::new(pattern).map_err(|e| anyhow!("fail regex error: {e}"))?;
-            self.fail_regex.push(regex);
+    /// 执行 `send` 与 `run` 规则要求的输入和命令，输入交给 `send` 写入控制台
+    fn react(ctx: &AppContext, reactions: Vec<Reaction>, mut send: impl FnMut(String)) {
+        for reaction in reactions {
+            match reaction {
+                Reaction::Send(text) => send(format!("{text}\r")),
+                Reaction::Run { cmd, vars } => {
+                    let ctx = ctx.clone();
+                    std::thread::spawn(move || {
+                        if let Err(e) = ctx.shell_run_cmd_with_env(&cmd, &vars) {
+                            warn!("{}", t!("rules.run_failed", cmd = cmd, error = e));
+                        }
+                    });
+                }
+            }
         }
+    }
 
+    fn prepare_rules(&mut self) -> anyhow::Result<()> {
+        self.rules = ConsoleRules::for_runner(
+            &self.ctx,
+            &self.config.success_regex,
+            &self.config.fail_regex,
+        )?;
         Ok(())
     }
 
@@ -586,14 +1446,22 @@ impl Runner {
         Some(ip_string)
     }
 
-    fn uboot_loady(uboot: &mut UbootShell, addr: usize, file: impl Into<PathBuf>) {
-        println!("{}", "\r\nsend file".green());
+    /// Spinner for a step that reports no progress, hidden for board runs.
+    fn spinner(&self, message: String) -> progress::Progress {
+        match self.board {
+            Some(_) => progress::hidden(),
+            None => progress::spinner(message),
+        }
+    }
 
-        let pb = ProgressBar::new(100);
-        pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta})")
-        .unwrap()
-        .with_key("eta", |state: &ProgressState, w: &mut dyn core::fmt::Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
-        .progress_chars("#>-"));
+    fn uboot_loady(&self, uboot: &mut UbootShell, addr: usize, file: impl Into<PathBuf>) {
+        println!("\r\n{}", t!("uboot.send_file").green());
+
+        // 多板并行时进度条会相互覆盖，不显示
+        let pb = match self.board {
+            Some(_) => progress::hidden(),
+            None => progress::bytes("YMODEM", None),
+        };
 
         let res = uboot
             .loady(addr, file, |x, a| {
@@ -602,9 +1470,170 @@ impl Runner {
             })
             .unwrap();
 
-        pb.finish_with_message("upload done");
+        pb.finish();
 
         println!("{}", res);
-        println!("send ok");
+        println!("{}", t!("uboot.send_ok"));
+    }
+}
+
+/// Opens the console of `config` and resets the board, returning once the
+/// U-Boot shell is ready.
+fn connect(ctx: &AppContext, config: &UbootConfig) -> anyhow::Result<UbootShell> {
+    let (rx, tx) = config.open_console()?;
+    let handle: thread::JoinHandle<anyhow::Result<UbootShell>> = thread::spawn(move || {
+        let uboot = UbootShell::new(tx, rx)?;
+        Ok(uboot)
+    });
+
+    config.reset_board(ctx)?;
+
+    handle.join().unwrap()
+}
+
+/// Saves the whole U-Boot environment to `path`.
+fn save_env(uboot: &mut UbootShell, path: &Path) -> anyhow::Result<()> {
+    let snapshot = uboot.env_snapshot()?;
+    std::fs::write(path, snapshot.to_string())
+        .with_context(|| format!("cannot write {}", path.display()))?;
+    info!(
+        "{}",
+        t!(
+            "uboot_env.saved",
+            count = snapshot.len(),
+            path = path.display()
+        )
+    );
+    Ok(())
+}
+
+/// Puts the environment saved to `path` back and, if anything changed,
+/// makes it persistent with `saveenv`.
+fn restore_env(uboot: &mut UbootShell, path: &Path) -> anyhow::Result<()> {
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+    let snapshot = EnvSnapshot::parse(&text);
+    // 空快照会删除板子上的所有变量
+    if snapshot.is_empty() {
+        bail!(t!("uboot_env.empty", path = path.display()));
+    }
+    let changed = uboot.env_restore(&snapshot)?;
+    if changed == 0 {
+        info!("{}", t!("uboot_env.unchanged"));
+        return Ok(());
+    }
+    uboot.env_save()?;
+    info!("{}", t!("uboot_env.restored", count = changed));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::run::rules::{ConsoleRule, RuleAction};
+
+    /// 控制台的发送端，保留写入的内容
+    #[derive(Clone, Default)]
+    struct Sent(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Sent {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn rule(regex: &str, action: RuleAction, message: Option<&str>) -> ConsoleRule {
+        ConsoleRule {
+            regex: regex.to_string(),
+            action,
+            message: message.map(String::from),
+        }
+    }
+
+    /// Runs `watch_console` on `rx`, returning its result and what was sent.
+    fn watch(
+        rx: impl Read + Send + 'static,
+        timeout: Duration,
+        rules: Vec<ConsoleRule>,
+    ) -> (anyhow::Result<()>, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ctx = AppContext::default();
+        ctx.paths.workspace = dir.path().to_path_buf();
+        let sent = Sent::default();
+        let console = Console {
+            tx: Box::new(sent.clone()),
+            rx: Box::new(rx),
+        };
+        let mut last_run = LastRun::start(&ctx, "uboot");
+        let mut rules = ConsoleRules::new(rules).unwrap();
+        let res =
+            Runner::watch_console(&ctx, "", timeout, console, None, &mut last_run, &mut rules);
+        let sent = String::from_utf8(sent.0.lock().unwrap().clone()).unwrap();
+        (res, sent)
+    }
+
+    #[test]
+    fn test_watch_console_success() {
+        // 提示符先单独到达，之后才是回显和后续输出
+        let rx = Cursor::new("Welcome\r\nbuildroot login: ")
+            .chain(Cursor::new("root\r\nAll tests passed\r\n"));
+        let (res, sent) = watch(
+            rx,
+            Duration::from_secs(10),
+            vec![
+                rule("login:", RuleAction::Send, Some("root")),
+                rule("All tests passed", RuleAction::Success, None),
+            ],
+        );
+        res.unwrap();
+        assert_eq!(sent, "root\r");
+    }
+
+    #[test]
+    fn test_watch_console_failure() {
+        let rx = Cursor::new("booting\npanicked at main.rs\nAll tests passed\n");
+        let (res, _) = watch(
+            rx,
+            Duration::from_secs(10),
+            vec![
+                rule("panicked at (.*)", RuleAction::Failure, Some("panic at $1")),
+                rule("All tests passed", RuleAction::Success, None),
+            ],
+        );
+        let err = res.unwrap_err();
+        assert_eq!(crate::exit::code(&err), 4);
+        assert!(err.to_string().contains("panic at main.rs"), "{err}");
+    }
+
+    #[test]
+    fn test_watch_console_timeout() {
+        let rx = Cursor::new("All tests passed\n");
+        let (res, _) = watch(
+            rx,
+            Duration::ZERO,
+            vec![rule("All tests passed", RuleAction::Success, None)],
+        );
+        let err = res.unwrap_err();
+        assert_eq!(crate::exit::code(&err), 4);
+        assert!(!err.to_string().contains("serial port closed"), "{err}");
+    }
+
+    #[test]
+    fn test_check_hashes() {
+        assert_eq!(
+            check_hashes("bootm 0x90000000 - 0x83000000"),
+            "iminfo 0x90000000 && bootm 0x90000000 - 0x83000000"
+        );
+        assert_eq!(
+            check_hashes("tftp image.fit && bootm"),
+            "tftp image.fit && iminfo && bootm"
+        );
     }
 }
@@ -0,0 +1,267 @@
+//! USB DFU and fastboot transfer for the U-Boot runner.
+//!
+//! With `[usb]` in `.uboot.toml` the FIT image is pushed over USB instead of
+//! YMODEM or TFTP: ostool starts U-Boot's `dfu` or `fastboot` gadget on the
+//! console, waits for the board to show up with `dfu-util -l` or
+//! `fastboot devices`, writes the configured images to their DFU alt
+//! settings or fastboot partitions, loads the FIT image into RAM at the
+//! FIT load address and returns U-Boot to its prompt for `bootm`.
+//!
+//! ```toml
+//! [usb]
+//! protocol = "fastboot"
+//! images = [{ target = "boot", file = "boot.img" }]
+//! ```
+
+use std::{
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use jkconfig::t;
+use log::info;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uboot_shell::UbootShell;
+
+use crate::{ctx::AppContext, utils::find_program};
+
+/// Alt setting of the FIT image in RAM.
+const FIT_ALT: &str = "fit";
+
+/// Image transfer over USB.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Default)]
+pub struct UsbConfig {
+    pub protocol: UsbProtocol,
+    /// DFU device `VID:PID` or fastboot serial number, when several boards
+    /// are connected
+    pub device: Option<String>,
+    /// Images written before the FIT image is loaded
+    #[serde(default)]
+    pub images: Vec<UsbImage>,
+    /// `dfu` arguments of the session writing `images`, `mmc 0` when unset;
+    /// the alt settings come from the board's `dfu_alt_info`
+    pub dfu_interface: Option<String>,
+    /// Seconds to wait for the board to show up on USB, 10 when unset
+    pub timeout: Option<u64>,
+}
+
+/// USB gadget protocol of U-Boot.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UsbProtocol {
+    /// `dfu` command, pushed with `dfu-util`.
+    #[default]
+    Dfu,
+    /// `fastboot` command, pushed with `fastboot`.
+    Fastboot,
+}
+
+impl UsbProtocol {
+    /// Host tool talking to the gadget.
+    pub fn tool(self) -> &'static str {
+        match self {
+            Self::Dfu => "dfu-util",
+            Self::Fastboot => "fastboot",
+        }
+    }
+}
+
+/// An image written over USB.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct UsbImage {
+    /// DFU alt setting or fastboot partition
+    pub target: String,
+    /// File relative to the workspace
+    #[schemars(extend("format" = "file-path"))]
+    pub file: String,
+}
+
+/// Writes the images and loads `fit` at `addr` over USB.
+///
+/// U-Boot is back at its prompt afterwards, ready for `bootm`.
+///
+/// # Errors
+///
+/// Returns an error if the host tool is missing or fails, or the board
+/// does not show up on USB.
+pub fn load(
+    ctx: &AppContext,
+    config: &UsbConfig,
+    uboot: &mut UbootShell,
+    addr: u64,
+    fit: &Path,
+) -> anyhow::Result<()> {
+    let tool = config.protocol.tool();
+    if find_program(tool).is_none() {
+        bail!(t!("usb.missing_tool", tool = tool));
+    }
+    let images = config
+        .images
+        .iter()
+        .map(|img| {
+            (
+                img.target.as_str(),
+                ctx.paths.workspace.join(&img.file).display().to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let fit = fit.display().to_string();
+    let device = config.device.as_deref();
+
+    match config.protocol {
+        UsbProtocol::Dfu => {
+            if !images.is_empty() {
+                let interface = config.dfu_interface.as_deref().unwrap_or("mmc 0");
+                uboot.cmd_without_reply(&format!("dfu 0 {interface}"))?;
+                wait_for_device(ctx, config)?;
+                for (i, (alt, file)) in images.iter().enumerate() {
+                    ctx.command(tool)
+                        .args(dfu_args(device, alt, file, i + 1 == images.len()))
+                        .run()?;
+                }
+                resync(uboot)?;
+            }
+            let size = std::fs::metadata(&fit)?.len();
+            uboot.set_env("dfu_alt_info", format!("{FIT_ALT} ram {addr:#x} {size:#x}"))?;
+            uboot.cmd_without_reply("dfu 0 ram 0")?;
+            wait_for_device(ctx, config)?;
+            ctx.command(tool)
+                .args(dfu_args(device, FIT_ALT, &fit, true))
+                .run()?;
+        }
+        UsbProtocol::Fastboot => {
+            // 下载缓冲区放在 FIT 加载地址，最后 stage 的 FIT 镜像就留在那里
+            uboot.cmd_without_reply(&format!("fastboot -l {addr:#x} usb 0"))?;
+            wait_for_device(ctx, config)?;
+            for (partition, file) in &images {
+                ctx.command(tool)
+                    .args(fastboot_args(device, &["flash", partition, file]))
+                    .run()?;
+            }
+            ctx.command(tool)
+                .args(fastboot_args(device, &["stage", &fit]))
+                .run()?;
+            ctx.command(tool)
+                .args(fastboot_args(device, &["continue"]))
+                .run()?;
+        }
+    }
+    resync(uboot)
+}
+
+/// `dfu-util` arguments downloading `file` to `alt`, resetting the USB
+/// device afterwards on the last download so U-Boot leaves DFU mode.
+fn dfu_args(device: Option<&str>, alt: &str, file: &str, last: bool) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(device) = device {
+        args.extend(["-d".to_string(), device.to_string()]);
+    }
+    args.extend(["-a", alt, "-D", file].map(String::from));
+    if last {
+        args.push("-R".to_string());
+    }
+    args
+}
+
+fn fastboot_args(device: Option<&str>, cmd: &[&str]) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(device) = device {
+        args.extend(["-s".to_string(), device.to_string()]);
+    }
+    args.extend(cmd.iter().map(|s| s.to_string()));
+    args
+}
+
+/// Whether the output of `dfu-util -l` or `fastboot devices` lists a
+/// device, `device` if given.
+fn device_listed(protocol: UsbProtocol, device: Option<&str>, output: &str) -> bool {
+    match protocol {
+        // dfu-util -d 已按 VID:PID 过滤
+        UsbProtocol::Dfu => output.lines().any(|l| l.starts_with("Found DFU: [")),
+        UsbProtocol::Fastboot => output.lines().any(|l| {
+            let serial = l.split_whitespace().next();
+            serial.is_some() && device.is_none_or(|d| serial == Some(d))
+        }),
+    }
+}
+
+fn wait_for_device(ctx: &AppContext, config: &UsbConfig) -> anyhow::Result<()> {
+    let protocol = config.protocol;
+    let tool = protocol.tool();
+    let device = config.device.as_deref();
+    let timeout = config.timeout.unwrap_or(10);
+    info!("{}", t!("usb.waiting", tool = tool));
+
+    let deadline = Instant::now() + Duration::from_secs(timeout);
+    while Instant::now() < deadline {
+        let mut cmd = ctx.command(tool);
+        match protocol {
+            UsbProtocol::Dfu => {
+                cmd.arg("-l");
+                if let Some(device) = device {
+                    cmd.args(["-d", device]);
+                }
+            }
+            UsbProtocol::Fastboot => {
+                cmd.arg("devices");
+            }
+        }
+        let output = cmd.output()?;
+        if device_listed(protocol, device, &String::from_utf8_lossy(&output.stdout)) {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+    bail!(t!("usb.not_found", tool = tool, secs = timeout))
+}
+
+/// Makes U-Boot leave the gadget and answer at its prompt again.
+fn resync(uboot: &mut UbootShell) -> anyhow::Result<()> {
+    uboot.resync()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_args() {
+        assert_eq!(
+            dfu_args(Some("0483:df11"), "fit", "fit.itb", true),
+            ["-d", "0483:df11", "-a", "fit", "-D", "fit.itb", "-R"]
+        );
+        assert_eq!(
+            dfu_args(None, "boot", "b", false),
+            ["-a", "boot", "-D", "b"]
+        );
+        assert_eq!(
+            fastboot_args(Some("1234"), &["flash", "boot", "boot.img"]),
+            ["-s", "1234", "flash", "boot", "boot.img"]
+        );
+        assert_eq!(fastboot_args(None, &["continue"]), ["continue"]);
+    }
+
+    #[test]
+    fn test_device_listed() {
+        let dfu = "dfu-util 0.11\n\nFound DFU: [0483:df11] ver=0200, devnum=5, cfg=1, intf=0, path=\"1-2\", alt=0, name=\"fit\", serial=\"UNKNOWN\"\n";
+        assert!(device_listed(UsbProtocol::Dfu, None, dfu));
+        assert!(!device_listed(UsbProtocol::Dfu, None, "dfu-util 0.11\n"));
+
+        let fastboot = "1234abcd\tfastboot\n";
+        assert!(device_listed(UsbProtocol::Fastboot, None, fastboot));
+        assert!(device_listed(
+            UsbProtocol::Fastboot,
+            Some("1234abcd"),
+            fastboot
+        ));
+        assert!(!device_listed(
+            UsbProtocol::Fastboot,
+            Some("ffff"),
+            fastboot
+        ));
+        assert!(!device_listed(UsbProtocol::Fastboot, None, ""));
+    }
+}
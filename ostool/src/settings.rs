@@ -0,0 +1,131 @@
+//! User-level ostool settings.
+//!
+//! Read from `$XDG_CONFIG_HOME/ostool/settings.toml` (falling back to
+//! `~/.config/ostool/settings.toml`, or `%APPDATA%\ostool\settings.toml` on
+//! Windows), or the file named by `OSTOOL_SETTINGS`. Unlike the project
+//! configs they hold what belongs to the machine, not the workspace:
+//!
+//! ```toml
+//! # Serial device picked for a configured port that was absent
+//! [serial_ports]
+//! "/dev/ttyUSB0" = "1a86:7523:5A7B012345"
+//!
+//! # Config files of a workspace kept outside of it, see `--config-dir`
+//! [config_dirs]
+//! "/home/me/src/kernel" = "/home/me/src/boards/kernel"
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    env,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Contents of the user settings file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Configured serial port to the `VID:PID[:SERIAL]` of the USB device
+    /// chosen in its place.
+    pub serial_ports: BTreeMap<String, String>,
+    /// Workspace directory to the directory holding its config files; a
+    /// relative directory is taken relative to the workspace.
+    pub config_dirs: BTreeMap<String, PathBuf>,
+}
+
+impl Settings {
+    /// Location of the settings file, if a config directory is known.
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os("OSTOOL_SETTINGS") {
+            return Some(path.into());
+        }
+        let dir = if cfg!(windows) {
+            env::var_os("APPDATA").map(PathBuf::from)
+        } else {
+            env::var_os("XDG_CONFIG_HOME")
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        };
+        dir.map(|dir| dir.join("ostool").join("settings.toml"))
+    }
+
+    /// Loads the settings file.
+    ///
+    /// A missing file gives the defaults; an unreadable one is reported and
+    /// ignored.
+    pub fn load() -> Self {
+        Self::path()
+            .filter(|path| path.exists())
+            .and_then(|path| match Self::from_file(&path) {
+                Ok(settings) => Some(settings),
+                Err(e) => {
+                    warn!("Ignoring {}: {e:#}", path.display());
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    /// Writes the settings file, creating its directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no config directory is known or writing fails.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = Self::path().ok_or_else(|| anyhow!("No config directory"))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Config directory set for `workspace` in `config_dirs`.
+    pub fn config_dir(&self, workspace: &Path) -> Option<PathBuf> {
+        let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let workspace = canonical(workspace);
+        self.config_dirs
+            .iter()
+            .find(|(key, _)| canonical(Path::new(key)) == workspace)
+            .map(|(_, dir)| workspace.join(dir))
+    }
+
+    fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_roundtrip() {
+        let mut settings = Settings::default();
+        settings
+            .serial_ports
+            .insert("/dev/ttyUSB0".to_string(), "1a86:7523:ABC".to_string());
+        let text = toml::to_string_pretty(&settings).unwrap();
+        assert!(text.contains("[serial_ports]"));
+        assert_eq!(toml::from_str::<Settings>(&text).unwrap(), settings);
+        assert_eq!(toml::from_str::<Settings>("").unwrap(), Settings::default());
+    }
+
+    #[test]
+    fn test_config_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let workspace = tmp.path().to_path_buf();
+        let settings: Settings = toml::from_str(&format!(
+            "[config_dirs]\n{:?} = \"../boards\"\n",
+            workspace.display().to_string()
+        ))
+        .unwrap();
+        let dir = settings.config_dir(&workspace.join(".")).unwrap();
+        assert_eq!(dir, workspace.canonicalize().unwrap().join("../boards"));
+        assert_eq!(settings.config_dir(&env::temp_dir()), None);
+    }
+}
@@ -0,0 +1,293 @@
+//! Named line hooks of the serial terminal.
+//!
+//! Every complete line received goes through the hooks registered on the
+//! [`TermHandle`], in the order they were added. A hook can be limited to
+//! lines matching a regex and debounced, ignoring lines for a while after
+//! its action ran, so the run log, console rules and other watchers hook
+//! the console side by side. Hooks are added and removed by name while the
+//! terminal runs, also from within a hook's action.
+//!
+//! Prompts such as `login: ` do not end with a newline. A hook with a
+//! prompt action also gets the unfinished line whenever the serial port
+//! pauses in the middle of it; the finished line follows as usual.
+
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+
+use super::TermHandle;
+
+type HookAction = Box<dyn FnMut(&TermHandle, &str) + Send>;
+
+/// A named watcher of the lines received by a [`SerialTerm`](super::SerialTerm).
+pub struct LineHook {
+    name: String,
+    regex: Option<Regex>,
+    debounce: Duration,
+    last_run: Option<Instant>,
+    action: HookAction,
+    prompt_action: Option<HookAction>,
+}
+
+impl LineHook {
+    /// A hook named `name` running `action` on every line.
+    pub fn new<F>(name: impl Into<String>, action: F) -> Self
+    where
+        F: FnMut(&TermHandle, &str) + Send + 'static,
+    {
+        Self {
+            name: name.into(),
+            regex: None,
+            debounce: Duration::ZERO,
+            last_run: None,
+            action: Box::new(action),
+            prompt_action: None,
+        }
+    }
+
+    /// Runs `action` on an unfinished line when the serial port pauses in
+    /// it, such as a login prompt.
+    pub fn on_prompt<F>(mut self, action: F) -> Self
+    where
+        F: FnMut(&TermHandle, &str) + Send + 'static,
+    {
+        self.prompt_action = Some(Box::new(action));
+        self
+    }
+
+    /// Runs the action only on lines matching `regex`.
+    pub fn matching(mut self, regex: Regex) -> Self {
+        self.regex = Some(regex);
+        self
+    }
+
+    /// Ignores lines for `debounce` after the action ran.
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Name the hook is registered under.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn fire(&mut self, handle: &TermHandle, line: &str, now: Instant, prompt: bool) {
+        if prompt && self.prompt_action.is_none() {
+            return;
+        }
+        if self
+            .last_run
+            .is_some_and(|last| now.duration_since(last) < self.debounce)
+        {
+            return;
+        }
+        if self.regex.as_ref().is_some_and(|r| !r.is_match(line)) {
+            return;
+        }
+        self.last_run = Some(now);
+        match &mut self.prompt_action {
+            Some(action) if prompt => action(handle, line),
+            _ => (self.action)(handle, line),
+        }
+    }
+}
+
+/// Hooks of a terminal.
+///
+/// While a line is dispatched the hooks are taken out, so that actions can
+/// change the registry; hooks added meanwhile wait in `hooks` and removals
+/// in `removed` until [`Self::finish`].
+#[derive(Default)]
+pub(super) struct Hooks {
+    hooks: Vec<LineHook>,
+    /// Names of the hooks taken out for the current line.
+    running: Vec<String>,
+    removed: Vec<String>,
+}
+
+impl Hooks {
+    fn add(&mut self, hook: LineHook) {
+        self.remove(&hook.name);
+        self.hooks.push(hook);
+    }
+
+    fn remove(&mut self, name: &str) -> bool {
+        let len = self.hooks.len();
+        self.hooks.retain(|h| h.name != name);
+        let running = self.running.iter().any(|n| n == name) && !self.is_removed(name);
+        if running {
+            self.removed.push(name.to_string());
+        }
+        running || self.hooks.len() != len
+    }
+
+    fn is_removed(&self, name: &str) -> bool {
+        self.removed.iter().any(|n| n == name)
+    }
+
+    fn names(&self) -> Vec<String> {
+        let mut names = self
+            .running
+            .iter()
+            .filter(|n| !self.is_removed(n))
+            .cloned()
+            .collect::<Vec<_>>();
+        names.extend(self.hooks.iter().map(|h| h.name.clone()));
+        names
+    }
+
+    fn take(&mut self) -> Vec<LineHook> {
+        let hooks = std::mem::take(&mut self.hooks);
+        self.running = hooks.iter().map(|h| h.name.clone()).collect();
+        hooks
+    }
+
+    fn finish(&mut self, mut hooks: Vec<LineHook>) {
+        let removed = std::mem::take(&mut self.removed);
+        hooks.retain(|h| !removed.contains(&h.name));
+        hooks.append(&mut self.hooks);
+        self.hooks = hooks;
+        self.running.clear();
+    }
+}
+
+impl TermHandle {
+    /// Registers `hook`, replacing the hook of the same name.
+    pub fn add_hook(&self, hook: LineHook) {
+        self.hooks.lock().unwrap().add(hook);
+    }
+
+    /// Unregisters the hook named `name`, returning whether there was one.
+    pub fn remove_hook(&self, name: &str) -> bool {
+        self.hooks.lock().unwrap().remove(name)
+    }
+
+    /// Names of the registered hooks, in the order they run.
+    pub fn hook_names(&self) -> Vec<String> {
+        self.hooks.lock().unwrap().names()
+    }
+
+    /// Runs the hooks on a received line.
+    pub(super) fn dispatch(&self, line: &str) {
+        self.run_hooks(line, false);
+    }
+
+    /// Runs the prompt actions of the hooks on an unfinished line.
+    pub(super) fn dispatch_prompt(&self, line: &str) {
+        self.run_hooks(line, true);
+    }
+
+    fn run_hooks(&self, line: &str, prompt: bool) {
+        let now = Instant::now();
+        let mut hooks = self.hooks.lock().unwrap().take();
+        for hook in &mut hooks {
+            // 前面的钩子可能已将其移除
+            if self.hooks.lock().unwrap().is_removed(&hook.name) {
+                continue;
+            }
+            hook.fire(self, line, now, prompt);
+        }
+        self.hooks.lock().unwrap().finish(hooks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    fn recorder(
+        seen: &Arc<Mutex<Vec<String>>>,
+        tag: &'static str,
+    ) -> impl FnMut(&TermHandle, &str) + Send + 'static {
+        let seen = seen.clone();
+        move |_, line| seen.lock().unwrap().push(format!("{tag}:{line}"))
+    }
+
+    #[test]
+    fn test_regex_and_order() {
+        let handle = TermHandle::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        handle.add_hook(LineHook::new("log", recorder(&seen, "log")));
+        handle.add_hook(
+            LineHook::new("panic", recorder(&seen, "panic"))
+                .matching(Regex::new("panicked").unwrap()),
+        );
+        handle.dispatch("boot");
+        handle.dispatch("panicked at main.rs");
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                "log:boot",
+                "log:panicked at main.rs",
+                "panic:panicked at main.rs"
+            ]
+        );
+        assert_eq!(handle.hook_names(), ["log", "panic"]);
+
+        // 同名钩子会被替换
+        handle.add_hook(LineHook::new("log", recorder(&seen, "log2")));
+        assert_eq!(handle.hook_names(), ["panic", "log"]);
+        assert!(handle.remove_hook("panic"));
+        assert!(!handle.remove_hook("panic"));
+        seen.lock().unwrap().clear();
+        handle.dispatch("panicked again");
+        assert_eq!(*seen.lock().unwrap(), ["log2:panicked again"]);
+    }
+
+    #[test]
+    fn test_debounce() {
+        let handle = TermHandle::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        handle.add_hook(
+            LineHook::new("login", recorder(&seen, "login")).debounce(Duration::from_secs(3600)),
+        );
+        handle.dispatch("login:");
+        handle.dispatch("login:");
+        assert_eq!(*seen.lock().unwrap(), ["login:login:"]);
+    }
+
+    #[test]
+    fn test_prompt() {
+        let handle = TermHandle::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        handle.add_hook(LineHook::new("log", recorder(&seen, "log")));
+        handle.add_hook(
+            LineHook::new("login", recorder(&seen, "line"))
+                .on_prompt(recorder(&seen, "prompt"))
+                .matching(Regex::new("login:").unwrap()),
+        );
+        handle.dispatch_prompt("buildroot login: ");
+        handle.dispatch("buildroot login: root\n");
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                "prompt:buildroot login: ",
+                "log:buildroot login: root\n",
+                "line:buildroot login: root\n"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_change_hooks_from_action() {
+        let handle = TermHandle::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        handle.add_hook(LineHook::new("once", {
+            let seen = seen.clone();
+            move |h: &TermHandle, _: &str| {
+                assert!(h.remove_hook("once"));
+                assert!(h.remove_hook("later"));
+                h.add_hook(LineHook::new("added", recorder(&seen, "added")));
+            }
+        }));
+        handle.add_hook(LineHook::new("later", recorder(&seen, "later")));
+        handle.dispatch("first");
+        assert!(seen.lock().unwrap().is_empty());
+        assert_eq!(handle.hook_names(), ["added"]);
+        handle.dispatch("second");
+        assert_eq!(*seen.lock().unwrap(), ["added:second"]);
+    }
+}
@@ -0,0 +1,213 @@
+//! Session logs of the serial terminal.
+//!
+//! All bytes received by a [`SerialTerm`](super::SerialTerm) can be copied
+//! to `<dir>/<name>-<unix time>.log`, so long bring-up sessions can be
+//! archived and searched later. ANSI escape sequences are optionally
+//! removed, also when split across reads. Once a file reaches
+//! `max_size_mib`, it is renamed to `<file>.1`, older files move up to
+//! `<file>.2` and so on, and the oldest beyond `keep` is deleted.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Rotated files kept by default besides the current one.
+const DEFAULT_KEEP: usize = 5;
+
+/// Log file of serial terminal sessions
+#[derive(Default, Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct SessionLogConfig {
+    /// Directory of the log files, relative to the workspace
+    #[schemars(extend("format" = "dir-path"))]
+    pub dir: String,
+    /// Remove ANSI escape sequences such as colors
+    #[serde(default)]
+    pub strip_ansi: bool,
+    /// Start a new file once the current one reaches this size, in MiB
+    pub max_size_mib: Option<u64>,
+    /// Rotated files kept besides the current one, 5 by default
+    pub keep: Option<usize>,
+}
+
+/// Where the ANSI stripper is within an escape sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Ansi {
+    #[default]
+    Text,
+    /// After `ESC`.
+    Esc,
+    /// In `ESC [ ...`, up to the final byte.
+    Csi,
+    /// In `ESC ] ...`, up to `BEL` or `ESC \`.
+    Osc,
+    /// `ESC` within an OSC sequence.
+    OscEsc,
+    /// After `ESC (` or `ESC )`, before the charset byte.
+    Charset,
+}
+
+impl Ansi {
+    /// Feeds `b`, returning whether it is text.
+    fn feed(&mut self, b: u8) -> bool {
+        let (next, text) = match (*self, b) {
+            (Ansi::Text, 0x1b) => (Ansi::Esc, false),
+            (Ansi::Text, _) => (Ansi::Text, true),
+            (Ansi::Esc, b'[') => (Ansi::Csi, false),
+            (Ansi::Esc, b']') => (Ansi::Osc, false),
+            (Ansi::Esc, b'(' | b')') => (Ansi::Charset, false),
+            (Ansi::Esc, _) | (Ansi::Charset, _) => (Ansi::Text, false),
+            (Ansi::Csi, 0x40..=0x7e) => (Ansi::Text, false),
+            (Ansi::Csi, _) => (Ansi::Csi, false),
+            (Ansi::Osc, 0x07) => (Ansi::Text, false),
+            (Ansi::Osc, 0x1b) => (Ansi::OscEsc, false),
+            (Ansi::Osc, _) => (Ansi::Osc, false),
+            (Ansi::OscEsc, b'\\') => (Ansi::Text, false),
+            (Ansi::OscEsc, _) => (Ansi::Osc, false),
+        };
+        *self = next;
+        text
+    }
+}
+
+/// Log file a [`SerialTerm`](super::SerialTerm) copies received bytes to.
+pub struct SessionLog {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_size: Option<u64>,
+    keep: usize,
+    ansi: Option<Ansi>,
+}
+
+impl SessionLog {
+    /// Creates `<dir>/<name>-<unix time>.log` for a session with `config`,
+    /// `dir` being resolved against `workspace`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory or file cannot be created.
+    pub fn create(workspace: &Path, name: &str, config: &SessionLogConfig) -> io::Result<Self> {
+        let dir = workspace.join(&config.dir);
+        std::fs::create_dir_all(&dir)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let path = dir.join(format!("{name}-{now}.log"));
+        Self::open(path, config)
+    }
+
+    /// Appends to the log file `path` with `config`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened.
+    pub fn open(path: PathBuf, config: &SessionLogConfig) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            written,
+            max_size: config.max_size_mib.map(|m| m.max(1) << 20),
+            keep: config.keep.unwrap_or(DEFAULT_KEEP),
+            ansi: config.strip_ansi.then(Ansi::default),
+        })
+    }
+
+    /// Path of the current log file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Copies received `data` to the log, rotating the file when full.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written or rotated.
+    pub fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        let data = match &mut self.ansi {
+            Some(ansi) => data.iter().copied().filter(|&b| ansi.feed(b)).collect(),
+            None => data.to_vec(),
+        };
+        self.file.write_all(&data)?;
+        self.written += data.len() as u64;
+        if self.max_size.is_some_and(|max| self.written >= max) {
+            self.rotate()?;
+        }
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let rotated = |i: usize| PathBuf::from(format!("{}.{i}", self.path.display()));
+        if self.keep == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            let _ = std::fs::remove_file(rotated(self.keep));
+            for i in (1..self.keep).rev() {
+                let from = rotated(i);
+                if from.exists() {
+                    std::fs::rename(from, rotated(i + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, rotated(1))?;
+        }
+        self.file = File::create(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        let mut ansi = Ansi::default();
+        let mut strip = |data: &[u8]| {
+            data.iter()
+                .copied()
+                .filter(|&b| ansi.feed(b))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(strip(b"\x1b[1;31mred\x1b[0m ok"), b"red ok");
+        // 跨两次读取的转义序列
+        assert_eq!(strip(b"a\x1b[3"), b"a");
+        assert_eq!(strip(b"2mb\x1b]0;title\x07c\x1b(Bd"), b"bcd");
+    }
+
+    #[test]
+    fn test_rotate() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        let config = SessionLogConfig {
+            dir: "logs".into(),
+            max_size_mib: Some(1),
+            keep: Some(2),
+            ..Default::default()
+        };
+        let mut log = SessionLog::create(&dir, "uboot", &config).unwrap();
+        let path = log.path().to_path_buf();
+        assert!(path.starts_with(dir.join("logs")));
+        let mib = vec![b'x'; 1 << 20];
+        for i in 0..3u8 {
+            log.write(&[i]).unwrap();
+            log.write(&mib).unwrap();
+        }
+        log.write(b"tail").unwrap();
+
+        let rotated = |i: usize| PathBuf::from(format!("{}.{i}", path.display()));
+        assert_eq!(std::fs::read(&path).unwrap(), b"tail");
+        assert_eq!(std::fs::read(rotated(1)).unwrap()[0], 2);
+        assert_eq!(std::fs::read(rotated(2)).unwrap()[0], 1);
+        assert!(!rotated(3).exists());
+    }
+}
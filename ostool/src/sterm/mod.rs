@@ -4,7 +4,8 @@
 //! with embedded devices and development boards. It supports:
 //!
 //! - Full keyboard input with special key sequences
-//! - Line-based output callback for pattern matching
+//! - Named line hooks for pattern matching, see [`hooks`]
+//! - Copying the session to a log file, see [`log`]
 //! - Raw terminal mode for proper character handling
 //!
 //! # Exit Sequence
@@ -22,11 +23,18 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode},
 };
 use futures::stream::StreamExt;
+use jkconfig::t;
 use tokio::task::{AbortHandle, spawn_blocking};
 
+pub mod hooks;
+pub mod log;
+pub mod ports;
+
+pub use hooks::LineHook;
+pub use log::{SessionLog, SessionLogConfig};
+
 type Tx = Box<dyn Write + Send>;
 type Rx = Box<dyn Read + Send>;
-type OnlineCallback = Box<dyn Fn(&TermHandle, &str) + Send + Sync>;
 
 /// Interactive serial terminal.
 ///
@@ -44,20 +52,39 @@ type OnlineCallback = Box<dyn Fn(&TermHandle, &str) + Send + Sync>;
 pub struct SerialTerm {
     tx: Arc<Mutex<Tx>>,
     rx: Arc<Mutex<Rx>>,
-    on_line: Option<OnlineCallback>,
+    handle: Arc<TermHandle>,
+    log: Arc<Mutex<Option<SessionLog>>>,
 }
 
 /// Handle for controlling the terminal session.
 ///
-/// Provides methods to stop the terminal from within callbacks.
+/// Provides methods to stop the terminal and to add or remove
+/// [`LineHook`]s, also from within a hook.
 pub struct TermHandle {
     is_running: AtomicBool,
+    hooks: Mutex<hooks::Hooks>,
+    /// Bytes queued by [`Self::send`].
+    input: Mutex<Vec<u8>>,
 }
 
 impl TermHandle {
+    fn new() -> Self {
+        Self {
+            is_running: AtomicBool::new(true),
+            hooks: Mutex::default(),
+            input: Mutex::default(),
+        }
+    }
+
+    /// Sends `data` to the serial port, e.g. to answer a prompt from a
+    /// line hook. It is written once the current line's hooks have run.
+    pub fn send(&self, data: impl AsRef<[u8]>) {
+        self.input.lock().unwrap().extend_from_slice(data.as_ref());
+    }
+
     /// Stops the terminal session.
     ///
-    /// This can be called from within a line callback to terminate the session
+    /// This can be called from within a line hook to terminate the session
     /// when a specific pattern is detected.
     pub fn stop(&self) {
         self.is_running
@@ -84,21 +111,36 @@ impl SerialTerm {
     ///
     /// * `tx` - Writer for sending data to the serial port.
     /// * `rx` - Reader for receiving data from the serial port.
-    /// * `on_line` - Callback function invoked for each complete line received.
-    pub fn new<F>(tx: Tx, rx: Rx, on_line: F) -> Self
-    where
-        F: Fn(&TermHandle, &str) + Send + Sync + 'static,
-    {
+    ///
+    /// Received lines are passed to the hooks added with [`Self::add_hook`].
+    pub fn new(tx: Tx, rx: Rx) -> Self {
         SerialTerm {
             tx: Arc::new(Mutex::new(tx)),
             rx: Arc::new(Mutex::new(rx)),
-            on_line: Some(Box::new(on_line)),
+            handle: Arc::new(TermHandle::new()),
+            log: Arc::default(),
         }
     }
 
+    /// Copies everything received from now on to `log`.
+    pub fn set_log(&self, log: SessionLog) {
+        *self.log.lock().unwrap() = Some(log);
+    }
+
+    /// Registers `hook`, replacing the hook of the same name.
+    pub fn add_hook(&self, hook: LineHook) {
+        self.handle.add_hook(hook);
+    }
+
+    /// Handle of the session, for adding and removing hooks or stopping
+    /// the terminal from elsewhere while it runs.
+    pub fn handle(&self) -> Arc<TermHandle> {
+        self.handle.clone()
+    }
+
     /// Runs the interactive serial terminal.
     ///
-    /// This method blocks until the user exits (Ctrl+A x) or the line callback
+    /// This method blocks until the user exits (Ctrl+A x) or a line hook
     /// calls `TermHandle::stop()`.
     ///
     /// # Errors
@@ -118,7 +160,7 @@ impl SerialTerm {
         if cleanup_needed {
             let _ = disable_raw_mode();
             println!(); // 添加换行符
-            eprintln!("✓ 已退出串口终端模式");
+            eprintln!("{}", t!("sterm.exited"));
         }
 
         result
@@ -127,21 +169,19 @@ impl SerialTerm {
     async fn run_terminal(&mut self) -> anyhow::Result<()> {
         let tx_port = self.tx.clone();
         let rx_port = self.rx.clone();
+        let log = self.log.clone();
 
-        let on_line = self.on_line.take().unwrap();
-
-        let handle = Arc::new(TermHandle {
-            is_running: AtomicBool::new(true),
-        });
+        let handle = self.handle.clone();
 
         // 使用 EventStream 异步处理键盘事件
+        let tx_port_for_hooks = tx_port.clone();
         let tx_handle = tokio::spawn(Self::tx_work_async(handle.clone(), tx_port));
 
         let tx_abort = tx_handle.abort_handle();
         // 启动串口接收线程
         let rx_handle = spawn_blocking({
             let handle = handle.clone();
-            move || Self::handle_serial_receive(rx_port, handle, tx_abort, on_line)
+            move || Self::handle_serial_receive(rx_port, tx_port_for_hooks, handle, log, tx_abort)
         });
         // 等待接收线程结束
         let _ = rx_handle.await?;
@@ -150,18 +190,18 @@ impl SerialTerm {
         Ok(())
     }
 
-    fn handle_serial_receive<F>(
+    fn handle_serial_receive(
         rx_port: Arc<Mutex<Rx>>,
+        tx_port: Arc<Mutex<Tx>>,
         handle: Arc<TermHandle>,
+        log: Arc<Mutex<Option<SessionLog>>>,
         tx_abort: AbortHandle,
-        on_line: F,
-    ) -> io::Result<()>
-    where
-        F: Fn(&TermHandle, &str) + Send + Sync + 'static,
-    {
+    ) -> io::Result<()> {
         let mut buffer = [0u8; 1024];
         let mut byte = [0u8; 1];
         let mut line = Vec::with_capacity(0x1000);
+        // 未完成的行已作为提示符交给钩子的长度
+        let mut prompted = 0;
 
         while handle.is_running() {
             // 从串口读取数据
@@ -169,18 +209,26 @@ impl SerialTerm {
                 Ok(bytes_read) if bytes_read > 0 => {
                     // 将数据直接写入stdout
                     let data = &buffer[..bytes_read];
+                    Self::write_log(&log, data);
                     for &b in data {
                         line.push(b);
                         if b == b'\n' {
                             byte[0] = b'\r';
                             io::stdout().write_all(&byte)?;
                             let line_str = String::from_utf8_lossy(&line);
-                            (on_line)(handle.as_ref(), &line_str);
+                            handle.dispatch(&line_str);
                             line.clear();
+                            prompted = 0;
+                            Self::flush_input(&handle, &tx_port)?;
                         }
                         byte[0] = b;
                         io::stdout().write_all(&byte)?;
                     }
+                    if line.len() > prompted {
+                        prompted = line.len();
+                        handle.dispatch_prompt(&String::from_utf8_lossy(&line));
+                        Self::flush_input(&handle, &tx_port)?;
+                    }
 
                     io::stdout().flush()?;
                 }
@@ -197,7 +245,7 @@ impl SerialTerm {
                     }
                 }
                 Err(e) => {
-                    eprintln!("\n串口读取错误: {}", e);
+                    eprintln!("\n{}", t!("sterm.read_error", error = e));
                     break;
                 }
             }
@@ -206,6 +254,31 @@ impl SerialTerm {
         Ok(())
     }
 
+    /// 写入钩子通过 [`TermHandle::send`] 排队的数据
+    fn flush_input(handle: &TermHandle, tx_port: &Mutex<Tx>) -> io::Result<()> {
+        let input = std::mem::take(&mut *handle.input.lock().unwrap());
+        if !input.is_empty() {
+            let mut tx = tx_port.lock().unwrap();
+            tx.write_all(&input)?;
+            tx.flush()?;
+        }
+        Ok(())
+    }
+
+    /// 写入失败后不再记录，避免每次读取都报错
+    fn write_log(log: &Mutex<Option<SessionLog>>, data: &[u8]) {
+        let mut log = log.lock().unwrap();
+        if let Some(file) = log.as_mut()
+            && let Err(e) = file.write(data)
+        {
+            eprintln!(
+                "\r\n{}",
+                t!("sterm.log_failed", path = file.path().display(), error = e)
+            );
+            *log = None;
+        }
+    }
+
     fn send_key_to_serial(
         tx_port: &Arc<Mutex<Tx>>,
         key: crossterm::event::KeyEvent,
@@ -522,24 +595,27 @@ impl SerialTerm {
                             } else {
                                 // 普通按键，发送到串口
                                 if let Err(e) = Self::send_key_to_serial(&tx_port, key) {
-                                    eprintln!("\r\n发送按键失败: {}", e);
+                                    eprintln!("\r\n{}", t!("sterm.send_key_failed", error = e));
                                 }
                             }
                         }
                         KeySequenceState::CtrlAPressed => {
                             if key.code == KeyCode::Char('x') {
                                 // 用户请求退出
-                                eprintln!("\r\nExit by: Ctrl+A+x");
+                                eprintln!("\r\n{}", t!("sterm.exit_by"));
                                 handle.stop();
                                 break;
                             } else {
                                 // 不是x键，发送上一个按键并重置状态
                                 if key.code != KeyCode::Char('a') {
                                     if let Err(e) = Self::send_ctrl_a_to_serial(&tx_port) {
-                                        eprintln!("\r\n发送 Ctrl+A 失败: {}", e);
+                                        eprintln!(
+                                            "\r\n{}",
+                                            t!("sterm.send_ctrl_a_failed", error = e)
+                                        );
                                     }
                                     if let Err(e) = Self::send_key_to_serial(&tx_port, key) {
-                                        eprintln!("\r\n发送按键失败: {}", e);
+                                        eprintln!("\r\n{}", t!("sterm.send_key_failed", error = e));
                                     }
                                     key_state = KeySequenceState::Normal;
                                 }
@@ -548,7 +624,7 @@ impl SerialTerm {
                     }
                 }
                 Some(Err(e)) => {
-                    eprintln!("\r\n键盘事件错误: {}", e);
+                    eprintln!("\r\n{}", t!("sterm.key_event_error", error = e));
                     break;
                 }
                 None => {
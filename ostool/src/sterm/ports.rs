@@ -0,0 +1,273 @@
+//! Picking the serial port of a board.
+//!
+//! When the configured port is absent, e.g. because the adapter came up as
+//! `/dev/ttyUSB1` this time, the USB serial devices present are the
+//! candidates. A device chosen before for the same configured port is used
+//! again; a single candidate is used as is; otherwise the user picks one
+//! from a list showing VID:PID, manufacturer and a sniff of each port's
+//! output, and the choice is remembered in the user
+//! [`Settings`](crate::settings::Settings).
+
+use std::{
+    io::{self, BufRead, IsTerminal, Read, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use colored::Colorize;
+use jkconfig::t;
+use serialport::{SerialPortInfo, SerialPortType};
+
+use crate::settings::Settings;
+
+/// How long each candidate is listened to.
+const SNIFF_TIME: Duration = Duration::from_millis(500);
+/// Longest sniff shown.
+const SNIFF_LEN: usize = 48;
+
+/// A USB serial device that may be the board's console.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Candidate {
+    /// Port name, e.g. `/dev/ttyUSB1` or `COM4`.
+    pub name: String,
+    pub vid: u16,
+    pub pid: u16,
+    pub serial_number: Option<String>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    /// Last printable output seen on the port.
+    pub sniff: String,
+}
+
+impl Candidate {
+    /// Stable identity of the device, `VID:PID[:SERIAL]`.
+    pub fn id(&self) -> String {
+        let mut id = format!("{:04x}:{:04x}", self.vid, self.pid);
+        if let Some(serial) = &self.serial_number {
+            id += ":";
+            id += serial;
+        }
+        id
+    }
+
+    fn describe(&self) -> String {
+        let mut text = format!("{}  {}", self.name.bold(), self.id());
+        for s in [&self.manufacturer, &self.product].into_iter().flatten() {
+            text += "  ";
+            text += s;
+        }
+        if !self.sniff.is_empty() {
+            text += &format!("  \"{}\"", self.sniff).dimmed().to_string();
+        }
+        text
+    }
+}
+
+/// The port to open for the configured `port` at `baud`, see the module
+/// documentation.
+///
+/// # Errors
+///
+/// Returns an error if there is no candidate, or several and no terminal to
+/// pick one on, or the user gives no valid choice.
+pub fn resolve(port: &str, baud: u32) -> anyhow::Result<String> {
+    let ports = serialport::available_ports().unwrap_or_default();
+    let name = normalize(port);
+    if Path::new(&name).exists() || ports.iter().any(|p| normalize(&p.port_name) == name) {
+        return Ok(name);
+    }
+    let mut candidates = candidates(&ports);
+
+    let mut settings = Settings::load();
+    if let Some(c) = settings
+        .serial_ports
+        .get(port)
+        .and_then(|id| candidates.iter().find(|c| &c.id() == id))
+    {
+        info!("{}", t!("ports.remembered", port = port, name = c.name));
+        return Ok(c.name.clone());
+    }
+    match candidates.len() {
+        0 => bail!(t!("ports.none", port = port)),
+        1 => {
+            warn!(
+                "{}",
+                t!("ports.only", port = port, name = candidates[0].name)
+            );
+            return Ok(candidates.remove(0).name);
+        }
+        _ => {}
+    }
+
+    for c in &mut candidates {
+        c.sniff = sniff(&c.name, baud);
+    }
+    println!("{}", t!("ports.absent", port = port).yellow());
+    for (i, c) in candidates.iter().enumerate() {
+        println!("  {}) {}", i + 1, c.describe());
+    }
+    if !io::stdin().is_terminal() {
+        bail!(t!("ports.need_port"));
+    }
+    print!("{}", t!("ports.choose", n = candidates.len()));
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    let Some(i) = parse_choice(&answer, candidates.len()) else {
+        bail!(t!("ports.bad_choice"));
+    };
+    let chosen = &candidates[i];
+    settings.serial_ports.insert(port.to_string(), chosen.id());
+    if let Err(e) = settings.save() {
+        warn!("{}: {e:#}", t!("ports.save_failed"));
+    }
+    Ok(chosen.name.clone())
+}
+
+/// Name of `port` as serialport opens and lists it.
+///
+/// On Windows `com3`, `\\.\COM3` and the MSYS2 name `/dev/ttyS2` all
+/// become `COM3`; elsewhere the name is kept.
+pub fn normalize(port: &str) -> String {
+    if cfg!(windows) {
+        windows_name(port)
+    } else {
+        port.to_string()
+    }
+}
+
+fn windows_name(port: &str) -> String {
+    let name = port.trim();
+    // serialport 自己会加上 \\.\ 前缀
+    let name = name.strip_prefix(r"\\.\").unwrap_or(name);
+    // MSYS2/Cygwin 的 /dev/ttyS<n> 对应 COM<n+1>
+    if let Some(n) = name
+        .strip_prefix("/dev/ttyS")
+        .and_then(|n| n.parse::<u32>().ok())
+    {
+        return format!("COM{}", n + 1);
+    }
+    match name.split_at_checked(3) {
+        Some((com, n)) if com.eq_ignore_ascii_case("com") && n.parse::<u32>().is_ok() => {
+            format!("COM{n}")
+        }
+        _ => name.to_string(),
+    }
+}
+
+/// USB serial devices among `ports`.
+fn candidates(ports: &[SerialPortInfo]) -> Vec<Candidate> {
+    ports
+        .iter()
+        .filter_map(|p| match &p.port_type {
+            SerialPortType::UsbPort(usb) => Some(Candidate {
+                name: p.port_name.clone(),
+                vid: usb.vid,
+                pid: usb.pid,
+                serial_number: usb.serial_number.clone(),
+                manufacturer: usb.manufacturer.clone(),
+                product: usb.product.clone(),
+                sniff: String::new(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Listens on the port for a moment and returns the last printable line.
+fn sniff(name: &str, baud: u32) -> String {
+    let Ok(mut port) = serialport::new(name, baud)
+        .timeout(Duration::from_millis(50))
+        .open()
+    else {
+        return String::new();
+    };
+    let mut data = Vec::new();
+    let mut buf = [0u8; 256];
+    let deadline = Instant::now() + SNIFF_TIME;
+    while Instant::now() < deadline {
+        if let Ok(n) = port.read(&mut buf) {
+            data.extend_from_slice(&buf[..n]);
+        }
+    }
+    sniff_text(&data)
+}
+
+fn sniff_text(data: &[u8]) -> String {
+    let text = String::from_utf8_lossy(data);
+    let line = text
+        .lines()
+        .map(|l| l.chars().filter(|c| !c.is_control()).collect::<String>())
+        .rfind(|l| !l.trim().is_empty())
+        .unwrap_or_default();
+    let line = line.trim();
+    match line.char_indices().nth(SNIFF_LEN) {
+        Some((at, _)) => format!("{}...", &line[..at]),
+        None => line.to_string(),
+    }
+}
+
+/// Index of the 1-based choice in `answer` among `n` candidates.
+fn parse_choice(answer: &str, n: usize) -> Option<usize> {
+    answer
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|i| (1..=n).contains(i))
+        .map(|i| i - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use serialport::UsbPortInfo;
+
+    use super::*;
+
+    #[test]
+    fn test_candidates() {
+        let ports = [
+            SerialPortInfo {
+                port_name: "/dev/ttyS0".to_string(),
+                port_type: SerialPortType::Unknown,
+            },
+            SerialPortInfo {
+                port_name: "/dev/ttyUSB1".to_string(),
+                port_type: SerialPortType::UsbPort(UsbPortInfo {
+                    vid: 0x1a86,
+                    pid: 0x7523,
+                    serial_number: Some("ABC".to_string()),
+                    manufacturer: Some("QinHeng".to_string()),
+                    product: None,
+                }),
+            },
+        ];
+        let candidates = candidates(&ports);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].name, "/dev/ttyUSB1");
+        assert_eq!(candidates[0].id(), "1a86:7523:ABC");
+    }
+
+    #[test]
+    fn test_windows_name() {
+        assert_eq!(windows_name("com3"), "COM3");
+        assert_eq!(windows_name(r"\\.\COM12"), "COM12");
+        assert_eq!(windows_name("/dev/ttyS0"), "COM1");
+        assert_eq!(windows_name(" COM4 "), "COM4");
+        assert_eq!(windows_name("common"), "common");
+        assert_eq!(normalize("/dev/ttyUSB0"), "/dev/ttyUSB0");
+    }
+
+    #[test]
+    fn test_sniff_and_choice() {
+        assert_eq!(
+            sniff_text(b"U-Boot 2024.01\r\n=> \x1b[0m\r\n\r\n"),
+            "=> [0m"
+        );
+        assert_eq!(sniff_text(b"\0\0"), "");
+        assert_eq!(sniff_text(&[b'x'; 100]).len(), SNIFF_LEN + 3);
+        assert_eq!(parse_choice(" 2\n", 3), Some(1));
+        assert_eq!(parse_choice("0", 3), None);
+        assert_eq!(parse_choice("4", 3), None);
+        assert_eq!(parse_choice("a", 3), None);
+    }
+}
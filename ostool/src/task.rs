@@ -0,0 +1,229 @@
+//! Project tasks, `ostool task`.
+//!
+//! The `[tasks]` table of `.build.toml` names sequences of ostool
+//! subcommands, shell commands and other tasks, replacing wrapper
+//! Makefiles:
+//!
+//! ```toml
+//! [tasks]
+//! release = [
+//!     { ostool = "build --set system.Cargo.release=true" },
+//!     { ostool = "mkimage -o target/disk.img" },
+//!     { sh = "cp target/disk.img ${env:TFTP_DIR}/" },
+//! ]
+//! ci = [{ task = "release" }, { ostool = "test" }]
+//! ```
+//!
+//! Steps run in order and the task stops at the first failing one. An
+//! `ostool` step runs this executable again with the workspace, `--set`
+//! overrides and `--offline` of the task; a `sh` step runs in the manifest
+//! directory like `build_cmd`. `${workspaceFolder}` and `${env:VAR}` are
+//! replaced in both.
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use colored::Colorize;
+use jkconfig::t;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{ctx::AppContext, exit::Failure, utils::replace_env_placeholders};
+
+/// One step of a task.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStep {
+    /// ostool subcommand with its arguments, e.g. `run qemu -d`
+    Ostool(String),
+    /// Shell command
+    Sh(String),
+    /// Another task of the table
+    Task(String),
+}
+
+impl TaskStep {
+    fn describe(&self) -> String {
+        match self {
+            TaskStep::Ostool(args) => format!("ostool {args}"),
+            TaskStep::Sh(cmd) => cmd.clone(),
+            TaskStep::Task(name) => format!("task {name}"),
+        }
+    }
+}
+
+/// Arguments for running a task.
+#[derive(Debug, Clone, Default)]
+pub struct RunTaskArgs {
+    /// Task to run; the tasks are listed when `None`.
+    pub name: Option<String>,
+    /// Optional path to the build configuration file.
+    pub build_config: Option<PathBuf>,
+    /// Global arguments passed on to `ostool` steps, e.g. `--offline`.
+    pub global_args: Vec<String>,
+}
+
+/// Runs the task `args.name` of `.build.toml`, or lists the tasks.
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded, the task is
+/// unknown or runs itself, or a step fails; a failing `ostool` step keeps
+/// its kind, see [`crate::exit`].
+pub async fn run_task(mut ctx: AppContext, args: RunTaskArgs) -> anyhow::Result<()> {
+    let config = ctx.prepare_build_config(args.build_config, false).await?;
+    let Some(name) = args.name else {
+        if config.tasks.is_empty() {
+            println!("{}", t!("task.none"));
+        }
+        for (name, steps) in &config.tasks {
+            let steps = steps.iter().map(TaskStep::describe).collect::<Vec<_>>();
+            println!("{:<20} {}", name.bold(), steps.join(" && ").dimmed());
+        }
+        return Ok(());
+    };
+    let mut runner = Runner {
+        ctx: &ctx,
+        tasks: &config.tasks,
+        global_args: &args.global_args,
+        stack: vec![],
+    };
+    runner.run(&name)
+}
+
+struct Runner<'a> {
+    ctx: &'a AppContext,
+    tasks: &'a BTreeMap<String, Vec<TaskStep>>,
+    global_args: &'a [String],
+    /// Tasks being run, outermost first.
+    stack: Vec<String>,
+}
+
+impl Runner<'_> {
+    fn run(&mut self, name: &str) -> anyhow::Result<()> {
+        let Some(steps) = self.tasks.get(name) else {
+            bail!(t!("task.unknown", name = name));
+        };
+        if self.stack.iter().any(|n| n == name) {
+            bail!(t!("task.cycle", name = name));
+        }
+        self.stack.push(name.to_string());
+        for (i, step) in steps.iter().enumerate() {
+            println!(
+                "{}",
+                format!("[{} {}/{}] {}", name, i + 1, steps.len(), step.describe())
+                    .cyan()
+                    .bold()
+            );
+            let msg = t!("task.failed", name = name, index = i + 1);
+            match step {
+                TaskStep::Ostool(args) => self.ostool(args, msg)?,
+                TaskStep::Sh(cmd) => {
+                    let cmd = replace_env_placeholders(cmd)?;
+                    self.ctx.shell_run_cmd(&cmd).map_err(|e| e.context(msg))?;
+                }
+                TaskStep::Task(task) => self.run(task)?,
+            }
+        }
+        self.stack.pop();
+        Ok(())
+    }
+
+    fn ostool(&self, args: &str, msg: String) -> anyhow::Result<()> {
+        let args = split_args(&replace_env_placeholders(args)?)?;
+        let exe = std::env::current_exe()?;
+        let mut cmd = self.ctx.command(&exe.display().to_string());
+        cmd.arg("--workdir")
+            .arg(&self.ctx.paths.workspace)
+            .args(self.global_args)
+            .args(args);
+        let status = cmd.status()?;
+        if status.success() {
+            return Ok(());
+        }
+        let msg = format!("{msg}: {status}");
+        Err(match Failure::from_status(status) {
+            Some(failure) => failure.error(msg),
+            None => anyhow!(msg),
+        })
+    }
+}
+
+/// Splits a command line into arguments, honoring single and double quotes.
+fn split_args(line: &str) -> anyhow::Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut arg = None::<String>;
+    let mut quote = None;
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => arg.get_or_insert_default().push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                arg.get_or_insert_default();
+            }
+            (None, c) if c.is_whitespace() => args.extend(arg.take()),
+            (None, c) => arg.get_or_insert_default().push(c),
+        }
+    }
+    if quote.is_some() {
+        bail!(t!("task.unclosed_quote", line = line));
+    }
+    args.extend(arg);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_args() {
+        assert_eq!(
+            split_args(r#"run qemu --set 'system.Cargo.features=["a b"]' "" -d"#).unwrap(),
+            [
+                "run",
+                "qemu",
+                "--set",
+                r#"system.Cargo.features=["a b"]"#,
+                "",
+                "-d"
+            ]
+        );
+        assert!(split_args("build 'oops").is_err());
+    }
+
+    #[test]
+    fn test_tasks_config() {
+        let config: crate::build::config::BuildConfig = toml::from_str(
+            r#"
+            [system.Custom]
+            build_cmd = "make"
+            elf_path = "kernel.elf"
+            to_bin = false
+
+            [tasks]
+            release = [{ ostool = "build" }, { sh = "cp a b" }]
+            loop = [{ task = "loop" }]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.tasks["release"],
+            [
+                TaskStep::Ostool("build".to_string()),
+                TaskStep::Sh("cp a b".to_string())
+            ]
+        );
+
+        let ctx = AppContext::default();
+        let mut runner = Runner {
+            ctx: &ctx,
+            tasks: &config.tasks,
+            global_args: &[],
+            stack: vec![],
+        };
+        let err = runner.run("loop").unwrap_err();
+        assert!(err.to_string().contains("loop"), "{err}");
+        assert!(runner.run("missing").is_err());
+    }
+}
@@ -0,0 +1,258 @@
+//! Config templates for common boards.
+//!
+//! A template is a set of known-good `.build.toml`, `.qemu.toml`,
+//! `.uboot.toml` and `.gdb.toml` files that pre-populate the menuconfig
+//! editor. Templates are built in (see [`BUILTIN`]), or read from a local
+//! directory or a URL holding `build.toml`, `qemu.toml`, `uboot.toml` and/or
+//! `gdb.toml`.
+
+use std::{collections::BTreeMap, path::Path, time::Duration};
+
+use anyhow::{Context, Result};
+use jkconfig::t;
+
+use crate::cache::{self, Cache};
+
+/// Config files a template may provide, as named in the workspace.
+pub const FILES: [&str; 4] = [".build.toml", ".qemu.toml", ".uboot.toml", ".gdb.toml"];
+
+/// A template embedded in ostool.
+#[derive(Debug)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Workspace file name and content.
+    pub files: &'static [(&'static str, &'static str)],
+}
+
+/// Built-in templates.
+pub const BUILTIN: &[Builtin] = &[
+    Builtin {
+        name: "qemu-virt-aarch64",
+        description: "QEMU virt machine, aarch64",
+        files: &[
+            (
+                ".build.toml",
+                include_str!("../templates/qemu-virt-aarch64/build.toml"),
+            ),
+            (
+                ".qemu.toml",
+                include_str!("../templates/qemu-virt-aarch64/qemu.toml"),
+            ),
+        ],
+    },
+    Builtin {
+        name: "raspi4",
+        description: "Raspberry Pi 4 Model B via U-Boot",
+        files: &[
+            (
+                ".build.toml",
+                include_str!("../templates/raspi4/build.toml"),
+            ),
+            (
+                ".uboot.toml",
+                include_str!("../templates/raspi4/uboot.toml"),
+            ),
+        ],
+    },
+    Builtin {
+        name: "visionfive2",
+        description: "StarFive VisionFive 2 via U-Boot",
+        files: &[
+            (
+                ".build.toml",
+                include_str!("../templates/visionfive2/build.toml"),
+            ),
+            (
+                ".uboot.toml",
+                include_str!("../templates/visionfive2/uboot.toml"),
+            ),
+        ],
+    },
+];
+
+/// A loaded template.
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub name: String,
+    /// Contents by workspace file name (`.build.toml`, ...).
+    pub files: BTreeMap<String, String>,
+}
+
+impl Template {
+    /// Load a template by built-in name, directory path or `http(s)` URL.
+    ///
+    /// URL templates are kept in the cache; `offline` uses the cached copy
+    /// without fetching.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name is unknown, or the directory or URL
+    /// holds none of the template files.
+    pub async fn load(source: &str, offline: bool) -> Result<Self> {
+        if let Some(builtin) = BUILTIN.iter().find(|b| b.name == source) {
+            return Ok(Self {
+                name: builtin.name.to_string(),
+                files: builtin
+                    .files
+                    .iter()
+                    .map(|(file, content)| (file.to_string(), content.to_string()))
+                    .collect(),
+            });
+        }
+
+        let files = if source.starts_with("http://") || source.starts_with("https://") {
+            let mut cache = Cache::open();
+            let key = cache::url_key(source);
+            let fetched = if offline {
+                Err(anyhow!(t!("cache.offline_missing", url = source)))
+            } else {
+                fetch(source).await
+            };
+            match fetched {
+                Ok(files) => {
+                    // 留一份在缓存里，下次下载失败时使用
+                    if !files.is_empty() {
+                        let dir = cache.path(cache::TEMPLATES, &key);
+                        let _ = std::fs::remove_dir_all(&dir);
+                        std::fs::create_dir_all(&dir)?;
+                        for (file, content) in &files {
+                            std::fs::write(dir.join(&file[1..]), content)?;
+                        }
+                        cache.insert(cache::TEMPLATES, &key, Some(source))?;
+                    }
+                    files
+                }
+                Err(e) => match cache.lookup(cache::TEMPLATES, &key) {
+                    Some(dir) => {
+                        if !offline {
+                            warn!("{e:#}");
+                            warn!("{}", t!("cache.stale", path = dir.display()));
+                        }
+                        read_dir(&dir)?
+                    }
+                    None => return Err(e),
+                },
+            }
+        } else if Path::new(source).is_dir() {
+            read_dir(Path::new(source))?
+        } else {
+            let names = BUILTIN.iter().map(|b| b.name).collect::<Vec<_>>();
+            bail!(
+                "Unknown template `{source}`, available: {}",
+                names.join(", ")
+            );
+        };
+
+        if files.is_empty() {
+            bail!(
+                "Template `{source}` has none of {}",
+                FILES.map(|f| &f[1..]).join(", ")
+            );
+        }
+        Ok(Self {
+            name: source.to_string(),
+            files,
+        })
+    }
+
+    /// Content of `file` (e.g. `.qemu.toml`), if the template provides it.
+    pub fn file(&self, file: &str) -> Option<&str> {
+        self.files.get(file).map(String::as_str)
+    }
+
+    /// Like [`Self::file`], failing when the template lacks the file.
+    pub fn require(&self, file: &str) -> Result<&str> {
+        self.file(file)
+            .with_context(|| format!("Template `{}` has no {file}", self.name))
+    }
+}
+
+/// Fetch the template files below `url`.
+async fn fetch(url: &str) -> Result<BTreeMap<String, String>> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let mut files = BTreeMap::new();
+    for file in FILES {
+        let url = format!("{}/{}", url.trim_end_matches('/'), &file[1..]);
+        let resp = client
+            .get(&url)
+            .header("User-Agent", "ostool")
+            .send()
+            .await
+            .with_context(|| format!("Failed to fetch {url}"))?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            continue;
+        }
+        let resp = resp
+            .error_for_status()
+            .with_context(|| format!("Failed to fetch {url}"))?;
+        files.insert(file.to_string(), resp.text().await?);
+    }
+    Ok(files)
+}
+
+/// Read the template files in `dir`.
+fn read_dir(dir: &Path) -> Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    for file in FILES {
+        let path = dir.join(&file[1..]);
+        if path.exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            files.insert(file.to_string(), content);
+        }
+    }
+    Ok(files)
+}
+
+/// Print the built-in templates and their files.
+pub fn list() {
+    for builtin in BUILTIN {
+        let files = builtin
+            .files
+            .iter()
+            .map(|(file, _)| *file)
+            .collect::<Vec<_>>();
+        println!(
+            "{:<20} {} ({})",
+            builtin.name,
+            builtin.description,
+            files.join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        build::config::BuildConfig, run::gdb::GdbConfig, run::qemu::QemuConfig,
+        run::uboot::UbootConfig,
+    };
+
+    #[test]
+    fn test_builtin_templates_parse() {
+        for Builtin { name, files, .. } in BUILTIN {
+            for (file, content) in *files {
+                let result = match *file {
+                    ".build.toml" => toml::from_str::<BuildConfig>(content).map(|_| ()),
+                    ".qemu.toml" => toml::from_str::<QemuConfig>(content).map(|_| ()),
+                    ".uboot.toml" => toml::from_str::<UbootConfig>(content).map(|_| ()),
+                    ".gdb.toml" => toml::from_str::<GdbConfig>(content).map(|_| ()),
+                    _ => panic!("{name}: unexpected file {file}"),
+                };
+                result.unwrap_or_else(|e| panic!("{name}/{file}: {e}"));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_load_template() {
+        let template = Template::load("raspi4", false).await.unwrap();
+        assert!(template.file(".build.toml").is_some());
+        assert!(template.require(".qemu.toml").is_err());
+        assert!(Template::load("no-such-board", false).await.is_err());
+    }
+}
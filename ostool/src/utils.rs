@@ -6,7 +6,7 @@
 use std::{
     ffi::OsStr,
     ops::{Deref, DerefMut},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 use anyhow::bail;
@@ -51,7 +51,21 @@ impl Command {
     where
         S: AsRef<OsStr>,
     {
-        let mut cmd = std::process::Command::new(program);
+        // Windows 上不在 PATH 中的工具再到 MSYS2 中查找
+        let program = program.as_ref();
+        let msys2 = program
+            .to_str()
+            .filter(|name| {
+                cfg!(windows) && !name.contains(['/', '\\']) && find_in_path(name).is_none()
+            })
+            .and_then(|name| find_in(msys2_bin_dirs(), name));
+        let mut cmd = match msys2 {
+            Some(path) => {
+                debug!("Using {} from MSYS2", path.display());
+                std::process::Command::new(path)
+            }
+            None => std::process::Command::new(program),
+        };
         cmd.current_dir(workdir);
         cmd.env("WORKSPACE_FOLDER", workdir.display().to_string());
 
@@ -61,16 +75,21 @@ impl Command {
         }
     }
 
-    /// Prints the command to stdout with colored formatting.
-    pub fn print_cmd(&self) {
+    /// The program and its arguments as one line.
+    pub fn cmd_string(&self) -> String {
         let mut cmd_str = self.get_program().to_string_lossy().to_string();
-
         for arg in self.get_args() {
             cmd_str += " ";
             cmd_str += arg.to_string_lossy().as_ref();
         }
+        cmd_str
+    }
 
-        println!("{}", cmd_str.purple().bold());
+    /// Prints the command to stdout with colored formatting.
+    pub fn print_cmd(&self) {
+        if log_enabled!(log::Level::Info) {
+            println!("{}", self.cmd_string().purple().bold());
+        }
     }
 
     /// Executes the command and waits for it to complete.
@@ -225,7 +244,8 @@ pub fn replace_env_placeholders(input: &str) -> anyhow::Result<String> {
                 // 获取环境变量值，如果不存在则替换为空字符串
                 match env::var(env_var_name) {
                     Ok(value) => {
-                        println!("Using {env_var_name}={value}");
+                        // 不打印变量值，其中可能是 token 等敏感信息
+                        info!("Using ${{env:{env_var_name}}}");
                         result.push_str(&value)
                     }
                     Err(_) => {
@@ -249,6 +269,102 @@ pub fn replace_env_placeholders(input: &str) -> anyhow::Result<String> {
     Ok(result)
 }
 
+/// Parses a decimal or `0x`-prefixed hexadecimal integer, as used for
+/// addresses in configuration files.
+///
+/// # Example
+///
+/// ```rust
+/// use ostool::utils::parse_int;
+///
+/// assert_eq!(parse_int("0x80200000"), Some(0x8020_0000));
+/// assert_eq!(parse_int("4096"), Some(4096));
+/// ```
+pub fn parse_int(s: &str) -> Option<u64> {
+    let s = s.trim();
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u64::from_str_radix(&hex.replace('_', ""), 16).ok()
+    } else {
+        s.replace('_', "").parse().ok()
+    }
+}
+
+/// Looks up `program` in the directories of `PATH`, like `which`, and on
+/// Windows then in the MSYS2 installation, see [`msys2_bin_dirs`].
+pub fn find_program(program: &str) -> Option<PathBuf> {
+    find_in_path(program).or_else(|| find_in(msys2_bin_dirs(), program))
+}
+
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    find_in(std::env::split_paths(&paths), program)
+}
+
+fn find_in(dirs: impl IntoIterator<Item = PathBuf>, program: &str) -> Option<PathBuf> {
+    dirs.into_iter().find_map(|dir| {
+        let path = dir.join(program);
+        if path.is_file() {
+            return Some(path);
+        }
+        let exe = path.with_extension(std::env::consts::EXE_EXTENSION);
+        exe.is_file().then_some(exe)
+    })
+}
+
+/// `bin` directories of the MSYS2 installation, where Windows users
+/// usually get QEMU, GDB and the binutils: that of the active environment
+/// (`MSYSTEM_PREFIX`, e.g. `/ucrt64`) first, then `ucrt64`, `mingw64`,
+/// `clang64` and `usr`, under `MSYS2_ROOT` or `C:\msys64`.
+///
+/// Empty on other systems.
+pub fn msys2_bin_dirs() -> Vec<PathBuf> {
+    if !cfg!(windows) {
+        return vec![];
+    }
+    let root =
+        std::env::var_os("MSYS2_ROOT").map_or_else(|| PathBuf::from(r"C:\msys64"), PathBuf::from);
+    let prefix = std::env::var("MSYSTEM_PREFIX").ok();
+    msys2_dirs(&root, prefix.as_deref())
+}
+
+fn msys2_dirs(root: &Path, prefix: Option<&str>) -> Vec<PathBuf> {
+    let mut envs = vec![];
+    // MSYSTEM_PREFIX 是 MSYS2 内部的 POSIX 路径，如 /ucrt64
+    if let Some(prefix) = prefix
+        .map(|p| p.trim_matches(['/', '\\']))
+        .filter(|p| !p.is_empty())
+    {
+        envs.push(prefix);
+    }
+    for env in ["ucrt64", "mingw64", "clang64", "usr"] {
+        if !envs.contains(&env) {
+            envs.push(env);
+        }
+    }
+    envs.into_iter()
+        .map(|env| root.join(env).join("bin"))
+        .collect()
+}
+
+/// Formats a byte count with a binary unit, e.g. `1.5 MiB`.
+pub fn human_size(bytes: u64) -> String {
+    format!(
+        "{:.1}",
+        byte_unit::Byte::from_u64(bytes).get_appropriate_unit(byte_unit::UnitType::Binary)
+    )
+}
+
+/// Apply `OSTOOL_LANG` (e.g. `zh`, `en_US.UTF-8`) to the shared message
+/// catalogs; without it jkconfig's own detection is used.
+pub fn apply_lang_env() {
+    if let Ok(value) = std::env::var("OSTOOL_LANG") {
+        match jkconfig::i18n::Lang::parse(&value) {
+            Some(lang) => jkconfig::i18n::set_lang(lang),
+            None => log::warn!("Unknown OSTOOL_LANG `{value}`"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -380,4 +496,14 @@ mod tests {
         assert_eq!(replace_env_placeholders("{env:VAR}").unwrap(), "{env:VAR}");
         assert_eq!(replace_env_placeholders("$env:VAR}").unwrap(), "$env:VAR}");
     }
+
+    #[test]
+    fn test_msys2_dirs() {
+        let root = Path::new("msys64");
+        let dirs = msys2_dirs(root, Some("/mingw64"));
+        assert_eq!(dirs[0], root.join("mingw64").join("bin"));
+        assert_eq!(dirs.len(), 4);
+        assert_eq!(msys2_dirs(root, None)[0], root.join("ucrt64").join("bin"));
+        assert_eq!(msys2_dirs(root, Some("/")).len(), 4);
+    }
 }
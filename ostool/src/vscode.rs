@@ -0,0 +1,336 @@
+//! VS Code debugging setup, `ostool gen vscode`.
+//!
+//! Adds two tasks to `.vscode/tasks.json`: `ostool build`, and a background
+//! `ostool run qemu -d` that VS Code waits for until QEMU listens for a
+//! debugger. `.vscode/launch.json` gets configurations attaching to that
+//! gdbstub with cppdbg (the C/C++ extension) and CodeLLDB, which start the
+//! QEMU task first. The ELF, debugger, gdbstub address and breakpoints come
+//! from `.build.toml` and `.gdb.toml`.
+//!
+//! Entries generated earlier are replaced by their label or name; all
+//! other entries of the files are kept.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use jkconfig::t;
+use object::Architecture;
+use serde_json::{Value, json};
+
+use crate::{
+    ctx::AppContext,
+    run::gdb::{self, DebuggerKind, GdbConfig, RunGdbArgs},
+};
+
+/// Label of the build task.
+pub const BUILD_TASK: &str = "ostool: build";
+/// Label of the task running QEMU in debug mode.
+pub const QEMU_TASK: &str = "ostool: qemu debug";
+
+/// Arguments for generating the VS Code configuration.
+#[derive(Debug, Clone, Default)]
+pub struct GenVscodeArgs {
+    /// Optional path to the build configuration file.
+    pub build_config: Option<PathBuf>,
+    /// Optional path to the QEMU configuration file.
+    pub qemu_config: Option<PathBuf>,
+    /// Overwrite files that are not valid JSON, e.g. with comments.
+    pub force: bool,
+}
+
+/// What the generated tasks and launch configurations use.
+#[derive(Debug, Clone, PartialEq)]
+struct Setup {
+    /// ELF with the symbols, as VS Code resolves it.
+    elf: String,
+    arch: Option<Architecture>,
+    /// gdb speaking the MI protocol for cppdbg.
+    gdb: String,
+    /// `host:port` of the gdbstub.
+    address: String,
+    breakpoints: Vec<String>,
+    /// `-c <file>` of `ostool build` and `ostool run`.
+    build_args: Vec<String>,
+    /// `-q <file>` of `ostool run qemu`.
+    qemu_args: Vec<String>,
+}
+
+impl Setup {
+    fn tasks(&self) -> Vec<Value> {
+        let mut build = vec!["build".to_string()];
+        build.extend(self.build_args.iter().cloned());
+        let mut qemu = vec!["run".to_string()];
+        qemu.extend(self.build_args.iter().cloned());
+        qemu.extend(["qemu".to_string(), "-d".to_string()]);
+        qemu.extend(self.qemu_args.iter().cloned());
+        vec![
+            json!({
+                "label": BUILD_TASK,
+                "type": "process",
+                "command": "ostool",
+                "args": build,
+                "group": { "kind": "build", "isDefault": true },
+                "problemMatcher": ["$rustc"],
+            }),
+            json!({
+                "label": QEMU_TASK,
+                "type": "process",
+                "command": "ostool",
+                "args": qemu,
+                "isBackground": true,
+                // QEMU 开始等待调试器时会打印 gdbstub 地址
+                "problemMatcher": {
+                    "owner": "ostool",
+                    "pattern": { "regexp": "^__never_matches__$" },
+                    "background": {
+                        "activeBegins": true,
+                        "beginsPattern": ".",
+                        "endsPattern": "localhost:1234",
+                    },
+                },
+            }),
+        ]
+    }
+
+    fn launch(&self) -> Vec<Value> {
+        let mut setup = Vec::new();
+        if let Some(name) = self.arch.and_then(gdb::gdb_arch) {
+            setup.push(json!({ "text": format!("set architecture {name}") }));
+        }
+        let breakpoints: Vec<_> = self
+            .breakpoints
+            .iter()
+            .map(|b| json!({ "text": format!("break {b}") }))
+            .collect();
+        let mut lldb_target = vec![format!("target create \"{}\"", self.elf)];
+        lldb_target.extend(self.breakpoints.iter().map(|b| format!("b {b}")));
+        vec![
+            json!({
+                "name": "ostool: QEMU (cppdbg)",
+                "type": "cppdbg",
+                "request": "launch",
+                "program": self.elf,
+                "cwd": "${workspaceFolder}",
+                "MIMode": "gdb",
+                "miDebuggerPath": self.gdb,
+                "miDebuggerServerAddress": self.address,
+                "setupCommands": setup,
+                "postRemoteConnectCommands": breakpoints,
+                "stopAtEntry": false,
+                "preLaunchTask": QEMU_TASK,
+            }),
+            json!({
+                "name": "ostool: QEMU (CodeLLDB)",
+                "type": "lldb",
+                "request": "custom",
+                "targetCreateCommands": lldb_target,
+                "processCreateCommands": [format!("gdb-remote {}", self.address)],
+                "preLaunchTask": QEMU_TASK,
+            }),
+        ]
+    }
+}
+
+/// gdb for cppdbg: the one `rust-gdb` wraps, as cppdbg cannot pass it
+/// `RUST_GDB`, and `gdb-multiarch` when only lldb is there.
+fn mi_gdb(config: &GdbConfig, arch: Option<Architecture>) -> String {
+    let Some(debugger) = arch.and_then(|arch| gdb::pick_debugger(config, arch).ok()) else {
+        return config
+            .debugger
+            .clone()
+            .unwrap_or_else(|| "gdb-multiarch".to_string());
+    };
+    match (debugger.kind, debugger.rust_gdb) {
+        (DebuggerKind::Lldb, _) => "gdb-multiarch".to_string(),
+        (DebuggerKind::Gdb, Some(gdb)) => gdb,
+        (DebuggerKind::Gdb, None) if debugger.program == "rust-gdb" => "gdb".to_string(),
+        (DebuggerKind::Gdb, None) => debugger.program,
+    }
+}
+
+/// `path` as VS Code resolves it, relative to `${workspaceFolder}` when
+/// inside `workspace`.
+fn workspace_path(workspace: &Path, path: &Path) -> String {
+    match path.strip_prefix(workspace) {
+        Ok(rel) => format!("${{workspaceFolder}}/{}", rel.display()),
+        Err(_) => path.display().to_string(),
+    }
+}
+
+/// A `.vscode` file holding a list of entries.
+#[derive(Debug, Clone, Copy)]
+struct File {
+    name: &'static str,
+    version: &'static str,
+    /// The array of entries.
+    list: &'static str,
+    /// Field naming an entry.
+    key: &'static str,
+}
+
+const TASKS: File = File {
+    name: "tasks.json",
+    version: "2.0.0",
+    list: "tasks",
+    key: "label",
+};
+
+const LAUNCH: File = File {
+    name: "launch.json",
+    version: "0.2.0",
+    list: "configurations",
+    key: "name",
+};
+
+/// Puts `entries` into the JSON `content` of `file`, replacing entries of
+/// the same name.
+fn merge(file: File, content: Option<&str>, entries: Vec<Value>) -> anyhow::Result<Value> {
+    let File { list, key, .. } = file;
+    let mut root = match content {
+        Some(content) => serde_json::from_str(content)?,
+        None => json!({ "version": file.version }),
+    };
+    let Some(object) = root.as_object_mut() else {
+        bail!("not a JSON object");
+    };
+    let items = object.entry(list).or_insert_with(|| json!([]));
+    let Some(items) = items.as_array_mut() else {
+        bail!("`{list}` is not an array");
+    };
+    for entry in entries {
+        match items.iter_mut().find(|item| item[key] == entry[key]) {
+            Some(item) => *item = entry,
+            None => items.push(entry),
+        }
+    }
+    Ok(root)
+}
+
+/// Merges `entries` into `file` of the directory `dir`.
+fn update(dir: &Path, file: File, entries: Vec<Value>, force: bool) -> anyhow::Result<()> {
+    let path = dir.join(file.name);
+    let content = std::fs::read_to_string(&path).ok();
+    let root = match merge(file, content.as_deref(), entries.clone()) {
+        Ok(root) => root,
+        Err(_) if force => merge(file, None, entries)?,
+        Err(e) => bail!(t!("vscode.unparsable", path = path.display(), error = e)),
+    };
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(&path, serde_json::to_string_pretty(&root)? + "\n")
+        .with_context(|| path.display().to_string())?;
+    println!("{}", t!("vscode.written", path = path.display()));
+    Ok(())
+}
+
+/// Writes the tasks and launch configurations for debugging the kernel in
+/// QEMU to `.vscode` of the workspace.
+///
+/// # Errors
+///
+/// Returns an error if the build configuration is invalid or a file cannot
+/// be updated.
+pub async fn gen_vscode(mut ctx: AppContext, args: GenVscodeArgs) -> anyhow::Result<()> {
+    let elf = gdb::locate_elf(
+        &mut ctx,
+        &RunGdbArgs {
+            build_config: args.build_config.clone(),
+            ..Default::default()
+        },
+    )
+    .await?;
+    // 尚未构建时按 .gdb.toml 或默认值生成
+    if elf.exists() {
+        ctx.set_elf_path(elf.clone()).await;
+    }
+    let config = gdb::load_config(&ctx, &ctx.paths.config_file(".gdb.toml"))
+        .await?
+        .unwrap_or_default();
+
+    let workspace = &ctx.paths.workspace;
+    let flag = |flag: &str, path: &Option<PathBuf>| match path {
+        Some(path) => vec![flag.to_string(), workspace_path(workspace, path)],
+        None => Vec::new(),
+    };
+    let setup = Setup {
+        elf: workspace_path(workspace, &elf),
+        arch: ctx.arch,
+        gdb: mi_gdb(&config, ctx.arch),
+        address: gdb::qemu_target(&config).address().to_string(),
+        breakpoints: config.breakpoints.clone(),
+        build_args: flag("-c", &args.build_config),
+        qemu_args: flag("-q", &args.qemu_config),
+    };
+
+    let dir = workspace.join(".vscode");
+    update(&dir, TASKS, setup.tasks(), args.force)?;
+    update(&dir, LAUNCH, setup.launch(), args.force)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_setup() {
+        let setup = Setup {
+            elf: workspace_path(Path::new("/w"), Path::new("/w/target/kernel")),
+            arch: Some(Architecture::Riscv64),
+            gdb: "gdb-multiarch".into(),
+            address: "localhost:1234".into(),
+            breakpoints: vec!["rust_main".into()],
+            build_args: vec!["-c".into(), "${workspaceFolder}/.build.toml".into()],
+            qemu_args: Vec::new(),
+        };
+        assert_eq!(setup.elf, "${workspaceFolder}/target/kernel");
+
+        let tasks = setup.tasks();
+        assert_eq!(
+            tasks[1]["args"],
+            json!(["run", "-c", "${workspaceFolder}/.build.toml", "qemu", "-d"])
+        );
+        let launch = setup.launch();
+        assert_eq!(launch[0]["miDebuggerServerAddress"], "localhost:1234");
+        assert_eq!(
+            launch[0]["setupCommands"][0]["text"],
+            "set architecture riscv:rv64"
+        );
+        assert_eq!(
+            launch[1]["targetCreateCommands"],
+            json!([
+                "target create \"${workspaceFolder}/target/kernel\"",
+                "b rust_main"
+            ])
+        );
+        assert_eq!(launch[1]["preLaunchTask"], QEMU_TASK);
+    }
+
+    #[test]
+    fn test_merge() {
+        let content = r#"{"version": "2.0.0", "tasks": [
+            {"label": "mine"}, {"label": "ostool: build", "command": "old"}
+        ]}"#;
+        let root = merge(
+            TASKS,
+            Some(content),
+            vec![
+                json!({"label": BUILD_TASK, "command": "ostool"}),
+                json!({"label": QEMU_TASK}),
+            ],
+        )
+        .unwrap();
+        let labels: Vec<_> = root["tasks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["label"].as_str().unwrap())
+            .collect();
+        assert_eq!(labels, ["mine", BUILD_TASK, QEMU_TASK]);
+        assert_eq!(root["tasks"][1]["command"], "ostool");
+
+        // 带注释的 JSON 无法合并
+        assert!(merge(LAUNCH, Some("// comment\n{}"), vec![]).is_err());
+        let root = merge(LAUNCH, None, vec![json!({"name": "a"})]).unwrap();
+        assert_eq!(root["version"], "0.2.0");
+    }
+}
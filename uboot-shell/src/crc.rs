@@ -1,7 +1,9 @@
-//! CRC16-CCITT checksum implementation.
+//! CRC16-CCITT and CRC32 checksum implementations.
 //!
 //! This module provides CRC16-CCITT checksum calculation used by the YMODEM protocol.
 //! The polynomial used is x^16 + x^12 + x^5 + 1 (0x1021).
+//!
+//! CRC32 matches U-Boot's `crc32` command and checks data loaded over the console.
 
 /// CRC16-CCITT lookup table - implements polynomial x^16+x^12+x^5+1
 const CRC16_TAB: &[u16] = &[
@@ -54,3 +56,28 @@ pub fn crc16_ccitt(mut cksum: u16, buf: &[u8]) -> u16 {
     }
     cksum
 }
+
+/// Calculates the CRC32 (IEEE 802.3) checksum of `buf`, as printed by
+/// U-Boot's `crc32` command.
+///
+/// # Example
+///
+/// ```rust
+/// use uboot_shell::crc::crc32;
+///
+/// assert_eq!(crc32(b"123456789"), 0xcbf43926);
+/// ```
+pub fn crc32(buf: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in buf {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
@@ -0,0 +1,355 @@
+//! Kermit file transfer protocol implementation.
+//!
+//! This module implements the Kermit sender used with U-Boot's `loadb`
+//! command, the only loader of many vendor U-Boot builds.
+//!
+//! ## Protocol Overview
+//!
+//! Kermit sends printable packets `SOH LEN SEQ TYPE DATA CHECK CR`, each
+//! acknowledged by the receiver with a `Y` packet:
+//!
+//! - `S` negotiates the parameters, `F` carries the file name, `D` the
+//!   data, `Z` ends the file and `B` the transfer
+//! - Control characters in the data are prefixed with `#`, bytes with the
+//!   8th bit set are sent as is, so the line must be 8-bit clean
+//! - Packets are at most 94 bytes long with a 6-bit checksum
+//! - A rejected packet is sent again, up to 10 times
+
+use std::io::*;
+
+use crate::transfer::FileTransfer;
+
+/// Start of a packet
+const MARK: u8 = 0x01;
+/// End of a packet
+const EOL: u8 = b'\r';
+/// Prefix of a control character
+const QCTL: u8 = b'#';
+/// Longest packet, counted from `SEQ`
+const MAXL: usize = 94;
+/// Longest encoded data of a packet
+const MAX_DATA: usize = MAXL - 3;
+
+/// Attempts to send a packet before giving up.
+const RETRIES: usize = 10;
+
+/// Kermit protocol handler for file transfers.
+pub struct Kermit {
+    /// Sequence number of the next packet, modulo 64
+    seq: u8,
+}
+
+impl Kermit {
+    /// Creates a new Kermit sender.
+    pub fn new() -> Self {
+        Self { seq: 0 }
+    }
+
+    /// Sends a file over the Kermit protocol.
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - The device implementing `Read + Write` (serial stream)
+    /// * `file` - The readable file stream
+    /// * `name` - File name reported to the receiver
+    /// * `on_progress` - Callback invoked with the total bytes sent so far
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error from the underlying device or file stream, or
+    /// an error if the receiver reports one or keeps rejecting a packet.
+    pub fn send<D: Write + Read, F: Read>(
+        &mut self,
+        dev: &mut D,
+        file: &mut F,
+        name: &str,
+        on_progress: impl Fn(usize),
+    ) -> Result<()> {
+        info!("Sending file: {name}");
+
+        let init = [
+            tochar(MAXL as u8),
+            tochar(5), // 超时秒数
+            tochar(0), // 无填充
+            ctl(0),
+            tochar(EOL),
+            QCTL,
+            b'N', // 不使用 8 位前缀
+        ];
+        self.send_packet(dev, b'S', &init)?;
+        let mut file_name = Vec::new();
+        for &c in name.as_bytes() {
+            encode(c, &mut file_name);
+        }
+        self.send_packet(dev, b'F', &file_name)?;
+
+        let mut buff = [0u8; 1024];
+        let (mut pos, mut filled) = (0, 0);
+        let mut data = Vec::with_capacity(MAX_DATA);
+        let mut send_size = 0;
+        loop {
+            // 按编码后的长度装满一个数据包
+            while data.len() < MAX_DATA {
+                if pos == filled {
+                    filled = file.read(&mut buff)?;
+                    pos = 0;
+                    if filled == 0 {
+                        break;
+                    }
+                }
+                let c = buff[pos];
+                if data.len() + encoded_len(c) > MAX_DATA {
+                    break;
+                }
+                encode(c, &mut data);
+                pos += 1;
+                send_size += 1;
+            }
+            if data.is_empty() {
+                break;
+            }
+            self.send_packet(dev, b'D', &data)?;
+            data.clear();
+            on_progress(send_size);
+        }
+
+        self.send_packet(dev, b'Z', &[])?;
+        self.send_packet(dev, b'B', &[])
+    }
+
+    fn send_packet<D: Write + Read>(&mut self, dev: &mut D, kind: u8, data: &[u8]) -> Result<()> {
+        let packet = packet(self.seq, kind, data);
+        let mut err = None;
+        for _ in 0..RETRIES {
+            dev.write_all(&packet)?;
+            dev.flush()?;
+            match wait_ack(dev, self.seq) {
+                Ok(()) => {
+                    self.seq = (self.seq + 1) % 64;
+                    return Ok(());
+                }
+                Err(e) if e.kind() == ErrorKind::BrokenPipe => {
+                    debug!("kermit packet {} rejected", self.seq);
+                    err = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(err.unwrap_or(Error::new(ErrorKind::BrokenPipe, "retry too much")))
+    }
+}
+
+impl Default for Kermit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileTransfer for Kermit {
+    const COMMAND: &'static str = "loadb";
+
+    fn new(_crc_mode: bool) -> Self {
+        Kermit::new()
+    }
+
+    /// U-Boot is ready after printing
+    /// `## Ready for binary (kermit) download to ...`.
+    fn ready(reply: &[u8]) -> Option<bool> {
+        let ready = reply.ends_with(b"\n") && String::from_utf8_lossy(reply).contains("(kermit)");
+        ready.then_some(false)
+    }
+
+    fn send<D: Read + Write, F: Read>(
+        &mut self,
+        dev: &mut D,
+        file: &mut F,
+        name: &str,
+        _size: usize,
+        on_progress: impl Fn(usize),
+    ) -> Result<()> {
+        Kermit::send(self, dev, file, name, on_progress)
+    }
+}
+
+fn tochar(c: u8) -> u8 {
+    c + 32
+}
+
+fn unchar(c: u8) -> u8 {
+    c.wrapping_sub(32)
+}
+
+fn ctl(c: u8) -> u8 {
+    c ^ 64
+}
+
+/// Type 1 block check of the characters from `LEN` to the end of `DATA`.
+fn check(bytes: &[u8]) -> u8 {
+    let s = bytes.iter().fold(0u32, |s, &b| s + b as u32);
+    tochar(((s + ((s & 192) >> 6)) & 63) as u8)
+}
+
+fn packet(seq: u8, kind: u8, data: &[u8]) -> Vec<u8> {
+    let mut packet = vec![MARK, tochar(data.len() as u8 + 3), tochar(seq), kind];
+    packet.extend_from_slice(data);
+    packet.push(check(&packet[1..]));
+    packet.push(EOL);
+    packet
+}
+
+fn needs_prefix(c: u8) -> bool {
+    let low = c & 0x7f;
+    low < 32 || low == 127 || low == QCTL
+}
+
+fn encoded_len(c: u8) -> usize {
+    if needs_prefix(c) { 2 } else { 1 }
+}
+
+/// Appends `c` to `out`, prefixed if it is a control character or `#`.
+fn encode(c: u8, out: &mut Vec<u8>) {
+    if !needs_prefix(c) {
+        out.push(c);
+    } else if c & 0x7f == QCTL {
+        out.extend_from_slice(&[QCTL, c]);
+    } else {
+        out.extend_from_slice(&[QCTL, ctl(c)]);
+    }
+}
+
+/// Waits for the receiver to acknowledge packet `seq`, `BrokenPipe` if it
+/// asks for it again.
+fn wait_ack<D: Read>(dev: &mut D, seq: u8) -> Result<()> {
+    let mut buff = [0u8; 1];
+    loop {
+        dev.read_exact(&mut buff)?;
+        if buff[0] != MARK {
+            // 接收端输出的其他内容
+            trace!("kermit: {:#04x}", buff[0]);
+            continue;
+        }
+        dev.read_exact(&mut buff)?;
+        let len = unchar(buff[0]) as usize;
+        if !(3..=MAXL).contains(&len) {
+            return Err(Error::new(ErrorKind::BrokenPipe, "bad packet length"));
+        }
+        let mut rest = vec![0u8; len];
+        dev.read_exact(&mut rest)?;
+        let (body, sum) = rest.split_at(len - 1);
+        if check(&[&[buff[0]], body].concat()) != sum[0] {
+            return Err(Error::new(ErrorKind::BrokenPipe, "bad checksum"));
+        }
+        match body[1] {
+            b'Y' if unchar(body[0]) == seq => return Ok(()),
+            b'E' => {
+                return Err(Error::new(
+                    ErrorKind::ConnectionAborted,
+                    String::from_utf8_lossy(&body[2..]).into_owned(),
+                ));
+            }
+            _ => return Err(Error::new(ErrorKind::BrokenPipe, "NAK")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// Receiver replying with `replies` and keeping what was sent.
+    struct Mock {
+        replies: VecDeque<u8>,
+        sent: Vec<u8>,
+    }
+
+    impl Read for Mock {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let Some(c) = self.replies.pop_front() else {
+                return Err(Error::from(ErrorKind::UnexpectedEof));
+            };
+            buf[0] = c;
+            Ok(1)
+        }
+    }
+
+    impl Write for Mock {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.sent.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Splits the sent bytes into `(seq, type, decoded data)`.
+    fn packets(sent: &[u8]) -> Vec<(u8, u8, Vec<u8>)> {
+        sent.split(|&c| c == EOL)
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                assert_eq!(p[0], MARK);
+                assert_eq!(unchar(p[1]) as usize, p.len() - 2);
+                assert_eq!(check(&p[1..p.len() - 1]), p[p.len() - 1]);
+                let mut data = Vec::new();
+                let mut enc = p[4..p.len() - 1].iter();
+                while let Some(&c) = enc.next() {
+                    if c == QCTL {
+                        let c = *enc.next().unwrap();
+                        data.push(if c & 0x7f == QCTL { c } else { ctl(c) });
+                    } else {
+                        data.push(c);
+                    }
+                }
+                (unchar(p[2]), p[3], data)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_send() {
+        let data: Vec<u8> = (0..=255).cycle().take(600).collect();
+        let mut replies: Vec<u8> = b"noise".to_vec();
+        // 第二个数据包被拒绝一次
+        let acks = [(0, b'Y'), (1, b'Y'), (2, b'Y'), (3, b'N')];
+        for (seq, kind) in acks.into_iter().chain((3..64).map(|seq| (seq, b'Y'))) {
+            replies.extend(packet(seq, kind, &[]));
+        }
+        let mut dev = Mock {
+            replies: replies.into(),
+            sent: Vec::new(),
+        };
+        Kermit::new()
+            .send(&mut dev, &mut data.as_slice(), "a.bin", |_| {})
+            .unwrap();
+
+        let packets = packets(&dev.sent);
+        assert_eq!(packets[0].1, b'S');
+        assert_eq!(packets[1], (1, b'F', b"a.bin".to_vec()));
+        let mut received = Vec::new();
+        let mut last = None;
+        for (seq, kind, data) in &packets[2..] {
+            if *kind == b'D' && last != Some(*seq) {
+                received.extend_from_slice(data);
+            }
+            last = Some(*seq);
+        }
+        assert_eq!(received, data);
+        assert_eq!(packets[3].0, packets[4].0);
+        let n = packets.len();
+        assert_eq!((packets[n - 2].1, packets[n - 1].1), (b'Z', b'B'));
+    }
+
+    #[test]
+    fn test_error_packet() {
+        let mut dev = Mock {
+            replies: packet(0, b'E', b"abort").into(),
+            sent: Vec::new(),
+        };
+        let err = Kermit::new()
+            .send(&mut dev, &mut b"x".as_slice(), "x", |_| {})
+            .unwrap_err();
+        assert_eq!(err.to_string(), "abort");
+    }
+}
@@ -3,15 +3,23 @@
 //! A Rust library for communicating with U-Boot bootloader over serial connection.
 //!
 //! This crate provides functionality to interact with U-Boot shell, execute commands,
-//! transfer files via YMODEM protocol, and manage environment variables.
+//! transfer files via Kermit, XMODEM or YMODEM protocol, and manage environment variables.
 //!
 //! ## Features
 //!
-//! - Automatic U-Boot shell detection and synchronization
+//! - Automatic U-Boot shell detection and re-synchronization
 //! - Command execution with retry support
-//! - YMODEM file transfer protocol implementation
-//! - Environment variable management
-//! - CRC16-CCITT checksum support
+//! - Long-running commands with a timeout or streamed output lines
+//! - Boot commands handing the console over to the caller
+//! - Memory reads and writes, and compare to verify loaded images
+//! - Loading files from a TFTP server
+//! - Kermit, XMODEM and YMODEM file transfer protocol implementations, behind a
+//!   shared [`FileTransfer`] trait
+//! - Receiving files sent from the board with YMODEM
+//! - Protocol-free loading by typing data into the console
+//! - Environment variable management, including multi-line scripts and
+//!   snapshots of the whole environment and saving it with `saveenv`
+//! - CRC16-CCITT and CRC32 checksum support
 //!
 //! ## Quick Start
 //!
@@ -45,13 +53,17 @@
 //!
 //! ## Modules
 //!
-//! - [`crc`] - CRC16-CCITT checksum implementation
+//! - [`crc`] - CRC16-CCITT and CRC32 checksum implementations
+//! - [`transfer`] - The [`FileTransfer`] trait shared by the protocols
+//! - [`kermit`] - Kermit file transfer protocol
+//! - [`xmodem`] - XMODEM file transfer protocol
 //! - [`ymodem`] - YMODEM file transfer protocol
 
 #[macro_use]
 extern crate log;
 
 use std::{
+    collections::HashMap,
     fs::File,
     io::*,
     path::PathBuf,
@@ -63,12 +75,23 @@ use std::{
     time::{Duration, Instant},
 };
 
-/// CRC16-CCITT checksum implementation.
+/// CRC16-CCITT and CRC32 checksum implementations.
 pub mod crc;
 
-/// YMODEM file transfer protocol implementation.
+/// File transfer protocols of the U-Boot `load*` commands.
+pub mod transfer;
+
+/// Kermit file transfer protocol implementation.
+pub mod kermit;
+
+/// XMODEM file transfer protocol implementation.
+pub mod xmodem;
+
+/// YMODEM file transfer protocol implementation, sending and receiving.
 pub mod ymodem;
 
+pub use transfer::FileTransfer;
+
 macro_rules! dbg {
     ($($arg:tt)*) => {{
         debug!("$ {}", &std::fmt::format(format_args!($($arg)*)));
@@ -78,6 +101,31 @@ macro_rules! dbg {
 const CTRL_C: u8 = 0x03;
 const INT_STR: &str = "<INTERRUPT>";
 const INT: &[u8] = INT_STR.as_bytes();
+/// Printed after a command succeeded, marking its end.
+const OK_STR: &str = "cmd-ok";
+/// Longest silence of U-Boot before a read times out.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// Bytes typed into the console between two `crc32` checks.
+const CONSOLE_CHUNK: usize = 1024;
+/// Attempts to type a chunk into the console before giving up.
+const CONSOLE_ATTEMPTS: usize = 3;
+/// Longest time `tftpboot` may take.
+const TFTP_TIMEOUT: Duration = Duration::from_secs(120);
+/// Bytes read by one `md.b`.
+const MD_CHUNK: usize = 4096;
+/// Longest time `saveenv` may take, erasing flash included.
+const SAVEENV_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long to wait for U-Boot output.
+#[derive(Debug, Clone, Copy)]
+enum Wait {
+    /// Until U-Boot is silent for [`READ_TIMEOUT`].
+    Silence,
+    /// Until the instant.
+    Until(Instant),
+    /// As long as it takes.
+    Forever,
+}
 
 /// U-Boot shell communication interface.
 ///
@@ -103,6 +151,8 @@ pub struct UbootShell {
     pub rx: Option<Box<dyn Read + Send>>,
     /// Shell prompt prefix detected during initialization.
     perfix: String,
+    /// When `perfix` was last detected.
+    synced_at: Instant,
 }
 
 impl UbootShell {
@@ -140,6 +190,7 @@ impl UbootShell {
             tx: Some(Box::new(tx)),
             rx: Some(Box::new(rx)),
             perfix: "".to_string(),
+            synced_at: Instant::now(),
         };
         s.wait_for_shell()?;
         debug!("shell ready, perfix: `{}`", s.perfix);
@@ -216,30 +267,80 @@ impl UbootShell {
         debug!("got {}", String::from_utf8_lossy(&line));
         line.resize(line.len() - INT.len(), 0);
         self.perfix = String::from_utf8_lossy(&line).to_string();
+        self.synced_at = Instant::now();
         self.clear_shell()?;
         Ok(())
     }
 
+    /// Returns the shell prompt, e.g. `=> `, as detected at the last
+    /// synchronization.
+    pub fn prompt(&self) -> &str {
+        &self.perfix
+    }
+
+    /// Returns when the prompt was last detected by [`Self::new`] or
+    /// [`Self::resync`].
+    pub fn synced_at(&self) -> Instant {
+        self.synced_at
+    }
+
+    /// Re-synchronizes with the U-Boot shell mid-session.
+    ///
+    /// Interrupts whatever is running with Ctrl+C, as [`Self::new`] does,
+    /// and detects the prompt again. Use it after the board printed
+    /// unexpected output or a sub-shell or gadget mode (e.g. `dfu`) was
+    /// entered, instead of re-opening the port.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serial I/O fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use uboot_shell::UbootShell;
+    /// # fn example(uboot: &mut UbootShell) {
+    /// uboot.cmd_without_reply("dfu 0 ram 0").unwrap();
+    /// // ... transfer over USB ...
+    /// uboot.resync().unwrap();
+    /// println!("prompt: {}", uboot.prompt());
+    /// # }
+    /// ```
+    pub fn resync(&mut self) -> Result<()> {
+        info!("resync shell");
+        self.wait_for_shell()?;
+        debug!("shell ready, perfix: `{}`", self.perfix);
+        Ok(())
+    }
+
     fn read_byte(&mut self) -> Result<u8> {
+        self.read_byte_with(Wait::Silence)
+    }
+
+    fn read_byte_with(&mut self, wait: Wait) -> Result<u8> {
         let mut buff = [0u8; 1];
-        let time_out = Duration::from_secs(5);
-        let start = Instant::now();
+        let deadline = match wait {
+            Wait::Silence => Some(Instant::now() + READ_TIMEOUT),
+            Wait::Until(deadline) => Some(deadline),
+            Wait::Forever => None,
+        };
 
         loop {
-            match self.rx().read_exact(&mut buff) {
-                Ok(_) => return Ok(buff[0]),
-                Err(e) => {
-                    if e.kind() == ErrorKind::TimedOut {
-                        if start.elapsed() > time_out {
-                            return Err(std::io::Error::new(
-                                std::io::ErrorKind::TimedOut,
-                                "Timeout",
-                            ));
-                        }
-                    } else {
-                        return Err(e);
-                    }
-                }
+            match self.rx().read(&mut buff) {
+                Ok(1..) => return Ok(buff[0]),
+                // Windows 的串口读超时返回 Ok(0) 而不是 TimedOut
+                Ok(0) if cfg!(windows) => {}
+                Ok(_) => return Err(Error::from(ErrorKind::UnexpectedEof)),
+                // TCP 串口（如远程板卡）超时时返回 WouldBlock
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        ErrorKind::TimedOut | ErrorKind::WouldBlock | ErrorKind::Interrupted
+                    ) => {}
+                Err(e) => return Err(e),
+            }
+            if deadline.is_some_and(|d| Instant::now() > d) {
+                return Err(Error::new(ErrorKind::TimedOut, "Timeout"));
             }
         }
     }
@@ -260,19 +361,46 @@ impl UbootShell {
     ///
     /// Returns an error when the underlying read operation times out or fails.
     pub fn wait_for_reply(&mut self, val: &str) -> Result<String> {
+        self.wait_for_reply_with(val, Wait::Silence, false, &mut |_| {})
+    }
+
+    /// Waits for the shell prompt at the start of a line, so that e.g. the
+    /// `==> ` printed by `crc32` does not end the reply early.
+    fn wait_for_prompt(&mut self) -> Result<String> {
+        let perfix = self.perfix.clone();
+        self.wait_for_reply_with(&perfix, Wait::Silence, true, &mut |_| {})
+    }
+
+    /// [`Self::wait_for_reply`], passing each complete line to `on_line`.
+    ///
+    /// With `line_start`, `val` only matches at the start of a line.
+    fn wait_for_reply_with(
+        &mut self,
+        val: &str,
+        wait: Wait,
+        line_start: bool,
+        on_line: &mut dyn FnMut(&str),
+    ) -> Result<String> {
         let mut reply = Vec::new();
         let mut display = Vec::new();
         debug!("wait for `{}`", val);
         loop {
-            let byte = self.read_byte()?;
+            let byte = self.read_byte_with(wait)?;
             reply.push(byte);
             display.push(byte);
             if byte == b'\n' {
-                dbg!("{}", String::from_utf8_lossy(&display).trim_end());
+                let line = String::from_utf8_lossy(&display);
+                dbg!("{}", line.trim_end());
+                on_line(line.trim_end());
                 display.clear();
             }
 
-            if reply.ends_with(val.as_bytes()) {
+            let matched = if line_start {
+                display == val.as_bytes()
+            } else {
+                reply.ends_with(val.as_bytes())
+            };
+            if matched {
                 dbg!("{}", String::from_utf8_lossy(&display).trim_end());
                 break;
             }
@@ -305,13 +433,31 @@ impl UbootShell {
     }
 
     fn _cmd(&mut self, cmd: &str) -> Result<String> {
+        self.run_cmd(cmd, Wait::Silence, &mut |_| {})
+    }
+
+    /// Runs `cmd`, passing its output lines to `on_line` as they arrive.
+    fn run_cmd(&mut self, cmd: &str, wait: Wait, on_line: &mut dyn FnMut(&str)) -> Result<String> {
         let _ = self.read_to_end(&mut vec![]);
-        let ok_str = "cmd-ok";
+        let ok_str = OK_STR;
         let cmd_with_id = format!("{cmd}&& echo {ok_str}");
         self.cmd_without_reply(&cmd_with_id)?;
         let perfix = self.perfix.clone();
+        // 跳过回显的命令和结束标记，只转发命令本身的输出
+        let mut echoed = false;
+        let mut forward = |line: &str| {
+            if !echoed {
+                echoed = line.ends_with(&cmd_with_id);
+                if echoed {
+                    return;
+                }
+            }
+            if line != ok_str {
+                on_line(line);
+            }
+        };
         let res = self
-            .wait_for_reply(&perfix)?
+            .wait_for_reply_with(&perfix, wait, true, &mut forward)?
             .trim_end()
             .trim_end_matches(self.perfix.as_str().trim())
             .trim_end()
@@ -378,6 +524,95 @@ impl UbootShell {
         )))
     }
 
+    /// Executes a command that may run for up to `timeout`, e.g. `dhcp` or
+    /// `mmc erase`, and returns the output.
+    ///
+    /// Unlike [`Self::cmd`], U-Boot may stay silent for the whole `timeout`
+    /// and the command is not retried.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ErrorKind::TimedOut` if the command does not finish within
+    /// `timeout`, or an error if it fails or serial I/O fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use uboot_shell::UbootShell;
+    /// # use std::time::Duration;
+    /// # fn example(uboot: &mut UbootShell) {
+    /// uboot.cmd_with_timeout("dhcp", Duration::from_secs(60)).unwrap();
+    /// # }
+    /// ```
+    pub fn cmd_with_timeout(&mut self, cmd: &str, timeout: Duration) -> Result<String> {
+        info!("cmd: {cmd} (timeout {timeout:?})");
+        self.run_cmd(cmd, Wait::Until(Instant::now() + timeout), &mut |_| {})
+    }
+
+    /// Executes a command, passing its output lines to `on_line` as they
+    /// arrive, and waits for it to finish however long it takes.
+    ///
+    /// The echoed command line is not passed on. The command is not
+    /// retried.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command fails or serial I/O fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use uboot_shell::UbootShell;
+    /// # fn example(uboot: &mut UbootShell) {
+    /// uboot.cmd_lines("mmc erase 0 0x100000", |line| println!("{line}")).unwrap();
+    /// # }
+    /// ```
+    pub fn cmd_lines(&mut self, cmd: &str, mut on_line: impl FnMut(&str)) -> Result<()> {
+        info!("cmd: {cmd}");
+        self.run_cmd(cmd, Wait::Forever, &mut on_line)?;
+        Ok(())
+    }
+
+    /// Compares `len` bytes at `addr_a` and `addr_b` with `cmp.b`, e.g. to
+    /// verify an image copied in RAM or read back from flash.
+    ///
+    /// # Returns
+    ///
+    /// Returns [`MemCompare::Same`] if the regions match, or the offset of
+    /// the first differing byte.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ErrorKind::InvalidData` if the output of `cmp.b` cannot be
+    /// parsed (e.g. U-Boot was built without `cmp`), or an error if serial
+    /// I/O fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use uboot_shell::{MemCompare, UbootShell};
+    /// # fn example(uboot: &mut UbootShell) {
+    /// match uboot.verify_mem(0x80000000, 0x90000000, 0x100000).unwrap() {
+    ///     MemCompare::Same => println!("ok"),
+    ///     MemCompare::Differ(offset) => println!("differs at {offset:#x}"),
+    /// }
+    /// # }
+    /// ```
+    pub fn verify_mem(&mut self, addr_a: usize, addr_b: usize, len: usize) -> Result<MemCompare> {
+        let cmd = format!("cmp.b {addr_a:#x} {addr_b:#x} {len:#x}");
+        info!("cmd: {cmd}");
+        let _ = self.read_to_end(&mut vec![]);
+        // 内容不一致时 cmp 返回失败，不能用 `&&` 连接结束标记
+        self.cmd_without_reply(&format!("{cmd}; echo {OK_STR}"))?;
+        let res = self.wait_for_prompt()?;
+        parse_cmp(&res).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("unexpected `{cmd}` response: {res}"),
+            )
+        })
+    }
+
     /// Sets a U-Boot environment variable.
     ///
     /// # Arguments
@@ -402,6 +637,50 @@ impl UbootShell {
         Ok(())
     }
 
+    /// Installs a multi-line script into a U-Boot environment variable,
+    /// to be started with [`Self::run_env`] or used as e.g. `bootcmd`.
+    ///
+    /// The lines are joined with `; ` and quoted for `setenv`, so
+    /// semicolons and quotes in them need no escaping. Empty lines and
+    /// `#` comment lines are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ErrorKind::InvalidInput` if a line contains a line break,
+    /// or any error from the underlying command execution.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use uboot_shell::UbootShell;
+    /// # fn example(uboot: &mut UbootShell) {
+    /// uboot
+    ///     .set_env_script(
+    ///         "bootcmd",
+    ///         ["dhcp ${loadaddr} image.fit", "echo 'booting...'", "bootm"],
+    ///     )
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn set_env_script<S: AsRef<str>>(
+        &mut self,
+        name: impl Into<String>,
+        script_lines: impl IntoIterator<Item = S>,
+    ) -> Result<()> {
+        let value = env_script(script_lines)?;
+        self.set_env(name, value)
+    }
+
+    /// Runs the script stored in a U-Boot environment variable with `run`
+    /// and returns the output.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the script fails or serial I/O fails.
+    pub fn run_env(&mut self, name: impl Into<String>) -> Result<String> {
+        self.cmd(&format!("run {}", name.into()))
+    }
+
     /// Gets the value of a U-Boot environment variable.
     ///
     /// # Arguments
@@ -472,6 +751,105 @@ impl UbootShell {
         ))
     }
 
+    /// Reads the whole U-Boot environment with `printenv`, to be put back
+    /// later with [`Self::env_restore`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `printenv` fails or serial I/O fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use uboot_shell::UbootShell;
+    /// # fn example(uboot: &mut UbootShell) {
+    /// let snapshot = uboot.env_snapshot().unwrap();
+    /// std::fs::write("uboot-env.txt", snapshot.to_string()).unwrap();
+    /// uboot.set_env("bootargs", "console=ttyS0,115200").unwrap();
+    /// uboot.env_restore(&snapshot).unwrap();
+    /// # }
+    /// ```
+    pub fn env_snapshot(&mut self) -> Result<EnvSnapshot> {
+        let output = self.cmd("printenv")?;
+        Ok(EnvSnapshot::parse(&output))
+    }
+
+    /// Reads the whole U-Boot environment with `printenv` as a map from
+    /// variable names to values.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `printenv` fails or serial I/O fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use uboot_shell::UbootShell;
+    /// # fn example(uboot: &mut UbootShell) {
+    /// let env = uboot.env_all().unwrap();
+    /// println!("bootcmd: {:?}", env.get("bootcmd"));
+    /// # }
+    /// ```
+    pub fn env_all(&mut self) -> Result<HashMap<String, String>> {
+        Ok(self.env_snapshot()?.into())
+    }
+
+    /// Makes the environment in RAM persistent with `saveenv`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `saveenv` fails, e.g. U-Boot has no environment
+    /// storage, or serial I/O fails.
+    pub fn env_save(&mut self) -> Result<()> {
+        self.cmd_with_timeout("saveenv", SAVEENV_TIMEOUT)?;
+        Ok(())
+    }
+
+    /// Puts the environment back as it was in `snapshot`: changed and
+    /// missing variables are set, variables not in it are deleted.
+    ///
+    /// Only the environment in RAM is changed; run `saveenv` afterwards to
+    /// make it persistent.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of variables that had to be changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a variable cannot be set or deleted, e.g. a
+    /// write-once `ethaddr`, after trying all others, or if serial I/O
+    /// fails.
+    pub fn env_restore(&mut self, snapshot: &EnvSnapshot) -> Result<usize> {
+        let current = self.env_snapshot()?;
+        let mut cmds = Vec::new();
+        for (name, _) in current.iter() {
+            if snapshot.get(name).is_none() {
+                cmds.push((name.to_string(), format!("setenv {name}")));
+            }
+        }
+        for (name, value) in snapshot.iter() {
+            if current.get(name) != Some(value) {
+                cmds.push((name.to_string(), format!("setenv {name} {}", quote(value))));
+            }
+        }
+
+        let mut failed = Vec::new();
+        for (name, cmd) in &cmds {
+            if let Err(e) = self.cmd(cmd) {
+                warn!("restore env {name}: {e}");
+                failed.push(name.as_str());
+            }
+        }
+        if !failed.is_empty() {
+            return Err(Error::other(format!(
+                "cannot restore env {}",
+                failed.join(", ")
+            )));
+        }
+        Ok(cmds.len())
+    }
+
     /// Transfers a file to U-Boot memory using YMODEM protocol.
     ///
     /// Uses the U-Boot `loady` command to receive files via YMODEM protocol.
@@ -508,9 +886,73 @@ impl UbootShell {
         file: impl Into<PathBuf>,
         on_progress: impl Fn(usize, usize),
     ) -> Result<String> {
-        self.cmd_without_reply(&format!("loady {:#x}", addr,))?;
-        let crc = self.wait_for_load_crc()?;
-        let mut p = ymodem::Ymodem::new(crc);
+        self.load_with::<ymodem::Ymodem>(addr, file, on_progress)
+    }
+
+    /// Transfers a file to U-Boot memory using XMODEM protocol.
+    ///
+    /// Uses the U-Boot `loadx` command, for U-Boots built without `loady`.
+    /// XMODEM sends whole blocks, so up to 1 KiB of `0x1A` padding may
+    /// follow the file in memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened or if the serial
+    /// transfer fails.
+    pub fn loadx(
+        &mut self,
+        addr: usize,
+        file: impl Into<PathBuf>,
+        on_progress: impl Fn(usize, usize),
+    ) -> Result<String> {
+        self.load_with::<xmodem::Xmodem>(addr, file, on_progress)
+    }
+
+    /// Transfers a file to U-Boot memory using Kermit protocol.
+    ///
+    /// Uses the U-Boot `loadb` command, the only loader of many vendor
+    /// U-Boot builds. The serial line must pass all 8 bits.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened, the path has a non-UTF-8
+    /// file name, or if the serial transfer fails.
+    pub fn loadb(
+        &mut self,
+        addr: usize,
+        file: impl Into<PathBuf>,
+        on_progress: impl Fn(usize, usize),
+    ) -> Result<String> {
+        self.load_with::<kermit::Kermit>(addr, file, on_progress)
+    }
+
+    /// Transfers a file to U-Boot memory with the protocol `T`, started by
+    /// its U-Boot command [`FileTransfer::COMMAND`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened, the path has a non-UTF-8
+    /// file name, or if the serial transfer fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use uboot_shell::{UbootShell, xmodem::Xmodem};
+    /// # fn example(uboot: &mut UbootShell) {
+    /// uboot.load_with::<Xmodem>(0x80000000, "kernel.bin", |sent, total| {
+    ///     println!("Progress: {}/{} bytes", sent, total);
+    /// }).unwrap();
+    /// # }
+    /// ```
+    pub fn load_with<T: FileTransfer>(
+        &mut self,
+        addr: usize,
+        file: impl Into<PathBuf>,
+        on_progress: impl Fn(usize, usize),
+    ) -> Result<String> {
+        self.cmd_without_reply(&format!("{} {:#x}", T::COMMAND, addr))?;
+        let crc = self.wait_for_receiver::<T>()?;
+        let mut p = T::new(crc);
 
         let file = file.into();
         let name = file
@@ -529,27 +971,520 @@ impl UbootShell {
         self.wait_for_reply(&perfix)
     }
 
-    fn wait_for_load_crc(&mut self) -> Result<bool> {
+    /// Loads `filename` from the TFTP server at `server_ip` to memory at
+    /// `addr` with `tftpboot`.
+    ///
+    /// Sets `serverip`, and `ipaddr` to `board_ip` if given; otherwise the
+    /// board keeps the address it has, e.g. from an earlier `dhcp`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of bytes loaded, from the `Bytes transferred`
+    /// line of `tftpboot`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `tftpboot` fails or takes longer than 2 minutes,
+    /// or `ErrorKind::InvalidData` if it does not print the size.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use uboot_shell::UbootShell;
+    /// # fn example(uboot: &mut UbootShell) {
+    /// let size = uboot
+    ///     .load_tftp(0x80000000, "kernel.bin", "192.168.1.2", Some("192.168.1.100"))
+    ///     .unwrap();
+    /// println!("loaded {size} bytes");
+    /// # }
+    /// ```
+    pub fn load_tftp(
+        &mut self,
+        addr: usize,
+        filename: &str,
+        server_ip: &str,
+        board_ip: Option<&str>,
+    ) -> Result<usize> {
+        self.set_env("serverip", server_ip)?;
+        if let Some(ip) = board_ip {
+            self.set_env("ipaddr", ip)?;
+        }
+        let res = self.cmd_with_timeout(&format!("tftpboot {addr:#x} {filename}"), TFTP_TIMEOUT)?;
+        parse_transferred(&res).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("cannot parse tftpboot output: {res}"),
+            )
+        })
+    }
+
+    /// Runs `cmd`, which sends a file from the board with YMODEM, and saves
+    /// the file at `dest`.
+    ///
+    /// Mainline U-Boot has no YMODEM sender, `loady` only receives, so `cmd`
+    /// is a command a vendor or board port added to U-Boot. Use it to pull
+    /// memory dumps or environment blobs back to the host.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `dest` cannot be written, `cmd` fails or if the
+    /// serial transfer fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use uboot_shell::UbootShell;
+    /// # fn example(uboot: &mut UbootShell) {
+    /// // `savey` is a command of the board's own U-Boot
+    /// let file = uboot
+    ///     .savey("savey 0x80000000 0x10000", "dump.bin", |received| {
+    ///         println!("Received: {} bytes", received);
+    ///     })
+    ///     .unwrap();
+    /// println!("{} bytes of {}", file.written, file.name);
+    /// # }
+    /// ```
+    pub fn savey(
+        &mut self,
+        cmd: &str,
+        dest: impl Into<PathBuf>,
+        on_progress: impl Fn(usize),
+    ) -> Result<ymodem::Received> {
+        let mut out = File::create(dest.into())?;
+        self.cmd_without_reply(cmd)?;
+        let received = ymodem::Ymodem::new(true).recv(self, &mut out, on_progress)?;
+        out.flush()?;
+        let perfix = self.perfix.clone();
+        self.wait_for_reply(&perfix)?;
+        Ok(received)
+    }
+
+    /// Loads `data` to memory at `addr` by typing it into the console with
+    /// `mm.l`, for U-Boots without `loady`, `tftp` or any other loader.
+    ///
+    /// Each 1 KiB chunk is checked with U-Boot's `crc32` command, if
+    /// present, and typed again on a mismatch. This is slow, around
+    /// 1-2 KiB/s at 115200 baud, but works with any U-Boot.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The memory address where the data will be loaded
+    /// * `data` - The data to load
+    /// * `on_progress` - Callback function called with (bytes_loaded, total_bytes)
+    ///
+    /// # Errors
+    ///
+    /// Returns `ErrorKind::InvalidData` if a chunk still mismatches after
+    /// retries, or an error if a command fails or serial I/O fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use uboot_shell::UbootShell;
+    /// # fn example(uboot: &mut UbootShell) {
+    /// let data = std::fs::read("kernel.bin").unwrap();
+    /// uboot.load_via_console(0x80000000, &data, |loaded, total| {
+    ///     println!("Progress: {}/{} bytes", loaded, total);
+    /// }).unwrap();
+    /// # }
+    /// ```
+    pub fn load_via_console(
+        &mut self,
+        addr: usize,
+        data: &[u8],
+        on_progress: impl Fn(usize, usize),
+    ) -> Result<()> {
+        info!("load {} bytes to {addr:#x} via console", data.len());
+        let little_endian = self.is_little_endian(addr)?;
+        let mut check_crc = true;
+        for (i, chunk) in data.chunks(CONSOLE_CHUNK).enumerate() {
+            let chunk_addr = addr + i * CONSOLE_CHUNK;
+            let expected = crc::crc32(chunk);
+            let mut ok = false;
+            for attempt in 1..=CONSOLE_ATTEMPTS {
+                self.type_into_mem(chunk_addr, chunk, little_endian)?;
+                if !check_crc {
+                    ok = true;
+                    break;
+                }
+                match self.mem_crc32(chunk_addr, chunk.len()) {
+                    Some(crc) if crc == expected => {
+                        ok = true;
+                        break;
+                    }
+                    Some(crc) => warn!(
+                        "chunk at {chunk_addr:#x}: crc32 {crc:08x} != {expected:08x} (attempt {attempt})"
+                    ),
+                    None => {
+                        warn!("crc32 command not available, loading without checks");
+                        check_crc = false;
+                        ok = true;
+                        break;
+                    }
+                }
+            }
+            if !ok {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "chunk at {chunk_addr:#x} still corrupted after {CONSOLE_ATTEMPTS} attempts"
+                    ),
+                ));
+            }
+            on_progress(chunk_addr - addr + chunk.len(), data.len());
+        }
+        Ok(())
+    }
+
+    /// Reads `len` bytes of memory at `addr` by parsing the hex dump of
+    /// `md.b`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ErrorKind::InvalidData` if the dump cannot be parsed, or an
+    /// error if `md.b` fails or serial I/O fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use uboot_shell::UbootShell;
+    /// # fn example(uboot: &mut UbootShell) {
+    /// let magic = uboot.read_mem(0x80000000, 4).unwrap();
+    /// println!("{magic:02x?}");
+    /// # }
+    /// ```
+    pub fn read_mem(&mut self, addr: usize, len: usize) -> Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(len);
+        while data.len() < len {
+            let chunk_addr = addr + data.len();
+            let chunk_len = MD_CHUNK.min(len - data.len());
+            let res = self.cmd(&format!("md.b {chunk_addr:#x} {chunk_len:#x}"))?;
+            let chunk = parse_md_bytes(&res, chunk_addr, chunk_len).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("cannot parse md.b output: {res}"),
+                )
+            })?;
+            data.extend_from_slice(&chunk);
+        }
+        Ok(data)
+    }
+
+    /// Writes `data` to memory at `addr`, one `mw.b` per byte.
+    ///
+    /// Meant for a few bytes; load larger data with [`Self::loady`] or
+    /// [`Self::load_via_console`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `mw.b` fails or serial I/O fails.
+    pub fn write_mem(&mut self, addr: usize, data: &[u8]) -> Result<()> {
+        for (i, byte) in data.iter().enumerate() {
+            self.cmd(&format!("mw.b {:#x} {byte:#04x}", addr + i))?;
+        }
+        Ok(())
+    }
+
+    /// Reads the 32-bit word at `addr` with `md.l`, in the byte order of
+    /// the board.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ErrorKind::InvalidData` if the output cannot be parsed, or
+    /// an error if `md.l` fails or serial I/O fails.
+    pub fn read_u32(&mut self, addr: usize) -> Result<u32> {
+        let res = self.cmd(&format!("md.l {addr:#x} 1"))?;
+        res.lines()
+            .find_map(|line| line.split_once(": "))
+            .and_then(|(_, words)| words.split_whitespace().next())
+            .and_then(|word| u32::from_str_radix(word, 16).ok())
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("cannot parse md.l output: {res}"),
+                )
+            })
+    }
+
+    /// Writes the 32-bit word `val` to `addr` with `mw.l`, in the byte
+    /// order of the board.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `mw.l` fails or serial I/O fails.
+    pub fn write_u32(&mut self, addr: usize, val: u32) -> Result<()> {
+        self.cmd(&format!("mw.l {addr:#x} {val:#010x}"))?;
+        Ok(())
+    }
+
+    /// Whether `mm.l` stores words little-endian, probed at `addr`.
+    fn is_little_endian(&mut self, addr: usize) -> Result<bool> {
+        self.cmd(&format!("mw.l {addr:#x} 0x04030201"))?;
+        let res = self.cmd(&format!("md.b {addr:#x} 4"))?;
+        let first = res
+            .lines()
+            .find_map(|line| line.split_once(": "))
+            .and_then(|(_, bytes)| bytes.split_whitespace().next());
+        Ok(first != Some("04"))
+    }
+
+    /// Types `data` into memory at `addr`, 4 bytes per `mm.l` input line
+    /// and any remaining bytes with `mw.b`.
+    fn type_into_mem(&mut self, addr: usize, data: &[u8], little_endian: bool) -> Result<()> {
+        let (words, tail) = data.split_at(data.len() / 4 * 4);
+        if !words.is_empty() {
+            let _ = self.read_to_end(&mut vec![]);
+            self.cmd_without_reply(&format!("mm.l {addr:#x}"))?;
+            for word in words.chunks_exact(4) {
+                self.wait_for_reply("? ")?;
+                let word = [word[0], word[1], word[2], word[3]];
+                let val = if little_endian {
+                    u32::from_le_bytes(word)
+                } else {
+                    u32::from_be_bytes(word)
+                };
+                // 整行一次写入，避免 TCP 串口上的小包延迟
+                self.tx().write_all(format!("{val:08x}\n").as_bytes())?;
+            }
+            // 输入非十六进制内容退出 mm
+            self.wait_for_reply("? ")?;
+            self.cmd_without_reply(".")?;
+            self.wait_for_prompt()?;
+        }
+        for (i, byte) in tail.iter().enumerate() {
+            self.cmd(&format!("mw.b {:#x} {byte:#04x}", addr + words.len() + i))?;
+        }
+        Ok(())
+    }
+
+    /// Runs the boot command `cmd` and hands the serial streams over, e.g.
+    /// to a terminal for the console of the booted system.
+    ///
+    /// The output of `cmd` is not read, so the caller sees all of it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command cannot be written.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use uboot_shell::UbootShell;
+    /// # use std::io::Read;
+    /// # fn example(uboot: UbootShell) {
+    /// let mut console = uboot.boot("run bootcmd").unwrap();
+    /// let mut buf = [0u8; 256];
+    /// let n = console.rx.read(&mut buf).unwrap();
+    /// # }
+    /// ```
+    pub fn boot(mut self, cmd: &str) -> Result<Console> {
+        info!("boot: {cmd}");
+        self.cmd_without_reply(cmd)?;
+        self.tx().flush()?;
+        Ok(Console {
+            tx: self.tx.take().unwrap(),
+            rx: self.rx.take().unwrap(),
+        })
+    }
+
+    /// Boots the image at `addr` with `bootm`, see [`Self::boot`].
+    ///
+    /// `initrd` is the address and size of a ramdisk and `fdt` the address
+    /// of a device tree. With both `None` they are taken from a FIT image at
+    /// `addr`; with only `fdt` set the ramdisk argument is `-`, which boots
+    /// without a ramdisk instead of the one in the FIT image.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command cannot be written.
+    pub fn bootm(
+        self,
+        addr: usize,
+        initrd: Option<(usize, usize)>,
+        fdt: Option<usize>,
+    ) -> Result<Console> {
+        self.boot(&boot_cmd("bootm", addr, initrd, fdt))
+    }
+
+    /// Boots the ARM64 or RISC-V `Image` at `addr` with `booti`, see
+    /// [`Self::boot`] and [`Self::bootm`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command cannot be written.
+    pub fn booti(
+        self,
+        addr: usize,
+        initrd: Option<(usize, usize)>,
+        fdt: Option<usize>,
+    ) -> Result<Console> {
+        self.boot(&boot_cmd("booti", addr, initrd, fdt))
+    }
+
+    /// Boots the ARM `zImage` at `addr` with `bootz`, see [`Self::boot`]
+    /// and [`Self::bootm`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command cannot be written.
+    pub fn bootz(
+        self,
+        addr: usize,
+        initrd: Option<(usize, usize)>,
+        fdt: Option<usize>,
+    ) -> Result<Console> {
+        self.boot(&boot_cmd("bootz", addr, initrd, fdt))
+    }
+
+    /// Jumps to the bare-metal program at `addr` with `go`, passing `args`,
+    /// see [`Self::boot`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command cannot be written.
+    pub fn go(self, addr: usize, args: &[&str]) -> Result<Console> {
+        let mut cmd = format!("go {addr:#x}");
+        for arg in args {
+            cmd.push(' ');
+            cmd.push_str(arg);
+        }
+        self.boot(&cmd)
+    }
+
+    /// CRC32 of `len` bytes of memory at `addr` as printed by `crc32`,
+    /// `None` if U-Boot has no `crc32` command.
+    ///
+    /// Compare with [`crc::crc32`] of the data expected there.
+    pub fn mem_crc32(&mut self, addr: usize, len: usize) -> Option<u32> {
+        let res = self._cmd(&format!("crc32 {addr:#x} {len:#x}")).ok()?;
+        let (_, crc) = res.rsplit_once("==>")?;
+        u32::from_str_radix(crc.trim(), 16).ok()
+    }
+
+    /// Waits for the receiver started by [`FileTransfer::COMMAND`] to be
+    /// ready, `true` for CRC16 mode.
+    fn wait_for_receiver<T: FileTransfer>(&mut self) -> Result<bool> {
         let mut reply = Vec::new();
         loop {
             let byte = self.read_byte()?;
             reply.push(byte);
             print_raw(&[byte]);
 
-            if reply.ends_with(b"C") {
-                return Ok(true);
+            if let Some(crc) = T::ready(&reply) {
+                return Ok(crc);
             }
             let res = String::from_utf8_lossy(&reply);
             if res.contains("try 'help'") {
                 return Err(Error::new(
                     ErrorKind::InvalidData,
-                    format!("U-Boot loady failed: {res}"),
+                    format!("U-Boot {} failed: {res}", T::COMMAND),
                 ));
             }
         }
     }
 }
 
+/// Serial streams of a board handed over by [`UbootShell::boot`].
+pub struct Console {
+    /// Transmit channel, e.g. for keys typed into a terminal.
+    pub tx: Box<dyn Write + Send>,
+    /// Receive channel with the output of the boot command and the booted
+    /// system.
+    pub rx: Box<dyn Read + Send>,
+}
+
+/// Result of [`UbootShell::verify_mem`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemCompare {
+    /// Both regions hold the same bytes.
+    Same,
+    /// Offset of the first byte that differs.
+    Differ(usize),
+}
+
+/// The U-Boot environment at one point in time, taken with
+/// [`UbootShell::env_snapshot`].
+///
+/// Its text form is the `name=value` lines printed by `printenv`, so a
+/// snapshot saved to a file can be read back with [`EnvSnapshot::parse`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvSnapshot {
+    vars: Vec<(String, String)>,
+}
+
+impl EnvSnapshot {
+    /// Parses `name=value` lines as printed by `printenv`, skipping all
+    /// other lines such as `Environment size: ...`.
+    ///
+    /// A line without `name=` continues the value of the variable before
+    /// it, which contains a newline.
+    pub fn parse(text: &str) -> Self {
+        let mut vars: Vec<(String, String)> = Vec::new();
+        // 空行可能只是 `Environment size` 之前的分隔
+        let mut blank = 0;
+        for line in text.lines().map(|line| line.trim_end_matches('\r')) {
+            if line.starts_with("Environment size:") {
+                break;
+            }
+            if line.is_empty() {
+                blank += 1;
+                continue;
+            }
+            match line.split_once('=') {
+                Some((name, value)) if !name.is_empty() && !name.contains(char::is_whitespace) => {
+                    vars.push((name.to_string(), value.to_string()));
+                }
+                _ => {
+                    if let Some((_, value)) = vars.last_mut() {
+                        value.push_str(&"\n".repeat(blank + 1));
+                        value.push_str(line);
+                    }
+                }
+            }
+            blank = 0;
+        }
+        Self { vars }
+    }
+
+    /// Returns the value of `name`, `None` if it is not set.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.vars
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Iterates over the variables as `(name, value)` in `printenv` order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.vars.iter().map(|(n, v)| (n.as_str(), v.as_str()))
+    }
+
+    /// Number of variables.
+    pub fn len(&self) -> usize {
+        self.vars.len()
+    }
+
+    /// Whether no variable is set.
+    pub fn is_empty(&self) -> bool {
+        self.vars.is_empty()
+    }
+}
+
+impl From<EnvSnapshot> for HashMap<String, String> {
+    fn from(snapshot: EnvSnapshot) -> Self {
+        snapshot.vars.into_iter().collect()
+    }
+}
+
+impl std::fmt::Display for EnvSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, value) in &self.vars {
+            writeln!(f, "{name}={value}")?;
+        }
+        Ok(())
+    }
+}
+
 impl Read for UbootShell {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
         self.rx().read(buf)
@@ -576,6 +1511,97 @@ fn parse_int(line: &str) -> Option<usize> {
     u64::from_str_radix(line, radix).ok().map(|o| o as _)
 }
 
+/// Joins script lines into one `;`-separated command list, single-quoted
+/// for `setenv`.
+fn env_script<S: AsRef<str>>(lines: impl IntoIterator<Item = S>) -> Result<String> {
+    let mut cmds = Vec::new();
+    for line in lines {
+        let line = line.as_ref().trim();
+        if line.contains(['\n', '\r']) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("script line contains a line break: {line:?}"),
+            ));
+        }
+        let line = line.trim_end_matches(';').trim_end();
+        // 注释会吞掉连接后同一行里其后的所有命令
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        cmds.push(line.to_string());
+    }
+    Ok(quote(&cmds.join("; ")))
+}
+
+/// Single-quotes `value` for the U-Boot shell.
+fn quote(value: &str) -> String {
+    // 单引号内无法转义，单引号本身写作 '\''
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Parses the output of `cmp.b`, which ends with
+/// `Total of <n> byte(s) were the same` and prints a `!=` line before it
+/// on a mismatch.
+fn parse_cmp(output: &str) -> Option<MemCompare> {
+    let total = output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Total of "))?;
+    let same = total.split_whitespace().next()?.parse::<usize>().ok()?;
+    if output.contains("!=") {
+        Some(MemCompare::Differ(same))
+    } else {
+        Some(MemCompare::Same)
+    }
+}
+
+/// `<boot> <addr> [<initrd>:<size> | -] [<fdt>]` for `bootm`, `booti` and
+/// `bootz`.
+fn boot_cmd(boot: &str, addr: usize, initrd: Option<(usize, usize)>, fdt: Option<usize>) -> String {
+    let mut cmd = format!("{boot} {addr:#x}");
+    match (initrd, fdt) {
+        (Some((initrd, size)), _) => cmd += &format!(" {initrd:#x}:{size:#x}"),
+        (None, Some(_)) => cmd += " -",
+        (None, None) => {}
+    }
+    if let Some(fdt) = fdt {
+        cmd += &format!(" {fdt:#x}");
+    }
+    cmd
+}
+
+/// Parses the `len` bytes at `addr` from the output of `md.b`, lines of
+/// `<addr>: <up to 16 bytes>    <ASCII>`.
+fn parse_md_bytes(output: &str, addr: usize, len: usize) -> Option<Vec<u8>> {
+    let mut data = vec![0u8; len];
+    let mut filled = 0;
+    for line in output.lines() {
+        let Some((line_addr, bytes)) = line.trim().split_once(": ") else {
+            continue;
+        };
+        let Ok(line_addr) = usize::from_str_radix(line_addr, 16) else {
+            continue;
+        };
+        let Some(offset) = line_addr.checked_sub(addr).filter(|&o| o < len) else {
+            continue;
+        };
+        // 之后的 ASCII 列中的空格也会分出两个字符的词，只取本行应有的字节数
+        let count = 16.min(len - offset);
+        for (i, byte) in bytes.split_whitespace().take(count).enumerate() {
+            data[offset + i] = u8::from_str_radix(byte, 16).ok()?;
+            filled += 1;
+        }
+    }
+    (filled == len).then_some(data)
+}
+
+/// Parses `Bytes transferred = <n> (<hex> hex)` printed by network loads.
+fn parse_transferred(output: &str) -> Option<usize> {
+    let line = output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Bytes transferred ="))?;
+    line.split_whitespace().next()?.parse().ok()
+}
+
 fn print_raw(buff: &[u8]) {
     #[cfg(target_os = "windows")]
     print_raw_win(buff);
@@ -598,3 +1624,30 @@ fn print_raw_win(buff: &[u8]) {
         g.clear();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boot_cmd() {
+        let initrd = Some((0x8800_0000, 0x1000));
+        let fdt = Some(0x8300_0000);
+        assert_eq!(
+            boot_cmd("bootm", 0x8000_0000, None, None),
+            "bootm 0x80000000"
+        );
+        assert_eq!(
+            boot_cmd("bootm", 0x8000_0000, initrd, None),
+            "bootm 0x80000000 0x88000000:0x1000"
+        );
+        assert_eq!(
+            boot_cmd("booti", 0x8000_0000, None, fdt),
+            "booti 0x80000000 - 0x83000000"
+        );
+        assert_eq!(
+            boot_cmd("bootz", 0x8000_0000, initrd, fdt),
+            "bootz 0x80000000 0x88000000:0x1000 0x83000000"
+        );
+    }
+}
@@ -0,0 +1,56 @@
+//! File transfer protocols of the U-Boot `load*` commands.
+//!
+//! Each protocol implements [`FileTransfer`], so
+//! [`UbootShell::load_with`](crate::UbootShell::load_with) can start the
+//! matching U-Boot command and send a file with any of them:
+//!
+//! | Protocol | U-Boot command | Module |
+//! |----------|----------------|--------|
+//! | Kermit   | `loadb`        | [`kermit`](crate::kermit) |
+//! | XMODEM   | `loadx`        | [`xmodem`](crate::xmodem) |
+//! | YMODEM   | `loady`        | [`ymodem`](crate::ymodem) |
+//!
+//! There is no ZMODEM sender: U-Boot has no `loadz` and no ZMODEM receiver
+//! at all, its `xyzModem` driver only speaks XMODEM and YMODEM.
+
+use std::io::{Read, Result, Write};
+
+/// A protocol sending a file to a receiver on a serial line.
+pub trait FileTransfer {
+    /// U-Boot command receiving files with this protocol.
+    const COMMAND: &'static str;
+
+    /// Creates a sender, in CRC16 mode (`true`) or checksum mode (`false`)
+    /// as requested by the receiver.
+    fn new(crc_mode: bool) -> Self;
+
+    /// Whether the receiver is ready for the first packet, judged by what
+    /// U-Boot printed since [`COMMAND`](Self::COMMAND), with the CRC16
+    /// mode it asked for.
+    ///
+    /// By default the receiver is ready once it polls with `C` for CRC16
+    /// mode or `NAK` for checksum mode.
+    fn ready(reply: &[u8]) -> Option<bool> {
+        match reply.last() {
+            Some(b'C') => Some(true),
+            Some(0x15) => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Sends `size` bytes of `file` named `name` over `dev`, calling
+    /// `on_progress` with the total bytes sent so far.
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error from the device or file stream, or an error if
+    /// the receiver keeps rejecting a block.
+    fn send<D: Read + Write, F: Read>(
+        &mut self,
+        dev: &mut D,
+        file: &mut F,
+        name: &str,
+        size: usize,
+        on_progress: impl Fn(usize),
+    ) -> Result<()>;
+}
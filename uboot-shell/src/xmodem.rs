@@ -0,0 +1,278 @@
+//! XMODEM file transfer protocol implementation.
+//!
+//! This module implements the XMODEM sender used with U-Boot's `loadx`
+//! command.
+//!
+//! ## Protocol Overview
+//!
+//! XMODEM sends the file in numbered blocks, starting at 1, without a file
+//! name or size:
+//!
+//! - In CRC16 mode, requested by the receiver with `C`, 1024 byte blocks
+//!   (XMODEM-1K) with a CRC16-CCITT, 128 byte blocks for a short tail
+//! - In checksum mode, requested with `NAK`, 128 byte blocks with an 8-bit
+//!   checksum
+//! - The last block is padded with `0x1A`, so the receiver may keep up to
+//!   a block of padding after the file
+//! - A rejected block is sent again, up to 10 times
+
+use std::io::*;
+
+use crate::{crc::crc16_ccitt, transfer::FileTransfer};
+
+/// Start of Header - 128 byte block
+const SOH: u8 = 0x01;
+/// Start of Text - 1024 byte block
+const STX: u8 = 0x02;
+/// End of Transmission
+const EOT: u8 = 0x04;
+/// Acknowledge
+const ACK: u8 = 0x06;
+/// Negative Acknowledge
+const NAK: u8 = 0x15;
+/// Cancel
+const CAN: u8 = 0x18;
+/// End of File padding character
+const EOF: u8 = 0x1A;
+/// CRC mode request character
+const CRC: u8 = 0x43;
+
+/// Attempts to send a block before giving up.
+const RETRIES: usize = 10;
+
+/// XMODEM protocol handler for file transfers.
+pub struct Xmodem {
+    /// Whether to use CRC16 mode (true) or checksum mode (false)
+    crc_mode: bool,
+    /// Current block number
+    blk: u8,
+}
+
+impl Xmodem {
+    /// Creates a new XMODEM sender.
+    ///
+    /// # Arguments
+    ///
+    /// * `crc_mode` - Whether the receiver asked for CRC16 mode (`true`) or
+    ///   checksum mode (`false`)
+    pub fn new(crc_mode: bool) -> Self {
+        Self { crc_mode, blk: 1 }
+    }
+
+    /// Sends a file over the XMODEM protocol.
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - The device implementing `Read + Write` (serial stream)
+    /// * `file` - The readable file stream
+    /// * `on_progress` - Callback invoked with the total bytes sent so far
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error from the underlying device or file stream, or
+    /// an error if the receiver cancels or keeps rejecting a block.
+    pub fn send<D: Write + Read, F: Read>(
+        &mut self,
+        dev: &mut D,
+        file: &mut F,
+        on_progress: impl Fn(usize),
+    ) -> Result<()> {
+        let len = if self.crc_mode { 1024 } else { 128 };
+        let mut buff = vec![0u8; len];
+        let mut send_size = 0;
+
+        loop {
+            let n = read_full(file, &mut buff)?;
+            if n == 0 {
+                break;
+            }
+            self.send_blk(dev, &buff[..n])?;
+            send_size += n;
+            on_progress(send_size);
+            if n < len {
+                break;
+            }
+        }
+
+        self.retry(|| {
+            dev.write_all(&[EOT])?;
+            dev.flush()?;
+            wait_ack(dev)
+        })
+    }
+
+    fn send_blk<D: Write + Read>(&mut self, dev: &mut D, data: &[u8]) -> Result<()> {
+        let (p, len) = if data.len() > 128 {
+            (STX, 1024)
+        } else {
+            (SOH, 128)
+        };
+        let mut buf = vec![EOF; len];
+        buf[..data.len()].copy_from_slice(data);
+
+        let blk = self.blk;
+        let crc_mode = self.crc_mode;
+        self.retry(|| {
+            dev.write_all(&[p, blk, !blk])?;
+            dev.write_all(&buf)?;
+            if crc_mode {
+                dev.write_all(&crc16_ccitt(0, &buf).to_be_bytes())?;
+            } else {
+                dev.write_all(&[checksum(&buf)])?;
+            }
+            dev.flush()?;
+            wait_ack(dev)
+        })?;
+
+        self.blk = self.blk.wrapping_add(1);
+        Ok(())
+    }
+
+    /// Runs `f` until the receiver acknowledges, at most [`RETRIES`] times.
+    fn retry(&self, mut f: impl FnMut() -> Result<()>) -> Result<()> {
+        let mut err = None;
+        for _ in 0..RETRIES {
+            match f() {
+                Ok(()) => return Ok(()),
+                Err(e) if e.kind() == ErrorKind::BrokenPipe => {
+                    debug!("xmodem block {} rejected", self.blk);
+                    err = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(err.unwrap_or(Error::new(ErrorKind::BrokenPipe, "retry too much")))
+    }
+}
+
+impl FileTransfer for Xmodem {
+    const COMMAND: &'static str = "loadx";
+
+    fn new(crc_mode: bool) -> Self {
+        Xmodem::new(crc_mode)
+    }
+
+    fn send<D: Read + Write, F: Read>(
+        &mut self,
+        dev: &mut D,
+        file: &mut F,
+        _name: &str,
+        _size: usize,
+        on_progress: impl Fn(usize),
+    ) -> Result<()> {
+        Xmodem::send(self, dev, file, on_progress)
+    }
+}
+
+/// Waits for the receiver to accept a block, `BrokenPipe` if it asks for
+/// it again.
+fn wait_ack<D: Read>(dev: &mut D) -> Result<()> {
+    let mut buff = [0u8; 1];
+    loop {
+        dev.read_exact(&mut buff)?;
+        match buff[0] {
+            ACK => return Ok(()),
+            NAK | CRC => return Err(Error::new(ErrorKind::BrokenPipe, "NAK")),
+            CAN => return Err(Error::new(ErrorKind::ConnectionAborted, "cancelled")),
+            // 接收端输出的其他内容
+            c => trace!("xmodem: {c:#04x}"),
+        }
+    }
+}
+
+/// Reads until `buf` is full or the stream ends.
+fn read_full<F: Read>(file: &mut F, buf: &mut [u8]) -> Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match file.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(m) => n += m,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(n)
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// Receiver replying with `replies` and keeping what was sent.
+    struct Mock {
+        replies: VecDeque<u8>,
+        sent: Vec<u8>,
+    }
+
+    impl Read for Mock {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let Some(c) = self.replies.pop_front() else {
+                return Err(Error::from(ErrorKind::UnexpectedEof));
+            };
+            buf[0] = c;
+            Ok(1)
+        }
+    }
+
+    impl Write for Mock {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.sent.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn mock(replies: &[u8]) -> Mock {
+        Mock {
+            replies: replies.iter().copied().collect(),
+            sent: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_send_crc() {
+        let data = vec![0x5a; 1100];
+        let mut dev = mock(&[ACK, ACK, ACK]);
+        Xmodem::new(true)
+            .send(&mut dev, &mut data.as_slice(), |_| {})
+            .unwrap();
+
+        let (first, rest) = dev.sent.split_at(3 + 1024 + 2);
+        assert_eq!(first[..3], [STX, 1, 0xfe]);
+        assert_eq!(first[1027..], crc16_ccitt(0, &data[..1024]).to_be_bytes());
+        let (second, eot) = rest.split_at(3 + 128 + 2);
+        assert_eq!(second[..3], [SOH, 2, 0xfd]);
+        assert_eq!(second[3 + 76..3 + 128], [EOF; 52]);
+        assert_eq!(eot, [EOT]);
+    }
+
+    #[test]
+    fn test_send_checksum_retry() {
+        let data = b"hello";
+        let mut dev = mock(&[NAK, ACK, ACK]);
+        Xmodem::new(false)
+            .send(&mut dev, &mut data.as_slice(), |_| {})
+            .unwrap();
+
+        let blk = 3 + 128 + 1;
+        assert_eq!(dev.sent.len(), blk * 2 + 1);
+        assert_eq!(dev.sent[..blk], dev.sent[blk..blk * 2]);
+        let mut padded = [EOF; 128];
+        padded[..5].copy_from_slice(data);
+        assert_eq!(dev.sent[blk - 1], checksum(&padded));
+
+        let mut dev = mock(&[CAN]);
+        let err = Xmodem::new(false)
+            .send(&mut dev, &mut data.as_slice(), |_| {})
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ConnectionAborted);
+    }
+}
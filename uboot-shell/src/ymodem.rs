@@ -12,10 +12,11 @@
 //! - Automatic block size selection (128 or 1024 bytes)
 //! - CRC16-CCITT or checksum error detection
 //! - Retry mechanism for failed transmissions
+//! - Receiving a file, in CRC16 mode, from a sender on the board
 
 use std::io::*;
 
-use crate::crc::crc16_ccitt;
+use crate::{crc::crc16_ccitt, transfer::FileTransfer};
 
 /// Start of Header - 128 byte block
 const SOH: u8 = 0x01;
@@ -27,7 +28,8 @@ const EOT: u8 = 0x04;
 const ACK: u8 = 0x06;
 /// Negative Acknowledge
 const NAK: u8 = 0x15;
-// const CAN: u8 = 0x18; // Cancel
+/// Cancel
+const CAN: u8 = 0x18;
 /// End of File padding character
 const EOF: u8 = 0x1A;
 /// CRC mode request character
@@ -221,3 +223,250 @@ impl Ymodem {
         Ok(())
     }
 }
+
+/// File announced by the header block of a YMODEM sender.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Received {
+    /// File name sent by the sender.
+    pub name: String,
+    /// File size in bytes, `None` if the sender did not tell.
+    pub size: Option<usize>,
+    /// Bytes written, without the padding of the last block.
+    pub written: usize,
+}
+
+impl Ymodem {
+    /// Receives a file over the YMODEM protocol, writing it to `out`.
+    ///
+    /// Asks the sender for CRC16 mode and takes the first file of the
+    /// batch. Without a size in the header, the padding of the last block
+    /// is kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `dev` - The device implementing `Read + Write` (serial stream)
+    /// * `out` - Where the file content is written
+    /// * `on_progress` - Callback invoked with the total bytes received so far
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error from the underlying device or output, or an
+    /// error if the sender cancels or keeps sending broken blocks.
+    pub fn recv<D: Write + Read, W: Write>(
+        &mut self,
+        dev: &mut D,
+        out: &mut W,
+        on_progress: impl Fn(usize),
+    ) -> Result<Received> {
+        self.crc_mode = true;
+        dev.write_all(&[CRC])?;
+        dev.flush()?;
+        let header = loop {
+            match self.recv_blk(dev, CRC)? {
+                Some((0, data)) => break data,
+                _ => self.reject(dev, CRC)?,
+            }
+        };
+        let (name, size) = parse_header(&header);
+        info!("Receiving file: {name}");
+        dev.write_all(&[ACK, CRC])?;
+        dev.flush()?;
+
+        let mut written = 0;
+        let mut blk = 1u8;
+        loop {
+            let Some((n, data)) = self.recv_blk(dev, NAK)? else {
+                // EOT
+                dev.write_all(&[ACK])?;
+                dev.flush()?;
+                break;
+            };
+            if n == blk {
+                let len = size.map_or(data.len(), |size| data.len().min(size - written));
+                out.write_all(&data[..len])?;
+                written += len;
+                on_progress(written);
+                blk = blk.wrapping_add(1);
+            } else if n != blk.wrapping_sub(1) {
+                dev.write_all(&[CAN, CAN])?;
+                return Err(Error::new(ErrorKind::InvalidData, "block out of sequence"));
+            }
+            // 重发的上一个块只确认
+            dev.write_all(&[ACK])?;
+            dev.flush()?;
+        }
+
+        // 只接收第一个文件，之后的批次结束块
+        dev.write_all(&[CRC])?;
+        dev.flush()?;
+        if let Some((0, _)) = self.recv_blk(dev, CRC)? {
+            dev.write_all(&[ACK])?;
+            dev.flush()?;
+        }
+        Ok(Received {
+            name,
+            size,
+            written,
+        })
+    }
+
+    /// Receives a block, asking for it again with `poll` on a timeout or a
+    /// broken block; `None` for `EOT`.
+    fn recv_blk<D: Write + Read>(
+        &mut self,
+        dev: &mut D,
+        poll: u8,
+    ) -> Result<Option<(u8, Vec<u8>)>> {
+        loop {
+            let len = match self.getc(dev) {
+                Ok(SOH) => 128,
+                Ok(STX) => 1024,
+                Ok(EOT) => return Ok(None),
+                Ok(CAN) => return Err(Error::new(ErrorKind::ConnectionAborted, "cancelled")),
+                // 发送端输出的其他内容
+                Ok(_) => continue,
+                Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => {
+                    self.reject(dev, poll)?;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let mut buf = vec![0u8; len + 4];
+            dev.read_exact(&mut buf)?;
+            let (blk, data, crc) = (buf[0], &buf[2..len + 2], &buf[len + 2..]);
+            if blk == !buf[1] && crc16_ccitt(0, data).to_be_bytes() == crc {
+                return Ok(Some((blk, data.to_vec())));
+            }
+            self.reject(dev, NAK)?;
+        }
+    }
+
+    fn reject<D: Write>(&mut self, dev: &mut D, poll: u8) -> Result<()> {
+        if self.retries == 0 {
+            let _ = dev.write_all(&[CAN, CAN]);
+            return Err(Error::new(ErrorKind::BrokenPipe, "retry too much"));
+        }
+        self.retries -= 1;
+        dev.write_all(&[poll])?;
+        dev.flush()
+    }
+}
+
+/// Name and size in a header block: `name\0size [mtime ...]\0`.
+fn parse_header(data: &[u8]) -> (String, Option<usize>) {
+    let mut fields = data.split(|&c| c == 0);
+    let name = String::from_utf8_lossy(fields.next().unwrap_or_default()).into_owned();
+    let size = fields
+        .next()
+        .map(String::from_utf8_lossy)
+        .and_then(|info| info.split_whitespace().next()?.parse().ok());
+    (name, size)
+}
+
+impl FileTransfer for Ymodem {
+    const COMMAND: &'static str = "loady";
+
+    fn new(crc_mode: bool) -> Self {
+        Ymodem::new(crc_mode)
+    }
+
+    fn send<D: Read + Write, F: Read>(
+        &mut self,
+        dev: &mut D,
+        file: &mut F,
+        name: &str,
+        size: usize,
+        on_progress: impl Fn(usize),
+    ) -> Result<()> {
+        Ymodem::send(self, dev, file, name, size, on_progress)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// Sender replying with `input` and keeping what was written.
+    struct Mock {
+        input: VecDeque<u8>,
+        sent: Vec<u8>,
+    }
+
+    impl Read for Mock {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let Some(c) = self.input.pop_front() else {
+                return Err(Error::from(ErrorKind::TimedOut));
+            };
+            buf[0] = c;
+            Ok(1)
+        }
+    }
+
+    impl Write for Mock {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.sent.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn block(blk: u8, data: &[u8], len: usize) -> Vec<u8> {
+        let mut buf = vec![EOF; len];
+        buf[..data.len()].copy_from_slice(data);
+        let mut out = vec![if len == 1024 { STX } else { SOH }, blk, !blk];
+        out.extend_from_slice(&buf);
+        out.extend_from_slice(&crc16_ccitt(0, &buf).to_be_bytes());
+        out
+    }
+
+    #[test]
+    fn test_recv() {
+        let data: Vec<u8> = (0..=255).cycle().take(1100).collect();
+        let mut input = b"noise".to_vec();
+        input.extend(block(0, b"env.bin\x001100 0\x00", 128));
+        input.extend(block(1, &data[..1024], 1024));
+        // 损坏的块与重发的块
+        let mut broken = block(2, &data[1024..], 128);
+        broken[10] ^= 0xff;
+        input.extend(broken);
+        input.extend(block(2, &data[1024..], 128));
+        input.extend(block(2, &data[1024..], 128));
+        input.push(EOT);
+        input.extend(block(0, &[], 128));
+
+        let mut dev = Mock {
+            input: input.into(),
+            sent: Vec::new(),
+        };
+        let mut out = Vec::new();
+        let received = Ymodem::new(true).recv(&mut dev, &mut out, |_| {}).unwrap();
+        assert_eq!(
+            received,
+            Received {
+                name: "env.bin".into(),
+                size: Some(1100),
+                written: 1100
+            }
+        );
+        assert_eq!(out, data);
+        assert_eq!(dev.sent, [CRC, ACK, CRC, ACK, NAK, ACK, ACK, ACK, CRC, ACK]);
+    }
+
+    #[test]
+    fn test_recv_timeout() {
+        let mut dev = Mock {
+            input: VecDeque::new(),
+            sent: Vec::new(),
+        };
+        let err = Ymodem::new(true)
+            .recv(&mut dev, &mut Vec::new(), |_| {})
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+        assert_eq!(dev.sent[..11], [CRC; 11]);
+        assert_eq!(dev.sent[11..], [CAN, CAN]);
+    }
+}
@@ -0,0 +1,512 @@
+//! `UbootShell` against a scripted stand-in for U-Boot on a loopback
+//! socket, for behavior that does not need a real board.
+
+use std::{
+    collections::HashMap,
+    io::{BufReader, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+use ntest::timeout;
+use uboot_shell::{EnvSnapshot, MemCompare, UbootShell, crc::crc32};
+
+/// What the fake prints for a command, line by line, with a pause before
+/// each line; `None` makes the command fail.
+type Script = fn(&str) -> Option<Vec<(u64, String)>>;
+
+/// Starts a fake U-Boot running `script` and connects a shell to it.
+fn fake_uboot(script: Script) -> UbootShell {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        stream.set_nodelay(true).unwrap();
+        serve(stream, script);
+    });
+    let tx = TcpStream::connect(addr).unwrap();
+    let rx = tx.try_clone().unwrap();
+    rx.set_read_timeout(Some(Duration::from_millis(300)))
+        .unwrap();
+    UbootShell::new(tx, rx).unwrap()
+}
+
+fn serve(stream: TcpStream, script: Script) {
+    let mut out = stream.try_clone().unwrap();
+    let mut input = BufReader::new(stream);
+    out.write_all(b"=> ").unwrap();
+    let mut line = Vec::new();
+    let mut board = Board::default();
+    loop {
+        let mut byte = [0u8];
+        if input.read(&mut byte).unwrap_or(0) == 0 {
+            return;
+        }
+        match byte[0] {
+            // Ctrl+C
+            0x03 => out.write_all(b"<INTERRUPT>\r\n=> ").unwrap(),
+            b'\n' => {
+                let cmd = String::from_utf8_lossy(&line).to_string();
+                line.clear();
+                let _ = out.write_all(b"\r\n");
+                if let Some(addr) = board.mm {
+                    board.modify(addr, &cmd);
+                    match board.mm {
+                        Some(addr) => {
+                            let _ = write!(out, "{addr:08x}: {:08x} ? ", board.word(addr));
+                        }
+                        None => {
+                            let _ = out.write_all(b"=> ");
+                        }
+                    }
+                    continue;
+                }
+                // `cmd; echo ok` prints `ok` even if `cmd` fails
+                let (cmd, ok, always) = if let Some((cmd, ok)) = cmd.split_once("&& echo ") {
+                    (cmd.to_string(), Some(ok.to_string()), false)
+                } else if let Some((cmd, ok)) = cmd.split_once("; echo ") {
+                    (cmd.to_string(), Some(ok.to_string()), true)
+                } else {
+                    (cmd, None, false)
+                };
+                let result = board.run(&cmd, script);
+                for (pause, text) in result.iter().flatten() {
+                    thread::sleep(Duration::from_millis(*pause));
+                    let _ = write!(out, "{text}\r\n");
+                }
+                if result.is_none() {
+                    let _ = write!(out, "Unknown command '{cmd}' - try 'help'\r\n");
+                }
+                if let Some(ok) = ok
+                    && (result.is_some() || always)
+                {
+                    let _ = write!(out, "{ok}\r\n");
+                }
+                match board.mm {
+                    Some(addr) => {
+                        let _ = write!(out, "{addr:08x}: {:08x} ? ", board.word(addr));
+                    }
+                    None => {
+                        let _ = out.write_all(b"=> ");
+                    }
+                }
+            }
+            b => {
+                line.push(b);
+                let _ = out.write_all(&[b]);
+            }
+        }
+    }
+}
+
+/// The first word written here by `mm.l` is stored corrupted.
+const GLITCH_ADDR: usize = 0x2000_0400;
+
+/// State of the fake board.
+#[derive(Default)]
+struct Board {
+    env: HashMap<String, String>,
+    /// Little-endian memory, zero where never written.
+    mem: HashMap<usize, u8>,
+    /// Address `mm.l` asks for next, while it runs.
+    mm: Option<usize>,
+    glitched: bool,
+}
+
+impl Board {
+    /// Runs `cmd`, handling `setenv`, `printenv`, `run` and memory commands like
+    /// U-Boot and anything else with `script`.
+    fn run(&mut self, cmd: &str, script: Script) -> Option<Vec<(u64, String)>> {
+        let words = words(cmd);
+        let hex = |i: usize| {
+            let word: &String = words.get(i)?;
+            usize::from_str_radix(word.trim_start_matches("0x"), 16).ok()
+        };
+        match words.first().map(String::as_str) {
+            Some("setenv") if words.len() == 2 => {
+                self.env.remove(&words[1]);
+                Some(vec![])
+            }
+            Some("setenv") => {
+                self.env.insert(words.get(1)?.clone(), words[2..].join(" "));
+                Some(vec![])
+            }
+            Some("printenv") => {
+                let mut lines = self
+                    .env
+                    .iter()
+                    .map(|(name, value)| (0, format!("{name}={value}")))
+                    .collect::<Vec<_>>();
+                lines.sort();
+                lines.push((0, String::new()));
+                lines.push((0, format!("Environment size: {}/8188 bytes", lines.len())));
+                Some(lines)
+            }
+            Some("run") => {
+                let value = self.env.get(words.get(1)?)?.clone();
+                let mut out = Vec::new();
+                for cmd in value.split(';') {
+                    out.extend(self.run(cmd.trim(), script)?);
+                }
+                Some(out)
+            }
+            Some("mm.l") => {
+                self.mm = Some(hex(1)?);
+                Some(vec![])
+            }
+            Some("mw.l") => {
+                self.write(hex(1)?, &(hex(2)? as u32).to_le_bytes());
+                Some(vec![])
+            }
+            Some("mw.b") => {
+                self.write(hex(1)?, &[hex(2)? as u8]);
+                Some(vec![])
+            }
+            Some("md.b") => {
+                let (addr, len) = (hex(1)?, hex(2)?);
+                let lines = (addr..addr + len)
+                    .step_by(16)
+                    .map(|line| {
+                        let bytes = (line..(line + 16).min(addr + len))
+                            .map(|a| self.byte(a))
+                            .collect::<Vec<_>>();
+                        let hex = bytes.iter().map(|b| format!("{b:02x}")).collect::<Vec<_>>();
+                        let ascii = bytes
+                            .iter()
+                            .map(|&b| {
+                                if b.is_ascii_graphic() || b == b' ' {
+                                    b as char
+                                } else {
+                                    '.'
+                                }
+                            })
+                            .collect::<String>();
+                        (0, format!("{line:08x}: {}    {ascii}", hex.join(" ")))
+                    })
+                    .collect();
+                Some(lines)
+            }
+            Some("md.l") => {
+                let addr = hex(1)?;
+                Some(vec![(
+                    0,
+                    format!("{addr:08x}: {:08x}    ....", self.word(addr)),
+                )])
+            }
+            Some("tftpboot") => {
+                let server = self.env.get("serverip")?.clone();
+                let ip = self.env.get("ipaddr")?.clone();
+                let (addr, name) = (hex(1)?, words.get(2)?);
+                let data = name.as_bytes().repeat(100);
+                self.write(addr, &data);
+                Some(vec![
+                    (
+                        0,
+                        format!("TFTP from server {server}; our IP address is {ip}"),
+                    ),
+                    (0, format!("Filename '{name}'.")),
+                    (0, format!("Load address: {addr:#x}")),
+                    (50, "Loading: #".to_string()),
+                    (0, "done".to_string()),
+                    (
+                        0,
+                        format!("Bytes transferred = {} ({:x} hex)", data.len(), data.len()),
+                    ),
+                ])
+            }
+            Some("crc32") => {
+                let (addr, len) = (hex(1)?, hex(2)?);
+                let data = (addr..addr + len).map(|a| self.byte(a)).collect::<Vec<_>>();
+                Some(vec![(
+                    0,
+                    format!(
+                        "crc32 for {addr:08x} ... {:08x} ==> {:08x}",
+                        addr + len - 1,
+                        crc32(&data)
+                    ),
+                )])
+            }
+            _ => script(cmd),
+        }
+    }
+
+    /// Handles a line typed into `mm.l` at `addr`: a hex word is stored
+    /// and anything else ends `mm.l`.
+    fn modify(&mut self, addr: usize, line: &str) {
+        match u32::from_str_radix(line.trim(), 16) {
+            Ok(mut val) => {
+                if addr == GLITCH_ADDR && !self.glitched {
+                    self.glitched = true;
+                    val ^= 1;
+                }
+                self.write(addr, &val.to_le_bytes());
+                self.mm = Some(addr + 4);
+            }
+            Err(_) => self.mm = None,
+        }
+    }
+
+    fn write(&mut self, addr: usize, bytes: &[u8]) {
+        for (i, &b) in bytes.iter().enumerate() {
+            self.mem.insert(addr + i, b);
+        }
+    }
+
+    fn byte(&self, addr: usize) -> u8 {
+        self.mem.get(&addr).copied().unwrap_or(0)
+    }
+
+    fn word(&self, addr: usize) -> u32 {
+        u32::from_le_bytes([0, 1, 2, 3].map(|i| self.byte(addr + i)))
+    }
+}
+
+/// Splits a command line into words like U-Boot's hush shell does with
+/// single quotes and backslashes.
+fn words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = None::<String>;
+    let mut quoted = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                quoted = !quoted;
+                word.get_or_insert_default();
+            }
+            '\\' if !quoted => word.get_or_insert_default().extend(chars.next()),
+            c if c.is_whitespace() && !quoted => words.extend(word.take()),
+            c => word.get_or_insert_default().push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+fn script(cmd: &str) -> Option<Vec<(u64, String)>> {
+    let mut args = cmd.split_whitespace();
+    match args.next()? {
+        "echo" => Some(vec![(0, args.collect::<Vec<_>>().join(" "))]),
+        "sleep" => {
+            let ms = args.next()?.parse::<u64>().ok()? * 1000;
+            Some(vec![(ms, String::new())])
+        }
+        "go" => Some(vec![(
+            0,
+            format!("## Starting application at {} ...", args.next()?),
+        )]),
+        "saveenv" => Some(vec![(100, "Saving Environment to MMC... OK".to_string())]),
+        "erase" => Some((0..3).map(|i| (200, format!("block {i} erased"))).collect()),
+        // 地址相同时内容一致，否则在第 5 字节处不同
+        "cmp.b" => {
+            let (a, b) = (args.next()?, args.next()?);
+            let mut lines = Vec::new();
+            if a != b {
+                lines.push((0, format!("byte at {a}5 (0x12) != byte at {b}5 (0x34)")));
+                lines.push((0, "Total of 5 byte(s) were the same".to_string()));
+            } else {
+                lines.push((0, "Total of 256 byte(s) were the same".to_string()));
+            }
+            Some(lines)
+        }
+        _ => None,
+    }
+}
+
+#[test]
+#[timeout(10000)]
+fn test_cmd_with_timeout() {
+    let mut uboot = fake_uboot(script);
+    assert_eq!(uboot.cmd("echo hello").unwrap(), "hello");
+
+    let res = uboot.cmd_with_timeout("sleep 1", Duration::from_secs(3));
+    assert!(res.is_ok(), "{res:?}");
+
+    let start = Instant::now();
+    let err = uboot
+        .cmd_with_timeout("sleep 3", Duration::from_millis(500))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+#[timeout(10000)]
+fn test_cmd_lines() {
+    let mut uboot = fake_uboot(script);
+    let mut lines = Vec::new();
+    uboot
+        .cmd_lines("erase", |line| lines.push(line.to_string()))
+        .unwrap();
+    assert_eq!(
+        lines,
+        ["block 0 erased", "block 1 erased", "block 2 erased"]
+    );
+
+    assert!(uboot.cmd_lines("bogus", |_| {}).is_err());
+}
+
+#[test]
+#[timeout(10000)]
+fn test_verify_mem() {
+    let mut uboot = fake_uboot(script);
+    assert_eq!(
+        uboot.verify_mem(0x1000, 0x1000, 0x100).unwrap(),
+        MemCompare::Same
+    );
+    assert_eq!(
+        uboot.verify_mem(0x1000, 0x2000, 0x100).unwrap(),
+        MemCompare::Differ(5)
+    );
+    // 命令仍可正常执行
+    assert_eq!(uboot.cmd("echo hello").unwrap(), "hello");
+}
+
+#[test]
+#[timeout(10000)]
+fn test_load_tftp() {
+    let mut uboot = fake_uboot(script);
+    // 没有 ipaddr 时 tftpboot 失败
+    assert!(uboot.load_tftp(0x1000, "a.bin", "10.0.0.1", None).is_err());
+    let size = uboot
+        .load_tftp(0x1000, "a.bin", "10.0.0.1", Some("10.0.0.2"))
+        .unwrap();
+    assert_eq!(size, 500);
+    assert_eq!(
+        uboot.mem_crc32(0x1000, size),
+        Some(crc32(&b"a.bin".repeat(100)))
+    );
+}
+
+#[test]
+#[timeout(10000)]
+fn test_mem() {
+    let mut uboot = fake_uboot(script);
+    // 含空格的 ASCII 列，且不从 16 字节边界开始
+    let data = b"ab cd ef 12 34 56 78";
+    uboot.write_mem(0x1003, data).unwrap();
+    assert_eq!(uboot.read_mem(0x1003, data.len()).unwrap(), data);
+    assert_eq!(uboot.read_mem(0x1000, 4).unwrap(), [0, 0, 0, b'a']);
+
+    uboot.write_u32(0x2000, 0x1234_5678).unwrap();
+    assert_eq!(uboot.read_u32(0x2000).unwrap(), 0x1234_5678);
+    assert_eq!(uboot.read_mem(0x2000, 2).unwrap(), [0x78, 0x56]);
+}
+
+#[test]
+#[timeout(10000)]
+fn test_boot() {
+    let uboot = fake_uboot(script);
+    let mut console = uboot.go(0x1000, &["a"]).unwrap();
+    let mut out = Vec::new();
+    let mut buf = [0u8; 64];
+    while !String::from_utf8_lossy(&out).contains("Starting application at 0x1000") {
+        let n = console.rx.read(&mut buf).unwrap();
+        out.extend_from_slice(&buf[..n]);
+    }
+}
+
+#[test]
+#[timeout(10000)]
+fn test_resync() {
+    let mut uboot = fake_uboot(script);
+    assert_eq!(uboot.prompt(), "=> ");
+    let synced_at = uboot.synced_at();
+
+    // 不读取输出，之后的命令会和板子输出错位
+    uboot.cmd_without_reply("erase").unwrap();
+    uboot.resync().unwrap();
+    assert_eq!(uboot.prompt(), "=> ");
+    assert!(uboot.synced_at() > synced_at);
+    assert_eq!(uboot.cmd("echo hello").unwrap(), "hello");
+}
+
+#[test]
+#[timeout(10000)]
+fn test_env_script() {
+    let mut uboot = fake_uboot(script);
+    uboot
+        .set_env_script(
+            "boot",
+            [
+                "echo one;",
+                "",
+                "# comment",
+                "echo it's; echo three",
+                "echo two",
+            ],
+        )
+        .unwrap();
+    assert_eq!(
+        uboot.run_env("boot").unwrap(),
+        "one\r\nit's\r\nthree\r\ntwo"
+    );
+
+    assert_eq!(
+        uboot
+            .set_env_script("boot", ["echo one\necho two"])
+            .unwrap_err()
+            .kind(),
+        ErrorKind::InvalidInput
+    );
+}
+
+#[test]
+#[timeout(10000)]
+fn test_env_snapshot() {
+    let mut uboot = fake_uboot(script);
+    uboot.set_env("bootargs", "console=ttyS0").unwrap();
+    uboot
+        .set_env_script("bootcmd", ["echo it's", "bootm"])
+        .unwrap();
+    let snapshot = uboot.env_snapshot().unwrap();
+    assert_eq!(snapshot.get("bootargs"), Some("console=ttyS0"));
+    assert_eq!(snapshot.get("bootcmd"), Some("echo it's; bootm"));
+    // 保存到文件后再读回
+    let snapshot = EnvSnapshot::parse(&snapshot.to_string());
+    assert_eq!(snapshot.len(), 2);
+
+    uboot.set_env("bootargs", "quiet").unwrap();
+    uboot.set_env("bootcmd", "bootm").unwrap();
+    uboot.set_env("serverip", "10.0.0.1").unwrap();
+    assert_eq!(uboot.env_restore(&snapshot).unwrap(), 3);
+    assert_eq!(uboot.env_snapshot().unwrap(), snapshot);
+    assert_eq!(uboot.env_restore(&snapshot).unwrap(), 0);
+
+    let env = uboot.env_all().unwrap();
+    assert_eq!(env.len(), 2);
+    assert_eq!(env["bootargs"], "console=ttyS0");
+    uboot.env_save().unwrap();
+
+    // 含换行的值
+    let snapshot = EnvSnapshot::parse(
+        "printenv\r\nboot_a=echo a\r\n\r\nload\r\nboot_b=b\r\n\r\nEnvironment size: 40/8188 bytes\r\n",
+    );
+    assert_eq!(snapshot.get("boot_a"), Some("echo a\n\nload"));
+    assert_eq!(snapshot.get("boot_b"), Some("b"));
+    assert_eq!(EnvSnapshot::parse(&snapshot.to_string()), snapshot);
+}
+
+#[test]
+#[timeout(30000)]
+fn test_load_via_console() {
+    let mut uboot = fake_uboot(script);
+    // 跨越多个块，末尾有不足 4 字节的部分
+    let data = (0..2503u32).map(|i| (i * 7 + 3) as u8).collect::<Vec<_>>();
+    let progress = std::cell::RefCell::new(Vec::new());
+    uboot
+        .load_via_console(0x2000_0000, &data, |loaded, total| {
+            progress.borrow_mut().push((loaded, total))
+        })
+        .unwrap();
+    assert_eq!(
+        progress.into_inner(),
+        [(1024, 2503), (2048, 2503), (2503, 2503)]
+    );
+
+    let res = uboot.cmd("md.b 0x20000000 8").unwrap();
+    assert_eq!(res, "20000000: 03 0a 11 18 1f 26 2d 34    .....&-4");
+    let res = uboot.cmd("crc32 0x20000000 0x9c7").unwrap();
+    assert!(res.ends_with(&format!("{:08x}", crc32(&data))), "{res}");
+}
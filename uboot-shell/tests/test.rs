@@ -96,3 +96,26 @@ fn test_env() {
         assert_eq!(uboot.env_int("fdt_addr").unwrap(), 0x40000000);
     });
 }
+
+#[test]
+#[timeout(15000)]
+fn test_cmd_with_timeout() {
+    with_uboot(|uboot| {
+        // 比默认的 5 秒读超时更久
+        uboot
+            .cmd_with_timeout("sleep 6", Duration::from_secs(10))
+            .unwrap();
+    });
+}
+
+#[test]
+#[timeout(5000)]
+fn test_cmd_lines() {
+    with_uboot(|uboot| {
+        let mut lines = Vec::new();
+        uboot
+            .cmd_lines("help", |line| lines.push(line.to_string()))
+            .unwrap();
+        assert!(lines.iter().any(|l| l.starts_with("printenv")));
+    });
+}